@@ -16,6 +16,7 @@
 
 use crate::keychain::Keychain;
 use crate::libwallet::api_impl::foreign;
+use crate::libwallet::swap::config::SwapConfig;
 use crate::libwallet::{
 	BlockFees, CbData, Error, NodeClient, NodeVersionInfo, Slate, VersionInfo, WalletInst,
 	WalletLCProvider,
@@ -70,6 +71,8 @@ where
 	middleware: Option<ForeignCheckMiddleware>,
 	/// Stored keychain mask (in case the stored wallet seed is tokenized)
 	keychain_mask: Option<SecretKey>,
+	/// Swap engine parameters, used for the income swap messages
+	swap_config: Mutex<SwapConfig>,
 }
 
 impl<'a, L, C, K> Foreign<'a, L, C, K>
@@ -168,9 +171,16 @@ where
 			doctest_mode: false,
 			middleware,
 			keychain_mask,
+			swap_config: Mutex::new(SwapConfig::default()),
 		}
 	}
 
+	/// Set the swap engine parameters used for the income swap messages.
+	pub fn set_swap_config(&self, swap_config: SwapConfig) {
+		let mut lock = self.swap_config.lock();
+		*lock = swap_config;
+	}
+
 	/// Return the version capabilities of the running ForeignApi Node
 	/// # Arguments
 	/// None
@@ -404,6 +414,46 @@ where
 		address: Option<String>,
 		dest_acct_name: Option<&str>,
 		message: Option<String>,
+	) -> Result<Slate, Error> {
+		self.receive_tx_impl(slate, address, None, dest_acct_name, message)
+	}
+
+	/// Same as [`receive_tx`](struct.Foreign.html#method.receive_tx), but the recipient knows the
+	/// amount it should get. Slate with any other amount is rejected before it is signed, so the
+	/// sender can't change the amount.
+	///
+	/// # Arguments
+	/// * `expected_amount` - The amount in nanocoins that the recipient expects to receive.
+	/// * Other arguments are the same as for [`receive_tx`](struct.Foreign.html#method.receive_tx).
+	///
+	/// # Returns
+	/// * `Ok`([`slate`](../grin_wallet_libwallet/slate/struct.Slate.html)`)` if successful.
+	/// * [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) with `ExpectedAmountMismatch`
+	/// kind if the slate amount is different.
+	pub fn receive_tx_expected_amount(
+		&self,
+		slate: &Slate,
+		expected_amount: u64,
+		address: Option<String>,
+		dest_acct_name: Option<&str>,
+		message: Option<String>,
+	) -> Result<Slate, Error> {
+		self.receive_tx_impl(
+			slate,
+			address,
+			Some(expected_amount),
+			dest_acct_name,
+			message,
+		)
+	}
+
+	fn receive_tx_impl(
+		&self,
+		slate: &Slate,
+		address: Option<String>,
+		expected_amount: Option<u64>,
+		dest_acct_name: Option<&str>,
+		message: Option<String>,
 	) -> Result<Slate, Error> {
 		let mut w_lock = self.wallet_inst.lock();
 		let w = w_lock.lc_provider()?.wallet_inst()?;
@@ -422,6 +472,7 @@ where
 			address,
 			None,
 			None,
+			expected_amount,
 			dest_acct_name,
 			message,
 			self.doctest_mode,
@@ -494,6 +545,7 @@ where
 			self.wallet_inst.clone(),
 			(&self.keychain_mask).as_ref(),
 			swap_message,
+			&self.swap_config.lock().clone(),
 		)
 	}
 }
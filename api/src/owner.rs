@@ -26,13 +26,19 @@ use crate::keychain::{Identifier, Keychain};
 use crate::libwallet::api_impl::owner_updater::{start_updater_log_thread, StatusMessage};
 use crate::libwallet::api_impl::{owner, owner_swap, owner_updater};
 use crate::libwallet::proof::tx_proof::TxProof;
+use crate::libwallet::swap::config::SwapConfig;
 use crate::libwallet::swap::fsm::state::{StateEtaInfo, StateId, StateProcessRespond};
-use crate::libwallet::swap::types::{Action, SwapTransactionsConfirmations};
-use crate::libwallet::swap::{message::Message, swap::Swap, swap::SwapJournalRecord};
+use crate::libwallet::swap::message::{Message, SwapMessageLog};
+use crate::libwallet::swap::metrics::SwapMetrics;
+use crate::libwallet::swap::types::{
+	Action, CompletionCertificate, RefundSimulation, SafetyAssessment,
+	SwapTransactionsConfirmations,
+};
+use crate::libwallet::swap::{swap::Swap, swap::SwapJournalRecord};
 use crate::libwallet::{
 	AcctPathMapping, Error, ErrorKind, InitTxArgs, IssueInvoiceTxArgs, NodeClient,
-	NodeHeightResult, OutputCommitMapping, PaymentProof, Slate, SwapStartArgs, TxLogEntry,
-	WalletInfo, WalletInst, WalletLCProvider,
+	NodeHeightResult, OutputCommitMapping, PaymentProof, Slate, SlateVerification, SwapAcceptArgs,
+	SwapStartArgs, TxLogEntry, WalletInfo, WalletInst, WalletLCProvider,
 };
 use crate::util::logger::LoggingConfig;
 use crate::util::secp::key::SecretKey;
@@ -85,6 +91,8 @@ where
 	/// Optional TOR configuration, holding address of sender and
 	/// data directory
 	tor_config: Mutex<Option<TorConfig>>,
+	/// Swap engine parameters, used by the swap trade methods
+	swap_config: Mutex<SwapConfig>,
 
 	/// updater log thread. Expected to be removed at next rebase
 	updater_log_thread: Option<JoinHandle<()>>,
@@ -228,6 +236,7 @@ where
 			status_tx: Mutex::new(Some(tx)),
 			updater_messages,
 			tor_config: Mutex::new(tor_config),
+			swap_config: Mutex::new(SwapConfig::default()),
 			updater_log_thread: handle,
 			updater_log_running_state: running,
 		}
@@ -246,6 +255,24 @@ where
 		*lock = tor_config;
 	}
 
+	/// Set the swap engine parameters for this instance of the OwnerAPI, used by
+	/// the swap trade methods.
+	///
+	/// # Arguments
+	/// * `swap_config` - The [SwapConfig](#) to use
+	/// # Returns
+	/// * Nothing
+
+	pub fn set_swap_config(&self, swap_config: SwapConfig) {
+		let mut lock = self.swap_config.lock();
+		*lock = swap_config;
+	}
+
+	/// Returns the swap engine parameters of this instance of the OwnerAPI.
+	pub fn swap_config(&self) -> SwapConfig {
+		self.swap_config.lock().clone()
+	}
+
 	/// Returns a list of accounts stored in the wallet (i.e. mappings between
 	/// user-specified labels and BIP32 derivation paths.
 	/// # Arguments
@@ -1054,6 +1081,37 @@ where
 		Ok(slate_res)
 	}
 
+	/// Export the slate after round 1. The slate must be created with
+	/// [`split_signing`](../grin_wallet_libwallet/api_impl/types/struct.InitTxArgs.html#structfield.split_signing),
+	/// the export can be finalized with [`finalize_exported_tx`](struct.Owner.html#method.finalize_exported_tx)
+	/// by another wallet with the same seed. The export has no secrets. The slate can be exported
+	/// only once, this wallet can't sign it after the export.
+	pub fn export_round_1(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		slate: &Slate,
+	) -> Result<String, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::export_round_1(&mut **w, keychain_mask, slate)
+	}
+
+	/// Sign the slate with the round 1 export from
+	/// [`export_round_1`](struct.Owner.html#method.export_round_1). The send and the invoice are
+	/// finalized and the resulting transaction can be posted by either wallet. The invoice
+	/// payment is returned with the payer signature for the invoicer. The export can sign only
+	/// one slate, the second use is rejected.
+	pub fn finalize_exported_tx(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		export: &str,
+		slate: &Slate,
+	) -> Result<Slate, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::finalize_exported_tx(&mut **w, keychain_mask, export, slate)
+	}
+
 	/// Posts a completed transaction to the listening node for validation and inclusion in a block
 	/// for mining.
 	///
@@ -1122,6 +1180,24 @@ where
 		owner::post_tx(&client, tx, fluff)
 	}
 
+	/// Verify the slate against the node without signing or posting it. Inputs are checked to be
+	/// unspent, kernels and fee are checked to be consistent with the slate.
+	/// Return the verification report, the slate is valid if the report has no problems.
+	pub fn verify_slate(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		slate: &Slate,
+	) -> Result<SlateVerification, Error> {
+		let client = {
+			let mut w_lock = self.wallet_inst.lock();
+			let w = w_lock.lc_provider()?.wallet_inst()?;
+			// Test keychain mask, to keep API consistent
+			let _ = w.keychain(keychain_mask)?;
+			w.w2n_client().clone()
+		};
+		owner::verify_slate(&client, slate)
+	}
+
 	/// Cancels a transaction. This entails:
 	/// * Setting the transaction status to either `TxSentCancelled` or `TxReceivedCancelled`
 	/// * Deleting all change outputs or recipient outputs associated with the transaction
@@ -1197,6 +1273,23 @@ where
 		)
 	}
 
+	/// Resume the slate from its checkpoint if the wallet was stopped between the signing rounds.
+	/// Checkpoints are saved only if `slate_checkpoints` is enabled in the wallet config.
+	/// If the other party already responded, the `received` slate is used when it extends the
+	/// checkpoint, otherwise the checkpoint is returned.
+	pub fn resume_slate(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		slate_id: &Uuid,
+		received: Option<&Slate>,
+	) -> Result<Slate, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		// Test keychain mask, to keep API consistent
+		let _ = w.keychain(keychain_mask)?;
+		owner::resume_slate(slate_id, received)
+	}
+
 	/// Retrieves the stored transaction associated with a TxLogEntry. Can be used even after the
 	/// transaction has completed.
 	///
@@ -1243,6 +1336,20 @@ where
 		owner::get_stored_tx(&**w, tx_log_entry)
 	}
 
+	/// Loads the slate that was stored when the transaction was sent. The slate can be
+	/// exported again at a different version without rebuilding the transaction.
+	pub fn get_stored_slate(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		slate_id: &Uuid,
+	) -> Result<Slate, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		// Test keychain mask, to keep API consistent
+		let _ = w.keychain(keychain_mask)?;
+		owner::get_stored_slate(&**w, slate_id)
+	}
+
 	/// Loads a stored transaction from a file
 	pub fn load_stored_tx(&self, file: &String) -> Result<Transaction, Error> {
 		let mut w_lock = self.wallet_inst.lock();
@@ -2318,18 +2425,38 @@ where
 	) -> Result<String, Error> {
 		// Updating wallet state first because we need to select outputs.
 		owner::update_wallet_state(self.wallet_inst.clone(), keychain_mask, &None)?;
-		owner_swap::swap_start(self.wallet_inst.clone(), keychain_mask, params)
+		owner_swap::swap_start(
+			self.wallet_inst.clone(),
+			keychain_mask,
+			params,
+			&self.swap_config(),
+		)
+	}
+
+	/// Preview the outputs that swap_start will lock with the same start arguments. If funds are
+	/// not enough, the largest possible selection and the shortfall are returned.
+	pub fn swap_preview_lock_inputs(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		params: &SwapStartArgs,
+	) -> Result<owner_swap::LockInputsPreview, Error> {
+		// Updating wallet state first because we need to select outputs.
+		owner::update_wallet_state(self.wallet_inst.clone(), keychain_mask, &None)?;
+		owner_swap::preview_lock_inputs(self.wallet_inst.clone(), keychain_mask, params)
 	}
 
 	pub fn swap_create_from_offer(
 		&self,
 		keychain_mask: Option<&SecretKey>,
 		message_filename: String,
+		accept_args: &SwapAcceptArgs,
 	) -> Result<String, Error> {
 		owner_swap::swap_create_from_offer(
 			self.wallet_inst.clone(),
 			keychain_mask,
 			message_filename,
+			accept_args,
+			&self.swap_config(),
 		)
 	}
 
@@ -2361,6 +2488,7 @@ where
 
 	/// Adjust the sate of swap trade.
 	/// method & destination required for adjust_cmd='destination'
+	/// redeem_fee (nanocoins) required for adjust_cmd='redeem_fee'
 	pub fn swap_adjust(
 		&self,
 		keychain_mask: Option<&SecretKey>,
@@ -2370,6 +2498,7 @@ where
 		destination: Option<String>,
 		secondary_address: Option<String>, // secondary address to adjust
 		secondary_fee: Option<f32>,
+		redeem_fee: Option<u64>,
 		electrum_node_uri1: Option<String>,
 		electrum_node_uri2: Option<String>,
 	) -> Result<(StateId, Action), Error> {
@@ -2382,6 +2511,7 @@ where
 			destination,
 			secondary_address,
 			secondary_fee,
+			redeem_fee,
 			electrum_node_uri1,
 			electrum_node_uri2,
 		)
@@ -2396,6 +2526,98 @@ where
 		owner_swap::swap_dump(self.wallet_inst.clone(), keychain_mask, &swap_id)
 	}
 
+	/// Recommendation if the swap trade can be continued: safe, refund window is near,
+	/// refund now or both redeem and refund are possible.
+	pub fn swap_safety_status(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		swap_id: String,
+	) -> Result<SafetyAssessment, Error> {
+		owner_swap::swap_safety_status(
+			self.wallet_inst.clone(),
+			keychain_mask,
+			&swap_id,
+			&self.swap_config(),
+		)
+	}
+
+	/// Check if the swap trade is waiting for the confirmations longer than expected.
+	/// The node might be stuck or the transaction dropped.
+	pub fn swap_is_stalled(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		swap_id: String,
+	) -> Result<bool, Error> {
+		owner_swap::swap_is_stalled(
+			self.wallet_inst.clone(),
+			keychain_mask,
+			&swap_id,
+			&self.swap_config(),
+		)
+	}
+
+	/// Certificate of the completed swap trade. It has the references to the transactions on
+	/// both chains, so a third party can verify that the swap is done.
+	pub fn swap_completion_certificate(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		swap_id: String,
+	) -> Result<CompletionCertificate, Error> {
+		owner_swap::swap_completion_certificate(self.wallet_inst.clone(), keychain_mask, &swap_id)
+	}
+
+	/// Simulate the refund of the swap trade. Seller refunds MWC, Buyer refunds the
+	/// secondary currency at the trade secondary fee rate.
+	pub fn swap_simulate_refund(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		swap_id: String,
+	) -> Result<RefundSimulation, Error> {
+		owner_swap::swap_simulate_refund(self.wallet_inst.clone(), keychain_mask, &swap_id)
+	}
+
+	/// Simulate the Buyer's refund for the offer from the file before accepting it.
+	/// secondary_fee - refund fee rate, the secondary currency default if None.
+	pub fn swap_simulate_offer_refund(
+		&self,
+		message_filename: String,
+		secondary_address: Option<String>,
+		secondary_fee: Option<f32>,
+	) -> Result<RefundSimulation, Error> {
+		owner_swap::swap_simulate_offer_refund(message_filename, secondary_address, secondary_fee)
+	}
+
+	/// Messages that were sent and received for the swap trade, with direction and time.
+	/// Signatures and secrets of the messages are redacted.
+	pub fn swap_message_log(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		swap_id: String,
+	) -> Result<SwapMessageLog, Error> {
+		owner_swap::swap_message_log(self.wallet_inst.clone(), keychain_mask, &swap_id)
+	}
+
+	/// Verify integrity of all stored swap trades.
+	/// Return the trades with problems: <SwapId, problems>
+	pub fn swap_verify_all(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+	) -> Result<Vec<(String, Vec<String>)>, Error> {
+		owner_swap::swap_verify_all(self.wallet_inst.clone(), keychain_mask)
+	}
+
+	/// Aggregated metrics of all stored swap trades: counts per status, completion time
+	/// and refund rate. `SwapMetrics::to_prometheus` formats them for a metrics endpoint.
+	pub fn swap_metrics(&self, keychain_mask: Option<&SecretKey>) -> Result<SwapMetrics, Error> {
+		owner_swap::swap_metrics(self.wallet_inst.clone(), keychain_mask)
+	}
+
+	/// MWC amount that is locked on-chain by the active swap trades and not redeemed or
+	/// refunded yet. Subtract it from the wallet balance to get the amount free from the swaps.
+	pub fn swap_locked_balance(&self, keychain_mask: Option<&SecretKey>) -> Result<u64, Error> {
+		owner_swap::swap_locked_balance(self.wallet_inst.clone(), keychain_mask)
+	}
+
 	/// Refresh and get a status and current expected action for the swap.
 	/// return: <state>, <Action>, <time limit>, <Readmap lines>, <Journal records>
 	/// time limit shows when this action will be expired
@@ -2471,16 +2693,27 @@ where
 			electrum_node_uri1,
 			electrum_node_uri2,
 			false,
+			&self.swap_config(),
 		)
 	}
 
 	/// Process swap income message
+	/// verified_sender - sender address verified by the transport, None if unknown
 	pub fn swap_income_message(
 		&self,
 		keychain_mask: Option<&SecretKey>,
 		message: String,
+		verified_sender: Option<String>,
 	) -> Result<Option<Message>, Error> {
-		owner_swap::swap_income_message(self.wallet_inst.clone(), keychain_mask, &message, None)
+		owner_swap::swap_income_message(
+			self.wallet_inst.clone(),
+			keychain_mask,
+			&message,
+			verified_sender.as_deref(),
+			None,
+			&SwapAcceptArgs::default(),
+			&self.swap_config(),
+		)
 	}
 }
 
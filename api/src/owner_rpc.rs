@@ -1181,6 +1181,218 @@ pub trait OwnerRpc: Sync + Send {
 	 */
 	fn cancel_tx(&self, tx_id: Option<u32>, tx_slate_id: Option<Uuid>) -> Result<(), ErrorKind>;
 
+	/**
+	Networked version of [Owner::resume_slate](struct.Owner.html#method.resume_slate).
+
+	```
+	# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"method": "resume_slate",
+		"params": {
+			"slate_id": "0436430c-2b02-624c-2032-570501212b00",
+			"received": null
+		},
+		"id": 1
+	}
+	# "#
+	# ,
+	# r#"
+	{
+		"id": 1,
+		"jsonrpc": "2.0",
+		"result": {
+			"Err": {
+				"GenericError": "Checkpoint for slate 0436430c-2b02-624c-2032-570501212b00 is not found"
+			}
+		}
+	}
+	# "#
+	# , false, 5, true, true, false, false);
+	```
+	 */
+	fn resume_slate(
+		&self,
+		slate_id: Uuid,
+		received: Option<VersionedSlate>,
+	) -> Result<VersionedSlate, ErrorKind>;
+
+	/**
+	Networked version of [Owner::export_round_1](struct.Owner.html#method.export_round_1).
+
+	```
+	# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"method": "export_round_1",
+		"params": {
+			"slate": {
+			  "version_info": {
+				"version": 2,
+				"orig_version": 3,
+				"block_header_version": 1
+			  },
+			  "num_participants": 2,
+			  "id": "0436430c-2b02-624c-2032-570501212b00",
+			  "tx": {
+				"offset": "d202964900000000d302964900000000d402964900000000d502964900000000",
+				"body": {
+				  "inputs": [
+					{
+					  "features": "Coinbase",
+					  "commit": "098778ce2243fa34e5876c8cb7f6dbbbd6a5649c1561973a807a6811941c12363c"
+					},
+					{
+					  "features": "Coinbase",
+					  "commit": "0910c1752100733bae49e877286835aab76d5856ef8139b6c6e3f51798aa461b03"
+					}
+				  ],
+				  "outputs": [
+					{
+					  "features": "Plain",
+					  "commit": "096e1669267c22ecb38c466d73b8578261d8e91c14dd66702dd5bf34f4232e10db",
+					  "proof": "7d567b0895a1103d19446929da8b98f2086819507ddce4b9dbb5ce6327107744e74aba59ef1834937da1b86eb7c1c1b0bc11d1c5d5ec79d25bc1e52aed1656f60d46f6878ba5ca8639efdbb9203e378e91171c11527c4a34713f06dc22f58ca4a08e68d83ff897e61cfc145fe376fa428b55e25cf20d15f10b9054778229798b30fb4e45d817a5053b682dcf591481a3c8174cfbba81e31aa525d5b884ca7a016713178f26c0fe8ae1f88b5382f8e70c4d91fb3828c0f307d828aa028281d3551525e68d20827ab0e6785c6b5747e895dcd38429b44e62b7f6c1c921d87ae954a9dd6e967ac52e6cd13a1d4bb2f1434da25a0723ef9c869cc573019577552dd0e0f808f8cc57723b041320025f6433779fe907998a4ec7606bf884b2199253b502065bed8e0625c2df858d6508c1aa44deddc68d06d00d81e97720e23e15a3464ed4733fc547e9fb772e563a1dbcd27ac55e40f674f9006e7dd4465444f3eb7527cb01905dee69a51cf2fc1810c861dd0834e7649d594c3e1740d85343a6b63c8a9e0a0f63059031899b38dfd9a192034d54029bd35e683ccab46282519b26cae20d398b754357abe1cf0370890f2897b5d8ada4fb3da777a8f8f1daa4197a380e6734504117dd2a92ea1917f174c44c59e0b50c6b7a5f9eb14e6d96cb6b3e5dbcb3d0eaf0e4aac1b6616d674bb708b7559e37de608e8a828bee7f25f627e2f06d9a87e8d651ade39e1e65db7204b94abc0b7ca6fdd75aadeeac6a876b6297e38039734ebdfa9a555152b4293cb00e423a66d64f827afa4748dd6fdc1dc33332bffb820dacbf5a6d347042db985bbd9cf476dceb45d6978035ba03d25612243fc164c0a902017ce7ffd632d041fa3c56554739e78c6d725ecbfdaa0739d3649239fb53294b7a46ee6ed403bf3815f6c78f06a8ca4e3c9b066234f7574fb6ea8f17d199"
+					}
+				  ],
+				  "kernels": [
+					{
+					  "features": "Plain",
+					  "fee": "7000000",
+					  "lock_height": "0",
+					  "excess": "000000000000000000000000000000000000000000000000000000000000000000",
+					  "excess_sig": "00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"
+					}
+				  ]
+				}
+			  },
+			  "amount": "2000000000",
+			  "fee": "7000000",
+			  "height": "5",
+			  "lock_height": "0",
+			  "participant_data": [
+				{
+				  "id": "0",
+				  "public_blind_excess": "03ad559b009e8231fcc2a06d40b7341322974c9b13a52000ca2462df2de60aba9f",
+				  "public_nonce": "031b84c5567b126440995d3ed5aaba0565d71e1834604819ff9c17f5e9d5dd078f",
+				  "part_sig": null,
+				  "message": null,
+				  "message_sig": null
+				}
+			  ]
+			}
+		},
+		"id": 1
+	}
+	# "#
+	# ,
+	# r#"
+	{
+		"id": 1,
+		"jsonrpc": "2.0",
+		"result": {
+			"Err": {
+				"GenericError": "Slate 0436430c-2b02-624c-2032-570501212b00 has no single split signing participant in this wallet, unable to export it"
+			}
+		}
+	}
+	# "#
+	# , false, 5, true, false, false, false);
+	```
+	 */
+	fn export_round_1(&self, slate: VersionedSlate) -> Result<String, ErrorKind>;
+
+	/**
+	Networked version of [Owner::finalize_exported_tx](struct.Owner.html#method.finalize_exported_tx).
+
+	```
+	# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"method": "finalize_exported_tx",
+		"params": {
+			"export": "",
+			"slate": {
+			  "version_info": {
+				"version": 2,
+				"orig_version": 3,
+				"block_header_version": 1
+			  },
+			  "num_participants": 2,
+			  "id": "0436430c-2b02-624c-2032-570501212b00",
+			  "tx": {
+				"offset": "d202964900000000d302964900000000d402964900000000d502964900000000",
+				"body": {
+				  "inputs": [
+					{
+					  "features": "Coinbase",
+					  "commit": "098778ce2243fa34e5876c8cb7f6dbbbd6a5649c1561973a807a6811941c12363c"
+					},
+					{
+					  "features": "Coinbase",
+					  "commit": "0910c1752100733bae49e877286835aab76d5856ef8139b6c6e3f51798aa461b03"
+					}
+				  ],
+				  "outputs": [
+					{
+					  "features": "Plain",
+					  "commit": "096e1669267c22ecb38c466d73b8578261d8e91c14dd66702dd5bf34f4232e10db",
+					  "proof": "7d567b0895a1103d19446929da8b98f2086819507ddce4b9dbb5ce6327107744e74aba59ef1834937da1b86eb7c1c1b0bc11d1c5d5ec79d25bc1e52aed1656f60d46f6878ba5ca8639efdbb9203e378e91171c11527c4a34713f06dc22f58ca4a08e68d83ff897e61cfc145fe376fa428b55e25cf20d15f10b9054778229798b30fb4e45d817a5053b682dcf591481a3c8174cfbba81e31aa525d5b884ca7a016713178f26c0fe8ae1f88b5382f8e70c4d91fb3828c0f307d828aa028281d3551525e68d20827ab0e6785c6b5747e895dcd38429b44e62b7f6c1c921d87ae954a9dd6e967ac52e6cd13a1d4bb2f1434da25a0723ef9c869cc573019577552dd0e0f808f8cc57723b041320025f6433779fe907998a4ec7606bf884b2199253b502065bed8e0625c2df858d6508c1aa44deddc68d06d00d81e97720e23e15a3464ed4733fc547e9fb772e563a1dbcd27ac55e40f674f9006e7dd4465444f3eb7527cb01905dee69a51cf2fc1810c861dd0834e7649d594c3e1740d85343a6b63c8a9e0a0f63059031899b38dfd9a192034d54029bd35e683ccab46282519b26cae20d398b754357abe1cf0370890f2897b5d8ada4fb3da777a8f8f1daa4197a380e6734504117dd2a92ea1917f174c44c59e0b50c6b7a5f9eb14e6d96cb6b3e5dbcb3d0eaf0e4aac1b6616d674bb708b7559e37de608e8a828bee7f25f627e2f06d9a87e8d651ade39e1e65db7204b94abc0b7ca6fdd75aadeeac6a876b6297e38039734ebdfa9a555152b4293cb00e423a66d64f827afa4748dd6fdc1dc33332bffb820dacbf5a6d347042db985bbd9cf476dceb45d6978035ba03d25612243fc164c0a902017ce7ffd632d041fa3c56554739e78c6d725ecbfdaa0739d3649239fb53294b7a46ee6ed403bf3815f6c78f06a8ca4e3c9b066234f7574fb6ea8f17d199"
+					}
+				  ],
+				  "kernels": [
+					{
+					  "features": "Plain",
+					  "fee": "7000000",
+					  "lock_height": "0",
+					  "excess": "000000000000000000000000000000000000000000000000000000000000000000",
+					  "excess_sig": "00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000"
+					}
+				  ]
+				}
+			  },
+			  "amount": "2000000000",
+			  "fee": "7000000",
+			  "height": "5",
+			  "lock_height": "0",
+			  "participant_data": [
+				{
+				  "id": "0",
+				  "public_blind_excess": "03ad559b009e8231fcc2a06d40b7341322974c9b13a52000ca2462df2de60aba9f",
+				  "public_nonce": "031b84c5567b126440995d3ed5aaba0565d71e1834604819ff9c17f5e9d5dd078f",
+				  "part_sig": null,
+				  "message": null,
+				  "message_sig": null
+				}
+			  ]
+			}
+		},
+		"id": 1
+	}
+	# "#
+	# ,
+	# r#"
+	{
+		"id": 1,
+		"jsonrpc": "2.0",
+		"result": {
+			"Err": {
+				"SlateDeser": "Unable to read round 1 export, EOF while parsing a value at line 1 column 0"
+			}
+		}
+	}
+	# "#
+	# , false, 5, true, false, false, false);
+	```
+	 */
+	fn finalize_exported_tx(
+		&self,
+		export: String,
+		slate: VersionedSlate,
+	) -> Result<VersionedSlate, ErrorKind>;
+
 	/**
 	Networked version of [Owner::get_stored_tx](struct.Owner.html#method.get_stored_tx).
 
@@ -1492,6 +1704,33 @@ where
 		Owner::cancel_tx(self, None, tx_id, tx_slate_id).map_err(|e| e.kind())
 	}
 
+	fn resume_slate(
+		&self,
+		slate_id: Uuid,
+		received: Option<VersionedSlate>,
+	) -> Result<VersionedSlate, ErrorKind> {
+		let received = received.map(Slate::from);
+		let slate =
+			Owner::resume_slate(self, None, &slate_id, received.as_ref()).map_err(|e| e.kind())?;
+		let version = slate.lowest_version();
+		Ok(VersionedSlate::into_version(slate, version))
+	}
+
+	fn export_round_1(&self, slate: VersionedSlate) -> Result<String, ErrorKind> {
+		Owner::export_round_1(self, None, &Slate::from(slate)).map_err(|e| e.kind())
+	}
+
+	fn finalize_exported_tx(
+		&self,
+		export: String,
+		slate: VersionedSlate,
+	) -> Result<VersionedSlate, ErrorKind> {
+		let out_slate = Owner::finalize_exported_tx(self, None, &export, &Slate::from(slate))
+			.map_err(|e| e.kind())?;
+		let version = out_slate.lowest_version();
+		Ok(VersionedSlate::into_version(out_slate, version))
+	}
+
 	fn get_stored_tx(&self, tx: &TxLogEntryAPI) -> Result<Option<TransactionV3>, ErrorKind> {
 		Owner::get_stored_tx(
 			self,
@@ -1748,6 +1987,7 @@ pub fn run_doctest_owner(
 				None,
 				None,
 				None,
+				None,
 				true,
 				false,
 			)
@@ -109,6 +109,54 @@ fn comments() -> HashMap<String, String> {
 		"dark_background_color_scheme".to_string(),
 		"
 #Whether to use the black background color scheme for command line
+"
+		.to_string(),
+	);
+	retval.insert(
+		"slate_checkpoints".to_string(),
+		"
+#If true, the partial slates are saved between the signing rounds, so the transaction
+#can be resumed if the wallet was stopped before it was finalized
+"
+		.to_string(),
+	);
+	retval.insert(
+		"require_payment_proof".to_string(),
+		"
+#If true, the wallet refuses to send the transactions without a payment proof.
+#Such slates are always written as V3 or newer
+"
+		.to_string(),
+	);
+	retval.insert(
+		"reject_missing_payment_proof".to_string(),
+		"
+#If true, the transaction that was sent with a payment proof request can't be finalized
+#if the recipient returned the slate without the proof
+"
+		.to_string(),
+	);
+	retval.insert(
+		"slate_version_log".to_string(),
+		"
+#If true, the version of every written slate file and the slate features that required it
+#are recorded at slate_versions.log in the wallet data directory
+"
+		.to_string(),
+	);
+	retval.insert(
+		"slate_schema_validation".to_string(),
+		"
+#If true, the slates that are read from the files are validated against the slate JSON schema.
+#The errors point to the exact field, it helps with the third party wallets interop
+"
+		.to_string(),
+	);
+	retval.insert(
+		"swap_counterparty_allowlist".to_string(),
+		"
+#MQS or Tor addresses of the counterparties that are allowed to send the swap offers.
+#Offers from other addresses are rejected. If not set, offers from anybody are accepted
 "
 		.to_string(),
 	);
@@ -135,6 +183,27 @@ fn comments() -> HashMap<String, String> {
 		.to_string(),
 	);
 
+	retval.insert(
+		"[wallet.swap_engine]".to_string(),
+		"
+# Swap engine parameters. The defaults are the protocol values, change them only if
+# you know what you are doing. Not listed parameters are using the defaults.
+# node_call_timeout_sec - limit for a single node call, seconds
+# lock_slate_max_age - number of blocks that the offer lock slate can be behind the tip
+# refund_fee_max_multiplier - refund fee limit, in the minimal refund fees
+# lock_inputs_batch_size - lock slate inputs are requested from the node by batches
+# pool_size_per_block - average number of transactions in a block, for the redeem fee
+# max_redeem_fee_multiplier - suggested redeem fee limit, in the minimal fees
+# clock_tolerance_sec - clock difference between Buyer and Seller, seconds
+# max_mwc_lock_blocks - longest time the MWC can be locked, blocks
+# message_min_interval_sec - minimal interval between the outbound swap messages, seconds
+# refund_lock_height_tolerance_percent - refund lock height tolerance, percent
+# [wallet.swap_engine.lock_time_tolerance_percent] - lock time tolerance in the offers,
+# percent. Key: secondary currency (btc, bch), value: percent
+"
+		.to_string(),
+	);
+
 	retval.insert(
 		"[logging]".to_string(),
 		"
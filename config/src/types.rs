@@ -62,10 +62,25 @@ pub struct WalletConfig {
 	pub dark_background_color_scheme: Option<bool>,
 	/// Wallet data directory. Default none is 'wallet_data'
 	pub wallet_data_dir: Option<String>,
+	/// Save the partial slates between the signing rounds, so they can be resumed
+	pub slate_checkpoints: Option<bool>,
+	/// If true, every outgoing slate must have a payment proof
+	pub require_payment_proof: Option<bool>,
+	/// If true, finalize fails when the requested payment proof is missing in the returned slate
+	pub reject_missing_payment_proof: Option<bool>,
+	/// Record the version chosen for every written slate and the features that required it
+	pub slate_version_log: Option<bool>,
+	/// Validate the slates that are read from the files against the bundled JSON schema
+	pub slate_schema_validation: Option<bool>,
+	/// MQS or Tor addresses of the counterparties that are allowed to send the swap offers.
+	/// None - offers from any counterparty are accepted.
+	pub swap_counterparty_allowlist: Option<Vec<String>>,
 	/// Electrum nodes for secondary coins
 	/// Key: <coin>_[main|test]_[1|2]
 	/// Value: url
 	pub swap_electrumx_addr: Option<BTreeMap<String, String>>,
+	/// Swap engine parameters. Not listed parameters are using the protocol defaults.
+	pub swap_engine: Option<SwapEngineConfig>,
 }
 
 impl Default for WalletConfig {
@@ -87,6 +102,12 @@ impl Default for WalletConfig {
 			tls_certificate_key: None,
 			dark_background_color_scheme: Some(true),
 			wallet_data_dir: None,
+			slate_checkpoints: Some(false),
+			require_payment_proof: Some(false),
+			reject_missing_payment_proof: Some(false),
+			slate_version_log: Some(false),
+			slate_schema_validation: Some(false),
+			swap_counterparty_allowlist: None,
 			swap_electrumx_addr: Some(
 				[
 					("btc_main_1", "btc.main1.swap.mwc.mw:18337"),
@@ -103,10 +124,39 @@ impl Default for WalletConfig {
 				.map(|i| (i.0.to_string(), i.1.to_string()))
 				.collect::<BTreeMap<String, String>>(),
 			),
+			swap_engine: Some(SwapEngineConfig::default()),
 		}
 	}
 }
 
+/// Swap engine parameters. None - the protocol default value is used.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct SwapEngineConfig {
+	/// Limit for a single node call, seconds
+	pub node_call_timeout_sec: Option<u64>,
+	/// Number of blocks that the offer lock slate can be behind the chain tip
+	pub lock_slate_max_age: Option<u64>,
+	/// Refund fee can't exceed the minimal refund fee more than that many times
+	pub refund_fee_max_multiplier: Option<u64>,
+	/// Lock slate inputs are requested from the node by batches of that size
+	pub lock_inputs_batch_size: Option<usize>,
+	/// Number of average transactions that fits into a block, used for the redeem fee
+	pub pool_size_per_block: Option<u64>,
+	/// Suggested redeem fee is never higher than that number of minimal fees
+	pub max_redeem_fee_multiplier: Option<u64>,
+	/// Clock difference between Buyer and Seller that is tolerated, seconds
+	pub clock_tolerance_sec: Option<i64>,
+	/// Longest time the MWC can be locked, blocks
+	pub max_mwc_lock_blocks: Option<u64>,
+	/// Minimal interval between the outbound swap messages, seconds. 0 means no limit.
+	pub message_min_interval_sec: Option<i64>,
+	/// Refund lock height tolerance, percent
+	pub refund_lock_height_tolerance_percent: Option<u64>,
+	/// Lock time tolerance, percent, that goes into the offers.
+	/// Key: secondary currency, Value: percent
+	pub lock_time_tolerance_percent: Option<BTreeMap<String, u64>>,
+}
+
 impl WalletConfig {
 	/// API Listen address
 	pub fn api_listen_addr(&self) -> String {
@@ -22,7 +22,9 @@ use crate::error::{Error, ErrorKind};
 use crate::impls::{create_sender, SlateGetter as _};
 use crate::impls::{PathToSlate, SlatePutter};
 use crate::keychain;
-use crate::libwallet::{InitTxArgs, IssueInvoiceTxArgs, NodeClient, WalletLCProvider};
+use crate::libwallet::{
+	InitTxArgs, IssueInvoiceTxArgs, NodeClient, SlateVersion, WalletLCProvider,
+};
 use crate::util::secp::key::SecretKey;
 use crate::util::{Mutex, ZeroingString};
 use crate::{controller, display};
@@ -32,7 +34,11 @@ use grin_wallet_impls::{Address, MWCMQSAddress, Publisher};
 use grin_wallet_libwallet::api_impl::owner_swap;
 use grin_wallet_libwallet::proof::proofaddress::ProvableAddress;
 use grin_wallet_libwallet::proof::tx_proof::TxProof;
+use grin_wallet_libwallet::swap::config::SwapConfig;
 use grin_wallet_libwallet::swap::message;
+use grin_wallet_libwallet::swap::rebroadcast::{
+	RebroadcastSchedule, RebroadcastStatus, DEFAULT_REBROADCAST_INTERVAL,
+};
 use grin_wallet_libwallet::swap::trades;
 use grin_wallet_libwallet::swap::types::Action;
 use grin_wallet_libwallet::{Slate, TxLogEntry, WalletInst};
@@ -157,6 +163,7 @@ where
 			let config = config.clone();
 			let tor_config = tor_config.clone();
 			let g_args = g_args.clone();
+			let swap_config = owner_api.swap_config();
 			let api_thread = thread::Builder::new()
 				.name("wallet-http-listener".to_string())
 				.spawn(move || {
@@ -166,6 +173,7 @@ where
 						&config.api_listen_addr(),
 						g_args.tls_conf.clone(),
 						tor_config.use_tor_listener,
+						swap_config,
 					);
 					if let Err(e) = res {
 						error!("Error starting http listener: {}", e);
@@ -189,6 +197,7 @@ where
 				mqs_config.clone(),
 				keychain_mask,
 				!cli_mode,
+				owner_api.swap_config(),
 			)
 			.map_err(|e| {
 				error!("Unable to start mwcmqs listener, {}", e);
@@ -204,6 +213,33 @@ where
 	Ok(())
 }
 
+/// Arguments for slate-serve command
+pub struct SlateServeArgs {
+	pub listen: String,
+	pub allow_remote: bool,
+}
+
+pub fn slate_serve<L, C, K>(
+	owner_api: &mut Owner<L, C, K>,
+	keychain_mask: Option<SecretKey>,
+	args: &SlateServeArgs,
+) -> Result<(), Error>
+where
+	L: WalletLCProvider<'static, C, K> + 'static,
+	C: NodeClient + 'static,
+	K: keychain::Keychain + 'static,
+{
+	controller::slate_serve_listener(
+		owner_api.wallet_inst.clone(),
+		Arc::new(Mutex::new(keychain_mask)),
+		&args.listen,
+		args.allow_remote,
+		controller::SLATE_SERVE_MAX_BODY_SIZE,
+	)
+	.map_err(|e| ErrorKind::LibWallet(format!("Unable to start slate serve listener, {}", e)))?;
+	Ok(())
+}
+
 pub fn owner_api<L, C, K>(
 	owner_api: &mut Owner<L, C, K>,
 	keychain_mask: Option<SecretKey>,
@@ -228,7 +264,7 @@ where
 			mqs_config.clone(),
 			km.clone(),
 			false,
-			//None,
+			owner_api.swap_config(),
 		)?;
 	}
 
@@ -241,6 +277,7 @@ where
 		g_args.tls_conf.clone(),
 		config.owner_api_include_foreign.clone(),
 		Some(tor_config.clone()),
+		owner_api.swap_config(),
 	)
 	.map_err(|e| ErrorKind::LibWallet(format!("Unable to start Listener, {}", e)))?;
 	Ok(())
@@ -307,11 +344,13 @@ pub struct SendArgs {
 	pub max_outputs: usize,
 	pub target_slate_version: Option<u16>,
 	pub payment_proof_address: Option<ProvableAddress>,
+	pub bind_to_recipient: bool,
 	pub ttl_blocks: Option<u64>,
 	pub exclude_change_outputs: bool,
 	pub minimum_confirmations_change_outputs: u64,
-	pub address: Option<String>,      //this is only for file proof.
-	pub outputs: Option<Vec<String>>, // Outputs to use. If None, all outputs can be used
+	pub address: Option<String>,       //this is only for file proof.
+	pub outputs: Option<Vec<String>>,  // Outputs to use. If None, all outputs can be used
+	pub split_signing: Option<String>, // Round 1 export file
 }
 
 pub fn send<L, C, K>(
@@ -362,12 +401,14 @@ where
 				message: args.message.clone(),
 				target_slate_version: args.target_slate_version,
 				payment_proof_recipient_address: args.payment_proof_address.clone(),
+				bind_to_recipient: Some(args.bind_to_recipient),
 				address: args.address.clone(),
 				ttl_blocks: args.ttl_blocks,
 				send_args: None,
 				exclude_change_outputs: Some(args.exclude_change_outputs),
 				minimum_confirmations_change_outputs: args.minimum_confirmations_change_outputs,
 				outputs: args.outputs.clone(),
+				split_signing: Some(args.split_signing.is_some()),
 				..Default::default()
 			};
 			let result = api.init_send_tx(m, init_args, 1);
@@ -416,7 +457,7 @@ where
 							mqs_config_unwrapped,
 							Arc::new(Mutex::new(km)),
 							false,
-							//None,
+							api.swap_config(),
 						)?;
 						thread::sleep(Duration::from_millis(2000));
 					}
@@ -427,7 +468,7 @@ where
 			match args.method.as_str() {
 				"file" => {
 					PathToSlate((&args.dest).into())
-						.put_tx(&slate)
+						.put_send_tx(&slate)
 						.map_err(|e| {
 							ErrorKind::IO(format!(
 								"Unable to store the file at {}, {}",
@@ -435,6 +476,9 @@ where
 							))
 						})?;
 					api.tx_lock_outputs(m, &slate, Some(String::from("file")), 0)?;
+					if let Some(export_file) = &args.split_signing {
+						write_round_1_export(api, m, &slate, export_file)?;
+					}
 					return Ok(());
 				}
 				"self" => {
@@ -494,6 +538,7 @@ where
 pub struct ReceiveArgs {
 	pub input: String,
 	pub message: Option<String>,
+	pub expected_amount: Option<u64>,
 }
 
 pub fn receive<L, C, K>(
@@ -507,7 +552,8 @@ where
 	C: NodeClient + 'static,
 	K: keychain::Keychain + 'static,
 {
-	let mut slate = PathToSlate((&args.input).into()).get_tx()?;
+	let tip_height = node_tip_height(owner_api, keychain_mask);
+	let mut slate = PathToSlate((&args.input).into()).get_tx_check_ttl(tip_height)?;
 	let km = match keychain_mask.as_ref() {
 		None => None,
 		Some(&m) => Some(m.to_owned()),
@@ -519,12 +565,21 @@ where
 				ErrorKind::LibWallet(format!("Unable to validate slate messages, {}", e)).into(),
 			);
 		}
-		slate = api.receive_tx(
-			&slate,
-			Some(String::from("file")),
-			Some(&g_args.account),
-			args.message.clone(),
-		)?;
+		slate = match args.expected_amount {
+			Some(expected_amount) => api.receive_tx_expected_amount(
+				&slate,
+				expected_amount,
+				Some(String::from("file")),
+				Some(&g_args.account),
+				args.message.clone(),
+			)?,
+			None => api.receive_tx(
+				&slate,
+				Some(String::from("file")),
+				Some(&g_args.account),
+				args.message.clone(),
+			)?,
+		};
 		Ok(())
 	})?;
 	PathToSlate(format!("{}.response", args.input).into()).put_tx(&slate)?;
@@ -535,6 +590,22 @@ where
 	Ok(())
 }
 
+/// Chain tip height for the slate validation, None if the node is not available
+fn node_tip_height<L, C, K>(
+	owner_api: &Owner<L, C, K>,
+	keychain_mask: Option<&SecretKey>,
+) -> Option<u64>
+where
+	L: WalletLCProvider<'static, C, K>,
+	C: NodeClient + 'static,
+	K: keychain::Keychain + 'static,
+{
+	match owner_api.node_height(keychain_mask) {
+		Ok(res) if res.updated_from_node => Some(res.height),
+		_ => None,
+	}
+}
+
 /// Finalize command args
 pub struct FinalizeArgs {
 	pub input: String,
@@ -554,7 +625,8 @@ where
 	C: NodeClient + 'static,
 	K: keychain::Keychain + 'static,
 {
-	let mut slate = PathToSlate((&args.input).into()).get_tx()?;
+	let tip_height = node_tip_height(owner_api, keychain_mask);
+	let mut slate = PathToSlate((&args.input).into()).get_tx_check_ttl(tip_height)?;
 
 	// Note!!! grin wallet was able to detect if it is invoice by using 'different' participant Ids (issuer use 1, fouset 0)
 	//    Unfortunatelly it is breaks mwc713 backward compatibility (issuer Participant Id 0, fouset 1)
@@ -623,6 +695,8 @@ pub struct IssueInvoiceArgs {
 	pub dest: String,
 	/// issue invoice tx args
 	pub issue_args: IssueInvoiceTxArgs,
+	/// round 1 export file
+	pub split_signing: Option<String>,
 }
 
 pub fn issue_invoice_tx<L, C, K>(
@@ -638,6 +712,9 @@ where
 	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
 		let slate = api.issue_invoice_tx(m, args.issue_args)?;
 		PathToSlate((&args.dest).into()).put_tx(&slate)?;
+		if let Some(export_file) = &args.split_signing {
+			write_round_1_export(api, m, &slate, export_file)?;
+		}
 		Ok(())
 	})?;
 	Ok(())
@@ -654,6 +731,7 @@ pub struct ProcessInvoiceArgs {
 	pub input: String,
 	pub estimate_selection_strategies: bool,
 	pub ttl_blocks: Option<u64>,
+	pub split_signing: Option<String>,
 }
 
 /// Process invoice
@@ -700,6 +778,7 @@ where
 				message: args.message.clone(),
 				ttl_blocks: args.ttl_blocks,
 				send_args: None,
+				split_signing: Some(args.split_signing.is_some()),
 				..Default::default()
 			};
 			if let Err(e) = api.verify_slate_messages(m, &slate) {
@@ -734,6 +813,9 @@ where
 					let slate_putter = PathToSlate((&args.dest).into());
 					slate_putter.put_tx(&slate)?;
 					api.tx_lock_outputs(m, &slate, Some(String::from("file")), 1)?;
+					if let Some(export_file) = &args.split_signing {
+						write_round_1_export(api, m, &slate, export_file)?;
+					}
 				}
 				"self" => {
 					api.tx_lock_outputs(m, &slate, Some(String::from("self")), 1)?;
@@ -1215,6 +1297,201 @@ where
 	}
 }
 
+/// Arguments for the slate inspect command
+pub struct SlateInspectArgs {
+	pub input: String,
+}
+
+pub fn slate_inspect(args: SlateInspectArgs) -> Result<(), Error> {
+	let slate = PathToSlate((&args.input).into()).get_versioned_tx()?;
+	println!("{}", slate.debug_summary());
+	Ok(())
+}
+
+/// Arguments for the slate reexport command
+pub struct SlateReexportArgs {
+	pub slate_id: Uuid,
+	pub version: SlateVersion,
+	pub dest: String,
+}
+
+/// Write the stored slate of the sent transaction at another version
+pub fn slate_reexport<L, C, K>(
+	owner_api: &mut Owner<L, C, K>,
+	keychain_mask: Option<&SecretKey>,
+	args: SlateReexportArgs,
+) -> Result<(), Error>
+where
+	L: WalletLCProvider<'static, C, K> + 'static,
+	C: NodeClient + 'static,
+	K: keychain::Keychain + 'static,
+{
+	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
+		let slate = api.get_stored_slate(m, &args.slate_id)?;
+		PathToSlate((&args.dest).into()).put_tx_version(&slate, args.version.clone())?;
+		println!(
+			"Slate {} is exported as {:?} to {}",
+			slate.id, args.version, args.dest
+		);
+		Ok(())
+	})?;
+	Ok(())
+}
+
+/// Arguments for the slate resume command
+pub struct SlateResumeArgs {
+	pub slate_id: Uuid,
+	pub input: Option<String>,
+	pub dest: String,
+}
+
+/// Write the partial slate from its checkpoint, the transaction can be continued with it
+pub fn slate_resume<L, C, K>(
+	owner_api: &mut Owner<L, C, K>,
+	keychain_mask: Option<&SecretKey>,
+	args: SlateResumeArgs,
+) -> Result<(), Error>
+where
+	L: WalletLCProvider<'static, C, K> + 'static,
+	C: NodeClient + 'static,
+	K: keychain::Keychain + 'static,
+{
+	let received = match &args.input {
+		Some(input) => Some(PathToSlate(input.into()).get_tx()?),
+		None => None,
+	};
+
+	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
+		let slate = api.resume_slate(m, &args.slate_id, received.as_ref())?;
+		PathToSlate((&args.dest).into()).put_tx(&slate)?;
+		println!(
+			"Slate {} is resumed from the checkpoint to {}",
+			slate.id, args.dest
+		);
+		Ok(())
+	})?;
+	Ok(())
+}
+
+// Export the slate after round 1 for the split signing
+fn write_round_1_export<L, C, K>(
+	api: &Owner<L, C, K>,
+	keychain_mask: Option<&SecretKey>,
+	slate: &Slate,
+	export_file: &str,
+) -> Result<(), Error>
+where
+	L: WalletLCProvider<'static, C, K> + 'static,
+	C: NodeClient + 'static,
+	K: keychain::Keychain + 'static,
+{
+	let export = api.export_round_1(keychain_mask, slate)?;
+	std::fs::write(export_file, export).map_err(|e| {
+		ErrorKind::IO(format!(
+			"Unable to store the round 1 export at {}, {}",
+			export_file, e
+		))
+	})?;
+	println!(
+		"Slate {} round 1 is exported to {}, sign it with finalize_exported on the machine with the same wallet seed",
+		slate.id, export_file
+	);
+	Ok(())
+}
+
+/// Arguments for the finalize_exported command
+pub struct FinalizeExportedArgs {
+	pub export: String,
+	pub input: String,
+	pub dest: String,
+}
+
+/// Sign the slate with the round 1 export from another machine
+pub fn finalize_exported<L, C, K>(
+	owner_api: &mut Owner<L, C, K>,
+	keychain_mask: Option<&SecretKey>,
+	args: FinalizeExportedArgs,
+) -> Result<(), Error>
+where
+	L: WalletLCProvider<'static, C, K> + 'static,
+	C: NodeClient + 'static,
+	K: keychain::Keychain + 'static,
+{
+	let export = std::fs::read_to_string(&args.export).map_err(|e| {
+		ErrorKind::IO(format!(
+			"Unable to read the round 1 export {}, {}",
+			args.export, e
+		))
+	})?;
+	let slate = PathToSlate((&args.input).into()).get_tx()?;
+
+	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
+		let slate = api.finalize_exported_tx(m, &export, &slate)?;
+		PathToSlate((&args.dest).into()).put_tx(&slate)?;
+		println!("Slate {} is signed and saved to {}", slate.id, args.dest);
+		Ok(())
+	})?;
+	Ok(())
+}
+
+/// Arguments for the slate verify command
+pub struct SlateVerifyArgs {
+	pub input: String,
+	pub json: bool,
+}
+
+pub fn slate_verify<L, C, K>(
+	owner_api: &mut Owner<L, C, K>,
+	keychain_mask: Option<&SecretKey>,
+	args: SlateVerifyArgs,
+) -> Result<(), Error>
+where
+	L: WalletLCProvider<'static, C, K> + 'static,
+	C: NodeClient + 'static,
+	K: keychain::Keychain + 'static,
+{
+	let slate = PathToSlate((&args.input).into()).get_tx()?;
+
+	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
+		let report = api.verify_slate(m, &slate)?;
+		if args.json {
+			println!(
+				"{}",
+				json::to_string_pretty(&report).map_err(|e| {
+					ErrorKind::GenericError(format!("Unable to serialize the report, {}", e))
+				})?
+			);
+		} else {
+			println!("Slate {}", report.slate_id);
+			for (commit, unspent) in &report.inputs {
+				println!(
+					"    Input {} {}",
+					commit,
+					if *unspent { "unspent" } else { "NOT FOUND" }
+				);
+			}
+			for problem in &report.problems {
+				println!("    Problem: {}", problem);
+			}
+			let status = if report.is_valid() {
+				"PASSED"
+			} else {
+				"FAILED"
+			};
+			println!("Verification {}", status);
+		}
+		if !report.is_valid() {
+			return Err(ErrorKind::GenericError(format!(
+				"Slate {} verification failed",
+				report.slate_id
+			))
+			.into());
+		}
+		Ok(())
+	})?;
+	Ok(())
+}
+
 pub fn dump_wallet_data<L, C, K>(
 	owner_api: &mut Owner<L, C, K>,
 	keychain_mask: Option<&SecretKey>,
@@ -1293,6 +1570,7 @@ pub fn swap_create_from_offer<L, C, K>(
 	owner_api: &mut Owner<L, C, K>,
 	keychain_mask: Option<&SecretKey>,
 	file: String,
+	accept_args: &grin_wallet_libwallet::api_impl::types::SwapAcceptArgs,
 ) -> Result<(), Error>
 where
 	L: WalletLCProvider<'static, C, K> + 'static,
@@ -1300,7 +1578,7 @@ where
 	K: keychain::Keychain + 'static,
 {
 	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, _m| {
-		let result = api.swap_create_from_offer(keychain_mask, file.clone());
+		let result = api.swap_create_from_offer(keychain_mask, file.clone(), accept_args);
 		match result {
 			Ok(swap_id) => {
 				warn!("Buyer Swap trade is created: {}", swap_id);
@@ -1330,9 +1608,15 @@ pub enum SwapSubcommand {
 	Autoswap,
 	Adjust,
 	Dump,
+	MessageLog,
+	SimulateRefund,
+	Certificate,
+	VerifyAll,
 	TradeExport,
 	TradeImport,
 	StopAllAutoSwap,
+	Rebroadcast,
+	SetAlias,
 }
 
 /// Arguments for the swap command
@@ -1351,6 +1635,8 @@ pub struct SwapArgs {
 	pub apisecret: Option<String>,
 	/// Secondary currency fee. Satoshi per byte.
 	pub secondary_fee: Option<f32>,
+	/// MWC redeem transaction fee in nanocoins
+	pub redeem_fee: Option<u64>,
 	/// File name with message content, if message need to be processed with files
 	pub message_file_name: Option<String>,
 	/// Refund address for the buyer
@@ -1367,6 +1653,10 @@ pub struct SwapArgs {
 	pub electrum_node_uri2: Option<String>,
 	/// Need to wait for the first backup.
 	pub wait_for_backup1: bool,
+	/// Seconds between the first re-broadcast attempts
+	pub rebroadcast_interval: Option<u64>,
+	/// Short alias for the swap trade
+	pub alias: Option<String>,
 }
 
 // For Json we can't use int 64, we have to convert all of them to Strings
@@ -1396,6 +1686,7 @@ pub fn swap<L, C, K>(
 	tor_config: Option<TorConfig>,
 	tls_conf: Option<TLSConfig>,
 	args: SwapArgs,
+	swap_config: &SwapConfig,
 	cli_mode: bool,
 ) -> Result<(), Error>
 where
@@ -1407,6 +1698,14 @@ where
 		None => None,
 		Some(&m) => Some(m.to_owned()),
 	};
+	// Trade can be referenced by the swap id, its unique prefix or the alias
+	let swap_id = match &args.swap_id {
+		Some(id) => Some(
+			trades::resolve_swap_id(id).map_err(|e| ErrorKind::ArgumentError(format!("{}", e)))?,
+		),
+		None => None,
+	};
+	let args = SwapArgs { swap_id, ..args };
 	match args.subcommand {
 		SwapSubcommand::List | SwapSubcommand::ListAndCheck => {
 			let result = owner_swap::swap_list(
@@ -1530,6 +1829,7 @@ where
 				args.destination.clone(),
 				secondary_address,
 				args.secondary_fee,
+				args.redeem_fee,
 				args.electrum_node_uri1,
 				args.electrum_node_uri2,
 			);
@@ -1577,6 +1877,9 @@ where
 									})
 									.collect();
 
+								let redeem_script = swap
+									.get_secondary_redeem_script()
+									.unwrap_or(("".to_string(), "".to_string()));
 								let item = json::json!({
 									"swapId" : swap.id.to_string(),
 									"isSeller" : swap.is_seller(),
@@ -1584,6 +1887,9 @@ where
 									"secondaryCurrency" : swap.secondary_currency.to_string(),
 									"secondaryAmount" : swap.secondary_currency.amount_to_hr_string(swap.secondary_amount, true),
 									"secondaryAddress" : swap.get_secondary_address(),
+									"secondaryLockAddress" : swap.get_secondary_lock_address().unwrap_or("".to_string()),
+									"secondaryRedeemScript" : redeem_script.0,
+									"secondaryRedeemScriptAsm" : redeem_script.1,
 									"secondaryFee" : swap.secondary_fee.to_string(),
 									"secondaryFeeUnits" : swap.secondary_currency.get_fee_units(),
 									"mwcConfirmations" : swap.mwc_confirmations,
@@ -1622,6 +1928,19 @@ where
 							args.wait_for_backup1,
 						)?;
 
+					let safety = owner_swap::swap_safety_status(
+						wallet_inst.clone(),
+						keychain_mask,
+						&swap_id,
+						swap_config,
+					)?;
+					let stalled = owner_swap::swap_is_stalled(
+						wallet_inst.clone(),
+						keychain_mask,
+						&swap_id,
+						swap_config,
+					)?;
+
 					let mwc_lock_time = if conf_status.mwc_tip < swap.refund_slate.lock_height {
 						Utc::now().timestamp() as u64
 							+ (swap.refund_slate.lock_height - conf_status.mwc_tip) * 60
@@ -1648,6 +1967,9 @@ where
 						.collect();
 
 					if args.json_format {
+						let redeem_script = swap
+							.get_secondary_redeem_script()
+							.unwrap_or(("".to_string(), "".to_string()));
 						let item = json::json!({
 							"swapId" : swap.id.to_string(),
 							"isSeller" : swap.is_seller(),
@@ -1655,6 +1977,9 @@ where
 							"secondaryCurrency" : swap.secondary_currency.to_string(),
 							"secondaryAmount" : swap.secondary_currency.amount_to_hr_string(swap.secondary_amount, true),
 							"secondaryAddress" : swap.get_secondary_address(),
+							"secondaryLockAddress" : swap.get_secondary_lock_address().unwrap_or("".to_string()),
+							"secondaryRedeemScript" : redeem_script.0,
+							"secondaryRedeemScriptAsm" : redeem_script.1,
 							"secondaryFee" : swap.secondary_fee.to_string(),
 							"secondaryFeeUnits" : swap.secondary_currency.get_fee_units(),
 							"mwcConfirmations" : swap.mwc_confirmations,
@@ -1670,6 +1995,9 @@ where
 
 							"last_process_error" : last_error,
 							"currentAction": action.to_string(),
+							"safetyStatus" : safety,
+							"safetyStatusMessage" : safety.to_string(),
+							"stalled" : stalled,
 							"roadmap" : road_map_to_print,
 							"journal_records" : journal_records_to_print,
 
@@ -1686,6 +2014,8 @@ where
 							&conf_status,
 							&roadmap,
 							&journal_records,
+							&safety,
+							stalled,
 							true,
 						)?;
 					}
@@ -1714,6 +2044,7 @@ where
 			let apisecret = args.apisecret.clone();
 			let swap_id2 = swap_id.clone();
 			let wallet_inst2 = wallet_inst.clone();
+			let listener_swap_config = swap_config.clone();
 			let message_sender = move |swap_message: message::Message,
 			                           method: String,
 			                           dest: String|
@@ -1732,6 +2063,7 @@ where
 								false,
 								Arc::new(Mutex::new(km)),
 								true,
+								listener_swap_config,
 							)
 							.map_err(|e| {
 								crate::libwallet::ErrorKind::SwapError(format!(
@@ -1771,6 +2103,7 @@ where
 										&api_listen_addr,
 										tls_conf,
 										tor_config.use_tor_listener,
+										listener_swap_config,
 									);
 									if let Err(e) = res {
 										error!("Error starting http listener: {}", e);
@@ -1855,6 +2188,7 @@ where
 				args.electrum_node_uri1,
 				args.electrum_node_uri2,
 				args.wait_for_backup1,
+				swap_config,
 			);
 
 			match result {
@@ -1905,6 +2239,7 @@ where
 							false,
 							Arc::new(Mutex::new(km)),
 							true,
+							swap_config.clone(),
 						)
 						.map_err(|e| {
 							ErrorKind::LibWallet(format!("Unable to start mwcmqs listener, {}", e))
@@ -1921,6 +2256,7 @@ where
 						let tor_config = tor_config.clone().ok_or(ErrorKind::GenericError(
 							"Tor configuration is not defined".to_string(),
 						))?;
+						let swap_config = swap_config.clone();
 						let _api_thread = thread::Builder::new()
 							.name("wallet-http-listener".to_string())
 							.spawn(move || {
@@ -1930,6 +2266,7 @@ where
 									&api_listen_addr,
 									tls_conf,
 									tor_config.use_tor_listener,
+									swap_config,
 								);
 								if let Err(e) = res {
 									error!("Error starting http listener: {}", e);
@@ -2079,6 +2416,18 @@ where
 				}
 
 				if !args.json_format {
+					let safety = owner_swap::swap_safety_status(
+						wallet_inst2.clone(),
+						keychain_mask,
+						&swap_id,
+						swap_config,
+					)?;
+					let stalled = owner_swap::swap_is_stalled(
+						wallet_inst2.clone(),
+						keychain_mask,
+						&swap_id,
+						swap_config,
+					)?;
 					display::swap_trade(
 						&swap,
 						&action,
@@ -2086,6 +2435,8 @@ where
 						&conf_status,
 						&roadmap,
 						&journal_records,
+						&safety,
+						stalled,
 						true,
 					)?;
 				}
@@ -2112,6 +2463,7 @@ where
 			let stop_thread_clone = SWAP_THREADS_RUN.clone();
 			let json_format_clone = args.json_format.clone();
 			let wait_for_backup1 = args.wait_for_backup1;
+			let swap_config2 = swap_config.clone();
 
 			debug!("Starting autoswap thread for swap id {}", swap_id);
 			let api_thread = thread::Builder::new()
@@ -2155,6 +2507,7 @@ where
 								secondary_address.clone(),
 								None, None, // URIs was already updated before. No need to update the same.
 								wait_for_backup1,
+								&swap_config2,
 							) {
 								Ok(res) => {
 									curr_state = res.next_state_id;
@@ -2304,6 +2657,84 @@ where
 			}
 			Ok(())
 		}
+		SwapSubcommand::Rebroadcast => {
+			let swap_id = args.swap_id.ok_or(ErrorKind::ArgumentError(
+				"Not found expected 'swap_id' argument".to_string(),
+			))?;
+			let interval = args
+				.rebroadcast_interval
+				.map(|i| i as i64)
+				.unwrap_or(DEFAULT_REBROADCAST_INTERVAL);
+
+			let wallet_inst2 = wallet_inst.clone();
+			let km2 = km.clone();
+			let stop_thread_clone = SWAP_THREADS_RUN.clone();
+			SWAP_THREADS_RUN.swap(false, Ordering::Relaxed);
+
+			let api_thread = thread::Builder::new()
+				.name("wallet-swap-rebroadcast".to_string())
+				.spawn(move || {
+					let mut schedule = RebroadcastSchedule::new(interval, Utc::now().timestamp());
+					loop {
+						if stop_thread_clone.load(Ordering::Relaxed) {
+							println!("Re-broadcast for trade {} is stopped", swap_id);
+							break;
+						}
+						let now = Utc::now().timestamp();
+						if schedule.is_due(now) {
+							match owner_swap::swap_rebroadcast(
+								wallet_inst2.clone(),
+								km2.as_ref(),
+								&swap_id,
+							) {
+								Ok(RebroadcastStatus::Pending(tx)) => {
+									println!("Swap Trade {}: {} is re-broadcasted", swap_id, tx);
+								}
+								Ok(RebroadcastStatus::Confirmed(tx)) => {
+									println!("Swap Trade {}: {} is confirmed", swap_id, tx);
+									break;
+								}
+								Ok(RebroadcastStatus::Conflict(tx)) => {
+									println!(
+										"Swap Trade {}: conflicting transaction for {} is found on the chain, re-broadcast is stopped",
+										swap_id, tx
+									);
+									break;
+								}
+								Ok(RebroadcastStatus::NotPending) => {
+									println!(
+										"Swap Trade {}: there is no published transaction to re-broadcast",
+										swap_id
+									);
+									break;
+								}
+								Err(e) => error!("Unable to re-broadcast Swap {}: {}", swap_id, e),
+							}
+							schedule.attempted(now);
+						}
+						thread::sleep(Duration::from_millis(1000));
+					}
+				});
+
+			if let Ok(t) = api_thread {
+				if !cli_mode {
+					let _ = t.join();
+				}
+			}
+			Ok(())
+		}
+		SwapSubcommand::SetAlias => {
+			let swap_id = args.swap_id.ok_or(ErrorKind::ArgumentError(
+				"Not found expected 'swap_id' argument".to_string(),
+			))?;
+			let alias = args.alias.ok_or(ErrorKind::ArgumentError(
+				"Not found expected 'alias' argument".to_string(),
+			))?;
+			trades::set_swap_alias(&swap_id, &alias)
+				.map_err(|e| ErrorKind::LibWallet(format!("Unable to set swap alias, {}", e)))?;
+			println!("Swap trade {} can be referenced as {}", swap_id, alias);
+			Ok(())
+		}
 		SwapSubcommand::Dump => {
 			let swap_id = args.swap_id.ok_or(ErrorKind::ArgumentError(
 				"Not found expected 'swap_id' argument".to_string(),
@@ -2327,6 +2758,109 @@ where
 				}
 			}
 		}
+		SwapSubcommand::MessageLog => {
+			let swap_id = args.swap_id.ok_or(ErrorKind::ArgumentError(
+				"Not found expected 'swap_id' argument".to_string(),
+			))?;
+			let log = owner_swap::swap_message_log(wallet_inst, keychain_mask, &swap_id).map_err(
+				|e| {
+					ErrorKind::LibWallet(format!(
+						"Unable to read the message log of swap {}, {}",
+						swap_id, e
+					))
+				},
+			)?;
+			if args.json_format {
+				let log = serde_json::to_string(&log).map_err(|e| {
+					ErrorKind::GenericError(format!("Unable to serialize the message log, {}", e))
+				})?;
+				println!("JSON: {}", log);
+			} else {
+				display::swap_message_log(&log);
+			}
+			Ok(())
+		}
+		SwapSubcommand::Certificate => {
+			let swap_id = args.swap_id.ok_or(ErrorKind::ArgumentError(
+				"Not found expected 'swap_id' argument".to_string(),
+			))?;
+			let certificate =
+				owner_swap::swap_completion_certificate(wallet_inst, keychain_mask, &swap_id)
+					.map_err(|e| {
+						ErrorKind::LibWallet(format!(
+							"Unable to build the completion certificate of swap {}, {}",
+							swap_id, e
+						))
+					})?;
+			// The certificate is shared with others as it is
+			let certificate = serde_json::to_string_pretty(&certificate).map_err(|e| {
+				ErrorKind::GenericError(format!(
+					"Unable to serialize the completion certificate, {}",
+					e
+				))
+			})?;
+			if args.json_format {
+				println!("JSON: {}", certificate);
+			} else {
+				println!("{}", certificate);
+			}
+			Ok(())
+		}
+		SwapSubcommand::SimulateRefund => {
+			// The offer from the file is evaluated before accepting, otherwise the stored trade
+			let simulation = match (&args.message_file_name, &args.swap_id) {
+				(Some(message_file_name), _) => owner_swap::swap_simulate_offer_refund(
+					message_file_name.clone(),
+					args.buyer_refund_address.clone(),
+					args.secondary_fee,
+				),
+				(None, Some(swap_id)) => {
+					owner_swap::swap_simulate_refund(wallet_inst, keychain_mask, swap_id)
+				}
+				(None, None) => {
+					return Err(ErrorKind::ArgumentError(
+						"Not found expected 'swap_id' or 'message_file_name' argument".to_string(),
+					)
+					.into())
+				}
+			}
+			.map_err(|e| {
+				ErrorKind::LibWallet(format!("Unable to simulate the swap refund, {}", e))
+			})?;
+			if args.json_format {
+				let simulation = serde_json::to_string(&simulation).map_err(|e| {
+					ErrorKind::GenericError(format!(
+						"Unable to serialize the refund simulation, {}",
+						e
+					))
+				})?;
+				println!("JSON: {}", simulation);
+			} else {
+				display::swap_refund_simulation(&simulation);
+			}
+			Ok(())
+		}
+		SwapSubcommand::VerifyAll => {
+			let result = owner_swap::swap_verify_all(wallet_inst, keychain_mask);
+			match result {
+				Ok(problems) => {
+					if problems.is_empty() {
+						println!("All stored swap trades are consistent");
+					}
+					for (swap_id, reasons) in &problems {
+						println!("Swap trade {} is inconsistent:", swap_id);
+						for r in reasons {
+							println!("    {}", r);
+						}
+					}
+					Ok(())
+				}
+				Err(e) => {
+					error!("Unable to verify swap trades: {}", e);
+					Err(ErrorKind::LibWallet(format!("Unable to verify swap trades: {}", e)).into())
+				}
+			}
+		}
 		SwapSubcommand::TradeExport => {
 			let swap_id = args.swap_id.ok_or(ErrorKind::ArgumentError(
 				"Not found expected 'swap_id' argument".to_string(),
@@ -16,7 +16,7 @@
 //! invocations) as needed.
 use crate::api::{self, ApiServer, BasicAuthMiddleware, ResponseFuture, Router, TLSConfig};
 use crate::libwallet::{
-	NodeClient, NodeVersionInfo, Slate, WalletInst, WalletLCProvider,
+	NodeClient, NodeVersionInfo, Slate, VersionedSlate, WalletInst, WalletLCProvider,
 	GRIN_BLOCK_HEADER_VERSION,
 };
 use crate::util::secp::key::SecretKey;
@@ -24,7 +24,7 @@ use crate::util::{from_hex, static_secp_instance, to_base64, Mutex};
 use crate::{Error, ErrorKind};
 use grin_wallet_api::JsonId;
 use grin_wallet_util::OnionV3Address;
-use hyper::body;
+use hyper::body::{self, HttpBody};
 use hyper::header::HeaderValue;
 use hyper::{Body, Request, Response, StatusCode};
 use serde::{Deserialize, Serialize};
@@ -33,6 +33,7 @@ use serde_json;
 use grin_wallet_impls::{Address, CloseReason, MWCMQPublisher, MWCMQSAddress, MWCMQSubscriber, Publisher,
 						Subscriber, SubscriptionHandler};
 use grin_wallet_libwallet::wallet_lock;
+use grin_wallet_libwallet::swap::config::SwapConfig;
 use grin_wallet_libwallet::swap::message::Message;
 use grin_wallet_util::grin_core::core;
 
@@ -273,6 +274,8 @@ pub struct Controller<L, C, K>
 	keychain_mask: Arc<Mutex<Option<SecretKey>>>,
 	// what to do with logs. Print them to console or into the logs
 	print_to_log: bool,
+	// swap engine parameters for the incoming swap messages
+	swap_config: SwapConfig,
 }
 
 impl<L, C, K> Controller<L, C, K>
@@ -287,6 +290,7 @@ impl<L, C, K> Controller<L, C, K>
 		keychain_mask: Arc<Mutex<Option<SecretKey>>>,
 		max_auto_accept_invoice: Option<u64>,
 		print_to_log: bool,
+		swap_config: SwapConfig,
 	) -> Self
 		where
 			L: WalletLCProvider<'static, C, K>,
@@ -305,6 +309,7 @@ impl<L, C, K> Controller<L, C, K>
 			slate_send_channel: Arc::new(Mutex::new(HashMap::new())),
 			keychain_mask,
 			print_to_log,
+			swap_config,
 		}
 	}
 
@@ -317,6 +322,7 @@ impl<L, C, K> Controller<L, C, K>
 			slate_send_channel: self.slate_send_channel.clone(),
 			keychain_mask: self.keychain_mask.clone(),
 			print_to_log: self.print_to_log,
+			swap_config: self.swap_config.clone(),
 		}
 	}
 
@@ -443,9 +449,11 @@ impl<L, C, K> Controller<L, C, K>
 
 	fn process_incoming_swap_message(
 		&self,
+		from: &dyn Address,
 		swapmessage: Message
 	) -> Result<Option<Message>, Error> {
 		let owner_api = Owner::new(self.wallet.clone(), None, None);
+		owner_api.set_swap_config(self.swap_config.clone());
 		let mask = self.keychain_mask.lock().clone();
 
 		let msg_str = serde_json::to_string(&swapmessage).map_err(|e| {
@@ -453,7 +461,12 @@ impl<L, C, K> Controller<L, C, K>
 				"Error in processing incoming swap message from mqs, {}", e
 			))
 		})?;
-		let ack_msg = owner_api.swap_income_message((&mask).as_ref(), msg_str)?;
+		// MQS message signature is verified with the sender address, so the sender is known
+		let ack_msg = owner_api.swap_income_message(
+			(&mask).as_ref(),
+			msg_str,
+			Some(from.get_full_name()),
+		)?;
 
 		Ok(ack_msg)
 	}
@@ -533,8 +546,8 @@ impl<L, C, K> SubscriptionHandler for Controller<L, C, K>
 		}
 	}
 
-	fn on_swap_message(&self, swap: Message) -> Option<Message> {
-		let result = self.process_incoming_swap_message(swap);
+	fn on_swap_message(&self, from: &dyn Address, swap: Message) -> Option<Message> {
+		let result = self.process_incoming_swap_message(from, swap);
 
 		match result {
 			Ok(message) => return message,
@@ -581,6 +594,7 @@ pub fn init_start_mwcmqs_listener<L, C, K>(
 	mqs_config: MQSConfig,
 	keychain_mask: Arc<Mutex<Option<SecretKey>>>,
 	wait_for_thread: bool,
+	swap_config: SwapConfig,
 ) -> Result<(MWCMQPublisher, MWCMQSubscriber), Error>
 	where
 		L: WalletLCProvider<'static, C, K> + 'static,
@@ -596,6 +610,7 @@ pub fn init_start_mwcmqs_listener<L, C, K>(
 		wait_for_thread,
 		keychain_mask,
 		true,
+		swap_config,
 	)
 		.map_err(|e| ErrorKind::GenericError(format!("cannot start mqs listener, {}", e)).into())
 }
@@ -607,6 +622,7 @@ pub fn start_mwcmqs_listener<L, C, K>(
 	wait_for_thread: bool,
 	keychain_mask: Arc<Mutex<Option<SecretKey>>>,
 	print_to_log: bool,
+	swap_config: SwapConfig,
 ) -> Result<(MWCMQPublisher, MWCMQSubscriber), Error>
 	where
 		L: WalletLCProvider<'static, C, K> + 'static,
@@ -646,6 +662,7 @@ pub fn start_mwcmqs_listener<L, C, K>(
 		keychain_mask,
 		None,
 		print_to_log,
+		swap_config,
 	);
 
 	let mwcmqs_publisher = MWCMQPublisher::new(
@@ -697,6 +714,7 @@ pub fn owner_listener<L, C, K>(
 	tls_config: Option<TLSConfig>,
 	owner_api_include_foreign: Option<bool>,
 	tor_config: Option<TorConfig>,
+	swap_config: SwapConfig,
 ) -> Result<(), Error>
 	where
 		L: WalletLCProvider<'static, C, K> + 'static,
@@ -729,12 +747,14 @@ pub fn owner_listener<L, C, K>(
 		router.add_middleware(basic_auth_middleware);
 	}
 
-	let api_handler_v2 = OwnerAPIHandlerV2::new(wallet.clone(), tor_config.clone());
+	let api_handler_v2 =
+		OwnerAPIHandlerV2::new(wallet.clone(), tor_config.clone(), swap_config.clone());
 	let api_handler_v3 = OwnerAPIHandlerV3::new(
 		wallet.clone(),
 		keychain_mask.clone(),
 		tor_config,
 		running_foreign,
+		swap_config.clone(),
 	);
 
 	router
@@ -752,7 +772,7 @@ pub fn owner_listener<L, C, K>(
 	// If so configured, add the foreign API to the same port
 	if running_foreign {
 		warn!("Starting HTTP Foreign API on Owner server at {}.", addr);
-		let foreign_api_handler_v2 = ForeignAPIHandlerV2::new(wallet, keychain_mask);
+		let foreign_api_handler_v2 = ForeignAPIHandlerV2::new(wallet, keychain_mask, swap_config);
 		router
 			.add_route("/v2/foreign", Arc::new(foreign_api_handler_v2))
 			.map_err(|e| {
@@ -793,6 +813,7 @@ pub fn foreign_listener<L, C, K>(
 	addr: &str,
 	tls_config: Option<TLSConfig>,
 	use_tor: bool,
+	swap_config: SwapConfig,
 ) -> Result<(), Error>
 	where
 		L: WalletLCProvider<'static, C, K> + 'static,
@@ -823,7 +844,7 @@ pub fn foreign_listener<L, C, K>(
 		false => None,
 	};
 
-	let api_handler_v2 = ForeignAPIHandlerV2::new(wallet, keychain_mask);
+	let api_handler_v2 = ForeignAPIHandlerV2::new(wallet, keychain_mask, swap_config);
 	let mut router = Router::new();
 
 	router
@@ -856,6 +877,64 @@ pub fn foreign_listener<L, C, K>(
 	res
 }
 
+/// Max size of the slate that slate-serve listener accepts
+pub const SLATE_SERVE_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Minimal slate exchange listener for the local processes. Slates POSTed to the
+/// listener are processed by the receive flow, the response slate is returned in the body.
+/// Only loopback addresses are accepted unless 'allow_remote' is set.
+pub fn slate_serve_listener<L, C, K>(
+	wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
+	keychain_mask: Arc<Mutex<Option<SecretKey>>>,
+	addr: &str,
+	allow_remote: bool,
+	max_body_size: usize,
+) -> Result<(), Error>
+	where
+		L: WalletLCProvider<'static, C, K> + 'static,
+		C: NodeClient + 'static,
+		K: Keychain + 'static,
+{
+	let socket_addr: SocketAddr = addr.parse().map_err(|e| {
+		ErrorKind::ArgumentError(format!("Invalid listen address {}, {}", addr, e))
+	})?;
+	if !allow_remote && !socket_addr.ip().is_loopback() {
+		return Err(ErrorKind::ArgumentError(format!(
+			"Slate serve listener can bind only to the localhost, {} is not a loopback address",
+			addr
+		))
+		.into());
+	}
+
+	// Check if wallet has been opened first
+	{
+		let mut w_lock = wallet.lock();
+		let lc = w_lock.lc_provider()?;
+		let _ = lc.wallet_inst()?;
+	}
+
+	let api_handler = SlateServeHandler::new(wallet, keychain_mask, max_body_size);
+	let mut router = Router::new();
+
+	router
+		.add_route("/v1/slate", Arc::new(api_handler))
+		.map_err(|e| {
+			ErrorKind::GenericError(format!("Router failed to add route /v1/slate, {}", e))
+		})?;
+
+	let mut apis = ApiServer::new();
+	warn!("Starting HTTP slate serve listener at {}.", addr);
+	let api_thread = apis
+		.start(socket_addr, router, None)
+		.map_err(|e| ErrorKind::GenericError(format!("API thread failed to start, {}", e)))?;
+
+	warn!("HTTP slate serve listener started.");
+
+	api_thread
+		.join()
+		.map_err(|e| ErrorKind::GenericError(format!("API thread panicked :{:?}", e)).into())
+}
+
 /// V2 API Handler/Wrapper for owner functions
 pub struct OwnerAPIHandlerV2<L, C, K>
 	where
@@ -866,6 +945,8 @@ pub struct OwnerAPIHandlerV2<L, C, K>
 	/// Wallet instance
 	pub wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
 	pub tor_config: Option<TorConfig>,
+	/// Swap engine parameters
+	pub swap_config: SwapConfig,
 }
 
 impl<L, C, K> OwnerAPIHandlerV2<L, C, K>
@@ -878,8 +959,13 @@ impl<L, C, K> OwnerAPIHandlerV2<L, C, K>
 	pub fn new(
 		wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
 		tor_config: Option<TorConfig>,
+		swap_config: SwapConfig,
 	) -> OwnerAPIHandlerV2<L, C, K> {
-		OwnerAPIHandlerV2 { wallet, tor_config }
+		OwnerAPIHandlerV2 {
+			wallet,
+			tor_config,
+			swap_config,
+		}
 	}
 
 	async fn call_api(req: Request<Body>, api: Owner<L, C, K>) -> Result<serde_json::Value, Error> {
@@ -898,8 +984,10 @@ async fn handle_post_request(
 	req: Request<Body>,
 	wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
 	tor_config: Option<TorConfig>,
+	swap_config: SwapConfig,
 ) -> Result<Response<Body>, Error> {
 	let api = Owner::new(wallet, None, tor_config);
+	api.set_swap_config(swap_config);
 
 	//Here is a wrapper to call future from that.
 	// Issue that we can't call future form future
@@ -922,8 +1010,9 @@ impl<L, C, K> api::Handler for OwnerAPIHandlerV2<L, C, K>
 	fn post(&self, req: Request<Body>) -> ResponseFuture {
 		let wallet = self.wallet.clone();
 		let tor_config = self.tor_config.clone();
+		let swap_config = self.swap_config.clone();
 		Box::pin(async move {
-			match Self::handle_post_request(req, wallet, tor_config).await {
+			match Self::handle_post_request(req, wallet, tor_config, swap_config).await {
 				Ok(r) => Ok(r),
 				Err(e) => {
 					error!("Request Error: {:?}", e);
@@ -1198,9 +1287,11 @@ impl<L, C, K> OwnerAPIHandlerV3<L, C, K>
 		keychain_mask: Arc<Mutex<Option<SecretKey>>>,
 		tor_config: Option<TorConfig>,
 		running_foreign: bool,
+		swap_config: SwapConfig,
 	) -> OwnerAPIHandlerV3<L, C, K> {
 		let owner_api = Owner::new(wallet.clone(), None, tor_config.clone());
 		owner_api.set_tor_config(tor_config);
+		owner_api.set_swap_config(swap_config);
 		let owner_api = Arc::new(owner_api);
 		OwnerAPIHandlerV3 {
 			wallet,
@@ -1335,6 +1426,8 @@ pub struct ForeignAPIHandlerV2<L, C, K>
 	pub wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
 	/// Keychain mask
 	pub keychain_mask: Arc<Mutex<Option<SecretKey>>>,
+	/// Swap engine parameters
+	pub swap_config: SwapConfig,
 }
 
 impl<L, C, K> ForeignAPIHandlerV2<L, C, K>
@@ -1347,10 +1440,12 @@ impl<L, C, K> ForeignAPIHandlerV2<L, C, K>
 	pub fn new(
 		wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
 		keychain_mask: Arc<Mutex<Option<SecretKey>>>,
+		swap_config: SwapConfig,
 	) -> ForeignAPIHandlerV2<L, C, K> {
 		ForeignAPIHandlerV2 {
 			wallet,
 			keychain_mask,
+			swap_config,
 		}
 	}
 
@@ -1373,8 +1468,10 @@ async fn handle_post_request(
 	req: Request<Body>,
 	mask: Option<SecretKey>,
 	wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
+	swap_config: SwapConfig,
 ) -> Result<Response<Body>, Error> {
 	let api = Foreign::new(wallet, mask, Some(check_middleware));
+	api.set_swap_config(swap_config);
 
 	//Here is a wrapper to call future from that.
 	// Issue that we can't call future form future
@@ -1396,9 +1493,10 @@ impl<L, C, K> api::Handler for ForeignAPIHandlerV2<L, C, K>
 	fn post(&self, req: Request<Body>) -> ResponseFuture {
 		let mask = self.keychain_mask.lock().clone();
 		let wallet = self.wallet.clone();
+		let swap_config = self.swap_config.clone();
 
 		Box::pin(async move {
-			match Self::handle_post_request(req, mask, wallet).await {
+			match Self::handle_post_request(req, mask, wallet, swap_config).await {
 				Ok(v) => Ok(v),
 				Err(e) => {
 					error!("Request Error: {:?}", e);
@@ -1413,6 +1511,103 @@ impl<L, C, K> api::Handler for ForeignAPIHandlerV2<L, C, K>
 	}
 }
 
+/// Handler for the slate-serve listener. Accepts the slate json, returns the received slate
+/// in the same version
+pub struct SlateServeHandler<L, C, K>
+	where
+		L: WalletLCProvider<'static, C, K> + 'static,
+		C: NodeClient + 'static,
+		K: Keychain + 'static,
+{
+	/// Wallet instance
+	pub wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
+	/// Keychain mask
+	pub keychain_mask: Arc<Mutex<Option<SecretKey>>>,
+	/// Max size of the request body
+	pub max_body_size: usize,
+}
+
+impl<L, C, K> SlateServeHandler<L, C, K>
+	where
+		L: WalletLCProvider<'static, C, K> + 'static,
+		C: NodeClient + 'static,
+		K: Keychain + 'static,
+{
+	/// Create a new slate serve handler
+	pub fn new(
+		wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
+		keychain_mask: Arc<Mutex<Option<SecretKey>>>,
+		max_body_size: usize,
+	) -> SlateServeHandler<L, C, K> {
+		SlateServeHandler {
+			wallet,
+			keychain_mask,
+			max_body_size,
+		}
+	}
+
+	async fn handle_post_request(
+		req: Request<Body>,
+		mask: Option<SecretKey>,
+		wallet: Arc<Mutex<Box<dyn WalletInst<'static, L, C, K> + 'static>>>,
+		max_body_size: usize,
+	) -> Result<Response<Body>, Error> {
+		let slate_json = match read_body_limited(req, max_body_size).await? {
+			Some(s) => s,
+			None => {
+				return Ok(response(
+					StatusCode::PAYLOAD_TOO_LARGE,
+					format!("Slate size exceeds the limit of {} bytes", max_body_size),
+				))
+			}
+		};
+
+		let api = Foreign::new(wallet, mask, Some(check_middleware));
+
+		// Receive calls the node, so it is running outside of the hyper runtime
+		let handler = move || -> Pin<Box<dyn std::future::Future<Output=Result<serde_json::Value, Error>>>> {
+			Box::pin(async move {
+				let versioned = VersionedSlate::from_json(&slate_json)?;
+				let version = versioned.version();
+				let slate = Slate::from(versioned);
+				let slate = api.receive_tx(&slate, None, None, None)?;
+				let res = serde_json::to_value(VersionedSlate::into_version(slate, version))
+					.map_err(|e| ErrorKind::GenericError(format!("Unable to serialize the slate, {}", e)))?;
+				Ok(res)
+			})
+		};
+		let res = crate::executor::RunHandlerInThread::new(handler).await?;
+		Ok(json_response_pretty(&res))
+	}
+}
+
+impl<L, C, K> api::Handler for SlateServeHandler<L, C, K>
+	where
+		L: WalletLCProvider<'static, C, K> + 'static,
+		C: NodeClient + 'static,
+		K: Keychain + 'static,
+{
+	fn post(&self, req: Request<Body>) -> ResponseFuture {
+		let mask = self.keychain_mask.lock().clone();
+		let wallet = self.wallet.clone();
+		let max_body_size = self.max_body_size;
+
+		Box::pin(async move {
+			match Self::handle_post_request(req, mask, wallet, max_body_size).await {
+				Ok(v) => Ok(v),
+				Err(e) => {
+					error!("Slate serve request Error: {:?}", e);
+					Ok(create_error_response(e))
+				}
+			}
+		})
+	}
+
+	fn options(&self, _req: Request<Body>) -> ResponseFuture {
+		Box::pin(async { Ok(create_ok_response("{}")) })
+	}
+}
+
 // Utility to serialize a struct into JSON and produce a sensible Response
 // out of it.
 fn _json_response<T>(s: &T) -> Response<Body>
@@ -1498,3 +1693,29 @@ async fn parse_body<T>(req: Request<Body>) -> Result<T, Error>
 	serde_json::from_reader(&body[..])
 		.map_err(|e| ErrorKind::GenericError(format!("Invalid request body, {}", e)).into())
 }
+
+// Read the request body as a string. Returns None if the body is larger than max_size.
+async fn read_body_limited(req: Request<Body>, max_size: usize) -> Result<Option<String>, Error> {
+	if let Some(len) = req.headers().get(hyper::header::CONTENT_LENGTH) {
+		let len = len.to_str().ok().and_then(|l| l.parse::<usize>().ok());
+		if len.map(|l| l > max_size).unwrap_or(false) {
+			return Ok(None);
+		}
+	}
+
+	// Content length can be missing or wrong, so checking while reading
+	let mut body = req.into_body();
+	let mut data: Vec<u8> = Vec::new();
+	while let Some(chunk) = body.data().await {
+		let chunk = chunk
+			.map_err(|e| ErrorKind::GenericError(format!("Failed to read request, {}", e)))?;
+		if data.len() + chunk.len() > max_size {
+			return Ok(None);
+		}
+		data.extend_from_slice(&chunk);
+	}
+
+	let s = String::from_utf8(data)
+		.map_err(|e| ErrorKind::GenericError(format!("Invalid request body, {}", e)))?;
+	Ok(Some(s))
+}
@@ -15,8 +15,9 @@
 use crate::core::core::{self, amount_to_hr_string};
 use crate::core::global;
 use crate::libwallet::swap::fsm::state::StateEtaInfo;
+use crate::libwallet::swap::message::{MessageDirection, SwapMessageLog};
 use crate::libwallet::swap::swap;
-use crate::libwallet::swap::types::{Action, Role};
+use crate::libwallet::swap::types::{Action, Currency, RefundSimulation, Role, SafetyAssessment};
 use crate::libwallet::{
 	AcctPathMapping, Error, OutputCommitMapping, OutputStatus, TxLogEntry, WalletInfo,
 };
@@ -28,6 +29,7 @@ use colored::*;
 use grin_wallet_libwallet::swap::swap::SwapJournalRecord;
 use grin_wallet_libwallet::swap::types::SwapTransactionsConfirmations;
 use prettytable;
+use std::convert::TryFrom;
 
 /// Display outputs in a pretty way
 pub fn outputs(
@@ -647,6 +649,8 @@ pub fn swap_trade(
 	tx_conf: &SwapTransactionsConfirmations,
 	roadmap: &Vec<StateEtaInfo>,
 	journal_records: &Vec<SwapJournalRecord>,
+	safety: &SafetyAssessment,
+	stalled: bool,
 	show_requied_action: bool,
 ) -> Result<(), Error> {
 	println!("");
@@ -751,6 +755,13 @@ pub fn swap_trade(
 			}
 		},
 	}
+	if let Ok(lock_address) = swap.get_secondary_lock_address() {
+		println!(
+			"    {} lock address: {}",
+			swap.secondary_currency,
+			lock_address.bold().yellow()
+		);
+	}
 	println!(
 		"    Current {} transaction fee: {} {}",
 		swap.secondary_currency,
@@ -771,6 +782,22 @@ pub fn swap_trade(
 		);
 	}
 
+	let safety_str = format!("{}", safety);
+	let safety_str = match safety {
+		SafetyAssessment::Safe => safety_str.green(),
+		SafetyAssessment::CautionRefundWindowNear => safety_str.yellow(),
+		SafetyAssessment::RefundNow | SafetyAssessment::Conflict => safety_str.red(),
+	};
+	println!("    Safety status: {}", safety_str.bold());
+	if stalled {
+		println!(
+			"    {}",
+			"WARNING: The swap is waiting for the confirmations longer than expected. Please check if your node is synced and the transactions are not dropped."
+				.bold()
+				.red()
+		);
+	}
+
 	let expired_str = swap::left_from_time_limit(time_limit);
 	let action_str = if expired_str.is_empty() {
 		format!("{}", action)
@@ -819,6 +846,54 @@ pub fn swap_trade(
 	Ok(())
 }
 
+/// Display the messages that were exchanged for the swap trade
+pub fn swap_message_log(log: &SwapMessageLog) {
+	println!("");
+	println!("-------- Swap {} Messages --------", log.swap_id);
+	if log.records.is_empty() {
+		println!("    No messages were sent or received yet");
+	}
+	for r in &log.records {
+		let direction = match r.direction {
+			MessageDirection::Sent => "Sent",
+			MessageDirection::Received => "Received",
+		};
+		println!(
+			"    {:20} {:10} {}",
+			timestamp_to_local_time(r.time),
+			direction,
+			r.message_type
+		);
+	}
+	println!("");
+}
+
+/// Display what the party gets back if the swap is refunded
+pub fn swap_refund_simulation(simulation: &RefundSimulation) {
+	let amount = |a: u64| match Currency::try_from(simulation.currency.as_str()) {
+		Ok(currency) => currency.amount_to_hr_string(a, true),
+		Err(_) => amount_to_hr_string(a, true),
+	};
+	println!("");
+	println!("-------- Refund Simulation --------");
+	println!(
+		"    Amount returned: {} {}",
+		amount(simulation.amount_returned),
+		simulation.currency
+	);
+	println!(
+		"    Refund fee:      {} {}",
+		amount(simulation.fee),
+		simulation.currency
+	);
+	println!(
+		"    Net loss:        {} {}",
+		amount(simulation.net_loss),
+		simulation.currency
+	);
+	println!("");
+}
+
 fn timestamp_to_local_time(timestamp: i64) -> String {
 	let dt = Local.timestamp(timestamp, 0);
 	dt.format("%B %e %H:%M:%S").to_string()
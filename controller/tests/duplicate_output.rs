@@ -0,0 +1,126 @@
+// Copyright 2020 The MWC Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the receive of the slate with the output that the wallet already has
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, Slate};
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn duplicate_output_test_impl(test_dir: &'static str) -> Result<(), wallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let bh = 10u64;
+	let _ =
+		test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, bh as usize, false);
+
+	let args = InitTxArgs {
+		src_acct_name: None,
+		amount: 2_000_000_000,
+		minimum_confirmations: 2,
+		max_outputs: 500,
+		num_change_outputs: 1,
+		selection_strategy_is_use_all: false,
+		..Default::default()
+	};
+
+	// First send, wallet2 gets the output
+	let mut slate_init = Slate::blank(1);
+	let mut slate = Slate::blank(1);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
+		slate_init = sender_api.init_send_tx(m, args.clone(), 1)?;
+		Ok(())
+	})?;
+	wallet::controller::foreign_single_use(wallet2.clone(), mask2_i.clone(), |api| {
+		slate = api.receive_tx(&slate_init, None, None, None)?;
+		Ok(())
+	})?;
+	let received_output = slate
+		.tx
+		.outputs()
+		.iter()
+		.find(|o| !slate_init.tx.outputs().contains(o))
+		.unwrap()
+		.clone();
+
+	// Second send has the output that wallet2 already has
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
+		slate_init = sender_api.init_send_tx(m, args.clone(), 1)?;
+		Ok(())
+	})?;
+	slate_init.tx.body.outputs.push(received_output);
+	wallet::controller::foreign_single_use(wallet2.clone(), mask2_i.clone(), |api| {
+		match api.receive_tx(&slate_init, None, None, None) {
+			Err(e) => match e.kind() {
+				ErrorKind::DuplicateOutputCommitment(_) => (),
+				_ => panic!("Unexpected receive error: {}", e),
+			},
+			Ok(_) => panic!("Slate with duplicate output is received"),
+		}
+		Ok(())
+	})?;
+
+	// let logging finish
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn duplicate_output() {
+	let test_dir = "test_output/duplicate_output";
+	setup(test_dir);
+	if let Err(e) = duplicate_output_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -24,7 +24,7 @@ use impls::{PathToSlate, SlateGetter as _, SlatePutter as _};
 use std::thread;
 use std::time::Duration;
 
-use grin_wallet_libwallet::InitTxArgs;
+use grin_wallet_libwallet::{ErrorKind, InitTxArgs};
 
 use serde_json;
 
@@ -147,7 +147,21 @@ fn file_exchange_test_impl(test_dir: &'static str) -> Result<(), wallet::Error>
 
 	// wallet 2 receives file, completes, sends file back
 	wallet::controller::foreign_single_use(wallet2.clone(), mask2_i.clone(), |api| {
-		slate = api.receive_tx(&slate, None, None, Some(sender2_message.clone()))?;
+		// Recipient that expects another amount rejects the slate
+		let amount = slate.amount;
+		assert_eq!(
+			api.receive_tx_expected_amount(&slate, amount + 1, None, None, None)
+				.unwrap_err()
+				.kind(),
+			ErrorKind::ExpectedAmountMismatch(amount + 1, amount)
+		);
+		slate = api.receive_tx_expected_amount(
+			&slate,
+			amount,
+			None,
+			None,
+			Some(sender2_message.clone()),
+		)?;
 		PathToSlate((&receive_file).into()).put_tx(&slate)?;
 		Ok(())
 	})?;
@@ -0,0 +1,123 @@
+// Copyright 2020 The MWC Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the finalize of the slate that was returned without the requested payment proof
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, Slate};
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+use grin_wallet_libwallet::proof::proofaddress::ProvableAddress;
+
+fn missing_payment_proof_test_impl(test_dir: &'static str) -> Result<(), wallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask2 = (&mask2_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let bh = 10u64;
+	let _ =
+		test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, bh as usize, false);
+
+	let mut address = None;
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		address = Some(api.get_public_proof_address(m)?);
+		Ok(())
+	})?;
+	let address = ProvableAddress::from_pub_key(&address.unwrap());
+
+	libwallet::set_missing_payment_proof_error(true);
+	let mut slate = Slate::blank(1);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 2_000_000_000,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: true,
+			payment_proof_recipient_address: Some(address.clone()),
+			..Default::default()
+		};
+		let slate_i = sender_api.init_send_tx(m, args, 1)?;
+		assert!(slate_i.payment_proof.is_some());
+
+		// Recipient doesn't cooperate and returns the slate without the proof
+		slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
+		assert!(slate.payment_proof.is_some());
+		slate.payment_proof = None;
+		sender_api.tx_lock_outputs(m, &slate, None, 0)?;
+
+		// The tx log doesn't have the proof, but the proof was requested, so finalize fails
+		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id))?;
+		assert!(txs[0].payment_proof.is_none());
+		assert!(sender_api.finalize_tx(m, &slate).is_err());
+
+		// Without the policy such slate is finalized as before
+		libwallet::set_missing_payment_proof_error(false);
+		slate = sender_api.finalize_tx(m, &slate)?;
+		sender_api.post_tx(m, &slate.tx, true)?;
+		Ok(())
+	})?;
+
+	// let logging finish
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn missing_payment_proof() {
+	let test_dir = "test_output/missing_payment_proof";
+	setup(test_dir);
+	if let Err(e) = missing_payment_proof_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
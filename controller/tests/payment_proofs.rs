@@ -165,6 +165,35 @@ fn payment_proofs_test_impl(test_dir: &'static str) -> Result<(), wallet::Error>
 		Ok(())
 	})?;
 
+	// Slate bound to the recipient. The responded slate is checked against the sender context
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: amount,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: true,
+			payment_proof_recipient_address: Some(address.clone()),
+			bind_to_recipient: Some(true),
+			..Default::default()
+		};
+		let slate_i = sender_api.init_send_tx(m, args, 1)?;
+		assert!(slate_i.recipient_binding.is_some());
+
+		let slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
+		sender_api.tx_lock_outputs(m, &slate, None, 0)?;
+
+		// Responder dropped the binding, finalize must fail
+		let mut stripped = slate.clone();
+		stripped.recipient_binding = None;
+		assert!(sender_api.finalize_tx(m, &stripped).is_err());
+
+		let slate = sender_api.finalize_tx(m, &slate)?;
+		sender_api.post_tx(m, &slate.tx, true)?;
+		Ok(())
+	})?;
+
 	// let logging finish
 	thread::sleep(Duration::from_millis(200));
 	Ok(())
@@ -0,0 +1,146 @@
+// Copyright 2020 The MWC Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test re-export of the sent slate at another version
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use impls::{PathToSlate, SlateGetter as _};
+use libwallet::{InitTxArgs, Slate, SlateVersion};
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn reexport_slate_test_impl(test_dir: &'static str) -> Result<(), wallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let reward = core::consensus::MWC_FIRST_GROUP_REWARD;
+	let bh = 10u64;
+	let _ =
+		test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, bh as usize, false);
+
+	let v2_file = format!("{}/reexport_v2.tx", test_dir);
+	let v3_file = format!("{}/reexport_v3.tx", test_dir);
+
+	let mut slate = Slate::blank(1);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: reward * 2,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: true,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args, 1)?;
+		api.tx_lock_outputs(m, &slate, None, 0)?;
+
+		// The sent slate is tracked, it can be written at any version
+		let stored = api.get_stored_slate(m, &slate.id)?;
+		assert_eq!(stored.id, slate.id);
+		assert_eq!(stored.tx, slate.tx);
+		PathToSlate((&v2_file).into()).put_tx_version(&stored, SlateVersion::V2)?;
+		PathToSlate((&v3_file).into()).put_tx_version(&stored, SlateVersion::V3)?;
+
+		assert!(api.get_stored_slate(m, &Uuid::new_v4()).is_err());
+		Ok(())
+	})?;
+
+	let v2 = PathToSlate((&v2_file).into()).get_versioned_tx()?;
+	assert_eq!(v2.version(), SlateVersion::V2);
+	let v3 = PathToSlate((&v3_file).into()).get_versioned_tx()?;
+	assert_eq!(v3.version(), SlateVersion::V3);
+
+	// Transaction and the sender data are the same, so the re-exported slate can be received
+	// and finalized as the original one
+	let v2_slate = PathToSlate((&v2_file).into()).get_tx()?;
+	assert_eq!(v2_slate.id, slate.id);
+	assert_eq!(v2_slate.amount, slate.amount);
+	assert_eq!(v2_slate.fee, slate.fee);
+	assert_eq!(v2_slate.tx, slate.tx);
+	assert_eq!(v2_slate.participant_data.len(), 1);
+	assert_eq!(
+		v2_slate.participant_data[0].public_nonce,
+		slate.participant_data[0].public_nonce
+	);
+	assert_eq!(
+		v2_slate.participant_data[0].public_blind_excess,
+		slate.participant_data[0].public_blind_excess
+	);
+
+	let mut response = Slate::blank(2);
+	wallet::controller::foreign_single_use(wallet2.clone(), mask2_i.clone(), |api| {
+		response = api.receive_tx(&v2_slate, None, None, None)?;
+		Ok(())
+	})?;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let slate = api.finalize_tx(m, &response)?;
+		api.post_tx(m, &slate.tx, false)?;
+		Ok(())
+	})?;
+
+	// let logging finish
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn reexport_slate() {
+	let test_dir = "test_output/reexport_slate";
+	setup(test_dir);
+	if let Err(e) = reexport_slate_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
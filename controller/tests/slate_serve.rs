@@ -0,0 +1,185 @@
+// Copyright 2020 The MWC Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the slate exchange over the local HTTP listener
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+use grin_wallet_util::grin_util::Mutex;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, Slate, SlateVersion, VersionedSlate};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+// POST the body to the listener, returns the status code and the response body
+fn post_slate(addr: &str, body: &str) -> (u16, String) {
+	let mut stream = None;
+	for _ in 0..50 {
+		match TcpStream::connect(addr) {
+			Ok(s) => {
+				stream = Some(s);
+				break;
+			}
+			Err(_) => thread::sleep(Duration::from_millis(100)),
+		}
+	}
+	let mut stream = stream.expect("slate serve listener is not running");
+	let request = format!(
+		"POST /v1/slate HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+		addr,
+		body.len(),
+		body
+	);
+	stream.write_all(request.as_bytes()).unwrap();
+	let mut resp = String::new();
+	stream.read_to_string(&mut resp).unwrap();
+
+	let status: u16 = resp.split(' ').nth(1).unwrap().parse().unwrap();
+	let body = resp.splitn(2, "\r\n\r\n").nth(1).unwrap_or("").to_string();
+	(status, body)
+}
+
+fn slate_serve_test_impl(test_dir: &'static str) -> Result<(), wallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let reward = core::consensus::MWC_FIRST_GROUP_REWARD;
+	let bh = 10u64;
+	let _ =
+		test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, bh as usize, false);
+
+	// Only the localhost is allowed by default
+	assert!(wallet::controller::slate_serve_listener(
+		wallet2.clone(),
+		Arc::new(Mutex::new(mask2_i.clone())),
+		"0.0.0.0:33418",
+		false,
+		wallet::controller::SLATE_SERVE_MAX_BODY_SIZE,
+	)
+	.is_err());
+
+	let addr = "127.0.0.1:33417";
+	let serve_wallet = wallet2.clone();
+	let serve_mask = Arc::new(Mutex::new(mask2_i.clone()));
+	thread::spawn(move || {
+		if let Err(e) = wallet::controller::slate_serve_listener(
+			serve_wallet,
+			serve_mask,
+			addr,
+			false,
+			1024 * 64,
+		) {
+			error!("Slate serve listener error: {}", e);
+		}
+	});
+
+	let mut slate = Slate::blank(1);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: reward * 2,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: true,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args, 1)?;
+		api.tx_lock_outputs(m, &slate, None, 0)?;
+		Ok(())
+	})?;
+
+	// Send the slate to the listener, the response slate has to be signed by the receiver
+	let request = serde_json::to_string(&VersionedSlate::into_version(
+		slate.clone(),
+		SlateVersion::V3,
+	))
+	.unwrap();
+	let (status, body) = post_slate(addr, &request);
+	assert_eq!(status, 200, "Unexpected response: {}", body);
+	let response = VersionedSlate::from_json(&body)?;
+	assert_eq!(response.version(), SlateVersion::V3);
+	let response = Slate::from(response);
+	assert_eq!(response.id, slate.id);
+	assert_eq!(response.amount, slate.amount);
+	assert_eq!(response.participant_data.len(), 2);
+
+	// Sender is able to finalize it
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let slate = api.finalize_tx(m, &response)?;
+		api.post_tx(m, &slate.tx, false)?;
+		Ok(())
+	})?;
+
+	// Garbage is rejected, the oversized body is not even parsed
+	let (status, _) = post_slate(addr, "{\"not\": \"a slate\"}");
+	assert_eq!(status, 500);
+	let big_body = format!("{{\"data\": \"{}\"}}", "0".repeat(1024 * 65));
+	let (status, _) = post_slate(addr, &big_body);
+	assert_eq!(status, 413);
+
+	// let logging finish
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn slate_serve() {
+	let test_dir = "test_output/slate_serve";
+	setup(test_dir);
+	if let Err(e) = slate_serve_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
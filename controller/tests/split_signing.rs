@@ -0,0 +1,205 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! tests the slate export after round 1 and signing on another wallet with the same seed
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+use grin_wallet_util::grin_util as util;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, IssueInvoiceTxArgs, Slate};
+use std::thread;
+use std::time::Duration;
+use util::ZeroingString;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn split_signing_impl(test_dir: &'static str) -> Result<(), wallet::Error> {
+	let seed_phrase = "affair pistol cancel crush garment candy ancient flag work \
+	                   market crush dry stand focus mutual weapon offer ceiling rival turn team spring \
+	                   where swift";
+	let seed_phrase = Some(ZeroingString::from(seed_phrase));
+
+	// Create a new proxy to simulate server and wallet responses
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+
+	// online and offline wallets with the same seed
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		seed_phrase,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client1_offline,
+		wallet1_offline,
+		mask1_offline_i,
+		test_dir,
+		"wallet1_offline",
+		seed_phrase,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1_offline = (&mask1_offline_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask2 = (&mask2_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let reward = core::consensus::MWC_FIRST_GROUP_REWARD;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	// Send, the online wallet does round 1 and exports the slate
+	let mut slate = Slate::blank(1);
+	let mut export = String::new();
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: reward,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: false,
+			split_signing: Some(true),
+			..Default::default()
+		};
+		let slate_i = api.init_send_tx(m, args, 1)?;
+		api.tx_lock_outputs(m, &slate_i, None, 0)?;
+		slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
+
+		export = api.export_round_1(m, &slate_i)?;
+		// The nonce left with the export, this wallet can't sign or export the slate again
+		assert!(api.export_round_1(m, &slate_i).is_err());
+		assert!(api.finalize_tx(m, &slate).is_err());
+		Ok(())
+	})?;
+
+	// The offline wallet signs the response of the recipient, only once
+	wallet::controller::owner_single_use(
+		Some(wallet1_offline.clone()),
+		mask1_offline,
+		None,
+		|api, m| {
+			let finalized = api.finalize_exported_tx(m, &export, &slate)?;
+			assert!(api.finalize_exported_tx(m, &export, &slate).is_err());
+			api.post_tx(m, &finalized.tx, false)?;
+			Ok(())
+		},
+	)?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, wallet2_info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(wallet2_info.amount_currently_spendable, reward);
+		Ok(())
+	})?;
+
+	// Invoice payment, the offline wallet adds the payer signature for the invoicer
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let args = IssueInvoiceTxArgs {
+			amount: reward / 2,
+			..Default::default()
+		};
+		slate = api.issue_invoice_tx(m, args)?;
+		Ok(())
+	})?;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: slate.amount,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: false,
+			split_signing: Some(true),
+			..Default::default()
+		};
+		slate = api.process_invoice_tx(m, &slate, args)?;
+		assert!(slate
+			.participant_with_id(1)
+			.and_then(|p| p.part_sig)
+			.is_none());
+		api.tx_lock_outputs(m, &slate, None, 1)?;
+		export = api.export_round_1(m, &slate)?;
+		Ok(())
+	})?;
+	wallet::controller::owner_single_use(
+		Some(wallet1_offline.clone()),
+		mask1_offline,
+		None,
+		|api, m| {
+			slate = api.finalize_exported_tx(m, &export, &slate)?;
+			assert!(slate
+				.participant_with_id(1)
+				.and_then(|p| p.part_sig)
+				.is_some());
+			Ok(())
+		},
+	)?;
+	wallet::controller::foreign_single_use(wallet2.clone(), mask2_i.clone(), |api| {
+		slate = api.finalize_invoice_tx(&slate)?;
+		Ok(())
+	})?;
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		api.post_tx(m, &slate.tx, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, wallet2_info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(wallet2_info.amount_currently_spendable, reward + reward / 2);
+		Ok(())
+	})?;
+
+	// let logging finish
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn wallet_split_signing() -> Result<(), wallet::Error> {
+	let test_dir = "test_output/split_signing";
+	setup(test_dir);
+	split_signing_impl(test_dir)?;
+	clean_output_dir(test_dir);
+	Ok(())
+}
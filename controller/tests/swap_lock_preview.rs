@@ -0,0 +1,149 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test preview of the outputs that the swap lock transaction will spend
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+use grin_wallet_util::grin_util as util;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, SwapStartArgs};
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn swap_lock_preview_test_impl(test_dir: &'static str) -> Result<(), wallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	libwallet::swap::trades::init_swap_trade_backend(test_dir, &None);
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let reward = core::consensus::reward(0, 1);
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 6, false);
+
+	// The same arguments as for swap_start
+	let start_args = |mwc_amount: u64, minimum_confirmations: Option<u64>| SwapStartArgs {
+		mwc_amount,
+		secondary_currency: "btc".to_string(),
+		secondary_amount: "0.01".to_string(),
+		secondary_redeem_address: "mjdcskZUz4KbJcWSMqzTFt2QpfD2nL8ZgB".to_string(),
+		secondary_fee: None,
+		seller_lock_first: true,
+		minimum_confirmations,
+		mwc_confirmations: 10,
+		secondary_confirmations: 6,
+		message_exchange_time_sec: 3600,
+		redeem_time_sec: 3600,
+		buyer_communication_method: "file".to_string(),
+		buyer_communication_address: "buyer_offer.swap".to_string(),
+		electrum_node_uri1: None,
+		electrum_node_uri2: None,
+		external_ref: None,
+		dry_run: true,
+	};
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let amount = reward * 2;
+		let preview = api.swap_preview_lock_inputs(m, &start_args(amount, Some(2)))?;
+		assert!(preview.shortfall.is_none());
+		assert_eq!(preview.inputs.len(), 3);
+		assert_eq!(preview.change, preview.total - amount - preview.fee);
+
+		// The same outputs are selected for the funding
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: false,
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args, 1)?;
+		assert_eq!(slate.fee, preview.fee);
+		let mut funded: Vec<String> = slate
+			.tx
+			.body
+			.inputs
+			.iter()
+			.map(|i| util::to_hex(i.commit.0.to_vec()))
+			.collect();
+		let mut previewed: Vec<String> = preview
+			.inputs
+			.iter()
+			.map(|o| o.commit.clone().unwrap())
+			.collect();
+		funded.sort();
+		previewed.sort();
+		assert_eq!(funded, previewed);
+
+		// Without minimum confirmations the swap default applies, the outputs are too fresh
+		let preview = api.swap_preview_lock_inputs(m, &start_args(amount, None))?;
+		assert!(preview.inputs.is_empty());
+		assert_eq!(preview.shortfall, Some(amount + preview.fee));
+
+		// Not enough funds, the largest selection is returned
+		let amount = reward * 100;
+		let preview = api.swap_preview_lock_inputs(m, &start_args(amount, Some(2)))?;
+		assert!(!preview.inputs.is_empty());
+		assert_eq!(preview.change, 0);
+		assert_eq!(
+			preview.shortfall,
+			Some(amount + preview.fee - preview.total)
+		);
+		Ok(())
+	})?;
+
+	// let logging finish
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn swap_lock_preview() {
+	let test_dir = "test_output/swap_lock_preview";
+	setup(test_dir);
+	if let Err(e) = swap_lock_preview_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
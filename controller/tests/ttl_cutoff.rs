@@ -124,6 +124,30 @@ fn ttl_cutoff_test_impl(test_dir: &'static str) -> Result<(), wallet::Error> {
 		Ok(())
 	})?;
 
+	// TTL that the recipient would reject is not accepted by the sender either
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
+		let args = |ttl_blocks| InitTxArgs {
+			src_acct_name: None,
+			amount: amount,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: true,
+			ttl_blocks: Some(ttl_blocks),
+			estimate_only: Some(true),
+			..Default::default()
+		};
+		assert!(sender_api
+			.init_send_tx(m, args(libwallet::MAX_TTL_CUTOFF_BLOCKS + 1), 1)
+			.is_err());
+		let slate_i = sender_api.init_send_tx(m, args(libwallet::MAX_TTL_CUTOFF_BLOCKS), 1)?;
+		assert_eq!(
+			slate_i.ttl_cutoff_height,
+			Some(slate_i.height + libwallet::MAX_TTL_CUTOFF_BLOCKS)
+		);
+		Ok(())
+	})?;
+
 	// try again, except try and send off the transaction for completion beyond the expiry
 	let mut slate = Slate::blank(1);
 	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
@@ -0,0 +1,201 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encodings of the slate files. The slate is always prepared as JSON first (version downgrade,
+//! envelope), the codec only changes how that data is stored. Format is detected on read.
+//! Binary is the V5 slate from VersionedSlate::to_binary, it can't carry the file envelope.
+
+use crate::error::{Error, ErrorKind};
+use crate::libwallet::VersionedSlate;
+use data_encoding::BASE64;
+
+/// Header of the binary format. The last byte is the format version.
+const BINARY_MAGIC: &[u8] = b"MWCS\x01";
+/// Armored slate is wrapped with these markers
+const ARMOR_HEADER: &str = "BEGINSLATE.";
+const ARMOR_FOOTER: &str = "ENDSLATE.";
+/// Line length of the armored data
+const ARMOR_LINE_LEN: usize = 64;
+
+/// Encoding of the slate file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlateCodec {
+	/// Plain JSON, default
+	Json,
+	/// Indented JSON for the human inspection and the archives. It is read back as Json.
+	PrettyJson,
+	/// CBOR of the JSON data
+	Cbor,
+	/// Binary V5 slate with the format header, so the format can't be confused
+	Binary,
+	/// Binary format as a base64 text, that is safe for copy/paste and messengers
+	Armored,
+}
+
+impl SlateCodec {
+	/// Detect the codec of the stored data
+	pub fn detect(data: &[u8]) -> Self {
+		if data.starts_with(BINARY_MAGIC) {
+			return SlateCodec::Binary;
+		}
+		let first = data.iter().find(|b| !b.is_ascii_whitespace());
+		match first {
+			Some(b'{') => SlateCodec::Json,
+			Some(_) if data_as_text(data).starts_with(ARMOR_HEADER) => SlateCodec::Armored,
+			_ => SlateCodec::Cbor,
+		}
+	}
+
+	/// True if the codec can store only the slate, without the file envelope
+	pub fn slate_only(&self) -> bool {
+		match self {
+			SlateCodec::Binary | SlateCodec::Armored => true,
+			SlateCodec::Json | SlateCodec::PrettyJson | SlateCodec::Cbor => false,
+		}
+	}
+
+	/// Encode the slate JSON
+	pub fn encode(&self, json: &str) -> Result<Vec<u8>, Error> {
+		let res = match self {
+			SlateCodec::Json => json.as_bytes().to_vec(),
+			SlateCodec::PrettyJson => {
+				let value: serde_json::Value = serde_json::from_str(json)
+					.map_err(|e| ErrorKind::Format(format!("Unable to parse slate JSON, {}", e)))?;
+				serde_json::to_string_pretty(&value)
+					.map_err(|e| ErrorKind::Format(format!("Unable to format slate JSON, {}", e)))?
+					.into_bytes()
+			}
+			SlateCodec::Cbor => to_cbor(json)?,
+			SlateCodec::Binary => {
+				let mut res = BINARY_MAGIC.to_vec();
+				res.extend(VersionedSlate::from_json(json)?.to_binary()?);
+				res
+			}
+			SlateCodec::Armored => {
+				let encoded = BASE64.encode(&SlateCodec::Binary.encode(json)?);
+				let lines: Vec<String> = encoded
+					.as_bytes()
+					.chunks(ARMOR_LINE_LEN)
+					.map(|l| String::from_utf8_lossy(l).to_string())
+					.collect();
+				format!("{}\n{}\n{}\n", ARMOR_HEADER, lines.join("\n"), ARMOR_FOOTER).into_bytes()
+			}
+		};
+		Ok(res)
+	}
+
+	/// Decode the data back into the slate JSON
+	pub fn decode(&self, data: &[u8]) -> Result<String, Error> {
+		match self {
+			SlateCodec::Json | SlateCodec::PrettyJson => {
+				String::from_utf8(data.to_vec()).map_err(|e| {
+					ErrorKind::Format(format!("Slate JSON is not a valid UTF-8, {}", e)).into()
+				})
+			}
+			SlateCodec::Cbor => from_cbor(data),
+			SlateCodec::Binary => {
+				if !data.starts_with(BINARY_MAGIC) {
+					return Err(ErrorKind::Format("Unknown binary slate format".to_string()).into());
+				}
+				let slate = VersionedSlate::from_binary(&data[BINARY_MAGIC.len()..])?;
+				serde_json::to_string(&slate).map_err(|e| {
+					ErrorKind::Format(format!("Unable to convert binary slate to JSON, {}", e))
+						.into()
+				})
+			}
+			SlateCodec::Armored => {
+				let text = data_as_text(data);
+				let text = text.trim_end();
+				if !text.starts_with(ARMOR_HEADER) || !text.ends_with(ARMOR_FOOTER) {
+					return Err(ErrorKind::Format(
+						"Armored slate markers are not found".to_string(),
+					)
+					.into());
+				}
+				let body = &text[ARMOR_HEADER.len()..text.len() - ARMOR_FOOTER.len()];
+				let encoded: String = body.split_whitespace().collect();
+				let binary = BASE64.decode(encoded.as_bytes()).map_err(|e| {
+					ErrorKind::Format(format!("Unable to decode armored slate, {}", e))
+				})?;
+				SlateCodec::Binary.decode(&binary)
+			}
+		}
+	}
+}
+
+/// Decode the slate data with auto detected codec
+pub fn decode_slate_data(data: &[u8]) -> Result<String, Error> {
+	SlateCodec::detect(data).decode(data)
+}
+
+fn data_as_text(data: &[u8]) -> String {
+	String::from_utf8_lossy(data).trim_start().to_string()
+}
+
+fn to_cbor(json: &str) -> Result<Vec<u8>, Error> {
+	let value: serde_json::Value = serde_json::from_str(json)
+		.map_err(|e| ErrorKind::Format(format!("Unable to parse slate JSON, {}", e)))?;
+	let res = serde_cbor::to_vec(&value)
+		.map_err(|e| ErrorKind::Format(format!("Unable to encode slate to CBOR, {}", e)))?;
+	Ok(res)
+}
+
+fn from_cbor(data: &[u8]) -> Result<String, Error> {
+	let value: serde_json::Value = serde_cbor::from_slice(data)
+		.map_err(|e| ErrorKind::Format(format!("Unable to decode CBOR slate, {}", e)))?;
+	Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::libwallet::{Slate, SlateVersion};
+
+	#[test]
+	fn test_slate_codec_detect() {
+		// Keys are sorted, the same way as the decoded JSON has
+		let json = "{\"amount\":\"1000\",\"id\":\"0436430c-2b02-624c-2032-570501212b00\"}";
+		for codec in &[SlateCodec::Json, SlateCodec::Cbor] {
+			let data = codec.encode(json).unwrap();
+			assert_eq!(SlateCodec::detect(&data), *codec);
+			assert_eq!(decode_slate_data(&data).unwrap(), json);
+		}
+
+		// Binary codecs need the real slate, it is read back as V5
+		let slate = Slate::blank(2);
+		let json = serde_json::to_string(&VersionedSlate::into_version(
+			slate.clone(),
+			SlateVersion::V3,
+		))
+		.unwrap();
+		for codec in &[SlateCodec::Binary, SlateCodec::Armored] {
+			let data = codec.encode(&json).unwrap();
+			assert_eq!(SlateCodec::detect(&data), *codec);
+			let decoded = VersionedSlate::from_json(&decode_slate_data(&data).unwrap()).unwrap();
+			assert_eq!(decoded.version(), SlateVersion::V5);
+			assert_eq!(Slate::from(decoded).id, slate.id);
+		}
+		// The envelope is not a slate
+		assert!(SlateCodec::Binary
+			.encode("{\"meta\":{},\"slate\":{}}")
+			.is_err());
+
+		let armored = String::from_utf8(SlateCodec::Armored.encode(&json).unwrap()).unwrap();
+		assert!(armored.starts_with(ARMOR_HEADER));
+		assert!(armored.lines().all(|l| l.len() <= ARMOR_LINE_LEN));
+		// Broken armor is rejected
+		let broken = armored.replace(ARMOR_FOOTER, "");
+		assert!(decode_slate_data(broken.as_bytes()).is_err());
+	}
+}
@@ -16,24 +16,133 @@
 use std::fs::File;
 use std::io::{Read, Write};
 
+use super::codec::{decode_slate_data, SlateCodec};
+use super::schema;
 use crate::error::{Error, ErrorKind};
-use crate::libwallet::{Slate, SlateVersion, VersionedSlate};
+use crate::libwallet::internal::version_log::{
+	record_slate_version_decision, SlateVersionDecision,
+};
+use crate::libwallet::{is_payment_proof_required, Slate, SlateVersion, VersionedSlate};
 use crate::{SlateGetter, SlatePutter};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct PathToSlate(pub PathBuf);
 
-impl SlatePutter for PathToSlate {
-	fn put_tx(&self, slate: &Slate) -> Result<(), Error> {
+/// Non consensus metadata (sender label, routing hint, etc.) that can be attached to the slate file.
+/// It is never used for the transaction building or signing.
+pub type SlateFileMeta = BTreeMap<String, String>;
+
+/// Max length of the slate file comment, characters
+pub const MAX_SLATE_COMMENT_LEN: usize = 256;
+
+/// Slate file with metadata: `{ "meta": {...}, "comment": "...", "slate": {...} }`
+/// Comment is a short note for the other party. It is not a part of the transaction.
+#[derive(Serialize, Deserialize)]
+struct SlateFileEnvelope {
+	#[serde(default)]
+	meta: SlateFileMeta,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	comment: Option<String>,
+	slate: serde_json::Value,
+}
+
+/// Slate file data besides the slate
+struct SlateFileExtras {
+	meta: Option<SlateFileMeta>,
+	comment: Option<String>,
+}
+
+impl PathToSlate {
+	/// Slate file for the signing step at the directory: `<slate_id>.S<step>.tx`.
+	/// Files of the same slate are easy to match and different steps don't overwrite each other.
+	pub fn for_step(dir: &Path, slate_id: &Uuid, step: u32) -> Self {
+		PathToSlate(dir.join(format!("{}.S{}.tx", slate_id, step)))
+	}
+
+	/// Write the slate into the file. If metadata is provided, the slate is wrapped with envelope.
+	pub fn put_tx_with_meta(
+		&self,
+		slate: &Slate,
+		meta: Option<&SlateFileMeta>,
+	) -> Result<(), Error> {
+		self.put_tx_with_comment(slate, meta, None)
+	}
+
+	/// Write the slate into the file with a comment for the other party. The slate is wrapped
+	/// with envelope if metadata or comment is provided.
+	pub fn put_tx_with_comment(
+		&self,
+		slate: &Slate,
+		meta: Option<&SlateFileMeta>,
+		comment: Option<&str>,
+	) -> Result<(), Error> {
+		self.put_tx_encoded(slate, meta, comment, SlateCodec::Json)
+	}
+
+	/// Write the slate into the file with the codec. Reading detects the codec automatically.
+	pub fn put_tx_with_codec(&self, slate: &Slate, codec: SlateCodec) -> Result<(), Error> {
+		self.put_tx_encoded(slate, None, None, codec)
+	}
+
+	/// Write the slate that the sender sends out. The wallet payment proof policy is applied to
+	/// the send flow only, the responses and the other slates are written as they are.
+	pub fn put_send_tx(&self, slate: &Slate) -> Result<(), Error> {
+		self.put_tx_with_policy(
+			slate,
+			None,
+			None,
+			SlateCodec::Json,
+			is_payment_proof_required(),
+		)
+	}
+
+	fn put_tx_encoded(
+		&self,
+		slate: &Slate,
+		meta: Option<&SlateFileMeta>,
+		comment: Option<&str>,
+		codec: SlateCodec,
+	) -> Result<(), Error> {
+		self.put_tx_with_policy(slate, meta, comment, codec, false)
+	}
+
+	fn put_tx_with_policy(
+		&self,
+		slate: &Slate,
+		meta: Option<&SlateFileMeta>,
+		comment: Option<&str>,
+		codec: SlateCodec,
+		proof_required: bool,
+	) -> Result<(), Error> {
+		if let Some(comment) = comment {
+			check_comment_len(comment)
+				.map_err(|e| ErrorKind::ArgumentError(format!("Invalid slate comment, {}", e)))?;
+		}
+		if codec.slate_only() && (meta.is_some() || comment.is_some()) {
+			return Err(ErrorKind::ArgumentError(format!(
+				"{:?} slate file can't have the metadata or the comment",
+				codec
+			))
+			.into());
+		}
+		if proof_required && slate.payment_proof.is_none() {
+			return Err(ErrorKind::PaymentProofRequired(format!(
+				"slate {} doesn't have a payment proof",
+				slate.id
+			))
+			.into());
+		}
 		let file_name = self.0.to_str().unwrap_or("INVALID PATH");
 		let mut pub_tx = File::create(&self.0).map_err(|e| {
 			ErrorKind::IO(format!("Unable to create proof file {}, {}", file_name, e))
 		})?;
 		let out_slate = {
-			if slate.payment_proof.is_some() || slate.ttl_cutoff_height.is_some() {
-				warn!("Transaction contains features that require mwc-wallet 3.0.0 or later");
-				warn!("Please ensure the other party is running mwc-wallet v3.0.0 or later before sending");
+			// Payment proof needs V3, the slate can't be downgraded
+			if proof_required || slate.lowest_version() == SlateVersion::V3 {
+				warn!("{}", SlateVersion::V3.compatibility_warning());
 				VersionedSlate::into_version(slate.clone(), SlateVersion::V3)
 			} else {
 				let mut s = slate.clone();
@@ -42,51 +151,514 @@ impl SlatePutter for PathToSlate {
 				VersionedSlate::into_version(s, SlateVersion::V2)
 			}
 		};
+		// Support needs to know why the slate went out with this version
+		let decision = SlateVersionDecision::new(slate, out_slate.version());
+		if let Err(e) = record_slate_version_decision(&decision) {
+			warn!("Unable to record the version of slate {}, {}", slate.id, e);
+		}
+		let out_json = match (meta, comment) {
+			(None, None) => serde_json::to_string(&out_slate),
+			(meta, comment) => serde_json::to_string(&SlateFileEnvelope {
+				meta: meta.cloned().unwrap_or_default(),
+				comment: comment.map(|c| c.to_string()),
+				slate: serde_json::to_value(&out_slate).map_err(|e| {
+					ErrorKind::GenericError(format!("Failed convert Slate to Json, {}", e))
+				})?,
+			}),
+		}
+		.map_err(|e| ErrorKind::GenericError(format!("Failed convert Slate to Json, {}", e)))?;
+		pub_tx.write_all(&codec.encode(&out_json)?).map_err(|e| {
+			ErrorKind::IO(format!(
+				"Unable to store data at proof file {}, {}",
+				file_name, e
+			))
+		})?;
+
+		pub_tx.sync_all().map_err(|e| {
+			ErrorKind::IO(format!(
+				"Unable to store data at proof file {}, {}",
+				file_name, e
+			))
+		})?;
+
+		Ok(())
+	}
+
+	/// Write the slate at the requested version. Features that the version can't carry are
+	/// dropped with a warning, the transaction and the signatures are written as they are.
+	/// The wallet payment proof policy is applied, the proof can't be dropped if it is required.
+	pub fn put_tx_version(&self, slate: &Slate, version: SlateVersion) -> Result<(), Error> {
+		self.put_tx_version_with_policy(slate, version, is_payment_proof_required())
+	}
+
+	fn put_tx_version_with_policy(
+		&self,
+		slate: &Slate,
+		version: SlateVersion,
+		proof_required: bool,
+	) -> Result<(), Error> {
+		// V2 can't carry the payment proof
+		if proof_required && (slate.payment_proof.is_none() || version == SlateVersion::V2) {
+			return Err(ErrorKind::PaymentProofRequired(format!(
+				"slate {} can't be written as {:?} without the payment proof",
+				slate.id, version
+			))
+			.into());
+		}
+		let lost = VersionedSlate::lost_features(slate, version.clone());
+		if !lost.is_empty() {
+			warn!(
+				"Slate {} is written as {:?}, these features are lost: {}",
+				slate.id,
+				version,
+				lost.join(", ")
+			);
+		}
+		let mut s = slate.clone();
+		if version == SlateVersion::V2 {
+			s.version_info.version = 2;
+			s.version_info.orig_version = 2;
+		}
+		let out_json = serde_json::to_string(&VersionedSlate::into_version(s, version))
+			.map_err(|e| ErrorKind::GenericError(format!("Failed convert Slate to Json, {}", e)))?;
+
+		let file_name = self.0.to_str().unwrap_or("INVALID PATH");
+		let mut pub_tx = File::create(&self.0).map_err(|e| {
+			ErrorKind::IO(format!("Unable to create slate file {}, {}", file_name, e))
+		})?;
 		pub_tx
-			.write_all(
-				serde_json::to_string(&out_slate)
-					.map_err(|e| {
-						ErrorKind::GenericError(format!("Failed convert Slate to Json, {}", e))
-					})?
-					.as_bytes(),
-			)
+			.write_all(out_json.as_bytes())
+			.and_then(|_| pub_tx.sync_all())
 			.map_err(|e| {
 				ErrorKind::IO(format!(
-					"Unable to store data at proof file {}, {}",
+					"Unable to store data at slate file {}, {}",
 					file_name, e
 				))
 			})?;
+		Ok(())
+	}
 
-		pub_tx.sync_all().map_err(|e| {
+	/// Read the slate and the metadata from the file. Plain slate files don't have metadata.
+	pub fn get_tx_with_meta(&self) -> Result<(Slate, Option<SlateFileMeta>), Error> {
+		let file_name = self.0.to_str().unwrap_or("INVALID PATH");
+		let (content, extras) = self.read_slate_json()?;
+		let slate = Slate::deserialize_upgrade(&content).map_err(|e| {
 			ErrorKind::IO(format!(
-				"Unable to store data at proof file {}, {}",
+				"Unable to build slate from json, file {}, {}",
 				file_name, e
 			))
 		})?;
+		Ok((slate, extras.meta))
+	}
 
-		Ok(())
+	/// Read the slate and the comment from the file. The comment is not a part of the slate.
+	pub fn get_tx_with_comment(&self) -> Result<(Slate, Option<String>), Error> {
+		let file_name = self.0.to_str().unwrap_or("INVALID PATH");
+		let (content, extras) = self.read_slate_json()?;
+		let slate = Slate::deserialize_upgrade(&content).map_err(|e| {
+			ErrorKind::IO(format!(
+				"Unable to build slate from json, file {}, {}",
+				file_name, e
+			))
+		})?;
+		Ok((slate, extras.comment))
 	}
-}
 
-impl SlateGetter for PathToSlate {
-	fn get_tx(&self) -> Result<Slate, Error> {
+	/// Read the slate with the version that it has at the file, without upgrade
+	pub fn get_versioned_tx(&self) -> Result<VersionedSlate, Error> {
+		let file_name = self.0.to_str().unwrap_or("INVALID PATH");
+		let (content, _extras) = self.read_slate_json()?;
+		let slate = VersionedSlate::from_json(&content).map_err(|e| {
+			ErrorKind::IO(format!(
+				"Unable to build slate from json, file {}, {}",
+				file_name, e
+			))
+		})?;
+		Ok(slate)
+	}
+
+	fn read_slate_json(&self) -> Result<(String, SlateFileExtras), Error> {
 		let file_name = self.0.to_str().unwrap_or("INVALID PATH");
 		let mut pub_tx_f = File::open(&self.0).map_err(|e| {
 			ErrorKind::IO(format!("Unable to open proof file {}, {}", file_name, e))
 		})?;
-		let mut content = String::new();
-		pub_tx_f.read_to_string(&mut content).map_err(|e| {
+		let mut data = Vec::new();
+		pub_tx_f.read_to_end(&mut data).map_err(|e| {
 			ErrorKind::IO(format!(
 				"Unable to read data from file {}, {}",
 				file_name, e
 			))
 		})?;
+		let content = decode_slate_data(&data)?;
 
-		Ok(Slate::deserialize_upgrade(&content).map_err(|e| {
-			ErrorKind::IO(format!(
-				"Unable to build slate from json, file {}, {}",
-				file_name, e
-			))
-		})?)
+		// Metadata and comment are stripped, only the core slate goes further
+		let res = match serde_json::from_str::<SlateFileEnvelope>(&content) {
+			Ok(envelope) => {
+				if let Some(comment) = &envelope.comment {
+					check_comment_len(comment).map_err(|e| {
+						ErrorKind::Format(format!(
+							"Invalid slate comment at file {}, {}",
+							file_name, e
+						))
+					})?;
+				}
+				(
+					envelope.slate.to_string(),
+					SlateFileExtras {
+						meta: Some(envelope.meta),
+						comment: envelope.comment,
+					},
+				)
+			}
+			Err(_) => (
+				content,
+				SlateFileExtras {
+					meta: None,
+					comment: None,
+				},
+			),
+		};
+		if schema::is_slate_schema_validation() {
+			schema::validate_slate_json(&res.0)?;
+		}
+		Ok(res)
+	}
+}
+
+fn check_comment_len(comment: &str) -> Result<(), String> {
+	let len = comment.chars().count();
+	if len > MAX_SLATE_COMMENT_LEN {
+		return Err(format!(
+			"comment has {} characters, the limit is {}",
+			len, MAX_SLATE_COMMENT_LEN
+		));
+	}
+	Ok(())
+}
+
+impl SlatePutter for PathToSlate {
+	fn put_tx(&self, slate: &Slate) -> Result<(), Error> {
+		self.put_tx_with_meta(slate, None)
+	}
+}
+
+impl SlateGetter for PathToSlate {
+	fn get_tx(&self) -> Result<Slate, Error> {
+		Ok(self.get_tx_with_meta()?.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::libwallet::internal::version_log;
+	use crate::libwallet::proof::proofaddress::ProvableAddress;
+	use crate::libwallet::PaymentInfo;
+	use serial_test::serial;
+
+	fn test_slate_file(name: &str) -> PathToSlate {
+		let dir = std::env::temp_dir().join("mwc_wallet_file_adapter_test");
+		std::fs::create_dir_all(&dir).unwrap();
+		PathToSlate(dir.join(name))
+	}
+
+	#[test]
+	fn test_slate_file_envelope() {
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		slate.fee = 8_000_000;
+
+		// Bare file, no metadata
+		let bare = test_slate_file("bare.tx");
+		bare.put_tx(&slate).unwrap();
+		let (read_slate, meta) = bare.get_tx_with_meta().unwrap();
+		assert!(meta.is_none());
+		assert_eq!(read_slate.id, slate.id);
+		assert_eq!(read_slate.amount, slate.amount);
+
+		// Enveloped file, metadata doesn't affect the slate
+		let mut meta = SlateFileMeta::new();
+		meta.insert("sender_label".to_string(), "exchange".to_string());
+		meta.insert("routing_hint".to_string(), "mwcmqs".to_string());
+		let enveloped = test_slate_file("enveloped.tx");
+		enveloped.put_tx_with_meta(&slate, Some(&meta)).unwrap();
+		let content = std::fs::read_to_string(&enveloped.0).unwrap();
+		assert!(content.contains("\"meta\""));
+		let (env_slate, env_meta) = enveloped.get_tx_with_meta().unwrap();
+		assert_eq!(env_meta, Some(meta));
+		assert_eq!(
+			serde_json::to_string(&env_slate).unwrap(),
+			serde_json::to_string(&read_slate).unwrap()
+		);
+		assert_eq!(enveloped.get_tx().unwrap().id, slate.id);
+	}
+
+	#[test]
+	fn test_slate_file_comment() {
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		slate.fee = 8_000_000;
+		let comment = "Invoice #42, please finalize by Friday";
+
+		let commented = test_slate_file("commented.tx");
+		commented
+			.put_tx_with_comment(&slate, None, Some(comment))
+			.unwrap();
+		let (read_slate, read_comment) = commented.get_tx_with_comment().unwrap();
+		assert_eq!(read_comment, Some(comment.to_string()));
+		// Comment is not a part of the slate
+		let plain = test_slate_file("plain.tx");
+		plain.put_tx(&slate).unwrap();
+		assert!(!std::fs::read_to_string(&plain.0)
+			.unwrap()
+			.contains("Invoice"));
+		assert_eq!(
+			serde_json::to_string(&read_slate).unwrap(),
+			serde_json::to_string(&plain.get_tx().unwrap()).unwrap()
+		);
+		assert_eq!(plain.get_tx_with_comment().unwrap().1, None);
+
+		// Comment together with metadata
+		let mut meta = SlateFileMeta::new();
+		meta.insert("sender_label".to_string(), "exchange".to_string());
+		commented
+			.put_tx_with_comment(&slate, Some(&meta), Some(comment))
+			.unwrap();
+		let (_, read_meta) = commented.get_tx_with_meta().unwrap();
+		assert_eq!(read_meta, Some(meta));
+		assert_eq!(
+			commented.get_tx_with_comment().unwrap().1,
+			Some(comment.to_string())
+		);
+
+		// Comment is limited
+		let long_comment = "a".repeat(MAX_SLATE_COMMENT_LEN + 1);
+		assert!(commented
+			.put_tx_with_comment(&slate, None, Some(&long_comment))
+			.is_err());
+		commented
+			.put_tx_with_comment(&slate, None, Some(&long_comment[1..]))
+			.unwrap();
+	}
+
+	#[test]
+	fn test_slate_file_codecs() {
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		slate.fee = 8_000_000;
+
+		let json_file = test_slate_file("codec.json.tx");
+		json_file.put_tx(&slate).unwrap();
+		let expected = serde_json::to_string(&json_file.get_tx().unwrap()).unwrap();
+
+		for (codec, name) in &[
+			(SlateCodec::Json, "codec.json2.tx"),
+			(SlateCodec::Cbor, "codec.cbor.tx"),
+			(SlateCodec::Binary, "codec.bin.tx"),
+			(SlateCodec::Armored, "codec.armored.tx"),
+		] {
+			let file = test_slate_file(name);
+			file.put_tx_with_codec(&slate, *codec).unwrap();
+			let data = std::fs::read(&file.0).unwrap();
+			assert_eq!(SlateCodec::detect(&data), *codec);
+			// Reading detects the codec
+			let read_slate = file.get_tx().unwrap();
+			assert_eq!(serde_json::to_string(&read_slate).unwrap(), expected);
+			// Binary is always the V5 slate
+			let version = match codec.slate_only() {
+				true => SlateVersion::V5,
+				false => SlateVersion::V2,
+			};
+			assert_eq!(file.get_versioned_tx().unwrap().version(), version);
+		}
+
+		// Binary file has no room for the metadata
+		let mut meta = SlateFileMeta::new();
+		meta.insert("label".to_string(), "test".to_string());
+		let file = test_slate_file("codec.meta.tx");
+		assert!(file
+			.put_tx_encoded(&slate, Some(&meta), None, SlateCodec::Binary)
+			.is_err());
+		file.put_tx_encoded(&slate, Some(&meta), None, SlateCodec::Cbor)
+			.unwrap();
+	}
+
+	#[test]
+	fn test_slate_file_pretty_json() {
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		slate.fee = 8_000_000;
+
+		let compact = test_slate_file("compact.tx");
+		compact.put_tx(&slate).unwrap();
+		let pretty = test_slate_file("pretty.tx");
+		pretty
+			.put_tx_with_codec(&slate, SlateCodec::PrettyJson)
+			.unwrap();
+
+		let content = std::fs::read_to_string(&pretty.0).unwrap();
+		assert!(content.lines().count() > 10);
+		assert!(content.contains("\n  \"amount\": \"1000000000\""));
+		assert_eq!(
+			std::fs::read_to_string(&compact.0).unwrap().lines().count(),
+			1
+		);
+
+		// Pretty file is read the same way as the compact one
+		assert_eq!(
+			serde_json::to_string(&pretty.get_tx().unwrap()).unwrap(),
+			serde_json::to_string(&compact.get_tx().unwrap()).unwrap()
+		);
+		assert_eq!(
+			pretty.get_versioned_tx().unwrap().version(),
+			SlateVersion::V2
+		);
+	}
+
+	#[test]
+	#[serial]
+	fn test_slate_file_schema_validation() {
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		slate.fee = 8_000_000;
+		let file = test_slate_file("schema.tx");
+		file.put_tx(&slate).unwrap();
+		// Third party wallet lost the fee
+		let content = std::fs::read_to_string(&file.0).unwrap();
+		let mut json: serde_json::Value = serde_json::from_str(&content).unwrap();
+		json.as_object_mut().unwrap().remove("fee");
+		std::fs::write(&file.0, json.to_string()).unwrap();
+
+		schema::set_slate_schema_validation(true);
+		let res = file.get_tx();
+		schema::set_slate_schema_validation(false);
+		match res.unwrap_err().kind() {
+			ErrorKind::SlateSchema(msg) => assert_eq!(msg, "field 'fee' is missing"),
+			e => panic!("Unexpected error {}", e),
+		}
+	}
+
+	#[test]
+	fn test_slate_file_payment_proof_required() {
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		slate.fee = 8_000_000;
+		let file = test_slate_file("proof_required.tx");
+		let put =
+			|slate: &Slate| file.put_tx_with_policy(slate, None, None, SlateCodec::Json, true);
+
+		match put(&slate).unwrap_err().kind() {
+			ErrorKind::PaymentProofRequired(_) => (),
+			e => panic!("Unexpected error {}", e),
+		}
+
+		let address =
+			ProvableAddress::from_str("fffqrlhd4ug2bdnnfyhvgmqk6pe5cqbffk5dmuswvgdx2adf4sspg3yd")
+				.unwrap();
+		slate.payment_proof = Some(PaymentInfo {
+			sender_address: address.clone(),
+			receiver_address: address,
+			receiver_signature: None,
+		});
+		put(&slate).unwrap();
+		assert_eq!(file.get_versioned_tx().unwrap().version(), SlateVersion::V3);
+		assert_eq!(file.get_tx().unwrap().id, slate.id);
+
+		// Re-export can't drop the proof
+		let reexport = test_slate_file("proof_required_reexport.tx");
+		match reexport
+			.put_tx_version_with_policy(&slate, SlateVersion::V2, true)
+			.unwrap_err()
+			.kind()
+		{
+			ErrorKind::PaymentProofRequired(_) => (),
+			e => panic!("Unexpected error {}", e),
+		}
+		reexport
+			.put_tx_version_with_policy(&slate, SlateVersion::V3, true)
+			.unwrap();
+		reexport
+			.put_tx_version_with_policy(&slate, SlateVersion::V2, false)
+			.unwrap();
+
+		// Slates that are not sent out, like the receive response, are written without the policy
+		slate.payment_proof = None;
+		file.put_tx(&slate).unwrap();
+	}
+
+	#[test]
+	#[serial]
+	fn test_slate_file_version_decision_log() {
+		let dir = std::env::temp_dir().join("mwc_wallet_version_log_test");
+		let _ = std::fs::remove_dir_all(&dir);
+		version_log::init_slate_version_log(dir.to_str().unwrap(), true).unwrap();
+
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		slate.fee = 8_000_000;
+		let file = test_slate_file("version_log.tx");
+
+		// Nothing requires V3, the slate goes as V2
+		file.put_tx(&slate).unwrap();
+		let decisions = version_log::read_slate_version_decisions(Some(&slate.id)).unwrap();
+		assert_eq!(decisions.len(), 1);
+		assert_eq!(decisions[0].version, SlateVersion::V2);
+		assert!(decisions[0].features.is_empty());
+
+		// Payment proof keeps the slate at V3
+		let address =
+			ProvableAddress::from_str("fffqrlhd4ug2bdnnfyhvgmqk6pe5cqbffk5dmuswvgdx2adf4sspg3yd")
+				.unwrap();
+		slate.payment_proof = Some(PaymentInfo {
+			sender_address: address.clone(),
+			receiver_address: address,
+			receiver_signature: None,
+		});
+		file.put_tx(&slate).unwrap();
+		let decisions = version_log::read_slate_version_decisions(Some(&slate.id)).unwrap();
+		assert_eq!(decisions.len(), 2);
+		assert_eq!(decisions[1].slate_id, slate.id);
+		assert_eq!(decisions[1].version, SlateVersion::V3);
+		assert_eq!(decisions[1].features, vec!["payment proof".to_string()]);
+		assert!(
+			version_log::read_slate_version_decisions(Some(&Uuid::new_v4()))
+				.unwrap()
+				.is_empty()
+		);
+
+		// Disabled log doesn't record anything
+		version_log::init_slate_version_log(dir.to_str().unwrap(), false).unwrap();
+		file.put_tx(&slate).unwrap();
+		version_log::init_slate_version_log(dir.to_str().unwrap(), true).unwrap();
+		assert_eq!(
+			version_log::read_slate_version_decisions(Some(&slate.id))
+				.unwrap()
+				.len(),
+			2
+		);
+		version_log::init_slate_version_log(dir.to_str().unwrap(), false).unwrap();
+	}
+
+	#[test]
+	fn test_slate_step_file_names() {
+		let dir = std::env::temp_dir().join("mwc_wallet_file_adapter_test");
+		let slate_id = Uuid::parse_str("0436430c-2b02-624c-2032-570501212b00").unwrap();
+
+		let step1 = PathToSlate::for_step(&dir, &slate_id, 1);
+		let step2 = PathToSlate::for_step(&dir, &slate_id, 2);
+		assert_eq!(
+			step1.0,
+			dir.join("0436430c-2b02-624c-2032-570501212b00.S1.tx")
+		);
+		assert_eq!(
+			step2.0,
+			dir.join("0436430c-2b02-624c-2032-570501212b00.S2.tx")
+		);
+		assert_ne!(step1.0, step2.0);
+
+		let mut slate = Slate::blank(2);
+		slate.id = slate_id.clone();
+		step1.put_tx(&slate).unwrap();
+		assert_eq!(step1.get_tx().unwrap().id, slate_id);
 	}
 }
@@ -383,7 +383,10 @@ impl SlateSender for HttpDataSender {
 		let (url_str, _tor) = self.set_up_tor_send_process()?;
 
 		let slate_send = match self.check_other_version(&url_str, None)? {
-			SlateVersion::V3B => VersionedSlate::into_version(slate.clone(), SlateVersion::V3),
+			// V5 is the binary form, it is never negotiated over http
+			SlateVersion::V5 | SlateVersion::V3B => {
+				VersionedSlate::into_version(slate.clone(), SlateVersion::V3)
+			}
 			SlateVersion::V2 | SlateVersion::V3 => {
 				let mut slate = slate.clone();
 				if slate.payment_proof.is_some() {
@@ -12,13 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod codec;
 mod file;
 pub mod http;
 mod mwcmq;
+mod qr;
+pub mod schema;
 mod types;
 
+pub use self::codec::SlateCodec;
 pub use self::file::PathToSlate;
 pub use self::http::HttpDataSender;
+pub use self::qr::{split_into_qr_parts, QrAssembler, QrPart};
 
 use crate::config::{TorConfig, WalletConfig};
 use crate::error::{Error, ErrorKind};
@@ -64,6 +69,18 @@ pub trait SlatePutter {
 pub trait SlateGetter {
 	/// Receive a transaction async. (Actually just read it from wherever and return the slate)
 	fn get_tx(&self) -> Result<Slate, Error>;
+
+	/// Receive a transaction and validate its TTL cutoff height with the chain tip.
+	/// Without the node (tip_height is None) the cutoff can't be checked.
+	fn get_tx_check_ttl(&self, tip_height: Option<u64>) -> Result<Slate, Error> {
+		let slate = self.get_tx()?;
+		if let Some(tip_height) = tip_height {
+			slate
+				.validate_ttl_cutoff(tip_height)
+				.map_err(crate::libwallet::Error::from)?;
+		}
+		Ok(slate)
+	}
 }
 
 /// Swap Message Sender
@@ -1074,7 +1074,7 @@ impl MWCMQSBroker {
 										}
 									};
 									let ack_message =
-										self.handler.lock().on_swap_message(swap_message);
+										self.handler.lock().on_swap_message(&from, swap_message);
 									if let Some(ack_message) = ack_message {
 										let mqs_cannel = MwcMqsChannel::new(from.to_string());
 										if let Err(e) = mqs_cannel.send_swap_message(&ack_message) {
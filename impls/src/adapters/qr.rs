@@ -0,0 +1,352 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Multi part QR codes for the slates. Slate is too large for a single QR code, so it is split
+//! into parts. Every part is self describing:
+//!    mwcqr:<slate_id>:<index>/<total>:<checksum>:<payload>
+//! index is 1 based, checksum is a blake2b hash of the payload.
+
+use crate::blake2::blake2b::blake2b;
+use crate::error::{Error, ErrorKind};
+use crate::util::to_hex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const QR_PART_PREFIX: &str = "mwcqr";
+
+/// Single part of the multi part QR slate
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrPart {
+	/// Slate that this part belongs to
+	pub slate_id: Uuid,
+	/// Part index, starting from 1
+	pub index: usize,
+	/// Total number of parts
+	pub total: usize,
+	/// Part of the slate data
+	pub payload: String,
+}
+
+fn payload_checksum(payload: &str) -> String {
+	to_hex(blake2b(4, &[], payload.as_bytes()).as_bytes().to_vec())
+}
+
+impl QrPart {
+	/// Parse the QR code content. Checksum is validated, so corrupted parts are rejected.
+	pub fn parse(part: &str) -> Result<Self, Error> {
+		let items: Vec<&str> = part.splitn(5, ':').collect();
+		if items.len() != 5 || items[0] != QR_PART_PREFIX {
+			return Err(ErrorKind::QrPart("unknown format".to_string()).into());
+		}
+		let slate_id = Uuid::parse_str(items[1])
+			.map_err(|e| ErrorKind::QrPart(format!("invalid slate id {}, {}", items[1], e)))?;
+		let counter: Vec<&str> = items[2].split('/').collect();
+		let (index, total) = match (
+			counter.get(0).and_then(|s| s.parse::<usize>().ok()),
+			counter.get(1).and_then(|s| s.parse::<usize>().ok()),
+		) {
+			(Some(index), Some(total)) if counter.len() == 2 && index >= 1 && index <= total => {
+				(index, total)
+			}
+			_ => {
+				return Err(ErrorKind::QrPart(format!("invalid part counter {}", items[2])).into())
+			}
+		};
+		let payload = items[4].to_string();
+		if payload_checksum(&payload) != items[3] {
+			return Err(ErrorKind::QrPart(format!(
+				"part {}/{} of slate {} is corrupted",
+				index, total, slate_id
+			))
+			.into());
+		}
+		Ok(Self {
+			slate_id,
+			index,
+			total,
+			payload,
+		})
+	}
+
+	/// QR code content for this part
+	pub fn to_qr_string(&self) -> String {
+		format!(
+			"{}:{}:{}/{}:{}:{}",
+			QR_PART_PREFIX,
+			self.slate_id,
+			self.index,
+			self.total,
+			payload_checksum(&self.payload),
+			self.payload
+		)
+	}
+}
+
+/// Split the slate data into QR parts, every part has at most max_payload characters of the data
+pub fn split_into_qr_parts(slate_id: &Uuid, data: &str, max_payload: usize) -> Vec<String> {
+	let chars: Vec<char> = data.chars().collect();
+	let chunks: Vec<String> = chars
+		.chunks(std::cmp::max(max_payload, 1))
+		.map(|c| c.iter().collect())
+		.collect();
+	let total = chunks.len();
+	chunks
+		.into_iter()
+		.enumerate()
+		.map(|(i, payload)| {
+			QrPart {
+				slate_id: slate_id.clone(),
+				index: i + 1,
+				total,
+				payload,
+			}
+			.to_qr_string()
+		})
+		.collect()
+}
+
+/// Persisted state of the assembly
+#[derive(Serialize, Deserialize)]
+struct QrAssemblyState {
+	slate_id: Uuid,
+	total: usize,
+	parts: BTreeMap<usize, String>,
+}
+
+/// Collect QR parts of a single slate. Received parts are stored at the directory,
+/// so scanning can be resumed after interruption.
+pub struct QrAssembler {
+	dir: PathBuf,
+	state: Option<QrAssemblyState>,
+}
+
+impl QrAssembler {
+	/// New assembly. Slate is defined by the first received part.
+	pub fn new(dir: PathBuf) -> Self {
+		Self { dir, state: None }
+	}
+
+	/// Resume the assembly of the slate with parts that were received before
+	pub fn resume(dir: PathBuf, slate_id: &Uuid) -> Result<Self, Error> {
+		let file_name = Self::state_file(&dir, slate_id);
+		let state = if file_name.exists() {
+			let content = fs::read_to_string(&file_name).map_err(|e| {
+				ErrorKind::IO(format!(
+					"Unable to read QR parts from {:?}, {}",
+					file_name, e
+				))
+			})?;
+			let state: QrAssemblyState = serde_json::from_str(&content).map_err(|e| {
+				ErrorKind::Format(format!(
+					"Unable to parse QR parts from {:?}, {}",
+					file_name, e
+				))
+			})?;
+			Some(state)
+		} else {
+			None
+		};
+		Ok(Self { dir, state })
+	}
+
+	fn state_file(dir: &PathBuf, slate_id: &Uuid) -> PathBuf {
+		dir.join(format!("{}.qrparts", slate_id))
+	}
+
+	/// Slate that is assembled
+	pub fn slate_id(&self) -> Option<Uuid> {
+		self.state.as_ref().map(|s| s.slate_id.clone())
+	}
+
+	/// Add the scanned part. Parts can come in any order, duplicates are ignored.
+	/// Parts from another slate or with inconsistent data are rejected.
+	pub fn add_part(&mut self, part: &str) -> Result<(), Error> {
+		let part = QrPart::parse(part)?;
+
+		let state = self.state.get_or_insert_with(|| QrAssemblyState {
+			slate_id: part.slate_id.clone(),
+			total: part.total,
+			parts: BTreeMap::new(),
+		});
+		if state.slate_id != part.slate_id {
+			return Err(ErrorKind::QrPart(format!(
+				"part belongs to slate {}, expected slate {}",
+				part.slate_id, state.slate_id
+			))
+			.into());
+		}
+		if state.total != part.total {
+			return Err(ErrorKind::QrPart(format!(
+				"slate {} has {} parts, but the part claims {}",
+				state.slate_id, state.total, part.total
+			))
+			.into());
+		}
+		if let Some(payload) = state.parts.get(&part.index) {
+			if *payload != part.payload {
+				return Err(ErrorKind::QrPart(format!(
+					"part {} of slate {} doesn't match the previously scanned one",
+					part.index, state.slate_id
+				))
+				.into());
+			}
+			return Ok(());
+		}
+		state.parts.insert(part.index, part.payload);
+
+		self.save()
+	}
+
+	fn save(&self) -> Result<(), Error> {
+		if let Some(state) = &self.state {
+			fs::create_dir_all(&self.dir).map_err(|e| {
+				ErrorKind::IO(format!("Unable to create directory {:?}, {}", self.dir, e))
+			})?;
+			let file_name = Self::state_file(&self.dir, &state.slate_id);
+			let content = serde_json::to_string(state)
+				.map_err(|e| ErrorKind::Format(format!("Unable to serialize QR parts, {}", e)))?;
+			fs::write(&file_name, content).map_err(|e| {
+				ErrorKind::IO(format!("Unable to save QR parts to {:?}, {}", file_name, e))
+			})?;
+		}
+		Ok(())
+	}
+
+	/// Indexes of the parts that are not received yet. Empty if nothing was received.
+	pub fn missing_parts(&self) -> Vec<usize> {
+		match &self.state {
+			Some(state) => (1..=state.total)
+				.filter(|i| !state.parts.contains_key(i))
+				.collect(),
+			None => vec![],
+		}
+	}
+
+	/// True if all parts are received
+	pub fn is_complete(&self) -> bool {
+		self.state.is_some() && self.missing_parts().is_empty()
+	}
+
+	/// Assemble the slate data. The stored parts are deleted because they are not needed any more.
+	pub fn assemble(&mut self) -> Result<String, Error> {
+		let state = match &self.state {
+			Some(state) => state,
+			None => return Err(ErrorKind::QrPart("no parts received".to_string()).into()),
+		};
+		let missing = self.missing_parts();
+		if !missing.is_empty() {
+			return Err(ErrorKind::QrPart(format!(
+				"slate {} is missing parts {:?}",
+				state.slate_id, missing
+			))
+			.into());
+		}
+		let data: String = state.parts.values().map(|p| p.as_str()).collect();
+		let file_name = Self::state_file(&self.dir, &state.slate_id);
+		if file_name.exists() {
+			fs::remove_file(&file_name).map_err(|e| {
+				ErrorKind::IO(format!("Unable to delete QR parts {:?}, {}", file_name, e))
+			})?;
+		}
+		self.state = None;
+		Ok(data)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn test_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join("mwc_wallet_qr_test").join(name);
+		let _ = fs::remove_dir_all(&dir);
+		dir
+	}
+
+	fn test_data() -> String {
+		(0..100)
+			.map(|i| format!("{{\"field{}\":{}}}", i, i))
+			.collect()
+	}
+
+	#[test]
+	fn test_qr_out_of_order() {
+		let slate_id = Uuid::new_v4();
+		let data = test_data();
+		let mut parts = split_into_qr_parts(&slate_id, &data, 150);
+		assert!(parts.len() > 3);
+		parts.reverse();
+
+		let mut assembler = QrAssembler::new(test_dir("out_of_order"));
+		for (i, part) in parts.iter().enumerate() {
+			assert!(!assembler.is_complete());
+			assembler.add_part(part).unwrap();
+			// duplicates are fine
+			assembler.add_part(part).unwrap();
+			assert_eq!(assembler.missing_parts().len(), parts.len() - i - 1);
+		}
+		assert!(assembler.is_complete());
+		assert_eq!(assembler.assemble().unwrap(), data);
+	}
+
+	#[test]
+	fn test_qr_partial_and_resume() {
+		let dir = test_dir("resume");
+		let slate_id = Uuid::new_v4();
+		let data = test_data();
+		let parts = split_into_qr_parts(&slate_id, &data, 100);
+		let total = parts.len();
+
+		// Scan everything except the part 3, then interrupt
+		{
+			let mut assembler = QrAssembler::new(dir.clone());
+			for (i, part) in parts.iter().enumerate() {
+				if i != 2 {
+					assembler.add_part(part).unwrap();
+				}
+			}
+			assert_eq!(assembler.missing_parts(), vec![3]);
+			assert!(assembler.assemble().is_err());
+		}
+
+		let mut assembler = QrAssembler::resume(dir.clone(), &slate_id).unwrap();
+		assert_eq!(assembler.slate_id(), Some(slate_id));
+		assert_eq!(assembler.missing_parts(), vec![3]);
+
+		// Part from another slate is rejected
+		let other = split_into_qr_parts(&Uuid::new_v4(), &data, 100);
+		assert!(assembler.add_part(&other[2]).is_err());
+		// Corrupted part is rejected
+		let corrupted = parts[2].replace("field", "fie1d");
+		assert!(assembler.add_part(&corrupted).is_err());
+		// Part with inconsistent total is rejected
+		let inconsistent = QrPart {
+			slate_id: slate_id.clone(),
+			index: 3,
+			total: total + 1,
+			payload: "{}".to_string(),
+		}
+		.to_qr_string();
+		assert!(assembler.add_part(&inconsistent).is_err());
+		assert_eq!(assembler.missing_parts(), vec![3]);
+
+		assembler.add_part(&parts[2]).unwrap();
+		assert_eq!(assembler.assemble().unwrap(), data);
+		// Assembled, nothing to resume any more
+		let assembler = QrAssembler::resume(dir, &slate_id).unwrap();
+		assert_eq!(assembler.slate_id(), None);
+	}
+}
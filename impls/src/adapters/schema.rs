@@ -0,0 +1,255 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of the slate JSON against the bundled JSON Schema. It is for the interop testing
+//! with the third party wallets, errors point to the exact field. Validation is disabled by default.
+//! Only the schema keywords that the bundled schemas use are supported:
+//! type, required, properties, items, pattern, description.
+
+use crate::error::{Error, ErrorKind};
+use crate::util::RwLock;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const SLATE_V2_SCHEMA: &str = include_str!("schema/slate_v2.json");
+const SLATE_V3_SCHEMA: &str = include_str!("schema/slate_v3.json");
+const SLATE_V5_SCHEMA: &str = include_str!("schema/slate_v5.json");
+
+lazy_static! {
+	static ref SLATE_SCHEMA_VALIDATION: RwLock<bool> = RwLock::new(false);
+	static ref SLATE_V2: Result<Schema, String> = Schema::new(SLATE_V2_SCHEMA);
+	static ref SLATE_V3: Result<Schema, String> = Schema::new(SLATE_V3_SCHEMA);
+	static ref SLATE_V5: Result<Schema, String> = Schema::new(SLATE_V5_SCHEMA);
+}
+
+/// Bundled schema with its patterns compiled
+struct Schema {
+	root: Value,
+	patterns: HashMap<String, Regex>,
+}
+
+impl Schema {
+	fn new(json: &str) -> Result<Schema, String> {
+		let root: Value =
+			serde_json::from_str(json).map_err(|e| format!("invalid bundled schema, {}", e))?;
+		let mut patterns = HashMap::new();
+		collect_patterns(&root, &mut patterns)?;
+		Ok(Schema { root, patterns })
+	}
+}
+
+fn collect_patterns(schema: &Value, patterns: &mut HashMap<String, Regex>) -> Result<(), String> {
+	match schema {
+		Value::Object(obj) => {
+			for (key, value) in obj {
+				match (key.as_str(), value) {
+					("pattern", Value::String(pattern)) => {
+						if !patterns.contains_key(pattern) {
+							let re = Regex::new(pattern)
+								.map_err(|e| format!("invalid pattern {}, {}", pattern, e))?;
+							patterns.insert(pattern.clone(), re);
+						}
+					}
+					_ => collect_patterns(value, patterns)?,
+				}
+			}
+		}
+		Value::Array(arr) => {
+			for value in arr {
+				collect_patterns(value, patterns)?;
+			}
+		}
+		_ => (),
+	}
+	Ok(())
+}
+
+/// Enable or disable the schema validation of the slates that are read from the files
+pub fn set_slate_schema_validation(enabled: bool) {
+	*SLATE_SCHEMA_VALIDATION.write() = enabled;
+}
+
+/// True if the slates are validated against the schema
+pub fn is_slate_schema_validation() -> bool {
+	*SLATE_SCHEMA_VALIDATION.read()
+}
+
+/// Validate the slate JSON against the schema of its version. Slates of the versions
+/// without schema are not validated.
+pub fn validate_slate_json(json: &str) -> Result<(), Error> {
+	let value: Value = serde_json::from_str(json)
+		.map_err(|e| ErrorKind::SlateSchema(format!("slate is not a valid JSON, {}", e)))?;
+	let schema = match value["version_info"]["version"].as_u64() {
+		Some(2) => &*SLATE_V2,
+		Some(3) => &*SLATE_V3,
+		Some(5) => &*SLATE_V5,
+		_ => return Ok(()),
+	};
+	let schema = schema
+		.as_ref()
+		.map_err(|e| ErrorKind::SlateSchema(e.clone()))?;
+	validate(schema, &schema.root, &value, "").map_err(|e| ErrorKind::SlateSchema(e).into())
+}
+
+fn field_path(path: &str, name: &str) -> String {
+	if path.is_empty() {
+		name.to_string()
+	} else {
+		format!("{}.{}", path, name)
+	}
+}
+
+fn type_matches(value: &Value, tp: &str) -> bool {
+	match tp {
+		"object" => value.is_object(),
+		"array" => value.is_array(),
+		"string" => value.is_string(),
+		"integer" => value.is_u64(),
+		"boolean" => value.is_boolean(),
+		"null" => value.is_null(),
+		_ => false,
+	}
+}
+
+fn validate(bundle: &Schema, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+	let expected = || match schema["description"].as_str() {
+		Some(descr) => format!("field '{}' must be {}", path, descr),
+		None => format!("field '{}' must be {}", path, schema["type"]),
+	};
+
+	if let Some(tp) = schema.get("type") {
+		let types: Vec<&str> = match tp {
+			Value::Array(types) => types.iter().filter_map(|t| t.as_str()).collect(),
+			t => t.as_str().into_iter().collect(),
+		};
+		if !types.iter().any(|t| type_matches(value, t)) {
+			return Err(expected());
+		}
+	}
+
+	if let (Some(pattern), Some(s)) = (schema["pattern"].as_str(), value.as_str()) {
+		let re = bundle
+			.patterns
+			.get(pattern)
+			.ok_or_else(|| format!("pattern {} is not compiled", pattern))?;
+		if !re.is_match(s) {
+			return Err(expected());
+		}
+	}
+
+	if let Some(obj) = value.as_object() {
+		if let Some(required) = schema["required"].as_array() {
+			for name in required.iter().filter_map(|n| n.as_str()) {
+				if !obj.contains_key(name) {
+					return Err(format!("field '{}' is missing", field_path(path, name)));
+				}
+			}
+		}
+		if let Some(properties) = schema["properties"].as_object() {
+			for (name, prop_schema) in properties {
+				if let Some(prop) = obj.get(name) {
+					validate(bundle, prop_schema, prop, &field_path(path, name))?;
+				}
+			}
+		}
+	}
+
+	if let (Some(items), Some(arr)) = (schema.get("items"), value.as_array()) {
+		for (i, item) in arr.iter().enumerate() {
+			validate(bundle, items, item, &format!("{}[{}]", path, i))?;
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::libwallet::{Slate, SlateVersion, VersionedSlate};
+
+	fn slate_json(version: SlateVersion) -> Value {
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		slate.fee = 8_000_000;
+		if version == SlateVersion::V2 {
+			slate.version_info.version = 2;
+			slate.version_info.orig_version = 2;
+		}
+		serde_json::to_value(&VersionedSlate::into_version(slate, version)).unwrap()
+	}
+
+	fn schema_error(json: &Value) -> String {
+		match validate_slate_json(&json.to_string()).unwrap_err().kind() {
+			ErrorKind::SlateSchema(msg) => msg,
+			e => panic!("Unexpected error {}", e),
+		}
+	}
+
+	#[test]
+	fn test_slate_schema_validation() {
+		for version in &[SlateVersion::V2, SlateVersion::V3, SlateVersion::V5] {
+			let json = slate_json(version.clone());
+			validate_slate_json(&json.to_string()).unwrap();
+
+			// Missing required field
+			let mut missing = json.clone();
+			missing.as_object_mut().unwrap().remove("amount");
+			assert_eq!(schema_error(&missing), "field 'amount' is missing");
+
+			// Amount type
+			let mut wrong = json.clone();
+			wrong["amount"] = Value::from(-1);
+			let expected = match version {
+				SlateVersion::V5 => "field 'amount' must be a u64",
+				_ => "field 'amount' must be a u64 or string-encoded u64",
+			};
+			assert_eq!(schema_error(&wrong), expected);
+
+			// Nested field
+			let mut nested = json.clone();
+			nested["tx"]["body"]
+				.as_object_mut()
+				.unwrap()
+				.remove("kernels");
+			assert_eq!(schema_error(&nested), "field 'tx.body.kernels' is missing");
+			let mut nested = json.clone();
+			nested["tx"]["offset"] = Value::from("not a hex");
+			assert_eq!(
+				schema_error(&nested),
+				"field 'tx.offset' must be a hex string"
+			);
+
+			if *version == SlateVersion::V5 {
+				let mut missing = json.clone();
+				missing.as_object_mut().unwrap().remove("compact");
+				assert_eq!(schema_error(&missing), "field 'compact' is missing");
+				continue;
+			}
+
+			// string_or_u64 fields accept both the string and the numeric values
+			let mut numeric = json.clone();
+			numeric["amount"] = Value::from(1_000_000_000u64);
+			numeric["ttl_cutoff_height"] = Value::from(100u64);
+			validate_slate_json(&numeric.to_string()).unwrap();
+			let mut wrong = json.clone();
+			wrong["fee"] = Value::from("8e6");
+			assert_eq!(
+				schema_error(&wrong),
+				"field 'fee' must be a u64 or string-encoded u64"
+			);
+		}
+	}
+}
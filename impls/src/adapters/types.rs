@@ -49,8 +49,8 @@ pub trait SubscriptionHandler: Send {
 	fn on_close(&self, result: CloseReason);
 	fn on_dropped(&self);
 	fn on_reestablished(&self);
-	// process swap message and return the message to respond
-	fn on_swap_message(&self, swap: Message) -> Option<Message>;
+	// process swap message and return the message to respond. from is verified by the transport
+	fn on_swap_message(&self, from: &dyn Address, swap: Message) -> Option<Message>;
 
 	fn set_notification_channels(&self, slate_id: &uuid::Uuid, slate_send_channel: Sender<Slate>);
 	fn reset_notification_channels(&self, slate_id: &uuid::Uuid);
@@ -31,8 +31,8 @@ use crate::store::{self, option_to_not_found, to_key, to_key_u64, u64_to_key};
 use crate::core::core::Transaction;
 use crate::core::ser;
 use crate::libwallet::{
-	AcctPathMapping, Context, Error, ErrorKind, NodeClient, OutputData, ScannedBlockInfo,
-	TxLogEntry, TxProof, WalletBackend, WalletOutputBatch,
+	AcctPathMapping, Context, Error, ErrorKind, NodeClient, OutputData, ScannedBlockInfo, Slate,
+	SlateVersion, TxLogEntry, TxProof, VersionedSlate, WalletBackend, WalletOutputBatch,
 };
 use crate::util::secp::constants::SECRET_KEY_SIZE;
 use crate::util::secp::key::SecretKey;
@@ -409,6 +409,37 @@ where
 			.or_else(|_| get_stored_tx_by_uuid_ext(uuid, "grintx"))
 	}
 
+	fn store_slate(&self, uuid: &str, slate: &Slate) -> Result<(), Error> {
+		let filename = format!("{}.mwcslate", uuid);
+		let path = path::Path::new(&self.data_file_dir)
+			.join(TX_SAVE_DIR)
+			.join(filename);
+		let slate_json = serde_json::to_string(&VersionedSlate::into_version(
+			slate.clone(),
+			SlateVersion::V3,
+		))
+		.map_err(|e| {
+			ErrorKind::StoredTransactionError(format!("Unable to store the slate, {}", e))
+		})?;
+		let mut stored_slate = File::create(path)?;
+		stored_slate.write_all(slate_json.as_bytes())?;
+		stored_slate.sync_all()?;
+		Ok(())
+	}
+
+	fn get_stored_slate(&self, uuid: &str) -> Result<Option<Slate>, Error> {
+		let filename = format!("{}.mwcslate", uuid);
+		let path = path::Path::new(&self.data_file_dir)
+			.join(TX_SAVE_DIR)
+			.join(filename);
+		if !path.exists() {
+			return Ok(None);
+		}
+		let mut content = String::new();
+		File::open(path)?.read_to_string(&mut content)?;
+		Ok(Some(Slate::deserialize_upgrade(&content)?))
+	}
+
 	fn load_stored_tx(&self, path: &str) -> Result<Transaction, Error> {
 		let tx_file = Path::new(&path).to_path_buf();
 		let mut tx_f = File::open(tx_file)?;
@@ -48,6 +48,8 @@ pub enum ErrorKind {
 	RequestError(String),
 	#[fail(display = "ResponseError error: {}", _0)]
 	ResponseError(String),
+	#[fail(display = "Timeout error: {}", _0)]
+	Timeout(String),
 }
 
 impl Fail for Error {
@@ -70,6 +72,14 @@ impl Error {
 	pub fn _kind(&self) -> &ErrorKind {
 		self.inner.get_context()
 	}
+
+	/// True if the request was not completed in time
+	pub fn is_timeout(&self) -> bool {
+		match self.inner.get_context() {
+			ErrorKind::Timeout(_) => true,
+			_ => false,
+		}
+	}
 }
 
 impl From<ErrorKind> for Error {
@@ -95,6 +105,8 @@ pub struct Client {
 	https_client: Arc<Option<hyper::Client<TimeoutConnector<hyper_rustls::HttpsConnector<HttpConnector>>>>>,
 	/// Socks proxy client
 	socks_client: Arc<Option<hyper::Client<TimeoutConnector<hyper_socks2::SocksConnector<hyper_rustls::HttpsConnector<HttpConnector>>>>>>,
+	/// Limit for the whole request. None - only the connector timeouts are applied
+	timeout: Option<Duration>,
 }
 
 impl Client {
@@ -104,9 +116,20 @@ impl Client {
 		Ok(Client {
 			https_client: Arc::new(https_client),
 			socks_client: Arc::new(socks_client),
+			timeout: None,
 		})
 	}
 
+	/// Limit the time of the whole request, including the response reading
+	pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+		self.timeout = timeout;
+	}
+
+	/// Current limit for the whole request
+	pub fn get_timeout(&self) -> Option<Duration> {
+		self.timeout
+	}
+
 	fn construct_client(use_socks: bool, socks_proxy_addr: Option<SocketAddr>) ->
 									Result< (Option<hyper::Client<TimeoutConnector<hyper_rustls::HttpsConnector<HttpConnector>>>>,
 										Option<hyper::Client<TimeoutConnector<hyper_socks2::SocksConnector<hyper_rustls::HttpsConnector<HttpConnector>>>>>), Error> {
@@ -388,6 +411,7 @@ impl Client {
 
 	pub fn send_request(&self, req: Request<Body>) -> Result<String, Error> {
 		let task = self.send_request_async(req);
+		let timeout = self.timeout;
 		scope(|s| {
 			let handle = s.spawn(|_| {
 				let mut rt = Builder::new()
@@ -397,7 +421,19 @@ impl Client {
 					.map_err(|e| {
 						ErrorKind::Internal(format!("can't create Tokio runtime, {}", e))
 					})?;
-				rt.block_on(task)
+				match timeout {
+					Some(timeout) => rt.block_on(async {
+						match tokio::time::timeout(timeout, task).await {
+							Ok(res) => res,
+							Err(_) => Err(ErrorKind::Timeout(format!(
+								"request is not completed in {} ms",
+								timeout.as_millis()
+							))
+							.into()),
+						}
+					}),
+					None => rt.block_on(task),
+				}
 			});
 			/*match handle.join() {
 				Ok(_) => Ok("Request successfully sent".to_string()),
@@ -145,6 +145,18 @@ pub enum ErrorKind {
 
 	#[fail(display = "proof address mismatch {}, {}!", _0, _1)]
 	ProofAddressMismatch(String, String),
+
+	/// Multi part QR slate error
+	#[fail(display = "Invalid QR part, {}", _0)]
+	QrPart(String),
+
+	/// Wallet policy requires the payment proof, but the slate doesn't have it
+	#[fail(display = "Payment proof is required by the wallet policy, {}", _0)]
+	PaymentProofRequired(String),
+
+	/// Slate JSON doesn't match the schema
+	#[fail(display = "Slate doesn't match the schema, {}", _0)]
+	SlateSchema(String),
 }
 
 impl Fail for Error {
@@ -47,11 +47,12 @@ pub mod tor;
 
 pub use crate::adapters::{
 	create_sender,
-	get_mwcmqs_brocker, init_mwcmqs_access_data,
+	get_mwcmqs_brocker, init_mwcmqs_access_data, split_into_qr_parts,
 	Address, AddressType, CloseReason,
 	HttpDataSender, HttpsAddress,
 	MWCMQPublisher, MWCMQSAddress, MWCMQSubscriber, MwcMqsChannel, PathToSlate, Publisher,
-	SlateGetter, SlatePutter, SlateReceiver, SlateSender, Subscriber, SubscriptionHandler,
+	QrAssembler, QrPart,
+	SlateCodec, SlateGetter, SlatePutter, SlateReceiver, SlateSender, Subscriber, SubscriptionHandler,
 	SwapMessageSender,
 };
 pub use crate::backends::{wallet_db_exists, LMDBBackend};
@@ -45,6 +45,11 @@ lazy_static! {
 	static ref NODE_CALL_DELAY: Vec<u64> = vec![1000, 1000];
 }
 
+// Node reports the unknown kernel with the NotFound error in the call result
+fn is_not_found(resp: &Response) -> bool {
+	resp.error.is_none() && resp.result.as_ref().map(|r| r["Err"] == "NotFound").unwrap_or(false)
+}
+
 // cashed values are stored by the key K
 #[derive(Clone)]
 struct CachedValue<K, T> {
@@ -123,6 +128,18 @@ impl HTTPNodeClient {
 		method: &str,
 		params: &serde_json::Value,
 		counter: i32
+	) -> Result<D, libwallet::Error> {
+		self.send_json_request_impl(method, params, counter, libwallet::ErrorKind::ClientCallback)
+	}
+
+	/// Error that the node responded with is built by node_error. The caller can tell it from
+	/// the connection problems, those are reported as ClientCallback or NodeTimeout.
+	fn send_json_request_impl<D: serde::de::DeserializeOwned>(
+		&self,
+		method: &str,
+		params: &serde_json::Value,
+		counter: i32,
+		node_error: fn(String) -> libwallet::ErrorKind,
 	) -> Result<D, libwallet::Error> {
 		let url = format!("{}{}", self.node_url(), ENDPOINT);
 		let req = build_request(method, params);
@@ -130,10 +147,16 @@ impl HTTPNodeClient {
 
 		match res {
 			Err(e) => {
+				// Retry doesn't make sense, the caller doesn't want to wait longer
+				if e.is_timeout() {
+					let report = format!("Calling {} at {}, {}", method, self.node_url, e);
+					error!("{}", report);
+					return Err(libwallet::ErrorKind::NodeTimeout(report).into());
+				}
 				if counter>0 {
 					debug!("Retrying to call Node API method {}: {}", method, e);
 					thread::sleep(Duration::from_millis(NODE_CALL_DELAY[(counter-1) as usize]));
-					return self.send_json_request(method, params, counter-1);
+					return self.send_json_request_impl(method, params, counter-1, node_error);
 				}
 				let report = format!("Error calling {}: {}", method, e);
 				error!("{}", report);
@@ -145,13 +168,13 @@ impl HTTPNodeClient {
 					if counter>0 {
 						debug!("Retrying to call Node API method {}: {}", method, e);
 						thread::sleep(Duration::from_millis(NODE_CALL_DELAY[(counter-1) as usize]));
-						return self.send_json_request(method, params, counter-1);
+						return self.send_json_request_impl(method, params, counter-1, node_error);
 					}
 					error!("{:?}", inner);
 					// error message is likely what user want to see...
 					let report = format!("{}", e);
 					error!("{}", report);
-					Err(libwallet::ErrorKind::ClientCallback(report).into())
+					Err(node_error(report).into())
 				}
 			},
 		}
@@ -207,13 +230,13 @@ impl HTTPNodeClient {
 				error!("{}", report);
 				Err(libwallet::ErrorKind::ClientCallback(report).into())
 			}
-			Ok(inner) => match inner.clone().into_result::<LocatedTxKernel>() {
-				Ok(r) => Ok(Some((r.tx_kernel, r.height, r.mmr_index))),
-				Err(e) => {
-					let contents = format!("{:?}", inner);
-					if contents.contains("NotFound") {
-						Ok(None)
-					} else {
+			Ok(inner) => {
+				if is_not_found(&inner) {
+					return Ok(None);
+				}
+				match inner.into_result::<LocatedTxKernel>() {
+					Ok(r) => Ok(Some((r.tx_kernel, r.height, r.mmr_index))),
+					Err(e) => {
 						let report = format!("Unable to parse response for {}: {}", method, e);
 						error!("{}", report);
 						Err(libwallet::ErrorKind::ClientCallback(report).into())
@@ -223,6 +246,73 @@ impl HTTPNodeClient {
 		}
 	}
 
+	/// Request the chain tip and the kernels as a single JSON-RPC batch
+	fn get_tip_and_kernels_batch(
+		&self,
+		kernels: &[(pedersen::Commitment, Option<u64>)],
+		counter: i32,
+	) -> Result<(u64, Vec<Option<(TxKernel, u64, u64)>>), libwallet::Error> {
+		let url = format!("{}{}", self.node_url(), ENDPOINT);
+		let tip_params = serde_json::Value::Null;
+		let kernel_params: Vec<serde_json::Value> = kernels.iter()
+			.map(|(excess, min_height)| json!([to_hex(excess.0.to_vec()), min_height, None::<u64>]))
+			.collect();
+
+		// Tip has id 1, kernels are going after it
+		let mut req = vec![build_request("get_tip", &tip_params)];
+		for (i, params) in kernel_params.iter().enumerate() {
+			let mut r = build_request("get_kernel", params);
+			r.id = From::from((i + 2) as u64);
+			req.push(r);
+		}
+
+		let res = match self.client.post::<Vec<Request>, Vec<Response>>(url.as_str(), self.node_api_secret(), &req) {
+			Ok(res) => res,
+			Err(e) => {
+				let report = format!("Calling get_tip and get_kernel batch at {}, {}", self.node_url, e);
+				// Retry doesn't make sense, the caller doesn't want to wait longer
+				if e.is_timeout() {
+					error!("{}", report);
+					return Err(libwallet::ErrorKind::NodeTimeout(report).into());
+				}
+				if counter>0 {
+					debug!("Retry to call get_tip and get_kernel batch, {}", e);
+					thread::sleep(Duration::from_millis(NODE_CALL_DELAY[(counter-1) as usize]));
+					return self.get_tip_and_kernels_batch(kernels, counter-1);
+				}
+				return Err(libwallet::ErrorKind::ClientCallback(report).into());
+			}
+		};
+		let mut responses: HashMap<u64, Response> = res.into_iter()
+			.filter_map(|r| r.id.as_u64().map(|id| (id, r)))
+			.collect();
+
+		let tip = responses.remove(&1)
+			.ok_or(libwallet::ErrorKind::ClientCallback("No get_tip respond in the batch".to_string()))?
+			.into_result::<GetTipResp>()
+			.map_err(|e| libwallet::ErrorKind::ClientCallback(format!("Unable to parse get_tip respond, {}", e)))?;
+
+		let mut found = Vec::with_capacity(kernels.len());
+		for i in 0..kernels.len() {
+			let resp = responses.remove(&((i + 2) as u64))
+				.ok_or(libwallet::ErrorKind::ClientCallback("No get_kernel respond in the batch".to_string()))?;
+			if is_not_found(&resp) {
+				found.push(None);
+				continue;
+			}
+			match resp.into_result::<LocatedTxKernel>() {
+				Ok(r) => found.push(Some((r.tx_kernel, r.height, r.mmr_index))),
+				Err(e) => {
+					let report = format!("Unable to parse response for get_kernel: {}", e);
+					return Err(libwallet::ErrorKind::ClientCallback(report).into());
+				}
+			}
+		}
+
+		self.chain_tip.set_value(0, (tip.height, tip.last_block_pushed, tip.total_difficulty));
+		Ok((tip.height, found))
+	}
+
 	/// Retrieve outputs from node
 	/// Result value: Commit, Height, MMR
 	fn get_outputs_from_node_impl(
@@ -290,6 +380,7 @@ impl HTTPNodeClient {
 			task.try_collect().await
 		};
 
+		let timeout = self.client.get_timeout();
 		let res = scope(|s| {
 			let handle = s.spawn(|_| {
 				let mut rt = Builder::new()
@@ -297,13 +388,26 @@ impl HTTPNodeClient {
 					.enable_all()
 					.build()
 					.unwrap();
-				let res: Result<Vec<Response>, _> = rt.block_on(task);
+				// None if the call is not completed in time
+				let res: Option<Result<Vec<Response>, _>> = match timeout {
+					Some(timeout) => rt.block_on(async { tokio::time::timeout(timeout, task).await.ok() }),
+					None => Some(rt.block_on(task)),
+				};
 				res
 			});
 			handle.join().unwrap()
 		})
 		.unwrap();
 
+		let res = match res {
+			Some(res) => res,
+			None => {
+				let report = format!("Outputs by id are not received from {} in time", self.node_url);
+				error!("{}", report);
+				return Err(libwallet::ErrorKind::NodeTimeout(report).into());
+			}
+		};
+
 		let results: Vec<OutputPrintable> = match res {
 			Ok(resps) => {
 				let mut results = vec![];
@@ -379,6 +483,10 @@ impl NodeClient for HTTPNodeClient {
 		self.block_info.clean();
 	}
 
+	fn set_call_timeout(&mut self, timeout: Option<Duration>) {
+		self.client.set_timeout(timeout);
+	}
+
 	fn get_version_info(&mut self) -> Option<NodeVersionInfo> {
 		if let Some(v) = self.node_version_info.as_ref() {
 			return Some(v.clone());
@@ -418,10 +526,27 @@ impl NodeClient for HTTPNodeClient {
 	/// Posts a transaction to a grin node
 	fn post_tx(&self, tx: &Transaction, fluff: bool) -> Result<(), libwallet::Error> {
 		let params = json!([tx, fluff]);
-		self.send_json_request::<serde_json::Value>("push_transaction", &params, NODE_CALL_RETRY)?;
+		// Node respond error means that the transaction is not accepted
+		self.send_json_request_impl::<serde_json::Value>(
+			"push_transaction",
+			&params,
+			NODE_CALL_RETRY,
+			libwallet::ErrorKind::TxRejected,
+		)?;
 		Ok(())
 	}
 
+	fn get_pool_size(&self) -> Option<u64> {
+		match self.send_json_request::<u64>("get_pool_size", &serde_json::Value::Null, 1) {
+			Ok(size) => Some(size),
+			Err(e) => {
+				// Old nodes don't have this API
+				debug!("Unable to get pool size from {}, {}", self.node_url, e);
+				None
+			}
+		}
+	}
+
 	/// Return the chain tip from a given node
 	fn get_chain_tip(&self) -> Result<(u64, String, u64), libwallet::Error> {
 		if let Some(tip) = self.chain_tip.get_value(&0) {
@@ -478,6 +603,29 @@ impl NodeClient for HTTPNodeClient {
 		self.get_kernel_impl(excess, min_height, max_height, NODE_CALL_RETRY)
 	}
 
+	/// Chain tip and kernels with a single batch request. If the node can't process the batch,
+	/// the data is requested call by call.
+	fn get_tip_and_kernels(
+		&self,
+		kernels: &[(pedersen::Commitment, Option<u64>)],
+	) -> Result<(u64, Vec<Option<(TxKernel, u64, u64)>>), libwallet::Error> {
+		match self.get_tip_and_kernels_batch(kernels, NODE_CALL_RETRY) {
+			Ok(res) => Ok(res),
+			Err(e) => {
+				if let libwallet::ErrorKind::NodeTimeout(_) = e.kind() {
+					return Err(e);
+				}
+				debug!("Batch request failed, requesting the tip and kernels one by one, {}", e);
+				let height = self.get_chain_tip()?.0;
+				let mut found = Vec::with_capacity(kernels.len());
+				for (excess, min_height) in kernels {
+					found.push(self.get_kernel(excess, *min_height, None)?);
+				}
+				Ok((height, found))
+			}
+		}
+	}
+
 	/// Retrieve outputs from node
 	/// Result value: Commit, Height, MMR
 	fn get_outputs_from_node(
@@ -636,11 +784,15 @@ mod tests {
 	use crate::HTTPNodeClient;
 	use std::thread::JoinHandle;
 	use grin_wallet_libwallet::NodeClient;
-	use std::time::Instant;
+	use std::time::{Duration, Instant};
 	use crate::util::secp::pedersen::Commitment;
 	use crate::libwallet;
 	use crate::util;
 	use crate::core::global;
+	use crate::core::core::TxKernel;
+	use crate::api::LocatedTxKernel;
+	use std::io::{Read, Write};
+	use std::net::{TcpListener, TcpStream};
 
 	// Let's do a stress test for the Node.
 	// Normally test is ignoting because the point of that test to run it manually and review the results.
@@ -766,4 +918,156 @@ mod tests {
 
 		Ok(())
 	}
+
+	// Read the whole http request: headers and the body with Content-Length size
+	fn read_http_request(stream: &mut TcpStream) -> String {
+		let mut data: Vec<u8> = Vec::new();
+		let mut buf = [0u8; 4096];
+		loop {
+			let sz = stream.read(&mut buf).unwrap();
+			if sz == 0 {
+				break;
+			}
+			data.extend_from_slice(&buf[..sz]);
+			let text = String::from_utf8_lossy(&data).to_string();
+			if let Some(pos) = text.find("\r\n\r\n") {
+				let content_length = text[..pos]
+					.lines()
+					.filter_map(|l| {
+						let l = l.to_lowercase();
+						if l.starts_with("content-length:") {
+							l["content-length:".len()..].trim().parse::<usize>().ok()
+						} else {
+							None
+						}
+					})
+					.next()
+					.unwrap_or(0);
+				if data.len() >= pos + 4 + content_length {
+					return text[pos + 4..].to_string();
+				}
+			}
+		}
+		String::from_utf8_lossy(&data).to_string()
+	}
+
+	// JSON-RPC listener that serves one connection per respond. None respond drops the connection
+	// without the answer. Every answer is sent after the delay, so the node can be slow.
+	fn json_rpc_listener(
+		responses: Vec<Option<serde_json::Value>>,
+		delay: Duration,
+	) -> (String, thread::JoinHandle<Vec<String>>) {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+		let handle = thread::spawn(move || {
+			let mut requests = Vec::new();
+			for resp in responses {
+				let (mut stream, _) = listener.accept().unwrap();
+				requests.push(read_http_request(&mut stream));
+				thread::sleep(delay);
+				if let Some(resp) = resp {
+					let body = resp.to_string();
+					let reply = format!(
+						"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+						body.len(),
+						body
+					);
+					// Client might be gone because of the timeout
+					let _ = stream.write_all(reply.as_bytes());
+				}
+			}
+			requests
+		});
+		(url, handle)
+	}
+
+	fn tip_and_kernels_respond(third_kernel: serde_json::Value) -> serde_json::Value {
+		let kernel = LocatedTxKernel {
+			tx_kernel: TxKernel::empty(),
+			height: 95,
+			mmr_index: 7,
+		};
+		json!([
+			{"jsonrpc": "2.0", "id": 1, "result": {"Ok": {
+				"height": 100,
+				"last_block_pushed": "0a",
+				"prev_block_to_last": "0b",
+				"total_difficulty": 1000
+			}}},
+			{"jsonrpc": "2.0", "id": 2, "result": {"Ok": serde_json::to_value(&kernel).unwrap()}},
+			{"jsonrpc": "2.0", "id": 3, "result": third_kernel},
+		])
+	}
+
+	#[test]
+	fn test_tip_and_kernels_batch() {
+		global::set_mining_mode(global::ChainTypes::AutomatedTesting);
+
+		let kernels = vec![
+			(Commitment::from_vec(vec![8u8; 33]), Some(90)),
+			(Commitment::from_vec(vec![9u8; 33]), None),
+		];
+
+		// First connection is dropped, the batch is retried
+		let (url, handle) = json_rpc_listener(vec![
+			None,
+			Some(tip_and_kernels_respond(json!({"Err": "NotFound"}))),
+		], Duration::from_millis(0));
+		let client = HTTPNodeClient::new(&url, None).unwrap();
+		let (height, found) = client.get_tip_and_kernels(&kernels).unwrap();
+		assert_eq!(height, 100);
+		assert_eq!(found.len(), 2);
+		let (_, kernel_height, mmr_index) = found[0].clone().unwrap();
+		assert_eq!((kernel_height, mmr_index), (95, 7));
+		assert!(found[1].is_none());
+
+		let requests = handle.join().unwrap();
+		assert_eq!(requests.len(), 2);
+		let batch: serde_json::Value = serde_json::from_str(&requests[1]).unwrap();
+		assert_eq!(batch[0]["method"], "get_tip");
+		assert_eq!(batch[1]["method"], "get_kernel");
+		assert_eq!(batch[2]["method"], "get_kernel");
+
+		// Any other error from the node is not a missing kernel
+		let (url, handle) = json_rpc_listener(vec![Some(tip_and_kernels_respond(
+			json!({"Err": {"Internal": "db failure"}}),
+		))], Duration::from_millis(0));
+		let client = HTTPNodeClient::new(&url, None).unwrap();
+		assert!(client.get_tip_and_kernels_batch(&kernels, 0).is_err());
+		handle.join().unwrap();
+	}
+
+	#[test]
+	fn test_call_timeout_slow_node() {
+		global::set_mining_mode(global::ChainTypes::AutomatedTesting);
+
+		let tip = json!({"jsonrpc": "2.0", "id": 1, "result": {"Ok": {
+			"height": 100,
+			"last_block_pushed": "0a",
+			"prev_block_to_last": "0b",
+			"total_difficulty": 1000
+		}}});
+
+		// The node answers in 3 seconds, the call is limited with 300 ms
+		let (url, _handle) = json_rpc_listener(vec![Some(tip.clone())], Duration::from_secs(3));
+		let mut client = HTTPNodeClient::new(&url, None).unwrap();
+		client.set_call_timeout(Some(Duration::from_millis(300)));
+		let now = Instant::now();
+		match client.get_chain_tip() {
+			Err(e) => match e.kind() {
+				libwallet::ErrorKind::NodeTimeout(_) => (),
+				k => panic!("Expected NodeTimeout, got {}", k),
+			},
+			Ok(_) => panic!("Slow node call is expected to fail"),
+		}
+		// No retry for the timed out call
+		assert!(now.elapsed() < Duration::from_secs(2));
+
+		// Node answers in time, the limit doesn't break the call
+		let (url, handle) = json_rpc_listener(vec![Some(tip)], Duration::from_millis(100));
+		let mut client = HTTPNodeClient::new(&url, None).unwrap();
+		client.set_call_timeout(Some(Duration::from_secs(5)));
+		assert_eq!(client.get_chain_tip().unwrap().0, 100);
+		handle.join().unwrap();
+	}
 }
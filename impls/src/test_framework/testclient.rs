@@ -228,6 +228,7 @@ where
 				None,
 				None,
 				None,
+				None,
 				false,
 				false,
 			) {
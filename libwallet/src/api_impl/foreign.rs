@@ -18,14 +18,15 @@ use crate::api_impl::owner_swap;
 use crate::grin_keychain::Keychain;
 use crate::grin_util::secp::key::SecretKey;
 use crate::grin_util::Mutex;
-use crate::internal::{tx, updater};
+use crate::internal::{checkpoint, tx, updater};
 use crate::proof::proofaddress;
 use crate::proof::proofaddress::ProofAddressType;
 use crate::proof::proofaddress::ProvableAddress;
 use crate::slate_versions::SlateVersion;
+use crate::swap::config::SwapConfig;
 use crate::{
-	BlockFees, CbData, Error, ErrorKind, NodeClient, Slate, TxLogEntryType, VersionInfo,
-	WalletBackend, WalletInst, WalletLCProvider,
+	BlockFees, CbData, Error, ErrorKind, NodeClient, Slate, SwapAcceptArgs, TxLogEntryType,
+	VersionInfo, WalletBackend, WalletInst, WalletLCProvider,
 };
 use grin_core::core::amount_to_hr_string;
 use grin_wallet_util::OnionV3Address;
@@ -109,6 +110,7 @@ pub fn receive_tx<'a, T: ?Sized, C, K>(
 	address: Option<String>,
 	key_id_opt: Option<&str>,
 	output_amounts: Option<Vec<u64>>,
+	expected_amount: Option<u64>,
 	dest_acct_name: Option<&str>,
 	message: Option<String>,
 	use_test_rng: bool,
@@ -123,6 +125,10 @@ where
 	let slate_message = &slate.participant_data[0].message;
 	let mut address_for_logging = address.clone();
 
+	// Recipient that knows the amount is protected from the sender that changed it
+	if let Some(expected_amount) = expected_amount {
+		check_expected_amount(slate, expected_amount)?;
+	}
 	check_ttl(w, &slate, refresh_from_node)?;
 
 	if address.is_none() {
@@ -193,6 +199,9 @@ where
 		}
 	}
 
+	// Sender outputs can't be the outputs that we already have
+	tx::check_output_duplicates(&mut *w, &ret_slate)?;
+
 	let message = match message {
 		Some(mut m) => {
 			m.truncate(USER_MESSAGE_MAX_LEN);
@@ -206,6 +215,13 @@ where
 		None => 1,
 	};
 
+	// Bound slate can be processed only by the intended recipient
+	{
+		let keychain = w.keychain(keychain_mask)?;
+		ret_slate
+			.check_recipient_binding(&proofaddress::payment_proof_address_pubkey(&keychain)?)?;
+	}
+
 	// Note: key_id & output_amounts needed for secure claims, mwc713.
 	tx::add_output_to_slate(
 		&mut *w,
@@ -219,11 +235,17 @@ where
 		message,
 		false,
 		use_test_rng,
+		false,
+		false,
 		num_outputs,
 	)?;
 	tx::update_message(&mut *w, keychain_mask, &ret_slate)?;
 
 	let keychain = w.keychain(keychain_mask)?;
+	ret_slate.sign_recipient_binding(
+		keychain.secp(),
+		&proofaddress::payment_proof_address_secret(&keychain)?,
+	)?;
 	let excess = ret_slate.calc_excess(&keychain)?;
 
 	if let Some(ref mut p) = ret_slate.payment_proof {
@@ -248,9 +270,19 @@ where
 		p.receiver_signature = Some(sig);
 	}
 
+	checkpoint::save_slate_checkpoint(&ret_slate)?;
 	Ok(ret_slate)
 }
 
+/// Check if the slate amount matches what receiver expects to get.
+/// Zero amount slate is never valid, it is rejected as well.
+pub fn check_expected_amount(slate: &Slate, expected_amount: u64) -> Result<(), Error> {
+	if slate.amount == 0 || slate.amount != expected_amount {
+		return Err(ErrorKind::ExpectedAmountMismatch(expected_amount, slate.amount).into());
+	}
+	Ok(())
+}
+
 /// Receive an tx that this wallet has issued
 pub fn finalize_invoice_tx<'a, T: ?Sized, C, K>(
 	w: &mut T,
@@ -265,8 +297,10 @@ where
 {
 	let mut sl = slate.clone();
 	check_ttl(w, &sl, refresh_from_node)?;
+	checkpoint::update_slate_checkpoint(&sl)?;
 	// Participant id 0 for mwc713 compatibility
 	let context = w.get_private_context(keychain_mask, sl.id.as_bytes(), 0)?;
+	tx::check_output_duplicates(&mut *w, &sl)?;
 	// Participant id 0 for mwc713 compatibility
 	tx::complete_tx(&mut *w, keychain_mask, &mut sl, 0, &context)?;
 	tx::update_stored_tx(&mut *w, keychain_mask, &context, &sl, true)?;
@@ -277,6 +311,7 @@ where
 		batch.delete_private_context(sl.id.as_bytes(), 0)?;
 		batch.commit()?;
 	}
+	checkpoint::delete_slate_checkpoint(&sl.id)?;
 	Ok(sl)
 }
 
@@ -285,13 +320,23 @@ pub fn receive_swap_message<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
 	keychain_mask: Option<&SecretKey>,
 	message: &String,
+	config: &SwapConfig,
 ) -> Result<(), Error>
 where
 	L: WalletLCProvider<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	owner_swap::swap_income_message(wallet_inst, keychain_mask, &message, None).map_err(|e| {
+	owner_swap::swap_income_message(
+		wallet_inst,
+		keychain_mask,
+		&message,
+		None,
+		None,
+		&SwapAcceptArgs::default(),
+		config,
+	)
+	.map_err(|e| {
 		ErrorKind::SwapError(format!(
 			"Error occurred in receiving the swap message by TOR, {}",
 			e
@@ -299,3 +344,28 @@ where
 	})?;
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_check_expected_amount() {
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		assert!(check_expected_amount(&slate, 1_000_000_000).is_ok());
+		assert_eq!(
+			check_expected_amount(&slate, 2_000_000_000)
+				.unwrap_err()
+				.kind(),
+			ErrorKind::ExpectedAmountMismatch(2_000_000_000, 1_000_000_000)
+		);
+
+		// Zero amount slate is always rejected
+		slate.amount = 0;
+		assert_eq!(
+			check_expected_amount(&slate, 0).unwrap_err().kind(),
+			ErrorKind::ExpectedAmountMismatch(0, 0)
+		);
+	}
+}
@@ -17,25 +17,31 @@
 use uuid::Uuid;
 
 use crate::grin_core::core::hash::Hashed;
-use crate::grin_core::core::Transaction;
+use crate::grin_core::core::{KernelFeatures, Transaction};
+use crate::grin_core::libtx::tx_fee;
 use crate::grin_util::secp::key::SecretKey;
 use crate::grin_util::Mutex;
 
 use crate::api_impl::owner_updater::StatusMessage;
 use crate::grin_keychain::{Identifier, Keychain};
 use crate::grin_util::secp::key::PublicKey;
+use crate::grin_util::secp::pedersen::Commitment;
+use crate::grin_util::to_hex;
 
-use crate::internal::{keys, scan, selection, tx, updater};
+use crate::internal::{checkpoint, keys, scan, selection, tx, updater};
 use crate::slate::{PaymentInfo, Slate};
 use crate::types::{AcctPathMapping, Context, NodeClient, TxLogEntry, WalletBackend, WalletInfo};
 use crate::{
 	wallet_lock, InitTxArgs, IssueInvoiceTxArgs, NodeHeightResult, OutputCommitMapping,
-	PaymentProof, ScannedBlockInfo, TxLogEntryType, WalletInst, WalletLCProvider,
+	PaymentProof, ScannedBlockInfo, SlateVerification, TxLogEntryType, WalletInst,
+	WalletLCProvider,
 };
 use crate::{Error, ErrorKind};
 
 use crate::proof::tx_proof::{pop_proof_for_slate, TxProof};
+use chrono::{DateTime, Utc};
 use std::cmp;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
 use std::sync::mpsc::Sender;
@@ -45,6 +51,35 @@ const USER_MESSAGE_MAX_LEN: usize = 256;
 use crate::proof::crypto;
 use crate::proof::proofaddress;
 use grin_core::global;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+lazy_static! {
+	/// Wallet policy, every outgoing transaction must have the payment proof
+	static ref PAYMENT_PROOF_REQUIRED: AtomicBool = AtomicBool::new(false);
+	/// Wallet policy, finalize fails if the requested payment proof is not in the returned slate
+	static ref MISSING_PAYMENT_PROOF_IS_ERROR: AtomicBool = AtomicBool::new(false);
+}
+
+/// Set the wallet policy that requires the payment proof for all outgoing slates
+pub fn set_payment_proof_required(required: bool) {
+	PAYMENT_PROOF_REQUIRED.store(required, Ordering::Relaxed);
+}
+
+/// True if every outgoing slate must have the payment proof
+pub fn is_payment_proof_required() -> bool {
+	PAYMENT_PROOF_REQUIRED.load(Ordering::Relaxed)
+}
+
+/// Set the wallet policy that rejects the slate at finalize if the payment proof was requested
+/// but the recipient didn't return it
+pub fn set_missing_payment_proof_error(is_error: bool) {
+	MISSING_PAYMENT_PROOF_IS_ERROR.store(is_error, Ordering::Relaxed);
+}
+
+/// True if the missing payment proof fails the finalize
+pub fn is_missing_payment_proof_error() -> bool {
+	MISSING_PAYMENT_PROOF_IS_ERROR.load(Ordering::Relaxed)
+}
 
 /// List of accounts
 pub fn accounts<'a, T: ?Sized, C, K>(w: &mut T) -> Result<Vec<AcctPathMapping>, Error>
@@ -418,6 +453,31 @@ where
 		return Ok(slate);
 	}
 
+	let file_proof = args.address.as_deref() == Some("file_proof");
+	if is_payment_proof_required() && args.payment_proof_recipient_address.is_none() && !file_proof
+	{
+		return Err(ErrorKind::PaymentProofRequired(
+			"please specify the payment proof recipient address".to_string(),
+		)
+		.into());
+	}
+
+	let bind_to_recipient = args.bind_to_recipient.unwrap_or(false);
+	if bind_to_recipient {
+		if args.payment_proof_recipient_address.is_none() {
+			return Err(ErrorKind::GenericError(
+				"Slate can be bound only to the payment proof recipient address".to_string(),
+			)
+			.into());
+		}
+		if args.target_slate_version == Some(2) {
+			return Err(ErrorKind::GenericError(
+				"Slate V2 doesn't support the recipient binding".to_string(),
+			)
+			.into());
+		}
+	}
+
 	let mut context = tx::add_inputs_to_slate(
 		&mut *w,
 		keychain_mask,
@@ -431,6 +491,8 @@ where
 		message,
 		true,
 		use_test_rng,
+		args.split_signing.unwrap_or(false),
+		args.deterministic_order.unwrap_or(false),
 		&args.outputs,
 		routputs,
 		args.exclude_change_outputs.unwrap_or(false),
@@ -459,6 +521,10 @@ where
 	}
 
 	if let Some(a) = args.payment_proof_recipient_address {
+		if bind_to_recipient {
+			slate.bind_to_recipient(a.public_key()?);
+			context.recipient_binding = Some(a.clone());
+		}
 		slate.payment_proof = Some(PaymentInfo {
 			sender_address: sender_a,
 			receiver_address: a,
@@ -497,9 +563,19 @@ where
 		slate.network_type = Some("mainnet".to_string());
 	}
 
+	checkpoint::save_slate_checkpoint(&slate)?;
 	Ok(slate)
 }
 
+/// Reload the partial slate from the checkpoint, if the wallet was stopped between the rounds.
+/// The slate that the other party responded with is used if it extends the checkpoint.
+pub fn resume_slate(id: &Uuid, received: Option<&Slate>) -> Result<Slate, Error> {
+	let checkpoint = checkpoint::resume_slate(id)?.ok_or_else(|| {
+		ErrorKind::GenericError(format!("Checkpoint for slate {} is not found", id))
+	})?;
+	checkpoint::select_resumed_slate(checkpoint, received.cloned())
+}
+
 /// Initiate a transaction as the recipient (invoicing)
 pub fn issue_invoice_tx<'a, T: ?Sized, C, K>(
 	w: &mut T,
@@ -545,6 +621,8 @@ where
 		message,
 		true,
 		use_test_rng,
+		args.split_signing.unwrap_or(false),
+		false,
 		num_outputs,
 	)?;
 
@@ -561,6 +639,7 @@ where
 		slate.version_info.orig_version = v;
 	}
 
+	checkpoint::save_slate_checkpoint(&slate)?;
 	Ok(slate)
 }
 
@@ -622,9 +701,12 @@ where
 
 	// update ttl if desired
 	if let Some(b) = args.ttl_blocks {
-		ret_slate.ttl_cutoff_height = Some(ret_slate.height + b);
+		ret_slate.ttl_cutoff_height = Some(tx::ttl_cutoff_height(ret_slate.height, b)?);
 	}
 
+	// With the split signing the payer stops after round 1, the slate is exported and signed
+	// on another machine
+	let split_signing = args.split_signing.unwrap_or(false);
 	let context = tx::add_inputs_to_slate(
 		&mut *w,
 		keychain_mask,
@@ -636,8 +718,10 @@ where
 		&parent_key_id,
 		1, // Participant id 1 for mwc713 compatibility
 		message,
-		false,
+		split_signing,
 		use_test_rng,
+		split_signing,
+		args.deterministic_order.unwrap_or(false),
 		&None,
 		1,
 		args.exclude_change_outputs.unwrap_or(false),
@@ -657,6 +741,7 @@ where
 		ret_slate.version_info.orig_version = v;
 	}
 
+	checkpoint::save_slate_checkpoint(&ret_slate)?;
 	Ok(ret_slate)
 }
 
@@ -692,7 +777,33 @@ where
 {
 	let mut sl = slate.clone();
 	check_ttl(w, &sl, refresh_from_node)?;
+	checkpoint::update_slate_checkpoint(&sl)?;
 	let context = w.get_private_context(keychain_mask, sl.id.as_bytes(), 0)?;
+	// The binding is checked against the recipient from the context, the responded slate
+	// can't be trusted to keep it.
+	if let Some(recipient) = &context.recipient_binding {
+		let recipient = recipient.public_key()?;
+		if sl.recipient_binding.as_ref().map(|b| b.recipient) != Some(recipient) {
+			return Err(ErrorKind::RecipientBindingMismatch(sl.id.to_string()).into());
+		}
+		sl.verify_recipient_binding(w.keychain(keychain_mask)?.secp())?;
+	}
+	// The proof was requested when the slate was created. If the outputs were locked with the
+	// returned slate, the tx log doesn't know about the proof, so checking the context.
+	if is_missing_payment_proof_error()
+		&& context.payment_proof_derivation_index.is_some()
+		&& sl
+			.payment_proof
+			.as_ref()
+			.map(|p| p.receiver_signature.is_none())
+			.unwrap_or(true)
+	{
+		return Err(ErrorKind::PaymentProof(
+			"Expected Payment Proof for this Transaction is not present".to_owned(),
+		)
+		.into());
+	}
+	tx::check_output_duplicates(&mut *w, &sl)?;
 	tx::complete_tx(&mut *w, keychain_mask, &mut sl, 0, &context)?;
 	tx::verify_slate_payment_proof(&mut *w, keychain_mask, &context, &sl)?;
 	tx::update_stored_tx(&mut *w, keychain_mask, &context, &sl, false)?;
@@ -702,6 +813,7 @@ where
 		batch.delete_private_context(sl.id.as_bytes(), 0)?;
 		batch.commit()?;
 	}
+	checkpoint::delete_slate_checkpoint(&sl.id)?;
 
 	// If Proof available, we can store it at that point
 	if let Some(mut proof) = pop_proof_for_slate(&slate.id) {
@@ -720,6 +832,72 @@ where
 	Ok((sl, context))
 }
 
+/// Export the slate after round 1, so it can be signed on another machine with the same seed.
+/// The slate must be created with the split signing: a send, an invoice or an invoice payment.
+/// The nonce leaves this wallet with the export, so the context is deleted and the slate
+/// can't be signed or exported here again.
+pub fn export_round_1<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &Slate,
+) -> Result<String, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// Participant that is waiting for round 2 with the split signing context
+	let mut contexts = vec![];
+	for p in slate
+		.participant_data
+		.iter()
+		.filter(|p| p.part_sig.is_none())
+	{
+		if let Ok(context) =
+			w.get_private_context(keychain_mask, slate.id.as_bytes(), p.id as usize)
+		{
+			if context.nonce_id.is_some() {
+				contexts.push(context);
+			}
+		}
+	}
+	if contexts.len() != 1 {
+		return Err(ErrorKind::GenericError(format!(
+			"Slate {} has no single split signing participant in this wallet, unable to export it",
+			slate.id
+		))
+		.into());
+	}
+	let context = contexts.remove(0);
+	let export = tx::export_round_1(&mut *w, keychain_mask, slate, &context)?;
+	{
+		let mut batch = w.batch(keychain_mask)?;
+		batch.delete_private_context(slate.id.as_bytes(), context.participant_id)?;
+		batch.commit()?;
+	}
+	Ok(export)
+}
+
+/// Sign the slate with the round 1 export from another machine. This wallet doesn't need
+/// the transaction data, the keys are derived from the seed. The send and the invoice are
+/// finalized, the invoice payment is returned with the payer signature for the invoicer.
+/// Every export can sign only one slate.
+pub fn finalize_exported_tx<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	export: &str,
+	slate: &Slate,
+) -> Result<Slate, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let mut sl = slate.clone();
+	tx::complete_exported_tx(&mut *w, keychain_mask, export, &mut sl)?;
+	Ok(sl)
+}
+
 /// cancel tx
 pub fn cancel_tx<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
@@ -756,6 +934,17 @@ where
 	w.get_stored_tx(entry)
 }
 
+/// Loads the slate that was stored when the transaction was sent
+pub fn get_stored_slate<'a, T: ?Sized, C, K>(w: &T, slate_id: &Uuid) -> Result<Slate, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	w.get_stored_slate(&slate_id.to_string())?
+		.ok_or(ErrorKind::TransactionDoesntExist(slate_id.to_string()).into())
+}
+
 /// Loads a stored transaction from a file
 pub fn load_stored_tx<'a, T: ?Sized, C, K>(w: &T, file: &String) -> Result<Transaction, Error>
 where
@@ -909,6 +1098,155 @@ where
 	}
 }
 
+/// Node tip older than that is considered as stale (seconds)
+pub const NODE_TIP_MAX_AGE_SEC: i64 = 60 * 60;
+/// Number of blocks the node can be behind the peers before we consider it lagging
+pub const NODE_MAX_BLOCKS_BEHIND_PEERS: u64 = 10;
+
+/// Check if the node chain state looks healthy. Node might be on a fork or far behind the peers,
+/// in that case swap and other height based operations are not safe.
+/// `tip_height` is the node chain tip that the caller already has.
+/// Return a warning message if node looks stale, None if everything is fine.
+pub fn check_node_sync_state<C: NodeClient>(
+	client: &C,
+	tip_height: u64,
+) -> Result<Option<String>, Error> {
+	let header = client.get_header_info(tip_height)?;
+	if !header.confirmed_time.is_empty() {
+		let tip_time = DateTime::parse_from_rfc3339(&header.confirmed_time)
+			.map_err(|e| {
+				ErrorKind::ClientCallback(format!(
+					"Unable to parse node tip time {}, {}",
+					header.confirmed_time, e
+				))
+			})?
+			.timestamp();
+		let warning = stale_tip_warning(tip_height, tip_time, Utc::now().timestamp());
+		if warning.is_some() {
+			return Ok(warning);
+		}
+	}
+
+	// Peers data is optional, some nodes don't expose it
+	if let Ok(peers) = client.get_connected_peer_info() {
+		if let Some(peers_height) = peers.iter().map(|p| p.height).max() {
+			return Ok(lagging_tip_warning(tip_height, peers_height));
+		}
+	}
+
+	Ok(None)
+}
+
+// Warning if the node tip is older than NODE_TIP_MAX_AGE_SEC
+fn stale_tip_warning(tip_height: u64, tip_time: i64, now: i64) -> Option<String> {
+	let tip_age = now - tip_time;
+	if tip_age > NODE_TIP_MAX_AGE_SEC {
+		Some(format!(
+			"Node tip at height {} is {} minutes old. Node might be out of sync or on a fork.",
+			tip_height,
+			tip_age / 60
+		))
+	} else {
+		None
+	}
+}
+
+// Warning if the node tip is more than NODE_MAX_BLOCKS_BEHIND_PEERS blocks behind the peers
+fn lagging_tip_warning(tip_height: u64, peers_height: u64) -> Option<String> {
+	if peers_height > tip_height + NODE_MAX_BLOCKS_BEHIND_PEERS {
+		Some(format!(
+			"Node tip at height {} is {} blocks behind its peers. Node might be out of sync or on a fork.",
+			tip_height,
+			peers_height - tip_height
+		))
+	} else {
+		None
+	}
+}
+
+/// Verify the slate against the node without signing or posting it.
+/// Inputs must exist and be unspent, kernels must agree with the slate fee and lock height,
+/// and the fee must cover the transaction weight.
+pub fn verify_slate<C: NodeClient>(client: &C, slate: &Slate) -> Result<SlateVerification, Error> {
+	let mut problems = Vec::new();
+
+	let commits: Vec<Commitment> = slate.tx.inputs().iter().map(|i| i.commit).collect();
+	let unspent = if commits.is_empty() {
+		HashMap::new()
+	} else {
+		client.get_outputs_from_node(&commits)?
+	};
+	let mut inputs = Vec::new();
+	for commit in &commits {
+		let commit_str = to_hex(commit.0.to_vec());
+		let is_unspent = unspent.contains_key(commit);
+		if !is_unspent {
+			problems.push(format!("Input {} is spent or doesn't exist", commit_str));
+		}
+		if commits.iter().filter(|c| *c == commit).count() > 1
+			&& !inputs.iter().any(|(c, _)| *c == commit_str)
+		{
+			problems.push(format!("Input {} is used more than once", commit_str));
+		}
+		inputs.push((commit_str, is_unspent));
+	}
+
+	if slate.tx.kernels().is_empty() {
+		problems.push("Transaction doesn't have any kernels".to_string());
+	}
+	let mut kernels_fee = 0;
+	for kernel in slate.tx.kernels() {
+		match kernel.features {
+			KernelFeatures::Plain { fee } => {
+				if slate.lock_height != 0 {
+					problems.push(format!(
+						"Plain kernel doesn't match slate lock height {}",
+						slate.lock_height
+					));
+				}
+				kernels_fee += fee;
+			}
+			KernelFeatures::HeightLocked { fee, lock_height } => {
+				if lock_height != slate.lock_height {
+					problems.push(format!(
+						"Kernel lock height {} doesn't match slate lock height {}",
+						lock_height, slate.lock_height
+					));
+				}
+				kernels_fee += fee;
+			}
+			KernelFeatures::Coinbase => {
+				problems.push("Coinbase kernel is not expected at the slate".to_string());
+			}
+		}
+	}
+	if kernels_fee != slate.fee {
+		problems.push(format!(
+			"Kernels fee {} doesn't match slate fee {}",
+			kernels_fee, slate.fee
+		));
+	}
+
+	let min_fee = tx_fee(
+		slate.tx.inputs().len(),
+		slate.tx.outputs().len(),
+		slate.tx.kernels().len(),
+		None,
+	);
+	if slate.fee < min_fee {
+		problems.push(format!(
+			"Slate fee {} is lower than required {}",
+			slate.fee, min_fee
+		));
+	}
+
+	Ok(SlateVerification {
+		slate_id: slate.id.to_string(),
+		inputs,
+		problems,
+	})
+}
+
 // write infor into the file or channel
 fn write_info(
 	message: String,
@@ -1254,3 +1592,89 @@ where
 
 	Ok((sender_mine, recipient_mine))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::swap;
+	use crate::swap::tests::{context_sell, keychain, seller_offer_message, TestNodeClient};
+	use serial_test::serial;
+
+	#[test]
+	fn test_check_node_sync_state() {
+		let nc = TestNodeClient::new(100_000);
+		// Fresh tip is fine
+		assert_eq!(check_node_sync_state(&nc, 100_000).unwrap(), None);
+
+		// Node reporting the tip from the past is stale
+		nc.state.lock().tip_time = Some(Utc::now().timestamp() - 3 * 3600);
+		let warning = check_node_sync_state(&nc, 100_000).unwrap();
+		assert!(warning.is_some());
+		assert!(warning.unwrap().contains("180 minutes old"));
+	}
+
+	#[test]
+	fn test_lagging_tip_warning() {
+		// Peers slightly ahead is a normal propagation delay
+		assert_eq!(
+			lagging_tip_warning(100_000, 100_000 + NODE_MAX_BLOCKS_BEHIND_PEERS),
+			None
+		);
+		assert_eq!(lagging_tip_warning(100_000, 99_000), None);
+		assert_eq!(
+			lagging_tip_warning(100_000, 100_000 + NODE_MAX_BLOCKS_BEHIND_PEERS + 1),
+			Some(format!(
+				"Node tip at height 100000 is {} blocks behind its peers. Node might be out of sync or on a fork.",
+				NODE_MAX_BLOCKS_BEHIND_PEERS + 1
+			))
+		);
+	}
+
+	#[test]
+	#[serial]
+	fn test_verify_slate() {
+		swap::set_test_mode(true);
+		swap::swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(100_000);
+		let (swap, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		// Lock slate inputs are at the chain
+		let report = verify_slate(&nc, &swap.lock_slate).unwrap();
+		assert!(report.is_valid(), "{:?}", report.problems);
+		assert_eq!(report.slate_id, swap.lock_slate.id.to_string());
+		assert_eq!(report.inputs.len(), swap.lock_slate.tx.inputs().len());
+		assert!(report.inputs.iter().all(|(_, unspent)| *unspent));
+
+		// The same slate against the node that doesn't have the inputs
+		let report = verify_slate(&TestNodeClient::new(100_000), &swap.lock_slate).unwrap();
+		assert!(!report.is_valid());
+		assert!(report.inputs.iter().all(|(_, unspent)| !*unspent));
+		assert_eq!(report.problems.len(), report.inputs.len());
+
+		// Kernel fee doesn't match the slate
+		let mut slate = swap.lock_slate.clone();
+		slate.tx.body.kernels[0].features = KernelFeatures::Plain { fee: slate.fee + 1 };
+		let report = verify_slate(&nc, &slate).unwrap();
+		assert_eq!(
+			report.problems,
+			vec![format!(
+				"Kernels fee {} doesn't match slate fee {}",
+				slate.fee + 1,
+				slate.fee
+			)]
+		);
+
+		// Refund slate lock height must match its kernel
+		let mut slate = swap.refund_slate.clone();
+		slate.lock_height += 1;
+		let report = verify_slate(&nc, &slate).unwrap();
+		assert!(report.problems.contains(&format!(
+			"Kernel lock height {} doesn't match slate lock height {}",
+			slate.lock_height - 1,
+			slate.lock_height
+		)));
+	}
+}
@@ -19,27 +19,40 @@ use crate::grin_util::Mutex;
 
 use crate::grin_keychain::{Identifier, Keychain, SwitchCommitmentType};
 use crate::internal::selection;
+use crate::swap::config::SwapConfig;
 use crate::swap::error::ErrorKind;
 use crate::swap::fsm::state::{Input, StateEtaInfo, StateId, StateProcessRespond};
-use crate::swap::message::{Message, SecondaryUpdate, Update};
-use crate::swap::swap::{Swap, SwapJournalRecord};
-use crate::swap::types::{Action, Currency, Role, SwapTransactionsConfirmations};
+use crate::swap::message::{Message, MessageDirection, SecondaryUpdate, SwapMessageLog, Update};
+use crate::swap::metrics::SwapMetrics;
+use crate::swap::rate_limit;
+use crate::swap::rebroadcast::{self, RebroadcastStatus};
+use crate::swap::swap::{node_client_with_timeout, Swap, SwapJournalRecord};
+use crate::swap::types::{
+	Action, CompletionCertificate, Currency, RefundSimulation, Role, RoleContext, SafetyAssessment,
+	SwapTransactionsConfirmations,
+};
 use crate::swap::{trades, BuyApi, Context, SwapApi};
 use crate::types::NodeClient;
 use crate::{get_receive_account, Error};
 use crate::{
-	wallet_lock, OutputData, OutputStatus, Slate, SwapStartArgs, TxLogEntry, TxLogEntryType,
-	WalletBackend, WalletInst, WalletLCProvider,
+	wallet_lock, OutputData, OutputStatus, Slate, SwapAcceptArgs, SwapStartArgs, TxLogEntry,
+	TxLogEntryType, WalletBackend, WalletInst, WalletLCProvider,
 };
 use grin_core::core;
 use grin_keychain::ExtKeychainPath;
 use grin_util::to_hex;
+use rand::thread_rng;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fs::File;
 use std::io::Read;
 use std::sync::Arc;
 
+/// Max number of the inputs for the seller lock transaction
+const SWAP_LOCK_MAX_OUTPUTS: usize = 500;
+/// Default minimum confirmations of the outputs that the seller lock transaction spends
+const SWAP_LOCK_MIN_CONFIRMATIONS: u64 = 10;
+
 fn get_swap_storage_key<K: Keychain>(keychain: &K) -> Result<SecretKey, Error> {
 	Ok(keychain.derive_key(
 		0,
@@ -48,21 +61,17 @@ fn get_swap_storage_key<K: Keychain>(keychain: &K) -> Result<SecretKey, Error> {
 	)?)
 }
 
-/// Start swap trade process. Return SwapID that can be used to check the status or perform further action.
-pub fn swap_start<'a, L, C, K>(
+// Outputs that can fund a new swap and amount that is reserved by the running swaps.
+// Inputs of the seller swaps that are not locked yet are excluded.
+fn swap_available_outputs<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
 	keychain_mask: Option<&SecretKey>,
-	params: &SwapStartArgs,
-) -> Result<String, Error>
+) -> Result<(Vec<String>, u64), Error>
 where
 	L: WalletLCProvider<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	// Starting a swap trade.
-	// This method only initialize and store the swap process. Nothing is done
-
-	// First we need to define outputs that we can use.
 	// Reading all output.
 	let (_, outputs) = super::owner::retrieve_outputs(
 		wallet_inst.clone(),
@@ -72,7 +81,6 @@ where
 		false,
 		None,
 	)?;
-	// Reading all swaps. We need to exclude
 
 	let mut outs: HashMap<String, u64> = outputs
 		.iter()
@@ -81,14 +89,8 @@ where
 		.collect();
 
 	wallet_lock!(wallet_inst, w);
-	let node_client = w.w2n_client().clone();
 	let keychain = w.keychain(keychain_mask)?;
 	let skey = get_swap_storage_key(&keychain)?;
-	let height = node_client.get_chain_tip()?.0;
-
-	if height == 0 {
-		return Err(ErrorKind::Generic("MWC node is syncing and not ready yet".to_string()).into());
-	}
 
 	let mut swap_reserved_amount = 0;
 
@@ -114,6 +116,168 @@ where
 		}
 	}
 
+	Ok((
+		outs.keys().map(|k| k.clone()).collect(),
+		swap_reserved_amount,
+	))
+}
+
+// Select inputs for the seller lock transaction. Lock transaction has a single multisig output
+// and a single change output. swap_start and the preview select with the same start arguments,
+// so the preview shows exactly the outputs that the swap will lock.
+fn select_lock_inputs<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	params: &SwapStartArgs,
+	height: u64,
+	outputs: Vec<String>,
+) -> Result<(Vec<OutputData>, u64, u64, u64), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let parent_key_id = wallet.parent_key_id(); // account is current one
+	selection::select_coins_and_fee(
+		wallet,
+		params.mwc_amount,
+		height,
+		lock_minimum_confirmations(params),
+		SWAP_LOCK_MAX_OUTPUTS,
+		1,
+		false,
+		&parent_key_id,
+		&Some(outputs), // outputs to include into the transaction
+		1,              // Number of resulting outputs. Normally it is 1
+		false,
+		0,
+	)
+}
+
+// Lock transaction change outputs amounts and the lock fee. Round change shows which lock
+// output is the change, such change is split in two outputs and the second output fee is paid
+// from the change.
+fn lock_change(inputs: usize, change: u64, fee: u64) -> (Vec<u64>, u64) {
+	let extra_output_fee = grin_core::libtx::tx_fee(inputs, 3, 1, None).saturating_sub(fee);
+	let change = selection::split_change_amount(change, extra_output_fee, &mut thread_rng());
+	let fee = if change.len() > 1 {
+		fee + extra_output_fee
+	} else {
+		fee
+	};
+	(change, fee)
+}
+
+// Minimum confirmations of the outputs that the lock transaction can spend
+fn lock_minimum_confirmations(params: &SwapStartArgs) -> u64 {
+	params
+		.minimum_confirmations
+		.unwrap_or(SWAP_LOCK_MIN_CONFIRMATIONS)
+}
+
+/// Outputs that the seller lock transaction will spend
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockInputsPreview {
+	/// Outputs that will be spent. If funds are not enough, it is the largest possible selection.
+	pub inputs: Vec<OutputData>,
+	/// Total value of the inputs
+	pub total: u64,
+	/// Lock transaction fee
+	pub fee: u64,
+	/// Change that will return to the wallet
+	pub change: u64,
+	/// Funds that are missing to fund the lock transaction. None if funds are enough.
+	pub shortfall: Option<u64>,
+}
+
+/// Preview the outputs that the swap will lock. Selection is the same as swap_start does with
+/// these start arguments, so the user can approve the outputs before starting the swap.
+pub fn preview_lock_inputs<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	params: &SwapStartArgs,
+) -> Result<LockInputsPreview, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let (outputs, _) = swap_available_outputs(wallet_inst.clone(), keychain_mask)?;
+
+	wallet_lock!(wallet_inst, w);
+	let height = w.w2n_client().get_chain_tip()?.0;
+
+	match select_lock_inputs(&mut **w, params, height, outputs.clone()) {
+		Ok((inputs, total, amount, fee)) => {
+			let (change, fee) = lock_change(inputs.len(), total - amount - fee, fee);
+			Ok(LockInputsPreview {
+				inputs,
+				total,
+				fee,
+				change: change.iter().sum(),
+				shortfall: None,
+			})
+		}
+		Err(e) => match e.kind() {
+			crate::ErrorKind::NotEnoughFunds { .. } => {
+				// Showing the best what we have
+				let amount = params.mwc_amount;
+				let parent_key_id = w.parent_key_id();
+				let (_, inputs) = selection::select_coins(
+					&mut **w,
+					amount,
+					height,
+					lock_minimum_confirmations(params),
+					SWAP_LOCK_MAX_OUTPUTS,
+					false,
+					&parent_key_id,
+					&Some(outputs),
+					false,
+					0,
+				);
+				let total: u64 = inputs.iter().map(|o| o.value).sum();
+				let fee = grin_core::libtx::tx_fee(inputs.len(), 2, 1, None);
+				Ok(LockInputsPreview {
+					inputs,
+					total,
+					fee,
+					change: 0,
+					shortfall: Some((amount + fee).saturating_sub(total)),
+				})
+			}
+			_ => Err(e),
+		},
+	}
+}
+
+/// Start swap trade process. Return SwapID that can be used to check the status or perform further action.
+pub fn swap_start<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	params: &SwapStartArgs,
+	config: &SwapConfig,
+) -> Result<String, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// Starting a swap trade.
+	// This method only initialize and store the swap process. Nothing is done
+
+	// First we need to define outputs that we can use.
+	let (outputs, swap_reserved_amount) =
+		swap_available_outputs(wallet_inst.clone(), keychain_mask)?;
+
+	wallet_lock!(wallet_inst, w);
+	let node_client = w.w2n_client().clone();
+	let keychain = w.keychain(keychain_mask)?;
+	let skey = get_swap_storage_key(&keychain)?;
+	let height = node_client.get_chain_tip()?.0;
+
+	if height == 0 {
+		return Err(ErrorKind::Generic("MWC node is syncing and not ready yet".to_string()).into());
+	}
+
 	if swap_reserved_amount > 0 {
 		let swap_reserved_amount_str =
 			grin_core::core::amount_to_hr_string(swap_reserved_amount, true);
@@ -121,7 +285,6 @@ where
 		println!("WARNING. This swap will need to reserve {} MWC. If you don't have enough funds, please cancel it.", swap_reserved_amount_str);
 	}
 
-	let outputs: Vec<String> = outs.keys().map(|k| k.clone()).collect();
 	let secondary_currency = Currency::try_from(params.secondary_currency.as_str())?;
 	let secondary_amount = secondary_currency.amount_from_hr_string(&params.secondary_amount)?;
 
@@ -136,21 +299,8 @@ where
 	// Checking ElectrumX nodes...
 	swap_api.test_client_connections()?;
 
-	let parent_key_id = w.parent_key_id(); // account is current one
-	let (outputs, total, amount, fee) = crate::internal::selection::select_coins_and_fee(
-		&mut **w,
-		params.mwc_amount,
-		height,
-		params.minimum_confirmations.unwrap_or(10),
-		500,
-		1,
-		false,
-		&parent_key_id,
-		&Some(outputs), // outputs to include into the transaction
-		1,              // Number of resulting outputs. Normally it is 1
-		false,
-		0,
-	)?;
+	let (outputs, total, amount, fee) = select_lock_inputs(&mut **w, params, height, outputs)?;
+	let (change, _) = lock_change(outputs.len(), total - amount - fee, fee);
 
 	let context = create_context(
 		&mut **w,
@@ -165,7 +315,8 @@ where
 				.map(|out| (out.key_id.clone(), out.mmr_index.clone(), out.value))
 				.collect(),
 		),
-		total - amount - fee,
+		change[0],
+		change.get(1).cloned(),
 	)?;
 
 	let mut swap = (*swap_api).create_swap_offer(
@@ -184,6 +335,8 @@ where
 		params.buyer_communication_address.clone(),
 		params.electrum_node_uri1.clone(),
 		params.electrum_node_uri2.clone(),
+		params.external_ref.clone(),
+		config,
 	)?;
 
 	// Store swap result into the file.
@@ -265,25 +418,48 @@ where
 	let keychain = w.keychain(keychain_mask)?;
 	let skey = get_swap_storage_key(&keychain)?;
 
+	// All trades stay locked until the list is done, the active ones are refreshed in one pass
+	let swap_locks: Vec<Arc<Mutex<()>>> = swap_id.iter().map(trades::get_swap_lock).collect();
+	let _l: Vec<_> = swap_locks.iter().map(|l| l.lock()).collect();
+	let mut trades: Vec<(Context, Swap)> = Vec::with_capacity(swap_id.len());
+	for (sw_id, swap_lock) in swap_id.iter().zip(&swap_locks) {
+		trades.push(trades::get_swap_trade(sw_id.as_str(), &skey, &**swap_lock)?);
+	}
+
+	let tx_confs = if do_check {
+		let swaps: Vec<&Swap> = trades.iter().map(|(_, swap)| swap).collect();
+		request_tx_confirmations_batch(&swaps, node_client.clone(), &keychain)?
+	} else {
+		(0..trades.len()).map(|_| None).collect()
+	};
+
 	let mut do_check = do_check;
 
-	for sw_id in &swap_id {
-		let swap_lock = trades::get_swap_lock(sw_id);
-		let _l = swap_lock.lock();
-		let (context, mut swap) = trades::get_swap_trade(sw_id.as_str(), &skey, &*swap_lock)?;
+	for ((((context, mut swap), sw_id), swap_lock), tx_conf) in trades
+		.into_iter()
+		.zip(&swap_id)
+		.zip(&swap_locks)
+		.zip(tx_confs)
+	{
 		let trade_start_time = swap.started.timestamp();
 		swap.wait_for_backup1 = true; // allways waiting becasue moving forward it is not a swap list task
 
 		if do_check && !swap.state.is_final_state() {
-			let (state, action, expiration) = match update_swap_status_action_impl(
-				&mut swap,
-				&context,
-				node_client.clone(),
-				&keychain,
-			) {
+			let res = match tx_conf {
+				Some(Err(e)) => Err(e),
+				tx_conf => update_swap_status_action_impl(
+					&mut swap,
+					&context,
+					node_client.clone(),
+					&keychain,
+					tx_conf.and_then(|c| c.ok()),
+				)
+				.map_err(|e| format!("{}", e)),
+			};
+			let (state, action, expiration) = match res {
 				Ok((state, action, expiration, _state_eta)) => {
 					swap.last_check_error = None;
-					trades::store_swap_trade(&context, &swap, &skey, &*swap_lock)?;
+					trades::store_swap_trade(&context, &swap, &skey, &**swap_lock)?;
 					(state, action, expiration)
 				}
 				Err(e) => {
@@ -326,12 +502,64 @@ where
 				last_error: swap.get_last_error(),
 			});
 		}
-		trades::store_swap_trade(&context, &swap, &skey, &*swap_lock)?;
+		trades::store_swap_trade(&context, &swap, &skey, &**swap_lock)?;
 	}
 
 	Ok(result)
 }
 
+/// Request the transaction confirmations for the active swaps in one pass. Swaps that use the
+/// same secondary node are requested together, so every node is asked about its tip once.
+/// Result has an item for every swap, None for the swaps in the final state.
+fn request_tx_confirmations_batch<'a, C, K>(
+	swaps: &[&Swap],
+	node_client: C,
+	keychain: &K,
+) -> Result<Vec<Option<Result<SwapTransactionsConfirmations, String>>>, Error>
+where
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let mut groups: Vec<((Currency, String, String), Vec<usize>)> = Vec::new();
+	for (i, swap) in swaps.iter().enumerate() {
+		if swap.state.is_final_state() {
+			continue;
+		}
+		let (uri1, uri2) = trades::get_electrumx_uri(
+			&swap.secondary_currency,
+			&swap.electrum_node_uri1,
+			&swap.electrum_node_uri2,
+		)?;
+		let group_key = (swap.secondary_currency, uri1, uri2);
+		match groups.iter_mut().find(|(k, _)| *k == group_key) {
+			Some((_, idx)) => idx.push(i),
+			None => groups.push((group_key, vec![i])),
+		}
+	}
+
+	let mut result: Vec<Option<Result<SwapTransactionsConfirmations, String>>> =
+		(0..swaps.len()).map(|_| None).collect();
+	for ((currency, uri1, uri2), idx) in groups {
+		let group: Vec<&Swap> = idx.iter().map(|i| swaps[*i]).collect();
+		let confs = crate::swap::api::create_instance(&currency, node_client.clone(), uri1, uri2)
+			.and_then(|swap_api| swap_api.request_tx_confirmations_batch(keychain, &group));
+		match confs {
+			Ok(confs) => {
+				for (i, conf) in idx.into_iter().zip(confs) {
+					result[i] = Some(Ok(conf));
+				}
+			}
+			Err(e) => {
+				let e = format!("{}", e);
+				for i in idx {
+					result[i] = Some(Err(e.clone()));
+				}
+			}
+		}
+	}
+	Ok(result)
+}
+
 /// Delete Swap trade.
 pub fn swap_delete<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
@@ -384,6 +612,7 @@ pub fn swap_adjust<'a, L, C, K>(
 	destination: Option<String>,
 	secondary_address: Option<String>, // secondary address to adjust
 	secondary_fee: Option<f32>,
+	redeem_fee: Option<u64>,
 	electrum_node_uri1: Option<String>,
 	electrum_node_uri2: Option<String>,
 ) -> Result<(StateId, Action), Error>
@@ -484,7 +713,33 @@ where
 				.into());
 			}
 
-			swap.secondary_fee = secondary_fee;
+			if swap.is_seller() && swap.secondary_data.unwrap_btc()?.redeem_tx.is_some() {
+				// The posted redeem is replaced with the new fee. It must not be confirmed yet
+				// and the redeem output must stay above the dust.
+				let (uri1, uri2) = trades::get_electrumx_uri(
+					&swap.secondary_currency,
+					&swap.electrum_node_uri1,
+					&swap.electrum_node_uri2,
+				)?;
+				let swap_api = crate::swap::api::create_instance(
+					&swap.secondary_currency,
+					node_client.clone(),
+					uri1,
+					uri2,
+				)?;
+				let tx_conf = swap_api.request_tx_confirmations(&keychain, &swap)?;
+				swap.bump_secondary_fee(secondary_fee, tx_conf.secondary_redeem_conf)?;
+			} else {
+				swap.secondary_fee = secondary_fee;
+			}
+			trades::store_swap_trade(&context, &swap, &skey, &*swap_lock)?;
+			return Ok((swap.state.clone(), Action::None));
+		}
+		"redeem_fee" => {
+			let redeem_fee = redeem_fee.ok_or(ErrorKind::Generic(
+				"Please define '--redeem_fee' value".to_string(),
+			))?;
+			swap.set_redeem_fee(redeem_fee)?;
 			trades::store_swap_trade(&context, &swap, &skey, &*swap_lock)?;
 			return Ok((swap.state.clone(), Action::None));
 		}
@@ -563,6 +818,55 @@ where
 	Ok(dump_res)
 }
 
+/// Verify integrity of all stored swaps.
+/// Return the swaps with the problems: <SwapId, problems>
+pub fn swap_verify_all<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+) -> Result<Vec<(String, Vec<String>)>, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	wallet_lock!(wallet_inst, w);
+	let keychain = w.keychain(keychain_mask)?;
+	let skey = get_swap_storage_key(&keychain)?;
+	Ok(trades::verify_all_swap_trades(&skey)?)
+}
+
+/// Aggregated metrics of all stored swap trades, for the monitoring systems
+pub fn swap_metrics<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+) -> Result<SwapMetrics, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	wallet_lock!(wallet_inst, w);
+	let keychain = w.keychain(keychain_mask)?;
+	let skey = get_swap_storage_key(&keychain)?;
+	Ok(trades::swap_metrics(&skey)?)
+}
+
+/// MWC amount locked by the active swap trades
+pub fn swap_locked_balance<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+) -> Result<u64, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	wallet_lock!(wallet_inst, w);
+	let keychain = w.keychain(keychain_mask)?;
+	let skey = get_swap_storage_key(&keychain)?;
+	Ok(trades::swap_locked_balance(&skey)?)
+}
+
 /// Import swap trade from the file
 /// Return: trade SwapId
 pub fn swap_import_trade<'a, L, C, K>(
@@ -610,6 +914,10 @@ where
 		uri2,
 	)?;
 
+	// Trade can come from another wallet instance, its keys might be above the local derivation
+	// index. Ordinary transactions must not derive them again.
+	reserve_swap_key_ids(&mut **w, keychain_mask, &swap, &context)?;
+
 	// let's calcutate the scrip hashes if needed and can
 	if swap.is_seller() && swap.secondary_data.unwrap_btc()?.redeem_tx.is_none() {
 		// try to calculate the hash if possible
@@ -672,6 +980,7 @@ fn update_swap_status_action_impl<'a, C, K>(
 	context: &Context,
 	node_client: C,
 	keychain: &K,
+	tx_conf: Option<SwapTransactionsConfirmations>,
 ) -> Result<(StateId, Action, Option<i64>, Vec<StateEtaInfo>), Error>
 where
 	C: NodeClient + 'a,
@@ -685,7 +994,10 @@ where
 	let swap_api =
 		crate::swap::api::create_instance(&swap.secondary_currency, node_client, uri1, uri2)?;
 	let mut fsm = swap_api.get_fsm(keychain, swap);
-	let tx_conf = swap_api.request_tx_confirmations(keychain, swap)?;
+	let tx_conf = match tx_conf {
+		Some(tx_conf) => tx_conf,
+		None => swap_api.request_tx_confirmations(keychain, swap)?,
+	};
 	let resp = fsm.process(Input::Check, swap, &context, &tx_conf)?;
 	let eta = fsm.get_swap_roadmap(swap)?;
 
@@ -742,10 +1054,17 @@ where
 
 	swap.wait_for_backup1 = wait_for_backup1;
 
-	match update_swap_status_action_impl(&mut swap, &context, node_client, &keychain) {
+	match update_swap_status_action_impl(&mut swap, &context, node_client.clone(), &keychain, None)
+	{
 		Ok((next_state_id, action, time_limit, eta)) => {
 			swap.last_check_error = None;
 			trades::store_swap_trade(&context, &swap, &skey, &*swap_lock)?;
+			if swap.is_stalled(&node_client, crate::swap::swap::get_cur_time()) {
+				warn!(
+					"Swap {} is waiting for confirmations at state {} longer than expected. Please check if your node is synced and the transactions are not dropped.",
+					swap.id, swap.state
+				);
+			}
 			let last_error = swap.get_last_error();
 			Ok((
 				next_state_id,
@@ -807,6 +1126,74 @@ where
 	Ok(res)
 }
 
+/// Re-broadcast the redeem or refund transaction of the swap if it is published but not confirmed yet.
+/// Return the status before the re-broadcast, caller should stop once it is final.
+pub fn swap_rebroadcast<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	swap_id: &str,
+) -> Result<RebroadcastStatus, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let (node_client, keychain) = {
+		wallet_lock!(wallet_inst, w);
+		let node_client = w.w2n_client().clone();
+		let keychain = w.keychain(keychain_mask)?;
+		(node_client, keychain)
+	};
+
+	let skey = get_swap_storage_key(&keychain)?;
+	let swap_lock = trades::get_swap_lock(&swap_id.to_string());
+	let _l = swap_lock.lock();
+
+	let (context, mut swap) = trades::get_swap_trade(swap_id, &skey, &*swap_lock)?;
+
+	let (uri1, uri2) = trades::get_electrumx_uri(
+		&swap.secondary_currency,
+		&swap.electrum_node_uri1,
+		&swap.electrum_node_uri2,
+	)?;
+	let swap_api = crate::swap::api::create_instance(
+		&swap.secondary_currency,
+		node_client.clone(),
+		uri1,
+		uri2,
+	)?;
+
+	let tx_conf = swap_api.request_tx_confirmations(&keychain, &swap)?;
+	let status = rebroadcast::get_rebroadcast_status(&swap, &tx_conf);
+	if let RebroadcastStatus::Pending(tx) = &status {
+		rebroadcast::rebroadcast_tx(&*swap_api, &node_client, &keychain, &mut swap, &context, tx)?;
+		swap.add_journal_message(format!("{} is re-broadcasted", tx));
+		trades::store_swap_trade(&context, &swap, &skey, &*swap_lock)?;
+	}
+	Ok(status)
+}
+
+// Send the message to the swap counterparty. The rate limit interval starts only when the
+// message is delivered, failed attempts can be retried right away.
+fn send_swap_message<F>(
+	swap: &Swap,
+	message: Message,
+	message_sender: F,
+	config: &SwapConfig,
+) -> Result<(bool, String), Error>
+where
+	F: FnOnce(Message, String, String) -> Result<(bool, String), Error>,
+{
+	rate_limit::check_message_rate(&swap.id, &swap.communication_address, config)?;
+	let res = message_sender(
+		message,
+		swap.communication_method.clone(),
+		swap.communication_address.clone(),
+	)?;
+	rate_limit::register_message_sent(&swap.id, &swap.communication_address);
+	Ok(res)
+}
+
 fn swap_process_impl<'a, L, C, K, F>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
 	keychain_mask: Option<&SecretKey>,
@@ -820,6 +1207,7 @@ fn swap_process_impl<'a, L, C, K, F>(
 	buyer_refund_address: Option<String>,
 	secondary_fee: Option<f32>,
 	secondary_address: Option<String>,
+	config: &SwapConfig,
 ) -> Result<StateProcessRespond, Error>
 where
 	L: WalletLCProvider<'a, C, K>,
@@ -871,11 +1259,14 @@ where
 		| Action::BuyerSendAcceptOfferMessage(message)
 		| Action::BuyerSendInitRedeemMessage(message)
 		| Action::SellerSendRedeemMessage(message) => {
-			let (has_ack, dest_str) = message_sender(
-				message,
-				swap.communication_method.clone(),
-				swap.communication_address.clone(),
-			)?;
+			let (has_ack, dest_str) =
+				send_swap_message(swap, message.clone(), message_sender, config)?;
+			log_swap_message(
+				&swap.id.to_string(),
+				MessageDirection::Sent,
+				&message,
+				&get_swap_storage_key(&keychain)?,
+			);
 			let process_respond = fsm.process(Input::Execute, swap, &context, &tx_conf)?;
 			swap.append_to_last_message(&format!(", {}", dest_str));
 			if has_ack {
@@ -904,7 +1295,7 @@ where
 			})?;
 			// processing the message with a regular API.
 
-			let message = Message::from_json(&contents)?;
+			let message = Message::parse(contents.as_bytes())?;
 			if message.id != swap.id {
 				return Err(ErrorKind::Generic(format!(
 					"Message id {} doesn't match selected trade id",
@@ -917,10 +1308,13 @@ where
 				wallet_inst.clone(),
 				keychain_mask,
 				&contents,
+				None,
 				Some(swap_lock.clone()),
+				&SwapAcceptArgs::default(),
+				config,
 			)?;
 		}
-		Action::SellerPublishMwcLockTx => {
+		Action::SellerPublishMwcLockTx | Action::SellerRetryMwcLockTx => {
 			wallet_lock!(wallet_inst, w);
 			// Checking if transaction is already created.
 			let kernel = &swap.lock_slate.tx.body.kernels[0].excess;
@@ -934,11 +1328,13 @@ where
 					&swap.lock_slate,
 					context.lock_nonce.clone(),
 					seller_context.inputs.clone(),
-					vec![(
+					std::iter::once((
 						seller_context.change_output.clone(),
-						None,
 						seller_context.change_amount,
-					)],
+					))
+					.chain(seller_context.change_split.clone())
+					.map(|(key_id, amount)| (key_id, None, amount))
+					.collect(),
 					seller_context.parent_key_id.clone(),
 					0,
 				)?;
@@ -1033,6 +1429,7 @@ pub fn swap_process<'a, L, C, K, F>(
 	electrum_node_uri1: Option<String>,
 	electrum_node_uri2: Option<String>,
 	wait_for_backup1: bool,
+	config: &SwapConfig,
 ) -> Result<StateProcessRespond, Error>
 where
 	L: WalletLCProvider<'a, C, K>,
@@ -1076,6 +1473,7 @@ where
 		buyer_refund_address,
 		secondary_fee,
 		secondary_address,
+		config,
 	) {
 		Ok(mut respond) => {
 			swap.last_process_error = None;
@@ -1155,14 +1553,123 @@ pub fn swap_create_from_offer<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
 	keychain_mask: Option<&SecretKey>,
 	message_filename: String,
+	accept_args: &SwapAcceptArgs,
+	config: &SwapConfig,
 ) -> Result<String, Error>
 where
 	L: WalletLCProvider<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	// Updating wallet state first because we need to select outputs.
-	let mut file = File::open(message_filename.clone()).map_err(|e| {
+	// processing the message with a regular API.
+	let (contents, message) = read_offer_file(&message_filename)?;
+
+	swap_income_message(
+		wallet_inst,
+		keychain_mask,
+		&contents,
+		None,
+		None,
+		accept_args,
+		config,
+	)?;
+	Ok(message.id.to_string())
+}
+
+/// Recommendation if the swap trade can be continued, see Swap::safety_status
+pub fn swap_safety_status<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	swap_id: &str,
+	config: &SwapConfig,
+) -> Result<SafetyAssessment, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let node_client = {
+		wallet_lock!(wallet_inst, w);
+		w.w2n_client().clone()
+	};
+	let swap = swap_get(wallet_inst, keychain_mask, swap_id)?;
+	let node_client = node_client_with_timeout(&node_client, config.node_call_timeout);
+	Ok(swap.safety_status(&node_client)?)
+}
+
+/// Check if the swap trade is waiting for the confirmations longer than expected, see Swap::is_stalled
+pub fn swap_is_stalled<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	swap_id: &str,
+	config: &SwapConfig,
+) -> Result<bool, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let node_client = {
+		wallet_lock!(wallet_inst, w);
+		w.w2n_client().clone()
+	};
+	let swap = swap_get(wallet_inst, keychain_mask, swap_id)?;
+	let node_client = node_client_with_timeout(&node_client, config.node_call_timeout);
+	Ok(swap.is_stalled(&node_client, crate::swap::swap::get_cur_time()))
+}
+
+/// Certificate of the completed swap trade, see Swap::completion_certificate
+pub fn swap_completion_certificate<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	swap_id: &str,
+) -> Result<CompletionCertificate, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let swap = swap_get(wallet_inst, keychain_mask, swap_id)?;
+	Ok(swap.completion_certificate()?)
+}
+
+/// Simulate the refund of the swap trade, see Swap::simulate_refund
+pub fn swap_simulate_refund<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	swap_id: &str,
+) -> Result<RefundSimulation, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let swap = swap_get(wallet_inst, keychain_mask, swap_id)?;
+	Ok(swap.simulate_refund()?)
+}
+
+/// Simulate the Buyer's refund for the offer from the file before accepting it.
+/// secondary_fee - fee rate for the refund transaction, the currency default if None.
+pub fn swap_simulate_offer_refund(
+	message_filename: String,
+	secondary_address: Option<String>,
+	secondary_fee: Option<f32>,
+) -> Result<RefundSimulation, Error> {
+	let (_, message) = read_offer_file(&message_filename)?;
+	let (_, offer, secondary_update) = message.unwrap_offer()?;
+	let secondary_fee =
+		secondary_fee.unwrap_or(offer.secondary_currency.get_default_fee(&offer.network));
+	Ok(BuyApi::simulate_offer_refund(
+		&offer,
+		secondary_update,
+		secondary_address.as_ref(),
+		secondary_fee,
+	)?)
+}
+
+// Read the offer message from the file. Return the file content and the parsed message.
+fn read_offer_file(message_filename: &str) -> Result<(String, Message), Error> {
+	let mut file = File::open(message_filename).map_err(|e| {
 		ErrorKind::Generic(format!("Unable to open file {}, {}", message_filename, e))
 	})?;
 	let mut contents = String::new();
@@ -1173,36 +1680,40 @@ where
 		))
 	})?;
 
-	// processing the message with a regular API.
-	// but first let's check if the message type matching expected
-	let message = Message::from_json(&contents)?;
+	// let's check if the message type matching expected
+	let message = Message::parse(contents.as_bytes())?;
 	if !message.is_offer() {
 		return Err(
 			ErrorKind::Generic("Expected offer message, get different one".to_string()).into(),
 		);
 	}
-
-	swap_income_message(wallet_inst, keychain_mask, &contents, None)?;
-	Ok(message.id.to_string())
+	Ok((contents, message))
 }
 
 /// Processing swap income message. Note result of that can be a new offer of modification of the current one
 /// We only notify user about that, no permission will be ask.
 /// Reason: Nothing will be done with the funds until user will go forward manually
+/// accept_args are applied if the message is an offer.
+/// verified_sender - sender address verified by the transport (MQS message signature),
+/// None if the sender is unknown.
 /// Return: option ack message
 pub fn swap_income_message<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
 	keychain_mask: Option<&SecretKey>,
 	swap_message: &str,
+	verified_sender: Option<&str>,
 	swap_lock: Option<Arc<Mutex<()>>>,
+	accept_args: &SwapAcceptArgs,
+	config: &SwapConfig,
 ) -> Result<Option<Message>, Error>
 where
 	L: WalletLCProvider<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	let message = Message::from_json(swap_message)?;
+	let message = Message::parse(swap_message.as_bytes())?;
 	let swap_id = message.id.to_string();
+	let received = message.clone();
 
 	debug!("Get swap message {:?}", message);
 
@@ -1234,6 +1745,10 @@ where
 				return Err( ErrorKind::Generic(format!("trade with SwapID {} already exist. Probably you already processed this message", swap_id)).into());
 			}
 
+			// Operator can limit the counterparties that can send the offers. The offer
+			// from_address is provided by the seller, only the transport verified sender counts.
+			crate::swap::swap::check_counterparty_allowed(verified_sender)?;
+
 			let (uri1, uri2) =
 				trades::get_electrumx_uri(&offer_update.secondary_currency, &None, &None)?;
 			let mut swap_api = crate::swap::api::create_instance(
@@ -1253,16 +1768,21 @@ where
 				false,
 				None,
 				0,
+				None,
 			)?;
 
 			let (id, offer, secondary_update) = message.unwrap_offer()?;
-			let swap = BuyApi::accept_swap_offer(
+			let swap = BuyApi::accept_swap_offer_with_overrides(
 				&keychain,
 				&context,
 				id,
 				offer,
 				secondary_update,
 				&node_client,
+				accept_args.min_mwc_confirmations,
+				accept_args.min_secondary_confirmations,
+				accept_args.external_ref.clone(),
+				config,
 			)?;
 
 			trades::store_swap_trade(&context, &swap, &skey, &*lock)?;
@@ -1332,9 +1852,79 @@ where
 			))
 		}
 	};
+	log_swap_message(&swap_id, MessageDirection::Received, &received, &skey);
 	Ok(ack_msg)
 }
 
+// Message log is needed for the UI only, failure to write it must not stop the swap
+fn log_swap_message(
+	swap_id: &str,
+	direction: MessageDirection,
+	message: &Message,
+	enc_key: &SecretKey,
+) {
+	if let Err(e) = trades::append_swap_message_log(swap_id, direction, message, enc_key) {
+		warn!(
+			"Unable to update the message log for swap {}, {}",
+			swap_id, e
+		);
+	}
+}
+
+/// Messages that were sent and received for the swap trade. Signatures and secrets are redacted.
+pub fn swap_message_log<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	swap_id: &str,
+) -> Result<SwapMessageLog, Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	wallet_lock!(wallet_inst, w);
+	let keychain = w.keychain(keychain_mask)?;
+	let skey = get_swap_storage_key(&keychain)?;
+	Ok(trades::get_swap_message_log(swap_id, &skey)?)
+}
+
+// Move the derivation index of the accounts above the keys that the swap is using. The next_child
+// used by the ordinary transactions will never return them.
+fn reserve_swap_key_ids<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	swap: &Swap,
+	context: &Context,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let mut next_child_index: HashMap<Identifier, u32> = HashMap::new();
+	for key_id in swap.reserved_key_ids(context) {
+		let parent_key_id = key_id.parent_path();
+		let child_index = key_id.to_path().last_path_index() + 1;
+		let index = next_child_index.entry(parent_key_id).or_insert(0);
+		if child_index > *index {
+			*index = child_index;
+		}
+	}
+
+	for (parent_key_id, child_index) in next_child_index {
+		if child_index > wallet.current_child_index(&parent_key_id)? {
+			debug!(
+				"Swap {} reserves the keys of {} up to index {}",
+				swap.id, parent_key_id, child_index
+			);
+			let mut batch = wallet.batch(keychain_mask)?;
+			batch.save_child_index(&parent_key_id, child_index)?;
+			batch.commit()?;
+		}
+	}
+	Ok(())
+}
+
 // Local Helper method to create a context
 fn create_context<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
@@ -1345,6 +1935,7 @@ fn create_context<'a, T: ?Sized, C, K>(
 	is_seller: bool,
 	inputs: Option<Vec<(Identifier, Option<u64>, u64)>>,
 	change_amount: u64,
+	change_split: Option<u64>,
 ) -> Result<Context, Error>
 where
 	T: WalletBackend<'a, C, K>,
@@ -1376,15 +1967,75 @@ where
 		keys.push(wallet.next_child(keychain_mask, Some(parent_key_id.clone()))?);
 	}
 
-	let context = (**swap_api).create_context(
+	let mut context = (**swap_api).create_context(
 		keychain,
 		secondary_currency,
 		is_seller,
 		inputs,
 		change_amount,
 		keys,
-		parent_key_id,
+		parent_key_id.clone(),
 	)?;
 
+	// Round lock change is split, the second change output needs its own key
+	if let Some(split_amount) = change_split {
+		if let RoleContext::Seller(seller_context) = &mut context.role_context {
+			let split_key = wallet.next_child(keychain_mask, Some(parent_key_id))?;
+			seller_context.change_split = Some((split_key, split_amount));
+		}
+	}
+
 	Ok(context)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::swap;
+	use crate::swap::tests::{context_sell, keychain, seller_offer_message, TestNodeClient};
+	use serial_test::serial;
+
+	#[test]
+	#[serial]
+	fn test_send_swap_message_rate_limit() {
+		swap::set_test_mode(true);
+		swap::swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		let config = SwapConfig::default();
+		assert!(config.message_min_interval_sec > 0);
+
+		// Failed delivery doesn't start the interval
+		let res = send_swap_message(
+			&swap,
+			message.clone(),
+			|_, _, _| Err(crate::ErrorKind::GenericError("offline".to_string()).into()),
+			&config,
+		);
+		assert!(res.is_err());
+		let (has_ack, dest) = send_swap_message(
+			&swap,
+			message.clone(),
+			|_, _, dest| Ok((true, dest)),
+			&config,
+		)
+		.unwrap();
+		assert!(has_ack);
+		assert_eq!(dest, swap.communication_address);
+
+		// The message is not passed to the transport until the interval is over
+		let res = send_swap_message(
+			&swap,
+			message.clone(),
+			|_, _, _| panic!("Rate limited message is sent"),
+			&config,
+		);
+		assert!(format!("{}", res.unwrap_err()).contains("rate limit"));
+
+		swap::swap::set_testing_cur_time(1567632152 + config.message_min_interval_sec);
+		send_swap_message(&swap, message, |_, _, dest| Ok((false, dest)), &config).unwrap();
+	}
+}
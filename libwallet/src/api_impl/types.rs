@@ -107,6 +107,10 @@ pub struct InitTxArgs {
 	)]
 	#[serde(default)]
 	pub payment_proof_recipient_address: Option<ProvableAddress>,
+	/// If true, bind the slate to the payment proof recipient. Only that recipient can
+	/// respond to the slate.
+	#[serde(default)]
+	pub bind_to_recipient: Option<bool>,
 	/// address of another party to store in tx history.
 	#[serde(default)]
 	pub address: Option<String>,
@@ -130,6 +134,14 @@ pub struct InitTxArgs {
 	/// transaction to a destination and optionally finalize the result
 	#[serde(default)]
 	pub send_args: Option<InitTxSendArgs>,
+	/// If true, the nonce is derived from the wallet seed, so the slate can be exported after
+	/// round 1 and finalized on another machine with the same seed.
+	#[serde(default)]
+	pub split_signing: Option<bool>,
+	/// If true, inputs, outputs and kernels of the transaction are sorted, so the same
+	/// slate is always built the same way.
+	#[serde(default)]
+	pub deterministic_order: Option<bool>,
 	/// Selected outputs. If none, will use all outputs
 	pub outputs: Option<Vec<String>>, // outputs to include into the transaction
 }
@@ -170,10 +182,13 @@ impl Default for InitTxArgs {
 			ttl_blocks: None,
 			estimate_only: Some(false),
 			payment_proof_recipient_address: None,
+			bind_to_recipient: None,
 			address: None,
 			exclude_change_outputs: Some(false),
 			minimum_confirmations_change_outputs: 1,
 			send_args: None,
+			split_signing: Some(false),
+			deterministic_order: Some(false),
 			outputs: None,
 		}
 	}
@@ -231,6 +246,10 @@ pub struct IssueInvoiceTxArgs {
 	/// recipient address
 	#[serde(default)]
 	pub address: Option<String>,
+	/// If true, the nonce is derived from the wallet seed, so the invoice can be exported after
+	/// round 1 and finalized on another machine with the same seed.
+	#[serde(default)]
+	pub split_signing: Option<bool>,
 }
 
 impl Default for IssueInvoiceTxArgs {
@@ -241,6 +260,7 @@ impl Default for IssueInvoiceTxArgs {
 			message: None,
 			target_slate_version: None,
 			address: None,
+			split_signing: Some(false),
 		}
 	}
 }
@@ -354,6 +374,40 @@ pub struct SwapStartArgs {
 	pub electrum_node_uri1: Option<String>,
 	/// ElectrumX failover URI2
 	pub electrum_node_uri2: Option<String>,
+	/// External reference of the swap, for example the exchange order id. It is shared
+	/// with the Buyer and echoed in every message.
+	#[serde(default)]
+	pub external_ref: Option<String>,
 	/// Dry run flag. Use true if you want to validate config
 	pub dry_run: bool,
 }
+
+/// Buyer own policy that is applied when the swap offer is accepted
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SwapAcceptArgs {
+	/// Buyer waits for that many MWC lock confirmations if it is more than the offer requires
+	pub min_mwc_confirmations: Option<u64>,
+	/// Buyer waits for that many Secondary lock confirmations if it is more than the offer requires
+	pub min_secondary_confirmations: Option<u64>,
+	/// Buyer external reference. If the offer has the reference, it must match.
+	#[serde(default)]
+	pub external_ref: Option<String>,
+}
+
+/// Result of the slate verification against the node
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SlateVerification {
+	/// Slate Id
+	pub slate_id: String,
+	/// Slate inputs: <commit>, <is unspent at the node>
+	pub inputs: Vec<(String, bool)>,
+	/// Found problems. Slate is valid if there are no problems
+	pub problems: Vec<String>,
+}
+
+impl SlateVerification {
+	/// True if no problems was found
+	pub fn is_valid(&self) -> bool {
+		self.problems.is_empty()
+	}
+}
@@ -63,6 +63,14 @@ pub enum ErrorKind {
 	#[fail(display = "Fee Error: {}", _0)]
 	Fee(String),
 
+	/// Slate amount is different from what receiver expects
+	#[fail(display = "Slate amount mismatch. Expected {}, slate has {}", _0, _1)]
+	ExpectedAmountMismatch(u64, u64),
+
+	/// Slate output has the same commitment as the output that the wallet already has
+	#[fail(display = "Slate output {} duplicates the existing wallet output", _0)]
+	DuplicateOutputCommitment(String),
+
 	/// LibTX Error
 	#[fail(display = "LibTx Error, {}", _0)]
 	LibTX(grin_core::libtx::ErrorKind),
@@ -115,6 +123,18 @@ pub enum ErrorKind {
 	#[fail(display = "Node API error: {}", _0)]
 	Node(String),
 
+	/// Node call is not completed in time
+	#[fail(display = "Node API call timeout: {}", _0)]
+	NodeTimeout(String),
+
+	/// Node responded that the posted transaction is not accepted
+	#[fail(display = "Node rejected the transaction: {}", _0)]
+	TxRejected(String),
+
+	/// Operation is cancelled by the user
+	#[fail(display = "Operation is cancelled, {}", _0)]
+	Cancelled(String),
+
 	/// Error when not found ready to process sync data node
 	#[fail(display = "Node not ready or not available")]
 	NodeNotReady,
@@ -171,6 +191,14 @@ pub enum ErrorKind {
 	#[fail(display = "Transaction already confirmed error")]
 	TransactionAlreadyConfirmed,
 
+	/// Slate is bound to another recipient, or wasn't signed by the bound recipient
+	#[fail(display = "Slate {} is bound to another recipient", _0)]
+	RecipientBindingMismatch(String),
+
+	/// Slate kernel features don't match the slate
+	#[fail(display = "Kernel features mismatch, {}", _0)]
+	KernelFeaturesMismatch(String),
+
 	/// Transaction has already been received
 	#[fail(display = "Transaction {} has already been received", _0)]
 	TransactionAlreadyReceived(String),
@@ -219,6 +247,18 @@ pub enum ErrorKind {
 	#[fail(display = "Unknown Slate Version: {}", _0)]
 	SlateVersion(u16),
 
+	/// Slate is built by a newer wallet
+	#[fail(
+		display = "Slate version {} is newer than supported version {}, please upgrade your wallet",
+		found, supported
+	)]
+	SlateVersionTooNew {
+		/// Version of the slate
+		found: u16,
+		/// Latest version that this wallet supports
+		supported: u16,
+	},
+
 	/// Slate Validation error
 	#[fail(display = "Unable to validate slate, {}", _0)]
 	SlateValidation(String),
@@ -259,6 +299,10 @@ pub enum ErrorKind {
 	#[fail(display = "Can't convert payment proof message, {}", _0)]
 	PaymentProofMessageSer(String),
 
+	/// Wallet policy requires the payment proof, but the slate doesn't have it
+	#[fail(display = "Payment proof is required by the wallet policy, {}", _0)]
+	PaymentProofRequired(String),
+
 	/// Payment Proof address
 	#[fail(display = "Payment Proof address error: {}", _0)]
 	PaymentProofAddress(String),
@@ -271,6 +315,34 @@ pub enum ErrorKind {
 	#[fail(display = "Transaction Expired")]
 	TransactionExpired,
 
+	/// Slate TTL cutoff height is already reached by the chain
+	#[fail(
+		display = "Slate TTL cutoff height {} is in the past, chain tip is {}",
+		_0, _1
+	)]
+	TtlCutoffExpired(u64, u64),
+
+	/// Slate TTL cutoff height is not after the slate height
+	#[fail(
+		display = "Slate TTL cutoff height {} is not after the slate height {}",
+		_0, _1
+	)]
+	TtlCutoffBeforeHeight(u64, u64),
+
+	/// Slate TTL cutoff height is before the slate lock height, the transaction can't be mined in time
+	#[fail(
+		display = "Slate TTL cutoff height {} is before the lock height {}",
+		_0, _1
+	)]
+	TtlCutoffBeforeLockHeight(u64, u64),
+
+	/// Slate TTL cutoff height is too far from the chain tip
+	#[fail(
+		display = "Slate TTL cutoff height {} is too far from the chain tip {}",
+		_0, _1
+	)]
+	TtlCutoffTooFar(u64, u64),
+
 	/// Stored Transaction issues
 	#[fail(display = "Stored transaction error, {}", _0)]
 	StoredTransactionError(String),
@@ -21,8 +21,10 @@
 #![deny(unused_mut)]
 #![warn(missing_docs)]
 
+pub mod checkpoint;
 pub mod keys;
 pub mod scan;
 pub mod selection;
 pub mod tx;
 pub mod updater;
+pub mod version_log;
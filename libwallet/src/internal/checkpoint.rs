@@ -0,0 +1,253 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checkpoints of the partial slates. If the wallet dies between the signing rounds,
+//! the slate can be resumed from the checkpoint. Checkpoints are disabled by default.
+
+use crate::error::{Error, ErrorKind};
+use crate::grin_util::RwLock;
+use crate::slate::Slate;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Location of the slate checkpoints
+pub const SLATE_CHECKPOINT_DIR: &str = "slate_checkpoints";
+
+lazy_static! {
+	static ref SLATE_CHECKPOINT_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+/// Init file storage for the slate checkpoints. If not enabled, checkpoints are not saved.
+pub fn init_slate_checkpoint_backend(data_file_dir: &str, enabled: bool) {
+	if !enabled {
+		SLATE_CHECKPOINT_PATH.write().take();
+		return;
+	}
+	let path = Path::new(data_file_dir).join(SLATE_CHECKPOINT_DIR);
+	fs::create_dir_all(&path).expect("Could not create slate checkpoint storage directory!");
+	SLATE_CHECKPOINT_PATH.write().replace(path);
+}
+
+fn checkpoint_file(id: &Uuid) -> Option<PathBuf> {
+	SLATE_CHECKPOINT_PATH
+		.read()
+		.as_ref()
+		.map(|p| p.join(format!("{}.slate", id)))
+}
+
+/// Save the partial slate. Previous checkpoint for this slate is replaced.
+pub fn save_slate_checkpoint(slate: &Slate) -> Result<(), Error> {
+	let file_name = match checkpoint_file(&slate.id) {
+		Some(f) => f,
+		None => return Ok(()),
+	};
+	let content = serde_json::to_string(slate)
+		.map_err(|e| ErrorKind::Format(format!("Unable to serialize slate {}, {}", slate.id, e)))?;
+	fs::write(&file_name, content).map_err(|e| {
+		ErrorKind::IO(format!(
+			"Unable to save slate checkpoint {:?}, {}",
+			file_name, e
+		))
+	})?;
+	Ok(())
+}
+
+/// Load the slate from the checkpoint. None if there is no checkpoint for this slate.
+pub fn resume_slate(id: &Uuid) -> Result<Option<Slate>, Error> {
+	let file_name = match checkpoint_file(id) {
+		Some(f) => f,
+		None => return Ok(None),
+	};
+	if !file_name.exists() {
+		return Ok(None);
+	}
+	let content = fs::read_to_string(&file_name).map_err(|e| {
+		ErrorKind::IO(format!(
+			"Unable to read slate checkpoint {:?}, {}",
+			file_name, e
+		))
+	})?;
+	Ok(Some(Slate::deserialize_upgrade(&content)?))
+}
+
+/// Save the slate that came back from the other party before it is processed. The slate
+/// must extend our checkpoint, if there is one.
+pub fn update_slate_checkpoint(slate: &Slate) -> Result<(), Error> {
+	let slate = match resume_slate(&slate.id)? {
+		Some(checkpoint) => select_resumed_slate(checkpoint, Some(slate.clone()))?,
+		None => slate.clone(),
+	};
+	save_slate_checkpoint(&slate)
+}
+
+/// Delete the checkpoint, the slate is completed or cancelled.
+pub fn delete_slate_checkpoint(id: &Uuid) -> Result<(), Error> {
+	if let Some(file_name) = checkpoint_file(id) {
+		if file_name.exists() {
+			fs::remove_file(&file_name).map_err(|e| {
+				ErrorKind::IO(format!(
+					"Unable to delete slate checkpoint {:?}, {}",
+					file_name, e
+				))
+			})?;
+		}
+	}
+	Ok(())
+}
+
+// Number of the completed steps: participants data and their signatures
+fn slate_progress(slate: &Slate) -> usize {
+	slate.participant_data.len()
+		+ slate
+			.participant_data
+			.iter()
+			.filter(|p| p.is_complete())
+			.count()
+}
+
+/// Select the slate to continue with. The other party might advance the slate after our
+/// checkpoint was saved, in this case the received slate is used. The received slate must
+/// extend the checkpoint, the participant data that we already have can't be changed.
+pub fn select_resumed_slate(checkpoint: Slate, received: Option<Slate>) -> Result<Slate, Error> {
+	let received = match received {
+		Some(s) => s,
+		None => return Ok(checkpoint),
+	};
+	if received.id != checkpoint.id {
+		return Err(ErrorKind::GenericError(format!(
+			"Slate {} doesn't match the checkpoint {}",
+			received.id, checkpoint.id
+		))
+		.into());
+	}
+	for p in &checkpoint.participant_data {
+		let matched = received.participant_data.iter().any(|r| {
+			r.id == p.id
+				&& r.public_blind_excess == p.public_blind_excess
+				&& r.public_nonce == p.public_nonce
+		});
+		if !matched {
+			return Err(ErrorKind::GenericError(format!(
+				"Slate {} participant {} data doesn't match the checkpoint",
+				received.id, p.id
+			))
+			.into());
+		}
+	}
+	if slate_progress(&received) >= slate_progress(&checkpoint) {
+		Ok(received)
+	} else {
+		Ok(checkpoint)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grin_core::libtx::{build, tx_fee, ProofBuilder};
+	use crate::grin_keychain::{ExtKeychain, ExtKeychainPath, Keychain};
+	use crate::grin_util::secp::key::SecretKey;
+	use rand::thread_rng;
+
+	#[test]
+	fn test_slate_checkpoint_resume() {
+		let dir = std::env::temp_dir().join("mwc_wallet_checkpoint_test");
+		let _ = fs::remove_dir_all(&dir);
+		init_slate_checkpoint_backend(dir.to_str().unwrap(), true);
+
+		let kc_send = ExtKeychain::from_random_seed(false).unwrap();
+		let kc_recv = ExtKeychain::from_random_seed(false).unwrap();
+		let key_id = |i| ExtKeychainPath::new(1, i, 0, 0, 0).to_identifier();
+		let fee = tx_fee(1, 2, 1, None);
+		let amount = 600_000_000;
+		let change = 1_000_000_000 - amount - fee;
+
+		// Sender, round 1
+		let mut slate = Slate::blank(2);
+		slate.amount = amount;
+		slate.fee = fee;
+		let blind = slate
+			.add_transaction_elements(
+				&kc_send,
+				&ProofBuilder::new(&kc_send),
+				vec![
+					build::input(1_000_000_000, key_id(1)),
+					build::output(change, key_id(2)),
+				],
+				false,
+			)
+			.unwrap();
+		let mut send_key = blind.secret_key(kc_send.secp()).unwrap();
+		let send_nonce = SecretKey::new(kc_send.secp(), &mut thread_rng());
+		slate
+			.fill_round_1(&kc_send, &mut send_key, &send_nonce, 0, None, false)
+			.unwrap();
+		save_slate_checkpoint(&slate).unwrap();
+		let id = slate.id.clone();
+		drop(slate);
+
+		// Sender died, the slate is resumed
+		let mut slate = resume_slate(&id).unwrap().unwrap();
+		assert_eq!(slate.participant_data.len(), 1);
+		assert!(resume_slate(&Uuid::new_v4()).unwrap().is_none());
+
+		// Recipient
+		let blind = slate
+			.add_transaction_elements(
+				&kc_recv,
+				&ProofBuilder::new(&kc_recv),
+				vec![build::output(amount, key_id(1))],
+				false,
+			)
+			.unwrap();
+		let mut recv_key = blind.secret_key(kc_recv.secp()).unwrap();
+		let recv_nonce = SecretKey::new(kc_recv.secp(), &mut thread_rng());
+		slate
+			.fill_round_1(&kc_recv, &mut recv_key, &recv_nonce, 1, None, false)
+			.unwrap();
+		slate
+			.fill_round_2(&kc_recv, &recv_key, &recv_nonce, 1)
+			.unwrap();
+
+		// Recipient advanced the slate, it is preferred over the checkpoint
+		let checkpoint = resume_slate(&id).unwrap().unwrap();
+		let mut slate = select_resumed_slate(checkpoint.clone(), Some(slate)).unwrap();
+		assert_eq!(slate.participant_data.len(), 2);
+		// Slate with replaced sender data is rejected
+		let mut forged = slate.clone();
+		forged.participant_data[0].public_nonce = forged.participant_data[1].public_nonce;
+		assert!(select_resumed_slate(checkpoint.clone(), Some(forged.clone())).is_err());
+
+		// Response is saved before finalizing, the forged one can't replace it
+		update_slate_checkpoint(&slate).unwrap();
+		assert!(update_slate_checkpoint(&forged).is_err());
+		let resumed = resume_slate(&id).unwrap().unwrap();
+		assert_eq!(resumed.participant_data.len(), 2);
+		assert_eq!(
+			resumed.participant_data[1].public_nonce,
+			slate.participant_data[1].public_nonce
+		);
+
+		// Sender, round 2
+		slate
+			.fill_round_2(&kc_send, &send_key, &send_nonce, 0)
+			.unwrap();
+		slate.finalize(&kc_send).unwrap();
+		delete_slate_checkpoint(&id).unwrap();
+		assert!(resume_slate(&id).unwrap().is_none());
+
+		init_slate_checkpoint_backend(dir.to_str().unwrap(), false);
+	}
+}
@@ -29,6 +29,7 @@ use crate::proof::proofaddress;
 use crate::slate::Slate;
 use crate::types::*;
 use grin_wallet_util::grin_util as util;
+use rand::Rng;
 use std::collections::HashMap;
 
 /// Initialize a transaction on the sender side, returns a corresponding
@@ -47,6 +48,7 @@ pub fn build_send_tx<'a, T: ?Sized, C, K>(
 	selection_strategy_is_use_all: bool,
 	parent_key_id: Identifier,
 	use_test_nonce: bool,
+	deterministic_order: bool,
 	outputs: &Option<Vec<String>>, // outputs to include into the transaction
 	routputs: usize,               // Number of resulting outputs. Normally it is 1
 	exclude_change_outputs: bool,
@@ -76,7 +78,12 @@ where
 	// Update the fee on the slate so we account for this when building the tx.
 	slate.fee = fee;
 
-	let blinding = slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), elems)?;
+	let blinding = slate.add_transaction_elements(
+		keychain,
+		&ProofBuilder::new(keychain),
+		elems,
+		deterministic_order,
+	)?;
 
 	// Create our own private context
 	let mut context = Context::new(
@@ -227,6 +234,7 @@ where
 		t
 	};
 	wallet.store_tx(&format!("{}", tx_entry.tx_slate_id.unwrap()), &slate.tx)?;
+	wallet.store_slate(&format!("{}", tx_entry.tx_slate_id.unwrap()), slate)?;
 	Ok(())
 }
 
@@ -244,6 +252,7 @@ pub fn build_recipient_output<'a, T: ?Sized, C, K>(
 	key_id_opt: Option<&str>,
 	output_amounts: Option<Vec<u64>>,
 	use_test_rng: bool,
+	deterministic_order: bool,
 	num_outputs: usize, // Number of outputs for this transaction. Normally it is 1
 ) -> Result<(Identifier, Context), Error>
 where
@@ -315,8 +324,12 @@ where
 		out_vec.push(build::output(kva.1, kva.0.clone()));
 	}
 
-	let blinding =
-		slate.add_transaction_elements(&keychain, &ProofBuilder::new(&keychain), out_vec)?;
+	let blinding = slate.add_transaction_elements(
+		&keychain,
+		&ProofBuilder::new(&keychain),
+		out_vec,
+		deterministic_order,
+	)?;
 
 	// Add blinding sum to our context
 	let mut context = Context::new(
@@ -624,6 +637,23 @@ where
 	Ok((parts, change_amounts_derivations))
 }
 
+/// Change that is a multiple of this amount looks round, 0.001 MWC
+pub const ROUND_CHANGE_UNIT: u64 = 1_000_000;
+
+/// Split the change amount into the change outputs. Round change (like exactly 1 MWC) reveals
+/// which output is the change, so it is split into two outputs at a random point. The second
+/// output fee is paid from the change. Other amounts, or change that is too small to pay for
+/// the second output, stay as a single output. The split is defined by the rng.
+pub fn split_change_amount<R: Rng>(change: u64, extra_output_fee: u64, rng: &mut R) -> Vec<u64> {
+	if change == 0 || change % ROUND_CHANGE_UNIT != 0 || change < extra_output_fee * 2 {
+		return vec![change];
+	}
+	let change = change - extra_output_fee;
+	// Both parts are somewhere around the half, so none of them is dust
+	let first = rng.gen_range(change / 4, change - change / 4);
+	vec![first, change - first]
+}
+
 /// Select spendable coins from a wallet.
 /// Default strategy is to spend the maximum number of outputs (up to
 /// max_outputs). Alternative strategy is to spend smallest outputs first
@@ -767,3 +797,43 @@ fn select_from(amount: u64, select_all: bool, outputs: Vec<OutputData>) -> Optio
 		None
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::rngs::mock::StepRng;
+
+	#[test]
+	fn test_split_change_amount() {
+		let extra_fee = 4_000_000;
+
+		// Random looking change stays as a single output
+		let mut rng = StepRng::new(1_234_567_890, 1);
+		assert_eq!(
+			split_change_amount(1_234_567_891, extra_fee, &mut rng),
+			vec![1_234_567_891]
+		);
+		assert_eq!(split_change_amount(0, extra_fee, &mut rng), vec![0]);
+		// Round, but too small to pay for the second output
+		assert_eq!(
+			split_change_amount(5_000_000, extra_fee, &mut rng),
+			vec![5_000_000]
+		);
+
+		// Exactly 1 MWC is split, the second output is paid from the change
+		let change = 1_000_000_000;
+		let split = split_change_amount(change, extra_fee, &mut StepRng::new(1_234_567_890, 1));
+		assert_eq!(split.len(), 2);
+		assert_eq!(split.iter().sum::<u64>(), change - extra_fee);
+		let rest = change - extra_fee;
+		assert!(split
+			.iter()
+			.all(|a| *a >= rest / 4 && *a <= rest - rest / 4));
+		// Same rng gives the same split
+		assert_eq!(
+			split_change_amount(change, extra_fee, &mut StepRng::new(1_234_567_890, 1)),
+			split
+		);
+		assert_eq!(split_change_amount(8_000_000, extra_fee, &mut rng).len(), 2);
+	}
+}
@@ -30,7 +30,7 @@ use crate::proof::proofaddress;
 use crate::proof::proofaddress::{get_address_index, ProvableAddress};
 use crate::proof::tx_proof::{push_proof_for_slate, TxProof};
 use crate::signature::Signature as otherSignature;
-use crate::slate::Slate;
+use crate::slate::{Slate, MAX_TTL_CUTOFF_BLOCKS};
 use crate::types::{Context, NodeClient, StoredProofInfo, TxLogEntryType, WalletBackend};
 use crate::{Error, ErrorKind};
 use ed25519_dalek::Keypair as DalekKeypair;
@@ -39,12 +39,23 @@ use ed25519_dalek::SecretKey as DalekSecretKey;
 use ed25519_dalek::Signature as DalekSignature;
 use ed25519_dalek::{Signer, Verifier};
 use grin_wallet_util::OnionV3Address;
+use std::collections::HashSet;
 
 // static for incrementing test UUIDs
 lazy_static! {
 	static ref SLATE_COUNTER: Mutex<u8> = Mutex::new(0);
 }
 
+/// TTL cutoff height for the slate that is created at the current height. The recipient
+/// rejects the cutoff that is further than MAX_TTL_CUTOFF_BLOCKS, so such slate is not created.
+pub fn ttl_cutoff_height(current_height: u64, ttl_blocks: u64) -> Result<u64, Error> {
+	let cutoff = current_height.saturating_add(ttl_blocks);
+	if ttl_blocks > MAX_TTL_CUTOFF_BLOCKS {
+		return Err(ErrorKind::TtlCutoffTooFar(cutoff, current_height).into());
+	}
+	Ok(cutoff)
+}
+
 /// Creates a new slate for a transaction, can be called by anyone involved in
 /// the transaction (sender(s), receiver(s))
 pub fn new_tx_slate<'a, T: ?Sized, C, K>(
@@ -62,7 +73,7 @@ where
 	let current_height = wallet.w2n_client().get_chain_tip()?.0;
 	let mut slate = Slate::blank(num_participants);
 	if let Some(b) = ttl_blocks {
-		slate.ttl_cutoff_height = Some(current_height + b);
+		slate.ttl_cutoff_height = Some(ttl_cutoff_height(current_height, b)?);
 	}
 	if use_test_rng {
 		{
@@ -163,6 +174,8 @@ pub fn add_inputs_to_slate<'a, T: ?Sized, C, K>(
 	message: Option<String>,
 	is_initator: bool,
 	use_test_rng: bool,
+	derive_nonce: bool,
+	deterministic_order: bool,
 	outputs: &Option<Vec<String>>, // outputs to include into the transaction
 	routputs: usize,               // Number of resulting outputs. Normally it is 1
 	exclude_change_outputs: bool,
@@ -191,12 +204,17 @@ where
 		selection_strategy_is_use_all,
 		parent_key_id.clone(),
 		use_test_rng,
+		deterministic_order,
 		outputs,  // outputs to include into the transaction
 		routputs, // Number of resulting outputs. Normally it is 1
 		exclude_change_outputs,
 		change_output_minimum_confirmations,
 	)?;
 
+	if derive_nonce {
+		context.derive_nonce(&wallet.keychain(keychain_mask)?)?;
+	}
+
 	// Generate a kernel offset and subtract from our context's secret key. Store
 	// the offset in the slate's transaction kernel, and adds our public key
 	// information to the slate
@@ -236,6 +254,8 @@ pub fn add_output_to_slate<'a, T: ?Sized, C, K>(
 	message: Option<String>,
 	is_initiator: bool,
 	use_test_rng: bool,
+	derive_nonce: bool,
+	deterministic_order: bool,
 	num_outputs: usize, // Number of outputs for this transaction. Normally it is 1
 ) -> Result<Context, Error>
 where
@@ -254,9 +274,14 @@ where
 		key_id_opt,
 		output_amounts,
 		use_test_rng,
+		deterministic_order,
 		num_outputs, // Number of outputs for this transaction. Normally it is 1
 	)?;
 
+	if derive_nonce {
+		context.derive_nonce(&wallet.keychain(keychain_mask)?)?;
+	}
+
 	// fill public keys
 	slate.fill_round_1(
 		&wallet.keychain(keychain_mask)?,
@@ -305,6 +330,133 @@ where
 	Ok(())
 }
 
+/// Export the slate after round 1, so round 2 can be done on another machine with the same seed.
+/// The caller must delete the context, the nonce must not sign on this machine too.
+pub fn export_round_1<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &Slate,
+	context: &Context,
+) -> Result<String, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	slate.export_round_1(
+		&wallet.keychain(keychain_mask)?,
+		context.participant_id,
+		&context.round_1_keys()?,
+	)
+}
+
+/// Complete the transaction that was exported by another machine after round 1. The secrets
+/// are derived from this wallet keychain. The slate must be the one that was exported, with
+/// the same data of the exporting participant. The slate is finalized if the exported
+/// participant signs last, otherwise it is returned with the partial signature.
+/// The export can sign only once. The same nonce with the data of another responded slate
+/// gives away the secret key, so the nonce id is recorded in the wallet before signing.
+pub fn complete_exported_tx<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	export: &str,
+	slate: &mut Slate,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let keychain = wallet.keychain(keychain_mask)?;
+	let import = Slate::import_round_1(&keychain, export, &slate.id)?;
+	let participant_id = import.participant_id;
+	let exported_part = import.slate.participant_with_id(participant_id);
+	let part = slate.participant_with_id(participant_id);
+	if exported_part.map(|p| (p.public_blind_excess, p.public_nonce))
+		!= part.map(|p| (p.public_blind_excess, p.public_nonce))
+		|| import.slate.tx.offset != slate.tx.offset
+	{
+		return Err(ErrorKind::GenericError(format!(
+			"Slate {} doesn't match the round 1 export",
+			slate.id
+		))
+		.into());
+	}
+
+	let nonce_key = import.nonce_id.to_bytes();
+	if wallet
+		.get_private_context(keychain_mask, &nonce_key, participant_id)
+		.is_ok()
+	{
+		return Err(ErrorKind::GenericError(format!(
+			"Round 1 export of slate {} is already used, its nonce can't sign again",
+			slate.id
+		))
+		.into());
+	}
+	{
+		let mut context = Context::new(
+			keychain.secp(),
+			import.sec_key.clone(),
+			&wallet.parent_key_id(),
+			false,
+			participant_id,
+		);
+		context.sec_nonce = import.sec_nonce.clone();
+		context.nonce_id = Some(import.nonce_id.clone());
+		let mut batch = wallet.batch(keychain_mask)?;
+		batch.save_private_context(&nonce_key, participant_id, &context)?;
+		batch.commit()?;
+	}
+
+	slate.fill_round_2(
+		&keychain,
+		&import.sec_key,
+		&import.sec_nonce,
+		participant_id,
+	)?;
+	if slate.participant_data.len() == slate.num_participants
+		&& slate.participant_data.iter().all(|p| p.part_sig.is_some())
+	{
+		slate.finalize(&keychain)?;
+	}
+	Ok(())
+}
+
+/// Check that the slate outputs don't collide with the outputs that the wallet already has.
+/// Wallet outputs of this slate (change, self send) are not duplicates.
+pub fn check_output_duplicates<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &Slate,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let slate_txs: HashSet<(Identifier, u32)> = wallet
+		.tx_log_iter()
+		.filter(|t| t.tx_slate_id == Some(slate.id))
+		.map(|t| (t.parent_key_id, t.id))
+		.collect();
+	let wallet_commits: HashSet<String> = wallet
+		.iter()
+		.filter(|o| match o.tx_log_entry {
+			Some(id) => !slate_txs.contains(&(o.root_key_id.clone(), id)),
+			None => true,
+		})
+		.filter_map(|o| o.commit)
+		.collect();
+
+	for output in slate.tx.outputs() {
+		let commit = util::to_hex(output.commit.0.to_vec());
+		if wallet_commits.contains(&commit) {
+			return Err(ErrorKind::DuplicateOutputCommitment(commit).into());
+		}
+	}
+	Ok(())
+}
+
 /// Rollback outputs associated with a transaction in the wallet
 pub fn cancel_tx<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
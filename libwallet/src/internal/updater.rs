@@ -30,10 +30,38 @@ use crate::grin_util::secp::key::SecretKey;
 use crate::grin_util::secp::pedersen;
 use crate::internal::keys;
 use crate::types::{
-	NodeClient, OutputData, OutputStatus, TxLogEntry, TxLogEntryType, WalletBackend, WalletInfo,
+	CancellationToken, NodeClient, OutputData, OutputStatus, TxLogEntry, TxLogEntryType,
+	WalletBackend, WalletInfo,
 };
 use crate::{BlockFees, CbData, OutputCommitMapping};
 
+/// Retrieve the outputs from the node by batches of batch_size commits. The cancellation is
+/// checked before every batch, the remaining batches are skipped and ErrorKind::Cancelled is returned.
+pub fn get_outputs_from_node_batched<C>(
+	client: &C,
+	commits: &[pedersen::Commitment],
+	batch_size: usize,
+	cancel: &CancellationToken,
+) -> Result<HashMap<pedersen::Commitment, (String, u64, u64)>, Error>
+where
+	C: NodeClient,
+{
+	let batch_size = batch_size.max(1);
+	let mut res = HashMap::new();
+	for (i, batch) in commits.chunks(batch_size).enumerate() {
+		if cancel.is_cancelled() {
+			return Err(ErrorKind::Cancelled(format!(
+				"outputs lookup is stopped after {} of {} commits",
+				i * batch_size,
+				commits.len()
+			))
+			.into());
+		}
+		res.extend(client.get_outputs_from_node(&batch.to_vec())?);
+	}
+	Ok(res)
+}
+
 /// Retrieve all of the outputs (doesn't attempt to update from node)
 pub fn retrieve_outputs<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
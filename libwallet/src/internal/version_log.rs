@@ -0,0 +1,132 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Log of the slate version decisions. When the slate is written, the version is chosen
+//! by the features that the slate has. The log keeps the reason, so it is possible to find out
+//! why the slate went out with that version. The log is disabled by default.
+
+use crate::error::{Error, ErrorKind};
+use crate::grin_util::RwLock;
+use crate::slate::Slate;
+use crate::slate_versions::SlateVersion;
+use chrono::Utc;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Name of the slate version decisions log file, one json record per line
+pub const SLATE_VERSION_LOG_FILE: &str = "slate_versions.log";
+
+lazy_static! {
+	static ref SLATE_VERSION_LOG_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+/// Version that was chosen for the slate and the features that require it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SlateVersionDecision {
+	/// Slate id
+	pub slate_id: Uuid,
+	/// Time of the decision, unix timestamp
+	pub time: i64,
+	/// Version that the slate was written with
+	pub version: SlateVersion,
+	/// Slate features that drove the choice. Empty if the slate could go as the lowest version.
+	pub features: Vec<String>,
+}
+
+impl SlateVersionDecision {
+	/// Decision for the slate that is written with the version
+	pub fn new(slate: &Slate, version: SlateVersion) -> Self {
+		SlateVersionDecision {
+			slate_id: slate.id.clone(),
+			time: Utc::now().timestamp(),
+			version,
+			features: slate.version_features(),
+		}
+	}
+}
+
+/// Init the version decisions log. If not enabled, the decisions are not recorded.
+pub fn init_slate_version_log(data_file_dir: &str, enabled: bool) -> Result<(), Error> {
+	if !enabled {
+		SLATE_VERSION_LOG_PATH.write().take();
+		return Ok(());
+	}
+	fs::create_dir_all(data_file_dir).map_err(|e| {
+		ErrorKind::IO(format!(
+			"Unable to create slate version log directory {}, {}",
+			data_file_dir, e
+		))
+	})?;
+	SLATE_VERSION_LOG_PATH
+		.write()
+		.replace(Path::new(data_file_dir).join(SLATE_VERSION_LOG_FILE));
+	Ok(())
+}
+
+/// Append the decision to the log
+pub fn record_slate_version_decision(decision: &SlateVersionDecision) -> Result<(), Error> {
+	let file_name = match SLATE_VERSION_LOG_PATH.read().clone() {
+		Some(f) => f,
+		None => return Ok(()),
+	};
+	let line = serde_json::to_string(decision).map_err(|e| {
+		ErrorKind::Format(format!(
+			"Unable to serialize version decision for slate {}, {}",
+			decision.slate_id, e
+		))
+	})?;
+	OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(&file_name)
+		.and_then(|mut f| writeln!(f, "{}", line))
+		.map_err(|e| {
+			ErrorKind::IO(format!(
+				"Unable to write slate version log {:?}, {}",
+				file_name, e
+			))
+		})?;
+	Ok(())
+}
+
+/// Read the recorded decisions, optionally only for the slate. Empty if the log is disabled.
+pub fn read_slate_version_decisions(
+	slate_id: Option<&Uuid>,
+) -> Result<Vec<SlateVersionDecision>, Error> {
+	let file_name = match SLATE_VERSION_LOG_PATH.read().clone() {
+		Some(f) => f,
+		None => return Ok(vec![]),
+	};
+	if !file_name.exists() {
+		return Ok(vec![]);
+	}
+	let content = fs::read_to_string(&file_name).map_err(|e| {
+		ErrorKind::IO(format!(
+			"Unable to read slate version log {:?}, {}",
+			file_name, e
+		))
+	})?;
+	let mut res = Vec::new();
+	for line in content.lines().filter(|l| !l.trim().is_empty()) {
+		let decision: SlateVersionDecision = serde_json::from_str(line).map_err(|e| {
+			ErrorKind::Format(format!("Invalid slate version log record {}, {}", line, e))
+		})?;
+		if slate_id.map(|id| *id == decision.slate_id).unwrap_or(true) {
+			res.push(decision);
+		}
+	}
+	Ok(res)
+}
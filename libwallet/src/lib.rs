@@ -67,10 +67,13 @@ extern crate bitcoin_hashes;
 pub use bitcoin::Address as BitcoinAddress;
 
 pub use crate::error::{Error, ErrorKind};
-pub use crate::slate::{ParticipantData, ParticipantMessageData, ParticipantMessages, Slate};
+pub use crate::slate::{
+	ParticipantData, ParticipantMessageData, ParticipantMessages, PaymentInfo, Round1Import,
+	Round1Keys, Slate, MAX_TTL_CUTOFF_BLOCKS,
+};
 pub use crate::slate_versions::{
-	SlateVersion, VersionedCoinbase, VersionedSlate, CURRENT_SLATE_VERSION,
-	GRIN_BLOCK_HEADER_VERSION,
+	supported_slate_versions, SlateVersion, VersionedCoinbase, VersionedSlate,
+	CURRENT_SLATE_VERSION, GRIN_BLOCK_HEADER_VERSION,
 };
 pub use api_impl::foreign;
 pub use api_impl::owner;
@@ -78,19 +81,24 @@ pub use api_impl::owner_swap;
 pub use api_impl::owner_updater::StatusMessage;
 pub use api_impl::types::{
 	BlockFees, InitTxArgs, InitTxSendArgs, IssueInvoiceTxArgs, NodeHeightResult,
-	OutputCommitMapping, PaymentProof, SendTXArgs, SwapStartArgs, VersionInfo,
+	OutputCommitMapping, PaymentProof, SendTXArgs, SlateVerification, SwapAcceptArgs,
+	SwapStartArgs, VersionInfo,
 };
 pub use internal::scan::scan;
 pub use proof::tx_proof::TxProof;
 pub use proof::tx_proof::{proof_ok, verify_tx_proof_wrapper};
 pub use slate_versions::ser as dalek_ser;
 pub use types::{
-	AcctPathMapping, BlockIdentifier, CbData, Context, HeaderInfo, NodeClient, NodeVersionInfo,
-	OutputData, OutputStatus, ScannedBlockInfo, StoredProofInfo, TxLogEntry, TxLogEntryType,
-	WalletBackend, WalletInfo, WalletInst, WalletLCProvider, WalletOutputBatch,
+	AcctPathMapping, BlockIdentifier, CancellationToken, CbData, Context, HeaderInfo, NodeClient,
+	NodeVersionInfo, OutputData, OutputStatus, ScannedBlockInfo, StoredProofInfo, TxLogEntry,
+	TxLogEntryType, WalletBackend, WalletInfo, WalletInst, WalletLCProvider, WalletOutputBatch,
 };
 
 pub use api_impl::foreign::{get_receive_account, set_receive_account};
+pub use api_impl::owner::{
+	is_missing_payment_proof_error, is_payment_proof_required, set_missing_payment_proof_error,
+	set_payment_proof_required,
+};
 
 /// Helper for taking a lock on the wallet instance
 #[macro_export]
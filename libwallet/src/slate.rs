@@ -25,13 +25,15 @@ use crate::grin_core::core::transaction::{
 use crate::grin_core::core::verifier_cache::LruVerifierCache;
 use crate::grin_core::libtx::{aggsig, build, proof::ProofBuild, secp_ser, tx_fee};
 use crate::grin_core::map_vec;
-use crate::grin_keychain::{BlindSum, BlindingFactor, Keychain};
+use crate::grin_keychain::{
+	BlindSum, BlindingFactor, ExtKeychainPath, Identifier, Keychain, SwitchCommitmentType,
+};
 use crate::grin_util::secp::key::{PublicKey, SecretKey};
 use crate::grin_util::secp::pedersen::Commitment;
 use crate::grin_util::secp::Signature;
 use crate::grin_util::{self, secp, RwLock};
 use rand::rngs::mock::StepRng;
-use rand::thread_rng;
+use rand::{thread_rng, Rng};
 use serde::ser::{Serialize, Serializer};
 use serde_json;
 use std::fmt;
@@ -40,15 +42,17 @@ use uuid::Uuid;
 
 use crate::slate_versions::v2::SlateV2;
 use crate::slate_versions::v2::SlateV2ParseTTL;
+use crate::slate_versions::v5::SlateV5;
 
 use crate::slate_versions::v3::{
-	CoinbaseV3, InputV3, OutputV3, ParticipantDataV3, PaymentInfoV3, SlateV3, TransactionBodyV3,
-	TransactionV3, TxKernelV3, VersionCompatInfoV3,
+	CoinbaseV3, InputV3, OutputV3, ParticipantDataV3, PaymentInfoV3, RecipientBindingV3, SlateV3,
+	TransactionBodyV3, TransactionV3, TxKernelV3, VersionCompatInfoV3,
 };
 
 // use crate::slate_versions::{CURRENT_SLATE_VERSION, GRIN_BLOCK_HEADER_VERSION};
 use crate::proof::proofaddress;
 use crate::proof::proofaddress::ProvableAddress;
+use crate::slate_versions::check_slate_version;
 use crate::types::CbData;
 use crate::{SlateVersion, CURRENT_SLATE_VERSION};
 
@@ -67,6 +71,18 @@ pub struct PaymentInfo {
 	pub receiver_signature: Option<String>,
 }
 
+/// Binding of the slate to the intended recipient. The recipient signs the commitment to
+/// the slate id, its key and its participant data, so the slate can't be completed by anybody else.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecipientBinding {
+	/// Public key of the intended recipient (payment proof address key)
+	#[serde(with = "secp_ser::pubkey_serde")]
+	pub recipient: PublicKey,
+	/// Recipient signature of the binding commitment
+	#[serde(default, with = "secp_ser::option_sig_serde")]
+	pub signature: Option<Signature>,
+}
+
 /// Public data for each participant in the slate
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct ParticipantData {
@@ -169,6 +185,9 @@ impl fmt::Display for ParticipantMessageData {
 /// the slate around by whatever means they choose, (but we can provide some
 /// binary or JSON serialization helpers here).
 
+/// Max distance from the chain tip to the slate TTL cutoff height. 30 days for 1 minute blocks.
+pub const MAX_TTL_CUTOFF_BLOCKS: u64 = 30 * 1440;
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct Slate {
 	/// Versioning info
@@ -209,6 +228,9 @@ pub struct Slate {
 	/// Payment Proof
 	#[serde(default = "default_payment_none")]
 	pub payment_proof: Option<PaymentInfo>,
+	/// Binding to the intended recipient. If set, only that recipient can respond.
+	#[serde(default)]
+	pub recipient_binding: Option<RecipientBinding>,
 }
 
 fn default_payment_none() -> Option<PaymentInfo> {
@@ -247,6 +269,7 @@ impl Slate {
 	/// Recieve a slate, upgrade it to the latest version internally
 	pub fn deserialize_upgrade(slate_json: &str) -> Result<Slate, Error> {
 		let version = Slate::parse_slate_version(slate_json)?;
+		check_slate_version(version)?;
 
 		//I don't think we need to do this for coin_type and network_type, the slate containing these two
 		//fields has to be version 3. If receiver wallet doesn't supported them, they will be filtered out.
@@ -263,6 +286,15 @@ impl Slate {
 		};
 
 		let v3: SlateV3 = match version {
+			5 => {
+				let v5: SlateV5 = serde_json::from_str(slate_json).map_err(|e| {
+					ErrorKind::SlateDeser(format!(
+						"Json to SlateV5 conversion failed for {}, {}",
+						slate_json, e
+					))
+				})?;
+				SlateV3::from(v5)
+			}
 			3 => serde_json::from_str(slate_json).map_err(|e| {
 				ErrorKind::SlateDeser(format!(
 					"Json to SlateV3 conversion failed for {}, {}",
@@ -282,7 +314,9 @@ impl Slate {
 			}
 			_ => return Err(ErrorKind::SlateVersion(version).into()),
 		};
-		Ok(v3.into())
+		let slate: Slate = v3.into();
+		slate.validate_kernel_consistency()?;
+		Ok(slate)
 	}
 
 	/// Create a new slate
@@ -305,6 +339,7 @@ impl Slate {
 				block_header_version: 1, // GRIN_BLOCK_HEADER_VERSION,
 			},
 			payment_proof: None,
+			recipient_binding: None,
 		}
 	}
 
@@ -391,20 +426,37 @@ impl Slate {
 	/// Calculate minimal Slate version. For exchange we want to keep the varsion as low as possible
 	/// because there are might be many non upgraded wallets and we want ot be friendly to them.
 	pub fn lowest_version(&self) -> SlateVersion {
-		if self.payment_proof.is_some() || self.ttl_cutoff_height.is_some() {
-			SlateVersion::V3
-		} else {
+		if self.version_features().is_empty() {
 			SlateVersion::V2
+		} else {
+			SlateVersion::V3
 		}
 	}
 
+	/// Slate features that V2 can't carry, they require V3
+	pub fn version_features(&self) -> Vec<String> {
+		let mut features = Vec::new();
+		if self.payment_proof.is_some() {
+			features.push("payment proof".to_string());
+		}
+		if self.ttl_cutoff_height.is_some() {
+			features.push("ttl cutoff height".to_string());
+		}
+		if self.recipient_binding.is_some() {
+			features.push("recipient binding".to_string());
+		}
+		features
+	}
+
 	/// Adds selected inputs and outputs to the slate's transaction
+	/// deterministic_order - sort the transaction elements, so the same slate is always built the same way
 	/// Returns blinding factor
 	pub fn add_transaction_elements<K, B>(
 		&mut self,
 		keychain: &K,
 		builder: &B,
 		elems: Vec<Box<build::Append<K, B>>>,
+		deterministic_order: bool,
 	) -> Result<BlindingFactor, Error>
 	where
 		K: Keychain,
@@ -413,9 +465,20 @@ impl Slate {
 		self.update_kernel();
 		let (tx, blind) = build::partial_transaction(self.tx.clone(), elems, keychain, builder)?;
 		self.tx = tx;
+		if deterministic_order {
+			self.sort_tx_elements();
+		}
 		Ok(blind)
 	}
 
+	/// Sort inputs, outputs and kernels of the transaction. Elements are ordered by the hash of
+	/// their commitments, it is the consensus order, so the transaction stays valid.
+	pub fn sort_tx_elements(&mut self) {
+		self.tx.body.inputs.sort_unstable();
+		self.tx.body.outputs.sort_unstable();
+		self.tx.body.kernels.sort_unstable();
+	}
+
 	/// Update the tx kernel based on kernel features derived from the current slate.
 	/// The fee may change as we build a transaction and we need to
 	/// update the tx kernel to reflect this during the tx building process.
@@ -506,6 +569,150 @@ impl Slate {
 		Ok(())
 	}
 
+	/// Completes both rounds for a single party: adds the public key info and the signature.
+	/// Result is the same as fill_round_1 followed by fill_round_2. Slate is updated only
+	/// if both rounds succeed, so it is never left half-signed.
+	pub fn fill_rounds<K>(
+		&mut self,
+		keychain: &K,
+		sec_key: &mut SecretKey,
+		sec_nonce: &SecretKey,
+		participant_id: usize,
+		message: Option<String>,
+		use_test_rng: bool,
+	) -> Result<(), Error>
+	where
+		K: Keychain,
+	{
+		let mut slate = self.clone();
+		let mut key = sec_key.clone();
+		slate.fill_round_1(
+			keychain,
+			&mut key,
+			sec_nonce,
+			participant_id,
+			message,
+			use_test_rng,
+		)?;
+		slate.fill_round_2(keychain, &key, sec_nonce, participant_id)?;
+		*self = slate;
+		*sec_key = key;
+		Ok(())
+	}
+
+	/// Export the slate after fill_round_1, so the round 2 can be completed on another machine.
+	/// The export has only the public data and the derivation paths of the participant keys,
+	/// the secret key and nonce are never exported. The keys are checked against the participant
+	/// data, so a wrong export is rejected here and not on the other machine.
+	pub fn export_round_1<K>(
+		&self,
+		keychain: &K,
+		participant_id: usize,
+		keys: &Round1Keys,
+	) -> Result<String, Error>
+	where
+		K: Keychain,
+	{
+		let _ = self.round_1_secrets(keychain, participant_id, keys)?;
+		let export = Round1Export {
+			participant_id: participant_id as u64,
+			keys: keys.clone(),
+			slate: serde_json::to_value(self).map_err(|e| ErrorKind::SlateSer(format!("{}", e)))?,
+		};
+		let res =
+			serde_json::to_string(&export).map_err(|e| ErrorKind::SlateSer(format!("{}", e)))?;
+		Ok(res)
+	}
+
+	/// Import the slate that was exported by export_round_1. The slate must be the expected one.
+	/// The secret key and nonce are derived from the keychain with the exported paths, so the
+	/// machine needs the same wallet seed, but not the secrets of the exporting machine.
+	/// The secret key has the kernel offset applied if the exported participant generated it.
+	/// The slate is ready for fill_round_2.
+	pub fn import_round_1<K>(
+		keychain: &K,
+		export: &str,
+		expected_id: &Uuid,
+	) -> Result<Round1Import, Error>
+	where
+		K: Keychain,
+	{
+		let export: Round1Export = serde_json::from_str(export)
+			.map_err(|e| ErrorKind::SlateDeser(format!("Unable to read round 1 export, {}", e)))?;
+		let slate = Slate::deserialize_upgrade(&export.slate.to_string())?;
+		if slate.id != *expected_id {
+			return Err(ErrorKind::SlateValidation(format!(
+				"exported slate {} doesn't match expected slate {}",
+				slate.id, expected_id
+			))
+			.into());
+		}
+		let participant_id = export.participant_id as usize;
+		let (sec_key, sec_nonce) = slate.round_1_secrets(keychain, participant_id, &export.keys)?;
+		Ok(Round1Import {
+			slate,
+			participant_id,
+			nonce_id: export.keys.nonce,
+			sec_key,
+			sec_nonce,
+		})
+	}
+
+	// Derive the participant secrets and check them against the round 1 participant data.
+	fn round_1_secrets<K>(
+		&self,
+		keychain: &K,
+		participant_id: usize,
+		keys: &Round1Keys,
+	) -> Result<(SecretKey, SecretKey), Error>
+	where
+		K: Keychain,
+	{
+		let participant =
+			self.participant_with_id(participant_id)
+				.ok_or(ErrorKind::SlateValidation(format!(
+					"slate {} doesn't have participant {} data, round 1 is not done",
+					self.id, participant_id
+				)))?;
+		if participant.part_sig.is_some() {
+			return Err(ErrorKind::SlateValidation(format!(
+				"slate {} participant {} is already signed",
+				self.id, participant_id
+			))
+			.into());
+		}
+
+		let secp = keychain.secp();
+		let sec_nonce = keys.sec_nonce(keychain)?;
+		if participant.public_nonce != PublicKey::from_secret_key(secp, &sec_nonce)? {
+			return Err(ErrorKind::SlateValidation(format!(
+				"slate {} participant {} nonce doesn't match",
+				self.id, participant_id
+			))
+			.into());
+		}
+		let mut sec_key = keys.sec_key(keychain)?;
+		if participant.public_blind_excess != PublicKey::from_secret_key(secp, &sec_key)? {
+			// fill_round_1 subtracted the kernel offset
+			let offset_key = keychain
+				.blind_sum(
+					&BlindSum::new()
+						.add_blinding_factor(BlindingFactor::from_secret_key(sec_key.clone()))
+						.sub_blinding_factor(self.tx.offset.clone()),
+				)?
+				.secret_key(secp)?;
+			if participant.public_blind_excess != PublicKey::from_secret_key(secp, &offset_key)? {
+				return Err(ErrorKind::SlateValidation(format!(
+					"slate {} participant {} blind excess doesn't match",
+					self.id, participant_id
+				))
+				.into());
+			}
+			sec_key = offset_key;
+		}
+		Ok((sec_key, sec_nonce))
+	}
+
 	/// Creates the final signature, callable by either the sender or recipient
 	/// (after phase 3: sender confirmation)
 	pub fn finalize<K>(&mut self, keychain: &K) -> Result<(), Error>
@@ -696,6 +903,54 @@ impl Slate {
 		Ok(())
 	}
 
+	/// Check that the kernel features match the slate lock height. Slate with lock height
+	/// must have a height locked kernel with the same height, otherwise the lock height
+	/// will not be enforced.
+	pub fn validate_kernel_consistency(&self) -> Result<(), ErrorKind> {
+		if self.lock_height == 0 {
+			return Ok(());
+		}
+		for kernel in self.tx.kernels() {
+			match kernel.features {
+				KernelFeatures::HeightLocked { lock_height, .. }
+					if lock_height == self.lock_height => {}
+				features => {
+					return Err(ErrorKind::KernelFeaturesMismatch(format!(
+						"slate {} has lock height {}, but its kernel features are {:?}",
+						self.id, self.lock_height, features
+					)));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Check that the TTL cutoff height is consistent with the slate heights and the chain tip.
+	/// Slate with the reached cutoff is dead on arrival, the cutoff before the slate height or
+	/// the lock height can never be valid.
+	pub fn validate_ttl_cutoff(&self, tip_height: u64) -> Result<(), ErrorKind> {
+		let cutoff = match self.ttl_cutoff_height {
+			Some(cutoff) => cutoff,
+			None => return Ok(()),
+		};
+		if cutoff <= self.height {
+			return Err(ErrorKind::TtlCutoffBeforeHeight(cutoff, self.height));
+		}
+		if cutoff < self.lock_height {
+			return Err(ErrorKind::TtlCutoffBeforeLockHeight(
+				cutoff,
+				self.lock_height,
+			));
+		}
+		if cutoff <= tip_height {
+			return Err(ErrorKind::TtlCutoffExpired(cutoff, tip_height));
+		}
+		if cutoff > tip_height + MAX_TTL_CUTOFF_BLOCKS {
+			return Err(ErrorKind::TtlCutoffTooFar(cutoff, tip_height));
+		}
+		Ok(())
+	}
+
 	/// Verifies all of the partial signatures in the Slate are valid
 	fn verify_part_sigs(&self, secp: &secp::Secp256k1) -> Result<(), Error> {
 		// collect public nonces
@@ -715,6 +970,90 @@ impl Slate {
 		Ok(())
 	}
 
+	/// Bind the slate to the recipient public key. Only the owner of that key can respond to it.
+	pub fn bind_to_recipient(&mut self, recipient: PublicKey) {
+		self.recipient_binding = Some(RecipientBinding {
+			recipient,
+			signature: None,
+		});
+	}
+
+	/// Check if the slate can be processed by the recipient with this public key.
+	/// Slates without binding can be processed by anybody.
+	pub fn check_recipient_binding(&self, recipient: &PublicKey) -> Result<(), Error> {
+		if let Some(binding) = &self.recipient_binding {
+			if binding.recipient != *recipient {
+				return Err(ErrorKind::RecipientBindingMismatch(self.id.to_string()).into());
+			}
+		}
+		Ok(())
+	}
+
+	// Commitment to the slate id, recipient key and the recipient participant data
+	fn recipient_binding_msg(
+		&self,
+		secp: &secp::Secp256k1,
+		recipient: &PublicKey,
+	) -> Result<secp::Message, Error> {
+		let participant =
+			self.participant_data
+				.iter()
+				.find(|p| p.id == 1)
+				.ok_or(ErrorKind::Signature(
+					"Recipient participant data is missing".to_owned(),
+				))?;
+		let mut data = self.id.as_bytes().to_vec();
+		data.extend_from_slice(&recipient.serialize_vec(secp, true));
+		data.extend_from_slice(&participant.public_blind_excess.serialize_vec(secp, true));
+		data.extend_from_slice(&participant.public_nonce.serialize_vec(secp, true));
+		let hashed = blake2b(secp::constants::MESSAGE_SIZE, &[], &data);
+		Ok(secp::Message::from_slice(&hashed.as_bytes())?)
+	}
+
+	/// Recipient signs the binding after adding its participant data.
+	/// sec_key - secret for the recipient public key
+	pub fn sign_recipient_binding(
+		&mut self,
+		secp: &secp::Secp256k1,
+		sec_key: &SecretKey,
+	) -> Result<(), Error> {
+		let recipient = PublicKey::from_secret_key(secp, sec_key)?;
+		self.check_recipient_binding(&recipient)?;
+		if self.recipient_binding.is_none() {
+			return Ok(());
+		}
+		let msg = self.recipient_binding_msg(secp, &recipient)?;
+		let sig = aggsig::sign_single(secp, &msg, sec_key, None, Some(&recipient))?;
+		if let Some(binding) = self.recipient_binding.as_mut() {
+			binding.signature = Some(sig);
+		}
+		Ok(())
+	}
+
+	/// Sender verifies that the bound slate was responded by the intended recipient
+	pub fn verify_recipient_binding(&self, secp: &secp::Secp256k1) -> Result<(), Error> {
+		let binding = match &self.recipient_binding {
+			Some(b) => b,
+			None => return Ok(()),
+		};
+		let signature = binding
+			.signature
+			.ok_or(ErrorKind::RecipientBindingMismatch(self.id.to_string()))?;
+		let msg = self.recipient_binding_msg(secp, &binding.recipient)?;
+		if !aggsig::verify_single(
+			secp,
+			&signature,
+			&msg,
+			None,
+			&binding.recipient,
+			Some(&binding.recipient),
+			false,
+		) {
+			return Err(ErrorKind::RecipientBindingMismatch(self.id.to_string()).into());
+		}
+		Ok(())
+	}
+
 	/// Verifies any messages in the slate's participant data match their signatures
 	pub fn verify_messages(&self) -> Result<(), Error> {
 		let secp = secp::Secp256k1::with_caps(secp::ContextFlag::VerifyOnly);
@@ -877,6 +1216,69 @@ impl Serialize for Slate {
 	}
 }
 
+/// Root of the derivation path for the split signing nonces. Normal wallet keys never use it.
+const ROUND_1_NONCE_ROOT: u32 = 0x7fff_fff0;
+
+/// Participant keys for the split signing. The keys are described by the derivation paths,
+/// the secrets are derived from the keychain by the machine that does the round 2.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Round1Keys {
+	/// Inputs: key id, amount
+	pub inputs: Vec<(Identifier, u64)>,
+	/// Outputs: key id, amount
+	pub outputs: Vec<(Identifier, u64)>,
+	/// Nonce key id
+	pub nonce: Identifier,
+}
+
+impl Round1Keys {
+	/// Random nonce key id. Nonce must never be reused, so every slate gets a new one.
+	pub fn new_nonce_id() -> Identifier {
+		let mut rng = thread_rng();
+		ExtKeychainPath::new(4, ROUND_1_NONCE_ROOT, rng.gen(), rng.gen(), rng.gen()).to_identifier()
+	}
+
+	/// Derive the nonce
+	pub fn sec_nonce<K: Keychain>(&self, keychain: &K) -> Result<SecretKey, Error> {
+		Ok(keychain.derive_key(0, &self.nonce, SwitchCommitmentType::None)?)
+	}
+
+	/// Derive the secret key: outputs minus inputs, before the kernel offset is applied
+	pub fn sec_key<K: Keychain>(&self, keychain: &K) -> Result<SecretKey, Error> {
+		let mut sum = BlindSum::new();
+		for (id, amount) in &self.outputs {
+			sum = sum.add_key_id(id.to_value_path(*amount));
+		}
+		for (id, amount) in &self.inputs {
+			sum = sum.sub_key_id(id.to_value_path(*amount));
+		}
+		Ok(keychain.blind_sum(&sum)?.secret_key(keychain.secp())?)
+	}
+}
+
+/// Result of the round 1 export import, everything that round 2 needs
+#[derive(Debug, Clone)]
+pub struct Round1Import {
+	/// Slate after round 1
+	pub slate: Slate,
+	/// Participant that does round 2
+	pub participant_id: usize,
+	/// Nonce key id. The nonce must sign only once.
+	pub nonce_id: Identifier,
+	/// Participant secret key, with the kernel offset applied
+	pub sec_key: SecretKey,
+	/// Participant nonce
+	pub sec_nonce: SecretKey,
+}
+
+/// Slate between the signing rounds with the keys of the participant that will do round 2
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Round1Export {
+	participant_id: u64,
+	keys: Round1Keys,
+	slate: serde_json::Value,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SlateVersionProbe {
 	#[serde(default)]
@@ -889,8 +1291,9 @@ impl SlateVersionProbe {
 	pub fn version(&self) -> u16 {
 		match &self.version_info {
 			Some(v) => v.version,
+			// Only V1 had the version at the top level, newer version there is a future format
 			None => match self.version {
-				Some(_) => 1,
+				Some(v) => std::cmp::min(v, u16::MAX as u64) as u16,
 				None => 0,
 			},
 		}
@@ -927,6 +1330,7 @@ impl From<Slate> for SlateV3 {
 			participant_data,
 			version_info,
 			payment_proof,
+			recipient_binding,
 		} = slate;
 		let participant_data = map_vec!(participant_data, |data| ParticipantDataV3::from(data));
 		let version_info = VersionCompatInfoV3::from(&version_info);
@@ -934,6 +1338,7 @@ impl From<Slate> for SlateV3 {
 			Some(p) => Some(PaymentInfoV3::from(&p)),
 			None => None,
 		};
+		let recipient_binding = recipient_binding.map(|b| RecipientBindingV3::from(&b));
 		let tx = TransactionV3::from(tx);
 		SlateV3 {
 			num_participants,
@@ -949,6 +1354,7 @@ impl From<Slate> for SlateV3 {
 			participant_data,
 			version_info,
 			payment_proof,
+			recipient_binding,
 		}
 	}
 }
@@ -969,6 +1375,7 @@ impl From<&Slate> for SlateV3 {
 			participant_data,
 			version_info,
 			payment_proof,
+			recipient_binding,
 		} = slate;
 		let num_participants = *num_participants;
 		let id = *id;
@@ -992,6 +1399,7 @@ impl From<&Slate> for SlateV3 {
 			Some(p) => Some(PaymentInfoV3::from(p)),
 			None => None,
 		};
+		let recipient_binding = recipient_binding.as_ref().map(RecipientBindingV3::from);
 		SlateV3 {
 			num_participants,
 			id,
@@ -1006,6 +1414,7 @@ impl From<&Slate> for SlateV3 {
 			participant_data,
 			version_info,
 			payment_proof,
+			recipient_binding,
 		}
 	}
 }
@@ -1073,6 +1482,15 @@ impl From<&PaymentInfo> for PaymentInfoV3 {
 	}
 }
 
+impl From<&RecipientBinding> for RecipientBindingV3 {
+	fn from(data: &RecipientBinding) -> RecipientBindingV3 {
+		RecipientBindingV3 {
+			recipient: data.recipient.clone(),
+			signature: data.signature.clone(),
+		}
+	}
+}
+
 impl From<Transaction> for TransactionV3 {
 	fn from(tx: Transaction) -> TransactionV3 {
 		let Transaction { offset, body } = tx;
@@ -1167,6 +1585,7 @@ impl From<SlateV3> for Slate {
 			participant_data,
 			version_info,
 			payment_proof,
+			recipient_binding,
 		} = slate;
 		let participant_data = map_vec!(participant_data, |data| ParticipantData::from(data));
 		let version_info = VersionCompatInfo::from(&version_info);
@@ -1174,6 +1593,7 @@ impl From<SlateV3> for Slate {
 			Some(p) => Some(PaymentInfo::from(&p)),
 			None => None,
 		};
+		let recipient_binding = recipient_binding.map(|b| RecipientBinding::from(&b));
 		let tx = Transaction::from(tx);
 		Slate {
 			num_participants,
@@ -1189,6 +1609,7 @@ impl From<SlateV3> for Slate {
 			participant_data,
 			version_info,
 			payment_proof,
+			recipient_binding,
 		}
 	}
 }
@@ -1256,6 +1677,15 @@ impl From<&PaymentInfoV3> for PaymentInfo {
 	}
 }
 
+impl From<&RecipientBindingV3> for RecipientBinding {
+	fn from(data: &RecipientBindingV3) -> RecipientBinding {
+		RecipientBinding {
+			recipient: data.recipient.clone(),
+			signature: data.signature.clone(),
+		}
+	}
+}
+
 impl From<TransactionV3> for Transaction {
 	fn from(tx: TransactionV3) -> Transaction {
 		let TransactionV3 { offset, body } = tx;
@@ -1327,3 +1757,447 @@ pub enum CompatKernelFeatures {
 	Coinbase,
 	HeightLocked,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grin_core::core::transaction::OutputFeatures;
+	use crate::grin_core::libtx::ProofBuilder;
+	use crate::grin_core::ser::{ser_vec, ProtocolVersion};
+	use crate::grin_keychain::ExtKeychain;
+	use crate::slate_versions::v5::SlateV5;
+	use crate::VersionedSlate;
+
+	fn build_slate(keychain: &ExtKeychain, id: &Uuid, reverse: bool) -> (Slate, BlindingFactor) {
+		let builder = ProofBuilder::new(keychain);
+		let key_id = |i| ExtKeychainPath::new(1, i, 0, 0, 0).to_identifier();
+
+		let mut slate = Slate::blank(2);
+		slate.id = id.clone();
+		slate.amount = 60;
+		slate.fee = 10;
+		let mut elems = vec![
+			build::input(100, key_id(1)),
+			build::output(20, key_id(2)),
+			build::output(10, key_id(3)),
+			build::output(60, key_id(4)),
+		];
+		if reverse {
+			elems.reverse();
+		}
+		let blind = slate
+			.add_transaction_elements(keychain, &builder, elems, true)
+			.unwrap();
+		(slate, blind)
+	}
+
+	#[test]
+	fn test_deterministic_output_order() {
+		let keychain = ExtKeychain::from_random_seed(false).unwrap();
+		let id = Uuid::new_v4();
+
+		let (slate1, blind1) = build_slate(&keychain, &id, false);
+		let (mut slate2, blind2) = build_slate(&keychain, &id, true);
+		assert_eq!(blind1, blind2);
+		assert_eq!(slate2.tx.body.outputs.len(), 3);
+
+		let ser = |slate: &Slate| {
+			(
+				ser_vec(&slate.tx, ProtocolVersion(1)).unwrap(),
+				serde_json::to_string(slate).unwrap(),
+			)
+		};
+		assert_eq!(ser(&slate1), ser(&slate2));
+
+		// Shuffled elements are returned to the same order
+		slate2.tx.body.outputs.reverse();
+		assert_ne!(ser(&slate1), ser(&slate2));
+		slate2.sort_tx_elements();
+		assert_eq!(ser(&slate1), ser(&slate2));
+	}
+
+	#[test]
+	fn test_recipient_binding() {
+		let keychain = ExtKeychain::from_random_seed(false).unwrap();
+		let secp = keychain.secp();
+		let id = Uuid::new_v4();
+		let key_a = SecretKey::new(secp, &mut thread_rng());
+		let key_b = SecretKey::new(secp, &mut thread_rng());
+		let pub_a = PublicKey::from_secret_key(secp, &key_a).unwrap();
+		let pub_b = PublicKey::from_secret_key(secp, &key_b).unwrap();
+
+		let (mut slate, _) = build_slate(&keychain, &id, false);
+		// Not bound slate can be processed by anybody
+		assert!(slate.check_recipient_binding(&pub_b).is_ok());
+		assert_eq!(slate.lowest_version(), SlateVersion::V2);
+
+		slate.bind_to_recipient(pub_a.clone());
+		assert_eq!(slate.lowest_version(), SlateVersion::V3);
+		// Binding survives the serialization
+		let mut slate =
+			Slate::deserialize_upgrade(&serde_json::to_string(&slate).unwrap()).unwrap();
+		assert_eq!(slate.recipient_binding.as_ref().unwrap().recipient, pub_a);
+
+		let mut sec_key = SecretKey::new(secp, &mut thread_rng());
+		let sec_nonce = SecretKey::new(secp, &mut thread_rng());
+		slate
+			.fill_round_1(&keychain, &mut sec_key, &sec_nonce, 1, None, false)
+			.unwrap();
+
+		// Recipient B can't respond
+		assert!(slate.check_recipient_binding(&pub_b).is_err());
+		let mut slate_b = slate.clone();
+		assert!(slate_b.sign_recipient_binding(secp, &key_b).is_err());
+		// Not signed binding is rejected by the sender
+		assert!(slate_b.verify_recipient_binding(secp).is_err());
+
+		// Recipient A can
+		assert!(slate.check_recipient_binding(&pub_a).is_ok());
+		slate.sign_recipient_binding(secp, &key_a).unwrap();
+		slate.verify_recipient_binding(secp).unwrap();
+
+		// Signature doesn't match if the participant data was replaced
+		let mut sec_key = SecretKey::new(secp, &mut thread_rng());
+		slate.participant_data.clear();
+		slate
+			.fill_round_1(&keychain, &mut sec_key, &sec_nonce, 1, None, false)
+			.unwrap();
+		assert!(slate.verify_recipient_binding(secp).is_err());
+	}
+
+	#[test]
+	fn test_kernel_consistency() {
+		let keychain = ExtKeychain::from_random_seed(false).unwrap();
+		let (mut slate, _) = build_slate(&keychain, &Uuid::new_v4(), false);
+		slate.validate_kernel_consistency().unwrap();
+
+		// Lock height with a plain kernel
+		slate.lock_height = 1000;
+		assert!(slate.validate_kernel_consistency().is_err());
+		let json = serde_json::to_string(&slate).unwrap();
+		assert!(Slate::deserialize_upgrade(&json).is_err());
+
+		// Height locked kernel with another height
+		slate.tx.body.kernels[0].features = KernelFeatures::HeightLocked {
+			fee: slate.fee,
+			lock_height: 999,
+		};
+		assert!(slate.validate_kernel_consistency().is_err());
+
+		// Correctly height locked
+		slate.update_kernel();
+		slate.validate_kernel_consistency().unwrap();
+		let json = serde_json::to_string(&slate).unwrap();
+		assert_eq!(Slate::deserialize_upgrade(&json).unwrap().lock_height, 1000);
+	}
+
+	#[test]
+	fn test_ttl_cutoff_validation() {
+		let mut slate = Slate::blank(2);
+		slate.height = 1000;
+
+		// No cutoff, nothing to check
+		slate.validate_ttl_cutoff(5000).unwrap();
+
+		// Valid cutoff
+		slate.ttl_cutoff_height = Some(1100);
+		slate.validate_ttl_cutoff(1000).unwrap();
+		slate.validate_ttl_cutoff(1099).unwrap();
+
+		// Cutoff is in the past
+		assert_eq!(
+			slate.validate_ttl_cutoff(1100),
+			Err(ErrorKind::TtlCutoffExpired(1100, 1100))
+		);
+		assert_eq!(
+			slate.validate_ttl_cutoff(2000),
+			Err(ErrorKind::TtlCutoffExpired(1100, 2000))
+		);
+
+		// Contradictory cutoffs
+		slate.ttl_cutoff_height = Some(1000);
+		assert_eq!(
+			slate.validate_ttl_cutoff(900),
+			Err(ErrorKind::TtlCutoffBeforeHeight(1000, 1000))
+		);
+		slate.ttl_cutoff_height = Some(1100);
+		slate.lock_height = 1200;
+		assert_eq!(
+			slate.validate_ttl_cutoff(1000),
+			Err(ErrorKind::TtlCutoffBeforeLockHeight(1100, 1200))
+		);
+		slate.lock_height = 1100;
+		slate.validate_ttl_cutoff(1000).unwrap();
+
+		// Absurdly far future
+		let far = 1000 + MAX_TTL_CUTOFF_BLOCKS + 1;
+		slate.ttl_cutoff_height = Some(far);
+		assert_eq!(
+			slate.validate_ttl_cutoff(1000),
+			Err(ErrorKind::TtlCutoffTooFar(far, 1000))
+		);
+	}
+
+	#[test]
+	fn test_fill_rounds() {
+		let keychain = ExtKeychain::from_random_seed(false).unwrap();
+		let secp = keychain.secp();
+		let (mut slate, blind) = build_slate(&keychain, &Uuid::new_v4(), false);
+		slate.fee = tx_fee(1, 3, 1, None);
+		slate.amount = slate.fee * 10;
+		slate.update_kernel();
+		let sec_key = blind.secret_key(secp).unwrap();
+		let sec_nonce = SecretKey::new(secp, &mut thread_rng());
+
+		let mut slate1 = slate.clone();
+		let mut key1 = sec_key.clone();
+		slate1
+			.fill_round_1(&keychain, &mut key1, &sec_nonce, 0, None, true)
+			.unwrap();
+		slate1
+			.fill_round_2(&keychain, &key1, &sec_nonce, 0)
+			.unwrap();
+
+		let mut slate2 = slate.clone();
+		let mut key2 = sec_key.clone();
+		slate2
+			.fill_rounds(&keychain, &mut key2, &sec_nonce, 0, None, true)
+			.unwrap();
+
+		assert_eq!(key1, key2);
+		assert!(slate2.participant_data[0].is_complete());
+		assert_eq!(
+			serde_json::to_string(&slate1).unwrap(),
+			serde_json::to_string(&slate2).unwrap()
+		);
+
+		// Failed round 2 doesn't leave the slate half-signed
+		let mut slate3 = slate.clone();
+		slate3.fee = 1;
+		slate3.update_kernel();
+		let mut key3 = sec_key.clone();
+		assert!(slate3
+			.fill_rounds(&keychain, &mut key3, &sec_nonce, 0, None, true)
+			.is_err());
+		assert!(slate3.participant_data.is_empty());
+		assert_eq!(slate3.tx.offset, BlindingFactor::zero());
+		assert_eq!(key3, sec_key);
+	}
+
+	#[test]
+	fn test_binary_slate() {
+		let keychain = ExtKeychain::from_random_seed(false).unwrap();
+		let secp = keychain.secp();
+		let (mut slate, blind) = build_slate(&keychain, &Uuid::new_v4(), false);
+		let mut sec_key = blind.secret_key(secp).unwrap();
+		let sec_nonce = SecretKey::new(secp, &mut thread_rng());
+		slate
+			.fill_rounds(
+				&keychain,
+				&mut sec_key,
+				&sec_nonce,
+				0,
+				Some("binary".to_string()),
+				true,
+			)
+			.unwrap();
+		slate.ttl_cutoff_height = Some(1000);
+		let pub_key = PublicKey::from_secret_key(secp, &sec_key).unwrap();
+		slate.bind_to_recipient(pub_key);
+		slate.payment_proof = Some(PaymentInfo {
+			sender_address: ProvableAddress::from_pub_key(&pub_key),
+			receiver_address: ProvableAddress::from_pub_key(&pub_key),
+			receiver_signature: Some("receiver signature".to_string()),
+		});
+
+		let v3 = VersionedSlate::into_version(slate.clone(), SlateVersion::V3);
+		let data = v3.to_binary().unwrap();
+		assert!(data.len() < v3.estimate_size());
+
+		let v5 = VersionedSlate::from_binary(&data).unwrap();
+		assert_eq!(v5.version(), SlateVersion::V5);
+		match &v5 {
+			VersionedSlate::V5(s) => assert!(s.compact),
+			_ => panic!("Expected V5 slate"),
+		}
+		let restored = Slate::from(v5);
+		assert!(restored.participant_data[0].part_sig.is_some());
+		assert_eq!(
+			restored.payment_proof.as_ref().unwrap().receiver_signature,
+			Some("receiver signature".to_string())
+		);
+		assert_eq!(
+			serde_json::to_string(&restored).unwrap(),
+			serde_json::to_string(&slate).unwrap()
+		);
+
+		// V5 json is readable as well, the older versions don't match it
+		let json = serde_json::to_string(&VersionedSlate::into_version(
+			slate.clone(),
+			SlateVersion::V5,
+		))
+		.unwrap();
+		let v5 = VersionedSlate::from_json(&json).unwrap();
+		assert_eq!(v5.version(), SlateVersion::V5);
+		assert_eq!(
+			serde_json::to_string(&Slate::deserialize_upgrade(&json).unwrap()).unwrap(),
+			serde_json::to_string(&slate).unwrap()
+		);
+		let v5: VersionedSlate = serde_json::from_str(&json).unwrap();
+		assert_eq!(v5.version(), SlateVersion::V5);
+		let v3: VersionedSlate =
+			serde_json::from_str(&serde_json::to_string(&v3).unwrap()).unwrap();
+		assert_eq!(v3.version(), SlateVersion::V3);
+
+		// Truncated data is rejected
+		assert!(VersionedSlate::from_binary(&data[..data.len() / 2]).is_err());
+	}
+
+	#[test]
+	fn test_compact_slate() {
+		let keychain = ExtKeychain::from_random_seed(false).unwrap();
+		let secp = keychain.secp();
+		let (mut slate, blind) = build_slate(&keychain, &Uuid::new_v4(), false);
+		slate.fee = tx_fee(1, 3, 1, None);
+		slate.amount = slate.fee * 10;
+		slate.update_kernel();
+		let mut sec_key = blind.secret_key(secp).unwrap();
+		let sec_nonce = SecretKey::new(secp, &mut thread_rng());
+		slate
+			.fill_rounds(&keychain, &mut sec_key, &sec_nonce, 0, None, true)
+			.unwrap();
+
+		let check_round_trip = |v3: &SlateV3| {
+			let full = SlateV5::from(v3.clone());
+			let compact = full.clone().into_compact();
+			assert!(compact.compact);
+			assert!(
+				bincode::serialize(&compact).unwrap().len()
+					< bincode::serialize(&full).unwrap().len()
+			);
+			// Upgraded to V5 and back is the same slate
+			assert_eq!(
+				serde_json::to_string(&SlateV3::from(compact.clone())).unwrap(),
+				serde_json::to_string(v3).unwrap()
+			);
+			assert_eq!(
+				serde_json::to_string(&SlateV3::from(full)).unwrap(),
+				serde_json::to_string(v3).unwrap()
+			);
+			compact
+		};
+
+		// Plain inputs and the kernel fee and lock height are derived from the slate
+		let v3 = SlateV3::from(slate.clone());
+		let compact = check_round_trip(&v3);
+		assert!(compact.tx.body.inputs.iter().all(|i| i.features.is_none()));
+		assert!(compact
+			.tx
+			.body
+			.kernels
+			.iter()
+			.all(|k| k.fee.is_none() && k.lock_height.is_none()));
+
+		// Values that can't be derived are kept
+		let mut v3 = SlateV3::from(slate);
+		v3.tx.body.inputs[0].features = OutputFeatures::Coinbase;
+		v3.tx.body.kernels[0].fee += 1;
+		let compact = check_round_trip(&v3);
+		assert_eq!(
+			compact.tx.body.inputs[0].features,
+			Some(OutputFeatures::Coinbase)
+		);
+		assert_eq!(compact.tx.body.kernels[0].fee, Some(v3.fee + 1));
+		assert_eq!(compact.tx.body.kernels[0].lock_height, None);
+	}
+
+	#[test]
+	fn test_slate_version_too_new() {
+		let check = |json: &str| match Slate::deserialize_upgrade(json).unwrap_err().kind() {
+			ErrorKind::SlateVersionTooNew { found, supported } => {
+				assert_eq!(found, 99);
+				assert_eq!(supported, CURRENT_SLATE_VERSION);
+			}
+			e => panic!("Unexpected error {}", e),
+		};
+
+		let mut slate = serde_json::to_value(&Slate::blank(2)).unwrap();
+		slate["version_info"]["version"] = serde_json::Value::from(99);
+		check(&slate.to_string());
+		check("{\"version\": 99, \"amount\": \"1000\"}");
+
+		let err = Slate::deserialize_upgrade(&slate.to_string()).unwrap_err();
+		assert!(format!("{}", err).contains("please upgrade your wallet"));
+	}
+
+	#[test]
+	fn test_round_1_export() {
+		let seed = [7u8; 32];
+		let keychain = ExtKeychain::from_seed(&seed, false).unwrap();
+		let secp = keychain.secp();
+		let (mut slate, blind) = build_slate(&keychain, &Uuid::new_v4(), false);
+		slate.fee = tx_fee(1, 3, 1, None);
+		slate.amount = slate.fee * 10;
+		slate.update_kernel();
+
+		// Same keys that build_slate used
+		let key_id = |i| ExtKeychainPath::new(1, i, 0, 0, 0).to_identifier();
+		let keys = Round1Keys {
+			inputs: vec![(key_id(1), 100)],
+			outputs: vec![(key_id(2), 20), (key_id(3), 10), (key_id(4), 60)],
+			nonce: Round1Keys::new_nonce_id(),
+		};
+		let sec_key = blind.secret_key(secp).unwrap();
+		assert_eq!(keys.sec_key(&keychain).unwrap(), sec_key);
+		let sec_nonce = keys.sec_nonce(&keychain).unwrap();
+
+		// Reference, both rounds on the same machine
+		let mut expected = slate.clone();
+		let mut expected_key = sec_key.clone();
+		expected
+			.fill_rounds(&keychain, &mut expected_key, &sec_nonce, 0, None, true)
+			.unwrap();
+
+		// Round 1 on the first machine
+		let mut slate1 = slate.clone();
+		let mut key1 = sec_key.clone();
+		slate1
+			.fill_round_1(&keychain, &mut key1, &sec_nonce, 0, None, true)
+			.unwrap();
+		let export = slate1.export_round_1(&keychain, 0, &keys).unwrap();
+		for secret in &[&sec_key, &key1, &sec_nonce] {
+			assert!(!export.contains(&grin_util::to_hex(secret.0.to_vec())));
+		}
+
+		// Round 2 on the second machine, it has the same seed and nothing else
+		let keychain2 = ExtKeychain::from_seed(&seed, false).unwrap();
+		let import = Slate::import_round_1(&keychain2, &export, &slate.id).unwrap();
+		assert_eq!(import.participant_id, 0);
+		assert_eq!(import.nonce_id, keys.nonce);
+		assert_eq!(import.sec_key, key1);
+		assert_eq!(import.sec_nonce, sec_nonce);
+		let mut slate2 = import.slate;
+		slate2
+			.fill_round_2(&keychain2, &import.sec_key, &import.sec_nonce, 0)
+			.unwrap();
+		assert_eq!(
+			serde_json::to_string(&slate2).unwrap(),
+			serde_json::to_string(&expected).unwrap()
+		);
+
+		// Export of another slate or to another wallet is rejected
+		assert!(Slate::import_round_1(&keychain, &export, &Uuid::new_v4()).is_err());
+		let foreign = ExtKeychain::from_random_seed(false).unwrap();
+		assert!(Slate::import_round_1(&foreign, &export, &slate.id).is_err());
+		// Keys that don't match the participant data can't be exported
+		let mut wrong_keys = keys.clone();
+		wrong_keys.nonce = Round1Keys::new_nonce_id();
+		assert!(slate1.export_round_1(&keychain, 0, &wrong_keys).is_err());
+		let mut wrong_keys = keys.clone();
+		wrong_keys.outputs.pop();
+		assert!(slate1.export_round_1(&keychain, 0, &wrong_keys).is_err());
+		// Signed slate is not a round 1 slate
+		assert!(slate2.export_round_1(&keychain, 0, &keys).is_err());
+		assert!(slate.export_round_1(&keychain, 0, &keys).is_err());
+	}
+}
@@ -17,10 +17,13 @@
 //! Versions earlier than V2 are removed for the 2.0.0 release, but versioning code
 //! remains for future needs
 
+use crate::error::{Error, ErrorKind};
 use crate::slate::Slate;
 use crate::slate_versions::v2::{CoinbaseV2, SlateV2};
 use crate::slate_versions::v3::{CoinbaseV3, SlateV3};
+use crate::slate_versions::v5::SlateV5;
 use crate::types::CbData;
+use strum::IntoEnumIterator;
 
 pub mod ser;
 
@@ -28,6 +31,8 @@ pub mod ser;
 pub mod v2;
 #[allow(missing_docs)]
 pub mod v3;
+#[allow(missing_docs)]
+pub mod v5;
 
 /// The most recent version of the slate
 pub const CURRENT_SLATE_VERSION: u16 = 3;
@@ -38,6 +43,10 @@ pub const GRIN_BLOCK_HEADER_VERSION: u16 = 3;
 /// Existing versions of the slate
 #[derive(EnumIter, Serialize, Deserialize, Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
 pub enum SlateVersion {
+	/// V5, binary transport form of V3. It is not negotiated with other wallets, so
+	/// CURRENT_SLATE_VERSION stays the same, but it is readable.
+	#[strum(disabled)]
+	V5,
 	/// V3b (most current) the difference between V3b and V3 is that the way to do payment proof is different
 	/// V3b support both mqs public key and dalek public key; V3 only support mqs public key.
 	/// they have the same format of slate though.
@@ -48,11 +57,78 @@ pub enum SlateVersion {
 	V2,
 }
 
+/// Minimum version of mwc-wallet that can process the slate version.
+/// Every SlateVersion must have an entry here.
+pub const SLATE_VERSION_MIN_WALLET: &[(SlateVersion, &str)] = &[
+	(SlateVersion::V5, "3.4.0"),
+	(SlateVersion::V3B, "3.3.0"),
+	(SlateVersion::V3, "3.0.0"),
+	(SlateVersion::V2, "2.0.0"),
+];
+
+/// Numeric slate versions that VersionedSlate and Slate::deserialize_upgrade can read,
+/// ascending. V5 is included, it is not negotiated but it can be read.
+pub fn supported_slate_versions() -> Vec<u16> {
+	let mut versions: Vec<u16> = SLATE_VERSION_MIN_WALLET
+		.iter()
+		.map(|(v, _)| v.to_u16())
+		.collect();
+	versions.sort();
+	versions.dedup();
+	versions
+}
+
+/// Check if the numeric slate version can be read
+pub fn check_slate_version(version: u16) -> Result<(), Error> {
+	if supported_slate_versions().contains(&version) {
+		return Ok(());
+	}
+	if version > CURRENT_SLATE_VERSION {
+		return Err(ErrorKind::SlateVersionTooNew {
+			found: version,
+			supported: CURRENT_SLATE_VERSION,
+		}
+		.into());
+	}
+	Err(ErrorKind::SlateVersion(version).into())
+}
+
+impl SlateVersion {
+	/// Numeric version that is written into the slate. V3B has the same format as V3.
+	pub fn to_u16(&self) -> u16 {
+		match self {
+			SlateVersion::V5 => 5,
+			SlateVersion::V3B | SlateVersion::V3 => 3,
+			SlateVersion::V2 => 2,
+		}
+	}
+
+	/// Minimum version of mwc-wallet that can process this slate version
+	pub fn min_wallet_version(&self) -> &'static str {
+		SLATE_VERSION_MIN_WALLET
+			.iter()
+			.find(|(v, _)| v == self)
+			.map(|(_, w)| *w)
+			.unwrap_or("unknown")
+	}
+
+	/// Warning for the user that the other party must run the compatible wallet
+	pub fn compatibility_warning(&self) -> String {
+		format!(
+			"Transaction contains features that require mwc-wallet {} or later. Please ensure the other party is running mwc-wallet v{} or later before sending",
+			self.min_wallet_version(),
+			self.min_wallet_version()
+		)
+	}
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 /// Versions are ordered newest to oldest so serde attempts to
 /// deserialize newer versions first, then falls back to older versions.
 pub enum VersionedSlate {
+	/// V5, binary transport form
+	V5(SlateV5),
 	/// Current (3.0.0 Onwards )
 	V3(SlateV3),
 	/// V2 (2.0.0 - Onwards)
@@ -63,6 +139,7 @@ impl VersionedSlate {
 	/// Return slate version
 	pub fn version(&self) -> SlateVersion {
 		match *self {
+			VersionedSlate::V5(_) => SlateVersion::V5,
 			VersionedSlate::V3(_) => SlateVersion::V3,
 			VersionedSlate::V2(_) => SlateVersion::V2,
 		}
@@ -71,6 +148,10 @@ impl VersionedSlate {
 	/// convert this slate type to a specified older version
 	pub fn into_version(slate: Slate, version: SlateVersion) -> VersionedSlate {
 		match version {
+			SlateVersion::V5 => {
+				let s = SlateV3::from(slate);
+				VersionedSlate::V5(SlateV5::from(s))
+			}
 			SlateVersion::V3B => VersionedSlate::V3(slate.into()),
 			SlateVersion::V3 => VersionedSlate::V3(slate.into()),
 			// Left here as a reminder of what needs to be inserted on
@@ -82,11 +163,228 @@ impl VersionedSlate {
 			}
 		}
 	}
+
+	/// Slate features that the version can't carry. They are dropped if the slate is
+	/// converted into this version.
+	pub fn lost_features(slate: &Slate, version: SlateVersion) -> Vec<String> {
+		match version {
+			SlateVersion::V2 => slate.version_features(),
+			SlateVersion::V5 | SlateVersion::V3 | SlateVersion::V3B => vec![],
+		}
+	}
+}
+
+/// Result of the slate serialized size check against the transport limit
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlateSizeCheck {
+	/// Slate fits into the limit as it is
+	Fits,
+	/// Slate doesn't fit, but the older more compact version does
+	UseVersion(SlateVersion),
+	/// No version fits, the transaction needs to be split. Value is the smallest size we can get.
+	Split(usize),
+}
+
+impl VersionedSlate {
+	/// Estimated size of the serialized slate in bytes
+	pub fn estimate_size(&self) -> usize {
+		serde_json::to_string(self)
+			.map(|s| s.len())
+			.unwrap_or(usize::MAX)
+	}
+
+	/// Check if the serialized slate exceeds the transport size limit (MQS and other transports
+	/// has limits). If it does, suggest the most recent older version that fits, or splitting.
+	/// Versions that can't carry the slate features (payment proof, ttl) are not suggested.
+	pub fn check_size_limit(&self, limit: usize) -> SlateSizeCheck {
+		let mut min_size = self.estimate_size();
+		if min_size <= limit {
+			return SlateSizeCheck::Fits;
+		}
+
+		let slate = Slate::from(self.clone());
+		let need_v3 = slate.lowest_version() == SlateVersion::V3;
+		let cur_version = self.version();
+		for version in SlateVersion::iter().filter(|v| *v > cur_version) {
+			if need_v3 && version == SlateVersion::V2 {
+				continue;
+			}
+			let size = VersionedSlate::into_version(slate.clone(), version.clone()).estimate_size();
+			if size <= limit {
+				warn!(
+					"Slate {} size {} exceeds the limit {}, version {:?} will fit",
+					slate.id, min_size, limit, version
+				);
+				return SlateSizeCheck::UseVersion(version);
+			}
+			min_size = std::cmp::min(min_size, size);
+		}
+		warn!(
+			"Slate {} size {} exceeds the limit {}, please split the transaction",
+			slate.id, min_size, limit
+		);
+		SlateSizeCheck::Split(min_size)
+	}
+
+	/// Read the slate json as it is, without upgrade to the latest version
+	pub fn from_json(slate_json: &str) -> Result<VersionedSlate, Error> {
+		let version = Slate::parse_slate_version(slate_json)?;
+		check_slate_version(version)?;
+		let slate = match version {
+			5 => VersionedSlate::V5(serde_json::from_str(slate_json).map_err(|e| {
+				ErrorKind::SlateDeser(format!("Json to SlateV5 conversion failed, {}", e))
+			})?),
+			3 => VersionedSlate::V3(serde_json::from_str(slate_json).map_err(|e| {
+				ErrorKind::SlateDeser(format!("Json to SlateV3 conversion failed, {}", e))
+			})?),
+			2 => VersionedSlate::V2(serde_json::from_str(slate_json).map_err(|e| {
+				ErrorKind::SlateDeser(format!("Json to SlateV2 conversion failed, {}", e))
+			})?),
+			_ => return Err(ErrorKind::SlateVersion(version).into()),
+		};
+		Ok(slate)
+	}
+
+	/// Serialize the slate into the binary form with bincode. The slate is converted into
+	/// the compact V5, the only version that can be written as binary.
+	pub fn to_binary(&self) -> Result<Vec<u8>, Error> {
+		let slate = match self {
+			VersionedSlate::V5(s) => s.clone(),
+			VersionedSlate::V3(s) => SlateV5::from(s.clone()),
+			VersionedSlate::V2(s) => SlateV5::from(SlateV3::from(s.clone())),
+		}
+		.into_compact();
+		let data = bincode::serialize(&slate).map_err(|e| {
+			ErrorKind::SlateSer(format!("SlateV5 to binary conversion failed, {}", e))
+		})?;
+		Ok(data)
+	}
+
+	/// Read the slate that was written by to_binary
+	pub fn from_binary(data: &[u8]) -> Result<VersionedSlate, Error> {
+		let slate: SlateV5 = bincode::deserialize(data).map_err(|e| {
+			ErrorKind::SlateDeser(format!("Binary to SlateV5 conversion failed, {}", e))
+		})?;
+		if slate.version_info.version != SlateVersion::V5.to_u16() {
+			return Err(ErrorKind::SlateVersion(slate.version_info.version).into());
+		}
+		Ok(VersionedSlate::V5(slate))
+	}
+
+	/// Compact human readable summary for debugging. Version, features and the counts of
+	/// the transaction parts are listed, range proofs and signatures are omitted.
+	pub fn debug_summary(&self) -> String {
+		let mut lines: Vec<String> = Vec::new();
+		match self {
+			VersionedSlate::V5(s) => {
+				lines.push(format!(
+					"Slate {} V5 (version {}, orig_version {}, block_header_version {}, compact {})",
+					s.id,
+					s.version_info.version,
+					s.version_info.orig_version,
+					s.version_info.block_header_version,
+					s.compact
+				));
+				// The rest of the content is the same as V3
+				let v3 = VersionedSlate::V3(SlateV3::from(s.clone()));
+				lines.extend(v3.debug_summary().lines().skip(1).map(|l| l.to_string()));
+			}
+			VersionedSlate::V3(s) => {
+				let signed = s.participant_data.iter().filter(|p| p.part_sig.is_some());
+				lines.push(format!(
+					"Slate {} V3 (version {}, orig_version {}, block_header_version {})",
+					s.id,
+					s.version_info.version,
+					s.version_info.orig_version,
+					s.version_info.block_header_version
+				));
+				lines.push(format!(
+					"  amount: {}, fee: {}, height: {}, lock_height: {}",
+					s.amount, s.fee, s.height, s.lock_height
+				));
+				lines.push(format!(
+					"  participants: {}, participant data: {}, signed: {}",
+					s.num_participants,
+					s.participant_data.len(),
+					signed.count()
+				));
+				lines.push(format!(
+					"  tx: {} inputs, {} outputs, {} kernels {:?}",
+					s.tx.body.inputs.len(),
+					s.tx.body.outputs.len(),
+					s.tx.body.kernels.len(),
+					s.tx.body
+						.kernels
+						.iter()
+						.map(|k| k.features)
+						.collect::<Vec<_>>()
+				));
+				lines.push(format!(
+					"  coin_type: {:?}, network_type: {:?}",
+					s.coin_type, s.network_type
+				));
+				lines.push(format!("  ttl_cutoff_height: {:?}", s.ttl_cutoff_height));
+				lines.push(match &s.payment_proof {
+					Some(proof) => format!(
+						"  payment_proof: sender {}, receiver {}, signed: {}",
+						proof.sender_address,
+						proof.receiver_address,
+						proof.receiver_signature.is_some()
+					),
+					None => "  payment_proof: None".to_string(),
+				});
+			}
+			VersionedSlate::V2(s) => {
+				let signed = s.participant_data.iter().filter(|p| p.part_sig.is_some());
+				lines.push(format!(
+					"Slate {} V2 (version {}, orig_version {}, block_header_version {})",
+					s.id,
+					s.version_info.version,
+					s.version_info.orig_version,
+					s.version_info.block_header_version
+				));
+				lines.push(format!(
+					"  amount: {}, fee: {}, height: {}, lock_height: {}",
+					s.amount, s.fee, s.height, s.lock_height
+				));
+				lines.push(format!(
+					"  participants: {}, participant data: {}, signed: {}",
+					s.num_participants,
+					s.participant_data.len(),
+					signed.count()
+				));
+				lines.push(format!(
+					"  tx: {} inputs, {} outputs, {} kernels {:?}",
+					s.tx.body.inputs.len(),
+					s.tx.body.outputs.len(),
+					s.tx.body.kernels.len(),
+					s.tx.body
+						.kernels
+						.iter()
+						.map(|k| k.features)
+						.collect::<Vec<_>>()
+				));
+				lines.push(format!(
+					"  coin_type: {:?}, network_type: {:?}",
+					s.coin_type, s.network_type
+				));
+				lines.push(format!(
+					"  ttl_cutoff_height, payment_proof: not supported by V2, require mwc-wallet {} or later",
+					SlateVersion::V3.min_wallet_version()
+				));
+			}
+		}
+		lines.join("\n")
+	}
 }
 
 impl From<VersionedSlate> for Slate {
 	fn from(slate: VersionedSlate) -> Slate {
 		match slate {
+			VersionedSlate::V5(s) => {
+				let s = SlateV3::from(s);
+				Slate::from(s)
+			}
 			VersionedSlate::V3(s) => Slate::from(s),
 			VersionedSlate::V2(s) => {
 				let s = SlateV3::from(s);
@@ -111,9 +409,163 @@ impl VersionedCoinbase {
 	/// convert this coinbase data to a specific versioned representation for the json api.
 	pub fn into_version(cb: CbData, version: SlateVersion) -> VersionedCoinbase {
 		match version {
+			SlateVersion::V5 => VersionedCoinbase::V3(cb.into()),
 			SlateVersion::V3B => VersionedCoinbase::V3(cb.into()),
 			SlateVersion::V3 => VersionedCoinbase::V3(cb.into()),
 			SlateVersion::V2 => VersionedCoinbase::V2(cb.into()),
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grin_core::core::transaction::{Output, OutputFeatures};
+	use crate::grin_util::secp::pedersen::{Commitment, RangeProof};
+
+	#[test]
+	fn test_slate_version_min_wallet() {
+		for version in SlateVersion::iter() {
+			assert_eq!(
+				SLATE_VERSION_MIN_WALLET
+					.iter()
+					.filter(|(v, _)| *v == version)
+					.count(),
+				1,
+				"SlateVersion {:?} must have a single minimum wallet version entry",
+				version
+			);
+			assert_ne!(version.min_wallet_version(), "unknown");
+		}
+		assert_eq!(SlateVersion::V3.min_wallet_version(), "3.0.0");
+		assert!(SlateVersion::V3
+			.compatibility_warning()
+			.contains("mwc-wallet 3.0.0 or later"));
+	}
+
+	#[test]
+	fn test_supported_slate_versions() {
+		let versions = supported_slate_versions();
+		assert_eq!(versions, vec![2, 3, 5]);
+		assert!(!versions.contains(&(CURRENT_SLATE_VERSION + 1)));
+		for version in SlateVersion::iter() {
+			assert!(versions.contains(&version.to_u16()));
+		}
+
+		// Every listed version is readable, with and without the upgrade
+		for n in versions {
+			let version = SLATE_VERSION_MIN_WALLET
+				.iter()
+				.map(|(v, _)| v.clone())
+				.find(|v| v.to_u16() == n)
+				.unwrap();
+			let json =
+				serde_json::to_string(&VersionedSlate::into_version(Slate::blank(2), version))
+					.unwrap();
+			assert_eq!(
+				VersionedSlate::from_json(&json).unwrap().version().to_u16(),
+				n
+			);
+			Slate::deserialize_upgrade(&json).unwrap();
+		}
+
+		// Not listed versions are rejected by both readers
+		for n in &[1, 4, 6] {
+			let mut json = serde_json::to_value(&Slate::blank(2)).unwrap();
+			json["version_info"]["version"] = serde_json::Value::from(*n);
+			assert!(VersionedSlate::from_json(&json.to_string()).is_err());
+			assert!(Slate::deserialize_upgrade(&json.to_string()).is_err());
+		}
+	}
+
+	#[test]
+	fn test_slate_size_limit() {
+		let mut slate = Slate::blank(2);
+		for _ in 0..20 {
+			slate.tx.body.outputs.push(Output {
+				features: OutputFeatures::Plain,
+				commit: Commitment::from_vec(vec![9; 33]),
+				proof: RangeProof::zero(),
+			});
+		}
+		let v3 = VersionedSlate::into_version(slate.clone(), SlateVersion::V3);
+		let v2_size = VersionedSlate::into_version(slate.clone(), SlateVersion::V2).estimate_size();
+		assert!(v3.estimate_size() > v2_size);
+
+		assert_eq!(
+			v3.check_size_limit(v3.estimate_size()),
+			SlateSizeCheck::Fits
+		);
+		assert_eq!(
+			v3.check_size_limit(v2_size),
+			SlateSizeCheck::UseVersion(SlateVersion::V2)
+		);
+		assert_eq!(
+			v3.check_size_limit(v2_size - 1),
+			SlateSizeCheck::Split(v2_size)
+		);
+
+		// ttl can't be carried by V2, so splitting is the only option
+		slate.ttl_cutoff_height = Some(1000);
+		let v3 = VersionedSlate::into_version(slate, SlateVersion::V3);
+		assert_eq!(
+			v3.check_size_limit(v2_size),
+			SlateSizeCheck::Split(v3.estimate_size())
+		);
+	}
+
+	#[test]
+	fn test_slate_lost_features() {
+		let mut slate = Slate::blank(2);
+		assert!(VersionedSlate::lost_features(&slate, SlateVersion::V2).is_empty());
+
+		slate.ttl_cutoff_height = Some(1000);
+		assert_eq!(
+			VersionedSlate::lost_features(&slate, SlateVersion::V2),
+			vec!["ttl cutoff height".to_string()]
+		);
+		assert!(VersionedSlate::lost_features(&slate, SlateVersion::V3).is_empty());
+	}
+
+	#[test]
+	fn test_slate_debug_summary() {
+		let mut slate = Slate::blank(2);
+		slate.amount = 1_000_000_000;
+		slate.fee = 8_000_000;
+		slate.ttl_cutoff_height = Some(1000);
+		slate.tx.body.outputs.push(Output {
+			features: OutputFeatures::Plain,
+			commit: Commitment::from_vec(vec![9; 33]),
+			proof: RangeProof::zero(),
+		});
+
+		let v3 = VersionedSlate::into_version(slate.clone(), SlateVersion::V3);
+		let summary = v3.debug_summary();
+		assert!(summary.starts_with(&format!("Slate {} V3 (version 3", slate.id)));
+		assert!(summary.contains("amount: 1000000000, fee: 8000000"));
+		assert!(summary.contains("participants: 2, participant data: 0, signed: 0"));
+		assert!(summary.contains("0 inputs, 1 outputs"));
+		assert!(summary.contains("ttl_cutoff_height: Some(1000)"));
+		assert!(summary.contains("payment_proof: None"));
+		// No range proofs, it is much smaller than json
+		assert!(summary.len() * 2 < v3.estimate_size());
+
+		let v5 = VersionedSlate::into_version(slate.clone(), SlateVersion::V5);
+		let summary = v5.debug_summary();
+		assert!(summary.starts_with(&format!("Slate {} V5 (version 5", slate.id)));
+		assert!(summary.contains("compact false"));
+		assert!(summary.contains("amount: 1000000000, fee: 8000000"));
+		assert!(summary.contains("ttl_cutoff_height: Some(1000)"));
+
+		let v2 = VersionedSlate::into_version(slate.clone(), SlateVersion::V2);
+		let summary = v2.debug_summary();
+		assert!(summary.starts_with(&format!("Slate {} V2 (version 2", slate.id)));
+		assert!(summary.contains("not supported by V2"));
+
+		// Version is taken from the json as it is
+		let json = serde_json::to_string(&v2).unwrap();
+		let v2 = VersionedSlate::from_json(&json).unwrap();
+		assert_eq!(v2.version(), SlateVersion::V2);
+		assert_eq!(v2.debug_summary(), summary);
+	}
+}
@@ -233,6 +233,52 @@ pub mod option_dalek_sig_serde {
 	}
 }
 
+/// Serializes an Option<secp::Signature> to and from hex. Unlike grin secp_ser::option_sig_serde
+/// the option tag is written for both variants, so it works with the binary serializers too.
+pub mod option_secp_sig_serde {
+	use serde::de::Error;
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+	use crate::grin_util::secp::Signature;
+	use crate::grin_util::{from_hex, static_secp_instance, to_hex};
+
+	///
+	pub fn serialize<S>(sig: &Option<Signature>, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let secp_inst = static_secp_instance();
+		let secp = secp_inst.lock();
+		sig.as_ref()
+			.map(|sig| to_hex(sig.serialize_compact(&secp).to_vec()))
+			.serialize(serializer)
+	}
+
+	///
+	pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Signature>, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Option::<String>::deserialize(deserializer).and_then(|res| match res {
+			Some(string) => from_hex(&string)
+				.map_err(|err| {
+					Error::custom(format!(
+						"Signature, Unable to parse HEX {}, {}",
+						string, err
+					))
+				})
+				.and_then(|bytes: Vec<u8>| {
+					let secp_inst = static_secp_instance();
+					let secp = secp_inst.lock();
+					Signature::from_compact(&secp, &bytes)
+						.map(Some)
+						.map_err(|err| Error::custom(format!("Unable to build Signature, {}", err)))
+				}),
+			None => Ok(None),
+		})
+	}
+}
+
 // Test serialization methods of components that are being used
 #[cfg(test)]
 mod test {
@@ -76,6 +76,9 @@ pub struct SlateV3 {
 	/// Payment Proof
 	///#[serde(default = "default_payment_none")]
 	pub payment_proof: Option<PaymentInfoV3>,
+	/// Binding to the intended recipient
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub recipient_binding: Option<RecipientBindingV3>,
 }
 
 //fn default_payment_none() -> Option<PaymentInfoV3> {
@@ -128,6 +131,16 @@ pub struct PaymentInfoV3 {
 	pub receiver_signature: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecipientBindingV3 {
+	/// Public key of the intended recipient
+	#[serde(with = "secp_ser::pubkey_serde")]
+	pub recipient: PublicKey,
+	/// Recipient signature of the binding commitment
+	#[serde(default, with = "secp_ser::option_sig_serde")]
+	pub signature: Option<Signature>,
+}
+
 /// A transaction
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TransactionV3 {
@@ -252,6 +265,7 @@ impl From<SlateV2> for SlateV3 {
 			participant_data,
 			version_info,
 			payment_proof: None,
+			recipient_binding: None,
 		}
 	}
 }
@@ -373,6 +387,7 @@ impl From<&SlateV3> for SlateV2 {
 			participant_data,
 			version_info,
 			payment_proof,
+			recipient_binding,
 		} = slate;
 		let num_participants = *num_participants;
 		let id = *id;
@@ -0,0 +1,573 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains V5 of the slate, the binary transport form
+//! Changes from V3:
+//! * Addition of the compact flag
+//! * Numbers are not written as strings and the optional signatures always carry the option tag,
+//!   so the slate can be serialized with bincode
+//! * Compact form: the plain input features and the kernel fee and lock height that are equal
+//!   to the slate values are omitted
+
+use crate::grin_core::core::transaction::OutputFeatures;
+use crate::grin_core::libtx::secp_ser;
+use crate::grin_core::map_vec;
+use crate::grin_keychain::BlindingFactor;
+use crate::grin_util::secp;
+use crate::grin_util::secp::key::PublicKey;
+use crate::grin_util::secp::pedersen::{Commitment, RangeProof};
+use crate::grin_util::secp::Signature;
+use crate::proof::proofaddress;
+use crate::proof::proofaddress::ProvableAddress;
+use crate::slate::CompatKernelFeatures;
+use crate::slate_versions::ser::option_secp_sig_serde;
+use uuid::Uuid;
+
+use crate::slate_versions::v3::{
+	InputV3, OutputV3, ParticipantDataV3, PaymentInfoV3, RecipientBindingV3, SlateV3,
+	TransactionBodyV3, TransactionV3, TxKernelV3, VersionCompatInfoV3,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SlateV5 {
+	/// Versioning info
+	pub version_info: VersionCompatInfoV5,
+	/// The number of participants intended to take part in this transaction
+	pub num_participants: usize,
+	/// Unique transaction ID, selected by sender
+	pub id: Uuid,
+	/// The core transaction data:
+	/// inputs, outputs, kernels, kernel offset
+	pub tx: TransactionV5,
+	/// base amount (excluding fee)
+	pub amount: u64,
+	/// fee amount
+	pub fee: u64,
+	/// Block height for the transaction
+	pub height: u64,
+	/// Lock height
+	pub lock_height: u64,
+	/// TTL, the block height at which wallets
+	/// should refuse to process the transaction and unlock all
+	/// associated outputs
+	pub ttl_cutoff_height: Option<u64>,
+	/// coin Type the default is mwc.
+	pub coin_type: Option<String>,
+	/// network type default is mainnet
+	pub network_type: Option<String>,
+	/// Participant data, each participant in the transaction will
+	/// insert their public data here. For now, 0 is sender and 1
+	/// is receiver, though this will change for multi-party
+	pub participant_data: Vec<ParticipantDataV5>,
+	/// Payment Proof
+	pub payment_proof: Option<PaymentInfoV5>,
+	/// Binding to the intended recipient
+	pub recipient_binding: Option<RecipientBindingV5>,
+	/// True if the slate was written in the compact binary form. The flag is required, so the json
+	/// of the older versions never matches V5.
+	pub compact: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionCompatInfoV5 {
+	/// The current version of the slate format
+	pub version: u16,
+	/// Original version this slate was converted from
+	pub orig_version: u16,
+	/// Version of grin block header this slate is compatible with
+	pub block_header_version: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ParticipantDataV5 {
+	/// Id of participant in the transaction. (For now, 0=sender, 1=rec)
+	pub id: u64,
+	/// Public key corresponding to private blinding factor
+	#[serde(with = "secp_ser::pubkey_serde")]
+	pub public_blind_excess: PublicKey,
+	/// Public key corresponding to private nonce
+	#[serde(with = "secp_ser::pubkey_serde")]
+	pub public_nonce: PublicKey,
+	/// Public partial signature
+	#[serde(with = "option_secp_sig_serde")]
+	pub part_sig: Option<Signature>,
+	/// A message for other participants
+	pub message: Option<String>,
+	/// Signature, created with private key corresponding to 'public_blind_excess'
+	#[serde(with = "option_secp_sig_serde")]
+	pub message_sig: Option<Signature>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PaymentInfoV5 {
+	#[serde(
+		serialize_with = "proofaddress::as_string",
+		deserialize_with = "proofaddress::proof_address_from_string"
+	)]
+	pub sender_address: ProvableAddress,
+	#[serde(
+		serialize_with = "proofaddress::as_string",
+		deserialize_with = "proofaddress::proof_address_from_string"
+	)]
+	pub receiver_address: ProvableAddress,
+	pub receiver_signature: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecipientBindingV5 {
+	/// Public key of the intended recipient
+	#[serde(with = "secp_ser::pubkey_serde")]
+	pub recipient: PublicKey,
+	/// Recipient signature of the binding commitment
+	#[serde(with = "option_secp_sig_serde")]
+	pub signature: Option<Signature>,
+}
+
+/// A transaction
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransactionV5 {
+	/// The kernel "offset" k2
+	/// excess is k1G after splitting the key k = k1 + k2
+	#[serde(
+		serialize_with = "secp_ser::as_hex",
+		deserialize_with = "secp_ser::blind_from_hex"
+	)]
+	pub offset: BlindingFactor,
+	/// The transaction body - inputs/outputs/kernels
+	pub body: TransactionBodyV5,
+}
+
+/// TransactionBody is a common abstraction for transaction and block
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransactionBodyV5 {
+	/// List of inputs spent by the transaction.
+	pub inputs: Vec<InputV5>,
+	/// List of outputs the transaction produces.
+	pub outputs: Vec<OutputV5>,
+	/// List of kernels that make up this transaction (usually a single kernel).
+	pub kernels: Vec<TxKernelV5>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InputV5 {
+	/// The features of the output being spent.
+	/// We will check maturity for coinbase output.
+	/// None for the plain output in the compact form.
+	pub features: Option<OutputFeatures>,
+	/// The commit referencing the output being spent.
+	#[serde(
+		serialize_with = "secp_ser::as_hex",
+		deserialize_with = "secp_ser::commitment_from_hex"
+	)]
+	pub commit: Commitment,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct OutputV5 {
+	/// Options for an output's structure or use
+	pub features: OutputFeatures,
+	/// The homomorphic commitment representing the output amount
+	#[serde(
+		serialize_with = "secp_ser::as_hex",
+		deserialize_with = "secp_ser::commitment_from_hex"
+	)]
+	pub commit: Commitment,
+	/// A proof that the commitment is in the right range
+	#[serde(
+		serialize_with = "secp_ser::as_hex",
+		deserialize_with = "secp_ser::rangeproof_from_hex"
+	)]
+	pub proof: RangeProof,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TxKernelV5 {
+	/// Options for a kernel's structure or use
+	pub features: CompatKernelFeatures,
+	/// Fee originally included in the transaction this proof is for.
+	/// None in the compact form if it is equal to the slate fee.
+	pub fee: Option<u64>,
+	/// This kernel is not valid earlier than lock_height blocks
+	/// The max lock_height of all *inputs* to this transaction
+	/// None in the compact form if it is equal to the slate lock height.
+	pub lock_height: Option<u64>,
+	/// Remainder of the sum of all transaction commitments. If the transaction
+	/// is well formed, amounts components should sum to zero and the excess
+	/// is hence a valid public key.
+	#[serde(
+		serialize_with = "secp_ser::as_hex",
+		deserialize_with = "secp_ser::commitment_from_hex"
+	)]
+	pub excess: Commitment,
+	/// The signature proving the excess is a valid public key, which signs
+	/// the transaction fee.
+	#[serde(with = "secp_ser::sig_serde")]
+	pub excess_sig: secp::Signature,
+}
+
+impl SlateV5 {
+	/// Convert into the compact form. Only the values that can be restored from the rest
+	/// of the slate are dropped, so the conversion is lossless.
+	pub fn into_compact(mut self) -> SlateV5 {
+		let (fee, lock_height) = (self.fee, self.lock_height);
+		for input in &mut self.tx.body.inputs {
+			if input.features == Some(OutputFeatures::Plain) {
+				input.features = None;
+			}
+		}
+		for kernel in &mut self.tx.body.kernels {
+			if kernel.fee == Some(fee) {
+				kernel.fee = None;
+			}
+			if kernel.lock_height == Some(lock_height) {
+				kernel.lock_height = None;
+			}
+		}
+		self.compact = true;
+		self
+	}
+
+	/// Restore the values that were dropped by into_compact
+	pub fn into_expanded(mut self) -> SlateV5 {
+		let (fee, lock_height) = (self.fee, self.lock_height);
+		for input in &mut self.tx.body.inputs {
+			input.features.get_or_insert(OutputFeatures::Plain);
+		}
+		for kernel in &mut self.tx.body.kernels {
+			kernel.fee.get_or_insert(fee);
+			kernel.lock_height.get_or_insert(lock_height);
+		}
+		self.compact = false;
+		self
+	}
+}
+
+// V3 to V5 For Slate
+impl From<SlateV3> for SlateV5 {
+	fn from(slate: SlateV3) -> SlateV5 {
+		let SlateV3 {
+			num_participants,
+			id,
+			tx,
+			amount,
+			fee,
+			height,
+			lock_height,
+			ttl_cutoff_height,
+			coin_type,
+			network_type,
+			participant_data,
+			version_info,
+			payment_proof,
+			recipient_binding,
+		} = slate;
+		let participant_data = map_vec!(participant_data, |data| ParticipantDataV5::from(data));
+		let version_info = VersionCompatInfoV5::from(&version_info);
+		let tx = TransactionV5::from(tx);
+		let payment_proof = payment_proof.map(PaymentInfoV5::from);
+		let recipient_binding = recipient_binding.map(RecipientBindingV5::from);
+		SlateV5 {
+			num_participants,
+			id,
+			tx,
+			amount,
+			fee,
+			height,
+			lock_height,
+			ttl_cutoff_height,
+			coin_type,
+			network_type,
+			participant_data,
+			version_info,
+			payment_proof,
+			recipient_binding,
+			compact: false,
+		}
+	}
+}
+
+impl From<&ParticipantDataV3> for ParticipantDataV5 {
+	fn from(data: &ParticipantDataV3) -> ParticipantDataV5 {
+		ParticipantDataV5 {
+			id: data.id,
+			public_blind_excess: data.public_blind_excess,
+			public_nonce: data.public_nonce,
+			part_sig: data.part_sig,
+			message: data.message.clone(),
+			message_sig: data.message_sig,
+		}
+	}
+}
+
+impl From<&VersionCompatInfoV3> for VersionCompatInfoV5 {
+	fn from(data: &VersionCompatInfoV3) -> VersionCompatInfoV5 {
+		VersionCompatInfoV5 {
+			version: 5,
+			orig_version: data.orig_version,
+			block_header_version: data.block_header_version,
+		}
+	}
+}
+
+impl From<PaymentInfoV3> for PaymentInfoV5 {
+	fn from(proof: PaymentInfoV3) -> PaymentInfoV5 {
+		let PaymentInfoV3 {
+			sender_address,
+			receiver_address,
+			receiver_signature,
+		} = proof;
+		PaymentInfoV5 {
+			sender_address,
+			receiver_address,
+			receiver_signature,
+		}
+	}
+}
+
+impl From<RecipientBindingV3> for RecipientBindingV5 {
+	fn from(binding: RecipientBindingV3) -> RecipientBindingV5 {
+		let RecipientBindingV3 {
+			recipient,
+			signature,
+		} = binding;
+		RecipientBindingV5 {
+			recipient,
+			signature,
+		}
+	}
+}
+
+impl From<TransactionV3> for TransactionV5 {
+	fn from(tx: TransactionV3) -> TransactionV5 {
+		let TransactionV3 { offset, body } = tx;
+		let body = TransactionBodyV5::from(&body);
+		TransactionV5 { offset, body }
+	}
+}
+
+impl From<&TransactionBodyV3> for TransactionBodyV5 {
+	fn from(body: &TransactionBodyV3) -> TransactionBodyV5 {
+		let TransactionBodyV3 {
+			inputs,
+			outputs,
+			kernels,
+		} = body;
+
+		let inputs = map_vec!(inputs, |inp| InputV5::from(inp));
+		let outputs = map_vec!(outputs, |out| OutputV5::from(out));
+		let kernels = map_vec!(kernels, |kern| TxKernelV5::from(kern));
+		TransactionBodyV5 {
+			inputs,
+			outputs,
+			kernels,
+		}
+	}
+}
+
+impl From<&InputV3> for InputV5 {
+	fn from(input: &InputV3) -> InputV5 {
+		let InputV3 { features, commit } = *input;
+		InputV5 {
+			features: Some(features),
+			commit,
+		}
+	}
+}
+
+impl From<&OutputV3> for OutputV5 {
+	fn from(output: &OutputV3) -> OutputV5 {
+		let OutputV3 {
+			features,
+			commit,
+			proof,
+		} = *output;
+		OutputV5 {
+			features,
+			commit,
+			proof,
+		}
+	}
+}
+
+impl From<&TxKernelV3> for TxKernelV5 {
+	fn from(kernel: &TxKernelV3) -> TxKernelV5 {
+		TxKernelV5 {
+			features: kernel.features,
+			fee: Some(kernel.fee),
+			lock_height: Some(kernel.lock_height),
+			excess: kernel.excess,
+			excess_sig: kernel.excess_sig,
+		}
+	}
+}
+
+// V5 to V3
+impl From<SlateV5> for SlateV3 {
+	fn from(slate: SlateV5) -> SlateV3 {
+		// Inputs and kernels below are converted from the expanded form
+		let SlateV5 {
+			num_participants,
+			id,
+			tx,
+			amount,
+			fee,
+			height,
+			lock_height,
+			ttl_cutoff_height,
+			coin_type,
+			network_type,
+			participant_data,
+			version_info,
+			payment_proof,
+			recipient_binding,
+			compact: _,
+		} = slate.into_expanded();
+		let participant_data = map_vec!(participant_data, |data| ParticipantDataV3::from(data));
+		let version_info = VersionCompatInfoV3::from(&version_info);
+		let tx = TransactionV3::from(tx);
+		let payment_proof = payment_proof.map(PaymentInfoV3::from);
+		let recipient_binding = recipient_binding.map(RecipientBindingV3::from);
+		SlateV3 {
+			num_participants,
+			id,
+			tx,
+			amount,
+			fee,
+			height,
+			lock_height,
+			ttl_cutoff_height,
+			coin_type,
+			network_type,
+			participant_data,
+			version_info,
+			payment_proof,
+			recipient_binding,
+		}
+	}
+}
+
+impl From<&ParticipantDataV5> for ParticipantDataV3 {
+	fn from(data: &ParticipantDataV5) -> ParticipantDataV3 {
+		ParticipantDataV3 {
+			id: data.id,
+			public_blind_excess: data.public_blind_excess,
+			public_nonce: data.public_nonce,
+			part_sig: data.part_sig,
+			message: data.message.clone(),
+			message_sig: data.message_sig,
+		}
+	}
+}
+
+impl From<&VersionCompatInfoV5> for VersionCompatInfoV3 {
+	fn from(data: &VersionCompatInfoV5) -> VersionCompatInfoV3 {
+		VersionCompatInfoV3 {
+			version: 3,
+			orig_version: data.orig_version,
+			block_header_version: data.block_header_version,
+		}
+	}
+}
+
+impl From<PaymentInfoV5> for PaymentInfoV3 {
+	fn from(proof: PaymentInfoV5) -> PaymentInfoV3 {
+		let PaymentInfoV5 {
+			sender_address,
+			receiver_address,
+			receiver_signature,
+		} = proof;
+		PaymentInfoV3 {
+			sender_address,
+			receiver_address,
+			receiver_signature,
+		}
+	}
+}
+
+impl From<RecipientBindingV5> for RecipientBindingV3 {
+	fn from(binding: RecipientBindingV5) -> RecipientBindingV3 {
+		let RecipientBindingV5 {
+			recipient,
+			signature,
+		} = binding;
+		RecipientBindingV3 {
+			recipient,
+			signature,
+		}
+	}
+}
+
+impl From<TransactionV5> for TransactionV3 {
+	fn from(tx: TransactionV5) -> TransactionV3 {
+		let TransactionV5 { offset, body } = tx;
+		let body = TransactionBodyV3::from(&body);
+		TransactionV3 { offset, body }
+	}
+}
+
+impl From<&TransactionBodyV5> for TransactionBodyV3 {
+	fn from(body: &TransactionBodyV5) -> TransactionBodyV3 {
+		let TransactionBodyV5 {
+			inputs,
+			outputs,
+			kernels,
+		} = body;
+
+		let inputs = map_vec!(inputs, |inp| InputV3::from(inp));
+		let outputs = map_vec!(outputs, |out| OutputV3::from(out));
+		let kernels = map_vec!(kernels, |kern| TxKernelV3::from(kern));
+		TransactionBodyV3 {
+			inputs,
+			outputs,
+			kernels,
+		}
+	}
+}
+
+impl From<&InputV5> for InputV3 {
+	fn from(input: &InputV5) -> InputV3 {
+		let InputV5 { features, commit } = *input;
+		InputV3 {
+			features: features.unwrap_or(OutputFeatures::Plain),
+			commit,
+		}
+	}
+}
+
+impl From<&OutputV5> for OutputV3 {
+	fn from(output: &OutputV5) -> OutputV3 {
+		let OutputV5 {
+			features,
+			commit,
+			proof,
+		} = *output;
+		OutputV3 {
+			features,
+			commit,
+			proof,
+		}
+	}
+}
+
+impl From<&TxKernelV5> for TxKernelV3 {
+	fn from(kernel: &TxKernelV5) -> TxKernelV3 {
+		TxKernelV3 {
+			features: kernel.features,
+			fee: kernel.fee.unwrap_or(0),
+			lock_height: kernel.lock_height.unwrap_or(0),
+			excess: kernel.excess,
+			excess_sig: kernel.excess_sig,
+		}
+	}
+}
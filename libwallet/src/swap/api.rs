@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::config::SwapConfig;
 use super::error::ErrorKind;
 use super::swap::Swap;
 use super::types::{Context, Currency};
@@ -23,9 +24,36 @@ use crate::swap::types::SwapTransactionsConfirmations;
 use crate::NodeClient;
 use grin_core::global;
 use grin_keychain::Identifier;
-use grin_util::Mutex;
+use grin_util::{Mutex, RwLock};
 use std::sync::Arc;
 
+lazy_static! {
+	static ref SECONDARY_LOCK_OBSERVER: RwLock<Option<Arc<dyn SecondaryLockObserver>>> =
+		RwLock::new(None);
+}
+
+/// Observer of the secondary currency lock account. By default the lock is observed with
+/// ElectrumX client. Custom observers (own indexers, cold observation) can replace it.
+///
+/// Contract: the confirmations number must be monotonic non-decreasing for the same lock
+/// account, unless there is a reorg. The swap state machine is making decisions based on it,
+/// so the confirmations number must not be inflated.
+pub trait SecondaryLockObserver: Sync + Send {
+	/// Amount at the lock account and the least confirmations number of its outputs.
+	/// Confirmations are Some(0) if some outputs are in mempool, None if nothing is locked.
+	fn lock_confirmations(
+		&self,
+		currency: Currency,
+		lock_address: &String,
+	) -> Result<(u64, Option<u64>), ErrorKind>;
+}
+
+/// Set the observer for the secondary currency lock. None - use ElectrumX.
+/// The observer is used by the swap API instances created after this call.
+pub fn set_secondary_lock_observer(observer: Option<Arc<dyn SecondaryLockObserver>>) {
+	*SECONDARY_LOCK_OBSERVER.write() = observer;
+}
+
 /// Swap API trait that is used by both Buyer and Seller.
 /// Every currency that Swap want to support, need to implement
 /// this trait. Current we have only implementaiton: api::BtcSwapApi
@@ -70,6 +98,8 @@ pub trait SwapApi<K: Keychain>: Sync + Send {
 		buyer_destination_address: String,
 		electrum_node_uri1: Option<String>,
 		electrum_node_uri2: Option<String>,
+		external_ref: Option<String>, // Shared reference, for example the exchange order id
+		config: &SwapConfig,
 	) -> Result<Swap, ErrorKind>;
 
 	/// get state machine fro this trade.
@@ -82,6 +112,14 @@ pub trait SwapApi<K: Keychain>: Sync + Send {
 		swap: &Swap,
 	) -> Result<SwapTransactionsConfirmations, ErrorKind>;
 
+	/// Request confirmation numbers for several swaps in one pass. The chain tips are requested
+	/// once for all of them. Result is in the same order as the swaps.
+	fn request_tx_confirmations_batch(
+		&self,
+		_keychain: &K,
+		swaps: &[&Swap],
+	) -> Result<Vec<SwapTransactionsConfirmations>, ErrorKind>;
+
 	/// Check How much BTC coins are locked on the chain
 	/// Return output with at least 1 confirmations because it is needed for refunds or redeems. Both party want to take everything
 	/// Return: (<pending_amount>, <confirmed_amount>, <least_confirmations>)
@@ -157,10 +195,12 @@ where
 		electrum_node_uri2,
 		currency.get_block1_tx_hash(!global::is_mainnet()),
 	);
-	Ok(Box::new(BtcSwapApi::new(
+	let mut api = BtcSwapApi::new(
 		currency.clone(),
 		Arc::new(node_client),
 		Arc::new(Mutex::new(secondary_currency_node_client1)),
 		Arc::new(Mutex::new(secondary_currency_node_client2)),
-	)))
+	);
+	api.lock_observer = SECONDARY_LOCK_OBSERVER.read().clone();
+	Ok(Box::new(api))
 }
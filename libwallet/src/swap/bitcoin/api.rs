@@ -16,8 +16,10 @@ use super::client::BtcNodeClient;
 use super::types::{BtcBuyerContext, BtcData, BtcSellerContext};
 use crate::grin_util::secp::pedersen;
 use crate::grin_util::Mutex;
+use crate::swap::api::SecondaryLockObserver;
 use crate::swap::bitcoin::types::BtcTtansaction;
 use crate::swap::bitcoin::Output;
+use crate::swap::config::SwapConfig;
 use crate::swap::fsm::machine::StateMachine;
 use crate::swap::fsm::{buyer_swap, seller_swap};
 use crate::swap::message::SecondaryUpdate;
@@ -33,6 +35,7 @@ use failure::_core::marker::PhantomData;
 use grin_keychain::{Identifier, Keychain, SwitchCommitmentType};
 use grin_util::secp;
 use grin_util::secp::aggsig::export_secnonce_single as generate_nonce;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// SwapApi trait implementaiton for BTC
@@ -50,6 +53,8 @@ where
 	pub btc_node_client1: Arc<Mutex<B>>,
 	/// Secondary Client for BTC electrumx node
 	pub btc_node_client2: Arc<Mutex<B>>,
+	/// Custom observer of the lock account. If None, electrumx nodes are used.
+	pub lock_observer: Option<Arc<dyn SecondaryLockObserver>>,
 
 	phantom: PhantomData<&'a C>,
 }
@@ -71,6 +76,7 @@ where
 			node_client,
 			btc_node_client1,
 			btc_node_client2,
+			lock_observer: None,
 			phantom: PhantomData,
 		}
 	}
@@ -82,6 +88,7 @@ where
 			node_client,
 			btc_node_client1: btc_node_client.clone(),
 			btc_node_client2: btc_node_client,
+			lock_observer: None,
 			phantom: PhantomData,
 		}
 	}
@@ -93,6 +100,7 @@ where
 			node_client: self.node_client.clone(),
 			btc_node_client1: self.btc_node_client1.clone(),
 			btc_node_client2: self.btc_node_client2.clone(),
+			lock_observer: self.lock_observer.clone(),
 			phantom: PhantomData,
 		}
 	}
@@ -176,6 +184,54 @@ where
 		))
 	}
 
+	/// Amount at the lock account and its least confirmations number.
+	/// Custom lock observer is used if it is set.
+	fn secondary_lock_confirmations(
+		&self,
+		swap: &Swap,
+		btc_tip: u64,
+	) -> Result<(u64, Option<u64>), ErrorKind> {
+		let btc_data = swap.secondary_data.unwrap_btc()?;
+		let address = match self.script(swap).and_then(|input_script| {
+			btc_data.address(swap.secondary_currency, &input_script, swap.network)
+		}) {
+			Ok(address) => address,
+			// Lock account is not known yet
+			Err(_) => return Ok((0, None)),
+		};
+
+		if let Some(observer) = &self.lock_observer {
+			return observer.lock_confirmations(swap.secondary_currency, &address);
+		}
+
+		let outputs = match self
+			.btc_node_client1
+			.lock()
+			.unspent(swap.secondary_currency, &address)
+		{
+			Ok(r) => r,
+			Err(_) => self
+				.btc_node_client2
+				.lock()
+				.unspent(swap.secondary_currency, &address)?,
+		};
+		let mut secondary_lock_amount = 0;
+		let mut least_confirmations = None;
+		for output in outputs {
+			secondary_lock_amount += output.value;
+			if output.height == 0 {
+				// Output in mempool
+				least_confirmations = Some(0);
+			} else {
+				let confirmations = btc_tip.saturating_sub(output.height) + 1;
+				if confirmations < least_confirmations.unwrap_or(std::i32::MAX as u64) {
+					least_confirmations = Some(confirmations);
+				}
+			}
+		}
+		Ok((secondary_lock_amount, least_confirmations))
+	}
+
 	/// Seller builds the transaction to redeem their Bitcoins, Status::Redeem
 	/// Updating data:  swap.secondary_data.redeem_tx
 	fn seller_build_redeem_tx<K: Keychain>(
@@ -272,10 +328,23 @@ where
 		Ok(())
 	}
 
+	// Kernel to check the slate transaction. None if the kernel is not known yet
+	fn slate_kernel(slate: &Slate) -> Option<pedersen::Commitment> {
+		debug_assert!(slate.tx.kernels().len() <= 1);
+		slate
+			.tx
+			.kernels()
+			.get(0)
+			.map(|k| k.excess.clone())
+			.filter(|excess| excess.0.iter().any(|v| *v != 0))
+	}
+
+	// kernel_height - result of the kernel request for the slate that has the kernel
 	fn get_slate_confirmation_number(
 		&self,
 		mwc_tip: &u64,
 		slate: &Slate,
+		kernel_height: Option<Option<u64>>,
 		outputs_ok: bool,
 	) -> Result<Option<u64>, ErrorKind> {
 		let result: Option<u64> = if slate.tx.kernels().is_empty() {
@@ -283,67 +352,91 @@ where
 		} else {
 			debug_assert!(slate.tx.kernels().len() == 1);
 
-			let kernel = &slate.tx.kernels()[0].excess;
-			if kernel.0.to_vec().iter().any(|v| *v != 0) {
+			if let Some(kernel_height) = kernel_height {
 				// kernel is non zero - we can check transaction by kernel
-				match self
-					.node_client
-					.get_kernel(kernel, Some(slate.height), None)?
-				{
-					Some((_tx_kernel, height, _mmr_index)) => {
-						Some(mwc_tip.saturating_sub(height) + 1)
-					}
+				kernel_height.map(|height| mwc_tip.saturating_sub(height) + 1)
+			} else if outputs_ok {
+				// kernel is not valid, still can use outputs.
+				let wallet_outputs: Vec<pedersen::Commitment> = slate
+					.tx
+					.outputs()
+					.iter()
+					.map(|o| o.commit.clone())
+					.collect();
+				let res = self.node_client.get_outputs_from_node(&wallet_outputs)?;
+				let height = res.values().map(|v| v.1).max();
+				match height {
+					Some(h) => Some(mwc_tip.saturating_sub(h) + 1),
 					None => None,
 				}
 			} else {
-				if outputs_ok {
-					// kernel is not valid, still can use outputs.
-					let wallet_outputs: Vec<pedersen::Commitment> = slate
-						.tx
-						.outputs()
-						.iter()
-						.map(|o| o.commit.clone())
-						.collect();
-					let res = self.node_client.get_outputs_from_node(&wallet_outputs)?;
-					let height = res.values().map(|v| v.1).max();
-					match height {
-						Some(h) => Some(mwc_tip.saturating_sub(h) + 1),
-						None => None,
-					}
-				} else {
-					None
-				}
+				None
 			}
 		};
 		Ok(result)
 	}
 
-	/// Retrieve confirmation number for BTC transaction.
-	fn get_btc_confirmation_number(
+	/// Retrieve confirmation numbers for BTC redeem and refund transactions of several swaps.
+	/// Transactions are requested from the node in batches.
+	/// Return (<redeem_conf>, <refund_conf>) for every swap
+	pub(crate) fn request_secondary_confirmations_batch(
 		&self,
-		btc_tip: &u64,
-		tx_hash: Option<sha256d::Hash>,
-	) -> Result<Option<u64>, ErrorKind> {
-		let result: Option<u64> = match tx_hash {
-			None => None,
-			Some(tx_hash) => {
-				let tx_resp = match self.btc_node_client1.lock().transaction(&tx_hash) {
-					Ok(r) => r,
-					Err(_) => self.btc_node_client2.lock().transaction(&tx_hash)?,
-				};
-				match tx_resp {
-					None => None,
-					Some((height, _tx)) => match height {
-						None => Some(0),
-						Some(h) => Some(btc_tip.saturating_sub(h) + 1),
-					},
-				}
-			}
+		btc_tip: u64,
+		swaps: &[&Swap],
+	) -> Result<Vec<(Option<u64>, Option<u64>)>, ErrorKind> {
+		let mut tx_hashes: Vec<sha256d::Hash> = Vec::new();
+		for swap in swaps {
+			let btc_data = swap.secondary_data.unwrap_btc()?;
+			tx_hashes.extend(btc_data.redeem_tx.iter().cloned());
+			tx_hashes.extend(btc_data.refund_tx.iter().cloned());
+		}
+		if tx_hashes.is_empty() {
+			return Ok(vec![(None, None); swaps.len()]);
+		}
+
+		let txs = match self.btc_node_client1.lock().transactions(&tx_hashes) {
+			Ok(r) => r,
+			Err(_) => self.btc_node_client2.lock().transactions(&tx_hashes)?,
 		};
+		if txs.len() != tx_hashes.len() {
+			return Err(ErrorKind::Generic(format!(
+				"Requested {} transactions, get {}",
+				tx_hashes.len(),
+				txs.len()
+			)));
+		}
+		let confirmations: HashMap<sha256d::Hash, u64> = tx_hashes
+			.into_iter()
+			.zip(txs)
+			.filter_map(|(tx_hash, tx)| {
+				tx.map(|(height, _tx)| (tx_hash, btc_confirmations(&btc_tip, height)))
+			})
+			.collect();
+
+		let mut result = Vec::with_capacity(swaps.len());
+		for swap in swaps {
+			let btc_data = swap.secondary_data.unwrap_btc()?;
+			result.push((
+				btc_data
+					.redeem_tx
+					.and_then(|h| confirmations.get(&h).cloned()),
+				btc_data
+					.refund_tx
+					.and_then(|h| confirmations.get(&h).cloned()),
+			));
+		}
 		Ok(result)
 	}
 }
 
+/// Number of confirmations for the transaction at the height. None height means that tx is in mempool
+fn btc_confirmations(btc_tip: &u64, height: Option<u64>) -> u64 {
+	match height {
+		None => 0,
+		Some(h) => btc_tip.saturating_sub(h) + 1,
+	}
+}
+
 impl<'a, K, C, B> SwapApi<K> for BtcSwapApi<'a, C, B>
 where
 	K: Keychain + 'a,
@@ -388,6 +481,7 @@ where
 				))?,
 				change_output: keys.next().unwrap(),
 				change_amount,
+				change_split: None,
 				refund_output: keys.next().unwrap(),
 				secondary_context: SecondarySellerContext::Btc(BtcSellerContext {
 					cosign: keys.next().unwrap(),
@@ -432,6 +526,8 @@ where
 		buyer_destination_address: String,
 		electrum_node_uri1: Option<String>,
 		electrum_node_uri2: Option<String>,
+		external_ref: Option<String>,
+		config: &SwapConfig,
 	) -> Result<Swap, ErrorKind> {
 		// Checking if address is valid
 
@@ -466,6 +562,8 @@ where
 			buyer_destination_address,
 			electrum_node_uri1,
 			electrum_node_uri2,
+			external_ref,
+			config,
 		)?;
 
 		let btc_data = BtcData::new(keychain, context.unwrap_seller()?.unwrap_btc()?)?;
@@ -484,7 +582,7 @@ where
 			.secondary_data
 			.unwrap_btc()
 			.expect("Secondary data of unexpected type");
-		SecondaryUpdate::BTC(btc_data.offer_update())
+		SecondaryUpdate::BTC(btc_data.offer_update(swap.network))
 	}
 
 	/// Build secondary update part of the accept offer message
@@ -528,76 +626,90 @@ where
 	/// Request confirmation numberss for all transactions that are known and in the in the swap
 	fn request_tx_confirmations(
 		&self,
-		_keychain: &K, // keychain is kept for Type. Compiler need to understand all types
+		keychain: &K,
 		swap: &Swap,
 	) -> Result<SwapTransactionsConfirmations, ErrorKind> {
-		let mwc_tip = self.node_client.get_chain_tip()?.0;
-
-		let is_seller = swap.is_seller();
+		let mut res = self.request_tx_confirmations_batch(keychain, &[swap])?;
+		Ok(res.remove(0))
+	}
 
-		let mwc_lock_conf =
-			self.get_slate_confirmation_number(&mwc_tip, &swap.lock_slate, !is_seller)?;
-		let mwc_redeem_conf =
-			self.get_slate_confirmation_number(&mwc_tip, &swap.redeem_slate, is_seller)?;
-		let mwc_refund_conf =
-			self.get_slate_confirmation_number(&mwc_tip, &swap.refund_slate, !is_seller)?;
+	fn request_tx_confirmations_batch(
+		&self,
+		_keychain: &K, // keychain is kept for Type. Compiler need to understand all types
+		swaps: &[&Swap],
+	) -> Result<Vec<SwapTransactionsConfirmations>, ErrorKind> {
+		// Tip and the known kernels of all swaps are requested from the node together
+		let slates: Vec<[&Slate; 3]> = swaps
+			.iter()
+			.map(|swap| [&swap.lock_slate, &swap.redeem_slate, &swap.refund_slate])
+			.collect();
+		let kernels: Vec<(pedersen::Commitment, Option<u64>)> = slates
+			.iter()
+			.flat_map(|s| s.iter())
+			.filter_map(|slate| Self::slate_kernel(slate).map(|k| (k, Some(slate.height))))
+			.collect();
+		let (mwc_tip, found) = self.node_client.get_tip_and_kernels(&kernels)?;
+		let mut found = found.into_iter();
 
 		let btc_tip = match self.btc_node_client1.lock().height() {
 			Ok(r) => r,
 			Err(_) => self.btc_node_client2.lock().height()?,
 		};
-		let btc_data = swap.secondary_data.unwrap_btc()?;
-		let secondary_redeem_conf =
-			self.get_btc_confirmation_number(&btc_tip, btc_data.redeem_tx.clone())?;
-		let secondary_refund_conf =
-			self.get_btc_confirmation_number(&btc_tip, btc_data.refund_tx.clone())?;
 
-		// BTC lock account...
-		// Checking Amount, it can be too hight as well
-		let mut secondary_lock_amount = 0;
-		let mut least_confirmations = None;
+		// BTC redeem and refund transactions of all swaps are requested together
+		let secondary_confs = self.request_secondary_confirmations_batch(btc_tip, swaps)?;
 
-		if let Ok(input_script) = self.script(swap) {
-			if let Ok(address) =
-				btc_data.address(swap.secondary_currency, &input_script, swap.network)
-			{
-				let outputs = match self
-					.btc_node_client1
-					.lock()
-					.unspent(swap.secondary_currency, &address)
-				{
-					Ok(r) => r,
-					Err(_) => self
-						.btc_node_client2
-						.lock()
-						.unspent(swap.secondary_currency, &address)?,
-				};
-				for output in outputs {
-					secondary_lock_amount += output.value;
-					if output.height == 0 {
-						// Output in mempool
-						least_confirmations = Some(0);
-					} else {
-						let confirmations = btc_tip.saturating_sub(output.height) + 1;
-						if confirmations < least_confirmations.unwrap_or(std::i32::MAX as u64) {
-							least_confirmations = Some(confirmations);
-						}
-					}
-				}
-			}
+		let mut result = Vec::with_capacity(swaps.len());
+		for ((swap, slates), (secondary_redeem_conf, secondary_refund_conf)) in
+			swaps.iter().zip(&slates).zip(secondary_confs)
+		{
+			let kernel_heights: Vec<Option<Option<u64>>> = slates
+				.iter()
+				.map(|slate| {
+					Self::slate_kernel(slate)
+						.map(|_| found.next().and_then(|k| k.map(|(_, height, _)| height)))
+				})
+				.collect();
+
+			let is_seller = swap.is_seller();
+
+			let mwc_lock_conf = self.get_slate_confirmation_number(
+				&mwc_tip,
+				&swap.lock_slate,
+				kernel_heights[0],
+				!is_seller,
+			)?;
+			let mwc_redeem_conf = self.get_slate_confirmation_number(
+				&mwc_tip,
+				&swap.redeem_slate,
+				kernel_heights[1],
+				is_seller,
+			)?;
+			let mwc_refund_conf = self.get_slate_confirmation_number(
+				&mwc_tip,
+				&swap.refund_slate,
+				kernel_heights[2],
+				!is_seller,
+			)?;
+
+			// BTC lock account...
+			// Checking Amount, it can be too hight as well
+			let (secondary_lock_amount, least_confirmations) =
+				self.secondary_lock_confirmations(swap, btc_tip)?;
+
+			result.push(SwapTransactionsConfirmations {
+				mwc_tip,
+				mwc_lock_conf,
+				mwc_redeem_conf,
+				mwc_refund_conf,
+				secondary_tip: btc_tip,
+				secondary_lock_conf: least_confirmations,
+				secondary_lock_amount,
+				secondary_redeem_conf,
+				secondary_refund_conf,
+			});
 		}
-
-		Ok(SwapTransactionsConfirmations {
-			mwc_tip,
-			mwc_lock_conf,
-			mwc_redeem_conf,
-			mwc_refund_conf,
-			secondary_tip: btc_tip,
-			secondary_lock_conf: least_confirmations,
-			secondary_lock_amount,
-			secondary_redeem_conf,
-			secondary_refund_conf,
-		})
+		Ok(result)
 	}
 
 	/// Check How much BTC coins are locked on the chain
@@ -610,6 +722,16 @@ where
 	) -> Result<(u64, u64, u64), ErrorKind> {
 		let input_script = self.script(swap)?;
 
+		if let Some(observer) = &self.lock_observer {
+			let btc_data = swap.secondary_data.unwrap_btc()?;
+			let address = btc_data.address(swap.secondary_currency, &input_script, swap.network)?;
+			let (amount, conf) = observer.lock_confirmations(swap.secondary_currency, &address)?;
+			return Ok(match conf {
+				Some(conf) if conf >= confirmations_needed => (0, amount, 0),
+				_ => (amount, 0, conf.unwrap_or(0)),
+			});
+		}
+
 		let (pending_amount, confirmed_amount, least_confirmations, _outputs) =
 			self.btc_balance(swap, &input_script, confirmations_needed)?;
 
@@ -635,6 +757,7 @@ where
 				)),
 				Box::new(seller_swap::SellerWaitingForBuyerLock::new()),
 				Box::new(seller_swap::SellerPostingLockMwcSlate::new(nc.clone())),
+				Box::new(seller_swap::SellerLockFailed::new(nc.clone())),
 				Box::new(seller_swap::SellerWaitingForLockConfirmations::new(
 					kc.clone(),
 				)),
@@ -668,7 +791,7 @@ where
 					kc.clone(),
 					swap_api.clone(),
 				)),
-				Box::new(buyer_swap::BuyerWaitingForSellerToLock::new()),
+				Box::new(buyer_swap::BuyerWaitingForSellerToLock::new(nc.clone())),
 				Box::new(buyer_swap::BuyerPostingSecondaryToMultisigAccount::new(
 					swap_api.clone(),
 				)),
@@ -698,13 +821,7 @@ where
 
 	/// Get a secondary address for the lock account
 	fn get_secondary_lock_address(&self, swap: &Swap) -> Result<String, ErrorKind> {
-		let input_script = self.script(swap)?;
-		let adrress = swap.secondary_data.unwrap_btc()?.address(
-			swap.secondary_currency,
-			&input_script,
-			swap.network,
-		)?;
-		Ok(adrress.to_string())
+		swap.get_secondary_lock_address()
 	}
 
 	/// Check if tx fee for the secondary is different from the posted
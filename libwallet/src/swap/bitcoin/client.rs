@@ -72,6 +72,15 @@ pub trait BtcNodeClient: Sync + Send + 'static {
 		&mut self,
 		tx_hash: &sha256d::Hash, // tx hash
 	) -> Result<Option<(Option<u64>, Transaction)>, ErrorKind>;
+	/// Get info for several BTC transactions. Clients that support batching can request them
+	/// in a single round, by default transactions are requested one by one.
+	/// Return (height, tx) for every tx_hash, in the same order
+	fn transactions(
+		&mut self,
+		tx_hashes: &[sha256d::Hash],
+	) -> Result<Vec<Option<(Option<u64>, Transaction)>>, ErrorKind> {
+		tx_hashes.iter().map(|h| self.transaction(h)).collect()
+	}
 }
 
 /// Mock BTC node for the testing
@@ -85,6 +94,8 @@ pub struct TestBtcNodeClientState {
 	pub txs: HashMap<sha256d::Hash, Transaction>,
 	/// Pending transactions
 	pub pending: HashMap<sha256d::Hash, Transaction>,
+	/// Number of the transactions batch requests
+	pub transactions_calls: usize,
 }
 
 /// Mock BTC node client
@@ -103,6 +114,7 @@ impl TestBtcNodeClient {
 				tx_heights: HashMap::new(),
 				txs: HashMap::new(),
 				pending: HashMap::new(),
+				transactions_calls: 0,
 			})),
 		}
 	}
@@ -259,4 +271,12 @@ impl BtcNodeClient for TestBtcNodeClient {
 
 		Ok(tx)
 	}
+
+	fn transactions(
+		&mut self,
+		tx_hashes: &[sha256d::Hash],
+	) -> Result<Vec<Option<(Option<u64>, Transaction)>>, ErrorKind> {
+		self.state.lock().transactions_calls += 1;
+		tx_hashes.iter().map(|h| self.transaction(h)).collect()
+	}
 }
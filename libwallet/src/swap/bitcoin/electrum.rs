@@ -78,9 +78,16 @@ impl ElectrumRpcClient {
 
 	fn wait<T: for<'de> Deserialize<'de>>(&mut self, id: String) -> Result<T, ElectrumError> {
 		let res = self.inner.read()?;
+		Self::parse_response(res, &id)
+	}
+
+	fn parse_response<T: for<'de> Deserialize<'de>>(
+		res: RpcResponse,
+		id: &String,
+	) -> Result<T, ElectrumError> {
 		match res {
 			RpcResponse::ResponseErr(e) => {
-				if e.id.map(|res_id| res_id == id).unwrap_or(true) {
+				if e.id.map(|res_id| &res_id == id).unwrap_or(true) {
 					let err: ElectrumResponseError =
 						serde_json::from_value(e.error).map_err(|e| {
 							ErrorKind::ElectrumNodeClient(format!(
@@ -93,7 +100,7 @@ impl ElectrumRpcClient {
 			}
 			RpcResponse::ResponseOk(o) => {
 				debug!("Get a response back: {:?}", o);
-				if o.id.map(|res_id| res_id == id).unwrap_or(false) {
+				if o.id.map(|res_id| &res_id == id).unwrap_or(false) {
 					let obj: T = serde_json::from_value(o.result).map_err(|e| {
 						ErrorKind::ElectrumNodeClient(format!("Unable to decode response, {}", e))
 					})?;
@@ -160,6 +167,52 @@ impl ElectrumRpcClient {
 		let request = RpcRequest::new(self.next_id(), "blockchain.transaction.get", params)?;
 		self.write(&request)?;
 		let tx: Result<ElectrumTransaction, ElectrumError> = self.wait(request.id);
+		Self::transaction_from_response(tx)
+	}
+
+	/// Request several transactions in a single round. All requests are sent first, then
+	/// responses are collected and matched by id, so the order of the responses doesn't matter.
+	pub fn transactions(
+		&mut self,
+		tx_hashes: Vec<String>,
+	) -> Result<Vec<Option<ElectrumTransaction>>, ErrorKind> {
+		let mut ids = Vec::with_capacity(tx_hashes.len());
+		for tx_hash in tx_hashes {
+			let params = TransactionParams::new(tx_hash);
+			let request = RpcRequest::new(self.next_id(), "blockchain.transaction.get", params)?;
+			self.write(&request)?;
+			ids.push(request.id);
+		}
+
+		let mut responses: HashMap<String, RpcResponse> = HashMap::new();
+		for _ in 0..ids.len() {
+			let res = self.inner.read()?;
+			let res_id = match &res {
+				RpcResponse::ResponseErr(e) => e.id.clone(),
+				RpcResponse::ResponseOk(o) => o.id.clone(),
+			};
+			let res_id = res_id.ok_or(ErrorKind::ElectrumNodeClient(
+				"Get a response without id for the batch request".to_string(),
+			))?;
+			responses.insert(res_id, res);
+		}
+
+		ids.iter()
+			.map(|id| {
+				let res = responses
+					.remove(id)
+					.ok_or(ErrorKind::ElectrumNodeClient(format!(
+						"No response received for the request {}",
+						id
+					)))?;
+				Self::transaction_from_response(Self::parse_response(res, id))
+			})
+			.collect()
+	}
+
+	fn transaction_from_response(
+		tx: Result<ElectrumTransaction, ElectrumError>,
+	) -> Result<Option<ElectrumTransaction>, ErrorKind> {
 		match tx {
 			Ok(t) => Ok(Some(t)),
 			Err(ElectrumError::Response(e)) => {
@@ -266,10 +319,15 @@ pub struct ElectrumNodeClient {
 	pub address: String,
 	/// transaction at block 1. The number of confirmations must be equal to the height
 	pub check_tx_hash: String,
+	/// Max number of transactions that are requested in a single round. 0 or 1 disables batching.
+	pub batch_size: usize,
 	/// ElectrumX client
 	client: Option<(ElectrumRpcClient, Instant)>,
 }
 
+/// Default number of transactions that ElectrumNodeClient requests in a single round
+pub const ELECTRUM_BATCH_SIZE: usize = 20;
+
 impl ElectrumNodeClient {
 	/// Create a new instance.
 	/// address - it is URI for electrumX host    host:port
@@ -277,6 +335,7 @@ impl ElectrumNodeClient {
 		Self {
 			address,
 			check_tx_hash,
+			batch_size: ELECTRUM_BATCH_SIZE,
 			client: None,
 		}
 	}
@@ -386,24 +445,65 @@ impl BtcNodeClient for ElectrumNodeClient {
 			None => return Ok(None),
 		};
 
-		let height = match tx.confirmations {
-			Some(c) if c > 0 => Some(head_height.saturating_sub(c - 1)),
-			_ => None,
-		};
-
-		let tx_bytes = from_hex(tx.hex.as_str()).map_err(|e| {
-			ErrorKind::ElectrumNodeClient(format!("Unable to parse hex {}, {}", tx.hex, e))
-		})?;
+		Ok(Some(decode_transaction(head_height, tx)?))
+	}
 
-		let cursor = Cursor::new(tx_bytes);
-		let tx = Transaction::consensus_decode(cursor).map_err(|e| {
-			ErrorKind::ElectrumNodeClient(format!("Unable to parse transaction, {}", e))
-		})?;
+	/// Request several transactions, up to batch_size in a single round
+	fn transactions(
+		&mut self,
+		tx_hashes: &[Hash],
+	) -> Result<Vec<Option<(Option<u64>, Transaction)>>, ErrorKind> {
+		if self.batch_size <= 1 {
+			return tx_hashes.iter().map(|h| self.transaction(h)).collect();
+		}
 
-		Ok(Some((height, tx)))
+		let head_height = self.height()?;
+		let batch_size = self.batch_size;
+		let mut result = Vec::with_capacity(tx_hashes.len());
+		for chunk in tx_hashes.chunks(batch_size) {
+			let client = self.client()?;
+			let txs = match client.transactions(chunk.iter().map(|h| format!("{}", h)).collect()) {
+				Ok(txs) => txs,
+				Err(e) => {
+					// Responses to the rest of the batch might be still in the stream, the
+					// connection can't be reused
+					self.client = None;
+					return Err(e);
+				}
+			};
+			for tx in txs {
+				result.push(match tx {
+					Some(t) => Some(decode_transaction(head_height, t)?),
+					None => None,
+				});
+			}
+		}
+		Ok(result)
 	}
 }
 
+/// Convert ElectrumX transaction into (height, tx)
+fn decode_transaction(
+	head_height: u64,
+	tx: ElectrumTransaction,
+) -> Result<(Option<u64>, Transaction), ErrorKind> {
+	let height = match tx.confirmations {
+		Some(c) if c > 0 => Some(head_height.saturating_sub(c - 1)),
+		_ => None,
+	};
+
+	let tx_bytes = from_hex(tx.hex.as_str()).map_err(|e| {
+		ErrorKind::ElectrumNodeClient(format!("Unable to parse hex {}, {}", tx.hex, e))
+	})?;
+
+	let cursor = Cursor::new(tx_bytes);
+	let tx = Transaction::consensus_decode(cursor).map_err(|e| {
+		ErrorKind::ElectrumNodeClient(format!("Unable to parse transaction, {}", e))
+	})?;
+
+	Ok((height, tx))
+}
+
 /// ElectrumX client error response.
 #[derive(Serialize, Deserialize, Debug)]
 struct ElectrumResponseError {
@@ -20,5 +20,5 @@ mod types;
 
 pub use api::BtcSwapApi;
 pub use client::*;
-pub use electrum::ElectrumNodeClient;
+pub use electrum::{ElectrumNodeClient, ELECTRUM_BATCH_SIZE};
 pub use types::{BtcBuyerContext, BtcData, BtcSellerContext, BtcUpdate};
@@ -19,12 +19,10 @@ use crate::swap::swap;
 use crate::swap::types::{Currency, Network, SecondaryData};
 use crate::swap::{ErrorKind, Keychain};
 use bitcoin::blockdata::opcodes::{all::*, OP_FALSE, OP_TRUE};
-use bitcoin::blockdata::script::Builder;
+use bitcoin::blockdata::script::{Builder, Instruction};
 use bitcoin::consensus::Encodable;
 use bitcoin::network::constants::Network as BtcNetwork;
-#[cfg(test)]
-use bitcoin::OutPoint;
-use bitcoin::{Address, Script, Transaction, TxIn, TxOut, VarInt};
+use bitcoin::{Address, OutPoint, Script, Transaction, TxIn, TxOut, VarInt};
 use bitcoin_hashes::sha256d;
 use byteorder::{ByteOrder, LittleEndian};
 use grin_keychain::{Identifier, SwitchCommitmentType};
@@ -88,15 +86,30 @@ impl BtcData {
 		})
 	}
 
-	/// Create buyer BTC data (party that sell BTC)
+	/// Create buyer BTC data (party that sell BTC). network is the MWC network of the offer,
+	/// secondary currency network must match it.
 	pub(crate) fn from_offer<K>(
 		keychain: &K,
 		offer: BtcOfferUpdate,
 		context: &BtcBuyerContext,
+		network: Network,
 	) -> Result<Self, ErrorKind>
 	where
 		K: Keychain,
 	{
+		// Mainnet MWC must be traded for the real coins, never for testnet ones.
+		// Offer without the network can't be checked, so it is rejected.
+		let secondary_network = offer
+			.network
+			.clone()
+			.unwrap_or_else(|| "unknown".to_string());
+		if secondary_network != secondary_network_name(network) {
+			return Err(ErrorKind::SecondaryNetworkMismatch(
+				format!("{:?}", network),
+				secondary_network,
+			));
+		}
+
 		let key = keychain.derive_key(0, &context.refund, SwitchCommitmentType::None)?;
 
 		Ok(Self {
@@ -143,16 +156,29 @@ impl BtcData {
 		}
 
 		// Locking for the past is very expected. We build this script every time when we need to calculate hash for the address.
+		let refund = self.refund.ok_or(ErrorKind::SecondaryDataIncomplete)?;
+		Ok(Self::lock_script(
+			secp,
+			&refund,
+			&self.cosign,
+			redeem,
+			btc_lock_time as u32,
+		))
+	}
 
+	// Multisig-with-timelocked-refund script for the keys
+	fn lock_script(
+		secp: &Secp256k1,
+		refund: &PublicKey,
+		cosign: &PublicKey,
+		redeem: &PublicKey,
+		btc_lock_time: u32,
+	) -> Script {
 		let mut time = [0; 4];
-		let btc_lock_time: u32 = btc_lock_time as u32;
 		LittleEndian::write_u32(&mut time, btc_lock_time);
 
-		let refund = self
-			.refund
-			.ok_or(ErrorKind::SecondaryDataIncomplete)?
-			.serialize_vec(secp, true);
-		let cosign = self.cosign.serialize_vec(secp, true);
+		let refund = refund.serialize_vec(secp, true);
+		let cosign = cosign.serialize_vec(secp, true);
 		let redeem = redeem.serialize_vec(secp, true);
 
 		let builder = Builder::new()
@@ -170,7 +196,7 @@ impl BtcData {
 			.push_opcode(OP_CHECKMULTISIG) // Check 2-of-2 multisig
 			.push_opcode(OP_ENDIF);
 
-		Ok(builder.into_script())
+		builder.into_script()
 	}
 
 	/// Generate the P2SH address for the script
@@ -202,6 +228,62 @@ impl BtcData {
 		}
 	}
 
+	/// Reconstruct the P2SH lock address from the multisig script data. This is the address
+	/// the seller funds and the buyer must observe.
+	pub fn lock_address(
+		&self,
+		secp: &Secp256k1,
+		currency: Currency,
+		redeem: &PublicKey,
+		btc_lock_time: u64,
+		network: Network,
+	) -> Result<String, ErrorKind> {
+		let script = self.script(secp, redeem, btc_lock_time)?;
+		self.address(currency, &script, network)
+	}
+
+	/// Redeem script bytes as hex, exactly as they are used by the redeem and refund
+	/// transactions, and its disassembly. The script has only the public keys and the lock time,
+	/// nothing private.
+	pub fn redeem_script_hex_asm(
+		&self,
+		secp: &Secp256k1,
+		redeem: &PublicKey,
+		btc_lock_time: u64,
+	) -> Result<(String, String), ErrorKind> {
+		let script = self.script(secp, redeem, btc_lock_time)?;
+		Ok((script.to_bytes().to_hex(), Self::script_asm(&script)?))
+	}
+
+	// Disassembly of the script: opcodes by name, the pushed data as hex
+	fn script_asm(script: &Script) -> Result<String, ErrorKind> {
+		let mut asm = Vec::new();
+		for instruction in script.iter(true) {
+			match instruction {
+				Instruction::Op(op) => asm.push(format!("{:?}", op)),
+				Instruction::PushBytes(data) => {
+					asm.push(match data.len() {
+						0 => "OP_0".to_string(),
+						len if len <= 75 => format!("OP_PUSHBYTES_{}", len),
+						len if len <= 0xff => "OP_PUSHDATA1".to_string(),
+						len if len <= 0xffff => "OP_PUSHDATA2".to_string(),
+						_ => "OP_PUSHDATA4".to_string(),
+					});
+					if !data.is_empty() {
+						asm.push(data.to_hex());
+					}
+				}
+				Instruction::Error(e) => {
+					return Err(ErrorKind::Generic(format!(
+						"Unable to disassemble the script, {:?}",
+						e
+					)))
+				}
+			}
+		}
+		Ok(asm.join(" "))
+	}
+
 	// Build input/output for redeem or refund btc transaciton
 	// Inputs need to have amounts for BCH signature
 	fn build_input_outputs(
@@ -304,16 +386,10 @@ impl BtcData {
 			output,
 		};
 
-		// Calculate tx size
-		let mut script_sig_size = input_script.len();
-		script_sig_size += VarInt(script_sig_size as u64).len();
-		script_sig_size += 2 * (1 + 72 + 1); // Signatures
-		script_sig_size += 2; // Opcodes
-		let tx_size = tx.get_weight() / 4 + script_sig_size * tx.input.len();
-
 		// Subtract fee from output
+		let tx_size = Self::redeem_tx_size(&tx, input_script);
 		tx.output[0].value =
-			total_amount.saturating_sub((tx_size as f32 * fee_sat_per_byte + 0.5) as u64);
+			Self::redeem_output_value(currency, tx_size, total_amount, fee_sat_per_byte)?;
 
 		match currency {
 			Currency::Btc => {
@@ -386,6 +462,67 @@ impl BtcData {
 		))
 	}
 
+	// Estimated size of the signed redeem transaction
+	fn redeem_tx_size(tx: &Transaction, input_script: &Script) -> usize {
+		let mut script_sig_size = input_script.len();
+		script_sig_size += VarInt(script_sig_size as u64).len();
+		script_sig_size += 2 * (1 + 72 + 1); // Signatures
+		script_sig_size += 2; // Opcodes
+		tx.get_weight() / 4 + script_sig_size * tx.input.len()
+	}
+
+	// Redeem output value that is left after the fee. Dust output will not be relayed.
+	fn redeem_output_value(
+		currency: &Currency,
+		tx_size: usize,
+		total_amount: u64,
+		fee_sat_per_byte: f32,
+	) -> Result<u64, ErrorKind> {
+		let value = swap::amount_minus_fee(
+			"secondary redeem",
+			total_amount,
+			(tx_size as f32 * fee_sat_per_byte + 0.5) as u64,
+		)?;
+		if value < currency.dust_limit() {
+			return Err(ErrorKind::DustOutput(
+				"secondary redeem".to_string(),
+				value,
+				currency.dust_limit(),
+			));
+		}
+		Ok(value)
+	}
+
+	/// Estimate the redeem output value for the amount that is locked with a single output.
+	/// Used to validate the fee before the redeem transaction is rebuilt.
+	pub(crate) fn estimate_redeem_output(
+		&self,
+		currency: &Currency,
+		redeem_address: &String,
+		input_script: &Script,
+		fee_sat_per_byte: f32,
+		amount: u64,
+	) -> Result<u64, ErrorKind> {
+		let conf_outputs = vec![Output {
+			out_point: OutPoint {
+				txid: sha256d::Hash::default(),
+				vout: 0,
+			},
+			value: amount,
+			height: 0,
+		}];
+		let (input, output, total_amount) =
+			self.build_input_outputs(currency, redeem_address, &conf_outputs)?;
+		let tx = Transaction {
+			version: 2,
+			lock_time: 0,
+			input: input.into_iter().map(|i| i.0).collect(),
+			output,
+		};
+		let tx_size = Self::redeem_tx_size(&tx, input_script);
+		Self::redeem_output_value(currency, tx_size, total_amount, fee_sat_per_byte)
+	}
+
 	fn redeem_script_sig(
 		&self,
 		currency: &Currency,
@@ -450,16 +587,14 @@ impl BtcData {
 			output,
 		};
 
-		// Calculate tx size
-		let mut script_sig_size = input_script.len();
-		script_sig_size += VarInt(script_sig_size as u64).len();
-		script_sig_size += 1 * (1 + 72 + 1); // Signature (uno for refund)
-		script_sig_size += 1; // Opcodes
-		let tx_size = tx.get_weight() / 4 + script_sig_size * tx.input.len();
+		let tx_size = Self::refund_tx_size(&tx, input_script);
 
 		// Subtract fee from output
-		tx.output[0].value =
-			total_amount.saturating_sub((tx_size as f32 * fee_sat_per_byte + 0.5) as u64);
+		tx.output[0].value = swap::amount_minus_fee(
+			"secondary refund",
+			total_amount,
+			(tx_size as f32 * fee_sat_per_byte + 0.5) as u64,
+		)?;
 
 		match currency {
 			Currency::Btc => {
@@ -529,6 +664,58 @@ impl BtcData {
 		})
 	}
 
+	// Estimated size of the signed refund transaction
+	fn refund_tx_size(tx: &Transaction, input_script: &Script) -> usize {
+		let mut script_sig_size = input_script.len();
+		script_sig_size += VarInt(script_sig_size as u64).len();
+		script_sig_size += 1 * (1 + 72 + 1); // Signature (uno for refund)
+		script_sig_size += 1; // Opcodes
+		tx.get_weight() / 4 + script_sig_size * tx.input.len()
+	}
+
+	/// Estimate the fee of the refund transaction that spends a single lock output. The size
+	/// doesn't depend on the key values, so the cosign key stands in for the keys that are not
+	/// known before the offer is accepted. If the refund address is not known, the legacy
+	/// pay-to-pubkey-hash output is assumed.
+	pub(crate) fn estimate_refund_fee(
+		currency: &Currency,
+		secp: &Secp256k1,
+		cosign: &PublicKey,
+		refund_address: Option<&String>,
+		fee_sat_per_byte: f32,
+	) -> Result<u64, ErrorKind> {
+		let input_script = Self::lock_script(secp, cosign, cosign, cosign, 0);
+		let script_pubkey = match refund_address {
+			Some(address) => currency.address_2_script_pubkey(address)?,
+			None => Builder::new()
+				.push_opcode(OP_DUP)
+				.push_opcode(OP_HASH160)
+				.push_slice(&[0; 20])
+				.push_opcode(OP_EQUALVERIFY)
+				.push_opcode(OP_CHECKSIG)
+				.into_script(),
+		};
+		let tx = Transaction {
+			version: 2,
+			lock_time: 0,
+			input: vec![TxIn {
+				previous_output: OutPoint {
+					txid: sha256d::Hash::default(),
+					vout: 0,
+				},
+				script_sig: Script::new(),
+				sequence: 0,
+				witness: Vec::new(),
+			}],
+			output: vec![TxOut {
+				value: 0,
+				script_pubkey,
+			}],
+		};
+		let tx_size = Self::refund_tx_size(&tx, &input_script);
+		Ok((tx_size as f32 * fee_sat_per_byte + 0.5) as u64)
+	}
+
 	fn refund_script_sig(
 		&self,
 		currency: &Currency,
@@ -560,9 +747,10 @@ impl BtcData {
 	}
 
 	/// Seller init BTC offer for buyer
-	pub(crate) fn offer_update(&self) -> BtcUpdate {
+	pub(crate) fn offer_update(&self, network: Network) -> BtcUpdate {
 		BtcUpdate::Offer(BtcOfferUpdate {
 			cosign: self.cosign.clone(), // Buyer part of Schnorr multisig.
+			network: Some(secondary_network_name(network)),
 		})
 	}
 
@@ -633,6 +821,10 @@ pub struct BtcOfferUpdate {
 	/// Public key to do cosign with Schnorr signature.
 	#[serde(serialize_with = "pubkey_to_hex", deserialize_with = "pubkey_from_hex")]
 	pub cosign: PublicKey,
+	/// Network of the secondary currency, 'mainnet' or 'testnet'. Older wallets don't send it,
+	/// such offers are rejected.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub network: Option<String>,
 }
 
 /// Buyer message back to Seller. Offer is accepted
@@ -643,6 +835,14 @@ pub struct BtcAcceptOfferUpdate {
 	pub refund: PublicKey,
 }
 
+/// Name of the secondary currency network that match to MWC network
+fn secondary_network_name(network: Network) -> String {
+	match network {
+		Network::Floonet => "testnet".to_string(),
+		Network::Mainnet => "mainnet".to_string(),
+	}
+}
+
 /// Map MWC network to matched BTC network
 fn btc_network(network: Network) -> BtcNetwork {
 	match network {
@@ -726,6 +926,40 @@ mod tests {
 			),
 			String::from("2NEwEAG9VyFYt2sjLpuHrU4Abb7nGJfc7PR")
 		);
+
+		let redeem = PublicKey::from_slice(
+			&secp,
+			&from_hex("03cf15041579b5fb7accbac2997fb2f3e1001e9a522a19c83ceabe5ae51a596c7c".into())
+				.unwrap(),
+		)
+		.unwrap();
+		assert_eq!(
+			data.lock_address(&secp, Currency::Btc, &redeem, lock_time, Network::Floonet)
+				.unwrap(),
+			String::from("2NEwEAG9VyFYt2sjLpuHrU4Abb7nGJfc7PR")
+		);
+		let (hex, asm) = data
+			.redeem_script_hex_asm(&secp, &redeem, lock_time)
+			.unwrap();
+		assert_eq!(
+			hex,
+			"63042539df5bb17521022fd8c0455bede249ad3b9a9fb8159829e8cfb2c360863896e5309ea133d122f2ac67522102b4e59070d367a364a31981a71fc5ab6c5034d0e279eecec19287f3c95db84aef2103cf15041579b5fb7accbac2997fb2f3e1001e9a522a19c83ceabe5ae51a596c7c52ae68"
+		);
+		assert!(asm.starts_with("OP_IF OP_PUSHBYTES_4 2539df5b "));
+		assert!(asm.contains(
+			"022fd8c0455bede249ad3b9a9fb8159829e8cfb2c360863896e5309ea133d122f2 OP_CHECKSIG OP_ELSE"
+		));
+		assert!(asm.ends_with("OP_PUSHNUM_2 OP_CHECKMULTISIG OP_ENDIF"));
+
+		let mainnet_address = data
+			.lock_address(&secp, Currency::Btc, &redeem, lock_time, Network::Mainnet)
+			.unwrap();
+		assert!(mainnet_address.starts_with("3"));
+		assert_eq!(
+			mainnet_address,
+			data.address(Currency::Btc, &input_script, Network::Mainnet)
+				.unwrap()
+		);
 	}
 
 	#[test]
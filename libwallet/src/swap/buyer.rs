@@ -12,33 +12,41 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::config::SwapConfig;
 #[cfg(test)]
 use super::is_test_mode;
 use super::message::*;
 use super::swap;
 use super::swap::{tx_add_input, tx_add_output, Swap};
 use super::types::*;
-use super::{ErrorKind, Keychain, CURRENT_VERSION};
+use super::{check_required_features, ErrorKind, Keychain, RejectionExplanation, CURRENT_VERSION};
+use crate::internal::updater;
 use crate::swap::bitcoin::BtcData;
 use crate::swap::fsm::state::StateId;
 use crate::swap::multisig::{Builder as MultisigBuilder, ParticipantData as MultisigParticipant};
-use crate::{NodeClient, ParticipantData as TxParticipant, Slate, SlateVersion, VersionedSlate};
+use crate::{
+	CancellationToken, NodeClient, ParticipantData as TxParticipant, Slate, SlateVersion,
+	VersionedSlate,
+};
 use grin_core::core::KernelFeatures;
 use grin_core::libtx::{build, proof, tx_fee};
 use grin_keychain::{BlindSum, BlindingFactor, SwitchCommitmentType};
 use grin_util::secp::aggsig;
 use grin_util::secp::key::{PublicKey, SecretKey};
-use grin_util::secp::pedersen::RangeProof;
 use rand::thread_rng;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use uuid::Uuid;
 
+pub use super::config::{LOCK_INPUTS_BATCH_SIZE, REFUND_FEE_MAX_MULTIPLIER};
+
 /// Buyer API. Bunch of methods that cover buyer action for MWC swap
 /// This party is Buying MWC and selling BTC
 pub struct BuyApi {}
 
 impl BuyApi {
-	/// Accepting Seller offer and create Swap instance
+	/// Accepting Seller offer and create Swap instance, the offer is validated with the default
+	/// swap parameters
 	pub fn accept_swap_offer<C: NodeClient, K: Keychain>(
 		keychain: &K,
 		context: &Context,
@@ -47,12 +55,143 @@ impl BuyApi {
 		secondary_update: SecondaryUpdate,
 		node_client: &C,
 	) -> Result<Swap, ErrorKind> {
-		if offer.version != CURRENT_VERSION {
+		Self::accept_swap_offer_with_config(
+			keychain,
+			context,
+			id,
+			offer,
+			secondary_update,
+			node_client,
+			&SwapConfig::default(),
+			&CancellationToken::new(),
+		)
+	}
+
+	/// Simulate the Buyer's refund for the Seller offer before accepting it, see
+	/// Swap::simulate_refund. The refund fee is estimated with the given secondary fee rate.
+	pub fn simulate_offer_refund(
+		offer: &OfferUpdate,
+		secondary_update: SecondaryUpdate,
+		refund_address: Option<&String>,
+		secondary_fee: f32,
+	) -> Result<RefundSimulation, ErrorKind> {
+		let btc_offer = secondary_update.unwrap_btc()?.unwrap_offer()?;
+		swap::simulate_secondary_refund(
+			offer.secondary_currency,
+			offer.secondary_amount,
+			&btc_offer.cosign,
+			refund_address,
+			secondary_fee,
+		)
+	}
+
+	/// Accepting Seller offer with the Buyer own confirmations policy and external reference.
+	/// The overrides can only raise the offer confirmations, the lower values are ignored.
+	/// The overrides that can't be reached in the offer time limits are rejected.
+	pub fn accept_swap_offer_with_overrides<C: NodeClient, K: Keychain>(
+		keychain: &K,
+		context: &Context,
+		id: Uuid,
+		offer: OfferUpdate,
+		secondary_update: SecondaryUpdate,
+		node_client: &C,
+		buyer_min_mwc_confirmations: Option<u64>,
+		buyer_min_secondary_confirmations: Option<u64>,
+		external_ref: Option<String>,
+		config: &SwapConfig,
+	) -> Result<Swap, ErrorKind> {
+		let mut swap = Self::accept_swap_offer_with_external_ref(
+			keychain,
+			context,
+			id,
+			offer,
+			secondary_update,
+			node_client,
+			external_ref,
+			config,
+		)?;
+		swap.buyer_min_mwc_confirmations =
+			buyer_min_mwc_confirmations.filter(|c| *c > swap.mwc_confirmations);
+		swap.buyer_min_secondary_confirmations =
+			buyer_min_secondary_confirmations.filter(|c| *c > swap.secondary_confirmations);
+
+		let max_mwc_confirmations = swap.max_lock_confirmations(60);
+		if swap.required_mwc_confirmations() > max_mwc_confirmations {
+			return Err(ErrorKind::ConfirmationsOutOfTime(
+				"MWC".to_string(),
+				swap.required_mwc_confirmations(),
+				max_mwc_confirmations,
+			));
+		}
+		let max_secondary_confirmations =
+			swap.max_lock_confirmations(swap.secondary_currency.block_time_period_sec());
+		if swap.required_secondary_confirmations() > max_secondary_confirmations {
+			return Err(ErrorKind::ConfirmationsOutOfTime(
+				swap.secondary_currency.to_string(),
+				swap.required_secondary_confirmations(),
+				max_secondary_confirmations,
+			));
+		}
+		Ok(swap)
+	}
+
+	/// Accepting Seller offer with the Buyer external reference. If the offer has a reference,
+	/// the Buyer one must match it.
+	pub fn accept_swap_offer_with_external_ref<C: NodeClient, K: Keychain>(
+		keychain: &K,
+		context: &Context,
+		id: Uuid,
+		offer: OfferUpdate,
+		secondary_update: SecondaryUpdate,
+		node_client: &C,
+		external_ref: Option<String>,
+		config: &SwapConfig,
+	) -> Result<Swap, ErrorKind> {
+		if offer.external_ref.is_some()
+			&& external_ref.is_some()
+			&& offer.external_ref != external_ref
+		{
+			return Err(ErrorKind::ExternalRefMismatch(
+				offer.external_ref,
+				external_ref,
+			));
+		}
+		let mut swap = Self::accept_swap_offer_with_config(
+			keychain,
+			context,
+			id,
+			offer,
+			secondary_update,
+			node_client,
+			config,
+			&CancellationToken::new(),
+		)?;
+		if swap.external_ref.is_none() {
+			swap.external_ref = external_ref;
+		}
+		Ok(swap)
+	}
+
+	/// Accepting Seller offer, the offer is validated with the given swap parameters. The validation
+	/// can be cancelled with the token while the lock slate inputs are requested from the node.
+	pub fn accept_swap_offer_with_config<C: NodeClient, K: Keychain>(
+		keychain: &K,
+		context: &Context,
+		id: Uuid,
+		offer: OfferUpdate,
+		secondary_update: SecondaryUpdate,
+		node_client: &C,
+		config: &SwapConfig,
+		cancel: &CancellationToken,
+	) -> Result<Swap, ErrorKind> {
+		// Newer versions are fine as long as we support all features that offer requires.
+		if offer.version < CURRENT_VERSION {
 			return Err(ErrorKind::IncompatibleVersion(
 				offer.version,
 				CURRENT_VERSION,
 			));
 		}
+		check_required_features(&offer.required_features)?;
 
 		// Checking if the network match expected value
 		if offer.network != Network::current_network()? {
@@ -63,11 +202,12 @@ impl BuyApi {
 		}
 
 		context.unwrap_buyer()?;
+		context.validate_nonces()?;
 
 		let now_ts = swap::get_cur_time();
 
-		// Tolerating 15 seconds clock difference. We don't want surprises with clocks.
-		if offer.start_time.timestamp() > (now_ts + 15) {
+		// Tolerating small clock difference. We don't want surprises with clocks.
+		if offer.start_time.timestamp() > (now_ts + config.clock_tolerance_sec) {
 			return Err(ErrorKind::InvalidMessageData(
 				"Buyer/Seller clock are out of sync".to_string(),
 			));
@@ -79,20 +219,11 @@ impl BuyApi {
 			return Err(ErrorKind::InvalidLockHeightLockTx);
 		}
 		if lock_slate.amount != offer.primary_amount {
-			return Err(ErrorKind::InvalidMessageData(
-				"Lock Slate amount doesn't match offer".to_string(),
-			));
-		}
-		if lock_slate.fee
-			!= tx_fee(
-				lock_slate.tx.body.inputs.len(),
-				lock_slate.tx.body.outputs.len() + 1,
-				1,
-				None,
-			) {
-			return Err(ErrorKind::InvalidMessageData(
-				"Lock Slate fee doesn't match expected value".to_string(),
-			));
+			return Err(ErrorKind::OfferRejected(RejectionExplanation::new(
+				"lock slate amount",
+				offer.primary_amount,
+				lock_slate.amount,
+			)));
 		}
 		if lock_slate.num_participants != 2 {
 			return Err(ErrorKind::InvalidMessageData(
@@ -127,8 +258,24 @@ impl BuyApi {
 				"Lock Slate empty inputs".to_string(),
 			));
 		}
-		let res = node_client
-			.get_outputs_from_node(&lock_slate.tx.body.inputs.iter().map(|i| i.commit).collect())?;
+		let mut input_commits = HashSet::new();
+		for input in &lock_slate.tx.body.inputs {
+			if !input_commits.insert(input.commit.clone()) {
+				return Err(ErrorKind::InvalidMessageData(format!(
+					"Lock Slate has duplicate input {}",
+					grin_util::to_hex(input.commit.0.to_vec())
+				)));
+			}
+		}
+		// Node calls are limited, a slow node must not freeze the offer processing
+		let node_client = swap::node_client_with_timeout(node_client, config.node_call_timeout);
+		let input_commits: Vec<_> = lock_slate.tx.body.inputs.iter().map(|i| i.commit).collect();
+		let res = updater::get_outputs_from_node_batched(
+			&node_client,
+			&input_commits,
+			config.lock_inputs_batch_size,
+			cancel,
+		)?;
 		if res.len() != lock_slate.tx.body.inputs.len() {
 			return Err(ErrorKind::InvalidMessageData(
 				"Lock Slate inputs are not found at the chain".to_string(),
@@ -140,6 +287,9 @@ impl BuyApi {
 				"Lock Slate height is invalid".to_string(),
 			));
 		}
+		if lock_slate.height + config.lock_slate_max_age < height {
+			return Err(ErrorKind::StaleOffer(lock_slate.height, height));
+		}
 
 		// Checking Refund slate.
 		// Refund tx needs to be locked until exactly as offer specify. For MWC we are expecting one block every 1 minute.
@@ -178,10 +328,28 @@ impl BuyApi {
 				"Refund Slate amount doesn't match offer".to_string(),
 			));
 		}
-		if refund_slate.fee != tx_fee(1, 1, 1, None) {
-			return Err(ErrorKind::InvalidMessageData(
-				"Refund Slate fee doesn't match expected value".to_string(),
-			));
+		// Seller might pay more for faster refund confirmation, it is fine for the Buyer.
+		// But fee can't be below the minimum and absurd values are rejected.
+		// Zero fee is fine only on the testing chains.
+		let base_refund_fee = tx_fee(1, 1, 1, None);
+		let min_refund_fee = if swap::is_zero_fee_allowed() {
+			0
+		} else {
+			base_refund_fee
+		};
+		if refund_slate.fee < min_refund_fee {
+			return Err(ErrorKind::OfferRejected(RejectionExplanation::new(
+				"refund slate fee",
+				format!(">= {}", min_refund_fee),
+				refund_slate.fee,
+			)));
+		}
+		if refund_slate.fee > base_refund_fee * config.refund_fee_max_multiplier {
+			return Err(ErrorKind::OfferRejected(RejectionExplanation::new(
+				"refund slate fee",
+				format!("<= {}", base_refund_fee * config.refund_fee_max_multiplier),
+				refund_slate.fee,
+			)));
 		}
 
 		// Checking Secondary data. Focus on timing issues
@@ -195,8 +363,12 @@ impl BuyApi {
 			keychain,
 			secondary_update.unwrap_btc()?.unwrap_offer()?,
 			context.unwrap_buyer()?.unwrap_btc()?,
+			offer.network,
 		)?;
 
+		// Seller multisig data must be well formed before we start building with it
+		offer.multisig.validate_round_1(keychain.secp())?;
+
 		// Start redeem slate
 		let mut redeem_slate = Slate::blank(2);
 
@@ -207,7 +379,8 @@ impl BuyApi {
 
 		redeem_slate.fee = tx_fee(1, 1, 1, None);
 		redeem_slate.height = height;
-		redeem_slate.amount = offer.primary_amount.saturating_sub(redeem_slate.fee);
+		redeem_slate.amount =
+			swap::amount_minus_fee("MWC redeem", offer.primary_amount, redeem_slate.fee)?;
 
 		redeem_slate.participant_data.push(offer.redeem_participant);
 
@@ -259,27 +432,75 @@ impl BuyApi {
 			secondary_fee,
 			electrum_node_uri1: None, // User need to review the offer first. Then to electrumX uri can be updated
 			electrum_node_uri2: None,
+			redeem_fee_base: None,
+			broadcast_txs: HashMap::new(),
+			buyer_min_mwc_confirmations: None,
+			buyer_min_secondary_confirmations: None,
+			external_ref: offer.external_ref.clone(),
+			lock_time_tolerance_percent: offer.lock_time_tolerance_percent,
 			last_process_error: None,
 			last_check_error: None,
 			wait_for_backup1: false,
 		};
 		swap.add_journal_message("Received a swap offer".to_string());
 
+		// Locks must be posted within the tolerance of the lock time. If that time is over,
+		// there is no point to accept the offer.
+		let tolerance_percent = swap.lock_time_tolerance_percent();
+		if tolerance_percent == 0 || tolerance_percent > swap::MAX_LOCK_TIME_TOLERANCE_PERCENT {
+			return Err(ErrorKind::OfferRejected(RejectionExplanation::new(
+				"lock time tolerance percent",
+				format!("1-{}", swap::MAX_LOCK_TIME_TOLERANCE_PERCENT),
+				tolerance_percent,
+			)));
+		}
+		let start_lock_time = swap.get_time_start_lock();
+		if now_ts >= start_lock_time {
+			return Err(ErrorKind::OfferRejected(RejectionExplanation::new(
+				&format!(
+					"start lock time ({}% lock time tolerance)",
+					tolerance_percent
+				),
+				format!("> {}", now_ts),
+				start_lock_time,
+			)));
+		}
+
 		// Minimum mwc heights
 		let expected_lock_height = height + (swap.get_time_mwc_lock() - now_ts) as u64 / 60;
 
-		if swap.refund_slate.lock_height < expected_lock_height * 9 / 10 {
-			return Err(ErrorKind::InvalidMessageData(
-				"Refund lock slate doesn't meet required number of confirmations".to_string(),
-			));
+		// Tolerance covers the block time variance and the offer processing time
+		let tolerance_percent = config.refund_lock_height_tolerance_percent.min(100);
+		let min_lock_height = expected_lock_height * (100 - tolerance_percent) / 100;
+		if swap.refund_slate.lock_height < min_lock_height {
+			return Err(ErrorKind::OfferRejected(RejectionExplanation::new(
+				"refund slate lock height",
+				format!(">= {}", min_lock_height),
+				swap.refund_slate.lock_height,
+			)));
 		}
 
+		swap.check_primary_amount()?;
+
 		swap.redeem_public = Some(PublicKey::from_secret_key(
 			keychain.secp(),
 			&Self::redeem_secret(keychain, context)?,
 		)?);
 
 		Self::build_multisig(keychain, &mut swap, context, offer.multisig)?;
+		// Fee is validated before we sign anything. The multisig output is added with the placeholder
+		// proof that has the size of the real one, so it is the transaction that will be published.
+		let mut lock_slate = swap.lock_slate.clone();
+		tx_add_output(
+			&mut lock_slate,
+			swap.multisig.commit(keychain.secp())?,
+			swap::multisig_proof_placeholder(),
+		);
+		if swap.lock_slate.fee != swap::tx_required_fee(&lock_slate.tx)? {
+			return Err(ErrorKind::InvalidMessageData(
+				"Lock Slate fee doesn't match expected value".to_string(),
+			));
+		}
 		Self::sign_lock_slate(keychain, &mut swap, context)?;
 		Self::sign_refund_slate(keychain, &mut swap, context)?;
 
@@ -314,6 +535,7 @@ impl BuyApi {
 					.ok_or(ErrorKind::Generic("redeem_public is empty".to_string()))?,
 				lock_participant: swap.lock_slate.participant_data[id].clone(),
 				refund_participant: swap.refund_slate.participant_data[id].clone(),
+				external_ref: swap.external_ref.clone(),
 			}),
 			inner_secondary,
 		)
@@ -403,13 +625,32 @@ impl BuyApi {
 		}
 
 		// Add multisig output to slate (with invalid proof)
-		let mut proof = RangeProof::zero();
-		proof.plen = grin_util::secp::constants::MAX_PROOF_SIZE;
+		tx_add_output(
+			slate,
+			swap.multisig.commit(keychain.secp())?,
+			swap::multisig_proof_placeholder(),
+		);
 
-		tx_add_output(slate, swap.multisig.commit(keychain.secp())?, proof);
+		// Seller's public excess must balance the transaction with our part. Otherwise the slate
+		// is malformed and we should not sign it.
+		let secp = keychain.secp();
+		let excess = slate.calc_excess(keychain)?.to_pubkey(secp)?;
+		let mut pub_blinds: Vec<PublicKey> = slate
+			.participant_data
+			.iter()
+			.map(|p| p.public_blind_excess.clone())
+			.collect();
+		pub_blinds.push(PublicKey::from_secret_key(secp, &sec_key)?);
+		let pub_blind_sum = PublicKey::from_combination(secp, pub_blinds.iter().collect())?;
+		if excess != pub_blind_sum {
+			return Err(ErrorKind::LockExcessMismatch(
+				grin_util::to_hex(excess.serialize_vec(secp, true).to_vec()),
+				grin_util::to_hex(pub_blind_sum.serialize_vec(secp, true).to_vec()),
+			));
+		}
 
 		// Sign slate
-		slate.fill_round_1(
+		slate.fill_rounds(
 			keychain,
 			&mut sec_key,
 			&context.lock_nonce,
@@ -417,13 +658,12 @@ impl BuyApi {
 			None,
 			false,
 		)?;
-		slate.fill_round_2(keychain, &sec_key, &context.lock_nonce, swap.participant_id)?;
 
 		Ok(())
 	}
 
 	/// Convenience function to calculate the secret that is used for signing the refund slate
-	fn refund_tx_secret<K: Keychain>(
+	pub fn refund_tx_secret<K: Keychain>(
 		keychain: &K,
 		swap: &Swap,
 		context: &Context,
@@ -453,9 +693,11 @@ impl BuyApi {
 
 		// Add multisig input to slate
 		tx_add_input(slate, commit);
+		swap.verify_refund_secret(keychain, &sec_key)?;
 
 		// Sign slate
-		slate.fill_round_1(
+		let slate = &mut swap.refund_slate;
+		slate.fill_rounds(
 			keychain,
 			&mut sec_key,
 			&context.refund_nonce,
@@ -463,12 +705,8 @@ impl BuyApi {
 			None,
 			false,
 		)?;
-		slate.fill_round_2(
-			keychain,
-			&sec_key,
-			&context.refund_nonce,
-			swap.participant_id,
-		)?;
+		// Partial signature is shared with the Seller, it must be valid
+		swap.verify_refund_secret(keychain, &sec_key)?;
 
 		Ok(())
 	}
@@ -501,8 +739,7 @@ impl BuyApi {
 		let bcontext = context.unwrap_buyer()?;
 
 		// This function should only be called once
-		let slate = &mut swap.redeem_slate;
-		if slate.participant_data.len() > 1 {
+		if swap.redeem_slate.participant_data.len() > 1 {
 			return Err(ErrorKind::OneShot(
 				"Buyer Fn build_redeem_slate(), redeem slate participant data is not empty"
 					.to_string(),
@@ -510,13 +747,19 @@ impl BuyApi {
 		}
 
 		// Build slate
-		slate.fee = tx_fee(1, 1, 1, None);
-		slate.amount = swap.primary_amount - slate.fee;
+		let fee = swap.redeem_fee();
+		swap.check_zero_fee("MWC redeem", fee)?;
+		let amount = swap::amount_minus_fee("MWC redeem", swap.primary_amount, fee)?;
+		let slate = &mut swap.redeem_slate;
+		slate.fee = fee;
+		slate.amount = amount;
 		let mut elems = Vec::new();
 		elems.push(build::output(slate.amount, bcontext.output.clone()));
 		slate
-			.add_transaction_elements(keychain, &proof::ProofBuilder::new(keychain), elems)?
+			.add_transaction_elements(keychain, &proof::ProofBuilder::new(keychain), elems, false)?
 			.secret_key(keychain.secp())?;
+		swap.check_primary_amount()?;
+		let slate = &mut swap.redeem_slate;
 
 		#[cfg(test)]
 		{
@@ -611,6 +854,13 @@ impl BuyApi {
 			));
 		}
 
+		// Seller will verify the adaptor signature with redeem_public, it must match our secret
+		let redeem_secret = Self::redeem_secret(keychain, context)?;
+		let redeem_public = PublicKey::from_secret_key(keychain.secp(), &redeem_secret)?;
+		if swap.redeem_public != Some(redeem_public) {
+			return Err(ErrorKind::RedeemPublicMismatch);
+		}
+
 		let sec_key = Self::redeem_tx_secret(keychain, swap, context)?;
 		let (pub_nonce_sum, pub_blind_sum, message) =
 			swap.redeem_tx_fields(keychain.secp(), &swap.redeem_slate)?;
@@ -620,7 +870,7 @@ impl BuyApi {
 			&message,
 			&sec_key,
 			Some(&context.redeem_nonce),
-			Some(&Self::redeem_secret(keychain, context)?),
+			Some(&redeem_secret),
 			Some(&pub_nonce_sum),
 			Some(&pub_blind_sum),
 			Some(&pub_nonce_sum),
@@ -0,0 +1,122 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tunable parameters of the swap engine. Defaults are the protocol values, the wallets
+//! that use them are compatible with each other. The parameters are passed to the swap API
+//! calls, there is no global state.
+
+use super::types::Currency;
+use grin_core::global::{self, ChainTypes};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Default limit for a single node call during the swap processing
+pub const DEFAULT_NODE_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default number of blocks that the offer lock slate can be behind the chain tip
+pub const DEFAULT_LOCK_SLATE_MAX_AGE: u64 = 720;
+/// Refund fee can't exceed the minimal refund fee more than that many times
+pub const REFUND_FEE_MAX_MULTIPLIER: u64 = 10;
+/// Lock slate inputs are requested from the node by batches of that size
+pub const LOCK_INPUTS_BATCH_SIZE: usize = 100;
+/// Number of average transactions that fits into a block. If the mempool is larger,
+/// transactions are competing for the block space.
+pub const POOL_SIZE_PER_BLOCK: u64 = 1000;
+/// Suggested redeem fee is never higher than that number of minimal fees
+pub const MAX_REDEEM_FEE_MULTIPLIER: u64 = 10;
+/// Clock difference between Buyer and Seller that is tolerated, seconds
+pub const CLOCK_TOLERANCE_SEC: i64 = 15;
+/// MWC can't be locked for longer than 30 days, blocks
+pub const MAX_MWC_LOCK_BLOCKS: u64 = 1440 * 30;
+/// Minimal interval between the outbound messages of the swap or to the same counterparty,
+/// seconds. The protocol resends the messages every few minutes, so the limit only stops
+/// the retry loops and the bursts to a single counterparty. 0 means no limit.
+pub const MESSAGE_MIN_INTERVAL_SEC: i64 = 10;
+/// Confirmations of the first lock that the party locking second waits for. The second lock
+/// must be posted before the start lock deadline, that is too short for the confirmations
+/// negotiated in the offer. Both parties must agree on it, so it is not a local setting.
+pub const FIRST_LOCK_CONFIRMATIONS: u64 = 1;
+/// Part of the lock time, percent, that the parties have to post the locks within. Offers that
+/// don't have the tolerance use this value.
+pub const LOCK_TIME_TOLERANCE_PERCENT: u64 = 5;
+/// Buyer rejects the offers with a wider lock time tolerance, percent
+pub const MAX_LOCK_TIME_TOLERANCE_PERCENT: u64 = 50;
+/// Buyer accepts the refund lock height that is below the expected one by that many percents.
+/// Blocks are not produced exactly every minute, and the offer needs time to be processed.
+pub const REFUND_LOCK_HEIGHT_TOLERANCE_PERCENT: u64 = 10;
+
+/// Swap engine parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapConfig {
+	/// Limit for a single node call
+	pub node_call_timeout: Duration,
+	/// Number of blocks that the offer lock slate can be behind the chain tip
+	pub lock_slate_max_age: u64,
+	/// Refund fee can't exceed the minimal refund fee more than that many times
+	pub refund_fee_max_multiplier: u64,
+	/// Lock slate inputs are requested from the node by batches of that size
+	pub lock_inputs_batch_size: usize,
+	/// Number of average transactions that fits into a block, used for the redeem fee
+	pub pool_size_per_block: u64,
+	/// Suggested redeem fee is never higher than that number of minimal fees
+	pub max_redeem_fee_multiplier: u64,
+	/// Clock difference between Buyer and Seller that is tolerated, seconds
+	pub clock_tolerance_sec: i64,
+	/// Longest time the MWC can be locked, blocks
+	pub max_mwc_lock_blocks: u64,
+	/// Minimal interval between the outbound messages, seconds. 0 means no limit.
+	pub message_min_interval_sec: i64,
+	/// Lock time tolerance, percent, that the Seller puts into the offers for the secondary
+	/// currency. Currencies that are not listed use their default tolerance.
+	pub lock_time_tolerance_percent: HashMap<Currency, u64>,
+	/// Refund lock height can be below the height expected from the MWC lock time by that
+	/// many percents. Networks with unstable block time might need a wider tolerance.
+	pub refund_lock_height_tolerance_percent: u64,
+}
+
+impl Default for SwapConfig {
+	fn default() -> Self {
+		SwapConfig {
+			node_call_timeout: DEFAULT_NODE_CALL_TIMEOUT,
+			lock_slate_max_age: DEFAULT_LOCK_SLATE_MAX_AGE,
+			refund_fee_max_multiplier: REFUND_FEE_MAX_MULTIPLIER,
+			lock_inputs_batch_size: LOCK_INPUTS_BATCH_SIZE,
+			pool_size_per_block: POOL_SIZE_PER_BLOCK,
+			max_redeem_fee_multiplier: MAX_REDEEM_FEE_MULTIPLIER,
+			clock_tolerance_sec: CLOCK_TOLERANCE_SEC,
+			max_mwc_lock_blocks: MAX_MWC_LOCK_BLOCKS,
+			message_min_interval_sec: MESSAGE_MIN_INTERVAL_SEC,
+			lock_time_tolerance_percent: HashMap::new(),
+			refund_lock_height_tolerance_percent: REFUND_LOCK_HEIGHT_TOLERANCE_PERCENT,
+		}
+	}
+}
+
+impl SwapConfig {
+	/// Lock time tolerance, percent, for the offers with the secondary currency
+	pub fn lock_time_tolerance_percent(&self, currency: Currency) -> u64 {
+		self.lock_time_tolerance_percent
+			.get(&currency)
+			.cloned()
+			.unwrap_or(currency.lock_time_tolerance_percent())
+	}
+}
+
+/// Check if the transactions without fee are permitted. Only the testing chains accept them,
+/// the permission follows the chain type and can't be configured.
+pub fn is_zero_fee_allowed() -> bool {
+	match global::get_chain_type() {
+		ChainTypes::AutomatedTesting | ChainTypes::UserTesting => true,
+		_ => false,
+	}
+}
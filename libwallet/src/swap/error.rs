@@ -13,10 +13,12 @@
 // limitations under the License.
 
 use super::multisig;
+use super::types::Network;
 use failure::Fail;
 use grin_core::core::committed;
 use grin_util::secp;
 use std::error::Error as StdError;
+use std::fmt;
 use std::io;
 
 /// Swap crate errors
@@ -34,6 +36,12 @@ pub enum ErrorKind {
 	/// Unexpected network
 	#[fail(display = "Swap Unexpected network {}", _0)]
 	UnexpectedNetwork(String),
+	/// Secondary currency network doesn't match MWC network
+	#[fail(
+		display = "Secondary currency is at {} network, but MWC is at {} network",
+		_1, _0
+	)]
+	SecondaryNetworkMismatch(String, String),
 	/// Unexpected role. Business logic is broken
 	#[fail(display = "Swap Unexpected role, {}", _0)]
 	UnexpectedRole(String),
@@ -52,9 +60,21 @@ pub enum ErrorKind {
 		_0, _1
 	)]
 	IncompatibleVersion(u8, u8),
+	/// Offer requires the feature that this wallet doesn't support. Upgrade is needed.
+	#[fail(
+		display = "Swap offer requires feature '{}' that is not supported by your wallet. Please upgrade your wallet.",
+		_0
+	)]
+	UnsupportedSwapFeature(String),
 	/// Message from different swap. Probably other party messing up with us.
 	#[fail(display = "Mismatch between swap and message IDs")]
 	MismatchedId,
+	/// Message has another external reference. Probably messages from different trades are mixed up.
+	#[fail(
+		display = "Swap external reference mismatch, expected {:?}, get {:?}",
+		_0, _1
+	)]
+	ExternalRefMismatch(Option<String>, Option<String>),
 	/// Unable to parse the amount string
 	#[fail(display = "Invalid amount string, {}", _0)]
 	InvalidAmountString(String),
@@ -67,6 +87,18 @@ pub enum ErrorKind {
 	/// Schnorr signature is invalid
 	#[fail(display = "Swap Invalid adaptor signature (Schnorr signature)")]
 	InvalidAdaptorSignature,
+	/// Redeem public key doesn't match the secret that is used for the adaptor signature
+	#[fail(display = "Swap redeem public key doesn't match the redeem secret")]
+	RedeemPublicMismatch,
+	/// Redeem secret can be exported only for the completed swap
+	#[fail(
+		display = "Redeem secret can't be released until the swap is complete, current state is {}",
+		_0
+	)]
+	SecretNotYetReleasable(String),
+	/// Offer doesn't pass the validation. Explanation lists every failed check
+	#[fail(display = "Swap offer is rejected, {}", _0)]
+	OfferRejected(RejectionExplanation),
 	/// swap.refund is not defined
 	#[fail(display = "Swap secondary currency data not complete")]
 	SecondaryDataIncomplete,
@@ -103,6 +135,12 @@ pub enum ErrorKind {
 	/// Requested swap trade not found
 	#[fail(display = "Swap trade {} not found", _0)]
 	TradeNotFound(String),
+	/// Swap id prefix matches several trades
+	#[fail(display = "Swap id {} is ambiguous, it matches: {}", _0, _1)]
+	AmbiguousSwapId(String, String),
+	/// Swap alias can't be used
+	#[fail(display = "Invalid swap alias '{}', {}", _0, _1)]
+	InvalidSwapAlias(String, String),
 	/// swap trade IO error
 	#[fail(display = "Swap trade {} IO error, {}", _0, _1)]
 	TradeIoError(String, String),
@@ -118,6 +156,75 @@ pub enum ErrorKind {
 	/// Invalid Swap state input
 	#[fail(display = "Swap state machine error, {}", _0)]
 	SwapStateMachineError(String),
+	/// Offer is built on the outdated chain state
+	#[fail(
+		display = "Offer lock slate is built at height {}, it is too old for the current height {}. Please request a new offer",
+		_0, _1
+	)]
+	StaleOffer(u64, u64),
+	/// Node call is not completed in time
+	#[fail(display = "Node call timeout, {}", _0)]
+	NodeTimeout(String),
+	/// Multisig commitment doesn't match the one that is built from the participants data
+	#[fail(
+		display = "Multisig commitment mismatch, expected {}, stored {}",
+		_0, _1
+	)]
+	MultisigCommitMismatch(String, String),
+	/// Lock slate excess doesn't match the sum of the participants public excesses
+	#[fail(
+		display = "Lock slate kernel excess {} doesn't match the participants public excess {}",
+		_0, _1
+	)]
+	LockExcessMismatch(String, String),
+	/// Slate amount doesn't match the swap MWC amount
+	#[fail(display = "MWC amount of the {} is {}, expected {}", _0, _2, _1)]
+	PrimaryAmountMismatch(String, u64, u64),
+	/// Refund transaction secret doesn't spend the multisig input
+	#[fail(display = "Invalid refund transaction secret, {}", _0)]
+	InvalidRefundSecret(String),
+	/// Offer sender is not in the counterparty allowlist
+	#[fail(display = "Counterparty {} is not allowed", _0)]
+	CounterpartyNotAllowed(String),
+	/// Operation is cancelled by the user
+	#[fail(display = "Swap operation is cancelled, {}", _0)]
+	Cancelled(String),
+	/// Outbound messages are sent too often, retry after the number of seconds
+	#[fail(
+		display = "Swap message rate limit is reached, retry after {} seconds",
+		_0
+	)]
+	RateLimited(i64),
+	/// Fee doesn't leave anything from the amount it is paid from
+	#[fail(display = "The {} fee {} exceeds the amount {}", _0, _1, _2)]
+	FeeExceedsAmount(String, u64, u64),
+	/// Output that is left after the fee is paid is too small to be relayed
+	#[fail(display = "The {} output {} is below the dust limit {}", _0, _1, _2)]
+	DustOutput(String, u64, u64),
+	/// Lock confirmations can't be reached before the redeem messages exchange
+	#[fail(
+		display = "The {} lock confirmations {} can't be reached in the swap time limits, maximum is {}",
+		_0, _1, _2
+	)]
+	ConfirmationsOutOfTime(String, u64, u64),
+	/// Fee of the confirmed transaction can't be changed
+	#[fail(
+		display = "The {} transaction already has {} confirmations, the fee can't be changed",
+		_0, _1
+	)]
+	TxAlreadyConfirmed(String, u64),
+	/// Transaction without fee is built or received for the network that doesn't permit it
+	#[fail(display = "Zero fee {} is not allowed at {:?} network", _0, _1)]
+	ZeroFeeNotAllowed(String, Network),
+	/// Swap context has the same nonce for the different purposes
+	#[fail(display = "Swap context {} and {} nonces are the same", _0, _1)]
+	DuplicateContextNonce(String, String),
+	/// Node rejected the MWC lock transaction
+	#[fail(display = "MWC lock transaction is rejected by the node, {}", _0)]
+	LockTxRejected(String),
+	/// Swap message is not JSON or armored
+	#[fail(display = "Unknown swap message format, {}", _0)]
+	UnknownMessageFormat(String),
 	/// Generic error
 	#[fail(display = "Swap generic error, {}", _0)]
 	Generic(String),
@@ -127,13 +234,72 @@ pub enum ErrorKind {
 	BchError(String),
 }
 
+/// Single failed check of the offer validation
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FailedCheck {
+	/// What was checked
+	pub check: String,
+	/// Expected value or condition
+	pub expected: String,
+	/// Actual value from the offer
+	pub actual: String,
+}
+
+impl fmt::Display for FailedCheck {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} is {}, expected {}",
+			self.check, self.actual, self.expected
+		)
+	}
+}
+
+/// Explanation why the offer was rejected
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct RejectionExplanation {
+	/// Checks that failed
+	pub failed_checks: Vec<FailedCheck>,
+}
+
+impl RejectionExplanation {
+	/// Explanation with a single failed check
+	pub fn new<E: fmt::Display, A: fmt::Display>(check: &str, expected: E, actual: A) -> Self {
+		Self {
+			failed_checks: vec![FailedCheck {
+				check: check.to_string(),
+				expected: expected.to_string(),
+				actual: actual.to_string(),
+			}],
+		}
+	}
+}
+
+impl fmt::Display for RejectionExplanation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let checks: Vec<String> = self.failed_checks.iter().map(|c| c.to_string()).collect();
+		write!(f, "{}", checks.join("; "))
+	}
+}
+
 impl ErrorKind {
+	/// Explanation of the rejected offer, if the error has it
+	pub fn rejection_explanation(&self) -> Option<&RejectionExplanation> {
+		match self {
+			ErrorKind::OfferRejected(explanation) => Some(explanation),
+			_ => None,
+		}
+	}
+
 	/// Check if this error network related
 	pub fn is_network_error(&self) -> bool {
 		use ErrorKind::*;
 		format!("");
 		match self {
-			Rpc(_) | ElectrumNodeClient(_) | LibWallet(crate::ErrorKind::Node(_)) => true,
+			Rpc(_)
+			| ElectrumNodeClient(_)
+			| NodeTimeout(_)
+			| LibWallet(crate::ErrorKind::Node(_)) => true,
 			_ => false,
 		}
 	}
@@ -153,7 +319,11 @@ impl From<multisig::ErrorKind> for ErrorKind {
 
 impl From<crate::Error> for ErrorKind {
 	fn from(error: crate::Error) -> ErrorKind {
-		ErrorKind::LibWallet(error.kind())
+		match error.kind() {
+			crate::ErrorKind::NodeTimeout(msg) => ErrorKind::NodeTimeout(msg),
+			crate::ErrorKind::Cancelled(msg) => ErrorKind::Cancelled(msg),
+			kind => ErrorKind::LibWallet(kind),
+		}
 	}
 }
 
@@ -21,7 +21,7 @@ use super::state::{
 use crate::swap::fsm::state::{Input, State, StateEtaInfo, StateId, StateProcessRespond};
 use crate::swap::message::Message;
 use crate::swap::swap;
-use crate::swap::types::{Action, SwapTransactionsConfirmations};
+use crate::swap::types::{Action, MwcTxAction, SwapTransactionsConfirmations};
 use crate::swap::{BuyApi, Context, ErrorKind, Swap, SwapApi};
 use crate::NodeClient;
 use chrono::{Local, TimeZone};
@@ -208,15 +208,45 @@ where
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// State BuyerWaitingForSellerToLock
-pub struct BuyerWaitingForSellerToLock {}
-impl BuyerWaitingForSellerToLock {
+pub struct BuyerWaitingForSellerToLock<'a, C>
+where
+	C: NodeClient + 'a,
+{
+	node_client: Arc<C>,
+	phantom: PhantomData<&'a C>,
+}
+impl<'a, C> BuyerWaitingForSellerToLock<'a, C>
+where
+	C: NodeClient + 'a,
+{
 	/// Create new instance
-	pub fn new() -> Self {
-		Self {}
+	pub fn new(node_client: Arc<C>) -> Self {
+		Self {
+			node_client,
+			phantom: PhantomData,
+		}
+	}
+
+	/// When Buyer is locking first, the only commitment from the Seller is the lock slate from the offer.
+	/// Its inputs must still be unspent, otherwise Seller will never be able to lock MWC.
+	fn seller_lock_inputs_unspent(&self, swap: &Swap) -> Result<bool, ErrorKind> {
+		let inputs: Vec<_> = swap
+			.lock_slate
+			.tx
+			.body
+			.inputs
+			.iter()
+			.map(|i| i.commit)
+			.collect();
+		let res = self.node_client.get_outputs_from_node(&inputs)?;
+		Ok(res.len() == inputs.len())
 	}
 }
 
-impl State for BuyerWaitingForSellerToLock {
+impl<'a, C> State for BuyerWaitingForSellerToLock<'a, C>
+where
+	C: NodeClient + 'a,
+{
 	fn get_state_id(&self) -> StateId {
 		StateId::BuyerWaitingForSellerToLock
 	}
@@ -284,19 +314,29 @@ impl State for BuyerWaitingForSellerToLock {
 				}
 
 				if !swap.seller_lock_first {
-					// Skipping this step. Seller waiting for us to start locking
+					// Seller waiting for us to start locking. Before posting anything, let's verify that
+					// Seller's lock inputs are still there. Not needed if the funds are already posted.
+					if tx_conf.mwc_lock_conf.is_none()
+						&& tx_conf.secondary_lock_amount == 0
+						&& !self.seller_lock_inputs_unspent(swap)?
+					{
+						swap.add_journal_message(
+							"Cancelled because Seller MWC lock inputs are spent".to_string(),
+						);
+						return Ok(StateProcessRespond::new(StateId::BuyerCancelled));
+					}
 					Ok(StateProcessRespond::new(
 						StateId::BuyerPostingSecondaryToMultisigAccount,
 					))
 				} else {
 					let conf = tx_conf.mwc_lock_conf.unwrap_or(0);
 
-					if conf < 1 {
+					if !swap.is_lock_allowed(tx_conf) {
 						Ok(
 							StateProcessRespond::new(StateId::BuyerWaitingForSellerToLock)
 								.action(Action::WaitForMwcConfirmations {
 									name: "Seller locking funds".to_string(),
-									required: 1,
+									required: swap.first_lock_required_confirmations(),
 									actual: conf,
 								})
 								.time_limit(time_limit),
@@ -392,9 +432,11 @@ where
 				}
 
 				// Check if mwc lock is already done
-				let (pending_amount, confirmed_amount, _least_confirmations) = self
-					.swap_api
-					.request_secondary_lock_balance(swap, swap.secondary_confirmations)?;
+				let (pending_amount, confirmed_amount, _least_confirmations) =
+					self.swap_api.request_secondary_lock_balance(
+						swap,
+						swap.required_secondary_confirmations(),
+					)?;
 
 				let chain_amount = pending_amount + confirmed_amount;
 				let time_limit = swap.get_time_start_lock();
@@ -557,8 +599,8 @@ where
 				}
 
 				let time_limit = swap.get_time_message_redeem();
-				if mwc_lock < swap.mwc_confirmations
-					|| secondary_lock < swap.secondary_confirmations
+				if mwc_lock < swap.required_mwc_confirmations()
+					|| secondary_lock < swap.required_secondary_confirmations()
 				{
 					// Checking for a deadline. Note time_message_redeem is fine, we can borrow time from that operation and still be safe
 					if swap::get_cur_time() > time_limit {
@@ -570,11 +612,11 @@ where
 					return Ok(
 						StateProcessRespond::new(StateId::BuyerWaitingForLockConfirmations)
 							.action(Action::WaitForLockConfirmations {
-								mwc_required: swap.mwc_confirmations,
+								mwc_required: swap.required_mwc_confirmations(),
 								mwc_actual: mwc_lock,
 								currency: swap.secondary_currency,
 								sec_expected_to_be_posted: 0,
-								sec_required: swap.secondary_confirmations,
+								sec_required: swap.required_secondary_confirmations(),
 								sec_actual: tx_conf.secondary_lock_conf,
 							})
 							.time_limit(time_limit),
@@ -649,8 +691,8 @@ impl State for BuyerSendingInitRedeemMessage {
 				// Check first if everything is still locked...
 				let mwc_lock = tx_conf.mwc_lock_conf.unwrap_or(0);
 				let secondary_lock = tx_conf.secondary_lock_conf.unwrap_or(0);
-				if mwc_lock < swap.mwc_confirmations
-					|| secondary_lock < swap.secondary_confirmations
+				if mwc_lock < swap.required_mwc_confirmations()
+					|| secondary_lock < swap.required_secondary_confirmations()
 				{
 					swap.add_journal_message(JOURNAL_NOT_LOCKED.to_string());
 					return Ok(StateProcessRespond::new(
@@ -756,8 +798,8 @@ impl<K: Keychain> State for BuyerWaitingForRespondRedeemMessage<K> {
 				// Check first if everything is still locked...
 				let mwc_lock = tx_conf.mwc_lock_conf.unwrap_or(0);
 				let secondary_lock = tx_conf.secondary_lock_conf.unwrap_or(0);
-				if mwc_lock < swap.mwc_confirmations
-					|| secondary_lock < swap.secondary_confirmations
+				if mwc_lock < swap.required_mwc_confirmations()
+					|| secondary_lock < swap.required_secondary_confirmations()
 				{
 					swap.add_journal_message(JOURNAL_NOT_LOCKED.to_string());
 					return Ok(StateProcessRespond::new(
@@ -912,8 +954,8 @@ where
 				// Check if everything is still locked...
 				let mwc_lock = tx_conf.mwc_lock_conf.unwrap_or(0);
 				let secondary_lock = tx_conf.secondary_lock_conf.unwrap_or(0);
-				if mwc_lock < swap.mwc_confirmations
-					|| secondary_lock < swap.secondary_confirmations
+				if mwc_lock < swap.required_mwc_confirmations()
+					|| secondary_lock < swap.required_secondary_confirmations()
 				{
 					swap.add_journal_message(JOURNAL_NOT_LOCKED.to_string());
 					return Ok(StateProcessRespond::new(
@@ -941,8 +983,8 @@ where
 				// Check if everything is still locked...
 				let mwc_lock = tx_conf.mwc_lock_conf.unwrap_or(0);
 				let secondary_lock = tx_conf.secondary_lock_conf.unwrap_or(0);
-				if mwc_lock < swap.mwc_confirmations
-					|| secondary_lock < swap.secondary_confirmations
+				if mwc_lock < swap.required_mwc_confirmations()
+					|| secondary_lock < swap.required_secondary_confirmations()
 				{
 					swap.add_journal_message(JOURNAL_NOT_LOCKED.to_string());
 					return Ok(StateProcessRespond::new(
@@ -950,7 +992,7 @@ where
 					));
 				}
 
-				swap::publish_transaction(&*self.node_client, &swap.redeem_slate.tx, false)?;
+				swap.publish_mwc_tx(&*self.node_client, MwcTxAction::Redeem)?;
 				swap.posted_redeem = Some(swap::get_cur_time());
 				swap.add_journal_message("MWC Redeem slate is posted".to_string());
 				Ok(StateProcessRespond::new(
@@ -1006,7 +1048,7 @@ impl State for BuyerWaitForRedeemMwcConfirmations {
 				// TODO   Check if need to do a retry.
 
 				let conf = tx_conf.mwc_redeem_conf.unwrap_or(0);
-				if conf >= swap.mwc_confirmations {
+				if conf >= swap.required_mwc_confirmations() {
 					// We are done
 					swap.add_journal_message(
 						"Redeem transacton has enough confirnation. The Swap trade is finished"
@@ -1027,7 +1069,7 @@ impl State for BuyerWaitForRedeemMwcConfirmations {
 					StateProcessRespond::new(StateId::BuyerWaitForRedeemMwcConfirmations).action(
 						Action::WaitForMwcConfirmations {
 							name: "Redeeming funds".to_string(),
-							required: swap.mwc_confirmations,
+							required: swap.required_mwc_confirmations(),
 							actual: conf,
 						},
 					),
@@ -1381,7 +1423,7 @@ where
 		match input {
 			Input::Check => {
 				if let Some(conf) = tx_conf.secondary_refund_conf {
-					if conf >= swap.secondary_confirmations {
+					if conf >= swap.required_secondary_confirmations() {
 						// We are done
 						swap.add_journal_message(format!("{} refund transaction has enough confirmations. The trade is completed, refund is redeemed.", swap.secondary_currency));
 						return Ok(StateProcessRespond::new(StateId::BuyerCancelledRefunded));
@@ -1413,7 +1455,7 @@ where
 							name: format!("{} Refund", swap.secondary_currency),
 							expected_to_be_posted: 0,
 							currency: swap.secondary_currency,
-							required: swap.secondary_confirmations,
+							required: swap.required_secondary_confirmations(),
 							actual: tx_conf.secondary_refund_conf.unwrap_or(0),
 						},
 					),
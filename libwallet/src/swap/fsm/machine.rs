@@ -74,6 +74,10 @@ impl<'a> StateMachine<'a> {
 			"Swap {} processing state {:?} for Input {:?}",
 			swap.id, swap.state, input
 		);
+		context.validate_nonces()?;
+		if let Input::IncomeMessage(message) = &input {
+			swap.check_external_ref(message)?;
+		}
 
 		let state = self
 			.state_map
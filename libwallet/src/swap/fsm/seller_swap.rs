@@ -20,7 +20,7 @@ use super::state::{
 };
 use crate::swap::fsm::state::{Input, State, StateEtaInfo, StateId, StateProcessRespond};
 use crate::swap::message::Message;
-use crate::swap::types::{Action, SwapTransactionsConfirmations};
+use crate::swap::types::{Action, MwcTxAction, SwapTransactionsConfirmations};
 use crate::swap::{swap, Context, ErrorKind, SellApi, Swap, SwapApi};
 use crate::NodeClient;
 use chrono::{Local, TimeZone};
@@ -357,7 +357,7 @@ impl State for SellerWaitingForBuyerLock {
 						return Ok(StateProcessRespond::new(StateId::SellerCancelled));
 					}
 
-					if conf < 1 {
+					if !swap.is_lock_allowed(tx_conf) {
 						Ok(StateProcessRespond::new(StateId::SellerWaitingForBuyerLock)
 							.action(Action::WaitForSecondaryConfirmations {
 								name: "Buyer to lock funds".to_string(),
@@ -365,7 +365,7 @@ impl State for SellerWaitingForBuyerLock {
 									.secondary_amount
 									.saturating_sub(tx_conf.secondary_lock_amount),
 								currency: swap.secondary_currency,
-								required: 1,
+								required: swap.first_lock_required_confirmations(),
 								actual: conf,
 							})
 							.time_limit(time_limit))
@@ -475,6 +475,11 @@ where
 					return Self::generate_cancel_respond(swap);
 				}
 
+				// Buyer is locking first, we can't be here before that
+				if swap.posted_lock.is_none() && !swap.is_lock_allowed(tx_conf) {
+					return Ok(StateProcessRespond::new(StateId::SellerWaitingForBuyerLock));
+				}
+
 				Ok(StateProcessRespond::new(StateId::SellerPostingLockMwcSlate)
 					.action(Action::SellerPublishMwcLockTx)
 					.time_limit(time_limit))
@@ -491,14 +496,12 @@ where
 					// cancelling because of timeout. The last Chance to cancel easy way.
 					return Self::generate_cancel_respond(swap);
 				}
+				if swap.posted_lock.is_none() && !swap.is_lock_allowed(tx_conf) {
+					// Not our turn, Buyer must lock first
+					return Ok(StateProcessRespond::new(StateId::SellerWaitingForBuyerLock));
+				}
 				// Posting the transaction
-				swap::publish_transaction(&*self.node_client, &swap.lock_slate.tx, false)?;
-				swap.posted_lock = Some(swap::get_cur_time());
-				swap.add_journal_message("MWC lock slate posted".to_string());
-
-				Ok(StateProcessRespond::new(
-					StateId::SellerWaitingForLockConfirmations,
-				))
+				post_lock_slate(swap, &*self.node_client)
 			}
 			Input::IncomeMessage(message) => {
 				// Message must be ignored. Late delivery sometimes is possible
@@ -519,6 +522,121 @@ where
 	}
 }
 
+// Post the MWC lock slate. If node rejects the first post, the swap goes to SellerLockFailed,
+// the lock is not done and the seller has to retry or cancel. Repost of the already posted
+// lock can be rejected because the node knows it, the error is returned as before.
+// Other errors, like the node timeout or the connection failure, don't say anything about
+// the lock, they are returned and the post is retried with the next processing.
+fn post_lock_slate<C: NodeClient>(
+	swap: &mut Swap,
+	node_client: &C,
+) -> Result<StateProcessRespond, ErrorKind> {
+	match swap.publish_mwc_tx(node_client, MwcTxAction::Lock) {
+		Ok(_) => {
+			swap.posted_lock = Some(swap::get_cur_time());
+			swap.add_journal_message("MWC lock slate posted".to_string());
+			Ok(StateProcessRespond::new(
+				StateId::SellerWaitingForLockConfirmations,
+			))
+		}
+		Err(ErrorKind::LibWallet(crate::ErrorKind::TxRejected(e)))
+			if swap.posted_lock.is_none() =>
+		{
+			swap.add_journal_message(format!("{}", ErrorKind::LockTxRejected(e)));
+			Ok(StateProcessRespond::new(StateId::SellerLockFailed))
+		}
+		Err(e) => Err(e),
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// State SellerLockFailed
+pub struct SellerLockFailed<'a, C>
+where
+	C: NodeClient + 'a,
+{
+	node_client: Arc<C>,
+	phantom: PhantomData<&'a C>,
+}
+
+impl<'a, C> SellerLockFailed<'a, C>
+where
+	C: NodeClient + 'a,
+{
+	/// Create an instance
+	pub fn new(node_client: Arc<C>) -> Self {
+		Self {
+			node_client,
+			phantom: PhantomData,
+		}
+	}
+}
+
+impl<'a, C> State for SellerLockFailed<'a, C>
+where
+	C: NodeClient + 'a,
+{
+	fn get_state_id(&self) -> StateId {
+		StateId::SellerLockFailed
+	}
+	fn get_eta(&self, swap: &Swap) -> Option<StateEtaInfo> {
+		Some(
+			StateEtaInfo::new("MWC lock is rejected, retry or cancel")
+				.end_time(swap.get_time_start_lock()),
+		)
+	}
+	fn is_cancellable(&self) -> bool {
+		true
+	}
+
+	/// Process the state. Result will be the next state
+	fn process(
+		&mut self,
+		input: Input,
+		swap: &mut Swap,
+		_context: &Context,
+		tx_conf: &SwapTransactionsConfirmations,
+	) -> Result<StateProcessRespond, ErrorKind> {
+		let time_limit = swap.get_time_start_lock();
+		match input {
+			Input::Cancel => {
+				swap.add_journal_message(JOURNAL_CANCELLED_BY_USER.to_string());
+				SellerPostingLockMwcSlate::<C>::generate_cancel_respond(swap)
+			}
+			Input::Check | Input::Execute => {
+				// The lock can be mined anyway, the node might reject it because it is already known
+				if tx_conf.mwc_lock_conf.is_some() {
+					return Ok(StateProcessRespond::new(
+						StateId::SellerWaitingForLockConfirmations,
+					));
+				}
+				if swap::get_cur_time() > time_limit {
+					swap.add_journal_message(JOURNAL_CANCELLED_BY_TIMEOUT.to_string());
+					return SellerPostingLockMwcSlate::<C>::generate_cancel_respond(swap);
+				}
+				match input {
+					Input::Execute => post_lock_slate(swap, &*self.node_client),
+					_ => Ok(StateProcessRespond::new(StateId::SellerLockFailed)
+						.action(Action::SellerRetryMwcLockTx)
+						.time_limit(time_limit)),
+				}
+			}
+			Input::IncomeMessage(message) => {
+				// Late Accept offer message, nothing to do
+				let _ = message.unwrap_accept_offer()?;
+				Ok(StateProcessRespond::new(StateId::SellerLockFailed))
+			}
+		}
+	}
+	fn get_prev_swap_state(&self) -> Option<StateId> {
+		Some(StateId::SellerPostingLockMwcSlate)
+	}
+	fn get_next_swap_state(&self) -> Option<StateId> {
+		Some(StateId::SellerWaitingForLockConfirmations)
+	}
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// State SellerWaitingForLockConfirmations
@@ -1619,7 +1737,7 @@ where
 				// Executing the MWC lock transaction
 				// Posting the transaction
 				debug_assert!(tx_conf.mwc_refund_conf.is_none());
-				swap::publish_transaction(&*self.node_client, &swap.refund_slate.tx, false)?;
+				swap.publish_mwc_tx(&*self.node_client, MwcTxAction::Refund)?;
 				swap.posted_refund = Some(swap::get_cur_time());
 				swap.add_journal_message("MWC refund slate is posted".to_string());
 				Ok(StateProcessRespond::new(
@@ -77,6 +77,9 @@ pub enum StateId {
 	/// Simple cancelled State for the seller (never was locked, refunded)
 	SellerCancelled,
 
+	/// Node rejected the MWC lock transaction. Seller can retry or cancel
+	SellerLockFailed,
+
 	// -------------- Buyer happy path ----------------
 	/// Buyer offer is created (initial state for the Buyer)
 	BuyerOfferCreated,
@@ -140,6 +143,7 @@ impl fmt::Display for StateId {
 			StateId::SellerCancelled => {
 				"Seller swap was cancelled, nothing was locked, no need to refund"
 			}
+			StateId::SellerLockFailed => "MWC lock transaction is rejected by the node",
 
 			StateId::BuyerOfferCreated => "Offer is ready to Accept",
 			StateId::BuyerSendingAcceptOfferMessage => "Send offer message to Seller",
@@ -206,6 +210,7 @@ impl StateId {
 			StateId::SellerWaitingForRefundConfirmations => "SellerWaitingForRefundConfirmations",
 			StateId::SellerCancelledRefunded => "SellerCancelledRefunded",
 			StateId::SellerCancelled => "SellerCancelled",
+			StateId::SellerLockFailed => "SellerLockFailed",
 
 			StateId::BuyerOfferCreated => "BuyerOfferCreated",
 			StateId::BuyerSendingAcceptOfferMessage => "BuyerSendingAcceptOfferMessage",
@@ -253,6 +258,7 @@ impl StateId {
 			}
 			"SellerCancelledRefunded" => Ok(StateId::SellerCancelledRefunded),
 			"SellerCancelled" => Ok(StateId::SellerCancelled),
+			"SellerLockFailed" => Ok(StateId::SellerLockFailed),
 			"BuyerOfferCreated" => Ok(StateId::BuyerOfferCreated),
 			"BuyerSendingAcceptOfferMessage" => Ok(StateId::BuyerSendingAcceptOfferMessage),
 			"BuyerWaitingForSellerToLock" => Ok(StateId::BuyerWaitingForSellerToLock),
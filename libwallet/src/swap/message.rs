@@ -15,6 +15,7 @@
 use super::bitcoin::BtcUpdate;
 use super::multisig::ParticipantData as MultisigParticipant;
 use super::ser::*;
+use super::swap;
 use super::types::{Currency, Network};
 use super::ErrorKind;
 use crate::proof::message::EncryptedMessage;
@@ -35,6 +36,9 @@ pub struct Message {
 	pub inner: Update,
 	/// Secondary currency (BTC) related data
 	inner_secondary: SecondaryUpdate,
+	/// External reference of the swap, every message echoes it
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub external_ref: Option<String>,
 }
 
 impl Message {
@@ -44,6 +48,7 @@ impl Message {
 			id,
 			inner,
 			inner_secondary,
+			external_ref: None,
 		}
 	}
 
@@ -121,6 +126,187 @@ impl Message {
 			ErrorKind::Serde(format!("Unable to parse Swap Message from {}, {}", s, e))
 		})?)
 	}
+
+	/// Name of the message type
+	pub fn type_name(&self) -> &'static str {
+		match &self.inner {
+			Update::None => "None",
+			Update::Offer(_) => "Offer",
+			Update::AcceptOffer(_) => "AcceptOffer",
+			Update::InitRedeem(_) => "InitRedeem",
+			Update::Redeem(_) => "Redeem",
+			Update::MessageAcknowledge(_) => "MessageAcknowledge",
+		}
+	}
+
+	/// Message as Json with the signatures and the secrets replaced by REDACTED_VALUE
+	pub fn to_redacted_json(&self) -> Result<serde_json::Value, ErrorKind> {
+		let mut value = serde_json::to_value(self)
+			.map_err(|e| ErrorKind::Serde(format!("Unable to serialize a message, {}", e)))?;
+		redact_json(&mut value);
+		Ok(value)
+	}
+
+	/// Message as the armored text, that is safe for copy/paste and messengers
+	pub fn to_armored(&self) -> Result<String, ErrorKind> {
+		let encoded = base64::encode(self.to_json()?.as_bytes());
+		let lines: Vec<String> = encoded
+			.as_bytes()
+			.chunks(ARMOR_LINE_LEN)
+			.map(|l| String::from_utf8_lossy(l).to_string())
+			.collect();
+		Ok(format!(
+			"{}\n{}\n{}\n",
+			ARMOR_HEADER,
+			lines.join("\n"),
+			ARMOR_FOOTER
+		))
+	}
+
+	/// Build message from the data in any of the supported formats. Format is auto detected.
+	/// MQS messages are not parsed here, the MQS listener decrypts them and verifies
+	/// the sender signature before the message gets to the swap.
+	pub fn parse(input: &[u8]) -> Result<Message, ErrorKind> {
+		let text = std::str::from_utf8(input).map_err(|e| {
+			ErrorKind::UnknownMessageFormat(format!("message is not a valid UTF-8, {}", e))
+		})?;
+		let text = text.trim();
+		match MessageFormat::detect(text) {
+			Some(MessageFormat::Json) => Message::from_json(text),
+			Some(MessageFormat::Armored) => {
+				if !text.ends_with(ARMOR_FOOTER) {
+					return Err(ErrorKind::UnknownMessageFormat(
+						"armored message footer is not found".to_string(),
+					));
+				}
+				let body = &text[ARMOR_HEADER.len()..text.len() - ARMOR_FOOTER.len()];
+				let encoded: String = body.split_whitespace().collect();
+				let json = base64::decode(&encoded).map_err(|e| {
+					ErrorKind::UnknownMessageFormat(format!(
+						"Unable to decode armored message, {}",
+						e
+					))
+				})?;
+				let json = String::from_utf8(json).map_err(|e| {
+					ErrorKind::UnknownMessageFormat(format!(
+						"armored message is not a valid UTF-8, {}",
+						e
+					))
+				})?;
+				Message::from_json(&json)
+			}
+			None => Err(ErrorKind::UnknownMessageFormat(
+				"expecting JSON or armored swap message".to_string(),
+			)),
+		}
+	}
+}
+
+/// Armored message is wrapped with these markers
+const ARMOR_HEADER: &str = "BEGINSWAPMESSAGE.";
+const ARMOR_FOOTER: &str = "ENDSWAPMESSAGE.";
+/// Line length of the armored data
+const ARMOR_LINE_LEN: usize = 64;
+
+/// Formats that the swap message can be received in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageFormat {
+	/// Raw JSON, the file transport
+	Json,
+	/// Base64 text with the markers, the chats and the messengers
+	Armored,
+}
+
+impl MessageFormat {
+	/// Detect the format of the message text. None if format is unknown.
+	pub fn detect(text: &str) -> Option<Self> {
+		let text = text.trim_start();
+		if text.starts_with('{') {
+			Some(MessageFormat::Json)
+		} else if text.starts_with(ARMOR_HEADER) {
+			Some(MessageFormat::Armored)
+		} else {
+			None
+		}
+	}
+}
+
+/// Extract the swap offer from the message in any of the supported formats.
+/// The offer is accepted the same way regardless of how it was received.
+pub fn parse_offer(input: &[u8]) -> Result<(Uuid, OfferUpdate, SecondaryUpdate), ErrorKind> {
+	Message::parse(input)?.unwrap_offer()
+}
+
+/// Message fields that are never written into the message log. Together with the final
+/// transactions, the signatures reveal the swap secrets.
+pub const REDACTED_MESSAGE_FIELDS: [&str; 4] =
+	["adaptor_signature", "part_sig", "message_sig", "tau_x"];
+/// Value that replaces the redacted fields
+pub const REDACTED_VALUE: &str = "<redacted>";
+
+fn redact_json(value: &mut serde_json::Value) {
+	match value {
+		serde_json::Value::Object(fields) => {
+			for (name, v) in fields.iter_mut() {
+				if REDACTED_MESSAGE_FIELDS.contains(&name.as_str()) {
+					if !v.is_null() {
+						*v = serde_json::Value::String(REDACTED_VALUE.to_string());
+					}
+				} else {
+					redact_json(v);
+				}
+			}
+		}
+		serde_json::Value::Array(items) => {
+			for v in items.iter_mut() {
+				redact_json(v);
+			}
+		}
+		_ => (),
+	}
+}
+
+/// Direction of the swap message
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MessageDirection {
+	/// Message was sent to the other party
+	Sent,
+	/// Message was received from the other party and processed
+	Received,
+}
+
+/// Record of the swap message log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SwapMessageLogRecord {
+	/// Unix timestamp, when the message was sent or processed
+	pub time: i64,
+	/// Sent or received
+	pub direction: MessageDirection,
+	/// Message type, see Message::type_name
+	pub message_type: String,
+	/// Message payload, the signatures and the secrets are redacted
+	pub message: serde_json::Value,
+}
+
+impl SwapMessageLogRecord {
+	/// Record for the message that is sent or received now
+	pub fn new(direction: MessageDirection, message: &Message) -> Result<Self, ErrorKind> {
+		Ok(Self {
+			time: swap::get_cur_time(),
+			direction,
+			message_type: message.type_name().to_string(),
+			message: message.to_redacted_json()?,
+		})
+	}
+}
+
+/// Messages that were exchanged for the swap trade, in the order they were sent or processed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SwapMessageLog {
+	/// Swap trade Id
+	pub swap_id: String,
+	/// Log records
+	pub records: Vec<SwapMessageLogRecord>,
 }
 
 /// Swap core data of the Seller/Buyer message
@@ -182,6 +368,16 @@ pub struct OfferUpdate {
 	pub message_exchange_time_sec: u64,
 	/// Time interval needed to redeem or execute a refund transaction.
 	pub redeem_time_sec: u64,
+	/// Protocol features that the buyer wallet must support to accept this offer.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub required_features: Vec<String>,
+	/// Seller external reference, for example the exchange order id
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub external_ref: Option<String>,
+	/// Part of the lock time, percent, that the parties have to post the locks within.
+	/// None is the default LOCK_TIME_TOLERANCE_PERCENT.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub lock_time_tolerance_percent: Option<u64>,
 }
 
 /// Buyer, Status::Offered  Buyer responded for offer
@@ -196,6 +392,9 @@ pub struct AcceptOfferUpdate {
 	pub lock_participant: TxParticipant,
 	/// Buyer part needed to build refund slate
 	pub refund_participant: TxParticipant,
+	/// External reference that Buyer agreed to, or proposed if the offer doesn't have it
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub external_ref: Option<String>,
 }
 
 /// Buyer, Status::Locked   Buyer building the redeem slate
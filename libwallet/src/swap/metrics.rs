@@ -0,0 +1,230 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Swap metrics for the monitoring systems. Metrics are aggregated from the stored swaps.
+
+use crate::swap::fsm::state::StateId;
+use crate::swap::swap::SwapJournalRecord;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt;
+use uuid::Uuid;
+
+/// Final status of the swap
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SwapStatus {
+	/// Swap is not finished yet
+	InProgress,
+	/// Swap is successfully completed
+	Completed,
+	/// Swap was cancelled, the locked funds are refunded
+	Refunded,
+	/// Swap was cancelled before the funds were locked
+	Cancelled,
+}
+
+impl SwapStatus {
+	/// Status of the swap at this state
+	pub fn from_state(state: &StateId) -> Self {
+		match state {
+			StateId::SellerSwapComplete | StateId::BuyerSwapComplete => SwapStatus::Completed,
+			StateId::SellerCancelledRefunded | StateId::BuyerCancelledRefunded => {
+				SwapStatus::Refunded
+			}
+			StateId::SellerCancelled | StateId::BuyerCancelled => SwapStatus::Cancelled,
+			_ => SwapStatus::InProgress,
+		}
+	}
+}
+
+impl fmt::Display for SwapStatus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let disp = match &self {
+			SwapStatus::InProgress => "InProgress",
+			SwapStatus::Completed => "Completed",
+			SwapStatus::Refunded => "Refunded",
+			SwapStatus::Cancelled => "Cancelled",
+		};
+		write!(f, "{}", disp)
+	}
+}
+
+/// Part of the stored swap that is needed for the metrics. Slates and the secondary
+/// currency data are skipped, so reading the summary is much cheaper than the whole swap.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SwapSummary {
+	/// Swap session uuid
+	pub id: Uuid,
+	/// Time when swap session was started
+	pub started: DateTime<Utc>,
+	/// Current state for this swap session
+	pub state: StateId,
+	/// Event log for this swap trade
+	pub journal: Vec<SwapJournalRecord>,
+}
+
+impl SwapSummary {
+	/// Status of the swap
+	pub fn status(&self) -> SwapStatus {
+		SwapStatus::from_state(&self.state)
+	}
+
+	/// Completion time, seconds. The last journal record is done when the swap is finished.
+	/// None for not finished swaps.
+	pub fn duration(&self) -> Option<i64> {
+		if !self.state.is_final_state() {
+			return None;
+		}
+		self.journal
+			.last()
+			.map(|j| j.time.saturating_sub(self.started.timestamp()))
+	}
+}
+
+/// Aggregated swap metrics
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SwapMetrics {
+	/// Total number of the swaps
+	pub total: usize,
+	/// Number of the swaps per status
+	pub by_status: BTreeMap<SwapStatus, usize>,
+	/// Average time of the completed swaps, seconds
+	pub avg_completion_time_sec: Option<i64>,
+	/// Longest time of the completed swaps, seconds
+	pub max_completion_time_sec: Option<i64>,
+	/// Share of the refunded swaps among the finished ones
+	pub refund_rate: f64,
+}
+
+impl SwapMetrics {
+	/// Aggregate the metrics
+	pub fn from_summaries(summaries: &[SwapSummary]) -> Self {
+		let mut by_status = BTreeMap::new();
+		for status in &[
+			SwapStatus::InProgress,
+			SwapStatus::Completed,
+			SwapStatus::Refunded,
+			SwapStatus::Cancelled,
+		] {
+			by_status.insert(*status, 0);
+		}
+		let mut durations: Vec<i64> = Vec::new();
+		for s in summaries {
+			let status = s.status();
+			*by_status.entry(status).or_insert(0) += 1;
+			if status == SwapStatus::Completed {
+				if let Some(d) = s.duration() {
+					durations.push(d);
+				}
+			}
+		}
+
+		let finished = summaries.len() - by_status[&SwapStatus::InProgress];
+		let refund_rate = if finished > 0 {
+			by_status[&SwapStatus::Refunded] as f64 / finished as f64
+		} else {
+			0.0
+		};
+
+		SwapMetrics {
+			total: summaries.len(),
+			by_status,
+			avg_completion_time_sec: if durations.is_empty() {
+				None
+			} else {
+				Some(durations.iter().sum::<i64>() / durations.len() as i64)
+			},
+			max_completion_time_sec: durations.iter().max().cloned(),
+			refund_rate,
+		}
+	}
+
+	/// Metrics in the Prometheus text format
+	pub fn to_prometheus(&self) -> String {
+		let mut res = String::new();
+		res.push_str("# TYPE mwc_swaps_total gauge\n");
+		res.push_str(&format!("mwc_swaps_total {}\n", self.total));
+		res.push_str("# TYPE mwc_swaps gauge\n");
+		for (status, count) in &self.by_status {
+			res.push_str(&format!("mwc_swaps{{status=\"{}\"}} {}\n", status, count));
+		}
+		if let Some(avg) = self.avg_completion_time_sec {
+			res.push_str("# TYPE mwc_swap_avg_completion_seconds gauge\n");
+			res.push_str(&format!("mwc_swap_avg_completion_seconds {}\n", avg));
+		}
+		if let Some(max) = self.max_completion_time_sec {
+			res.push_str("# TYPE mwc_swap_max_completion_seconds gauge\n");
+			res.push_str(&format!("mwc_swap_max_completion_seconds {}\n", max));
+		}
+		res.push_str("# TYPE mwc_swap_refund_rate gauge\n");
+		res.push_str(&format!("mwc_swap_refund_rate {}\n", self.refund_rate));
+		res
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::TimeZone;
+
+	fn summary(state: StateId, started: i64, finished: Option<i64>) -> SwapSummary {
+		SwapSummary {
+			id: Uuid::new_v4(),
+			started: Utc.timestamp(started, 0),
+			state,
+			journal: finished
+				.map(|time| {
+					vec![SwapJournalRecord {
+						time,
+						message: "done".to_string(),
+					}]
+				})
+				.unwrap_or_default(),
+		}
+	}
+
+	#[test]
+	fn test_swap_metrics() {
+		let summaries = vec![
+			summary(StateId::SellerSwapComplete, 1000, Some(4000)),
+			summary(StateId::BuyerSwapComplete, 2000, Some(3000)),
+			summary(StateId::SellerCancelledRefunded, 1000, Some(9000)),
+			summary(StateId::BuyerCancelled, 1000, Some(1500)),
+			summary(StateId::SellerWaitingForLockConfirmations, 1000, None),
+			summary(StateId::BuyerSendingAcceptOfferMessage, 1000, None),
+		];
+		let metrics = SwapMetrics::from_summaries(&summaries);
+		assert_eq!(metrics.total, 6);
+		assert_eq!(metrics.by_status[&SwapStatus::InProgress], 2);
+		assert_eq!(metrics.by_status[&SwapStatus::Completed], 2);
+		assert_eq!(metrics.by_status[&SwapStatus::Refunded], 1);
+		assert_eq!(metrics.by_status[&SwapStatus::Cancelled], 1);
+		// Only completed swaps are counted for the timing
+		assert_eq!(metrics.avg_completion_time_sec, Some(2000));
+		assert_eq!(metrics.max_completion_time_sec, Some(3000));
+		assert_eq!(metrics.refund_rate, 0.25);
+
+		let prometheus = metrics.to_prometheus();
+		assert!(prometheus.contains("mwc_swaps_total 6\n"));
+		assert!(prometheus.contains("mwc_swaps{status=\"Completed\"} 2\n"));
+		assert!(prometheus.contains("mwc_swap_refund_rate 0.25\n"));
+
+		// Nothing stored yet
+		let metrics = SwapMetrics::from_summaries(&[]);
+		assert_eq!(metrics.total, 0);
+		assert_eq!(metrics.by_status[&SwapStatus::Completed], 0);
+		assert_eq!(metrics.avg_completion_time_sec, None);
+		assert_eq!(metrics.refund_rate, 0.0);
+	}
+}
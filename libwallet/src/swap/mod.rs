@@ -18,6 +18,9 @@ pub mod api;
 /// Library that support bitcoin operations
 pub mod bitcoin;
 
+/// Tunable parameters of the swap engine
+pub mod config;
+
 /// Swap crate errors
 pub mod error;
 
@@ -32,6 +35,12 @@ pub mod fsm;
 
 /// Swap buyer API (selling MWC for BTC)
 pub mod buyer;
+/// Swap metrics for the monitoring systems
+pub mod metrics;
+/// Rate limit for the outbound swap messages
+pub mod rate_limit;
+/// Re-broadcast of the pending redeem and refund transactions
+pub mod rebroadcast;
 /// Swap Seller API (selling BTC for MWC)
 pub mod seller;
 /// Swap state object that is used by both byer abd seller
@@ -45,7 +54,7 @@ pub mod ser;
 /// Types used by swap library
 pub mod types;
 
-pub use self::error::ErrorKind;
+pub use self::error::{ErrorKind, FailedCheck, RejectionExplanation};
 pub use self::swap::Swap;
 pub use self::types::Context;
 //pub use self::types::BtcSellerContext;
@@ -63,6 +72,30 @@ use std::sync::atomic::{AtomicBool, Ordering};
 
 const CURRENT_VERSION: u8 = 1;
 
+/// Swap protocol features that this wallet is able to handle. An offer can list the features
+/// it requires, the buyer will reject only offers that need something missing from this list.
+pub const SUPPORTED_SWAP_FEATURES: &[&str] = &[
+	"btc",
+	"bch",
+	"seller_lock_first",
+	"buyer_lock_first",
+	LOCK_TIME_TOLERANCE_FEATURE,
+];
+
+/// Offer has the lock time tolerance that differs from the default one
+pub const LOCK_TIME_TOLERANCE_FEATURE: &str = "lock_time_tolerance";
+
+/// Check if all features required by the offer are supported by this wallet.
+/// Return the first unsupported feature name as an error.
+pub fn check_required_features(required_features: &[String]) -> Result<(), ErrorKind> {
+	for feature in required_features {
+		if !SUPPORTED_SWAP_FEATURES.contains(&feature.as_str()) {
+			return Err(ErrorKind::UnsupportedSwapFeature(feature.clone()));
+		}
+	}
+	Ok(())
+}
+
 #[cfg(test)]
 lazy_static! {
 	/// Flag to set test mode
@@ -95,15 +128,17 @@ pub fn is_test_response() -> bool {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
 	use crate::grin_util::{Mutex, RwLock};
-	use crate::{NodeClient, Slate, SlateVersion, VersionedSlate};
+	use crate::proof::proofaddress::ProvableAddress;
+	use crate::{CancellationToken, NodeClient, Slate, SlateVersion, VersionedSlate};
 	use bitcoin_lib::network::constants::Network as BtcNetwork;
 	use bitcoin_lib::util::key::PublicKey as BtcPublicKey;
 	use bitcoin_lib::{Address, Transaction as BtcTransaction, TxOut};
 	use grin_core::core::transaction::Weighting;
 	use grin_core::core::verifier_cache::LruVerifierCache;
 	use grin_core::core::{KernelFeatures, Transaction, TxKernel};
+	use grin_core::libtx::tx_fee;
 	use grin_keychain::{ExtKeychain, Identifier, Keychain, SwitchCommitmentType};
 	use grin_util::secp::key::{PublicKey, SecretKey};
 	use grin_util::secp::pedersen::{Commitment, RangeProof};
@@ -122,19 +157,19 @@ mod tests {
 	use super::*;
 	use crate::swap::fsm::machine::StateMachine;
 	use crate::swap::fsm::state::{Input, StateId, StateProcessRespond};
-	use crate::swap::message::{SecondaryUpdate, Update};
+	use crate::swap::message::{MessageDirection, OfferUpdate, SecondaryUpdate, Update};
 	use grin_core::global;
 	use grin_core::global::ChainTypes;
 
 	const GRIN_UNIT: u64 = 1_000_000_000;
 
-	fn keychain(idx: u8) -> ExtKeychain {
+	pub(crate) fn keychain(idx: u8) -> ExtKeychain {
 		let seed_sell: String = format!("fixed0rng0for0testing0purposes0{}", idx % 10);
 		let seed_sell = crate::blake2::blake2b::blake2b(32, &[], seed_sell.as_bytes());
 		ExtKeychain::from_seed(seed_sell.as_bytes(), false).unwrap()
 	}
 
-	fn context_sell(kc: &ExtKeychain) -> Context {
+	pub(crate) fn context_sell(kc: &ExtKeychain) -> Context {
 		Context {
 			multisig_key: key_id(0, 0),
 			multisig_nonce: key(kc, 1, 0),
@@ -149,6 +184,7 @@ mod tests {
 				],
 				change_output: key_id(0, 3),
 				change_amount: 20 * GRIN_UNIT, // selling 100 coins, so 20 will be left
+				change_split: None,
 				refund_output: key_id(0, 4),
 				secondary_context: SecondarySellerContext::Btc(BtcSellerContext {
 					cosign: key_id(0, 5),
@@ -197,16 +233,31 @@ mod tests {
 	}
 
 	#[derive(Debug, Clone)]
-	struct TestNodeClientState {
+	pub(crate) struct TestNodeClientState {
 		pub height: u64,
 		pub pending: Vec<Transaction>,
 		pub outputs: HashMap<Commitment, u64>,
 		pub kernels: HashMap<Commitment, (TxKernel, u64)>,
+		pub tip_time: Option<i64>,
+		pub pool_size: Option<u64>,
+		// Slow node, every call takes that long
+		pub delay: Option<std::time::Duration>,
+		// Number of the outputs requests
+		pub outputs_calls: usize,
+		// Token that is cancelled by the outputs request, simulates the user cancellation
+		pub cancel_on_outputs: Option<CancellationToken>,
+		// Number of the chain tip, kernel and batched tip with kernels requests
+		pub tip_calls: usize,
+		pub kernel_calls: usize,
+		pub batch_calls: usize,
+		// Error that post_tx fails with before the node sees the transaction
+		pub post_tx_error: Option<crate::ErrorKind>,
 	}
 
 	#[derive(Debug, Clone)]
-	struct TestNodeClient {
+	pub(crate) struct TestNodeClient {
 		pub state: Arc<Mutex<TestNodeClientState>>,
+		pub call_timeout: Option<std::time::Duration>,
 	}
 
 	impl TestNodeClient {
@@ -216,10 +267,39 @@ mod tests {
 				pending: Vec::new(),
 				outputs: HashMap::new(),
 				kernels: HashMap::new(),
+				tip_time: None,
+				pool_size: None,
+				delay: None,
+				outputs_calls: 0,
+				cancel_on_outputs: None,
+				tip_calls: 0,
+				kernel_calls: 0,
+				batch_calls: 0,
+				post_tx_error: None,
 			};
 			Self {
 				state: Arc::new(Mutex::new(state)),
+				call_timeout: None,
+			}
+		}
+
+		// Simulate the node respond time
+		fn wait_for_node(&self) -> Result<(), crate::Error> {
+			let delay = self.state.lock().delay.clone();
+			if let Some(delay) = delay {
+				match self.call_timeout {
+					Some(timeout) if timeout < delay => {
+						std::thread::sleep(timeout);
+						return Err(crate::ErrorKind::NodeTimeout(format!(
+							"no respond in {} ms",
+							timeout.as_millis()
+						))
+						.into());
+					}
+					_ => std::thread::sleep(delay),
+				}
 			}
+			Ok(())
 		}
 
 		pub fn push_output(&self, commit: Commitment) {
@@ -292,17 +372,39 @@ mod tests {
 		fn set_node_api_secret(&mut self, _node_api_secret: Option<String>) {
 			unimplemented!()
 		}
+		fn set_call_timeout(&mut self, timeout: Option<std::time::Duration>) {
+			self.call_timeout = timeout;
+		}
 		fn get_chain_tip(&self) -> Result<(u64, String, u64), crate::Error> {
-			let res = (self.state.lock().height, "testnodehash".to_string(), 123455);
+			self.wait_for_node()?;
+			let mut state = self.state.lock();
+			state.tip_calls += 1;
+			let res = (state.height, "testnodehash".to_string(), 123455);
 			Ok(res)
 		}
-		fn get_header_info(&self, _height: u64) -> Result<crate::HeaderInfo, crate::Error> {
-			unimplemented!()
+		fn get_header_info(&self, height: u64) -> Result<crate::HeaderInfo, crate::Error> {
+			let tip_time = self
+				.state
+				.lock()
+				.tip_time
+				.unwrap_or(chrono::Utc::now().timestamp());
+			Ok(crate::HeaderInfo {
+				height,
+				hash: "testnodehash".to_string(),
+				confirmed_time: chrono::DateTime::<chrono::Utc>::from_utc(
+					chrono::NaiveDateTime::from_timestamp(tip_time, 0),
+					chrono::Utc,
+				)
+				.to_rfc3339(),
+				version: 1,
+				nonce: 0,
+				total_difficulty: 123455,
+			})
 		}
 		fn get_connected_peer_info(
 			&self,
 		) -> Result<Vec<grin_p2p::types::PeerInfoDisplayLegacy>, crate::Error> {
-			unimplemented!()
+			Ok(vec![])
 		}
 		fn height_range_to_pmmr_indices(
 			&self,
@@ -323,24 +425,29 @@ mod tests {
 			unimplemented!()
 		}
 		fn post_tx(&self, tx: &Transaction, _fluff: bool) -> Result<(), crate::Error> {
+			if let Some(e) = self.state.lock().post_tx_error.clone() {
+				return Err(e.into());
+			}
 			tx.validate(
 				Weighting::AsTransaction,
 				Arc::new(RwLock::new(LruVerifierCache::new())),
 			)
-			.map_err(|e| crate::ErrorKind::Node(format!("Node failure, {}", e)))?;
+			.map_err(|e| crate::ErrorKind::TxRejected(format!("Node failure, {}", e)))?;
 
 			let mut state = self.state.lock();
 			for input in tx.inputs() {
 				// Output not unspent
 				if !state.outputs.contains_key(&input.commit) {
-					return Err(crate::ErrorKind::Node("Node failure".to_string()).into());
+					return Err(crate::ErrorKind::TxRejected("Node failure".to_string()).into());
 				}
 
 				// Double spend attempt
 				for tx_pending in state.pending.iter() {
 					for in_pending in tx_pending.inputs() {
 						if in_pending.commit == input.commit {
-							return Err(crate::ErrorKind::Node("Node failure".to_string()).into());
+							return Err(
+								crate::ErrorKind::TxRejected("Node failure".to_string()).into()
+							);
 						}
 					}
 				}
@@ -348,13 +455,15 @@ mod tests {
 			// Check for duplicate output
 			for output in tx.outputs() {
 				if state.outputs.contains_key(&output.commit) {
-					return Err(crate::ErrorKind::Node("Node failure".to_string()).into());
+					return Err(crate::ErrorKind::TxRejected("Node failure".to_string()).into());
 				}
 
 				for tx_pending in state.pending.iter() {
 					for out_pending in tx_pending.outputs() {
 						if out_pending.commit == output.commit {
-							return Err(crate::ErrorKind::Node("Node failure".to_string()).into());
+							return Err(
+								crate::ErrorKind::TxRejected("Node failure".to_string()).into()
+							);
 						}
 					}
 				}
@@ -363,13 +472,15 @@ mod tests {
 			for kernel in tx.kernels() {
 				// Duplicate kernel
 				if state.kernels.contains_key(&kernel.excess) {
-					return Err(crate::ErrorKind::Node("Node failure".to_string()).into());
+					return Err(crate::ErrorKind::TxRejected("Node failure".to_string()).into());
 				}
 
 				for tx_pending in state.pending.iter() {
 					for kernel_pending in tx_pending.kernels() {
 						if kernel_pending.excess == kernel.excess {
-							return Err(crate::ErrorKind::Node("Node failure".to_string()).into());
+							return Err(
+								crate::ErrorKind::TxRejected("Node failure".to_string()).into()
+							);
 						}
 					}
 				}
@@ -378,6 +489,9 @@ mod tests {
 
 			Ok(())
 		}
+		fn get_pool_size(&self) -> Option<u64> {
+			self.state.lock().pool_size
+		}
 		fn get_version_info(&mut self) -> Option<crate::NodeVersionInfo> {
 			unimplemented!()
 		}
@@ -385,8 +499,13 @@ mod tests {
 			&self,
 			wallet_outputs: &Vec<Commitment>,
 		) -> Result<HashMap<Commitment, (String, u64, u64)>, crate::Error> {
+			self.wait_for_node()?;
 			let mut map = HashMap::new();
-			let state = self.state.lock();
+			let mut state = self.state.lock();
+			state.outputs_calls += 1;
+			if let Some(cancel) = &state.cancel_on_outputs {
+				cancel.cancel();
+			}
 			for output in wallet_outputs {
 				if let Some(height) = state.outputs.get(&output) {
 					map.insert(output.clone(), (to_hex(output.0.to_vec()), *height, 0));
@@ -408,13 +527,81 @@ mod tests {
 			_min_height: Option<u64>,
 			_max_height: Option<u64>,
 		) -> Result<Option<(TxKernel, u64, u64)>, crate::Error> {
-			let state = self.state.lock();
+			let mut state = self.state.lock();
+			state.kernel_calls += 1;
 			let res = state
 				.kernels
 				.get(excess)
 				.map(|(kernel, height)| (kernel.clone(), *height, 0));
 			Ok(res)
 		}
+		fn get_tip_and_kernels(
+			&self,
+			kernels: &[(Commitment, Option<u64>)],
+		) -> Result<(u64, Vec<Option<(TxKernel, u64, u64)>>), crate::Error> {
+			self.wait_for_node()?;
+			let mut state = self.state.lock();
+			state.batch_calls += 1;
+			let res = kernels
+				.iter()
+				.map(|(excess, _)| {
+					state
+						.kernels
+						.get(excess)
+						.map(|(kernel, height)| (kernel.clone(), *height, 0))
+				})
+				.collect();
+			Ok((state.height, res))
+		}
+	}
+
+	#[test]
+	#[serial]
+	fn test_refund_lock_height_tolerance() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let accept = |config: &swap::SwapConfig| {
+			BuyApi::accept_swap_offer_with_config(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer.clone(),
+				secondary_update.clone(),
+				&nc,
+				config,
+				&CancellationToken::new(),
+			)
+		};
+
+		// Two blocks later the refund lock height is just below the expected one
+		nc.mine_blocks(2);
+		let config = swap::SwapConfig::default();
+		assert_eq!(config.refund_lock_height_tolerance_percent, 10);
+		assert!(accept(&config).is_ok());
+
+		let mut strict = config.clone();
+		strict.refund_lock_height_tolerance_percent = 0;
+		match accept(&strict).err().unwrap() {
+			ErrorKind::OfferRejected(explanation) => {
+				let check = &explanation.failed_checks[0];
+				assert_eq!(check.check, "refund slate lock height");
+				assert_eq!(check.actual, swap_sell.refund_slate.lock_height.to_string());
+			}
+			e => panic!("Unexpected error {:?}", e),
+		}
+
+		// Any tolerance covers a couple of blocks
+		strict.refund_lock_height_tolerance_percent = 1;
+		assert!(accept(&strict).is_ok());
 	}
 
 	#[test]
@@ -429,18 +616,3039 @@ mod tests {
 		let height = 100_000;
 
 		let mut api_sell = BtcSwapApi::new_test(
-			Arc::new(TestNodeClient::new(height)),
+			Arc::new(TestNodeClient::new(height)),
+			Arc::new(Mutex::new(TestBtcNodeClient::new(1))),
+		);
+		let mut swap = api_sell
+			.create_swap_offer(
+				&kc_sell,
+				&ctx_sell,
+				100 * GRIN_UNIT,
+				3_000_000,
+				Currency::Btc,
+				secondary_redeem_address,
+				true, // mwc should be publisher first
+				30,
+				3,
+				3600,
+				3600,
+				"file".to_string(),
+				"/tmp/del.me".to_string(),
+				None,
+				None,
+				None,
+				&swap::SwapConfig::default(),
+			)
+			.unwrap();
+		let mut fsm_sell = api_sell.get_fsm(&kc_sell, &swap);
+		let tx_state = api_sell
+			.request_tx_confirmations(&kc_sell, &mut swap)
+			.unwrap();
+
+		let message = match fsm_sell
+			.process(Input::Check, &mut swap, &ctx_sell, &tx_state)
+			.unwrap()
+			.action
+			.unwrap()
+		{
+			Action::SellerSendOfferMessage(message) => message,
+			_ => panic!("Unexpected action"),
+		};
+
+		// Simulate short refund lock time by passing height+4h
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let nc = TestNodeClient::new(height + 12 * 60);
+
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let res = BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc);
+
+		assert_eq!(
+			res.err().unwrap(),
+			ErrorKind::InvalidMessageData(
+				"Lock Slate inputs are not found at the chain".to_string()
+			)
+		); // Swap cannot be accepted
+	}
+
+	#[test]
+	#[serial]
+	fn test_lock_change_split() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		// 120 coins in, selling 100. The second change output fee is paid from the change.
+		let kc_sell = keychain(1);
+		let mut ctx_sell = context_sell(&kc_sell);
+		let split_amount = 7 * GRIN_UNIT;
+		let fee = tx_fee(2, 3, 1, None);
+		let change = 20 * GRIN_UNIT - fee;
+		match &mut ctx_sell.role_context {
+			RoleContext::Seller(c) => {
+				c.change_amount = change - split_amount;
+				c.change_split = Some((key_id(0, 6), split_amount));
+			}
+			_ => panic!("Unexpected role"),
+		}
+		let nc = TestNodeClient::new(100_000);
+		let (swap, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		assert_eq!(swap.lock_slate.fee, fee);
+		assert_eq!(swap.unwrap_seller().unwrap().1, change);
+		let outputs: Vec<Commitment> = swap
+			.lock_slate
+			.tx
+			.outputs()
+			.iter()
+			.map(|o| o.commit.clone())
+			.collect();
+		let expected = vec![
+			kc_sell
+				.commit(
+					change - split_amount,
+					&key_id(0, 3),
+					SwitchCommitmentType::Regular,
+				)
+				.unwrap(),
+			kc_sell
+				.commit(split_amount, &key_id(0, 6), SwitchCommitmentType::Regular)
+				.unwrap(),
+		];
+		assert_eq!(outputs.len(), expected.len());
+		assert!(expected.iter().all(|c| outputs.contains(c)));
+		assert_eq!(
+			ctx_sell
+				.derivation_paths()
+				.iter()
+				.filter(|p| p.usage == "change_output")
+				.count(),
+			2
+		);
+
+		// Buyer accepts the lock slate with the extra output and its fee
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap_buy =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+		assert_eq!(swap_buy.lock_slate.fee, fee);
+	}
+
+	#[test]
+	#[serial]
+	fn test_replay_messages() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(100_000);
+		let (swap, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		// Recording the buyer messages
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let ack = Message::new(
+			swap.id,
+			Update::MessageAcknowledge(1),
+			SecondaryUpdate::Empty,
+		);
+		let (id, offer, secondary_update) = message.clone().unwrap_offer().unwrap();
+		let mut original =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+		original.ack_msg1();
+
+		let replayed =
+			Swap::replay_messages(&[message.clone(), ack.clone()], &kc_buy, &ctx_buy, &nc).unwrap();
+		assert_eq!(
+			serde_json::to_string_pretty(&replayed).unwrap(),
+			serde_json::to_string_pretty(&original).unwrap()
+		);
+
+		// Replay must start from the offer
+		assert!(Swap::replay_messages(&[ack, message], &kc_buy, &ctx_buy, &nc).is_err());
+	}
+
+	#[test]
+	#[serial]
+	fn test_redeem_fee_base() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(100_000);
+		let (mut swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let mut default_swap = BuyApi::accept_swap_offer(
+			&kc_buy,
+			&ctx_buy,
+			id,
+			offer.clone(),
+			secondary_update.clone(),
+			&nc,
+		)
+		.unwrap();
+		let mut fast_swap =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+
+		let default_fee = tx_fee(1, 1, 1, None);
+		let elevated_fee = default_fee * 5;
+		fast_swap.set_redeem_fee(elevated_fee).unwrap();
+		assert_eq!(default_swap.redeem_fee(), default_fee);
+		assert_eq!(fast_swap.redeem_fee(), elevated_fee);
+
+		// Only the Buyer pays the redeem fee, and it must leave something to redeem
+		assert!(swap_sell.set_redeem_fee(elevated_fee).is_err());
+		assert!(fast_swap.set_redeem_fee(100 * GRIN_UNIT).is_err());
+		assert_eq!(fast_swap.redeem_fee(), elevated_fee);
+
+		BuyApi::init_redeem(&kc_buy, &mut default_swap, &ctx_buy).unwrap();
+		BuyApi::init_redeem(&kc_buy, &mut fast_swap, &ctx_buy).unwrap();
+
+		// Built redeem transaction keeps its fee
+		assert!(fast_swap.set_redeem_fee(default_fee).is_err());
+
+		assert_eq!(default_swap.redeem_slate.fee, default_fee);
+		assert_eq!(
+			default_swap.redeem_slate.amount,
+			100 * GRIN_UNIT - default_fee
+		);
+		assert_eq!(fast_swap.redeem_slate.fee, elevated_fee);
+		assert_eq!(
+			fast_swap.redeem_slate.amount,
+			100 * GRIN_UNIT - elevated_fee
+		);
+		assert_eq!(
+			default_swap.redeem_slate.amount - fast_swap.redeem_slate.amount,
+			elevated_fee - default_fee
+		);
+
+		// The fee must leave something to redeem
+		let mut broken_swap = default_swap.clone();
+		broken_swap.redeem_slate = Slate::blank(2);
+		broken_swap.adaptor_signature = None;
+		broken_swap.redeem_fee_base = Some(100 * GRIN_UNIT);
+		assert!(BuyApi::init_redeem(&kc_buy, &mut broken_swap, &ctx_buy).is_err());
+	}
+
+	#[test]
+	#[serial]
+	fn test_zero_fee_redeem() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (mut swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let mut swap_buy =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+		let (_, accept_offer, _) = BuyApi::accept_offer_message(&swap_buy, SecondaryUpdate::Empty)
+			.unwrap()
+			.unwrap_accept_offer()
+			.unwrap();
+		SellApi::accepted_offer(&kc_sell, &mut swap_sell, &ctx_sell, accept_offer).unwrap();
+		swap_buy.redeem_fee_base = Some(0);
+
+		// Zero fee is not allowed on the public networks
+		global::set_mining_mode(ChainTypes::Floonet);
+		assert!(!swap::is_zero_fee_allowed());
+		let mut rejected = swap_buy.clone();
+		assert_eq!(
+			BuyApi::init_redeem(&kc_buy, &mut rejected, &ctx_buy).err(),
+			Some(ErrorKind::ZeroFeeNotAllowed(
+				"MWC redeem".to_string(),
+				swap_buy.network
+			))
+		);
+
+		// Testing chain permits zero fee
+		global::set_mining_mode(ChainTypes::AutomatedTesting);
+		assert!(swap::is_zero_fee_allowed());
+		BuyApi::init_redeem(&kc_buy, &mut swap_buy, &ctx_buy).unwrap();
+		assert_eq!(swap_buy.redeem_slate.fee, 0);
+		assert_eq!(swap_buy.redeem_slate.amount, swap_buy.primary_amount);
+		match swap_buy.redeem_slate.tx.body.kernels[0].features {
+			KernelFeatures::Plain { fee } => assert_eq!(fee, 0),
+			f => panic!("Unexpected kernel features {:?}", f),
+		}
+		let (_, init_redeem, _) = BuyApi::init_redeem_message(&swap_buy)
+			.unwrap()
+			.unwrap_init_redeem()
+			.unwrap();
+
+		// Seller doesn't accept the zero fee redeem if the chain doesn't permit it
+		global::set_mining_mode(ChainTypes::Floonet);
+		assert_eq!(
+			SellApi::init_redeem(&kc_sell, &mut swap_sell, &ctx_sell, init_redeem).err(),
+			Some(ErrorKind::ZeroFeeNotAllowed(
+				"MWC redeem".to_string(),
+				swap_sell.network
+			))
+		);
+		assert!(swap_sell.adaptor_signature.is_none());
+	}
+
+	#[test]
+	#[serial]
+	fn test_completion_likelihood() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (mut swap, _message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		let refund_height = swap.refund_slate.lock_height;
+
+		let secondary_amount = swap.secondary_amount;
+
+		let conf =
+			|tip: u64, lock: Option<u64>, redeem: Option<u64>| SwapTransactionsConfirmations {
+				mwc_tip: tip,
+				mwc_lock_conf: lock,
+				mwc_redeem_conf: None,
+				mwc_refund_conf: None,
+				secondary_tip: 1000,
+				secondary_lock_conf: lock,
+				secondary_lock_amount: secondary_amount,
+				secondary_redeem_conf: redeem,
+				secondary_refund_conf: None,
+			};
+
+		// Deeply confirmed redeem
+		swap.state = StateId::SellerWaitingForRedeemConfirmations;
+		let redeemed = swap.completion_likelihood(&conf(refund_height - 100, Some(100), Some(100)));
+		assert!(redeemed > 0.99);
+		let redeem_mempool =
+			swap.completion_likelihood(&conf(refund_height - 100, Some(100), Some(0)));
+		assert!(redeem_mempool < redeemed);
+		assert!(redeem_mempool >= 0.9);
+
+		// Early stage: nothing is locked yet
+		swap.state = StateId::SellerWaitingForBuyerLock;
+		let early = swap.completion_likelihood(&conf(refund_height - 1000, None, None));
+		assert!(early < 0.5);
+		let locked = swap.completion_likelihood(&conf(refund_height - 1000, Some(100), None));
+		assert!(locked > early);
+		assert!(locked < redeem_mempool);
+
+		// Stalled past the refund height, the likelihood drops sharply
+		let stalled = swap.completion_likelihood(&conf(refund_height + 1, None, None));
+		assert!(stalled < early / 5.0);
+		let closing = swap.completion_likelihood(&conf(refund_height - 5, Some(100), None));
+		assert!(closing < locked);
+		assert!(closing > stalled);
+
+		// Final states
+		swap.state = StateId::SellerSwapComplete;
+		assert!(swap.completion_likelihood(&conf(refund_height + 1, None, None)) >= 1.0);
+		swap.state = StateId::SellerWaitingForRefundHeight;
+		assert_eq!(
+			swap.completion_likelihood(&conf(refund_height - 1000, Some(100), None)),
+			0.0
+		);
+	}
+
+	#[test]
+	#[serial]
+	fn test_reserved_key_ids() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap_buy =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+
+		let buyer_ctx = ctx_buy.unwrap_buyer().unwrap();
+		let reserved = swap_buy.reserved_key_ids(&ctx_buy);
+		assert!(reserved.contains(&ctx_buy.multisig_key));
+		assert!(reserved.contains(&buyer_ctx.output));
+		assert!(reserved.contains(&buyer_ctx.redeem));
+		assert!(reserved.contains(&buyer_ctx.unwrap_btc().unwrap().refund));
+		assert_eq!(reserved.len(), 4);
+
+		let seller_ctx = ctx_sell.unwrap_seller().unwrap();
+		let reserved = swap_sell.reserved_key_ids(&ctx_sell);
+		assert!(reserved.contains(&ctx_sell.multisig_key));
+		assert!(reserved.contains(&seller_ctx.change_output));
+		assert!(reserved.contains(&seller_ctx.refund_output));
+		assert!(reserved.contains(&seller_ctx.unwrap_btc().unwrap().cosign));
+		// Lock inputs are the existing outputs, they are not reserved
+		for (input, _, _) in &seller_ctx.inputs {
+			assert!(!reserved.contains(input));
+		}
+
+		// The second change output of the split round change is reserved too
+		let mut ctx_split = ctx_sell.clone();
+		let split_key = key_id(0, 6);
+		if let RoleContext::Seller(c) = &mut ctx_split.role_context {
+			c.change_split = Some((split_key.clone(), 1000));
+		}
+		let reserved = swap_sell.reserved_key_ids(&ctx_split);
+		assert!(reserved.contains(&split_key));
+		assert_eq!(reserved.len(), 5);
+	}
+
+	#[test]
+	#[serial]
+	fn test_context_nonces_distinct() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		assert!(ctx_sell.validate_nonces().is_ok());
+		let nc = TestNodeClient::new(300_000);
+		let (mut swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		// Seller can't start the swap with the repeated nonce
+		let mut bad_ctx_sell = ctx_sell.clone();
+		bad_ctx_sell.lock_nonce = bad_ctx_sell.multisig_nonce.clone();
+		let err = ErrorKind::DuplicateContextNonce("multisig".to_string(), "lock".to_string());
+		assert_eq!(bad_ctx_sell.validate_nonces().err(), Some(err.clone()));
+		let mut api_sell = BtcSwapApi::new_test(
+			Arc::new(nc.clone()),
+			Arc::new(Mutex::new(TestBtcNodeClient::new(1))),
+		);
+		let res = api_sell.create_swap_offer(
+			&kc_sell,
+			&bad_ctx_sell,
+			100 * GRIN_UNIT,
+			3_000_000,
+			Currency::Btc,
+			btc_address(&kc_sell),
+			true,
+			30,
+			3,
+			3600,
+			3600,
+			"file".to_string(),
+			"/tmp/del.me".to_string(),
+			None,
+			None,
+			None,
+			&swap::SwapConfig::default(),
+		);
+		assert_eq!(res.err(), Some(err.clone()));
+
+		// And can't process it
+		let mut fsm_sell = api_sell.get_fsm(&kc_sell, &swap_sell);
+		let tx_state = api_sell
+			.request_tx_confirmations(&kc_sell, &mut swap_sell)
+			.unwrap();
+		let state = swap_sell.state.clone();
+		assert_eq!(
+			fsm_sell
+				.process(Input::Check, &mut swap_sell, &bad_ctx_sell, &tx_state)
+				.err(),
+			Some(err)
+		);
+		assert_eq!(swap_sell.state, state);
+
+		// Buyer doesn't accept the offer with the redeem nonce that is used for the refund
+		let kc_buy = keychain(2);
+		let mut ctx_buy = context_buy(&kc_buy);
+		ctx_buy.redeem_nonce = ctx_buy.refund_nonce.clone();
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		assert_eq!(
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).err(),
+			Some(ErrorKind::DuplicateContextNonce(
+				"refund".to_string(),
+				"redeem".to_string()
+			))
+		);
+	}
+
+	#[test]
+	#[serial]
+	fn test_parse_offer_formats() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		let expected = serde_json::to_value(message.clone().unwrap_offer().unwrap()).unwrap();
+
+		// The same offer from the file JSON blob and from the chat armored text
+		let json = message.to_json().unwrap();
+		let armored = message.to_armored().unwrap();
+		assert!(armored.starts_with("BEGINSWAPMESSAGE.\n"));
+		assert_eq!(
+			message::MessageFormat::detect(&json),
+			Some(message::MessageFormat::Json)
+		);
+		assert_eq!(
+			message::MessageFormat::detect(&armored),
+			Some(message::MessageFormat::Armored)
+		);
+		let from_json = message::parse_offer(json.as_bytes()).unwrap();
+		let from_armored = message::parse_offer(armored.as_bytes()).unwrap();
+		assert_eq!(serde_json::to_value(&from_json).unwrap(), expected);
+		assert_eq!(serde_json::to_value(&from_armored).unwrap(), expected);
+
+		// MQS envelope is not trusted without the listener signature check
+		let escaped = json
+			.replace("{", "%7B")
+			.replace("}", "%7D")
+			.replace(":", "%3A")
+			.replace(",", "%2C")
+			.replace("\"", "%22");
+		let framed = format!("swapmessage={}&from=xmj6hXXZ&signature=30440220", escaped);
+		assert_eq!(message::MessageFormat::detect(&framed), None);
+		match message::parse_offer(framed.as_bytes()) {
+			Err(ErrorKind::UnknownMessageFormat(_)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+
+		// Unknown formats and non offer messages are rejected
+		match message::parse_offer(b"offer from the seller") {
+			Err(ErrorKind::UnknownMessageFormat(_)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+		match message::parse_offer(b"BEGINSWAPMESSAGE.\nnot base64\nENDSWAPMESSAGE.") {
+			Err(ErrorKind::UnknownMessageFormat(_)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+		let ack = Message::new(
+			swap_sell.id,
+			Update::MessageAcknowledge(1),
+			SecondaryUpdate::Empty,
+		);
+		match message::parse_offer(ack.to_armored().unwrap().as_bytes()) {
+			Err(ErrorKind::UnexpectedMessageType(_)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+
+		// Buyer accepts the offer regardless of how it was received
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = from_armored;
+		let swap_buy =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+		assert_eq!(swap_buy.id, swap_sell.id);
+	}
+
+	#[test]
+	#[serial]
+	fn test_lock_fee_proof_size() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(100_000);
+		let (mut swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		// Buyer signs the lock slate with a placeholder multisig proof
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap_buy =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+
+		// Seller finalizes the lock slate with the real multisig proof
+		let (_, accept_offer, _) = BuyApi::accept_offer_message(&swap_buy, SecondaryUpdate::Empty)
+			.unwrap()
+			.unwrap_accept_offer()
+			.unwrap();
+		SellApi::accepted_offer(&kc_sell, &mut swap_sell, &ctx_sell, accept_offer).unwrap();
+
+		let placeholder_tx = &swap_buy.lock_slate.tx;
+		let real_tx = &swap_sell.lock_slate.tx;
+		assert_eq!(placeholder_tx.outputs().len(), real_tx.outputs().len());
+
+		let multisig_commit = swap_sell.multisig.commit(kc_sell.secp()).unwrap();
+		let placeholder_proof_len = placeholder_tx
+			.outputs()
+			.iter()
+			.find(|o| o.commit == multisig_commit)
+			.unwrap()
+			.proof
+			.plen;
+		let real_proof_len = real_tx
+			.outputs()
+			.iter()
+			.find(|o| o.commit == multisig_commit)
+			.unwrap()
+			.proof
+			.plen;
+		assert_eq!(placeholder_proof_len, real_proof_len);
+		assert_eq!(
+			grin_core::ser::ser_vec(placeholder_tx, grin_core::ser::ProtocolVersion(1))
+				.unwrap()
+				.len(),
+			grin_core::ser::ser_vec(real_tx, grin_core::ser::ProtocolVersion(1))
+				.unwrap()
+				.len()
+		);
+
+		// Both parties agree on the fee, and it is what the network requires for the published tx
+		let placeholder_fee = swap::tx_required_fee(placeholder_tx).unwrap();
+		let real_fee = swap::tx_required_fee(real_tx).unwrap();
+		assert_eq!(placeholder_fee, real_fee);
+		assert_eq!(swap_sell.lock_slate.fee, real_fee);
+		assert_eq!(swap_buy.lock_slate.fee, placeholder_fee);
+		// Outputs with the full size proof are counted one by one
+		assert_eq!(
+			real_fee,
+			tx_fee(real_tx.inputs().len(), real_tx.outputs().len(), 1, None)
+		);
+
+		// Proof that is larger than possible can't be accounted
+		let mut broken_tx = real_tx.clone();
+		broken_tx.body.outputs[0].proof.plen = grin_util::secp::constants::MAX_PROOF_SIZE + 1;
+		assert!(swap::tx_required_fee(&broken_tx).is_err());
+	}
+
+	/// Seller creates the offer, returns the seller's swap and the offer message for the buyer
+	pub(crate) fn seller_offer_message(
+		kc_sell: &ExtKeychain,
+		ctx_sell: &Context,
+		nc: &TestNodeClient,
+	) -> (Swap, Message) {
+		let mut api_sell = BtcSwapApi::new_test(
+			Arc::new(nc.clone()),
+			Arc::new(Mutex::new(TestBtcNodeClient::new(1))),
+		);
+		let mut swap = api_sell
+			.create_swap_offer(
+				kc_sell,
+				ctx_sell,
+				100 * GRIN_UNIT,
+				3_000_000,
+				Currency::Btc,
+				btc_address(kc_sell),
+				true,
+				30,
+				3,
+				3600,
+				3600,
+				"file".to_string(),
+				"/tmp/del.me".to_string(),
+				None,
+				None,
+				None,
+				&swap::SwapConfig::default(),
+			)
+			.unwrap();
+		for input in swap.lock_slate.tx.inputs() {
+			nc.push_output(input.commit.clone());
+		}
+		let mut fsm_sell = api_sell.get_fsm(kc_sell, &swap);
+		let tx_state = api_sell
+			.request_tx_confirmations(kc_sell, &mut swap)
+			.unwrap();
+		let message = match fsm_sell
+			.process(Input::Check, &mut swap, ctx_sell, &tx_state)
+			.unwrap()
+			.action
+			.unwrap()
+		{
+			Action::SellerSendOfferMessage(message) => message,
+			_ => panic!("Unexpected action"),
+		};
+		(swap, message)
+	}
+
+	#[test]
+	#[serial]
+	fn test_adaptor_redeem_public_mismatch() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(100_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+
+		// Matching pair works
+		let mut good_swap = swap.clone();
+		BuyApi::init_redeem(&kc_buy, &mut good_swap, &ctx_buy).unwrap();
+		assert!(good_swap.adaptor_signature.is_some());
+
+		// redeem_public from the different secret
+		let mut bad_swap = swap.clone();
+		let other_secret = key(&kc_buy, 4, 0);
+		bad_swap.redeem_public =
+			Some(PublicKey::from_secret_key(kc_buy.secp(), &other_secret).unwrap());
+		assert_eq!(
+			BuyApi::init_redeem(&kc_buy, &mut bad_swap, &ctx_buy),
+			Err(ErrorKind::RedeemPublicMismatch)
+		);
+		assert!(bad_swap.adaptor_signature.is_none());
+	}
+
+	#[test]
+	#[serial]
+	fn test_secondary_confirmations_batch() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(100_000);
+		let (swap, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let btc_nc = TestBtcNodeClient::new(500);
+		let api = BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+
+		let new_tx = |value: u64| BtcTransaction {
+			version: 2,
+			lock_time: 0,
+			input: vec![],
+			output: vec![TxOut {
+				value,
+				script_pubkey: Default::default(),
+			}],
+		};
+		// mined at 500, mined at 502, in mempool, unknown to the node
+		let tx_mined_early = new_tx(1);
+		btc_nc.push_transaction(&tx_mined_early);
+		btc_nc.mine_blocks(2);
+		let tx_mined_late = new_tx(2);
+		btc_nc.push_transaction(&tx_mined_late);
+		btc_nc.mine_blocks(3);
+		let tx_pending = new_tx(3);
+		btc_nc.post_transaction(&tx_pending);
+		let tx_unknown = new_tx(4);
+
+		let with_txs = |redeem: Option<&BtcTransaction>, refund: Option<&BtcTransaction>| {
+			let mut s = swap.clone();
+			let btc_data = s.secondary_data.unwrap_btc_mut().unwrap();
+			btc_data.redeem_tx = redeem.map(|t| t.txid());
+			btc_data.refund_tx = refund.map(|t| t.txid());
+			s
+		};
+		let swaps = vec![
+			with_txs(None, None),
+			with_txs(Some(&tx_mined_early), None),
+			with_txs(Some(&tx_pending), Some(&tx_mined_late)),
+			with_txs(Some(&tx_unknown), Some(&tx_mined_early)),
+		];
+
+		let batch = api
+			.request_secondary_confirmations_batch(
+				btc_nc.state.lock().height,
+				&swaps.iter().collect::<Vec<&Swap>>(),
+			)
+			.unwrap();
+		assert_eq!(
+			batch,
+			vec![
+				(None, None),
+				(Some(6), None),
+				(Some(0), Some(4)),
+				(None, Some(6))
+			]
+		);
+
+		// Swap refresh gets the redeem and refund confirmations with a single batch
+		let conf = api.request_tx_confirmations(&kc_sell, &swaps[2]).unwrap();
+		assert_eq!(
+			(conf.secondary_redeem_conf, conf.secondary_refund_conf),
+			(Some(0), Some(4))
+		);
+
+		// Refresh of all swaps requests the transactions of all of them at once
+		btc_nc.state.lock().transactions_calls = 0;
+		let confs = api
+			.request_tx_confirmations_batch(&kc_sell, &swaps.iter().collect::<Vec<&Swap>>())
+			.unwrap();
+		assert_eq!(btc_nc.state.lock().transactions_calls, 1);
+		assert_eq!(
+			confs
+				.iter()
+				.map(|c| (c.secondary_redeem_conf, c.secondary_refund_conf))
+				.collect::<Vec<_>>(),
+			batch
+		);
+
+		// Fallback to the single queries produce the same result
+		let hashes = vec![tx_mined_late.txid(), tx_unknown.txid(), tx_pending.txid()];
+		let mut client = btc_nc.clone();
+		let batched = client.transactions(&hashes).unwrap();
+		for (tx_hash, tx) in hashes.iter().zip(batched) {
+			assert_eq!(
+				tx.map(|(h, t)| (h, t.txid())),
+				client
+					.transaction(tx_hash)
+					.unwrap()
+					.map(|(h, t)| (h, t.txid()))
+			);
+		}
+	}
+
+	#[test]
+	#[serial]
+	fn test_export_redeem_secret() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(100_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let mut swap =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+
+		// Funds are still at risk
+		swap.state = StateId::BuyerRedeemMwc;
+		assert_eq!(
+			swap.export_redeem_secret(&kc_buy, &ctx_buy),
+			Err(ErrorKind::SecretNotYetReleasable(
+				StateId::BuyerRedeemMwc.to_string()
+			))
+		);
+
+		swap.state = StateId::BuyerSwapComplete;
+		let secret = swap.export_redeem_secret(&kc_buy, &ctx_buy).unwrap();
+		assert_eq!(secret, BuyApi::redeem_secret(&kc_buy, &ctx_buy).unwrap());
+		assert_eq!(
+			Some(PublicKey::from_secret_key(kc_buy.secp(), &secret).unwrap()),
+			swap.redeem_public
+		);
+	}
+
+	// Create the seller with the offer and the buyer that accepted it
+	fn lock_ordering_traders<'a>(
+		api_sell: &'a BtcSwapApi<'a, TestNodeClient, TestBtcNodeClient>,
+		swap_sell: Swap,
+		api_buy: &'a BtcSwapApi<'a, TestNodeClient, TestBtcNodeClient>,
+		nc: &TestNodeClient,
+	) -> (Trader<'a>, Trader<'a>) {
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let fsm_sell = api_sell.get_fsm(&kc_sell, &swap_sell);
+		let mut seller = Trader {
+			api: api_sell,
+			swap: swap_sell,
+			fsm: fsm_sell,
+			kc: kc_sell,
+			ctx: ctx_sell,
+			swap_stack: Vec::new(),
+		};
+
+		nc.mine_blocks(2);
+		for input in seller.swap.lock_slate.tx.inputs() {
+			nc.push_output(input.commit.clone());
+		}
+
+		swap::set_testing_cur_time(START_TIME + 20);
+		let message = match seller.process(Input::Check).unwrap().action.unwrap() {
+			Action::SellerSendOfferMessage(m) => m,
+			_ => panic!("Unexpected action"),
+		};
+		seller.process(Input::Execute).unwrap();
+
+		swap::set_testing_cur_time(START_TIME + 100);
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap_buy =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, nc).unwrap();
+		let fsm_buy = api_buy.get_fsm(&kc_buy, &swap_buy);
+		let buyer = Trader {
+			api: api_buy,
+			swap: swap_buy,
+			fsm: fsm_buy,
+			kc: kc_buy,
+			ctx: ctx_buy,
+			swap_stack: Vec::new(),
+		};
+		(seller, buyer)
+	}
+
+	fn lock_ordering_offer(
+		api_sell: &mut BtcSwapApi<TestNodeClient, TestBtcNodeClient>,
+		seller_lock_first: bool,
+	) -> Swap {
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		api_sell
+			.create_swap_offer(
+				&kc_sell,
+				&ctx_sell,
+				100 * GRIN_UNIT,
+				3_000_000,
+				Currency::Btc,
+				btc_address(&kc_sell),
+				seller_lock_first,
+				MWC_CONFIRMATION,
+				BTC_CONFIRMATION,
+				MSG_EXCHANGE_TIME as u64,
+				REDEEM_TIME as u64,
+				"file".to_string(),
+				"/tmp/del.me".to_string(),
+				None,
+				None,
+				None,
+				&swap::SwapConfig::default(),
+			)
+			.unwrap()
+	}
+
+	// Process a single step for the trader and perform the requested action. Returns the action id.
+	fn lock_ordering_step(
+		trader: &mut Trader,
+		other: &mut Trader,
+		btc_nc: &TestBtcNodeClient,
+	) -> Option<String> {
+		let action = trader.process(Input::Check).unwrap().action?;
+		match &action {
+			Action::SellerSendOfferMessage(m)
+			| Action::BuyerSendAcceptOfferMessage(m)
+			| Action::BuyerSendInitRedeemMessage(m)
+			| Action::SellerSendRedeemMessage(m) => {
+				other.process(Input::IncomeMessage(m.clone())).unwrap();
+				trader.process(Input::Execute).unwrap();
+			}
+			Action::SellerPublishMwcLockTx
+			| Action::SellerPublishTxSecondaryRedeem(_)
+			| Action::BuyerPublishMwcRedeemTx => {
+				trader.process(Input::Execute).unwrap();
+			}
+			Action::DepositSecondary {
+				currency,
+				amount,
+				address,
+			} => {
+				btc_nc.post_transaction(&BtcTransaction {
+					version: 2,
+					lock_time: 0,
+					input: vec![],
+					output: vec![TxOut {
+						value: *amount,
+						script_pubkey: currency.address_2_script_pubkey(address).unwrap(),
+					}],
+				});
+			}
+			_ => (),
+		}
+		Some(action.get_id_str())
+	}
+
+	// Drive both parties to the swap completion. Returns actions in the order they were requested.
+	fn drive_lock_ordering(seller_lock_first: bool) -> Vec<(bool, String)> {
+		drive_to_completion(seller_lock_first).0
+	}
+
+	// Drive both parties to the swap completion. Returns actions log, Seller and Buyer swaps.
+	fn drive_to_completion(seller_lock_first: bool) -> (Vec<(bool, String)>, Swap, Swap) {
+		set_test_mode(true);
+		swap::set_testing_cur_time(START_TIME);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let btc_nc = TestBtcNodeClient::new(500_000);
+		let mut api_sell =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let api_buy =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let swap_sell = lock_ordering_offer(&mut api_sell, seller_lock_first);
+		let (mut seller, mut buyer) = lock_ordering_traders(&api_sell, swap_sell, &api_buy, &nc);
+
+		let mut log: Vec<(bool, String)> = vec![(true, "SellerSendOfferMessage".to_string())];
+		for _ in 0..300 {
+			if seller.swap.state == StateId::SellerSwapComplete
+				&& buyer.swap.state == StateId::BuyerSwapComplete
+			{
+				break;
+			}
+			for is_seller in &[true, false] {
+				let action = if *is_seller {
+					lock_ordering_step(&mut seller, &mut buyer, &btc_nc)
+				} else {
+					lock_ordering_step(&mut buyer, &mut seller, &btc_nc)
+				};
+				if let Some(action) = action {
+					// Log the action once, it is requested while waiting for something
+					let last = log.iter().rev().find(|(s, _)| s == is_seller);
+					if last.map(|(_, a)| *a != action).unwrap_or(true) {
+						log.push((*is_seller, action));
+					}
+				}
+			}
+			nc.mine_block();
+			btc_nc.mine_block();
+		}
+		assert_eq!(seller.swap.state, StateId::SellerSwapComplete);
+		assert_eq!(buyer.swap.state, StateId::BuyerSwapComplete);
+		(log, seller.swap, buyer.swap)
+	}
+
+	fn lock_ordering_pos(log: &Vec<(bool, String)>, is_seller: bool, action: &str) -> usize {
+		log.iter()
+			.position(|(s, a)| *s == is_seller && a == action)
+			.unwrap_or_else(|| panic!("Action {} not found at {:?}", action, log))
+	}
+
+	#[test]
+	#[serial]
+	fn test_seller_lock_first_ordering() {
+		let log = drive_lock_ordering(true);
+		let mwc_lock = lock_ordering_pos(&log, true, "SellerPublishMwcLockTx");
+		let deposit = lock_ordering_pos(&log, false, "DepositSecondary");
+		// Seller - Receiver - Seller: MWC are locked first, buyer waits for them before deposit
+		assert!(mwc_lock < deposit);
+		assert!(lock_ordering_pos(&log, false, "WaitForMwcConfirmations") < deposit);
+		assert!(!log[..mwc_lock]
+			.iter()
+			.any(|(s, a)| *s && a == "WaitForSecondaryConfirmations"));
+		assert!(
+			lock_ordering_pos(&log, false, "BuyerSendAcceptOfferMessage")
+				< lock_ordering_pos(&log, false, "BuyerSendInitRedeemMessage")
+		);
+		assert!(deposit < lock_ordering_pos(&log, false, "BuyerPublishMwcRedeemTx"));
+		assert!(
+			lock_ordering_pos(&log, false, "BuyerPublishMwcRedeemTx")
+				< lock_ordering_pos(&log, true, "SellerPublishTxSecondaryRedeem")
+		);
+	}
+
+	#[test]
+	#[serial]
+	fn test_buyer_lock_first_ordering() {
+		let log = drive_lock_ordering(false);
+		let mwc_lock = lock_ordering_pos(&log, true, "SellerPublishMwcLockTx");
+		let deposit = lock_ordering_pos(&log, false, "DepositSecondary");
+		// Receiver - Seller - Receiver: BTC are deposited first, seller waits for them before locking
+		assert!(deposit < mwc_lock);
+		assert!(lock_ordering_pos(&log, true, "WaitForSecondaryConfirmations") < mwc_lock);
+		assert!(!log[..deposit]
+			.iter()
+			.any(|(s, a)| !*s && a == "WaitForMwcConfirmations"));
+		assert!(
+			lock_ordering_pos(&log, false, "BuyerSendAcceptOfferMessage")
+				< lock_ordering_pos(&log, false, "BuyerSendInitRedeemMessage")
+		);
+		assert!(mwc_lock < lock_ordering_pos(&log, false, "BuyerPublishMwcRedeemTx"));
+		assert!(
+			lock_ordering_pos(&log, false, "BuyerPublishMwcRedeemTx")
+				< lock_ordering_pos(&log, true, "SellerPublishTxSecondaryRedeem")
+		);
+	}
+
+	#[test]
+	#[serial]
+	fn test_safety_status_conflict() {
+		let (_, mut seller, mut buyer) = drive_to_completion(true);
+		let refund_height = seller.refund_slate.lock_height;
+		assert_eq!(buyer.refund_slate.lock_height, refund_height);
+		let nc = TestNodeClient::new(refund_height);
+
+		// Finished swap has nothing to race for
+		assert_eq!(seller.safety_status(&nc).unwrap(), SafetyAssessment::Safe);
+
+		// Redeem slate is signed by the Seller, refund height is reached. Both are valid.
+		seller.state = StateId::SellerWaitingForBuyerToRedeemMwc;
+		seller.redeem_kernel_updated = false;
+		assert_eq!(
+			seller.safety_status(&nc).unwrap(),
+			SafetyAssessment::Conflict
+		);
+		nc.state.lock().height = refund_height + 100;
+		assert_eq!(
+			seller.safety_status(&nc).unwrap(),
+			SafetyAssessment::Conflict
+		);
+
+		// Redeem is not signed yet, refund is the only option
+		let mut unsigned_seller = seller.clone();
+		unsigned_seller.redeem_slate.participant_data[unsigned_seller.participant_id].part_sig =
+			None;
+		assert_eq!(
+			unsigned_seller.safety_status(&nc).unwrap(),
+			SafetyAssessment::RefundNow
+		);
+
+		// Buyer redeem is found, the Seller can redeem the secondary
+		unsigned_seller.redeem_kernel_updated = true;
+		assert_eq!(
+			unsigned_seller.safety_status(&nc).unwrap(),
+			SafetyAssessment::Safe
+		);
+
+		// Nothing to race with if MWC are not locked
+		seller.posted_lock = None;
+		assert_eq!(
+			seller.safety_status(&nc).unwrap(),
+			SafetyAssessment::RefundNow
+		);
+
+		// Buyer race is the secondary refund against the secondary redeem. MWC refund height
+		// is passed, but the Buyer already posted the redeem.
+		buyer.state = StateId::BuyerRedeemMwc;
+		assert!(buyer.posted_redeem.is_some());
+		let refund_time = buyer.get_time_btc_lock_script();
+		swap::set_testing_cur_time(refund_time - 1);
+		assert_eq!(buyer.safety_status(&nc).unwrap(), SafetyAssessment::Safe);
+		swap::set_testing_cur_time(refund_time);
+		assert_eq!(
+			buyer.safety_status(&nc).unwrap(),
+			SafetyAssessment::Conflict
+		);
+
+		// Redeem is not posted, the secret is not known to the Seller
+		buyer.posted_redeem = None;
+		assert_eq!(
+			buyer.safety_status(&nc).unwrap(),
+			SafetyAssessment::RefundNow
+		);
+		swap::set_testing_cur_time(refund_time - 1);
+		nc.state.lock().height = refund_height - 10_000;
+		assert_eq!(buyer.safety_status(&nc).unwrap(), SafetyAssessment::Safe);
+	}
+
+	#[test]
+	#[serial]
+	fn test_bump_secondary_fee() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(START_TIME);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let btc_nc = TestBtcNodeClient::new(500_000);
+		let mut api_sell =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let api_buy =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let swap_sell = lock_ordering_offer(&mut api_sell, true);
+		let (mut seller, mut buyer) = lock_ordering_traders(&api_sell, swap_sell, &api_buy, &nc);
+
+		// Drive the trade until the Seller posts the BTC redeem, it is not mined yet
+		for _ in 0..300 {
+			lock_ordering_step(&mut seller, &mut buyer, &btc_nc);
+			if seller.swap.state == StateId::SellerWaitingForRedeemConfirmations {
+				break;
+			}
+			lock_ordering_step(&mut buyer, &mut seller, &btc_nc);
+			nc.mine_block();
+			btc_nc.mine_block();
+		}
+		assert_eq!(
+			seller.swap.state,
+			StateId::SellerWaitingForRedeemConfirmations
+		);
+
+		let redeem_tx = |trader: &Trader| {
+			let txid = trader
+				.swap
+				.secondary_data
+				.unwrap_btc()
+				.unwrap()
+				.redeem_tx
+				.unwrap();
+			btc_nc.get_state().pending.get(&txid).cloned().unwrap()
+		};
+		let old_tx = redeem_tx(&seller);
+		let locked = seller.swap.secondary_amount;
+		let old_rate = seller.swap.secondary_fee;
+		let old_fee = locked - old_tx.output[0].value;
+		let tx_size = (old_fee as f32 / old_rate).round();
+		let conf = seller._get_tx_conf().unwrap().secondary_redeem_conf;
+		assert_eq!(conf, Some(0));
+
+		// The fee must be higher and leave more than dust
+		assert!(seller.swap.bump_secondary_fee(old_rate, conf).is_err());
+		let dust_rate = (locked - 100) as f32 / tx_size;
+		match seller.swap.bump_secondary_fee(dust_rate, conf) {
+			Err(ErrorKind::DustOutput(_, value, limit)) => assert!(value < limit),
+			r => panic!("Unexpected result {:?}", r),
+		}
+		assert_eq!(seller.swap.secondary_fee, old_rate);
+
+		// Bumped redeem spends the same output with the higher fee
+		let new_rate = old_rate * 5.0;
+		seller.swap.bump_secondary_fee(new_rate, conf).unwrap();
+		let res = seller.process(Input::Check).unwrap();
+		assert_eq!(res.next_state_id, StateId::SellerRedeemSecondaryCurrency);
+		assert_eq!(
+			res.action.unwrap().get_id_str(),
+			"SellerPublishTxSecondaryRedeem"
+		);
+		seller.process(Input::Execute).unwrap();
+		let new_tx = redeem_tx(&seller);
+		assert_ne!(new_tx.txid(), old_tx.txid());
+		assert_eq!(
+			new_tx.input[0].previous_output,
+			old_tx.input[0].previous_output
+		);
+		assert_eq!(new_tx.output.len(), 1);
+		assert!(new_tx.output[0].value < old_tx.output[0].value);
+		assert_eq!(
+			locked - new_tx.output[0].value,
+			(tx_size * new_rate + 0.5) as u64
+		);
+
+		// Mined redeem can't be replaced
+		btc_nc.mine_block();
+		let conf = seller._get_tx_conf().unwrap().secondary_redeem_conf;
+		assert_eq!(conf, Some(1));
+		match seller.swap.bump_secondary_fee(new_rate * 2.0, conf) {
+			Err(ErrorKind::TxAlreadyConfirmed(_, 1)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+	}
+
+	#[test]
+	#[serial]
+	fn test_external_ref() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let nc = TestNodeClient::new(300_000);
+		let order = Some("order-42".to_string());
+		let other_order = Some("order-43".to_string());
+
+		// Seller without the reference adopts the one that Buyer proposed
+		let (mut swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		assert_eq!(message.external_ref, None);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap_buy = BuyApi::accept_swap_offer_with_external_ref(
+			&kc_buy,
+			&ctx_buy,
+			id,
+			offer,
+			secondary_update,
+			&nc,
+			order.clone(),
+			&swap::SwapConfig::default(),
+		)
+		.unwrap();
+		assert_eq!(swap_buy.external_ref, order);
+		let accept_message =
+			BuyApi::accept_offer_message(&swap_buy, SecondaryUpdate::Empty).unwrap();
+		assert_eq!(accept_message.external_ref, order);
+		swap_sell.check_external_ref(&accept_message).unwrap();
+		let (_, accept_offer, _) = accept_message.clone().unwrap_accept_offer().unwrap();
+		SellApi::accepted_offer(&kc_sell, &mut swap_sell, &ctx_sell, accept_offer).unwrap();
+		assert_eq!(swap_sell.external_ref, order);
+
+		// From now on every message must echo the reference
+		let mut bad_message = accept_message.clone();
+		bad_message.external_ref = other_order.clone();
+		assert_eq!(
+			swap_sell.check_external_ref(&bad_message).err(),
+			Some(ErrorKind::ExternalRefMismatch(
+				order.clone(),
+				other_order.clone()
+			))
+		);
+		// Older wallet doesn't support the reference, the message without it is fine
+		let mut old_message = accept_message.clone();
+		old_message.external_ref = None;
+		swap_sell.check_external_ref(&old_message).unwrap();
+		swap_buy.check_external_ref(&old_message).unwrap();
+
+		// Seller reference is a part of the offer, Buyer can't change it
+		let mut api_sell = BtcSwapApi::new_test(
+			Arc::new(nc.clone()),
+			Arc::new(Mutex::new(TestBtcNodeClient::new(1))),
+		);
+		let mut swap_sell = api_sell
+			.create_swap_offer(
+				&kc_sell,
+				&ctx_sell,
+				100 * GRIN_UNIT,
+				3_000_000,
+				Currency::Btc,
+				btc_address(&kc_sell),
+				true,
+				30,
+				3,
+				3600,
+				3600,
+				"file".to_string(),
+				"/tmp/del.me".to_string(),
+				None,
+				None,
+				order.clone(),
+				&swap::SwapConfig::default(),
+			)
+			.unwrap();
+		assert_eq!(swap_sell.external_ref, order);
+		for input in swap_sell.lock_slate.tx.inputs() {
+			nc.push_output(input.commit.clone());
+		}
+		let secondary_update =
+			api_sell.build_offer_message_secondary_update(&kc_sell, &mut swap_sell);
+		let message = SellApi::offer_message(&swap_sell, secondary_update).unwrap();
+		assert_eq!(message.external_ref, order);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		assert_eq!(
+			BuyApi::accept_swap_offer_with_external_ref(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer.clone(),
+				secondary_update.clone(),
+				&nc,
+				other_order.clone(),
+				&swap::SwapConfig::default(),
+			)
+			.err(),
+			Some(ErrorKind::ExternalRefMismatch(
+				order.clone(),
+				other_order.clone()
+			))
+		);
+		let swap_buy = BuyApi::accept_swap_offer_with_external_ref(
+			&kc_buy,
+			&ctx_buy,
+			id,
+			offer,
+			secondary_update,
+			&nc,
+			None,
+			&swap::SwapConfig::default(),
+		)
+		.unwrap();
+		assert_eq!(swap_buy.external_ref, order);
+
+		// Acceptance with another reference is rejected
+		let (_, mut accept_offer, _) =
+			BuyApi::accept_offer_message(&swap_buy, SecondaryUpdate::Empty)
+				.unwrap()
+				.unwrap_accept_offer()
+				.unwrap();
+		assert_eq!(accept_offer.external_ref, order);
+		accept_offer.external_ref = other_order.clone();
+		let mut old_accept_offer = accept_offer.clone();
+		assert_eq!(
+			SellApi::accepted_offer(&kc_sell, &mut swap_sell, &ctx_sell, accept_offer).err(),
+			Some(ErrorKind::ExternalRefMismatch(order.clone(), other_order))
+		);
+
+		// Older Buyer doesn't echo the reference, it stays with the Seller
+		old_accept_offer.external_ref = None;
+		SellApi::accepted_offer(&kc_sell, &mut swap_sell, &ctx_sell, old_accept_offer).unwrap();
+		assert_eq!(swap_sell.external_ref, order);
+	}
+
+	#[test]
+	#[serial]
+	fn test_completion_certificate() {
+		let (_, seller, buyer) = drive_to_completion(true);
+
+		let cert = seller.completion_certificate().unwrap();
+		assert_eq!(cert.swap_id, seller.id);
+		assert_eq!(cert.network, seller.network);
+		assert_eq!(cert.primary_amount, 100 * GRIN_UNIT);
+		assert_eq!(cert.secondary_amount, 3_000_000);
+		assert_eq!(cert.secondary_currency, Currency::Btc);
+		// MWC chain references, the redeem kernel is the one that the Buyer published
+		assert_eq!(
+			cert.mwc_lock_kernel,
+			to_hex(seller.lock_slate.tx.kernels()[0].excess.0.to_vec())
+		);
+		assert_eq!(
+			cert.mwc_redeem_kernel,
+			to_hex(buyer.redeem_slate.tx.kernels()[0].excess.0.to_vec())
+		);
+		// Secondary chain reference, the lock address is the hash of the lock script
+		assert_eq!(
+			cert.secondary_lock_address,
+			seller.get_secondary_lock_address().unwrap()
+		);
+		assert_eq!(
+			cert.secondary_lock_script,
+			seller.get_secondary_redeem_script().unwrap().0
+		);
+		assert_eq!(
+			cert.secondary_lock_address,
+			seller
+				.secondary_data
+				.unwrap_btc()
+				.unwrap()
+				.address(
+					Currency::Btc,
+					&bitcoin_lib::Script::from(
+						grin_util::from_hex(cert.secondary_lock_script.clone()).unwrap(),
+					),
+					seller.network,
+				)
+				.unwrap()
+		);
+		assert_eq!(
+			cert.secondary_redeem_tx,
+			seller
+				.secondary_data
+				.unwrap_btc()
+				.unwrap()
+				.redeem_tx
+				.unwrap()
+				.to_string()
+		);
+
+		// Certificate is a standalone artifact
+		let json = serde_json::to_string(&cert).unwrap();
+		let cert2: CompletionCertificate = serde_json::from_str(&json).unwrap();
+		assert_eq!(cert, cert2);
+
+		// Buyer doesn't publish the secondary redeem, the Seller has the full evidence
+		assert!(buyer.completion_certificate().is_err());
+		let mut active = seller.clone();
+		active.state = StateId::SellerWaitingForRedeemConfirmations;
+		assert!(active.completion_certificate().is_err());
+	}
+
+	#[test]
+	#[serial]
+	fn test_buyer_lock_first_spent_seller_inputs() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(START_TIME);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let btc_nc = TestBtcNodeClient::new(500_000);
+		let mut api_sell =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let api_buy =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let swap_sell = lock_ordering_offer(&mut api_sell, false);
+		let (_seller, mut buyer) = lock_ordering_traders(&api_sell, swap_sell, &api_buy, &nc);
+
+		let res = buyer.process(Input::Check).unwrap();
+		assert_eq!(res.next_state_id, StateId::BuyerSendingAcceptOfferMessage);
+
+		// Seller spent the lock inputs after the offer was accepted. Buyer must not deposit.
+		buyer.pushs();
+		for input in buyer.swap.lock_slate.tx.inputs() {
+			nc.state.lock().outputs.remove(&input.commit);
+		}
+		let res = buyer.process(Input::Execute).unwrap();
+		assert_eq!(res.next_state_id, StateId::BuyerCancelled);
+		buyer.pops();
+
+		// Inputs are still there, the Buyer can deposit
+		let res = buyer.process(Input::Execute).unwrap();
+		assert_eq!(
+			res.next_state_id,
+			StateId::BuyerPostingSecondaryToMultisigAccount
+		);
+		assert_eq!(res.action.unwrap().get_id_str(), "DepositSecondary");
+	}
+
+	#[test]
+	#[serial]
+	fn test_seller_lock_rejected() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(START_TIME);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let btc_nc = TestBtcNodeClient::new(500_000);
+		let mut api_sell =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let api_buy =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let swap_sell = lock_ordering_offer(&mut api_sell, true);
+		let (mut seller, mut buyer) = lock_ordering_traders(&api_sell, swap_sell, &api_buy, &nc);
+
+		assert_eq!(
+			lock_ordering_step(&mut buyer, &mut seller, &btc_nc),
+			Some("BuyerSendAcceptOfferMessage".to_string())
+		);
+		let res = seller.process(Input::Check).unwrap();
+		assert_eq!(res.action.unwrap().get_id_str(), "SellerPublishMwcLockTx");
+
+		// Node is not reachable, the lock is not rejected. The error goes to the caller,
+		// the Seller stays in the lock state and posts again with the next processing.
+		for error in &[
+			crate::ErrorKind::NodeTimeout("no respond".to_string()),
+			crate::ErrorKind::ClientCallback("Connection refused".to_string()),
+		] {
+			nc.state.lock().post_tx_error = Some(error.clone());
+			match seller.process(Input::Execute).unwrap_err() {
+				ErrorKind::NodeTimeout(_) | ErrorKind::LibWallet(_) => (),
+				e => panic!("Unexpected error {}", e),
+			}
+			assert_eq!(seller.swap.state, StateId::SellerPostingLockMwcSlate);
+			assert!(seller.swap.posted_lock.is_none());
+		}
+		nc.state.lock().post_tx_error = None;
+
+		// Lock inputs are spent by something else, node rejects the lock
+		let inputs: Vec<Commitment> = seller
+			.swap
+			.lock_slate
+			.tx
+			.inputs()
+			.iter()
+			.map(|i| i.commit.clone())
+			.collect();
+		for commit in &inputs {
+			nc.state.lock().outputs.remove(commit);
+		}
+		let res = seller.process(Input::Execute).unwrap();
+		assert_eq!(res.next_state_id, StateId::SellerLockFailed);
+		let action = res.action.unwrap();
+		assert_eq!(action.get_id_str(), "SellerRetryMwcLockTx");
+		assert!(action.can_execute());
+		assert!(seller.swap.posted_lock.is_none());
+		assert!(seller
+			.swap
+			.journal
+			.last()
+			.unwrap()
+			.message
+			.starts_with("MWC lock transaction is rejected by the node"));
+		assert!(seller.is_cancellable());
+
+		// Retry is still rejected
+		let res = seller.process(Input::Execute).unwrap();
+		assert_eq!(res.next_state_id, StateId::SellerLockFailed);
+		assert!(seller.swap.posted_lock.is_none());
+
+		// Retry succeeds once the node accepts the lock
+		seller.pushs();
+		for commit in &inputs {
+			nc.push_output(commit.clone());
+		}
+		let res = seller.process(Input::Execute).unwrap();
+		assert_eq!(
+			res.next_state_id,
+			StateId::SellerWaitingForLockConfirmations
+		);
+		assert!(seller.swap.posted_lock.is_some());
+		seller.pops();
+
+		// Or the Seller cancels, nothing was locked
+		let res = seller.process(Input::Cancel).unwrap();
+		assert_eq!(res.next_state_id, StateId::SellerCancelled);
+	}
+
+	#[test]
+	#[serial]
+	fn test_lock_order_enforced() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(START_TIME);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let btc_nc = TestBtcNodeClient::new(500_000);
+		let mut api_sell =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let api_buy =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let swap_sell = lock_ordering_offer(&mut api_sell, false);
+		let (mut seller, mut buyer) = lock_ordering_traders(&api_sell, swap_sell, &api_buy, &nc);
+
+		assert_eq!(
+			lock_ordering_step(&mut buyer, &mut seller, &btc_nc),
+			Some("BuyerSendAcceptOfferMessage".to_string())
+		);
+		assert_eq!(seller.swap.state, StateId::SellerWaitingForBuyerLock);
+
+		// Buyer is locking first, Seller is prompted to wait
+		assert_eq!(
+			lock_ordering_step(&mut seller, &mut buyer, &btc_nc),
+			Some("WaitForSecondaryConfirmations".to_string())
+		);
+		assert!(!seller.swap.is_lock_allowed(&seller._get_tx_conf().unwrap()));
+
+		// Even if the Seller gets to the lock state out of turn, MWC are not published
+		seller.pushs();
+		seller.swap.state = StateId::SellerPostingLockMwcSlate;
+		let res = seller.process(Input::Execute).unwrap();
+		assert_eq!(res.next_state_id, StateId::SellerWaitingForBuyerLock);
+		assert!(seller.swap.posted_lock.is_none());
+		seller.swap.state = StateId::SellerPostingLockMwcSlate;
+		let res = seller.process(Input::Check).unwrap();
+		assert_eq!(res.next_state_id, StateId::SellerWaitingForBuyerLock);
+		assert_eq!(
+			res.action.unwrap().get_id_str(),
+			"WaitForSecondaryConfirmations"
+		);
+		seller.pops();
+
+		assert!(buyer.swap.is_lock_allowed(&buyer._get_tx_conf().unwrap()));
+		assert_eq!(
+			lock_ordering_step(&mut buyer, &mut seller, &btc_nc),
+			Some("DepositSecondary".to_string())
+		);
+		assert!(!seller.swap.is_lock_allowed(&seller._get_tx_conf().unwrap()));
+		btc_nc.mine_block();
+
+		// Buyer funds are on the chain, now it is the Seller turn
+		assert!(seller.swap.is_lock_allowed(&seller._get_tx_conf().unwrap()));
+		assert_eq!(
+			lock_ordering_step(&mut seller, &mut buyer, &btc_nc),
+			Some("SellerPublishMwcLockTx".to_string())
+		);
+		assert!(seller.swap.posted_lock.is_some());
+
+		// With the opposite order the Buyer would have to wait for the MWC lock confirmation
+		buyer.swap.seller_lock_first = true;
+		assert!(!buyer.swap.is_lock_allowed(&buyer._get_tx_conf().unwrap()));
+		nc.mine_block();
+		assert!(buyer.swap.is_lock_allowed(&buyer._get_tx_conf().unwrap()));
+	}
+
+	#[test]
+	#[serial]
+	fn test_buyer_waits_for_seller_lock_confirmations() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(START_TIME);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let btc_nc = TestBtcNodeClient::new(500_000);
+		let mut api_sell =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let api_buy =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let swap_sell = lock_ordering_offer(&mut api_sell, true);
+		let (mut seller, mut buyer) = lock_ordering_traders(&api_sell, swap_sell, &api_buy, &nc);
+
+		assert_eq!(
+			lock_ordering_step(&mut buyer, &mut seller, &btc_nc),
+			Some("BuyerSendAcceptOfferMessage".to_string())
+		);
+		assert_eq!(
+			lock_ordering_step(&mut seller, &mut buyer, &btc_nc),
+			Some("SellerPublishMwcLockTx".to_string())
+		);
+		assert!(seller.swap.posted_lock.is_some());
+
+		// MWC lock is not on the chain yet, the Buyer is held in the wait state
+		assert_eq!(buyer.swap.first_lock_required_confirmations(), 1);
+		let time_limit = buyer.swap.get_time_start_lock();
+		swap::set_testing_cur_time(START_TIME + 60);
+		let res = buyer.process(Input::Check).unwrap();
+		assert_eq!(res.next_state_id, StateId::BuyerWaitingForSellerToLock);
+		assert_eq!(res.time_limit, Some(time_limit));
+		match res.action.unwrap() {
+			Action::WaitForMwcConfirmations {
+				required, actual, ..
+			} => {
+				assert_eq!(required, 1);
+				assert_eq!(actual, 0);
+			}
+			a => panic!("Unexpected action {:?}", a),
+		}
+		assert!(!buyer.swap.is_lock_allowed(&buyer._get_tx_conf().unwrap()));
+
+		// Without the lock on the chain the Buyer cancels at the deadline
+		buyer.pushs();
+		buyer.swap.ack_msg1();
+		swap::set_testing_cur_time(time_limit + 1);
+		let res = buyer.process(Input::Check).unwrap();
+		assert_eq!(res.next_state_id, StateId::BuyerCancelled);
+		buyer.pops();
+
+		// The next block puts the lock on the chain, the Buyer doesn't wait for the
+		// confirmations from the offer
+		nc.mine_block();
+		swap::set_testing_cur_time(START_TIME + 120);
+		assert!(buyer.swap.is_lock_allowed(&buyer._get_tx_conf().unwrap()));
+		assert_eq!(
+			lock_ordering_step(&mut buyer, &mut seller, &btc_nc),
+			Some("DepositSecondary".to_string())
+		);
+		assert_eq!(
+			buyer.swap.state,
+			StateId::BuyerPostingSecondaryToMultisigAccount
+		);
+	}
+
+	#[test]
+	#[serial]
+	fn test_tip_and_kernels_batch() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(START_TIME);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let btc_nc = TestBtcNodeClient::new(500_000);
+		let mut api_sell =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let api_buy =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let swap_sell = lock_ordering_offer(&mut api_sell, true);
+		let (mut seller, mut buyer) = lock_ordering_traders(&api_sell, swap_sell, &api_buy, &nc);
+
+		assert_eq!(
+			lock_ordering_step(&mut buyer, &mut seller, &btc_nc),
+			Some("BuyerSendAcceptOfferMessage".to_string())
+		);
+		assert_eq!(
+			lock_ordering_step(&mut seller, &mut buyer, &btc_nc),
+			Some("SellerPublishMwcLockTx".to_string())
+		);
+		nc.mine_block();
+		nc.mine_block();
+
+		// Batched result matches the separate calls
+		let query = vec![
+			(
+				seller.swap.lock_slate.tx.kernels()[0].excess.clone(),
+				Some(seller.swap.lock_slate.height),
+			),
+			(Commitment::from_vec(vec![9; 33]), None),
+		];
+		let (tip, batched) = nc.get_tip_and_kernels(&query).unwrap();
+		assert_eq!(tip, nc.get_chain_tip().unwrap().0);
+		let separate: Vec<Option<(TxKernel, u64, u64)>> = query
+			.iter()
+			.map(|(excess, min_height)| nc.get_kernel(excess, *min_height, None).unwrap())
+			.collect();
+		assert_eq!(batched, separate);
+		assert!(batched[0].is_some());
+		assert!(batched[1].is_none());
+
+		// Confirmations poll makes a single MWC node request instead of the tip and a kernel per slate
+		{
+			let mut state = nc.state.lock();
+			state.tip_calls = 0;
+			state.kernel_calls = 0;
+			state.batch_calls = 0;
+		}
+		let tx_conf = seller._get_tx_conf().unwrap();
+		assert_eq!(tx_conf.mwc_tip, tip);
+		assert_eq!(tx_conf.mwc_lock_conf, Some(2));
+		let state = nc.state.lock();
+		assert_eq!(state.batch_calls, 1);
+		assert_eq!(state.tip_calls, 0);
+		assert_eq!(state.kernel_calls, 0);
+	}
+
+	#[test]
+	#[serial]
+	fn test_request_tx_confirmations_batch() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(START_TIME);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let btc_nc = TestBtcNodeClient::new(500_000);
+		let mut api_sell =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let api_buy =
+			BtcSwapApi::new_test(Arc::new(nc.clone()), Arc::new(Mutex::new(btc_nc.clone())));
+		let new_offer = lock_ordering_offer(&mut api_sell, true);
+		let swap_sell = lock_ordering_offer(&mut api_sell, true);
+		let (mut seller, mut buyer) = lock_ordering_traders(&api_sell, swap_sell, &api_buy, &nc);
+
+		assert_eq!(
+			lock_ordering_step(&mut buyer, &mut seller, &btc_nc),
+			Some("BuyerSendAcceptOfferMessage".to_string())
+		);
+		assert_eq!(
+			lock_ordering_step(&mut seller, &mut buyer, &btc_nc),
+			Some("SellerPublishMwcLockTx".to_string())
+		);
+		nc.mine_block();
+		nc.mine_block();
+
+		let swaps = vec![&seller.swap, &buyer.swap, &new_offer];
+		{
+			let mut state = nc.state.lock();
+			state.tip_calls = 0;
+			state.kernel_calls = 0;
+			state.batch_calls = 0;
+		}
+		let confs = api_sell
+			.request_tx_confirmations_batch(&keychain(1), &swaps)
+			.unwrap();
+		{
+			// The tip is requested once, together with the kernels of all swaps
+			let state = nc.state.lock();
+			assert_eq!(state.batch_calls, 1);
+			assert_eq!(state.tip_calls, 0);
+			assert_eq!(state.kernel_calls, 0);
+		}
+		let tip = nc.state.lock().height;
+		assert_eq!(confs.len(), 3);
+		assert!(confs.iter().all(|c| c.mwc_tip == tip));
+		assert_eq!(confs[0].mwc_lock_conf, Some(2));
+		assert_eq!(confs[1].mwc_lock_conf, Some(2));
+		assert_eq!(confs[2].mwc_lock_conf, None);
+
+		// The same values as the single swap requests
+		for (conf, trader) in confs.iter().zip(&[&seller, &buyer]) {
+			let single = trader._get_tx_conf().unwrap();
+			assert_eq!(conf.mwc_lock_conf, single.mwc_lock_conf);
+			assert_eq!(conf.mwc_redeem_conf, single.mwc_redeem_conf);
+			assert_eq!(conf.mwc_refund_conf, single.mwc_refund_conf);
+			assert_eq!(conf.secondary_tip, single.secondary_tip);
+			assert_eq!(conf.secondary_lock_conf, single.secondary_lock_conf);
+			assert_eq!(conf.secondary_lock_amount, single.secondary_lock_amount);
+		}
+
+		// New block updates all of them
+		nc.mine_block();
+		let confs = api_sell
+			.request_tx_confirmations_batch(&keychain(1), &swaps)
+			.unwrap();
+		assert_eq!(confs[0].mwc_lock_conf, Some(3));
+		assert_eq!(confs[1].mwc_lock_conf, Some(3));
+		assert_eq!(confs[2].mwc_tip, tip + 1);
+	}
+
+	#[test]
+	#[serial]
+	fn test_rebroadcast_status() {
+		use crate::swap::rebroadcast::{get_rebroadcast_status, PendingTx, RebroadcastStatus};
+
+		set_test_mode(true);
+		swap::set_testing_cur_time(START_TIME);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let (mut swap, _message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let conf = |redeem: Option<u64>, refund: Option<u64>| SwapTransactionsConfirmations {
+			mwc_tip: 1000,
+			mwc_lock_conf: Some(100),
+			mwc_redeem_conf: None,
+			mwc_refund_conf: None,
+			secondary_tip: 1000,
+			secondary_lock_conf: Some(100),
+			secondary_lock_amount: swap.secondary_amount,
+			secondary_redeem_conf: redeem,
+			secondary_refund_conf: refund,
+		};
+		let (pending, mempool, confirmed, refunded) = (
+			conf(None, None),
+			conf(Some(0), None),
+			conf(Some(1), None),
+			conf(None, Some(1)),
+		);
+
+		// Nothing to re-broadcast before the redeem is posted
+		assert_eq!(
+			get_rebroadcast_status(&swap, &pending),
+			RebroadcastStatus::NotPending
+		);
+		swap.state = StateId::SellerWaitingForRedeemConfirmations;
+		assert_eq!(
+			get_rebroadcast_status(&swap, &pending),
+			RebroadcastStatus::NotPending
+		);
+
+		swap.posted_redeem = Some(START_TIME);
+		let tx = PendingTx::SecondaryRedeem;
+		// Dropped from the mempool or still there - publish again
+		assert_eq!(
+			get_rebroadcast_status(&swap, &pending),
+			RebroadcastStatus::Pending(tx.clone())
+		);
+		assert_eq!(
+			get_rebroadcast_status(&swap, &mempool),
+			RebroadcastStatus::Pending(tx.clone())
+		);
+		assert_eq!(
+			get_rebroadcast_status(&swap, &confirmed),
+			RebroadcastStatus::Confirmed(tx.clone())
+		);
+		// Buyer refunded, the redeem is a double spend now
+		assert_eq!(
+			get_rebroadcast_status(&swap, &refunded),
+			RebroadcastStatus::Conflict(tx)
+		);
+		assert!(!get_rebroadcast_status(&swap, &mempool).is_final());
+		assert!(get_rebroadcast_status(&swap, &refunded).is_final());
+	}
+
+	#[test]
+	#[serial]
+	fn test_suggest_redeem_fee() {
+		let nc = TestNodeClient::new(300_000);
+		let config = swap::SwapConfig::default();
+		let min_fee = tx_fee(1, 1, 1, None);
+
+		// Node without mempool stats
+		assert_eq!(swap::suggest_redeem_fee(&nc, &config), min_fee);
+
+		// Nearly empty mempool
+		nc.state.lock().pool_size = Some(20);
+		assert_eq!(swap::suggest_redeem_fee(&nc, &config), min_fee);
+
+		// Congested mempool, needs several blocks to be mined
+		nc.state.lock().pool_size = Some(3500);
+		let fee = swap::suggest_redeem_fee(&nc, &config);
+		assert!(fee > min_fee);
+		assert_eq!(fee, min_fee * 4);
+
+		// Fee growth is limited
+		nc.state.lock().pool_size = Some(1_000_000);
+		assert_eq!(swap::suggest_redeem_fee(&nc, &config), min_fee * 10);
+		let mut cheap = config.clone();
+		cheap.max_redeem_fee_multiplier = 2;
+		assert_eq!(swap::suggest_redeem_fee(&nc, &cheap), min_fee * 2);
+	}
+
+	#[test]
+	#[serial]
+	fn test_expected_redeem_commitment() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let mut swap =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+
+		// Redeem slate is not built yet
+		assert!(swap.expected_redeem_commitment(&kc_buy, &ctx_buy).is_err());
+
+		BuyApi::init_redeem(&kc_buy, &mut swap, &ctx_buy).unwrap();
+		let expected = swap.expected_redeem_commitment(&kc_buy, &ctx_buy).unwrap();
+		let outputs = swap.redeem_slate.tx.outputs();
+		assert_eq!(outputs.len(), 1);
+		assert_eq!(outputs[0].commit, expected);
+
+		// Tampered amount doesn't match
+		let mut tampered = swap.clone();
+		tampered.redeem_slate.amount -= 1;
+		assert_ne!(
+			tampered
+				.expected_redeem_commitment(&kc_buy, &ctx_buy)
+				.unwrap(),
+			outputs[0].commit
+		);
+
+		// Seller doesn't have the buyer context
+		assert!(swap
+			.expected_redeem_commitment(&kc_sell, &ctx_sell)
+			.is_err());
+	}
+
+	#[test]
+	#[serial]
+	fn test_verify_adaptor_consistency() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (mut swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let mut swap_buy =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+		let (_, accept_offer, _) = BuyApi::accept_offer_message(&swap_buy, SecondaryUpdate::Empty)
+			.unwrap()
+			.unwrap_accept_offer()
+			.unwrap();
+		SellApi::accepted_offer(&kc_sell, &mut swap_sell, &ctx_sell, accept_offer).unwrap();
+
+		// Not at the redeem stage yet
+		assert!(swap_buy.verify_adaptor_consistency().is_err());
+
+		BuyApi::init_redeem(&kc_buy, &mut swap_buy, &ctx_buy).unwrap();
+		// Adaptor signature is there, but the redeem slate is not finalized
+		assert!(swap_buy.verify_adaptor_consistency().is_err());
+
+		let (_, init_redeem, _) = BuyApi::init_redeem_message(&swap_buy)
+			.unwrap()
+			.unwrap_init_redeem()
+			.unwrap();
+		SellApi::init_redeem(&kc_sell, &mut swap_sell, &ctx_sell, init_redeem).unwrap();
+		let (_, redeem, _) = SellApi::redeem_message(&swap_sell)
+			.unwrap()
+			.unwrap_redeem()
+			.unwrap();
+		BuyApi::finalize_redeem_slate(&kc_buy, &mut swap_buy, &ctx_buy, redeem.redeem_participant)
+			.unwrap();
+
+		// Completed swap is consistent
+		assert_eq!(swap_buy.verify_adaptor_consistency().unwrap(), true);
+
+		// Seller gets the kernel from the chain
+		swap_sell.redeem_slate.tx = swap_buy.redeem_slate.tx.clone();
+		swap_sell.redeem_kernel_updated = true;
+		assert_eq!(swap_sell.verify_adaptor_consistency().unwrap(), true);
+
+		// Tampered adaptor signature doesn't reveal the redeem secret
+		let mut tampered = swap_buy.clone();
+		tampered.adaptor_signature = Some(swap_buy.redeem_slate.tx.kernels()[0].excess_sig.clone());
+		assert_eq!(tampered.verify_adaptor_consistency().unwrap(), false);
+	}
+
+	struct FixedLockObserver {
+		lock: (u64, Option<u64>),
+		address: Mutex<Option<String>>,
+	}
+
+	impl api::SecondaryLockObserver for FixedLockObserver {
+		fn lock_confirmations(
+			&self,
+			_currency: Currency,
+			lock_address: &String,
+		) -> Result<(u64, Option<u64>), ErrorKind> {
+			self.address.lock().replace(lock_address.clone());
+			Ok(self.lock.clone())
+		}
+	}
+
+	#[test]
+	#[serial]
+	fn test_secondary_lock_observer() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+
+		let mut api_buy = BtcSwapApi::new_test(
+			Arc::new(nc.clone()),
+			Arc::new(Mutex::new(TestBtcNodeClient::new(1))),
+		);
+		// Electrum client doesn't see anything
+		let conf = api_buy.request_tx_confirmations(&kc_buy, &swap).unwrap();
+		assert_eq!(conf.secondary_lock_amount, 0);
+		assert_eq!(conf.secondary_lock_conf, None);
+
+		// Custom observer replaces electrum
+		let observer = Arc::new(FixedLockObserver {
+			lock: (swap.secondary_amount, Some(2)),
+			address: Mutex::new(None),
+		});
+		api_buy.lock_observer = Some(observer.clone());
+		let conf = api_buy.request_tx_confirmations(&kc_buy, &swap).unwrap();
+		assert_eq!(conf.secondary_lock_amount, swap.secondary_amount);
+		assert_eq!(conf.secondary_lock_conf, Some(2));
+		assert_eq!(
+			observer.address.lock().clone(),
+			Some(SwapApi::<ExtKeychain>::get_secondary_lock_address(&api_buy, &swap).unwrap())
+		);
+
+		let balance = |confirmations_needed| {
+			SwapApi::<ExtKeychain>::request_secondary_lock_balance(
+				&api_buy,
+				&swap,
+				confirmations_needed,
+			)
+			.unwrap()
+		};
+		assert_eq!(balance(1), (0, swap.secondary_amount, 0));
+		assert_eq!(balance(6), (swap.secondary_amount, 0, 2));
+	}
+
+	#[test]
+	#[serial]
+	fn test_swap_locked_balance() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+
+		// Active, waiting for the buyer to redeem
+		let (mut redeeming, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		redeeming.state = StateId::SellerWaitingForBuyerToRedeemMwc;
+		redeeming.posted_lock = Some(1567632200);
+
+		// Active, on the refund path but not refunded yet
+		let (mut refunding, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		refunding.primary_amount = 30 * GRIN_UNIT;
+		refunding.state = StateId::SellerWaitingForRefundHeight;
+		refunding.posted_lock = Some(1567632200);
+
+		// Completed
+		let (mut completed, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		completed.state = StateId::SellerSwapComplete;
+		completed.posted_lock = Some(1567632200);
+
+		// Lock is not posted yet
+		let (mut not_locked, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		not_locked.state = StateId::SellerPostingLockMwcSlate;
+
+		// Buyer doesn't lock MWC
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let mut buyer =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+		buyer.state = StateId::BuyerWaitingForLockConfirmations;
+		buyer.posted_lock = Some(1567632200);
+
+		assert!(redeeming.is_primary_locked());
+		assert!(refunding.is_primary_locked());
+		assert!(!completed.is_primary_locked());
+		assert!(!not_locked.is_primary_locked());
+		assert!(!buyer.is_primary_locked());
+
+		let swaps = vec![redeeming, refunding, completed, not_locked, buyer];
+		assert_eq!(trades::locked_balance(&swaps), 130 * GRIN_UNIT);
+		assert_eq!(trades::locked_balance(&swaps[2..]), 0);
+	}
+
+	#[test]
+	#[serial]
+	fn test_swap_is_stalled() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let posted = 1567640000;
+
+		// Waiting for the MWC refund confirmations, refund tx is not on the chain
+		let (mut swap, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		swap.state = StateId::SellerWaitingForRefundConfirmations;
+		swap.posted_refund = Some(posted);
+		let expected = swap.get_timeinterval_mwc_lock();
+		assert!(expected > 0);
+		assert!(!swap.is_stalled(&nc, posted + 10));
+		assert!(!swap.is_stalled(&nc, posted + expected));
+		assert!(swap.is_stalled(&nc, posted + expected + 1));
+
+		// Lock is waiting for the slowest of the chains
+		swap.state = StateId::SellerWaitingForLockConfirmations;
+		swap.posted_lock = Some(posted);
+		let expected = std::cmp::max(
+			swap.get_timeinterval_mwc_lock(),
+			swap.get_timeinterval_btc_lock(),
+		);
+		assert!(!swap.is_stalled(&nc, posted + expected));
+		assert!(swap.is_stalled(&nc, posted + expected + 1));
+
+		// Buyer doesn't post the lock, waiting since the last protocol step. The journal
+		// records of the checks don't delay the detection.
+		swap.state = StateId::BuyerWaitingForLockConfirmations;
+		swap.posted_lock = None;
+		swap.posted_refund = None;
+		swap.posted_msg1 = Some(posted);
+		assert_eq!(swap.get_time_last_progress(), posted);
+		swap.journal.push(swap::SwapJournalRecord {
+			time: posted + expected,
+			message: "Processing error: node is not available".to_string(),
+		});
+		assert!(!swap.is_stalled(&nc, posted + expected));
+		assert!(swap.is_stalled(&nc, posted + expected + 1));
+
+		// Not waiting for the confirmations
+		swap.state = StateId::SellerWaitingForBuyerToRedeemMwc;
+		assert!(!swap.is_stalled(&nc, posted + expected * 100));
+		swap.state = StateId::SellerCancelledRefunded;
+		assert!(!swap.is_stalled(&nc, posted + expected * 100));
+	}
+
+	#[test]
+	#[serial]
+	fn test_export_swaps_csv() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+		use chrono::TimeZone;
+		use grin_core::core::amount_to_hr_string;
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let journal = |time: i64| {
+			vec![swap::SwapJournalRecord {
+				time,
+				message: "done".to_string(),
+			}]
+		};
+
+		// Seller, complete
+		let (mut completed, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		completed.started = chrono::Utc.timestamp(1567632152, 0);
+		completed.state = StateId::SellerSwapComplete;
+		completed.posted_lock = Some(1567632200);
+		completed.journal = journal(1567640000);
+
+		// Seller, refunded
+		let (mut refunded, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		refunded.started = chrono::Utc.timestamp(1567632153, 0);
+		refunded.state = StateId::SellerCancelledRefunded;
+		refunded.posted_lock = Some(1567632200);
+		refunded.posted_refund = Some(1567650000);
+		refunded.journal = journal(1567650100);
+
+		// Buyer, cancelled before locking
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let mut cancelled =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+		cancelled.started = chrono::Utc.timestamp(1567632154, 0);
+		cancelled.state = StateId::BuyerCancelled;
+		cancelled.journal = journal(1567633000);
+
+		let trades_dir = std::env::temp_dir().join("mwc_swap_csv_test");
+		let _ = std::fs::remove_dir_all(&trades_dir);
+		trades::init_swap_trade_backend(trades_dir.to_str().unwrap(), &None);
+		let skey = key(&kc_sell, 3, 0);
+		for (swap, ctx) in vec![
+			(&cancelled, &ctx_buy),
+			(&refunded, &ctx_sell),
+			(&completed, &ctx_sell),
+		] {
+			let lock = trades::get_swap_lock(&swap.id.to_string());
+			let _l = lock.lock();
+			trades::store_swap_trade(ctx, swap, &skey, &*lock).unwrap();
+		}
+
+		let mut csv = Vec::new();
+		trades::export_swap_trades_csv(&mut csv, &skey).unwrap();
+		let csv = String::from_utf8(csv).unwrap();
+		let lines: Vec<&str> = csv.lines().collect();
+		assert_eq!(lines.len(), 4);
+		assert_eq!(lines[0], trades::SWAP_CSV_HEADER);
+		assert_eq!(
+			lines[0],
+			"id,started,completed,role,secondary_currency,primary_amount,secondary_amount,fees,status"
+		);
+		assert_eq!(
+			lines[1],
+			format!(
+				"{},2019-09-04T21:22:32+00:00,2019-09-04T23:33:20+00:00,Seller,BTC,{},0.03,{},Completed",
+				completed.id,
+				amount_to_hr_string(completed.primary_amount, true),
+				amount_to_hr_string(completed.lock_slate.fee, true)
+			)
+		);
+		assert_eq!(
+			lines[2],
+			format!(
+				"{},2019-09-04T21:22:33+00:00,2019-09-05T02:21:40+00:00,Seller,BTC,{},0.03,{},Refunded",
+				refunded.id,
+				amount_to_hr_string(refunded.primary_amount, true),
+				amount_to_hr_string(refunded.lock_slate.fee + refunded.refund_slate.fee, true)
+			)
+		);
+		assert_eq!(
+			lines[3],
+			format!(
+				"{},2019-09-04T21:22:34+00:00,2019-09-04T21:36:40+00:00,Buyer,BTC,{},0.03,{},Cancelled",
+				cancelled.id,
+				amount_to_hr_string(cancelled.primary_amount, true),
+				amount_to_hr_string(0, true)
+			)
+		);
+		let _ = std::fs::remove_dir_all(&trades_dir);
+	}
+
+	#[test]
+	#[serial]
+	fn test_offer_malformed_multisig() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let accept = |update: &dyn Fn(&mut serde_json::Value)| {
+			let mut offer = offer.clone();
+			let mut multisig = serde_json::to_value(&offer.multisig).unwrap();
+			update(&mut multisig);
+			offer.multisig = serde_json::from_value(multisig).unwrap();
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update.clone(), &nc)
+		};
+
+		// Commitment is not a valid curve point
+		let res = accept(&|m| {
+			m["partial_commitment"] = serde_json::Value::String("00".repeat(33));
+		});
+		match res.err().unwrap() {
+			ErrorKind::Multisig(multisig::ErrorKind::ParticipantMalformed(msg)) => {
+				assert!(msg.starts_with("invalid partial commitment"))
+			}
+			e => panic!("Unexpected error {}", e),
+		}
+		// Round 1 data is missing
+		let res = accept(&|m| {
+			m.as_object_mut().unwrap().remove("t_2");
+		});
+		assert_eq!(
+			res.err().unwrap(),
+			ErrorKind::Multisig(multisig::ErrorKind::ParticipantMalformed(
+				"public key t_2 is missing".to_string()
+			))
+		);
+		// Round 2 data can't be in the offer
+		let res = accept(&|m| {
+			m["tau_x"] = serde_json::Value::String("01".repeat(32));
+		});
+		assert_eq!(
+			res.err().unwrap(),
+			ErrorKind::Multisig(multisig::ErrorKind::ParticipantMalformed(
+				"unexpected round 2 data".to_string()
+			))
+		);
+
+		// Original offer is fine
+		assert!(accept(&|_| ()).is_ok());
+	}
+
+	#[test]
+	#[serial]
+	fn test_offer_lock_excess_mismatch() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let accept = |update: &dyn Fn(&mut serde_json::Value)| {
+			let mut offer = offer.clone();
+			let mut lock_slate = serde_json::to_value(&offer.lock_slate).unwrap();
+			update(&mut lock_slate);
+			offer.lock_slate = serde_json::from_value(lock_slate).unwrap();
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update.clone(), &nc)
+		};
+
+		// Seller's public excess is replaced with another valid key
+		let res = accept(&|s| {
+			let nonce = s["participant_data"][0]["public_nonce"].clone();
+			s["participant_data"][0]["public_blind_excess"] = nonce;
+		});
+		match res.err().unwrap() {
+			ErrorKind::LockExcessMismatch(_, _) => (),
+			e => panic!("Unexpected error {}", e),
+		}
+		// Offset doesn't match the seller's excess
+		let res = accept(&|s| {
+			s["tx"]["offset"] = serde_json::Value::String("01".repeat(32));
+		});
+		match res.err().unwrap() {
+			ErrorKind::LockExcessMismatch(_, _) => (),
+			e => panic!("Unexpected error {}", e),
+		}
+
+		// Original offer is fine
+		assert!(accept(&|_| ()).is_ok());
+	}
+
+	#[test]
+	#[serial]
+	fn test_invalid_refund_secret() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap = BuyApi::accept_swap_offer(
+			&kc_buy,
+			&ctx_buy,
+			id,
+			offer.clone(),
+			secondary_update.clone(),
+			&nc,
+		)
+		.unwrap();
+
+		let sec_key = BuyApi::refund_tx_secret(&kc_buy, &swap, &ctx_buy).unwrap();
+		swap.verify_refund_secret(&kc_buy, &sec_key).unwrap();
+
+		// Context with the wrong multisig key can't spend the multisig output
+		let mut wrong_ctx = context_buy(&kc_buy);
+		wrong_ctx.multisig_key = key_id(0, 7);
+		let wrong_key = BuyApi::refund_tx_secret(&kc_buy, &swap, &wrong_ctx).unwrap();
+		match swap.verify_refund_secret(&kc_buy, &wrong_key) {
+			Err(ErrorKind::InvalidRefundSecret(_)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+
+		// Refund slate that can't be finalized is not signed
+		let mut refund_slate = serde_json::to_value(&offer.refund_slate).unwrap();
+		let nonce = refund_slate["participant_data"][0]["public_nonce"].clone();
+		refund_slate["participant_data"][0]["public_blind_excess"] = nonce;
+		let mut bad_offer = offer.clone();
+		bad_offer.refund_slate = serde_json::from_value(refund_slate).unwrap();
+		match BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, bad_offer, secondary_update, &nc) {
+			Err(ErrorKind::InvalidRefundSecret(_)) => (),
+			r => panic!("Unexpected result {:?}", r.map(|s| s.id)),
+		}
+	}
+
+	#[test]
+	#[serial]
+	fn test_primary_amount_consistency() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+		swap.check_primary_amount().unwrap();
+
+		// Redeem spends more than it was locked
+		let mut redeem_diverged = swap.clone();
+		redeem_diverged.redeem_slate.amount += 1;
+		assert_eq!(
+			redeem_diverged.check_primary_amount(),
+			Err(ErrorKind::PrimaryAmountMismatch(
+				"redeem slate".to_string(),
+				100 * GRIN_UNIT,
+				100 * GRIN_UNIT + 1
+			))
+		);
+
+		// Lock slate doesn't lock the whole amount
+		let mut lock_diverged = swap.clone();
+		lock_diverged.lock_slate.amount -= 1;
+		match lock_diverged.check_primary_amount() {
+			Err(ErrorKind::PrimaryAmountMismatch(slate, _, _)) => assert_eq!(slate, "lock slate"),
+			r => panic!("Unexpected result {:?}", r),
+		}
+
+		// Amount and fee from the other party can't overflow
+		let mut overflow = swap.clone();
+		overflow.refund_slate.amount = u64::MAX;
+		match overflow.check_primary_amount() {
+			Err(ErrorKind::InvalidMessageData(_)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+	}
+
+	#[test]
+	#[serial]
+	fn test_accept_offer_node_timeout() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+
+		let mut config = swap::SwapConfig::default();
+		assert_eq!(config.node_call_timeout, swap::DEFAULT_NODE_CALL_TIMEOUT);
+		let accept = |config: &swap::SwapConfig| {
+			BuyApi::accept_swap_offer_with_config(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer.clone(),
+				secondary_update.clone(),
+				&nc,
+				config,
+				&CancellationToken::new(),
+			)
+		};
+
+		// Node is much slower than the limit
+		config.node_call_timeout = std::time::Duration::from_millis(200);
+		nc.state.lock().delay = Some(std::time::Duration::from_secs(60));
+		let started = std::time::Instant::now();
+		let res = accept(&config);
+		assert!(started.elapsed() < std::time::Duration::from_secs(10));
+		match res.err().unwrap() {
+			ErrorKind::NodeTimeout(_) => (),
+			e => panic!("Unexpected error {}", e),
+		}
+
+		// Node is slow, but still within the limit
+		nc.state.lock().delay = Some(std::time::Duration::from_millis(10));
+		assert!(accept(&config).is_ok());
+	}
+
+	#[test]
+	#[serial]
+	fn test_accept_offer_stale_lock_slate() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let accept = |config: &swap::SwapConfig| {
+			BuyApi::accept_swap_offer_with_config(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer.clone(),
+				secondary_update.clone(),
+				&nc,
+				config,
+				&CancellationToken::new(),
+			)
+		};
+
+		// Fresh offer
+		let mut config = swap::SwapConfig::default();
+		assert_eq!(config.lock_slate_max_age, swap::DEFAULT_LOCK_SLATE_MAX_AGE);
+		nc.mine_blocks(swap::DEFAULT_LOCK_SLATE_MAX_AGE);
+		assert!(accept(&config).is_ok());
+
+		// Offer is one block too old
+		nc.mine_blocks(1);
+		assert_eq!(
+			accept(&config).err().unwrap(),
+			ErrorKind::StaleOffer(300_000, 300_000 + swap::DEFAULT_LOCK_SLATE_MAX_AGE + 1)
+		);
+
+		// Wider window
+		config.lock_slate_max_age = swap::DEFAULT_LOCK_SLATE_MAX_AGE * 2;
+		assert!(accept(&config).is_ok());
+		// Narrow window
+		config.lock_slate_max_age = 10;
+		match accept(&config).err().unwrap() {
+			ErrorKind::StaleOffer(..) => (),
+			e => panic!("Unexpected error {}", e),
+		}
+	}
+
+	#[test]
+	#[serial]
+	fn test_cancel_lock_inputs_validation() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		let commits: Vec<Commitment> = swap
+			.lock_slate
+			.tx
+			.inputs()
+			.iter()
+			.map(|i| i.commit)
+			.collect();
+		assert_eq!(commits.len(), 2);
+
+		// Cancelled after the first batch, the second one is not requested
+		let cancel = CancellationToken::new();
+		nc.state.lock().cancel_on_outputs = Some(cancel.clone());
+		let calls = nc.get_state().outputs_calls;
+		match crate::internal::updater::get_outputs_from_node_batched(&nc, &commits, 1, &cancel)
+			.unwrap_err()
+			.kind()
+		{
+			crate::ErrorKind::Cancelled(_) => (),
+			e => panic!("Unexpected error {}", e),
+		}
+		assert!(cancel.is_cancelled());
+		assert_eq!(nc.get_state().outputs_calls, calls + 1);
+		nc.state.lock().cancel_on_outputs = None;
+
+		// Offer validation is stopped before the node is requested
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let accept = |cancel: &CancellationToken| {
+			BuyApi::accept_swap_offer_with_config(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer.clone(),
+				secondary_update.clone(),
+				&nc,
+				&swap::SwapConfig::default(),
+				cancel,
+			)
+		};
+		match accept(&cancel).err().unwrap() {
+			ErrorKind::Cancelled(_) => (),
+			e => panic!("Unexpected error {}", e),
+		}
+		assert_eq!(nc.get_state().outputs_calls, calls + 1);
+		assert!(accept(&CancellationToken::new()).is_ok());
+		assert_eq!(nc.get_state().outputs_calls, calls + 2);
+	}
+
+	#[test]
+	#[serial]
+	fn test_accept_offer_counterparty_allowlist() {
+		set_test_mode(true);
+
+		let address = |kc: &ExtKeychain| {
+			let pk = PublicKey::from_secret_key(kc.secp(), &key(kc, 2, 0)).unwrap();
+			ProvableAddress::from_pub_key(&pk).to_string()
+		};
+		let seller_address = format!("mwcmqs://{}", address(&keychain(1)));
+		let other_address = address(&keychain(2));
+
+		// No allowlist, any counterparty is fine, even not verified one
+		swap::check_counterparty_allowed(Some(&seller_address)).unwrap();
+		swap::check_counterparty_allowed(None).unwrap();
+
+		// Seller is allowed
+		swap::set_counterparty_allowlist(Some(vec![seller_address.clone()])).unwrap();
+		swap::check_counterparty_allowed(Some(&seller_address)).unwrap();
+
+		// Sender that is not verified by transport is rejected, whatever the offer claims
+		assert!(swap::check_counterparty_allowed(None).is_err());
+
+		// Seller is unknown
+		swap::set_counterparty_allowlist(Some(vec![other_address])).unwrap();
+		assert_eq!(
+			swap::check_counterparty_allowed(Some(&seller_address))
+				.err()
+				.unwrap(),
+			ErrorKind::CounterpartyNotAllowed(seller_address.clone())
+		);
+
+		// Not valid addresses can't be in the allowlist
+		assert!(
+			swap::set_counterparty_allowlist(Some(vec!["not an address".to_string()])).is_err()
+		);
+
+		swap::set_counterparty_allowlist(None).unwrap();
+		swap::check_counterparty_allowed(None).unwrap();
+	}
+
+	#[test]
+	#[serial]
+	fn test_recompute_multisig_commit() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let swap =
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc).unwrap();
+
+		// Valid swap, commitment matches the stored one
+		let commit = swap.recompute_multisig_commit(&kc_buy, &ctx_buy).unwrap();
+		assert_eq!(commit, swap.multisig.commit(kc_buy.secp()).unwrap());
+		assert_eq!(commit, swap.refund_slate.tx.inputs()[0].commit);
+
+		// Corrupted partial commitment
+		let mut corrupted = swap.clone();
+		corrupted.multisig.participants[corrupted.participant_id].partial_commitment =
+			Some(kc_buy.secp().commit(0, key(&kc_buy, 3, 0)).unwrap());
+		match corrupted.recompute_multisig_commit(&kc_buy, &ctx_buy) {
+			Err(ErrorKind::MultisigCommitMismatch(expected, _)) => {
+				assert_eq!(expected, to_hex(commit.0.to_vec()))
+			}
+			r => panic!("Unexpected result {:?}", r),
+		}
+
+		// Corrupted amount
+		let mut corrupted = swap.clone();
+		corrupted.multisig.amount += 1;
+		match corrupted.recompute_multisig_commit(&kc_buy, &ctx_buy) {
+			Err(ErrorKind::MultisigCommitMismatch(..)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+	}
+
+	#[test]
+	#[serial]
+	fn test_buyer_confirmation_overrides() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let accept = |mwc: Option<u64>, secondary: Option<u64>| {
+			let (id, offer, secondary_update) = message.clone().unwrap_offer().unwrap();
+			BuyApi::accept_swap_offer_with_overrides(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer,
+				secondary_update,
+				&nc,
+				mwc,
+				secondary,
+				None,
+				&swap::SwapConfig::default(),
+			)
+			.unwrap()
+		};
+
+		// Offer values are used without the overrides
+		let swap = accept(None, None);
+		assert_eq!(swap.required_mwc_confirmations(), 30);
+		assert_eq!(swap.required_secondary_confirmations(), 3);
+
+		// Overrides can't weaken the offer
+		let swap = accept(Some(10), Some(1));
+		assert_eq!(swap.buyer_min_mwc_confirmations, None);
+		assert_eq!(swap.buyer_min_secondary_confirmations, None);
+		assert_eq!(swap.required_mwc_confirmations(), 30);
+		assert_eq!(swap.required_secondary_confirmations(), 3);
+
+		// Stricter Buyer policy
+		let mut swap = accept(Some(40), Some(5));
+		assert_eq!(swap.mwc_confirmations, 30);
+		assert_eq!(swap.secondary_confirmations, 3);
+		assert_eq!(swap.required_mwc_confirmations(), 40);
+		assert_eq!(swap.required_secondary_confirmations(), 5);
+
+		// Overrides must be reached before the redeem messages exchange
+		let (id, offer, secondary_update) = message.clone().unwrap_offer().unwrap();
+		let accept_result = |mwc: Option<u64>, secondary: Option<u64>| {
+			BuyApi::accept_swap_offer_with_overrides(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer.clone(),
+				secondary_update.clone(),
+				&nc,
+				mwc,
+				secondary,
+				None,
+				&swap::SwapConfig::default(),
+			)
+		};
+		assert_eq!(swap.max_lock_confirmations(60), 84);
+		assert_eq!(swap.max_lock_confirmations(600), 8);
+		assert!(accept_result(Some(84), Some(8)).is_ok());
+		match accept_result(Some(85), None) {
+			Err(ErrorKind::ConfirmationsOutOfTime(_, 85, 84)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+		match accept_result(None, Some(9)) {
+			Err(ErrorKind::ConfirmationsOutOfTime(_, 9, 8)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+
+		// Offer confirmations are reached, but the Buyer is still waiting for own ones
+		let api_buy = BtcSwapApi::new_test(
+			Arc::new(nc.clone()),
+			Arc::new(Mutex::new(TestBtcNodeClient::new(1))),
+		);
+		let mut fsm_buy = api_buy.get_fsm(&kc_buy, &swap);
+		swap.state = StateId::BuyerWaitingForLockConfirmations;
+		let tx_conf = |mwc_lock: u64, secondary_lock: u64| SwapTransactionsConfirmations {
+			mwc_tip: 300_000,
+			mwc_lock_conf: Some(mwc_lock),
+			mwc_redeem_conf: None,
+			mwc_refund_conf: None,
+			secondary_tip: 1000,
+			secondary_lock_conf: Some(secondary_lock),
+			secondary_lock_amount: 3_000_000,
+			secondary_redeem_conf: None,
+			secondary_refund_conf: None,
+		};
+		let res = fsm_buy
+			.process(Input::Check, &mut swap, &ctx_buy, &tx_conf(35, 4))
+			.unwrap();
+		assert_eq!(res.next_state_id, StateId::BuyerWaitingForLockConfirmations);
+		match res.action.unwrap() {
+			Action::WaitForLockConfirmations {
+				mwc_required,
+				sec_required,
+				..
+			} => {
+				assert_eq!(mwc_required, 40);
+				assert_eq!(sec_required, 5);
+			}
+			a => panic!("Unexpected action {}", a),
+		}
+
+		// Override values are reached
+		let res = fsm_buy
+			.process(Input::Check, &mut swap, &ctx_buy, &tx_conf(40, 5))
+			.unwrap();
+		assert_eq!(res.next_state_id, StateId::BuyerSendingInitRedeemMessage);
+	}
+
+	#[test]
+	#[serial]
+	fn test_swap_safety_status() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		// 30 lock confirmations and 60 blocks for the redeem
+		assert_eq!(swap.mwc_confirmations, 30);
+		assert_eq!(swap.redeem_time_sec, 3600);
+		let refund_height = swap.refund_slate.lock_height;
+
+		let status_at = |height: u64| {
+			nc.state.lock().height = height;
+			swap.safety_status(&nc).unwrap()
+		};
+		assert_eq!(status_at(300_000), SafetyAssessment::Safe);
+		assert_eq!(status_at(refund_height - 91), SafetyAssessment::Safe);
+		assert_eq!(
+			status_at(refund_height - 90),
+			SafetyAssessment::CautionRefundWindowNear
+		);
+		assert_eq!(
+			status_at(refund_height - 31),
+			SafetyAssessment::CautionRefundWindowNear
+		);
+		assert_eq!(status_at(refund_height - 30), SafetyAssessment::RefundNow);
+		assert_eq!(status_at(refund_height), SafetyAssessment::RefundNow);
+		assert_eq!(status_at(refund_height + 100), SafetyAssessment::RefundNow);
+
+		// Redeem is posted, the swap must be finished
+		let mut redeemed = swap.clone();
+		redeemed.redeem_kernel_updated = true;
+		assert_eq!(redeemed.safety_status(&nc).unwrap(), SafetyAssessment::Safe);
+	}
+
+	#[test]
+	#[serial]
+	fn test_simulate_refund() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap_sell, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		// Seller gets back the lock amount minus the refund fee
+		let refund_fee = swap_sell.refund_slate.fee;
+		let lock_fee = swap_sell.lock_slate.fee;
+		assert!(refund_fee > 0 && lock_fee > 0);
+		let simulation = swap_sell.simulate_refund().unwrap();
+		assert_eq!(
+			simulation,
+			RefundSimulation {
+				currency: "MWC".to_string(),
+				amount_returned: 100 * GRIN_UNIT - refund_fee,
+				fee: refund_fee,
+				net_loss: lock_fee + refund_fee,
+			}
+		);
+		// Refund transaction spends the multisig output that has the whole swap amount
+		assert_eq!(
+			simulation.amount_returned + simulation.fee,
+			swap_sell.primary_amount
+		);
+
+		// Buyer can evaluate the refund of the offer before accepting it
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let offer_simulation =
+			BuyApi::simulate_offer_refund(&offer, secondary_update.clone(), None, 26.0).unwrap();
+		assert_eq!(offer_simulation.currency, "BTC");
+		assert!(offer_simulation.fee > 0);
+		assert_eq!(
+			offer_simulation.amount_returned + offer_simulation.fee,
+			3_000_000
+		);
+		assert_eq!(offer_simulation.net_loss, offer_simulation.fee);
+
+		// Buyer gets back the BTC lock amount minus the refund fee, same as for the offer
+		let mut swap_buy = BuyApi::accept_swap_offer(
+			&kc_buy,
+			&ctx_buy,
+			id,
+			offer.clone(),
+			secondary_update.clone(),
+			&nc,
+		)
+		.unwrap();
+		swap_buy.secondary_fee = 26.0;
+		assert_eq!(swap_buy.simulate_refund().unwrap(), offer_simulation);
+
+		// Fee that takes the whole lock amount is an error, not a zero refund
+		swap_buy.secondary_fee = 100_000.0;
+		match swap_buy.simulate_refund() {
+			Err(ErrorKind::FeeExceedsAmount(_, fee, amount)) => {
+				assert!(fee > amount);
+				assert_eq!(amount, 3_000_000);
+			}
+			r => panic!("Expected FeeExceedsAmount, get {:?}", r),
+		}
+		assert!(BuyApi::simulate_offer_refund(&offer, secondary_update, None, 100_000.0).is_err());
+	}
+
+	#[test]
+	#[serial]
+	fn test_swap_config() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		// Defaults are the protocol values
+		let config = swap::SwapConfig::default();
+		assert_eq!(config.node_call_timeout, swap::DEFAULT_NODE_CALL_TIMEOUT);
+		assert_eq!(config.lock_slate_max_age, swap::DEFAULT_LOCK_SLATE_MAX_AGE);
+		assert_eq!(config.refund_fee_max_multiplier, 10);
+		assert_eq!(config.clock_tolerance_sec, 15);
+		assert_eq!(config.max_mwc_lock_blocks, 1440 * 30);
+		assert_eq!(config.message_min_interval_sec, 10);
+		assert_eq!(config.refund_lock_height_tolerance_percent, 10);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (_, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		let accept = |config: &swap::SwapConfig| {
+			BuyApi::accept_swap_offer_with_config(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer.clone(),
+				secondary_update.clone(),
+				&nc,
+				config,
+				&CancellationToken::new(),
+			)
+		};
+
+		// Buyer clock is 20 seconds behind the Seller
+		swap::set_testing_cur_time(1567632152 - 20);
+		assert_eq!(
+			accept(&config).err().unwrap(),
+			ErrorKind::InvalidMessageData("Buyer/Seller clock are out of sync".to_string())
+		);
+		let mut tolerant = config.clone();
+		tolerant.clock_tolerance_sec = 30;
+		assert!(accept(&tolerant).is_ok());
+
+		// Default parameters are used if the config is not passed
+		assert_eq!(
+			BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc)
+				.err()
+				.unwrap(),
+			ErrorKind::InvalidMessageData("Buyer/Seller clock are out of sync".to_string())
+		);
+	}
+
+	#[test]
+	#[serial]
+	fn test_lock_time_tolerance() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap_btc, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+
+		// 5% of the lock time by default, the offer doesn't have the tolerance
+		let config = swap::SwapConfig::default();
+		assert_eq!(config.lock_time_tolerance_percent(Currency::Btc), 5);
+		assert_eq!(config.lock_time_tolerance_percent(Currency::Bch), 5);
+		assert_eq!(swap_btc.lock_time_tolerance_percent, None);
+		assert_eq!(swap_btc.lock_time_tolerance_percent(), 5);
+		let lock_interval = std::cmp::max(
+			swap_btc.get_timeinterval_mwc_lock(),
+			swap_btc.get_timeinterval_btc_lock(),
+		);
+		assert!(lock_interval > 0);
+		assert_eq!(
+			swap_btc.get_time_start_lock(),
+			swap_btc.get_time_message_offers() + lock_interval / 20
+		);
+		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
+		assert_eq!(offer.lock_time_tolerance_percent, None);
+		assert!(offer.required_features.is_empty());
+
+		// Currencies can have different tolerances, the Seller puts it into the offer
+		let mut wide_btc = config.clone();
+		wide_btc
+			.lock_time_tolerance_percent
+			.insert(Currency::Btc, 10);
+		wide_btc
+			.lock_time_tolerance_percent
+			.insert(Currency::Bch, 20);
+		assert_eq!(wide_btc.lock_time_tolerance_percent(Currency::Btc), 10);
+		assert_eq!(wide_btc.lock_time_tolerance_percent(Currency::Bch), 20);
+		let mut api_sell = BtcSwapApi::new_test(
+			Arc::new(nc.clone()),
 			Arc::new(Mutex::new(TestBtcNodeClient::new(1))),
 		);
-		let mut swap = api_sell
-			.create_swap_offer(
+		let create_offer = |api_sell: &mut BtcSwapApi<TestNodeClient, TestBtcNodeClient>,
+		                    config: &swap::SwapConfig| {
+			api_sell.create_swap_offer(
 				&kc_sell,
 				&ctx_sell,
 				100 * GRIN_UNIT,
 				3_000_000,
 				Currency::Btc,
-				secondary_redeem_address,
-				true, // mwc should be publisher first
+				btc_address(&kc_sell),
+				true,
 				30,
 				3,
 				3600,
@@ -449,37 +3657,458 @@ mod tests {
 				"/tmp/del.me".to_string(),
 				None,
 				None,
+				None,
+				config,
 			)
+		};
+		let swap_wide = create_offer(&mut api_sell, &wide_btc).unwrap();
+		assert_eq!(swap_wide.lock_time_tolerance_percent, Some(10));
+		assert_eq!(
+			swap_wide.get_time_start_lock(),
+			swap_wide.get_time_message_offers() + lock_interval / 10
+		);
+		let (_, wide_offer, _) = SellApi::offer_message(&swap_wide, secondary_update.clone())
+			.unwrap()
+			.unwrap_offer()
 			.unwrap();
-		let mut fsm_sell = api_sell.get_fsm(&kc_sell, &swap);
-		let tx_state = api_sell
-			.request_tx_confirmations(&kc_sell, &mut swap)
-			.unwrap();
+		assert_eq!(wide_offer.lock_time_tolerance_percent, Some(10));
+		assert_eq!(
+			wide_offer.required_features,
+			vec![LOCK_TIME_TOLERANCE_FEATURE.to_string()]
+		);
+		let mut bad_btc = config.clone();
+		bad_btc.lock_time_tolerance_percent.insert(Currency::Btc, 0);
+		assert!(create_offer(&mut api_sell, &bad_btc).is_err());
 
-		let message = match fsm_sell
-			.process(Input::Check, &mut swap, &ctx_sell, &tx_state)
-			.unwrap()
-			.action
-			.unwrap()
-		{
-			Action::SellerSendOfferMessage(message) => message,
-			_ => panic!("Unexpected action"),
+		// The offer can't be accepted when the time to start the locks is over
+		let kc_buy = keychain(2);
+		let ctx_buy = context_buy(&kc_buy);
+		let accept = |offer: &OfferUpdate| {
+			BuyApi::accept_swap_offer(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer.clone(),
+				secondary_update.clone(),
+				&nc,
+			)
 		};
+		swap::set_testing_cur_time(swap_btc.get_time_start_lock() - 1);
+		assert!(accept(&offer).is_ok());
+		swap::set_testing_cur_time(swap_btc.get_time_start_lock());
+		match accept(&offer).err().unwrap() {
+			ErrorKind::OfferRejected(explanation) => {
+				assert_eq!(explanation.failed_checks.len(), 1);
+				assert_eq!(
+					explanation.failed_checks[0].check,
+					"start lock time (5% lock time tolerance)"
+				);
+				assert_eq!(
+					explanation.failed_checks[0].actual,
+					swap_btc.get_time_start_lock().to_string()
+				);
+			}
+			e => panic!("Unexpected error {}", e),
+		}
+
+		// Buyer follows the tolerance from the offer
+		let mut offer_10 = offer.clone();
+		offer_10.lock_time_tolerance_percent = Some(10);
+		offer_10.required_features = vec![LOCK_TIME_TOLERANCE_FEATURE.to_string()];
+		let swap_buy = accept(&offer_10).unwrap();
+		assert_eq!(swap_buy.lock_time_tolerance_percent, Some(10));
+		assert!(swap_buy.get_time_start_lock() > swap_btc.get_time_start_lock());
+		swap::set_testing_cur_time(swap_buy.get_time_start_lock());
+		match accept(&offer_10).err().unwrap() {
+			ErrorKind::OfferRejected(explanation) => {
+				assert_eq!(
+					explanation.failed_checks[0].check,
+					"start lock time (10% lock time tolerance)"
+				);
+			}
+			e => panic!("Unexpected error {}", e),
+		}
+
+		// Tolerance out of range is rejected
+		swap::set_testing_cur_time(swap_btc.get_time_start_lock() - 1);
+		for bad in &[0, swap::MAX_LOCK_TIME_TOLERANCE_PERCENT + 1] {
+			let mut bad_offer = offer_10.clone();
+			bad_offer.lock_time_tolerance_percent = Some(*bad);
+			match accept(&bad_offer).err().unwrap() {
+				ErrorKind::OfferRejected(explanation) => {
+					assert_eq!(
+						explanation.failed_checks[0].check,
+						"lock time tolerance percent"
+					);
+					assert_eq!(explanation.failed_checks[0].actual, bad.to_string());
+				}
+				e => panic!("Unexpected error {}", e),
+			}
+		}
+	}
+
+	#[test]
+	#[serial]
+	fn test_swap_semantically_equal() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		assert!(swap.semantically_equal(&swap.clone()));
+
+		// Journal, errors and caches are not the part of the protocol state
+		let mut replayed = swap.clone();
+		for record in replayed.journal.iter_mut() {
+			record.time += 100;
+		}
+		swap::set_testing_cur_time(1567632152 + 200);
+		replayed.add_journal_message("Resumed".to_string());
+		replayed.last_check_error = Some("Node is not available".to_string());
+		replayed.electrum_node_uri1 = Some("127.0.0.1:8000".to_string());
+		assert!(swap.semantically_equal(&replayed));
+		assert!(replayed.semantically_equal(&swap));
+
+		// Protocol fields are compared
+		let mut other = replayed.clone();
+		other.primary_amount += 1;
+		assert!(!swap.semantically_equal(&other));
+		let mut other = replayed.clone();
+		other.state = StateId::SellerCancelled;
+		assert!(!swap.semantically_equal(&other));
+		let mut other = replayed.clone();
+		other.lock_slate.fee += 1;
+		assert!(!swap.semantically_equal(&other));
+	}
+
+	#[test]
+	#[serial]
+	fn test_fee_exceeds_amount() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		assert_eq!(swap::amount_minus_fee("MWC redeem", 100, 30).unwrap(), 70);
+		// Fee that takes everything or more is not silently turned into a zero output
+		assert_eq!(
+			swap::amount_minus_fee("MWC redeem", 100, 100)
+				.err()
+				.unwrap(),
+			ErrorKind::FeeExceedsAmount("MWC redeem".to_string(), 100, 100)
+		);
+		assert_eq!(
+			swap::amount_minus_fee("MWC refund", 100, 101)
+				.err()
+				.unwrap(),
+			ErrorKind::FeeExceedsAmount("MWC refund".to_string(), 101, 100)
+		);
+
+		// Seller can't create an offer that doesn't cover the refund fee
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let mut api_sell = BtcSwapApi::new_test(
+			Arc::new(nc.clone()),
+			Arc::new(Mutex::new(TestBtcNodeClient::new(1))),
+		);
+		let fee = tx_fee(1, 1, 1, None);
+		let res = api_sell.create_swap_offer(
+			&kc_sell,
+			&ctx_sell,
+			fee - 1,
+			3_000_000,
+			Currency::Btc,
+			btc_address(&kc_sell),
+			true,
+			30,
+			3,
+			3600,
+			3600,
+			"file".to_string(),
+			"/tmp/del.me".to_string(),
+			None,
+			None,
+			None,
+			&swap::SwapConfig::default(),
+		);
+		assert_eq!(
+			res.err().unwrap(),
+			ErrorKind::FeeExceedsAmount("MWC refund".to_string(), fee, fee - 1)
+		);
+	}
+
+	#[test]
+	#[serial]
+	fn test_secondary_network_mismatch() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+		global::set_mining_mode(ChainTypes::Floonet);
+
+		let nc = TestNodeClient::new(300_000);
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let (_swap, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		let (_id, _offer, secondary_update) = message.unwrap_offer().unwrap();
+		let btc_offer = secondary_update
+			.unwrap_btc()
+			.unwrap()
+			.unwrap_offer()
+			.unwrap();
+		assert_eq!(btc_offer.network, Some("testnet".to_string()));
 
-		// Simulate short refund lock time by passing height+4h
 		let kc_buy = keychain(2);
 		let ctx_buy = context_buy(&kc_buy);
-		let nc = TestNodeClient::new(height + 12 * 60);
+		let btc_ctx = ctx_buy.unwrap_buyer().unwrap().unwrap_btc().unwrap();
 
-		let (id, offer, secondary_update) = message.unwrap_offer().unwrap();
-		let res = BuyApi::accept_swap_offer(&kc_buy, &ctx_buy, id, offer, secondary_update, &nc);
+		assert!(BtcData::from_offer(&kc_buy, btc_offer.clone(), btc_ctx, Network::Floonet).is_ok());
+
+		// Mainnet MWC against testnet BTC must be rejected
+		match BtcData::from_offer(&kc_buy, btc_offer.clone(), btc_ctx, Network::Mainnet) {
+			Err(e) => {
+				assert_eq!(
+					e,
+					ErrorKind::SecondaryNetworkMismatch(
+						"Mainnet".to_string(),
+						"testnet".to_string()
+					)
+				);
+				assert_eq!(
+					format!("{}", e),
+					"Secondary currency is at testnet network, but MWC is at Mainnet network"
+				);
+			}
+			Ok(_) => panic!("Cross network offer is accepted"),
+		}
 
+		// Offers from older wallets don't have the network, they are rejected
+		let mut btc_offer = btc_offer;
+		btc_offer.network = None;
 		assert_eq!(
-			res.err().unwrap(),
-			ErrorKind::InvalidMessageData(
-				"Lock Slate inputs are not found at the chain".to_string()
-			)
-		); // Swap cannot be accepted
+			BtcData::from_offer(&kc_buy, btc_offer.clone(), btc_ctx, Network::Floonet)
+				.err()
+				.unwrap(),
+			ErrorKind::SecondaryNetworkMismatch("Floonet".to_string(), "unknown".to_string())
+		);
+		assert!(BtcData::from_offer(&kc_buy, btc_offer, btc_ctx, Network::Mainnet).is_err());
+	}
+
+	#[test]
+	#[serial]
+	fn test_verify_all_swap_trades() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(100_000);
+		let (swap, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		assert!(swap.verify_integrity().is_empty());
+
+		let trades_dir = std::env::temp_dir().join("mwc_swap_verify_all_test");
+		let _ = std::fs::remove_dir_all(&trades_dir);
+		trades::init_swap_trade_backend(trades_dir.to_str().unwrap(), &None);
+		let skey = key(&kc_sell, 3, 0);
+
+		let mut corrupted = swap.clone();
+		corrupted.id = uuid::Uuid::new_v4();
+		corrupted.refund_slate.amount += 1;
+
+		for s in &[&swap, &corrupted] {
+			let lock = trades::get_swap_lock(&s.id.to_string());
+			let _l = lock.lock();
+			trades::store_swap_trade(&ctx_sell, s, &skey, &*lock).unwrap();
+		}
+
+		let problems = trades::verify_all_swap_trades(&skey).unwrap();
+		assert_eq!(problems.len(), 1);
+		assert_eq!(problems[0].0, corrupted.id.to_string());
+		assert_eq!(
+			problems[0].1,
+			vec![format!(
+				"Refund slate amount {} and fee {} don't sum up to the lock amount {}",
+				corrupted.refund_slate.amount,
+				corrupted.refund_slate.fee,
+				corrupted.lock_slate.amount
+			)]
+		);
+		let _ = std::fs::remove_dir_all(&trades_dir);
+
+		// Amount and fee that overflow are reported as a problem
+		let mut overflow = swap.clone();
+		overflow.redeem_slate.fee = u64::MAX;
+		let problems = overflow.verify_integrity();
+		assert_eq!(problems.len(), 1);
+		assert!(problems[0].contains("overflow"));
+	}
+
+	#[test]
+	#[serial]
+	fn test_swap_message_log() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap, message) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		let swap_id = swap.id.to_string();
+
+		let trades_dir = std::env::temp_dir().join("mwc_swap_message_log_test");
+		let _ = std::fs::remove_dir_all(&trades_dir);
+		let trades_dir = trades_dir.to_str().unwrap().to_string();
+		trades::init_swap_trade_backend(&trades_dir, &None);
+		let skey = key(&kc_sell, 3, 0);
+		{
+			let lock = trades::get_swap_lock(&swap_id);
+			let _l = lock.lock();
+			trades::store_swap_trade(&ctx_sell, &swap, &skey, &*lock).unwrap();
+		}
+		assert!(trades::get_swap_message_log(&swap_id, &skey)
+			.unwrap()
+			.records
+			.is_empty());
+		match trades::get_swap_message_log("unknown", &skey) {
+			Err(ErrorKind::TradeNotFound(_)) => (),
+			r => panic!("Unexpected result {:?}", r),
+		}
+
+		// Offer carries the signature that must not get into the log
+		let (id, mut offer, secondary_update) = message.unwrap_offer().unwrap();
+		offer.redeem_participant.part_sig =
+			Some(swap.lock_slate.tx.body.kernels[0].excess_sig.clone());
+		let offer_message = Message::new(id, Update::Offer(offer), secondary_update);
+		trades::append_swap_message_log(&swap_id, MessageDirection::Sent, &offer_message, &skey)
+			.unwrap();
+		swap::set_testing_cur_time(1567632152 + 60);
+		let ack = Message::new(id, Update::MessageAcknowledge(1), SecondaryUpdate::Empty);
+		trades::append_swap_message_log(&swap_id, MessageDirection::Received, &ack, &skey).unwrap();
+
+		// Log is encrypted with the swap key
+		let log_file = std::path::Path::new(&trades_dir).join(format!("{}.msglog", swap_id));
+		let raw = std::fs::read_to_string(&log_file).unwrap();
+		assert_eq!(raw.lines().count(), 2);
+		assert!(!raw.contains("MessageAcknowledge"));
+		assert!(trades::get_swap_message_log(&swap_id, &key(&kc_sell, 4, 0)).is_err());
+
+		// Log is persisted and append-only
+		trades::init_swap_trade_backend(&trades_dir, &None);
+		let log = trades::get_swap_message_log(&swap_id, &skey).unwrap();
+		assert_eq!(log.swap_id, swap_id);
+		assert_eq!(log.records.len(), 2);
+		assert_eq!(log.records[0].time, 1567632152);
+		assert_eq!(log.records[0].direction, MessageDirection::Sent);
+		assert_eq!(log.records[0].message_type, "Offer");
+		assert_eq!(log.records[1].time, 1567632152 + 60);
+		assert_eq!(log.records[1].direction, MessageDirection::Received);
+		assert_eq!(log.records[1].message_type, "MessageAcknowledge");
+		assert_eq!(log.records[1].message, serde_json::to_value(&ack).unwrap());
+
+		let payload = &log.records[0].message;
+		assert_eq!(payload["id"], serde_json::json!(swap_id));
+		assert_eq!(
+			payload["inner"]["Offer"]["redeem_participant"]["part_sig"],
+			serde_json::json!(message::REDACTED_VALUE)
+		);
+		assert_eq!(
+			payload["inner"]["Offer"]["primary_amount"],
+			serde_json::to_value(&swap.primary_amount).unwrap()
+		);
+		let plain = serde_json::to_value(&offer_message).unwrap();
+		assert!(plain["inner"]["Offer"]["redeem_participant"]["part_sig"].is_string());
+
+		// Log is exported and imported with the trade
+		let export_file = std::env::temp_dir().join("mwc_swap_message_log_test.trade");
+		let export_file = export_file.to_str().unwrap();
+		trades::export_trade(&swap_id, export_file).unwrap();
+		let _ = std::fs::remove_dir_all(&trades_dir);
+		trades::init_swap_trade_backend(&trades_dir, &None);
+		{
+			let lock = trades::get_swap_lock(&"import".to_string());
+			let _l = lock.lock();
+			trades::import_trade(export_file, &skey, &*lock).unwrap();
+		}
+		let imported = trades::get_swap_message_log(&swap_id, &skey).unwrap();
+		assert_eq!(imported.records.len(), 2);
+		let _ = std::fs::remove_file(export_file);
+		let _ = std::fs::remove_file(format!("{}.msglog", export_file));
+
+		let _ = std::fs::remove_dir_all(&trades_dir);
+	}
+
+	#[test]
+	#[serial]
+	fn test_swap_storage_shutdown_recovery() {
+		set_test_mode(true);
+		swap::set_testing_cur_time(1567632152);
+
+		let kc_sell = keychain(1);
+		let ctx_sell = context_sell(&kc_sell);
+		let nc = TestNodeClient::new(300_000);
+		let (swap, _) = seller_offer_message(&kc_sell, &ctx_sell, &nc);
+		let swap_id = swap.id.to_string();
+
+		let trades_dir = std::env::temp_dir().join("mwc_swap_shutdown_test");
+		let _ = std::fs::remove_dir_all(&trades_dir);
+		let trades_dir = trades_dir.to_str().unwrap().to_string();
+		trades::init_swap_trade_backend(&trades_dir, &None);
+		let skey = key(&kc_sell, 3, 0);
+		let lock = trades::get_swap_lock(&swap_id);
+		let stored_state = |lock: &Mutex<()>| {
+			let _l = lock.lock();
+			trades::get_swap_trade(&swap_id, &skey, lock)
+				.unwrap()
+				.1
+				.state
+		};
+		{
+			let _l = lock.lock();
+			trades::store_swap_trade(&ctx_sell, &swap, &skey, &*lock).unwrap();
+		}
+
+		// Status is changed, wallet is stopped while the new state is written
+		let mut updated = swap.clone();
+		updated.state = StateId::SellerCancelled;
+		let backup = trades::write_swap_trade_backup(&ctx_sell, &updated, &skey).unwrap();
+		let content = std::fs::read_to_string(&backup).unwrap();
+		std::fs::write(&backup, &content[..content.len() / 2]).unwrap();
+		// Partial file is deleted at restart, the previous state is kept
+		trades::init_swap_trade_backend(&trades_dir, &None);
+		assert!(!backup.exists());
+		assert_eq!(stored_state(&*lock), swap.state);
+
+		// New state is written, but wallet is stopped before it is committed
+		let backup = trades::write_swap_trade_backup(&ctx_sell, &updated, &skey).unwrap();
+		trades::init_swap_trade_backend(&trades_dir, &None);
+		assert!(!backup.exists());
+		assert_eq!(stored_state(&*lock), StateId::SellerCancelled);
+
+		// Shutdown waits until the swap in the middle of update is stored
+		let (tx, rx) = std::sync::mpsc::channel();
+		let mut updated = swap.clone();
+		updated.state = StateId::SellerSendingInitRedeemMessage;
+		let (lock2, ctx2, skey2) = (lock.clone(), ctx_sell.clone(), skey.clone());
+		let update_thread = std::thread::spawn(move || {
+			let _l = lock2.lock();
+			tx.send(()).unwrap();
+			std::thread::sleep(std::time::Duration::from_millis(200));
+			trades::store_swap_trade(&ctx2, &updated, &skey2, &*lock2).unwrap();
+		});
+		rx.recv().unwrap();
+		trades::shutdown_swap_trades();
+		{
+			// No processing after the shutdown
+			let _l = lock.lock();
+			assert!(trades::get_swap_trade(&swap_id, &skey, &*lock).is_err());
+		}
+		update_thread.join().unwrap();
+
+		trades::init_swap_trade_backend(&trades_dir, &None);
+		assert_eq!(
+			stored_state(&*lock),
+			StateId::SellerSendingInitRedeemMessage
+		);
+		let _ = std::fs::remove_dir_all(&trades_dir);
 	}
 
 	// Because of gonden output new line symbol we skipping Windows.
@@ -526,6 +4155,8 @@ mod tests {
 				"/tmp/del.me".to_string(),
 				None,
 				None,
+				None,
+				&swap::SwapConfig::default(),
 			)
 			.unwrap();
 
@@ -832,6 +4463,11 @@ mod tests {
 			.process(Input::Execute, &mut swap_sell, &ctx_sell, &tx_conf)
 			.unwrap();
 		assert_eq!(swap_sell.state, StateId::SellerWaitingForLockConfirmations);
+		assert_eq!(
+			swap_sell.last_broadcast_tx(MwcTxAction::Lock),
+			Some(swap_sell.lock_slate.tx.clone())
+		);
+		assert!(swap_sell.last_broadcast_tx(MwcTxAction::Refund).is_none());
 		match sell_resp.action.unwrap() {
 			Action::WaitForLockConfirmations {
 				mwc_required: required,
@@ -1109,6 +4745,17 @@ mod tests {
 			buy_resp.action.unwrap().get_id_str(),
 			"WaitForMwcConfirmations"
 		);
+		// Exactly the published redeem is kept for the re-broadcast
+		assert_eq!(
+			swap_buy.last_broadcast_tx(MwcTxAction::Redeem),
+			Some(swap_buy.redeem_slate.tx.clone())
+		);
+		let swap_buy_ser = serde_json::to_string(&swap_buy).unwrap();
+		let swap_buy_deser: Swap = serde_json::from_str(&swap_buy_ser).unwrap();
+		assert_eq!(
+			swap_buy_deser.last_broadcast_tx(MwcTxAction::Redeem),
+			Some(swap_buy.redeem_slate.tx.clone())
+		);
 
 		// Buyer: almost done, just need to wait for confirmations
 		nc.mine_block();
@@ -1581,6 +5228,8 @@ mod tests {
 					"/tmp/del.me".to_string(),
 					None,
 					None,
+					None,
+					&swap::SwapConfig::default(),
 				)
 				.unwrap();
 			let fsm_sell = api_sell.get_fsm(&kc_sell, &swap_sell);
@@ -1780,6 +5429,43 @@ mod tests {
 			);
 		}
 
+		{
+			// Offer requires the feature that we don't know about
+			let (id, mut offer, secondary_update) = message1.clone().unwrap_offer().unwrap();
+			offer.required_features = vec!["btc".to_string(), "future_feature".to_string()];
+			assert_eq!(
+				BuyApi::accept_swap_offer(
+					&kc_buy,
+					&ctx_buy,
+					id,
+					offer.clone(),
+					secondary_update.clone(),
+					&nc
+				)
+				.err()
+				.unwrap(),
+				ErrorKind::UnsupportedSwapFeature("future_feature".to_string())
+			);
+		}
+		{
+			// Newer version of the protocol with known features only is fine
+			let (id, mut offer, secondary_update) = message1.clone().unwrap_offer().unwrap();
+			offer.version = CURRENT_VERSION + 1;
+			offer.required_features = vec!["btc".to_string(), "seller_lock_first".to_string()];
+			assert_eq!(
+				BuyApi::accept_swap_offer(
+					&kc_buy,
+					&ctx_buy,
+					id,
+					offer.clone(),
+					secondary_update.clone(),
+					&nc
+				)
+				.is_ok(),
+				true
+			);
+		}
+
 		{
 			// Offer lock slate has height (not important)...
 			let (id, mut offer, secondary_update) = message1.clone().unwrap_offer().unwrap();
@@ -1845,6 +5531,31 @@ mod tests {
 			);
 		}
 
+		{
+			// Offer lock slate spends the same input twice
+			let (id, mut offer, secondary_update) = message1.clone().unwrap_offer().unwrap();
+			let mut lock_slate: Slate = offer.lock_slate.into();
+			let input = lock_slate.tx.body.inputs[0].clone();
+			lock_slate.tx.body.inputs.push(input.clone());
+			offer.lock_slate = VersionedSlate::into_version(lock_slate, SlateVersion::V3);
+			assert_eq!(
+				BuyApi::accept_swap_offer(
+					&kc_buy,
+					&ctx_buy,
+					id,
+					offer.clone(),
+					secondary_update.clone(),
+					&nc
+				)
+				.err()
+				.unwrap(),
+				ErrorKind::InvalidMessageData(format!(
+					"Lock Slate has duplicate input {}",
+					to_hex(input.commit.0.to_vec())
+				))
+			);
+		}
+
 		// Trying to tweak the fees
 		{
 			// Offer lock slate has height
@@ -1925,8 +5636,11 @@ mod tests {
 					secondary_update.clone(),
 					&nc
 				)
-				.is_err(),
-				true
+				.err()
+				.unwrap(),
+				ErrorKind::InvalidMessageData(
+					"Lock Slate fee doesn't match expected value".to_string()
+				)
 			);
 		}
 
@@ -2007,6 +5721,91 @@ mod tests {
 				true
 			);
 		}
+		{
+			// Refund fee might be higher than minimal, but within the limits
+			let min_fee = tx_fee(1, 1, 1, None);
+			for (fee, accepted) in &[
+				(min_fee - 1, false),
+				(min_fee * 2, true),
+				(min_fee * buyer::REFUND_FEE_MAX_MULTIPLIER, true),
+				(min_fee * buyer::REFUND_FEE_MAX_MULTIPLIER + 1, false),
+			] {
+				let (id, mut offer, secondary_update) = message1.clone().unwrap_offer().unwrap();
+				let mut refund_slate: Slate = offer.refund_slate.into();
+				refund_slate.amount = refund_slate.amount + refund_slate.fee - fee;
+				refund_slate.fee = *fee;
+				refund_slate.tx.body.kernels[0].features = KernelFeatures::HeightLocked {
+					fee: *fee,
+					lock_height: refund_slate.lock_height,
+				};
+				offer.refund_slate = VersionedSlate::into_version(refund_slate, SlateVersion::V3);
+				assert_eq!(
+					BuyApi::accept_swap_offer(
+						&kc_buy,
+						&ctx_buy,
+						id,
+						offer.clone(),
+						secondary_update.clone(),
+						&nc
+					)
+					.is_ok(),
+					*accepted
+				);
+			}
+		}
+		{
+			// Refund lock height below the required one is explained with expected and actual values
+			let (id, offer, secondary_update) = message1.clone().unwrap_offer().unwrap();
+			let swap_ok = BuyApi::accept_swap_offer(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer.clone(),
+				secondary_update.clone(),
+				&nc,
+			)
+			.unwrap();
+			let height = nc.get_chain_tip().unwrap().0;
+			let min_lock_height = (height
+				+ (swap_ok.get_time_mwc_lock() - swap::get_cur_time()) as u64 / 60)
+				* 9 / 10;
+
+			let mut offer = offer;
+			let mut refund_slate: Slate = offer.refund_slate.into();
+			refund_slate.lock_height = min_lock_height - 1;
+			refund_slate.tx.body.kernels[0].features = KernelFeatures::HeightLocked {
+				fee: refund_slate.fee,
+				lock_height: refund_slate.lock_height,
+			};
+			offer.refund_slate = VersionedSlate::into_version(refund_slate, SlateVersion::V3);
+			let err = BuyApi::accept_swap_offer(
+				&kc_buy,
+				&ctx_buy,
+				id,
+				offer,
+				secondary_update.clone(),
+				&nc,
+			)
+			.unwrap_err();
+			assert_eq!(
+				err.rejection_explanation(),
+				Some(&RejectionExplanation {
+					failed_checks: vec![FailedCheck {
+						check: "refund slate lock height".to_string(),
+						expected: format!(">= {}", min_lock_height),
+						actual: format!("{}", min_lock_height - 1),
+					}]
+				})
+			);
+			assert_eq!(
+				err.to_string(),
+				format!(
+					"Swap offer is rejected, refund slate lock height is {}, expected >= {}",
+					min_lock_height - 1,
+					min_lock_height
+				)
+			);
+		}
 		{
 			// Refund slate must have expected lock value, tweaking kernel, adding one more plain one
 			let (id, mut offer, secondary_update) = message1.clone().unwrap_offer().unwrap();
@@ -49,6 +49,9 @@ pub enum ErrorKind {
 	/// Round 2 missing field
 	#[fail(display = "Multisig Round 2 missing field")]
 	Round2Missing,
+	/// Participant data from the other party is malformed
+	#[fail(display = "Multisig Participant data is malformed, {}", _0)]
+	ParticipantMalformed(String),
 	/// Secp error
 	#[fail(display = "Multisig Secp: {}", _0)]
 	Secp(String),
@@ -460,6 +460,48 @@ impl ParticipantData {
 		})
 	}
 
+	/// Validate the data of the participant that finished the round 1, before it is imported.
+	/// Revealed commitment and round 1 public keys are expected, round 2 data must be empty.
+	pub fn validate_round_1(&self, secp: &Secp256k1) -> Result<(), ErrorKind> {
+		if self.partial_commitment_hash.is_some() {
+			return Err(ErrorKind::ParticipantMalformed(
+				"unexpected commitment hash".to_string(),
+			));
+		}
+		let commit = self
+			.partial_commitment
+			.as_ref()
+			.ok_or(ErrorKind::ParticipantMalformed(
+				"partial commitment is missing".to_string(),
+			))?;
+		commit.to_pubkey(secp).map_err(|e| {
+			ErrorKind::ParticipantMalformed(format!("invalid partial commitment, {:?}", e))
+		})?;
+		for (name, t) in vec![("t_1", &self.t_1), ("t_2", &self.t_2)] {
+			match t {
+				Some(t) if *t != PublicKey::new() => (),
+				Some(_) => {
+					return Err(ErrorKind::ParticipantMalformed(format!(
+						"invalid public key {}",
+						name
+					)))
+				}
+				None => {
+					return Err(ErrorKind::ParticipantMalformed(format!(
+						"public key {} is missing",
+						name
+					)))
+				}
+			}
+		}
+		if self.tau_x.is_some() {
+			return Err(ErrorKind::ParticipantMalformed(
+				"unexpected round 2 data".to_string(),
+			));
+		}
+		Ok(())
+	}
+
 	/// Check if partial_commitment match the hash
 	fn reveal(&mut self, partial_commitment: &Commitment) -> Result<(), ErrorKind> {
 		let hash = self
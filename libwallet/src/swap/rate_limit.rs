@@ -0,0 +1,142 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rate limit for the outbound swap messages, so retries don't flood the counterparty
+//! and the transport. The interval is applied per swap and per counterparty.
+
+use super::config::SwapConfig;
+use super::swap;
+use super::ErrorKind;
+use grin_util::Mutex;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Time of the last sent messages
+#[derive(Debug, Default)]
+pub struct MessageRateLimiter {
+	last_by_swap: HashMap<Uuid, i64>,
+	last_by_counterparty: HashMap<String, i64>,
+}
+
+impl MessageRateLimiter {
+	/// New limiter, nothing is sent yet
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Check if the message can be sent at time 'now'. If the previous message for the swap
+	/// or the counterparty was sent less than min_interval_sec ago, ErrorKind::RateLimited
+	/// with the seconds to wait is returned.
+	pub fn check(
+		&self,
+		swap_id: &Uuid,
+		counterparty: &str,
+		now: i64,
+		min_interval_sec: i64,
+	) -> Result<(), ErrorKind> {
+		if min_interval_sec <= 0 {
+			return Ok(());
+		}
+		let last = std::cmp::max(
+			self.last_by_swap.get(swap_id).cloned(),
+			self.last_by_counterparty.get(counterparty).cloned(),
+		);
+		if let Some(last) = last {
+			let retry_after = last + min_interval_sec - now;
+			if retry_after > 0 {
+				return Err(ErrorKind::RateLimited(retry_after));
+			}
+		}
+		Ok(())
+	}
+
+	/// Register the message that is sent at time 'now'. Only delivered messages are
+	/// registered, failed attempts can be retried right away.
+	pub fn register(&mut self, swap_id: &Uuid, counterparty: &str, now: i64) {
+		self.last_by_swap.insert(swap_id.clone(), now);
+		self.last_by_counterparty
+			.insert(counterparty.to_string(), now);
+	}
+}
+
+lazy_static! {
+	static ref MESSAGE_RATE_LIMITER: Mutex<MessageRateLimiter> =
+		Mutex::new(MessageRateLimiter::new());
+}
+
+// The same counterparty can be reached with different address formats
+fn counterparty(address: &str) -> String {
+	swap::counterparty_identity(address).unwrap_or(address.to_string())
+}
+
+/// Check if the swap message can be sent to the counterparty address now, see SwapConfig::message_min_interval_sec
+pub fn check_message_rate(
+	swap_id: &Uuid,
+	address: &str,
+	config: &SwapConfig,
+) -> Result<(), ErrorKind> {
+	MESSAGE_RATE_LIMITER.lock().check(
+		swap_id,
+		&counterparty(address),
+		swap::get_cur_time(),
+		config.message_min_interval_sec,
+	)
+}
+
+/// Register the swap message that is delivered to the counterparty address
+pub fn register_message_sent(swap_id: &Uuid, address: &str) {
+	MESSAGE_RATE_LIMITER
+		.lock()
+		.register(swap_id, &counterparty(address), swap::get_cur_time());
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_message_rate_limiter() {
+		let mut limiter = MessageRateLimiter::new();
+		let swap1 = Uuid::new_v4();
+		let swap2 = Uuid::new_v4();
+
+		limiter.check(&swap1, "alice", 1000, 10).unwrap();
+		// Nothing is registered until the message is sent
+		limiter.check(&swap1, "alice", 1000, 10).unwrap();
+		limiter.register(&swap1, "alice", 1000);
+		// Too fast for the same swap
+		assert_eq!(
+			limiter.check(&swap1, "alice", 1004, 10),
+			Err(ErrorKind::RateLimited(6))
+		);
+		// Another swap with the same counterparty has to wait as well
+		assert_eq!(
+			limiter.check(&swap2, "alice", 1009, 10),
+			Err(ErrorKind::RateLimited(1))
+		);
+		// Another counterparty is fine
+		limiter.check(&swap2, "bob", 1009, 10).unwrap();
+		limiter.register(&swap2, "bob", 1009);
+		// Rejected attempts don't move the interval
+		limiter.check(&swap1, "alice", 1010, 10).unwrap();
+		assert_eq!(
+			limiter.check(&swap2, "bob", 1015, 10),
+			Err(ErrorKind::RateLimited(4))
+		);
+
+		// No limit
+		limiter.register(&swap1, "alice", 1010);
+		limiter.check(&swap1, "alice", 1010, 0).unwrap();
+	}
+}
@@ -0,0 +1,213 @@
+// Copyright 2020 The MWC Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Re-broadcast of the published redeem and refund transactions. With poor network conditions
+//! the transaction might be dropped from the mempool, so it needs to be published again until
+//! it is confirmed.
+
+use crate::swap::fsm::state::StateId;
+use crate::swap::types::{MwcTxAction, SwapTransactionsConfirmations};
+use crate::swap::{swap, Context, ErrorKind, Keychain, Swap, SwapApi};
+use crate::NodeClient;
+use std::cmp;
+use std::fmt;
+
+/// Default interval between re-broadcasts, seconds
+pub const DEFAULT_REBROADCAST_INTERVAL: i64 = 60;
+/// Backoff never makes the interval longer than that, seconds
+pub const MAX_REBROADCAST_INTERVAL: i64 = 30 * 60;
+
+/// Transaction that is published and waiting for confirmations
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingTx {
+	/// Buyer MWC redeem
+	MwcRedeem,
+	/// Seller MWC refund
+	MwcRefund,
+	/// Seller secondary currency redeem
+	SecondaryRedeem,
+	/// Buyer secondary currency refund
+	SecondaryRefund,
+}
+
+impl fmt::Display for PendingTx {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let disp = match &self {
+			PendingTx::MwcRedeem => "MWC redeem transaction",
+			PendingTx::MwcRefund => "MWC refund transaction",
+			PendingTx::SecondaryRedeem => "Secondary redeem transaction",
+			PendingTx::SecondaryRefund => "Secondary refund transaction",
+		};
+		write!(f, "{}", disp)
+	}
+}
+
+/// Status of the swap for re-broadcast
+#[derive(Debug, Clone, PartialEq)]
+pub enum RebroadcastStatus {
+	/// Swap is not waiting for redeem or refund confirmations
+	NotPending,
+	/// Transaction is still not confirmed, it is time to re-broadcast it
+	Pending(PendingTx),
+	/// Transaction is confirmed, nothing to do
+	Confirmed(PendingTx),
+	/// Conflicting transaction is on the chain. Re-broadcast will fail, must be stopped.
+	Conflict(PendingTx),
+}
+
+impl RebroadcastStatus {
+	/// True if re-broadcasting must be stopped
+	pub fn is_final(&self) -> bool {
+		match self {
+			RebroadcastStatus::Pending(_) => false,
+			_ => true,
+		}
+	}
+}
+
+/// Check if the swap has a published transaction that is waiting for confirmations.
+/// MWC confirmations are None until the transaction is mined, the secondary ones are Some(0)
+/// while the transaction is in the mempool.
+pub fn get_rebroadcast_status(
+	swap: &Swap,
+	tx_conf: &SwapTransactionsConfirmations,
+) -> RebroadcastStatus {
+	let (tx, conf, conflict, posted) = match swap.state {
+		StateId::BuyerWaitForRedeemMwcConfirmations => (
+			PendingTx::MwcRedeem,
+			tx_conf.mwc_redeem_conf,
+			tx_conf.mwc_refund_conf,
+			swap.posted_redeem,
+		),
+		StateId::SellerWaitingForRefundConfirmations => (
+			PendingTx::MwcRefund,
+			tx_conf.mwc_refund_conf,
+			tx_conf.mwc_redeem_conf,
+			swap.posted_refund,
+		),
+		StateId::SellerWaitingForRedeemConfirmations => (
+			PendingTx::SecondaryRedeem,
+			tx_conf.secondary_redeem_conf,
+			tx_conf.secondary_refund_conf,
+			swap.posted_redeem,
+		),
+		StateId::BuyerWaitingForRefundConfirmations => (
+			PendingTx::SecondaryRefund,
+			tx_conf.secondary_refund_conf,
+			tx_conf.secondary_redeem_conf,
+			swap.posted_refund,
+		),
+		_ => return RebroadcastStatus::NotPending,
+	};
+
+	if posted.is_none() {
+		return RebroadcastStatus::NotPending;
+	}
+	if conflict.is_some() {
+		return RebroadcastStatus::Conflict(tx);
+	}
+	if conf.unwrap_or(0) > 0 {
+		return RebroadcastStatus::Confirmed(tx);
+	}
+	RebroadcastStatus::Pending(tx)
+}
+
+/// Publish the pending transaction one more time
+pub(crate) fn rebroadcast_tx<'a, C, K>(
+	swap_api: &(dyn SwapApi<K> + 'a),
+	node_client: &C,
+	keychain: &K,
+	swap: &mut Swap,
+	context: &Context,
+	tx: &PendingTx,
+) -> Result<(), ErrorKind>
+where
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	match tx {
+		PendingTx::MwcRedeem => swap.rebroadcast_mwc_tx(node_client, MwcTxAction::Redeem),
+		PendingTx::MwcRefund => swap.rebroadcast_mwc_tx(node_client, MwcTxAction::Refund),
+		PendingTx::SecondaryRedeem => {
+			swap_api.publish_secondary_transaction(keychain, swap, context, true)
+		}
+		PendingTx::SecondaryRefund => {
+			let refund_address = swap.unwrap_buyer()?;
+			swap_api.post_secondary_refund_tx(keychain, context, swap, refund_address, true)
+		}
+	}
+}
+
+/// Timing for the re-broadcast. Every next attempt waits twice longer than the previous one.
+#[derive(Debug, Clone)]
+pub struct RebroadcastSchedule {
+	interval: i64,
+	max_interval: i64,
+	attempts: u32,
+	next_time: i64,
+}
+
+impl RebroadcastSchedule {
+	/// New schedule, the first attempt is due now.
+	/// interval - seconds before the second attempt
+	pub fn new(interval: i64, now: i64) -> Self {
+		let interval = cmp::max(interval, 1);
+		Self {
+			interval,
+			max_interval: cmp::max(interval, MAX_REBROADCAST_INTERVAL),
+			attempts: 0,
+			next_time: now,
+		}
+	}
+
+	/// True if it is time for the next attempt
+	pub fn is_due(&self, now: i64) -> bool {
+		now >= self.next_time
+	}
+
+	/// Interval before the next attempt
+	pub fn next_interval(&self) -> i64 {
+		let factor = 1i64.checked_shl(cmp::min(self.attempts, 30)).unwrap_or(1);
+		cmp::min(self.interval.saturating_mul(factor), self.max_interval)
+	}
+
+	/// Register the attempt that was done at 'now'
+	pub fn attempted(&mut self, now: i64) {
+		self.next_time = now + self.next_interval();
+		self.attempts += 1;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_rebroadcast_schedule() {
+		let mut schedule = RebroadcastSchedule::new(60, 1000);
+		assert!(schedule.is_due(1000));
+
+		let mut now = 1000;
+		let mut intervals = vec![];
+		for _ in 0..8 {
+			schedule.attempted(now);
+			assert!(!schedule.is_due(now + 1));
+			let interval = schedule.next_time - now;
+			intervals.push(interval);
+			now += interval;
+			assert!(schedule.is_due(now));
+		}
+		assert_eq!(intervals, vec![60, 120, 240, 480, 960, 1800, 1800, 1800]);
+	}
+}
@@ -13,22 +13,24 @@
 // limitations under the License.
 
 #[cfg(test)]
+use super::config::SwapConfig;
 use super::is_test_mode;
 use super::message::*;
 use super::multisig::{Builder as MultisigBuilder, ParticipantData as MultisigParticipant};
 use super::swap;
 use super::swap::{signature_as_secret, tx_add_input, tx_add_output, Swap};
 use super::types::*;
-use super::{ErrorKind, Keychain, CURRENT_VERSION};
+use super::{ErrorKind, Keychain, CURRENT_VERSION, LOCK_TIME_TOLERANCE_FEATURE};
 use crate::swap::fsm::state::StateId;
 use crate::{ParticipantData as TxParticipant, Slate, SlateVersion, VersionedSlate};
 use chrono::{DateTime, NaiveDateTime, Utc};
 use grin_core::libtx::{build, proof, tx_fee};
-use grin_keychain::{BlindSum, BlindingFactor};
+use grin_keychain::{BlindSum, BlindingFactor, Identifier};
 use grin_util::secp::aggsig;
 use grin_util::secp::key::{PublicKey, SecretKey};
 use grin_util::secp::pedersen::{Commitment, RangeProof};
 use rand::thread_rng;
+use std::collections::HashMap;
 
 #[cfg(test)]
 use uuid::Uuid;
@@ -60,10 +62,13 @@ impl SellApi {
 		buyer_destination_address: String,
 		electrum_node_uri1: Option<String>,
 		electrum_node_uri2: Option<String>,
+		external_ref: Option<String>,
+		config: &SwapConfig,
 	) -> Result<Swap, ErrorKind> {
 		#[cfg(test)]
 		let test_mode = is_test_mode();
 		let scontext = context.unwrap_seller()?;
+		context.validate_nonces()?;
 		let multisig = MultisigBuilder::new(
 			2,
 			primary_amount,
@@ -91,6 +96,18 @@ impl SellApi {
 		let network = Network::current_network()?;
 		let secondary_fee = secondary_currency.get_default_fee(&network);
 
+		let lock_time_tolerance_percent = config.lock_time_tolerance_percent(secondary_currency);
+		if lock_time_tolerance_percent == 0
+			|| lock_time_tolerance_percent > swap::MAX_LOCK_TIME_TOLERANCE_PERCENT
+		{
+			return Err(ErrorKind::Generic(format!(
+				"Lock time tolerance {}% for {} is out of range, expected 1-{}%",
+				lock_time_tolerance_percent,
+				secondary_currency,
+				swap::MAX_LOCK_TIME_TOLERANCE_PERCENT
+			)));
+		}
+
 		let mut swap = Swap {
 			id,
 			version: CURRENT_VERSION,
@@ -128,6 +145,19 @@ impl SellApi {
 			secondary_fee,
 			electrum_node_uri1,
 			electrum_node_uri2,
+			redeem_fee_base: None,
+			broadcast_txs: HashMap::new(),
+			buyer_min_mwc_confirmations: None,
+			buyer_min_secondary_confirmations: None,
+			external_ref,
+			// Default tolerance is not sent, the offer stays readable by the older wallets
+			lock_time_tolerance_percent: if lock_time_tolerance_percent
+				== swap::LOCK_TIME_TOLERANCE_PERCENT
+			{
+				None
+			} else {
+				Some(lock_time_tolerance_percent)
+			},
 			last_process_error: None,
 			last_check_error: None,
 			wait_for_backup1: false,
@@ -146,7 +176,9 @@ impl SellApi {
 			lock_slate.id = Uuid::parse_str("55b79f54-c40d-45e1-9544-a52dcf426db2").unwrap();
 		}
 
-		lock_slate.fee = tx_fee(scontext.inputs.len(), 2, 1, None);
+		// Multisig output and the change output(s)
+		let lock_outputs = 2 + scontext.change_split.iter().count();
+		lock_slate.fee = tx_fee(scontext.inputs.len(), lock_outputs, 1, None);
 		lock_slate.amount = primary_amount;
 		lock_slate.height = height;
 
@@ -157,20 +189,15 @@ impl SellApi {
 			refund_slate.id = Uuid::parse_str("703fac15-913c-4e66-a7c2-5f648ca4ca7d").unwrap();
 		}
 		refund_slate.fee = tx_fee(1, 1, 1, None);
-		if primary_amount <= refund_slate.fee {
-			return Err(ErrorKind::Generic(
-				"MWC amount to trade is too low, it doesn't cover the fees".to_string(),
-			)
-			.into());
-		}
+		let refund_amount = swap::amount_minus_fee("MWC refund", primary_amount, refund_slate.fee)?;
 
 		refund_slate.height = height;
 		// Calculating lock height from locking time. For MWC the mining speed is about 1 minute
 		refund_slate.lock_height = height + (mwc_lock_time - start_time) as u64 / 60 + 1;
-		refund_slate.amount = primary_amount.saturating_sub(refund_slate.fee);
+		refund_slate.amount = refund_amount;
 
 		// Don't lock for more than 30 days.
-		let max_lock_time = 1440 * 30;
+		let max_lock_time = config.max_mwc_lock_blocks;
 
 		if refund_slate.lock_height - refund_slate.height > max_lock_time {
 			return Err(ErrorKind::Generic(
@@ -223,6 +250,22 @@ impl SellApi {
 	) -> Result<(), ErrorKind> {
 		assert!(swap.is_seller());
 
+		// Buyer must echo the offer reference, or it proposes its own. Older Buyer doesn't
+		// support the reference and doesn't echo it, then it stays local to the Seller.
+		match &swap.external_ref {
+			Some(_)
+				if accept_offer.external_ref.is_some()
+					&& swap.external_ref != accept_offer.external_ref =>
+			{
+				return Err(ErrorKind::ExternalRefMismatch(
+					swap.external_ref.clone(),
+					accept_offer.external_ref,
+				));
+			}
+			Some(_) => (),
+			None => swap.external_ref = accept_offer.external_ref.clone(),
+		}
+
 		// Finalize multisig proof
 		let proof = Self::finalize_multisig(keychain, swap, context, accept_offer.multisig)?;
 
@@ -271,6 +314,7 @@ impl SellApi {
 		}
 
 		let mut redeem_slate: Slate = init_redeem.redeem_slate.into();
+		swap.check_zero_fee("MWC redeem", redeem_slate.fee)?;
 
 		// Validate adaptor signature
 		let (pub_nonce_sum, _, message) = swap.redeem_tx_fields(keychain.secp(), &redeem_slate)?;
@@ -294,6 +338,7 @@ impl SellApi {
 		}
 
 		swap.redeem_slate = redeem_slate;
+		swap.check_primary_amount()?;
 		swap.adaptor_signature = Some(init_redeem.adaptor_signature);
 
 		Self::sign_redeem_slate(keychain, swap, context)?;
@@ -380,6 +425,13 @@ impl SellApi {
 				secondary_confirmations: swap.secondary_confirmations,
 				message_exchange_time_sec: swap.message_exchange_time_sec,
 				redeem_time_sec: swap.redeem_time_sec,
+				// Older wallets would compute another lock deadline, they must reject the offer
+				required_features: match swap.lock_time_tolerance_percent {
+					Some(_) => vec![LOCK_TIME_TOLERANCE_FEATURE.to_string()],
+					None => vec![],
+				},
+				external_ref: swap.external_ref.clone(),
+				lock_time_tolerance_percent: swap.lock_time_tolerance_percent,
 			}),
 			secondary_update,
 		)
@@ -440,6 +492,28 @@ impl SellApi {
 		Ok(proof)
 	}
 
+	/// Lock slate change outputs: <key id, amount>. Round change is split in two outputs.
+	fn lock_change_outputs(
+		scontext: &SellerContext,
+		change: u64,
+	) -> Result<Vec<(Identifier, u64)>, ErrorKind> {
+		match &scontext.change_split {
+			None => Ok(vec![(scontext.change_output.clone(), change)]),
+			Some((split_identifier, split_amount)) => {
+				if *split_amount >= change {
+					return Err(ErrorKind::Generic(format!(
+						"Lock change split {} doesn't fit into the change {}",
+						split_amount, change
+					)));
+				}
+				Ok(vec![
+					(scontext.change_output.clone(), change - split_amount),
+					(split_identifier.clone(), *split_amount),
+				])
+			}
+		}
+	}
+
 	/// Convenience function to calculate the secret that is used for signing the lock slate
 	fn lock_tx_secret<K: Keychain>(
 		keychain: &K,
@@ -455,9 +529,11 @@ impl SellApi {
 			sum = sum.sub_key_id(input_identifier.to_value_path(*input_amount));
 		}
 
-		// Change output, partial multisig output, offset
+		// Change output(s), partial multisig output, offset
+		for (change_identifier, change_amount) in Self::lock_change_outputs(scontext, change)? {
+			sum = sum.add_key_id(change_identifier.to_value_path(change_amount));
+		}
 		sum = sum
-			.add_key_id(scontext.change_output.to_value_path(change))
 			.add_blinding_factor(BlindingFactor::from_secret_key(
 				swap.multisig_secret(keychain, context)?,
 			))
@@ -490,8 +566,15 @@ impl SellApi {
 		for (input_identifier, _, input_amount) in &scontext.inputs {
 			elems.push(build::input(*input_amount, input_identifier.clone()));
 		}
-		elems.push(build::output(change, scontext.change_output.clone()));
-		slate.add_transaction_elements(keychain, &proof::ProofBuilder::new(keychain), elems)?;
+		for (change_identifier, change_amount) in Self::lock_change_outputs(scontext, change)? {
+			elems.push(build::output(change_amount, change_identifier));
+		}
+		slate.add_transaction_elements(
+			keychain,
+			&proof::ProofBuilder::new(keychain),
+			elems,
+			false,
+		)?;
 		slate.tx.offset =
 			BlindingFactor::from_secret_key(SecretKey::new(keychain.secp(), &mut thread_rng()));
 
@@ -543,6 +626,14 @@ impl SellApi {
 
 		// Add multisig output to slate
 		tx_add_output(slate, commit, proof);
+		// The real multisig proof is in place, the fee must still cover the transaction
+		let required_fee = swap::tx_required_fee(&slate.tx)?;
+		if slate.fee < required_fee {
+			return Err(ErrorKind::Generic(format!(
+				"Lock slate fee {} is lower than required {}",
+				slate.fee, required_fee
+			)));
+		}
 
 		// Sign + finalize slate
 		slate.fill_round_2(keychain, &sec_key, &context.lock_nonce, swap.participant_id)?;
@@ -564,7 +655,7 @@ impl SellApi {
 			.sub_blinding_factor(BlindingFactor::from_secret_key(
 				swap.multisig_secret(keychain, context)?,
 			))
-			.add_key_id(scontext.refund_output.to_value_path(swap.refund_amount()))
+			.add_key_id(scontext.refund_output.to_value_path(swap.refund_amount()?))
 			.sub_blinding_factor(swap.refund_slate.tx.offset.clone());
 		let sec_key = keychain.blind_sum(&sum)?.secret_key(keychain.secp())?;
 
@@ -577,7 +668,7 @@ impl SellApi {
 		context: &Context,
 	) -> Result<(), ErrorKind> {
 		let scontext = context.unwrap_seller()?;
-		let refund_amount = swap.refund_amount();
+		let refund_amount = swap.refund_amount()?;
 
 		// This function should only be called once
 		let slate = &mut swap.refund_slate;
@@ -592,7 +683,7 @@ impl SellApi {
 		let mut elems = Vec::new();
 		elems.push(build::output(refund_amount, scontext.refund_output.clone()));
 		slate
-			.add_transaction_elements(keychain, &proof::ProofBuilder::new(keychain), elems)?
+			.add_transaction_elements(keychain, &proof::ProofBuilder::new(keychain), elems, false)?
 			.secret_key(keychain.secp())?;
 		slate.tx.offset =
 			BlindingFactor::from_secret_key(SecretKey::new(keychain.secp(), &mut thread_rng()));
@@ -644,8 +735,10 @@ impl SellApi {
 
 		// Add multisig input to slate
 		tx_add_input(slate, commit);
+		swap.verify_refund_secret(keychain, &sec_key)?;
 
 		// Sign + finalize slate
+		let slate = &mut swap.refund_slate;
 		slate.fill_round_2(
 			keychain,
 			&sec_key,
@@ -12,24 +12,34 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::bitcoin::BtcData;
+pub use super::config::{
+	is_zero_fee_allowed, SwapConfig, DEFAULT_LOCK_SLATE_MAX_AGE, DEFAULT_NODE_CALL_TIMEOUT,
+	FIRST_LOCK_CONFIRMATIONS, LOCK_TIME_TOLERANCE_PERCENT, MAX_LOCK_TIME_TOLERANCE_PERCENT,
+};
 use super::message::*;
 use super::multisig::{Builder as MultisigBuilder, Hashed};
 use super::ser::*;
 use super::types::*;
-use super::{ErrorKind, Keychain};
+use super::{BuyApi, ErrorKind, Keychain, SellApi};
+use crate::proof::proofaddress::ProvableAddress;
 use crate::swap::fsm::state::StateId;
 use crate::{NodeClient, Slate};
 use chrono::{DateTime, Utc};
 use grin_core::core::verifier_cache::LruVerifierCache;
 use grin_core::core::{transaction as tx, KernelFeatures, TxKernel, Weighting};
-use grin_core::libtx::secp_ser;
+use grin_core::libtx::{secp_ser, tx_fee};
 use grin_core::ser;
 use grin_keychain::{Identifier, SwitchCommitmentType};
+use grin_util::secp::aggsig;
+use grin_util::secp::constants::MAX_PROOF_SIZE;
 use grin_util::secp::key::{PublicKey, SecretKey};
 use grin_util::secp::pedersen::{Commitment, RangeProof};
 use grin_util::secp::{Message as SecpMessage, Secp256k1, Signature};
 use grin_util::RwLock;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// Dummy wrapper for the hex-encoded serialized transaction.
@@ -144,6 +154,27 @@ pub struct Swap {
 	pub electrum_node_uri1: Option<String>,
 	/// ElectrumX failover URI2
 	pub electrum_node_uri2: Option<String>,
+	/// MWC redeem transaction fee. If None, the standard fee for 1 input, 1 output and 1 kernel is used.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub redeem_fee_base: Option<u64>,
+	/// MWC transactions exactly as they were sent to the node, the re-broadcast is using them
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub broadcast_txs: HashMap<MwcTxAction, tx::Transaction>,
+	/// Buyer own policy for the MWC lock confirmations. It can only raise mwc_confirmations.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub buyer_min_mwc_confirmations: Option<u64>,
+	/// Buyer own policy for the Secondary lock confirmations. It can only raise secondary_confirmations.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub buyer_min_secondary_confirmations: Option<u64>,
+	/// Reference that both parties agreed on, for example the exchange order id. Unlike the local
+	/// trade alias it is the part of the protocol: it is sent with every message and verified.
+	/// Seller can set it before the offer is sent.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub external_ref: Option<String>,
+	/// Part of the lock time, percent, that the parties agreed to post the locks within.
+	/// None for the default LOCK_TIME_TOLERANCE_PERCENT, the older offers don't have it.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub lock_time_tolerance_percent: Option<u64>,
 
 	// --------------------------------
 	// Additional non stored params for the trade. They good for a single call, not for all sessions
@@ -158,6 +189,27 @@ fn default_false() -> bool {
 	false
 }
 
+/// Amount that the slate spends, the fee included. Slates come from the other party,
+/// the sum can overflow.
+fn slate_spent_amount(name: &str, slate: &Slate) -> Result<u64, ErrorKind> {
+	slate.amount.checked_add(slate.fee).ok_or_else(|| {
+		ErrorKind::InvalidMessageData(format!(
+			"{} amount {} and fee {} overflow",
+			name, slate.amount, slate.fee
+		))
+	})
+}
+
+/// Swap fields that are not the part of the protocol state
+const NON_SEMANTIC_SWAP_FIELDS: [&str; 6] = [
+	"journal",
+	"last_check_error",
+	"last_process_error",
+	"electrum_node_uri1",
+	"electrum_node_uri2",
+	"broadcast_txs",
+];
+
 impl Swap {
 	/// Return true for Seller
 	pub fn is_seller(&self) -> bool {
@@ -167,6 +219,126 @@ impl Swap {
 		}
 	}
 
+	/// MWC confirmations that this party is waiting for. The Buyer overrides can't go below the offer value.
+	pub fn required_mwc_confirmations(&self) -> u64 {
+		std::cmp::max(
+			self.mwc_confirmations,
+			self.buyer_min_mwc_confirmations.unwrap_or(0),
+		)
+	}
+
+	/// Secondary confirmations that this party is waiting for. The Buyer overrides can't go below the offer value.
+	pub fn required_secondary_confirmations(&self) -> u64 {
+		std::cmp::max(
+			self.secondary_confirmations,
+			self.buyer_min_secondary_confirmations.unwrap_or(0),
+		)
+	}
+
+	/// Return true if the seller's MWC are locked on-chain and not redeemed or refunded yet.
+	/// The Buyer doesn't lock any MWC.
+	pub fn is_primary_locked(&self) -> bool {
+		if !self.is_seller() || self.posted_lock.is_none() {
+			return false;
+		}
+		match self.state {
+			StateId::SellerRedeemSecondaryCurrency
+			| StateId::SellerWaitingForRedeemConfirmations
+			| StateId::SellerSwapComplete
+			| StateId::SellerCancelledRefunded
+			| StateId::SellerCancelled => false,
+			_ => true,
+		}
+	}
+
+	/// Exchange rate of this swap, secondary currency units for 1 MWC
+	pub fn implied_rate(&self) -> f64 {
+		self.secondary_currency
+			.rate(self.primary_amount, self.secondary_amount)
+	}
+
+	/// Check if it is our turn to lock the funds. The party that is locking second
+	/// must wait until the first lock has the required number of confirmations.
+	pub fn is_lock_allowed(&self, tx_conf: &SwapTransactionsConfirmations) -> bool {
+		let required = self.first_lock_required_confirmations();
+		match (self.is_seller(), self.seller_lock_first) {
+			(true, true) | (false, false) => true,
+			// Seller waiting for the Buyer to lock all the secondary funds
+			(true, false) => {
+				tx_conf.secondary_lock_amount >= self.secondary_amount
+					&& tx_conf.secondary_lock_conf.unwrap_or(0) >= required
+			}
+			// Buyer waiting for the Seller to lock MWC
+			(false, true) => tx_conf.mwc_lock_conf.unwrap_or(0) >= required,
+		}
+	}
+
+	/// Number of confirmations of the first lock that the party locking second waits for.
+	/// The lock must be on the chain, the confirmations negotiated in the offer are checked
+	/// later, before the redeem.
+	pub fn first_lock_required_confirmations(&self) -> u64 {
+		FIRST_LOCK_CONFIRMATIONS
+	}
+
+	/// Advisory estimate, from 0.0 to 1.0, that the swap completes. It is a heuristic for the
+	/// dashboards, based on the swap progress and the distance to the MWC refund height.
+	pub fn completion_likelihood(&self, tx_conf: &SwapTransactionsConfirmations) -> f64 {
+		match self.state {
+			StateId::SellerSwapComplete | StateId::BuyerSwapComplete => return 1.0,
+			StateId::SellerCancelled
+			| StateId::BuyerCancelled
+			| StateId::SellerCancelledRefunded
+			| StateId::BuyerCancelledRefunded
+			| StateId::SellerWaitingForRefundHeight
+			| StateId::SellerPostingRefundSlate
+			| StateId::SellerWaitingForRefundConfirmations
+			| StateId::BuyerWaitingForRefundTime
+			| StateId::BuyerPostingRefundForSecondary
+			| StateId::BuyerWaitingForRefundConfirmations => return 0.0,
+			_ => (),
+		}
+
+		let conf_ratio = |conf: Option<u64>, required: u64| -> f64 {
+			match conf {
+				Some(conf) if conf >= required => 1.0,
+				Some(conf) => conf as f64 / required as f64,
+				None => 0.0,
+			}
+		};
+
+		// Redeem reveals the secret, after that the swap is done by both parties
+		let redeem = f64::max(
+			conf_ratio(tx_conf.mwc_redeem_conf, self.mwc_confirmations),
+			conf_ratio(tx_conf.secondary_redeem_conf, self.secondary_confirmations),
+		);
+		if tx_conf.mwc_redeem_conf.is_some() || tx_conf.secondary_redeem_conf.is_some() {
+			return 0.9 + 0.1 * redeem;
+		}
+
+		let locks = (conf_ratio(tx_conf.mwc_lock_conf, self.mwc_confirmations)
+			+ conf_ratio(tx_conf.secondary_lock_conf, self.secondary_confirmations))
+			/ 2.0;
+		let progress = if self.posted_redeem.is_some() {
+			0.85
+		} else {
+			0.2 + 0.6 * locks
+		};
+
+		// Nobody redeems after the refund height, the funds are going back
+		let refund_height = self.refund_slate.lock_height;
+		if tx_conf.mwc_tip >= refund_height {
+			return progress * 0.1;
+		}
+		// The redeem needs time, the swap is at risk when the refund height is close
+		let redeem_blocks = std::cmp::max(self.redeem_time_sec / 60, 1);
+		let blocks_left = refund_height - tx_conf.mwc_tip;
+		if blocks_left < redeem_blocks {
+			progress * (0.5 + 0.5 * blocks_left as f64 / redeem_blocks as f64)
+		} else {
+			progress
+		}
+	}
+
 	/// Get MWC lock slate, change outputs
 	pub fn change_output<K: Keychain>(
 		&self,
@@ -187,6 +359,80 @@ impl Swap {
 		Ok((identifier, amount, commit))
 	}
 
+	/// Expected commitment of the MWC redeem output. Buyer can compare it with the output of
+	/// the finalized redeem slate and later find it on the chain.
+	pub fn expected_redeem_commitment<K: Keychain>(
+		&self,
+		keychain: &K,
+		context: &Context,
+	) -> Result<Commitment, ErrorKind> {
+		let bcontext = context.unwrap_buyer()?;
+		if self.redeem_slate.amount == 0 {
+			return Err(ErrorKind::Generic(
+				"Redeem slate is not built yet".to_string(),
+			));
+		}
+		let commit = keychain.commit(
+			self.redeem_slate.amount,
+			&bcontext.output,
+			SwitchCommitmentType::Regular,
+		)?;
+		Ok(commit)
+	}
+
+	/// Evidence that the swap was honest. The redeem secret is recovered from the adaptor signature,
+	/// the seller partial signature and the final redeem kernel signature. It must match the redeem
+	/// public key that the buyer committed to at the offer.
+	/// Error if the redeem slate is not finalized yet.
+	pub fn verify_adaptor_consistency(&self) -> Result<bool, ErrorKind> {
+		let adaptor_signature = self.adaptor_signature.as_ref().ok_or(ErrorKind::Generic(
+			"Adaptor signature is not created yet".to_string(),
+		))?;
+		let redeem_public = self.redeem_public.as_ref().ok_or(ErrorKind::Generic(
+			"Redeem public key is not known yet".to_string(),
+		))?;
+		let finalized = if self.is_seller() {
+			self.redeem_kernel_updated
+		} else {
+			self.redeem_slate
+				.participant_data
+				.iter()
+				.all(|p| p.is_complete())
+		};
+		let kernel = match self.redeem_slate.tx.kernels().get(0) {
+			Some(k) if finalized => k,
+			_ => {
+				return Err(ErrorKind::Generic(
+					"Redeem slate is not finalized yet".to_string(),
+				))
+			}
+		};
+		let seller_id = if self.is_seller() {
+			self.participant_id
+		} else {
+			self.other_participant_id()
+		};
+		let seller_signature = self
+			.redeem_slate
+			.participant_data
+			.get(seller_id)
+			.and_then(|p| p.part_sig.clone())
+			.ok_or(ErrorKind::Generic(
+				"Redeem slate doesn't have the seller signature".to_string(),
+			))?;
+
+		let secp = grin_util::static_secp_instance();
+		let secp = secp.lock();
+		let redeem = secp.blind_sum(
+			vec![
+				signature_as_secret(&secp, adaptor_signature)?,
+				signature_as_secret(&secp, &seller_signature)?,
+			],
+			vec![signature_as_secret(&secp, &kernel.excess_sig)?],
+		)?;
+		Ok(PublicKey::from_secret_key(&secp, &redeem)? == *redeem_public)
+	}
+
 	/// Return Seller specific data
 	pub fn unwrap_seller(&self) -> Result<(String, u64), ErrorKind> {
 		match &self.role {
@@ -227,12 +473,92 @@ impl Swap {
 		}
 	}
 
+	/// Secondary currency lock address that the buyer funds and the seller observes.
+	/// Reconstructed from the multisig script data, so it is correct for the swap network.
+	pub fn get_secondary_lock_address(&self) -> Result<String, ErrorKind> {
+		let secp = Secp256k1::new();
+		self.secondary_data.unwrap_btc()?.lock_address(
+			&secp,
+			self.secondary_currency,
+			self.get_redeem_public()?,
+			self.get_time_btc_lock_script() as u64,
+			self.network,
+		)
+	}
+
+	/// Secondary currency redeem script as hex and its disassembly. It is public data, good for
+	/// the verification with the secondary currency tools.
+	pub fn get_secondary_redeem_script(&self) -> Result<(String, String), ErrorKind> {
+		let secp = Secp256k1::new();
+		self.secondary_data.unwrap_btc()?.redeem_script_hex_asm(
+			&secp,
+			self.get_redeem_public()?,
+			self.get_time_btc_lock_script() as u64,
+		)
+	}
+
+	fn get_redeem_public(&self) -> Result<&PublicKey, ErrorKind> {
+		self.redeem_public
+			.as_ref()
+			.ok_or(ErrorKind::UnexpectedAction(
+				"swap.redeem_public value is not defined".to_string(),
+			))
+	}
+
+	/// Key ids that the swap derives its keys from: the multisig, the redeem, refund and change
+	/// outputs and the secondary currency keys. The wallet must not use them for other
+	/// transactions. The lock inputs are not included, they are existing outputs.
+	pub fn reserved_key_ids(&self, context: &Context) -> Vec<Identifier> {
+		let mut ids = vec![context.multisig_key.clone()];
+		match &context.role_context {
+			RoleContext::Seller(c) => {
+				ids.push(c.change_output.clone());
+				if let Some((split_key, _)) = &c.change_split {
+					ids.push(split_key.clone());
+				}
+				ids.push(c.refund_output.clone());
+				match &c.secondary_context {
+					SecondarySellerContext::Btc(btc) => ids.push(btc.cosign.clone()),
+				}
+			}
+			RoleContext::Buyer(c) => {
+				ids.push(c.output.clone());
+				ids.push(c.redeem.clone());
+				match &c.secondary_context {
+					SecondaryBuyerContext::Btc(btc) => ids.push(btc.refund.clone()),
+				}
+			}
+		}
+		let mut unique = HashSet::new();
+		ids.retain(|id| unique.insert(id.clone()));
+		ids
+	}
+
 	pub(super) fn message(
 		&self,
 		inner: Update,
 		inner_secondary: SecondaryUpdate,
 	) -> Result<Message, ErrorKind> {
-		Ok(Message::new(self.id.clone(), inner, inner_secondary))
+		let mut message = Message::new(self.id.clone(), inner, inner_secondary);
+		message.external_ref = self.external_ref.clone();
+		Ok(message)
+	}
+
+	/// Check if the income message has the same external reference as the swap. The Seller that
+	/// doesn't have the reference accepts the one that the Buyer proposes with the offer acceptance.
+	/// Older wallets don't support the reference and never echo it, the message without
+	/// the reference is accepted. Only a different reference is a mixed up message.
+	pub fn check_external_ref(&self, message: &Message) -> Result<(), ErrorKind> {
+		if message.external_ref.is_none() || message.external_ref == self.external_ref {
+			return Ok(());
+		}
+		match message.inner {
+			Update::AcceptOffer(_) if self.is_seller() && self.external_ref.is_none() => Ok(()),
+			_ => Err(ErrorKind::ExternalRefMismatch(
+				self.external_ref.clone(),
+				message.external_ref.clone(),
+			)),
+		}
 	}
 
 	pub(super) fn multisig_secret<K: Keychain>(
@@ -249,8 +575,186 @@ impl Swap {
 		Ok(sec_key)
 	}
 
-	pub(super) fn refund_amount(&self) -> u64 {
-		self.primary_amount - self.refund_slate.fee
+	/// Rebuild the multisig commitment from this party secret, the other party partial commitment
+	/// and the swap amount. It must match the commitment from the stored multisig data and
+	/// the refund slate input, otherwise ErrorKind::MultisigCommitMismatch is returned.
+	pub fn recompute_multisig_commit<K: Keychain>(
+		&self,
+		keychain: &K,
+		context: &Context,
+	) -> Result<Commitment, ErrorKind> {
+		let secp = keychain.secp();
+		let own_commit = secp.commit(0, self.multisig_secret(keychain, context)?)?;
+		let other_commit = self
+			.multisig
+			.participants
+			.get(self.other_participant_id())
+			.and_then(|p| p.partial_commitment.clone())
+			.ok_or(super::multisig::ErrorKind::MultiSigIncomplete)?;
+		let value_commit = secp.commit_value(self.primary_amount)?;
+		let expected = secp.commit_sum(vec![own_commit, other_commit, value_commit], vec![])?;
+
+		let stored = self.multisig.commit(secp)?;
+		if stored != expected {
+			return Err(ErrorKind::MultisigCommitMismatch(
+				grin_util::to_hex(expected.0.to_vec()),
+				grin_util::to_hex(stored.0.to_vec()),
+			));
+		}
+		if let Some(input) = self.refund_slate.tx.inputs().get(0) {
+			if input.commit != expected {
+				return Err(ErrorKind::MultisigCommitMismatch(
+					grin_util::to_hex(expected.0.to_vec()),
+					grin_util::to_hex(input.commit.0.to_vec()),
+				));
+			}
+		}
+		Ok(expected)
+	}
+
+	/// Check that the MWC amount of the swap is the same for all slates. The lock slate locks
+	/// the whole amount, the refund and redeem slates spend it, the fee included.
+	/// The redeem slate is checked only after it is built.
+	pub fn check_primary_amount(&self) -> Result<(), ErrorKind> {
+		let mut slates = vec![
+			("lock slate", self.lock_slate.amount),
+			(
+				"refund slate",
+				slate_spent_amount("refund slate", &self.refund_slate)?,
+			),
+		];
+		if !self.redeem_slate.participant_data.is_empty() {
+			slates.push((
+				"redeem slate",
+				slate_spent_amount("redeem slate", &self.redeem_slate)?,
+			));
+		}
+		for (name, amount) in slates {
+			if amount != self.primary_amount {
+				return Err(ErrorKind::PrimaryAmountMismatch(
+					name.to_string(),
+					self.primary_amount,
+					amount,
+				));
+			}
+		}
+		Ok(())
+	}
+
+	/// Check that the refund transaction secret of this party spends the multisig input
+	/// together with the other party data. If our partial signature is already in the refund
+	/// slate, it is verified as well. The refund is the safety net, a slate that can't be
+	/// finalized must never be shared.
+	pub fn verify_refund_secret<K: Keychain>(
+		&self,
+		keychain: &K,
+		sec_key: &SecretKey,
+	) -> Result<(), ErrorKind> {
+		let secp = keychain.secp();
+		let slate = &self.refund_slate;
+		let multisig_commit = self.multisig.commit(secp)?;
+		let spends_multisig = slate
+			.tx
+			.inputs()
+			.iter()
+			.any(|i| i.commit == multisig_commit);
+		if !spends_multisig {
+			return Err(ErrorKind::InvalidRefundSecret(
+				"refund slate doesn't spend the multisig output".to_string(),
+			));
+		}
+
+		let pub_key = PublicKey::from_secret_key(secp, sec_key)?;
+		let mut pub_blinds: Vec<PublicKey> = slate
+			.participant_data
+			.iter()
+			.filter(|p| p.id as usize != self.participant_id)
+			.map(|p| p.public_blind_excess.clone())
+			.collect();
+		pub_blinds.push(pub_key.clone());
+		let pub_blind_sum = PublicKey::from_combination(secp, pub_blinds.iter().collect())?;
+		if slate.calc_excess(keychain)?.to_pubkey(secp)? != pub_blind_sum {
+			return Err(ErrorKind::InvalidRefundSecret(
+				"refund slate excess doesn't match the participants public excess".to_string(),
+			));
+		}
+
+		let own = slate
+			.participant_data
+			.iter()
+			.find(|p| p.id as usize == self.participant_id);
+		if let Some(own) = own {
+			if own.public_blind_excess != pub_key {
+				return Err(ErrorKind::InvalidRefundSecret(
+					"public excess in the refund slate doesn't match the secret".to_string(),
+				));
+			}
+			if let Some(part_sig) = own.part_sig.as_ref() {
+				let pub_nonces = slate
+					.participant_data
+					.iter()
+					.map(|p| &p.public_nonce)
+					.collect();
+				let pub_nonce_sum = PublicKey::from_combination(secp, pub_nonces)?;
+				let message = KernelFeatures::HeightLocked {
+					fee: slate.fee,
+					lock_height: slate.lock_height,
+				}
+				.kernel_sig_msg()
+				.map_err(|e| ErrorKind::Generic(format!("Unable to generate message, {}", e)))?;
+				aggsig::verify_partial_sig(
+					secp,
+					part_sig,
+					&pub_nonce_sum,
+					&pub_key,
+					Some(&pub_blind_sum),
+					&message,
+				)
+				.map_err(|e| {
+					ErrorKind::InvalidRefundSecret(format!(
+						"refund partial signature is invalid, {}",
+						e
+					))
+				})?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Fee for the MWC redeem transaction
+	pub fn redeem_fee(&self) -> u64 {
+		self.redeem_fee_base.unwrap_or(tx_fee(1, 1, 1, None))
+	}
+
+	/// Set the fee for the MWC redeem transaction. The Buyer builds that transaction, the fee
+	/// can be changed until it is built.
+	pub fn set_redeem_fee(&mut self, fee: u64) -> Result<(), ErrorKind> {
+		if self.is_seller() {
+			return Err(ErrorKind::Generic(
+				"MWC redeem fee is paid by the Buyer, Seller can't change it".to_string(),
+			));
+		}
+		if self.redeem_slate.participant_data.len() > 1 {
+			return Err(ErrorKind::Generic(
+				"MWC redeem transaction is already built, its fee can't be changed".to_string(),
+			));
+		}
+		self.check_zero_fee("MWC redeem", fee)?;
+		amount_minus_fee("MWC redeem", self.primary_amount, fee)?;
+		self.redeem_fee_base = Some(fee);
+		Ok(())
+	}
+
+	/// Transaction without fee is valid only on the testing chains
+	pub(super) fn check_zero_fee(&self, name: &str, fee: u64) -> Result<(), ErrorKind> {
+		if fee == 0 && !is_zero_fee_allowed() {
+			return Err(ErrorKind::ZeroFeeNotAllowed(name.to_string(), self.network));
+		}
+		Ok(())
+	}
+
+	pub(super) fn refund_amount(&self) -> Result<u64, ErrorKind> {
+		amount_minus_fee("MWC refund", self.primary_amount, self.refund_slate.fee)
 	}
 
 	pub(super) fn redeem_tx_fields(
@@ -338,6 +842,87 @@ impl Swap {
 		}
 	}
 
+	/// Replay the recorded sequence of the messages that Buyer received on a fresh swap.
+	/// It is a diagnostic tool, the result must be identical to the swap that processed the
+	/// same messages. Only Buyer swap can be restored this way because the Seller
+	/// swap is created from the wallet data, not from the message.
+	pub fn replay_messages<C: NodeClient, K: Keychain>(
+		messages: &[Message],
+		keychain: &K,
+		context: &Context,
+		node_client: &C,
+	) -> Result<Swap, ErrorKind> {
+		let mut messages = messages.iter();
+		let (id, offer, secondary_update) = messages
+			.next()
+			.ok_or(ErrorKind::Generic("No messages to replay".to_string()))?
+			.clone()
+			.unwrap_offer()?;
+		let mut swap =
+			BuyApi::accept_swap_offer(keychain, context, id, offer, secondary_update, node_client)?;
+		// Buyer might propose the reference with the acceptance, Seller echoes it back
+		if swap.external_ref.is_none() {
+			swap.external_ref = messages
+				.as_slice()
+				.first()
+				.and_then(|m| m.external_ref.clone());
+		}
+
+		for message in messages {
+			if message.id != swap.id {
+				return Err(ErrorKind::MismatchedId);
+			}
+			swap.check_external_ref(message)?;
+			match &message.inner {
+				Update::Redeem(redeem) => {
+					// Buyer builds its part of redeem slate before it can accept the seller's one
+					if swap.redeem_slate.participant_data.len() < 2 {
+						BuyApi::init_redeem(keychain, &mut swap, context)?;
+					}
+					BuyApi::finalize_redeem_slate(
+						keychain,
+						&mut swap,
+						context,
+						redeem.redeem_participant.clone(),
+					)?;
+					swap.ack_msg2();
+				}
+				Update::MessageAcknowledge(1) => swap.ack_msg1(),
+				Update::MessageAcknowledge(2) => swap.ack_msg2(),
+				_ => {
+					return Err(ErrorKind::UnexpectedMessageType(format!(
+						"Buyer doesn't expect to receive message {:?}",
+						message.inner
+					)))
+				}
+			}
+		}
+		Ok(swap)
+	}
+
+	/// Swap data without the fields that don't affect the protocol: the journal, the last errors,
+	/// the electrum nodes and the re-broadcast cache. Two swaps with the same canonical data
+	/// are in the same protocol state.
+	pub fn canonical_json(&self) -> Result<serde_json::Value, ErrorKind> {
+		let mut value = serde_json::to_value(self).map_err(|e| {
+			ErrorKind::Generic(format!("Unable to serialize swap {}, {}", self.id, e))
+		})?;
+		if let Some(fields) = value.as_object_mut() {
+			for name in &NON_SEMANTIC_SWAP_FIELDS {
+				fields.remove(*name);
+			}
+		}
+		Ok(value)
+	}
+
+	/// Check if two swaps are in the same protocol state, see canonical_json
+	pub fn semantically_equal(&self, other: &Swap) -> bool {
+		match (self.canonical_json(), other.canonical_json()) {
+			(Ok(a), Ok(b)) => a == b,
+			_ => false,
+		}
+	}
+
 	/// Add a journal message for this swap trade
 	pub fn add_journal_message(&mut self, msg: String) {
 		self.journal.push(SwapJournalRecord {
@@ -371,12 +956,19 @@ impl Swap {
 
 	/// When locking need to be started
 	pub fn get_time_start_lock(&self) -> i64 {
-		// We can get 5% from the total lock time. We have to post fast
+		// We can get only a small part of the total lock time. We have to post fast
 		self.get_time_message_offers()
 			+ std::cmp::max(
 				self.get_timeinterval_mwc_lock(),
 				self.get_timeinterval_btc_lock(),
-			) / 20
+			) * self.lock_time_tolerance_percent() as i64
+				/ 100
+	}
+
+	/// Part of the lock time, percent, that the parties have to post the locks within
+	pub fn lock_time_tolerance_percent(&self) -> u64 {
+		self.lock_time_tolerance_percent
+			.unwrap_or(LOCK_TIME_TOLERANCE_PERCENT)
 	}
 
 	/// When locking time will be expired
@@ -453,6 +1045,410 @@ impl Swap {
 			/ 10
 	}
 
+	/// Maximum lock confirmations for the chain with this block time that can be reached after
+	/// the offer messages exchange and before the redeem messages exchange. Includes the same
+	/// extra 10% for chain instability as the lock time intervals.
+	pub fn max_lock_confirmations(&self, block_time_sec: i64) -> u64 {
+		let interval = self.get_time_message_redeem() - self.get_time_message_offers();
+		(interval * 10 / 11 / block_time_sec) as u64
+	}
+
+	/// Estimate if it is still safe to continue the swap. It is a read only analysis.
+	/// Seller: the MWC refund can be published at the refund slate lock height, until then the
+	/// remaining MWC lock confirmations and the redeem must be done. If the redeem slate is
+	/// signed, the Buyer can publish the MWC redeem after that height as well.
+	/// Buyer: the secondary refund is valid after the lock script time. Once the MWC redeem is
+	/// posted, the secret is public and the Seller can redeem the secondary as well.
+	pub fn safety_status<C: NodeClient>(
+		&self,
+		node_client: &C,
+	) -> Result<SafetyAssessment, ErrorKind> {
+		if self.state.is_final_state() {
+			return Ok(SafetyAssessment::Safe);
+		}
+		if self.is_seller() {
+			// Buyer published the MWC redeem, the Seller knows the secret and can redeem
+			if self.redeem_kernel_updated {
+				return Ok(SafetyAssessment::Safe);
+			}
+		} else {
+			let refund_valid = get_cur_time() >= self.get_time_btc_lock_script();
+			if self.posted_redeem.is_some() {
+				return Ok(if refund_valid {
+					SafetyAssessment::Conflict
+				} else {
+					SafetyAssessment::Safe
+				});
+			}
+			if refund_valid {
+				return Ok(SafetyAssessment::RefundNow);
+			}
+		}
+
+		let height = node_client.get_chain_tip()?.0;
+		let refund_height = self.refund_slate.lock_height;
+		if height >= refund_height {
+			// Seller signature for the redeem is given to the Buyer
+			let redeem_signed = self.is_seller()
+				&& self.posted_lock.is_some()
+				&& self
+					.redeem_slate
+					.participant_data
+					.get(self.participant_id)
+					.map(|p| p.is_complete())
+					.unwrap_or(false);
+			return Ok(if redeem_signed {
+				SafetyAssessment::Conflict
+			} else {
+				SafetyAssessment::RefundNow
+			});
+		}
+
+		// Lock confirmations that we still need to get
+		let lock_conf = match self.lock_slate.tx.kernels().get(0) {
+			Some(kernel) if kernel.excess.0.iter().any(|v| *v != 0) => node_client
+				.get_kernel(&kernel.excess, Some(self.lock_slate.height), None)?
+				.map(|(_, kernel_height, _)| height.saturating_sub(kernel_height) + 1)
+				.unwrap_or(0),
+			_ => 0,
+		};
+		let remaining_conf = self.mwc_confirmations.saturating_sub(lock_conf);
+		// Expecting one MWC block every minute
+		let redeem_blocks = self.redeem_time_sec / 60;
+
+		let blocks_left = refund_height - height;
+		Ok(if blocks_left <= remaining_conf {
+			SafetyAssessment::RefundNow
+		} else if blocks_left <= remaining_conf + redeem_blocks {
+			SafetyAssessment::CautionRefundWindowNear
+		} else {
+			SafetyAssessment::Safe
+		})
+	}
+
+	/// Raise the fee rate of the secondary redeem transaction that is stuck in the mempool.
+	/// The redeem transaction is rebuilt at the new rate with the reduced output, signed and
+	/// posted again at the next state machine check. redeem_conf is the current number of the
+	/// secondary redeem confirmations, the confirmed redeem can't be replaced.
+	pub fn bump_secondary_fee(
+		&mut self,
+		new_rate: f32,
+		redeem_conf: Option<u64>,
+	) -> Result<(), ErrorKind> {
+		let (redeem_address, _) = self.unwrap_seller()?;
+		let btc_data = self.secondary_data.unwrap_btc()?;
+		if btc_data.redeem_tx.is_none() {
+			return Err(ErrorKind::UnexpectedAction(format!(
+				"{} redeem transaction is not posted yet",
+				self.secondary_currency
+			)));
+		}
+		if let Some(conf) = redeem_conf.filter(|conf| *conf > 0) {
+			return Err(ErrorKind::TxAlreadyConfirmed(
+				format!("{} redeem", self.secondary_currency),
+				conf,
+			));
+		}
+		if !new_rate.is_finite() || new_rate <= self.secondary_fee {
+			return Err(ErrorKind::Generic(format!(
+				"New {} redeem fee {} must be higher than the current fee {}",
+				self.secondary_currency, new_rate, self.secondary_fee
+			)));
+		}
+
+		// Dust output or the fee over the amount must be rejected before the swap is changed
+		let redeem_public = self
+			.redeem_public
+			.as_ref()
+			.ok_or(ErrorKind::UnexpectedAction(
+				"swap.redeem_public value is not defined. Method Swap::bump_secondary_fee"
+					.to_string(),
+			))?;
+		let input_script = btc_data.script(
+			&Secp256k1::new(),
+			redeem_public,
+			self.get_time_btc_lock_script() as u64,
+		)?;
+		btc_data.estimate_redeem_output(
+			&self.secondary_currency,
+			&redeem_address,
+			&input_script,
+			new_rate,
+			self.secondary_amount,
+		)?;
+
+		self.add_journal_message(format!(
+			"{} redeem fee is raised from {} to {} {}",
+			self.secondary_currency,
+			self.secondary_fee,
+			new_rate,
+			self.secondary_currency.get_fee_units()
+		));
+		self.secondary_fee = new_rate;
+		// The retry period is for the same fee, the bumped transaction is posted at the next check
+		self.posted_redeem = None;
+		Ok(())
+	}
+
+	/// Time of the last protocol step that this party did: a message was sent or a transaction
+	/// was posted. Swap start time if nothing is done yet. Unlike the journal, checks and
+	/// errors don't move it.
+	pub fn get_time_last_progress(&self) -> i64 {
+		vec![
+			self.posted_msg1,
+			self.posted_msg2,
+			self.posted_lock,
+			self.posted_redeem,
+			self.posted_refund,
+		]
+		.into_iter()
+		.flatten()
+		.max()
+		.unwrap_or(self.get_time_start())
+	}
+
+	/// Check if the swap is waiting for the transaction confirmations longer than expected.
+	/// Expected time is the confirmation interval of the involved currencies, counted from
+	/// the moment the transaction was posted, or from the last protocol step if this party
+	/// didn't post it. A stalled swap needs an investigation:
+	/// the node might be stuck or the transaction dropped.
+	pub fn is_stalled<C: NodeClient>(&self, node_client: &C, now: i64) -> bool {
+		let (posted, expected, mwc_slate) = match self.state {
+			StateId::SellerWaitingForLockConfirmations
+			| StateId::BuyerWaitingForLockConfirmations => (
+				self.posted_lock,
+				std::cmp::max(
+					self.get_timeinterval_mwc_lock(),
+					self.get_timeinterval_btc_lock(),
+				),
+				None,
+			),
+			StateId::BuyerWaitForRedeemMwcConfirmations => (
+				self.posted_redeem,
+				self.get_timeinterval_mwc_lock(),
+				Some(&self.redeem_slate),
+			),
+			StateId::SellerWaitingForRefundConfirmations => (
+				self.posted_refund,
+				self.get_timeinterval_mwc_lock(),
+				Some(&self.refund_slate),
+			),
+			StateId::SellerWaitingForRedeemConfirmations => {
+				(self.posted_redeem, self.get_timeinterval_btc_lock(), None)
+			}
+			StateId::BuyerWaitingForRefundConfirmations => {
+				(self.posted_refund, self.get_timeinterval_btc_lock(), None)
+			}
+			_ => return false,
+		};
+		// Buyer doesn't post MWC lock, the secondary is deposited after the last message
+		let start = posted.unwrap_or(self.get_time_last_progress());
+		if now <= start + expected {
+			return false;
+		}
+		// The transaction might be confirmed already, just the swap wasn't processed since then
+		if let Some(slate) = mwc_slate {
+			if let Ok(conf) = Self::mwc_tx_confirmations(node_client, slate) {
+				if conf >= self.mwc_confirmations {
+					return false;
+				}
+			}
+		}
+		true
+	}
+
+	// Number of confirmations for the slate transaction, 0 if it is not on the chain
+	fn mwc_tx_confirmations<C: NodeClient>(
+		node_client: &C,
+		slate: &Slate,
+	) -> Result<u64, ErrorKind> {
+		let kernel = match slate.tx.kernels().get(0) {
+			Some(kernel) if kernel.excess.0.iter().any(|v| *v != 0) => kernel.clone(),
+			_ => return Ok(0),
+		};
+		let (height, kernels) =
+			node_client.get_tip_and_kernels(&[(kernel.excess.clone(), Some(slate.height))])?;
+		Ok(kernels
+			.into_iter()
+			.next()
+			.flatten()
+			.map(|(_, kernel_height, _)| height.saturating_sub(kernel_height) + 1)
+			.unwrap_or(0))
+	}
+
+	/// Certificate of the completed swap with the references to the MWC and secondary chains.
+	/// Seller has all the data after the secondary funds are redeemed.
+	pub fn completion_certificate(&self) -> Result<CompletionCertificate, ErrorKind> {
+		match self.state {
+			StateId::SellerSwapComplete | StateId::BuyerSwapComplete => (),
+			_ => {
+				return Err(ErrorKind::Generic(format!(
+					"Swap {} is not completed",
+					self.id
+				)))
+			}
+		}
+		let kernel_hex = |slate: &Slate, name: &str| -> Result<String, ErrorKind> {
+			match slate.tx.kernels().get(0) {
+				Some(kernel) if kernel.excess.0.iter().any(|v| *v != 0) => {
+					Ok(grin_util::to_hex(kernel.excess.0.to_vec()))
+				}
+				_ => Err(ErrorKind::Generic(format!(
+					"Swap {} {} kernel is not known",
+					self.id, name
+				))),
+			}
+		};
+		let btc_data = self.secondary_data.unwrap_btc()?;
+		let secondary_redeem_tx = btc_data.redeem_tx.ok_or(ErrorKind::Generic(format!(
+			"Swap {} {} redeem transaction is not known",
+			self.id, self.secondary_currency
+		)))?;
+
+		Ok(CompletionCertificate {
+			swap_id: self.id.clone(),
+			network: self.network,
+			primary_amount: self.primary_amount,
+			secondary_amount: self.secondary_amount,
+			secondary_currency: self.secondary_currency,
+			mwc_lock_kernel: kernel_hex(&self.lock_slate, "MWC lock")?,
+			mwc_redeem_kernel: kernel_hex(&self.redeem_slate, "MWC redeem")?,
+			secondary_lock_address: self.get_secondary_lock_address()?,
+			secondary_lock_script: self.get_secondary_redeem_script()?.0,
+			secondary_redeem_tx: secondary_redeem_tx.to_string(),
+		})
+	}
+
+	/// Simulate the refund of this swap. Seller gets back the locked MWC minus the refund fee,
+	/// the lock fee is lost as well. Buyer gets back the locked secondary currency minus the
+	/// refund transaction fee at the current secondary fee rate.
+	pub fn simulate_refund(&self) -> Result<RefundSimulation, ErrorKind> {
+		match &self.role {
+			Role::Seller(_, _) => RefundSimulation::mwc(
+				self.primary_amount,
+				self.lock_slate.fee,
+				self.refund_slate.fee,
+			),
+			Role::Buyer(refund_address) => simulate_secondary_refund(
+				self.secondary_currency,
+				self.secondary_amount,
+				&self.secondary_data.unwrap_btc()?.cosign,
+				refund_address.as_ref(),
+				self.secondary_fee,
+			),
+		}
+	}
+
+	/// Transaction that was sent to the node for the action last time. None if it wasn't published.
+	pub fn last_broadcast_tx(&self, action: MwcTxAction) -> Option<tx::Transaction> {
+		self.broadcast_txs.get(&action).cloned()
+	}
+
+	/// Slate transaction for the action
+	fn action_tx(&self, action: MwcTxAction) -> &tx::Transaction {
+		match action {
+			MwcTxAction::Lock => &self.lock_slate.tx,
+			MwcTxAction::Refund => &self.refund_slate.tx,
+			MwcTxAction::Redeem => &self.redeem_slate.tx,
+		}
+	}
+
+	/// Publish the slate transaction for the action and remember it, see last_broadcast_tx
+	pub fn publish_mwc_tx<C: NodeClient>(
+		&mut self,
+		node_client: &C,
+		action: MwcTxAction,
+	) -> Result<(), ErrorKind> {
+		let tx = self.action_tx(action).clone();
+		publish_transaction(node_client, &tx, false)?;
+		self.broadcast_txs.insert(action, tx);
+		Ok(())
+	}
+
+	/// Publish the same transaction that was sent before. If the action was never published,
+	/// the slate transaction is published.
+	pub fn rebroadcast_mwc_tx<C: NodeClient>(
+		&mut self,
+		node_client: &C,
+		action: MwcTxAction,
+	) -> Result<(), ErrorKind> {
+		match self.last_broadcast_tx(action) {
+			Some(tx) => publish_transaction(node_client, &tx, false),
+			None => self.publish_mwc_tx(node_client, action),
+		}
+	}
+
+	/// Check that the persisted swap state is internally consistent.
+	/// Return the list of the found problems, empty list means that swap is healthy.
+	pub fn verify_integrity(&self) -> Vec<String> {
+		let mut problems = Vec::new();
+
+		if self.primary_amount == 0 {
+			problems.push("MWC amount is zero".to_string());
+		}
+		if self.secondary_amount == 0 {
+			problems.push(format!("{} amount is zero", self.secondary_currency));
+		}
+		let expected_participant_id = if self.is_seller() { 0 } else { 1 };
+		if self.participant_id != expected_participant_id {
+			problems.push(format!(
+				"Participant id {} doesn't match the role",
+				self.participant_id
+			));
+		}
+		if self.lock_slate.amount != self.primary_amount {
+			problems.push(format!(
+				"Lock slate amount {} doesn't match MWC amount {}",
+				self.lock_slate.amount, self.primary_amount
+			));
+		}
+		match slate_spent_amount("Refund slate", &self.refund_slate) {
+			Ok(amount) if amount == self.lock_slate.amount => (),
+			Ok(_) => problems.push(format!(
+				"Refund slate amount {} and fee {} don't sum up to the lock amount {}",
+				self.refund_slate.amount, self.refund_slate.fee, self.lock_slate.amount
+			)),
+			Err(e) => problems.push(format!("{}", e)),
+		}
+		match slate_spent_amount("Redeem slate", &self.redeem_slate) {
+			Ok(amount) if amount == self.primary_amount => (),
+			Ok(_) => problems.push(format!(
+				"Redeem slate amount {} and fee {} don't sum up to MWC amount {}",
+				self.redeem_slate.amount, self.redeem_slate.fee, self.primary_amount
+			)),
+			Err(e) => problems.push(format!("{}", e)),
+		}
+		// Buyer has redeem_public from the start, Seller gets it with the accepted offer
+		let offer_accepted = !self.is_seller() || self.lock_slate.participant_data.len() > 1;
+		if offer_accepted && self.redeem_public.is_none() {
+			problems.push("Missing redeem_public".to_string());
+		}
+		if let SecondaryData::Empty = self.secondary_data {
+			problems.push("Missing secondary currency data".to_string());
+		}
+
+		problems
+	}
+
+	/// Export the redeem secret for the recovery purposes. Until the swap is complete the secret
+	/// allows to take the funds, so it is released only for the completed swap.
+	pub fn export_redeem_secret<K: Keychain>(
+		&self,
+		keychain: &K,
+		context: &Context,
+	) -> Result<SecretKey, ErrorKind> {
+		match self.state {
+			StateId::SellerSwapComplete | StateId::BuyerSwapComplete => (),
+			_ => return Err(ErrorKind::SecretNotYetReleasable(self.state.to_string())),
+		}
+
+		if self.is_seller() {
+			SellApi::calculate_redeem_secret(keychain, self)
+		} else {
+			BuyApi::redeem_secret(keychain, context)
+		}
+	}
+
 	/// Latest error message. Check has higher priority because it is normally done first
 	pub fn get_last_error(&self) -> Option<String> {
 		if self.last_check_error.is_some() {
@@ -517,6 +1513,45 @@ pub fn tx_add_output(slate: &mut Slate, commit: Commitment, proof: RangeProof) {
 		.map(|e| outputs.insert(e, output));
 }
 
+/// Placeholder for the multisig output range proof. The real proof is known only when the multisig
+/// is finalized, so until then we are using a zero proof that has the same serialized size.
+pub fn multisig_proof_placeholder() -> RangeProof {
+	let mut proof = RangeProof::zero();
+	proof.plen = MAX_PROOF_SIZE;
+	proof
+}
+
+/// Minimal fee that the network requires for the transaction. It is calculated from the actual
+/// transaction body, so all outputs (including the multisig one) must be already added.
+/// Outputs are counted by the serialized size with the range proof. The output with the full
+/// size proof is counted as one, outputs are never counted as less than one.
+pub fn tx_required_fee(tx: &tx::Transaction) -> Result<u64, ErrorKind> {
+	let full_output_size = output_size(&tx::Output {
+		features: tx::OutputFeatures::Plain,
+		commit: Commitment::from_vec(vec![0; 33]),
+		proof: multisig_proof_placeholder(),
+	})?;
+	let mut outputs = 0;
+	for output in tx.outputs() {
+		if output.proof.plen > MAX_PROOF_SIZE {
+			return Err(ErrorKind::Generic(format!(
+				"Output {:?} has invalid range proof size {}",
+				output.commit, output.proof.plen
+			)));
+		}
+		let size = output_size(output)?;
+		outputs += std::cmp::max(1, (size + full_output_size - 1) / full_output_size);
+	}
+	Ok(tx_fee(tx.inputs().len(), outputs, tx.kernels().len(), None))
+}
+
+// Serialized size of the output with the range proof
+fn output_size(output: &tx::Output) -> Result<usize, ErrorKind> {
+	let data = ser::ser_vec(output, ser::ProtocolVersion(1))
+		.map_err(|e| ErrorKind::Generic(format!("Unable to serialize the output, {}", e)))?;
+	Ok(data.len())
+}
+
 /// Interpret the final 32 bytes of the signature as a secret key
 pub fn signature_as_secret(
 	secp: &Secp256k1,
@@ -543,6 +1578,116 @@ pub fn publish_transaction<C: NodeClient>(
 	Ok(())
 }
 
+/// Simulate the Buyer's refund of the secondary currency lock, see Swap::simulate_refund.
+/// Works with the offer data, so the refund can be evaluated before the offer is accepted.
+pub fn simulate_secondary_refund(
+	currency: Currency,
+	amount: u64,
+	cosign: &PublicKey,
+	refund_address: Option<&String>,
+	fee_sat_per_byte: f32,
+) -> Result<RefundSimulation, ErrorKind> {
+	let fee = BtcData::estimate_refund_fee(
+		&currency,
+		&Secp256k1::new(),
+		cosign,
+		refund_address,
+		fee_sat_per_byte,
+	)?;
+	RefundSimulation::secondary(currency, amount, fee)
+}
+
+/// Amount that is left after the fee is paid from it. The fee that takes the whole amount or more
+/// is an error, the transaction would have a zero or wrapped output.
+pub fn amount_minus_fee(name: &str, amount: u64, fee: u64) -> Result<u64, ErrorKind> {
+	match amount.checked_sub(fee) {
+		Some(left) if left > 0 => Ok(left),
+		_ => Err(ErrorKind::FeeExceedsAmount(name.to_string(), fee, amount)),
+	}
+}
+
+/// Suggest the fee for MWC redeem transaction (value for redeem_fee_base). For congested mempool the
+/// fee is increased in proportion to the number of blocks needed to mine the pool.
+/// If the node doesn't report the mempool size, the minimal fee is returned.
+pub fn suggest_redeem_fee<C: NodeClient>(node_client: &C, config: &SwapConfig) -> u64 {
+	let min_fee = tx_fee(1, 1, 1, None);
+	match node_client.get_pool_size() {
+		Some(pool_size) => {
+			let multiplier = 1 + pool_size / config.pool_size_per_block;
+			min_fee * std::cmp::min(multiplier, config.max_redeem_fee_multiplier)
+		}
+		None => min_fee,
+	}
+}
+
+/// Copy of the node client with the call timeout. Slow node fails the call with
+/// ErrorKind::NodeTimeout instead of stalling the swap.
+pub fn node_client_with_timeout<C: NodeClient>(node_client: &C, timeout: Duration) -> C {
+	let mut node_client = node_client.clone();
+	node_client.set_call_timeout(Some(timeout));
+	node_client
+}
+
+lazy_static! {
+	static ref COUNTERPARTY_ALLOWLIST: RwLock<Option<HashSet<String>>> = RwLock::new(None);
+}
+
+/// Identity of the counterparty address, it is the public key of the MQS or Tor address.
+/// The address can have the transport scheme, Tor domain and MQS server suffix.
+pub fn counterparty_identity(address: &str) -> Result<String, ErrorKind> {
+	let mut identity = address.trim().trim_end_matches('/');
+	if let Some(pos) = identity.find("://") {
+		identity = &identity[pos + 3..];
+	}
+	if let Some(pos) = identity.find('@') {
+		identity = &identity[..pos];
+	}
+	let identity = identity
+		.trim_end_matches(".onion")
+		.trim_end_matches(".ONION");
+	let address = ProvableAddress::from_str(identity).map_err(|e| {
+		ErrorKind::Generic(format!(
+			"Address {} is not a valid MQS or Tor address, {}",
+			address, e
+		))
+	})?;
+	Ok(address.public_key)
+}
+
+/// Set the counterparties that are allowed to send the swap offers. None - offers from any
+/// counterparty are accepted.
+pub fn set_counterparty_allowlist(addresses: Option<Vec<String>>) -> Result<(), ErrorKind> {
+	let allowlist = match addresses {
+		Some(addresses) => Some(
+			addresses
+				.iter()
+				.map(|a| counterparty_identity(a))
+				.collect::<Result<HashSet<String>, ErrorKind>>()?,
+		),
+		None => None,
+	};
+	*COUNTERPARTY_ALLOWLIST.write() = allowlist;
+	Ok(())
+}
+
+/// Check if the offer from that sender can be accepted.
+/// verified_sender - address verified by the transport. If the allowlist is set, offers
+/// from unknown senders are rejected.
+pub fn check_counterparty_allowed(verified_sender: Option<&str>) -> Result<(), ErrorKind> {
+	match &*COUNTERPARTY_ALLOWLIST.read() {
+		Some(allowlist) => {
+			let address = verified_sender.ok_or(ErrorKind::CounterpartyNotAllowed(
+				"with not verified address".to_string(),
+			))?;
+			match counterparty_identity(address) {
+				Ok(identity) if allowlist.contains(&identity) => Ok(()),
+				_ => Err(ErrorKind::CounterpartyNotAllowed(address.to_string())),
+			}
+		}
+		None => Ok(()),
+	}
+}
+
 #[cfg(test)]
 lazy_static! {
 	static ref CURRENT_TEST_TIME: RwLock<Option<i64>> = RwLock::new(None);
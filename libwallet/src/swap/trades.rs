@@ -13,25 +13,37 @@
 // limitations under the License.
 
 use super::ErrorKind;
+use crate::blake2::blake2b::blake2b;
+use crate::swap::message::{Message, MessageDirection, SwapMessageLog, SwapMessageLogRecord};
+use crate::swap::metrics::{SwapMetrics, SwapStatus, SwapSummary};
 use crate::swap::types::{Context, Currency};
 use crate::swap::Swap;
 use base64;
+use chrono::{TimeZone, Utc};
 use grin_util::secp::key::SecretKey;
+use grin_util::secp::Secp256k1;
 use grin_util::{from_hex, to_hex};
 use grin_util::{Mutex, RwLock};
+use grin_wallet_util::grin_core::core::amount_to_hr_string;
 use grin_wallet_util::grin_core::global;
 use rand::{thread_rng, Rng};
 use ring::aead;
 use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Lacation of the swaps states
 pub const SWAP_DEAL_SAVE_DIR: &'static str = "saved_swap_deal";
+/// File with the short aliases of the swap trades, stored at SWAP_DEAL_SAVE_DIR
+const SWAP_ALIASES_FILE: &'static str = "swap_aliases.json";
+/// Extension of the swap message log files, stored at SWAP_DEAL_SAVE_DIR
+const SWAP_MESSAGE_LOG_EXT: &'static str = "msglog";
 
 lazy_static! {
 	static ref TRADE_DEALS_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
@@ -40,6 +52,9 @@ lazy_static! {
 	static ref SWAP_LOCKS: RwLock<HashMap< String, Arc<Mutex<()>>>> = RwLock::new(HashMap::new());
 }
 
+// Set when the wallet is shutting down. No new swap processing is allowed after that.
+static SWAP_SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
 /// Init for file storage for saving swap deals
 pub fn init_swap_trade_backend(
 	data_file_dir: &str,
@@ -55,6 +70,123 @@ pub fn init_swap_trade_backend(
 			.write()
 			.replace(electrumx_config_uri.clone().unwrap());
 	}
+	SWAP_SHUTDOWN.store(false, Ordering::Relaxed);
+
+	match recover_swap_trades() {
+		Ok(repairs) => {
+			for r in repairs {
+				warn!("{}", r);
+			}
+		}
+		Err(e) => error!("Unable to check the swap trades storage, {}", e),
+	}
+}
+
+/// Stop the swap processing before the wallet exit. Waits until every swap that is in the
+/// middle of update is stored. After that no swap can be loaded for processing.
+pub fn shutdown_swap_trades() {
+	SWAP_SHUTDOWN.store(true, Ordering::Relaxed);
+	let locks: Vec<Arc<Mutex<()>>> = SWAP_LOCKS.read().values().cloned().collect();
+	for l in locks {
+		// Lock is released by the swap processing only after the state is stored
+		let _l = l.lock();
+	}
+}
+
+/// Repair the swap storage after the wallet was stopped in the middle of the swap update.
+/// Swap is written into the '.bak' file first and then renamed, so if the wallet was stopped
+/// before rename, the '.bak' file is left. Complete '.bak' file has the latest state of the swap,
+/// it is restored. Partially written files are deleted.
+/// Returns the description of the repairs.
+pub fn recover_swap_trades() -> Result<Vec<String>, ErrorKind> {
+	let dir = TRADE_DEALS_PATH.read().clone().unwrap();
+
+	let mut backups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+	for entry in fs::read_dir(&dir)? {
+		let entry = entry?;
+		if let Some(name) = entry.file_name().to_str() {
+			if name.ends_with(".bak") {
+				if let Some(pos) = name.find(".swap_") {
+					backups
+						.entry(name[..pos].to_string())
+						.or_insert(vec![])
+						.push(entry.path());
+				}
+			}
+		}
+	}
+
+	let remove = |swap_id: &str, path: &Path| -> Result<(), ErrorKind> {
+		fs::remove_file(path).map_err(|e| {
+			ErrorKind::TradeIoError(
+				swap_id.to_string(),
+				format!(
+					"Unable to delete the file {}, {}",
+					path.to_str().unwrap(),
+					e
+				),
+			)
+		})
+	};
+
+	let mut result = Vec::new();
+	for (swap_id, files) in backups {
+		let swap_lock = get_swap_lock(&swap_id);
+		let _l = swap_lock.lock();
+
+		// Last complete backup
+		let mut latest: Option<(SystemTime, PathBuf)> = None;
+		for path in files {
+			let complete = fs::read_to_string(&path)
+				.ok()
+				.and_then(|c| serde_json::from_str::<EncryptedSwap>(&c).ok())
+				.is_some();
+			let time = fs::metadata(&path).and_then(|m| m.modified()).ok();
+			match time {
+				Some(time) if complete => {
+					if latest.as_ref().map(|(t, _)| time >= *t).unwrap_or(true) {
+						if let Some((_, prev)) = latest.replace((time, path)) {
+							remove(&swap_id, &prev)?;
+						}
+					} else {
+						remove(&swap_id, &path)?;
+					}
+				}
+				_ => {
+					remove(&swap_id, &path)?;
+					result.push(format!(
+						"Swap {}: partially written file {} is deleted",
+						swap_id,
+						path.to_str().unwrap()
+					));
+				}
+			}
+		}
+
+		if let Some((time, path)) = latest {
+			let target = dir.join(format!("{}.swap", swap_id));
+			let target_time = fs::metadata(&target).and_then(|m| m.modified()).ok();
+			if target_time.map(|t| time >= t).unwrap_or(true) {
+				fs::rename(&path, &target).map_err(|e| {
+					ErrorKind::TradeIoError(
+						swap_id.clone(),
+						format!(
+							"Unable to restore the swap state, rename failed with error {}",
+							e
+						),
+					)
+				})?;
+				result.push(format!(
+					"Swap {}: the latest state is restored from file {}",
+					swap_id,
+					path.to_str().unwrap()
+				));
+			} else {
+				remove(&swap_id, &path)?;
+			}
+		}
+	}
+	Ok(result)
 }
 
 /// Get ElextrumX URL.
@@ -107,6 +239,210 @@ pub fn list_swap_trades() -> Result<Vec<String>, ErrorKind> {
 	Ok(result)
 }
 
+/// Short aliases of the swap trades. Key: alias, value: swap id
+pub fn get_swap_aliases() -> Result<BTreeMap<String, String>, ErrorKind> {
+	let path = TRADE_DEALS_PATH
+		.read()
+		.clone()
+		.unwrap()
+		.join(SWAP_ALIASES_FILE);
+	if !path.exists() {
+		return Ok(BTreeMap::new());
+	}
+	let content = fs::read_to_string(&path)?;
+	serde_json::from_str(&content).map_err(|e| {
+		ErrorKind::Generic(format!(
+			"Unable to read swap aliases from {}, {}",
+			path.to_str().unwrap_or("INVALID PATH"),
+			e
+		))
+	})
+}
+
+/// Assign the short alias to the swap trade, so it can be used instead of the swap id.
+/// The previous alias of this trade is replaced.
+pub fn set_swap_alias(swap_id: &str, alias: &str) -> Result<(), ErrorKind> {
+	let swap_ids = list_swap_trades()?;
+	let mut aliases = get_swap_aliases()?;
+	add_swap_alias(&mut aliases, &swap_ids, swap_id, alias)?;
+
+	let path = TRADE_DEALS_PATH
+		.read()
+		.clone()
+		.unwrap()
+		.join(SWAP_ALIASES_FILE);
+	let content = serde_json::to_string_pretty(&aliases)
+		.map_err(|e| ErrorKind::Generic(format!("Unable to serialize swap aliases, {}", e)))?;
+	fs::write(&path, content).map_err(|e| {
+		ErrorKind::TradeIoError(swap_id.to_string(), format!("Unable to store alias, {}", e))
+	})?;
+	Ok(())
+}
+
+/// Find the swap id by the id, the alias or the unique prefix of the id
+pub fn resolve_swap_id(id: &str) -> Result<String, ErrorKind> {
+	resolve_swap_id_from(id, &list_swap_trades()?, &get_swap_aliases()?)
+}
+
+fn add_swap_alias(
+	aliases: &mut BTreeMap<String, String>,
+	swap_ids: &[String],
+	swap_id: &str,
+	alias: &str,
+) -> Result<(), ErrorKind> {
+	if !swap_ids.iter().any(|id| id == swap_id) {
+		return Err(ErrorKind::TradeNotFound(swap_id.to_string()));
+	}
+	if alias.is_empty()
+		|| !alias
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+	{
+		return Err(ErrorKind::InvalidSwapAlias(
+			alias.to_string(),
+			"only letters, digits, '-' and '_' are allowed".to_string(),
+		));
+	}
+	// Alias can't shadow the swap ids, otherwise the same value would point to different trades
+	if swap_ids.iter().any(|id| id.starts_with(alias)) {
+		return Err(ErrorKind::InvalidSwapAlias(
+			alias.to_string(),
+			"it matches the swap id".to_string(),
+		));
+	}
+	if let Some(other) = aliases.get(alias) {
+		// Alias of the deleted trade can be reused
+		if other != swap_id && swap_ids.contains(other) {
+			return Err(ErrorKind::InvalidSwapAlias(
+				alias.to_string(),
+				format!("it is already used by swap {}", other),
+			));
+		}
+	}
+	aliases.retain(|_, id| id != swap_id);
+	aliases.insert(alias.to_string(), swap_id.to_string());
+	Ok(())
+}
+
+fn resolve_swap_id_from(
+	id: &str,
+	swap_ids: &[String],
+	aliases: &BTreeMap<String, String>,
+) -> Result<String, ErrorKind> {
+	if swap_ids.iter().any(|s| s == id) {
+		return Ok(id.to_string());
+	}
+	if let Some(swap_id) = aliases.get(id) {
+		if !swap_ids.contains(swap_id) {
+			return Err(ErrorKind::TradeNotFound(format!("{} ({})", swap_id, id)));
+		}
+		return Ok(swap_id.clone());
+	}
+	let prefix = id.to_lowercase();
+	let mut matches: Vec<&String> = swap_ids.iter().filter(|s| s.starts_with(&prefix)).collect();
+	match matches.len() {
+		0 => Err(ErrorKind::TradeNotFound(id.to_string())),
+		1 => Ok(matches[0].clone()),
+		_ => {
+			matches.sort();
+			Err(ErrorKind::AmbiguousSwapId(
+				id.to_string(),
+				matches
+					.iter()
+					.map(|s| s.as_str())
+					.collect::<Vec<&str>>()
+					.join(", "),
+			))
+		}
+	}
+}
+
+/// Append the sent or received message to the swap message log. The log is append-only,
+/// the records are never changed. Every record is encrypted with the swap key, the same way
+/// as the trade. The log is deleted and exported together with the trade.
+pub fn append_swap_message_log(
+	swap_id: &str,
+	direction: MessageDirection,
+	message: &Message,
+	enc_key: &SecretKey,
+) -> Result<(), ErrorKind> {
+	let record = SwapMessageLogRecord::new(direction, message)?;
+	let record = serde_json::to_string(&record).map_err(|e| {
+		ErrorKind::Generic(format!("Unable to serialize message log record, {}", e))
+	})?;
+	let line = serde_json::to_string(&EncryptedSwap::from_json(&record, swap_id, enc_key)?)
+		.map_err(|e| {
+			ErrorKind::TradeEncDecError(format!(
+				"Unable to serialize encrypted message log record, {}",
+				e
+			))
+		})?;
+	let path = swap_message_log_path(swap_id);
+	OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(&path)
+		.and_then(|mut f| {
+			writeln!(f, "{}", line)?;
+			f.sync_all()
+		})
+		.map_err(|e| {
+			ErrorKind::TradeIoError(
+				swap_id.to_string(),
+				format!("Unable to write the message log, {}", e),
+			)
+		})?;
+	Ok(())
+}
+
+/// Messages that were sent and received for the swap trade
+pub fn get_swap_message_log(
+	swap_id: &str,
+	dec_key: &SecretKey,
+) -> Result<SwapMessageLog, ErrorKind> {
+	if !list_swap_trades()?.iter().any(|id| id == swap_id) {
+		return Err(ErrorKind::TradeNotFound(swap_id.to_string()));
+	}
+	let path = swap_message_log_path(swap_id);
+	let mut records = Vec::new();
+	if path.exists() {
+		let content = fs::read_to_string(&path).map_err(|e| {
+			ErrorKind::TradeIoError(
+				swap_id.to_string(),
+				format!("Unable to read the message log, {}", e),
+			)
+		})?;
+		for line in content.lines().filter(|l| !l.trim().is_empty()) {
+			let enc_record: EncryptedSwap = serde_json::from_str(line)?;
+			let record = enc_record.decrypt(dec_key)?;
+			let record: SwapMessageLogRecord = serde_json::from_str(&record).map_err(|e| {
+				ErrorKind::TradeIoError(
+					swap_id.to_string(),
+					format!("Invalid message log record, {}", e),
+				)
+			})?;
+			records.push(record);
+		}
+	}
+	Ok(SwapMessageLog {
+		swap_id: swap_id.to_string(),
+		records,
+	})
+}
+
+fn swap_message_log_path(swap_id: &str) -> PathBuf {
+	TRADE_DEALS_PATH
+		.read()
+		.clone()
+		.unwrap()
+		.join(format!("{}.{}", swap_id, SWAP_MESSAGE_LOG_EXT))
+}
+
+// Message log is exported next to the trade file
+fn exported_message_log_path(trade_file_name: &str) -> PathBuf {
+	PathBuf::from(format!("{}.{}", trade_file_name, SWAP_MESSAGE_LOG_EXT))
+}
+
 /// Caller suppose to lock the swap object first before call other swap related functions.
 pub fn get_swap_lock(swap_id: &String) -> Arc<Mutex<()>> {
 	let mut swap_lock_hash = SWAP_LOCKS.write();
@@ -156,6 +492,17 @@ pub fn delete_swap_trade(
 	fs::rename(target_path, deleted_path).map_err(|e| {
 		ErrorKind::TradeIoError(swap_id.to_string(), format!("Unable to delete, {}", e))
 	})?;
+
+	let log_path = swap_message_log_path(swap_id);
+	if log_path.exists() {
+		let deleted_log_path = log_path.with_extension(format!("{}.del", SWAP_MESSAGE_LOG_EXT));
+		fs::rename(log_path, deleted_log_path).map_err(|e| {
+			ErrorKind::TradeIoError(
+				swap_id.to_string(),
+				format!("Unable to delete the message log, {}", e),
+			)
+		})?;
+	}
 	Ok(())
 }
 
@@ -172,6 +519,12 @@ pub fn get_swap_trade(
 			swap_id
 		)));
 	}
+	if SWAP_SHUTDOWN.load(Ordering::Relaxed) {
+		return Err(ErrorKind::Generic(format!(
+			"Wallet is shutting down, swap {} can't be processed",
+			swap_id
+		)));
+	}
 
 	let path = TRADE_DEALS_PATH
 		.read()
@@ -243,21 +596,13 @@ fn read_swap_data_from_file(
 	Ok((context, swap))
 }
 
-/// Store swap deal to a file
-pub fn store_swap_trade(
+// Writing to bak file. We don't want to loose the data in case of failure. It least the prev step will be left.
+// The file is complete only after this call, the caller renames it to the swap file.
+pub(crate) fn write_swap_trade_backup(
 	context: &Context,
 	swap: &Swap,
 	enc_key: &SecretKey,
-	lock: &Mutex<()>,
-) -> Result<(), ErrorKind> {
-	if lock.try_lock().is_some() {
-		return Err(ErrorKind::Generic(format!(
-			"store_swap_trade processing unlocked instance {}",
-			swap.id
-		)));
-	}
-
-	// Writing to bak file. We don't want to loose the data in case of failure. It least the prev step will be left
+) -> Result<PathBuf, ErrorKind> {
 	let swap_id = swap.id.to_string();
 	let mut rng = thread_rng();
 	let r: u64 = rng.gen();
@@ -291,7 +636,7 @@ pub fn store_swap_trade(
 			)
 		})?;
 		let res_str = context_ser + "<#>" + swap_ser.as_str();
-		let encrypted_swap = EncryptedSwap::from_json(&res_str, enc_key)?;
+		let encrypted_swap = EncryptedSwap::from_json(&res_str, &swap_id, enc_key)?;
 		let enc_swap_ser = serde_json::to_string(&encrypted_swap).map_err(|e| {
 			ErrorKind::TradeEncDecError(format!("Unable to serialize encrypted swap, {}", e))
 		})?;
@@ -320,6 +665,26 @@ pub fn store_swap_trade(
 		})?;
 	}
 
+	Ok(path)
+}
+
+/// Store swap deal to a file
+pub fn store_swap_trade(
+	context: &Context,
+	swap: &Swap,
+	enc_key: &SecretKey,
+	lock: &Mutex<()>,
+) -> Result<(), ErrorKind> {
+	if lock.try_lock().is_some() {
+		return Err(ErrorKind::Generic(format!(
+			"store_swap_trade processing unlocked instance {}",
+			swap.id
+		)));
+	}
+
+	let swap_id = swap.id.to_string();
+	let path = write_swap_trade_backup(context, swap, enc_key)?;
+
 	let path_target = TRADE_DEALS_PATH
 		.read()
 		.clone()
@@ -335,6 +700,100 @@ pub fn store_swap_trade(
 	Ok(())
 }
 
+/// Load every stored swap and check its integrity.
+/// Return the swaps with problems: <SwapId, problems>. Swaps that can't be read are reported as well.
+pub fn verify_all_swap_trades(
+	dec_key: &SecretKey,
+) -> Result<Vec<(String, Vec<String>)>, ErrorKind> {
+	let mut result = Vec::new();
+	for swap_id in list_swap_trades()? {
+		let swap_lock = get_swap_lock(&swap_id);
+		let _l = swap_lock.lock();
+		let problems = match get_swap_trade(&swap_id, dec_key, &*swap_lock) {
+			Ok((_context, swap)) => swap.verify_integrity(),
+			Err(e) => vec![format!("Unable to read the swap, {}", e)],
+		};
+		if !problems.is_empty() {
+			result.push((swap_id, problems));
+		}
+	}
+	Ok(result)
+}
+
+/// Read the summary of the stored swap. Only the swap part is parsed, the context and
+/// the slates are skipped.
+pub fn get_swap_summary(
+	swap_id: &str,
+	dec_key: &SecretKey,
+	lock: &Mutex<()>,
+) -> Result<SwapSummary, ErrorKind> {
+	if lock.try_lock().is_some() {
+		return Err(ErrorKind::Generic(format!(
+			"get_swap_summary processing unlocked instance {}",
+			swap_id
+		)));
+	}
+
+	let path = TRADE_DEALS_PATH
+		.read()
+		.clone()
+		.unwrap()
+		.join(format!("{}.swap", swap_id));
+	if !path.exists() {
+		return Err(ErrorKind::TradeNotFound(swap_id.to_string()));
+	}
+
+	let dec_swap_content = read_swap_content(path.as_path(), dec_key)?;
+	let swap_str = dec_swap_content
+		.split("<#>")
+		.nth(1)
+		.ok_or(ErrorKind::IO(format!(
+			"Not found all packages at the swap trade file {}",
+			path.to_str().unwrap()
+		)))?;
+	let summary: SwapSummary = serde_json::from_str(swap_str).map_err(|e| {
+		ErrorKind::IO(format!(
+			"Unable to parse Swap data from file {}, {}",
+			path.to_str().unwrap(),
+			e
+		))
+	})?;
+	Ok(summary)
+}
+
+/// Aggregate the metrics over all stored swaps
+pub fn swap_metrics(dec_key: &SecretKey) -> Result<SwapMetrics, ErrorKind> {
+	let mut summaries = Vec::new();
+	for swap_id in list_swap_trades()? {
+		let swap_lock = get_swap_lock(&swap_id);
+		let _l = swap_lock.lock();
+		summaries.push(get_swap_summary(&swap_id, dec_key, &*swap_lock)?);
+	}
+	Ok(SwapMetrics::from_summaries(&summaries))
+}
+
+/// MWC amount that is locked by the active swaps, see Swap::is_primary_locked.
+/// Completed and refunded swaps don't lock anything.
+pub fn locked_balance(swaps: &[Swap]) -> u64 {
+	swaps
+		.iter()
+		.filter(|s| s.is_primary_locked())
+		.map(|s| s.primary_amount)
+		.sum()
+}
+
+/// MWC amount that is locked by all stored swaps
+pub fn swap_locked_balance(dec_key: &SecretKey) -> Result<u64, ErrorKind> {
+	let mut swaps = Vec::new();
+	for swap_id in list_swap_trades()? {
+		let swap_lock = get_swap_lock(&swap_id);
+		let _l = swap_lock.lock();
+		let (_context, swap) = get_swap_trade(&swap_id, dec_key, &*swap_lock)?;
+		swaps.push(swap);
+	}
+	Ok(locked_balance(&swaps))
+}
+
 /// Dump the content of swap file
 pub fn dump_swap_trade(
 	swap_id: &str,
@@ -379,9 +838,90 @@ pub fn export_trade(swap_id: &str, export_file_name: &str) -> Result<(), ErrorKi
 		))
 	})?;
 
+	let log_path = swap_message_log_path(swap_id);
+	if log_path.exists() {
+		let export_log_path = exported_message_log_path(export_file_name);
+		fs::copy(log_path, &export_log_path).map_err(|e| {
+			ErrorKind::IO(format!(
+				"Unable to export the message log into the file {}, {}",
+				export_log_path.display(),
+				e
+			))
+		})?;
+	}
+
 	Ok(())
 }
 
+/// Header of the swap history CSV export
+pub const SWAP_CSV_HEADER: &str =
+	"id,started,completed,role,secondary_currency,primary_amount,secondary_amount,fees,status";
+
+// MWC fees that this party paid. Secondary fees are paid from the secondary amount.
+fn swap_csv_fees(swap: &Swap) -> u64 {
+	if swap.is_seller() {
+		let lock_fee = swap.posted_lock.map(|_| swap.lock_slate.fee).unwrap_or(0);
+		let refund_fee = swap
+			.posted_refund
+			.map(|_| swap.refund_slate.fee)
+			.unwrap_or(0);
+		lock_fee + refund_fee
+	} else {
+		swap.posted_redeem
+			.map(|_| swap.redeem_slate.fee)
+			.unwrap_or(0)
+	}
+}
+
+/// Write the swaps history as CSV, one row per swap, ordered by start time.
+/// Completion time is the time of the last journal record for the finished swaps.
+pub fn write_swaps_csv<W: Write>(writer: &mut W, swaps: &[Swap]) -> Result<(), ErrorKind> {
+	let mut swaps: Vec<&Swap> = swaps.iter().collect();
+	swaps.sort_by_key(|s| s.started);
+
+	writeln!(writer, "{}", SWAP_CSV_HEADER)?;
+	for swap in swaps {
+		let completed = if swap.state.is_final_state() {
+			swap.journal
+				.last()
+				.map(|j| Utc.timestamp(j.time, 0).to_rfc3339())
+				.unwrap_or_default()
+		} else {
+			String::new()
+		};
+		writeln!(
+			writer,
+			"{},{},{},{},{},{},{},{},{}",
+			swap.id,
+			swap.started.to_rfc3339(),
+			completed,
+			if swap.is_seller() { "Seller" } else { "Buyer" },
+			swap.secondary_currency,
+			amount_to_hr_string(swap.primary_amount, true),
+			swap.secondary_currency
+				.amount_to_hr_string(swap.secondary_amount, true),
+			amount_to_hr_string(swap_csv_fees(swap), true),
+			SwapStatus::from_state(&swap.state)
+		)?;
+	}
+	Ok(())
+}
+
+/// Export history of all stored swaps as CSV. Cancelled and refunded swaps are included.
+pub fn export_swap_trades_csv<W: Write>(
+	writer: &mut W,
+	dec_key: &SecretKey,
+) -> Result<(), ErrorKind> {
+	let mut swaps = Vec::new();
+	for swap_id in list_swap_trades()? {
+		let swap_lock = get_swap_lock(&swap_id);
+		let _l = swap_lock.lock();
+		let (_context, swap) = get_swap_trade(&swap_id, dec_key, &*swap_lock)?;
+		swaps.push(swap);
+	}
+	write_swaps_csv(writer, &swaps)
+}
+
 /// Import the trade data
 /// return: swap Id
 pub fn import_trade(
@@ -404,12 +944,38 @@ pub fn import_trade(
 
 	store_swap_trade(&context, &swap, dec_key, lock)?;
 
+	let import_log_path = exported_message_log_path(trade_file_name);
+	if import_log_path.exists() {
+		let swap_id = swap.id.to_string();
+		fs::copy(&import_log_path, swap_message_log_path(&swap_id)).map_err(|e| {
+			ErrorKind::TradeIoError(
+				swap_id,
+				format!(
+					"Unable to import the message log {}, {}",
+					import_log_path.display(),
+					e
+				),
+			)
+		})?;
+	}
+
 	Ok(format!("{}", swap.id))
 }
 
+/// Version of the encrypted swap file format that we are writing.
+///   0 - legacy, all swaps are encrypted with the wallet swap storage key.
+///   1 - every swap is encrypted with own key, derived from the wallet swap storage key and swap Id.
+pub const ENCRYPTED_SWAP_VERSION: u8 = 1;
+
 /// Encrypt and decrypt swap files
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EncryptedSwap {
+	/// Format version. Legacy files don't have it, so it is 0 for them
+	#[serde(default)]
+	pub version: u8,
+	/// Swap Id, needed to derive the swap key. Legacy files don't have it.
+	#[serde(default, skip_serializing_if = "String::is_empty")]
+	pub swap_id: String,
 	/// nonce used for encryption
 	pub nonce: String,
 	/// Encrypted base64 body swap + context
@@ -417,8 +983,30 @@ pub struct EncryptedSwap {
 }
 
 impl EncryptedSwap {
+	/// Derive the key for the swap from the wallet swap storage key.
+	/// Leaked key for a single swap doesn't expose other swaps.
+	fn swap_key(version: u8, swap_id: &str, key: &SecretKey) -> Result<SecretKey, ErrorKind> {
+		match version {
+			0 => Ok(key.clone()),
+			1 => {
+				let hash = blake2b(32, &key.0, swap_id.as_bytes());
+				SecretKey::from_slice(&Secp256k1::without_caps(), hash.as_bytes())
+					.map_err(|e| ErrorKind::Generic(format!("Unable to build a swap key, {}", e)))
+			}
+			v => Err(ErrorKind::TradeEncDecError(format!(
+				"EncryptedSwap: unsupported format version {}",
+				v
+			))),
+		}
+	}
+
 	/// Encrypts and encodes json as base 64
-	pub fn from_json(json_in: &String, enc_key: &SecretKey) -> Result<Self, ErrorKind> {
+	pub fn from_json(
+		json_in: &String,
+		swap_id: &str,
+		enc_key: &SecretKey,
+	) -> Result<Self, ErrorKind> {
+		let enc_key = Self::swap_key(ENCRYPTED_SWAP_VERSION, swap_id, enc_key)?;
 		let mut to_encrypt = serde_json::to_string(&json_in)
 			.map_err(|e| {
 				ErrorKind::TradeEncDecError(format!(
@@ -448,6 +1036,8 @@ impl EncryptedSwap {
 		}
 
 		Ok(EncryptedSwap {
+			version: ENCRYPTED_SWAP_VERSION,
+			swap_id: swap_id.to_string(),
 			nonce: to_hex(nonce.to_vec()),
 			body_enc: base64::encode(&to_encrypt),
 		})
@@ -475,6 +1065,8 @@ impl EncryptedSwap {
 			.into());
 		}
 
+		let dec_key = Self::swap_key(self.version, &self.swap_id, dec_key)?;
+
 		let mut n = [0u8; 12];
 		n.copy_from_slice(&nonce[0..12]);
 		let unbound_key = aead::UnboundKey::new(&aead::AES_256_GCM, &dec_key.0)
@@ -501,3 +1093,116 @@ impl EncryptedSwap {
 		})?)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn test_key(seed: u8) -> SecretKey {
+		SecretKey::from_slice(&Secp256k1::without_caps(), &[seed; 32]).unwrap()
+	}
+
+	#[test]
+	fn test_swap_id_resolution() {
+		let swap_ids: Vec<String> = vec![
+			"a8e6c4d4-2b1c-4a3e-9d6e-4f5e0b1c2d3e",
+			"a8e6f000-1111-4a3e-9d6e-4f5e0b1c2d3e",
+			"0436430c-2b02-624c-2032-570501212b00",
+		]
+		.iter()
+		.map(|s| s.to_string())
+		.collect();
+		let mut aliases = BTreeMap::new();
+
+		// Full id and unique prefix
+		assert_eq!(
+			resolve_swap_id_from(&swap_ids[2], &swap_ids, &aliases).unwrap(),
+			swap_ids[2]
+		);
+		assert_eq!(
+			resolve_swap_id_from("0436", &swap_ids, &aliases).unwrap(),
+			swap_ids[2]
+		);
+		assert_eq!(
+			resolve_swap_id_from("A8E6C", &swap_ids, &aliases).unwrap(),
+			swap_ids[0]
+		);
+		// Ambiguous prefix lists all matches
+		assert_eq!(
+			resolve_swap_id_from("a8e6", &swap_ids, &aliases).unwrap_err(),
+			ErrorKind::AmbiguousSwapId(
+				"a8e6".to_string(),
+				format!("{}, {}", swap_ids[0], swap_ids[1])
+			)
+		);
+		assert_eq!(
+			resolve_swap_id_from("ffff", &swap_ids, &aliases).unwrap_err(),
+			ErrorKind::TradeNotFound("ffff".to_string())
+		);
+
+		// Aliases
+		add_swap_alias(&mut aliases, &swap_ids, &swap_ids[0], "alice-btc-1").unwrap();
+		assert_eq!(
+			resolve_swap_id_from("alice-btc-1", &swap_ids, &aliases).unwrap(),
+			swap_ids[0]
+		);
+		// Alias collisions
+		match add_swap_alias(&mut aliases, &swap_ids, &swap_ids[1], "alice-btc-1") {
+			Err(ErrorKind::InvalidSwapAlias(_, msg)) => assert!(msg.contains(&swap_ids[0])),
+			r => panic!("Unexpected result {:?}", r),
+		}
+		assert!(add_swap_alias(&mut aliases, &swap_ids, &swap_ids[1], "a8e6").is_err());
+		assert!(add_swap_alias(&mut aliases, &swap_ids, &swap_ids[1], "bob btc").is_err());
+		assert!(add_swap_alias(&mut aliases, &swap_ids, "unknown", "bob").is_err());
+		// New alias replaces the old one
+		add_swap_alias(&mut aliases, &swap_ids, &swap_ids[0], "alice").unwrap();
+		assert_eq!(aliases.len(), 1);
+		assert!(resolve_swap_id_from("alice-btc-1", &swap_ids, &aliases).is_err());
+		// Alias of the deleted trade can be reused
+		let remaining = swap_ids[1..].to_vec();
+		add_swap_alias(&mut aliases, &remaining, &swap_ids[1], "alice").unwrap();
+		assert_eq!(
+			resolve_swap_id_from("alice", &remaining, &aliases).unwrap(),
+			swap_ids[1]
+		);
+	}
+
+	#[test]
+	fn test_encrypted_swap() {
+		let data = "context_data<#>swap_data".to_string();
+		let swap_id = "a8e6c4d4-2b1c-4a3e-9d6e-4f5e0b1c2d3e";
+		let key = test_key(7);
+
+		let enc = EncryptedSwap::from_json(&data, swap_id, &key).unwrap();
+		assert_eq!(enc.version, ENCRYPTED_SWAP_VERSION);
+		assert!(!enc.body_enc.contains("swap_data"));
+
+		// Round trip with file serialization
+		let enc: EncryptedSwap =
+			serde_json::from_str(&serde_json::to_string(&enc).unwrap()).unwrap();
+		assert_eq!(enc.decrypt(&key).unwrap(), data);
+
+		// Without the key data is unreadable
+		assert!(enc.decrypt(&test_key(8)).is_err());
+
+		// Swap key is bound to the swap Id
+		let mut other = enc.clone();
+		other.swap_id = "another_swap".to_string();
+		assert!(other.decrypt(&key).is_err());
+
+		// Legacy records without version are encrypted with the storage key directly
+		let mut legacy = EncryptedSwap::from_json(&data, swap_id, &key).unwrap();
+		legacy.version = 0;
+		legacy.swap_id = String::new();
+		assert!(legacy.decrypt(&key).is_err());
+		let legacy_key = EncryptedSwap::swap_key(1, swap_id, &key).unwrap();
+		assert_eq!(legacy.decrypt(&legacy_key).unwrap(), data);
+		let legacy: EncryptedSwap = serde_json::from_str(&format!(
+			r#"{{"nonce":"{}","body_enc":"{}"}}"#,
+			legacy.nonce, legacy.body_enc
+		))
+		.unwrap();
+		assert_eq!(legacy.version, 0);
+		assert_eq!(legacy.decrypt(&legacy_key).unwrap(), data);
+	}
+}
@@ -13,18 +13,23 @@
 // limitations under the License.
 
 use super::bitcoin::{BtcBuyerContext, BtcData, BtcSellerContext};
+use super::config::LOCK_TIME_TOLERANCE_PERCENT;
 use super::ser::*;
+use super::swap;
 use super::ErrorKind;
 use crate::swap::message::Message;
 use bitcoin::Address;
+use grin_core::consensus::GRIN_BASE;
 use grin_core::global::ChainTypes;
+use grin_core::libtx::secp_ser;
 use grin_core::{global, ser};
-use grin_keychain::Identifier;
+use grin_keychain::{ExtKeychainPath, Identifier};
 use grin_util::secp::key::SecretKey;
 use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::fmt;
 use std::str::FromStr;
+use uuid::Uuid;
 
 /// MWC Network where SWAP happens.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
@@ -103,6 +108,14 @@ impl Currency {
 		}
 	}
 
+	/// Default part of the lock time, percent, that the parties have to post the locks within.
+	/// Chains with the faster and more volatile blocks need a wider tolerance.
+	pub fn lock_time_tolerance_percent(&self) -> u64 {
+		match self {
+			Currency::Btc | Currency::Bch => LOCK_TIME_TOLERANCE_PERCENT,
+		}
+	}
+
 	/// Print amount in nano coins normally
 	pub fn amount_to_hr_string(&self, amount: u64, truncate: bool) -> String {
 		let exp = self.exponent();
@@ -149,6 +162,33 @@ impl Currency {
 		Ok(amount)
 	}
 
+	/// Exchange rate, units of this currency for 1 MWC. Amounts are in nano coins and Satoshis.
+	/// Zero MWC amount has zero rate.
+	pub fn rate(&self, mwc_amount: u64, secondary_amount: u64) -> f64 {
+		if mwc_amount == 0 {
+			return 0.0;
+		}
+		// Single division keeps the result precise for the exact amounts
+		(secondary_amount as f64 * GRIN_BASE as f64)
+			/ (mwc_amount as f64 * 10u64.pow(self.exponent() as u32) as f64)
+	}
+
+	/// Secondary amount in Satoshis for the MWC amount in nano coins at this rate
+	pub fn secondary_amount_at_rate(&self, mwc_amount: u64, rate: f64) -> u64 {
+		let mwc = mwc_amount as f64 / GRIN_BASE as f64;
+		(mwc * rate * 10u64.pow(self.exponent() as u32) as f64).round() as u64
+	}
+
+	/// MWC amount in nano coins for the secondary amount in Satoshis at this rate.
+	/// Zero rate has zero amount.
+	pub fn mwc_amount_at_rate(&self, secondary_amount: u64, rate: f64) -> u64 {
+		if rate <= 0.0 {
+			return 0;
+		}
+		let secondary = secondary_amount as f64 / 10u64.pow(self.exponent() as u32) as f64;
+		(secondary / rate * GRIN_BASE as f64).round() as u64
+	}
+
 	fn bch_network() -> bch::network::Network {
 		if global::is_mainnet() {
 			bch::network::Network::Mainnet
@@ -290,6 +330,13 @@ impl Currency {
 		}
 	}
 
+	/// Smallest output value that the network relays, satoshi
+	pub fn dust_limit(&self) -> u64 {
+		match self {
+			Currency::Btc | Currency::Bch => 546,
+		}
+	}
+
 	/// Fee units for this coin
 	pub fn get_fee_units(&self) -> String {
 		match self {
@@ -427,6 +474,79 @@ impl Context {
 			RoleContext::Buyer(c) => Ok(c),
 		}
 	}
+
+	/// Check that the nonces are pairwise distinct. Signatures with the same nonce reveal
+	/// the secret key, so the context with a repeated nonce must never be used for signing.
+	pub fn validate_nonces(&self) -> Result<(), ErrorKind> {
+		let nonces = [
+			("multisig", &self.multisig_nonce),
+			("lock", &self.lock_nonce),
+			("refund", &self.refund_nonce),
+			("redeem", &self.redeem_nonce),
+		];
+		for (i, (name1, nonce1)) in nonces.iter().enumerate() {
+			for (name2, nonce2) in &nonces[i + 1..] {
+				if nonce1 == nonce2 {
+					return Err(ErrorKind::DuplicateContextNonce(
+						name1.to_string(),
+						name2.to_string(),
+					));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// List the key derivation paths that this swap is using. Secrets are not exposed,
+	/// so the result can be used to audit keys usage.
+	pub fn derivation_paths(&self) -> Vec<DerivationPath> {
+		let mut paths = vec![DerivationPath::new("multisig", &self.multisig_key)];
+		match &self.role_context {
+			RoleContext::Seller(c) => {
+				for (input, _, _) in &c.inputs {
+					paths.push(DerivationPath::new("lock_input", input));
+				}
+				paths.push(DerivationPath::new("change_output", &c.change_output));
+				if let Some((split, _)) = &c.change_split {
+					paths.push(DerivationPath::new("change_output", split));
+				}
+				paths.push(DerivationPath::new("refund_output", &c.refund_output));
+				match &c.secondary_context {
+					SecondarySellerContext::Btc(btc) => {
+						paths.push(DerivationPath::new("secondary_cosign", &btc.cosign))
+					}
+				}
+			}
+			RoleContext::Buyer(c) => {
+				paths.push(DerivationPath::new("redeem_output", &c.output));
+				paths.push(DerivationPath::new("redeem_secret", &c.redeem));
+				match &c.secondary_context {
+					SecondaryBuyerContext::Btc(btc) => {
+						paths.push(DerivationPath::new("secondary_refund", &btc.refund))
+					}
+				}
+			}
+		}
+		paths
+	}
+}
+
+/// Key derivation path that is used by the swap
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivationPath {
+	/// What the key is used for
+	pub usage: String,
+	/// Key derivation path
+	pub path: ExtKeychainPath,
+}
+
+impl DerivationPath {
+	fn new(usage: &str, key_id: &Identifier) -> Self {
+		Self {
+			usage: usage.to_string(),
+			path: key_id.to_path(),
+		}
+	}
 }
 
 impl ser::Writeable for Context {
@@ -466,6 +586,10 @@ pub struct SellerContext {
 	pub change_output: Identifier,
 	/// Lock slate change amount
 	pub change_amount: u64,
+	/// Second lock slate change output and its amount. Round change is split in two outputs,
+	/// so it doesn't show which lock output is the change.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub change_split: Option<(Identifier, u64)>,
 	/// MWC refund output  (Derivative ID)
 	pub refund_output: Identifier,
 	/// Secondary currency (BTC) related context
@@ -543,6 +667,8 @@ pub enum Action {
 	WaitingForTradeBackup,
 	/// Seller Publishing an MWC lock transaction to the network
 	SellerPublishMwcLockTx,
+	/// Node rejected the MWC lock transaction. Seller can retry to publish it or cancel the swap
+	SellerRetryMwcLockTx,
 	/// Seller Publishing BTC redeem transaction to the network
 	SellerPublishTxSecondaryRedeem(Currency),
 	/// Deposit secondary currency
@@ -643,6 +769,7 @@ impl Action {
 			| Action::BuyerSendInitRedeemMessage(_)
 			| Action::SellerSendRedeemMessage(_)
 			| Action::SellerPublishMwcLockTx
+			| Action::SellerRetryMwcLockTx
 			| Action::SellerPublishTxSecondaryRedeem(_)
 			| Action::BuyerPublishMwcRedeemTx
 			| Action::SellerPublishMwcRefundTx
@@ -664,6 +791,7 @@ impl Action {
 			Action::BuyerWaitingForRedeemMessage => "BuyerWaitingForRedeemMessage",
 			Action::WaitingForTradeBackup => "WaitingForTradeBackup",
 			Action::SellerPublishMwcLockTx => "SellerPublishMwcLockTx",
+			Action::SellerRetryMwcLockTx => "SellerRetryMwcLockTx",
 			Action::SellerPublishTxSecondaryRedeem(_) => "SellerPublishTxSecondaryRedeem",
 			Action::DepositSecondary {
 				currency: _,
@@ -728,6 +856,7 @@ impl fmt::Display for Action {
 			}
 			Action::WaitingForTradeBackup => "Waiting when backup will be done".to_string(),
 			Action::SellerPublishMwcLockTx => "Posting MWC lock transaction".to_string(),
+			Action::SellerRetryMwcLockTx => "MWC lock transaction is rejected, probably the inputs are spent. Please retry to post it or cancel the swap and start a new one with the different inputs".to_string(),
 			Action::SellerPublishTxSecondaryRedeem(currency) => {
 				format!("Posting {} redeem transaction", currency)
 			}
@@ -830,6 +959,127 @@ impl fmt::Display for Action {
 	}
 }
 
+/// Recommendation if the swap can be continued, see Swap::safety_status
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafetyAssessment {
+	/// There is enough time to finish the swap
+	Safe,
+	/// Refund window is close, the swap might not be finished before the refund
+	CautionRefundWindowNear,
+	/// Swap can't be finished in time, refund is recommended
+	RefundNow,
+	/// Refund can be published and the redeem is possible as well. Both transactions spend
+	/// the locked coins, the one that is mined first wins. User must choose one deliberately.
+	Conflict,
+}
+
+impl fmt::Display for SafetyAssessment {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let disp = match &self {
+			SafetyAssessment::Safe => "Safe to continue",
+			SafetyAssessment::CautionRefundWindowNear => {
+				"Caution, the refund window is near. The swap might not be finished in time"
+			}
+			SafetyAssessment::RefundNow => "The swap can't be finished in time, refund it",
+			SafetyAssessment::Conflict => {
+				"Both redeem and refund are possible, the first mined transaction wins"
+			}
+		};
+		write!(f, "{}", disp)
+	}
+}
+
+/// MWC transaction of the swap that is published by the wallet, see Swap::last_broadcast_tx
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MwcTxAction {
+	/// Seller lock transaction
+	Lock,
+	/// Seller refund transaction
+	Refund,
+	/// Buyer redeem transaction
+	Redeem,
+}
+
+/// What the party gets back if the swap is refunded, see Swap::simulate_refund. Seller refunds
+/// MWC, the amounts are in nanocoins. Buyer refunds the secondary currency, the amounts are in
+/// its smallest units (satoshi).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RefundSimulation {
+	/// Currency of the refund, MWC or the secondary currency
+	pub currency: String,
+	/// Amount that the refund transaction returns to the wallet
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub amount_returned: u64,
+	/// Fee of the refund transaction
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub fee: u64,
+	/// Loss of the failed swap. For the Seller it is the lock and refund transaction fees. For
+	/// the Buyer it is the refund fee, the lock is funded by the external wallet that pays its fee.
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub net_loss: u64,
+}
+
+impl RefundSimulation {
+	/// Refund of the MWC that the Seller locked. The lock fee is lost as well.
+	pub fn mwc(amount: u64, lock_fee: u64, refund_fee: u64) -> Result<Self, ErrorKind> {
+		Ok(RefundSimulation {
+			currency: "MWC".to_string(),
+			amount_returned: swap::amount_minus_fee("MWC refund", amount, refund_fee)?,
+			fee: refund_fee,
+			net_loss: lock_fee + refund_fee,
+		})
+	}
+
+	/// Refund of the secondary currency that the Buyer locked. Dust output is not relayed,
+	/// such refund can't be posted.
+	pub fn secondary(currency: Currency, amount: u64, refund_fee: u64) -> Result<Self, ErrorKind> {
+		let amount_returned = swap::amount_minus_fee("secondary refund", amount, refund_fee)?;
+		if amount_returned < currency.dust_limit() {
+			return Err(ErrorKind::DustOutput(
+				"secondary refund".to_string(),
+				amount_returned,
+				currency.dust_limit(),
+			));
+		}
+		Ok(RefundSimulation {
+			currency: currency.to_string(),
+			amount_returned,
+			fee: refund_fee,
+			net_loss: refund_fee,
+		})
+	}
+}
+
+/// Evidence of the completed swap on both chains, see Swap::completion_certificate.
+/// A third party can verify it by finding the kernels on the MWC chain and the redeem
+/// transaction on the secondary chain. The redeem transaction must spend the lock address,
+/// that is the hash of the lock script with the public keys of both parties.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompletionCertificate {
+	/// Swap id
+	pub swap_id: Uuid,
+	/// Network of both chains
+	pub network: Network,
+	/// MWC amount, nano coins
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub primary_amount: u64,
+	/// Secondary currency amount, smallest units
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub secondary_amount: u64,
+	/// Secondary currency
+	pub secondary_currency: Currency,
+	/// Kernel excess of the MWC lock transaction, hex
+	pub mwc_lock_kernel: String,
+	/// Kernel excess of the MWC redeem transaction, hex
+	pub mwc_redeem_kernel: String,
+	/// Secondary lock address that the Buyer funded
+	pub secondary_lock_address: String,
+	/// Secondary lock script, hex. Its hash is the lock address.
+	pub secondary_lock_script: String,
+	/// Id of the secondary redeem transaction
+	pub secondary_redeem_tx: String,
+}
+
 /// Status of the transactions that can be published.
 ///  None for confirmations - Unable to verify, probably Transaction data is not here.
 pub struct SwapTransactionsConfirmations {
@@ -856,6 +1106,46 @@ pub struct SwapTransactionsConfirmations {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::grin_util::secp::Secp256k1;
+	use grin_keychain::ExtKeychain;
+	use grin_keychain::Keychain;
+
+	#[test]
+	fn test_buyer_derivation_paths() {
+		let secp = Secp256k1::new();
+		let nonce = SecretKey::new(&secp, &mut rand::thread_rng());
+		let key_id = |d1: u32, d2: u32| ExtKeychain::derive_key_id(2, d1, d2, 0, 0);
+		let context = Context {
+			multisig_key: key_id(0, 0),
+			multisig_nonce: nonce.clone(),
+			lock_nonce: nonce.clone(),
+			refund_nonce: nonce.clone(),
+			redeem_nonce: nonce,
+			role_context: RoleContext::Buyer(BuyerContext {
+				parent_key_id: key_id(0, 0),
+				output: key_id(0, 1),
+				redeem: key_id(0, 2),
+				secondary_context: SecondaryBuyerContext::Btc(BtcBuyerContext {
+					refund: key_id(0, 3),
+				}),
+			}),
+		};
+
+		let paths = context.derivation_paths();
+		let usage: Vec<&str> = paths.iter().map(|p| p.usage.as_str()).collect();
+		assert_eq!(
+			usage,
+			vec![
+				"multisig",
+				"redeem_output",
+				"redeem_secret",
+				"secondary_refund"
+			]
+		);
+		for (i, p) in paths.iter().enumerate() {
+			assert_eq!(p.path, ExtKeychainPath::new(2, 0, i as u32, 0, 0));
+		}
+	}
 
 	#[test]
 	fn test_amounts_to_hr() {
@@ -937,6 +1227,30 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_amounts_rate() {
+		let c = Currency::Btc;
+		// 100 MWC for 0.03 BTC
+		assert_eq!(c.rate(100 * GRIN_BASE, 3_000_000), 0.0003);
+		assert_eq!(
+			c.secondary_amount_at_rate(100 * GRIN_BASE, 0.0003),
+			3_000_000
+		);
+		assert_eq!(c.mwc_amount_at_rate(3_000_000, 0.0003), 100 * GRIN_BASE);
+		// 1.5 MWC for 3 BTC
+		assert_eq!(c.rate(1_500_000_000, 300_000_000), 2.0);
+		assert_eq!(c.secondary_amount_at_rate(1_500_000_000, 2.0), 300_000_000);
+		assert_eq!(c.mwc_amount_at_rate(300_000_000, 2.0), 1_500_000_000);
+		// 1 nano MWC for 1 Satoshi
+		assert_eq!(c.rate(1, 1), 10.0);
+		assert_eq!(c.mwc_amount_at_rate(1, 10.0), 1);
+
+		let c = Currency::Bch;
+		assert_eq!(c.rate(2 * GRIN_BASE, 50_000_000), 0.25);
+		assert_eq!(c.rate(0, 50_000_000), 0.0);
+		assert_eq!(c.mwc_amount_at_rate(50_000_000, 0.0), 0);
+	}
+
 	#[test]
 	fn test_bch_address_parsers() {
 		global::set_mining_mode(ChainTypes::Floonet);
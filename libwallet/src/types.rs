@@ -21,20 +21,23 @@ use crate::grin_core::core::hash::Hash;
 use crate::grin_core::core::{Output, Transaction, TxKernel};
 use crate::grin_core::libtx::{aggsig, secp_ser};
 use crate::grin_core::{global, ser};
-use crate::grin_keychain::{Identifier, Keychain};
+use crate::grin_keychain::{Identifier, Keychain, SwitchCommitmentType};
 use crate::grin_util::logger::LoggingConfig;
 use crate::grin_util::secp::key::{PublicKey, SecretKey, ZERO_KEY};
 use crate::grin_util::secp::pedersen::Commitment;
 use crate::grin_util::secp::{self, pedersen, Secp256k1};
 use crate::grin_util::ZeroingString;
-use crate::proof::proofaddress::ProvableAddress;
-use crate::slate::ParticipantMessages;
+use crate::proof::proofaddress::{self, ProvableAddress};
+use crate::slate::{ParticipantMessages, Round1Keys};
 use crate::Slate;
 use chrono::prelude::*;
 use serde;
 use serde_json;
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// Combined trait to allow dynamic wallet dispatch
@@ -229,6 +232,17 @@ where
 	/// Load a txn from specified file
 	fn load_stored_tx(&self, path: &str) -> Result<Transaction, Error>;
 
+	/// Stores the slate that was sent, so it can be exported again. Backends that don't keep
+	/// the slates skip it, such slates can't be exported again.
+	fn store_slate(&self, _uuid: &str, _slate: &Slate) -> Result<(), Error> {
+		Ok(())
+	}
+
+	/// Load the stored slate by UUID. None if the slate wasn't stored
+	fn get_stored_slate(&self, _uuid: &str) -> Result<Option<Slate>, Error> {
+		Ok(None)
+	}
+
 	/// Create a new write batch to update or remove output data
 	fn batch<'a>(
 		&'a mut self,
@@ -359,9 +373,19 @@ pub trait NodeClient: Send + Sync + Clone {
 	/// Reset cache data
 	fn reset_cache(&self);
 
+	/// Limit the time of a single call to the node. The call that takes longer fails with
+	/// ErrorKind::NodeTimeout. None - default client timeouts are applied.
+	/// Clients that are never slow can ignore it.
+	fn set_call_timeout(&mut self, _timeout: Option<Duration>) {}
+
 	/// Posts a transaction to a grin node
 	fn post_tx(&self, tx: &Transaction, fluff: bool) -> Result<(), Error>;
 
+	/// Number of transactions in the node's mempool. None if the node doesn't report it
+	fn get_pool_size(&self) -> Option<u64> {
+		None
+	}
+
 	/// Returns the api version string and block header version as reported
 	/// by the node. Result can be cached for later use
 	fn get_version_info(&mut self) -> Option<NodeVersionInfo>;
@@ -386,6 +410,21 @@ pub trait NodeClient: Send + Sync + Clone {
 		max_height: Option<u64>,
 	) -> Result<Option<(TxKernel, u64, u64)>, Error>;
 
+	/// Get the chain tip height and the kernels with a single request. Kernels are requested by
+	/// (excess, min_height), results are in the same order. Used by the polling loops that need
+	/// both on every check. Default implementation calls get_chain_tip and get_kernel one by one.
+	fn get_tip_and_kernels(
+		&self,
+		kernels: &[(pedersen::Commitment, Option<u64>)],
+	) -> Result<(u64, Vec<Option<(TxKernel, u64, u64)>>), Error> {
+		let height = self.get_chain_tip()?.0;
+		let mut res = Vec::with_capacity(kernels.len());
+		for (excess, min_height) in kernels {
+			res.push(self.get_kernel(excess, *min_height, None)?);
+		}
+		Ok((height, res))
+	}
+
 	/// retrieve a list of outputs from the specified grin node
 	/// need "by_height" and "by_id" variants
 	/// Result value: Commit, Height, MMR
@@ -624,6 +663,17 @@ pub struct Context {
 	/// Input commitments, mwc713 payment proof support.
 	#[serde(default)]
 	pub input_commits: Vec<Commitment>,
+	/// Recipient the slate was bound to. The responded slate is verified against it.
+	#[serde(
+		serialize_with = "proofaddress::option_as_string",
+		deserialize_with = "proofaddress::option_proof_address_from_string"
+	)]
+	#[serde(default)]
+	pub recipient_binding: Option<ProvableAddress>,
+	/// Nonce key id if the nonce is derived from the keychain. Such slate can be signed
+	/// on another machine with the same seed.
+	#[serde(default)]
+	pub nonce_id: Option<Identifier>,
 }
 
 impl Context {
@@ -651,6 +701,8 @@ impl Context {
 			payment_proof_derivation_index: None,
 			output_commits: vec![],
 			input_commits: vec![],
+			recipient_binding: None,
+			nonce_id: None,
 		}
 	}
 
@@ -677,6 +729,8 @@ impl Context {
 			payment_proof_derivation_index: None,
 			output_commits: slate.tx.body.outputs.iter().map(|o| o.commit).collect(),
 			input_commits: slate.tx.body.inputs.iter().map(|i| i.commit).collect(),
+			recipient_binding: None,
+			nonce_id: None,
 		})
 	}
 }
@@ -705,6 +759,34 @@ impl Context {
 		self.input_ids.clone()
 	}
 
+	/// Replace the random nonce with the one derived from the keychain. Call it before round 1.
+	pub fn derive_nonce<K: Keychain>(&mut self, keychain: &K) -> Result<(), Error> {
+		let nonce_id = Round1Keys::new_nonce_id();
+		self.sec_nonce = keychain.derive_key(0, &nonce_id, SwitchCommitmentType::None)?;
+		self.nonce_id = Some(nonce_id);
+		Ok(())
+	}
+
+	/// Keys of this context for the split signing
+	pub fn round_1_keys(&self) -> Result<Round1Keys, Error> {
+		let nonce = self.nonce_id.clone().ok_or(ErrorKind::GenericError(
+			"Transaction nonce is random, it can't be signed on another machine".to_string(),
+		))?;
+		Ok(Round1Keys {
+			inputs: self
+				.input_ids
+				.iter()
+				.map(|(id, _, a)| (id.clone(), *a))
+				.collect(),
+			outputs: self
+				.output_ids
+				.iter()
+				.map(|(id, _, a)| (id.clone(), *a))
+				.collect(),
+			nonce,
+		})
+	}
+
 	/// Returns private key, private nonce
 	pub fn get_private_keys(&self) -> (SecretKey, SecretKey) {
 		(self.sec_key.clone(), self.sec_nonce.clone())
@@ -1241,3 +1323,27 @@ pub struct HeaderInfo {
 	/// total chain difficulty for this header
 	pub total_difficulty: u64,
 }
+
+/// Token to cancel the long running operation. Clones share the state, so the token can be
+/// triggered from another thread while the operation is in progress.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+	cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+	/// New, not cancelled token
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Request the cancellation. The operation stops at the next check point.
+	pub fn cancel(&self) {
+		self.cancelled.store(true, Ordering::SeqCst);
+	}
+
+	/// True if the cancellation was requested
+	pub fn is_cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::SeqCst)
+	}
+}
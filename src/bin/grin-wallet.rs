@@ -165,5 +165,13 @@ fn real_main() -> i32 {
 	let node_client = HTTPNodeClient::new(&wallet_config.check_node_api_http_addr, None)
 		.expect("Unable create HTTP client for mwc-node connection");
 
+	// Auto swap threads might be in the middle of the swap update when Ctrl-C is pressed
+	if let Err(e) = ctrlc::set_handler(|| {
+		grin_wallet_libwallet::swap::trades::shutdown_swap_trades();
+		std::process::exit(130);
+	}) {
+		warn!("Unable to set Ctrl-C handler, {}", e);
+	}
+
 	cmd::wallet_command(&args, config, node_client)
 }
@@ -129,6 +129,7 @@ where
 	// catch updater messages
 	// mwc updater thread is better, it will be created for None
 	let mut owner_api = Owner::new(wallet_inst, None, None);
+	owner_api.set_swap_config(wallet_args::swap_config(wallet_config)?);
 
 	// start the automatic updater
 	owner_api.start_updater((&keychain_mask).as_ref(), Duration::from_secs(60))?;
@@ -187,6 +188,27 @@ where
 									wallet_inst.get_data_file_dir(),
 									&wallet_config.swap_electrumx_addr,
 								);
+								grin_wallet_libwallet::internal::checkpoint::init_slate_checkpoint_backend(
+									wallet_inst.get_data_file_dir(),
+									wallet_config.slate_checkpoints.unwrap_or(false),
+								);
+								grin_wallet_libwallet::set_payment_proof_required(
+									wallet_config.require_payment_proof.unwrap_or(false),
+								);
+								grin_wallet_libwallet::set_missing_payment_proof_error(
+									wallet_config.reject_missing_payment_proof.unwrap_or(false),
+								);
+								grin_wallet_libwallet::internal::version_log::init_slate_version_log(
+									wallet_inst.get_data_file_dir(),
+									wallet_config.slate_version_log.unwrap_or(false),
+								)?;
+								grin_wallet_impls::adapters::schema::set_slate_schema_validation(
+									wallet_config.slate_schema_validation.unwrap_or(false),
+								);
+								grin_wallet_libwallet::swap::swap::set_counterparty_allowlist(
+									wallet_config.swap_counterparty_allowlist.clone(),
+								)
+								.map_err(grin_wallet_libwallet::Error::from)?;
 
 								if let Some(account) = args.value_of("account") {
 									if wallet_opened {
@@ -242,6 +264,8 @@ where
 			}
 		}
 	}
+	// Auto swap threads might be in the middle of the swap update
+	grin_wallet_libwallet::swap::trades::shutdown_swap_trades();
 	Ok(())
 
 	//let _ = reader.save_history(&history_file);
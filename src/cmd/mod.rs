@@ -15,4 +15,4 @@
 mod wallet;
 pub mod wallet_args;
 
-pub use self::wallet::wallet_command;
+pub use self::wallet::{is_node_compatible, required_node_version, wallet_command, WalletOp};
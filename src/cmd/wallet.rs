@@ -15,12 +15,105 @@
 use crate::cmd::wallet_args;
 use crate::config::GlobalWalletConfig;
 use clap::ArgMatches;
-use grin_wallet_libwallet::NodeClient;
+use grin_wallet_libwallet::{owner, NodeClient};
 use semver::Version;
-use std::thread;
-use std::time::Duration;
 
 const MIN_COMPAT_NODE_VERSION: &str = "3.0.0";
+/// Swaps request the chain tip together with the kernels as one JSON-RPC batch to the node
+/// foreign API v2 (see `HTTPNodeClient::get_tip_and_kernels_batch`). Older nodes don't serve
+/// such batches, update this version if swaps start to use newer node API calls.
+const MIN_SWAP_NODE_VERSION: &str = "3.2.0";
+
+/// Groups of wallet operations that have different requirements to the node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WalletOp {
+	/// Offline commands and queries: info, txs, outputs, etc.
+	Basic,
+	/// Building and posting transactions
+	Transaction,
+	/// Atomic swaps
+	Swap,
+}
+
+impl WalletOp {
+	/// Operation group for the command line subcommand
+	pub fn from_command(command: &str) -> Self {
+		match command {
+			"send" | "receive" | "finalize" | "invoice" | "finalize_invoice" | "pay" | "post"
+			| "submit" | "repost" => WalletOp::Transaction,
+			c if c.starts_with("swap") => WalletOp::Swap,
+			_ => WalletOp::Basic,
+		}
+	}
+}
+
+/// Minimal node version that is required for the operation
+pub fn required_node_version(op: WalletOp) -> Version {
+	let version = match op {
+		WalletOp::Basic | WalletOp::Transaction => MIN_COMPAT_NODE_VERSION,
+		WalletOp::Swap => MIN_SWAP_NODE_VERSION,
+	};
+	Version::parse(version).expect("Invalid required node version")
+}
+
+/// Check if the node version is good enough for the operation.
+/// Unparsable versions are treated as incompatible, the user can override it with
+/// --allow-incompatible-node.
+pub fn is_node_compatible(op: WalletOp, node_version: &str) -> bool {
+	match Version::parse(node_version) {
+		Ok(v) => v >= required_node_version(op),
+		Err(_) => false,
+	}
+}
+
+/// Check the node version for the operation. Returns the warning if the node is incompatible,
+/// but it is allowed by the user. Error with the report if the command can't be run.
+pub fn check_node_version(
+	op: WalletOp,
+	node_version: &str,
+	allow_incompatible: bool,
+) -> Result<Option<String>, String> {
+	if is_node_compatible(op, node_version) {
+		return Ok(None);
+	}
+	if Version::parse(node_version).is_err() {
+		let report = format!(
+			"Unable to recognize the version '{}' of the Grin Node in use. Version {} or later is required for this wallet command.",
+			node_version,
+			required_node_version(op)
+		);
+		return if allow_incompatible {
+			Ok(Some(format!(
+				"{} Continuing because of --allow-incompatible-node, the command might fail or produce wrong results.",
+				report
+			)))
+		} else {
+			Err(format!(
+				"{}\nPlease check the node, or run the command with --allow-incompatible-node if you trust it.",
+				report
+			))
+		};
+	}
+	if allow_incompatible {
+		Ok(Some(format!(
+			"The Grin Node in use (version {}) is outdated and incompatible with this wallet command. Version {} or later is required. Continuing because of --allow-incompatible-node, the command might fail or produce wrong results.",
+			node_version,
+			required_node_version(op)
+		)))
+	} else {
+		Err(format!(
+			"The Grin Node in use (version {}) is outdated and incompatible with this wallet command.\nPlease update the node to version {} or later and try again.",
+			node_version,
+			required_node_version(op)
+		))
+	}
+}
+
+/// Write all buffered log records. The logger flushes its appenders, so nothing is lost
+/// on exit and there is no need to wait for it.
+pub fn flush_logs() {
+	log::logger().flush();
+}
 
 pub fn wallet_command<C>(
 	wallet_args: &ArgMatches<'_>,
@@ -42,18 +135,39 @@ where
 	node_client.set_node_api_secret(global_wallet_args.node_api_secret.clone());
 
 	// This will also cache the node version info for calls to foreign API check middleware
+	// Requirements depend on the command, basic functions are available with older nodes.
+	let op = WalletOp::from_command(wallet_args.subcommand().0);
 	if let Some(v) = node_client.clone().get_version_info() {
-		if Version::parse(&v.node_version) < Version::parse(MIN_COMPAT_NODE_VERSION) {
-			println!("The Grin Node in use (version {}) is outdated and incompatible with this wallet version.", v.node_version);
-			println!(
-				"Please update the node to version {} or later and try again.",
-				MIN_COMPAT_NODE_VERSION
-			);
-			return 1;
+		match check_node_version(
+			op,
+			&v.node_version,
+			wallet_args.is_present("allow_incompatible_node"),
+		) {
+			Ok(None) => (),
+			Ok(Some(warning)) => println!("WARNING: {}", warning),
+			Err(report) => {
+				println!("{}", report);
+				return 1;
+			}
 		}
 	}
 	// ... if node isn't available, allow offline functions
 
+	// Node might be alive but stale or on a fork. Swaps and height based checks are unsafe in this case.
+	// Basic commands don't depend on the chain height, no need for the extra node calls.
+	if op != WalletOp::Basic {
+		if let Ok((tip_height, _, _)) = node_client.get_chain_tip() {
+			if let Ok(Some(warning)) = owner::check_node_sync_state(&node_client, tip_height) {
+				println!("WARNING: {}", warning);
+				if wallet_args.is_present("strict_node_check") {
+					println!("Please check your node or use another one and try again.");
+					return 1;
+				}
+				println!("Swap and other height based operations might be unsafe with this node.");
+			}
+		}
+	}
+
 	let res = wallet_args::wallet_command(
 		wallet_args,
 		wallet_config,
@@ -64,8 +178,8 @@ where
 		|_| {},
 	);
 
-	// we need to give log output a chance to catch up before exiting
-	thread::sleep(Duration::from_millis(100));
+	// log output must be written before exiting
+	flush_logs();
 
 	if let Err(e) = res {
 		println!("Wallet command failed: {}", e);
@@ -78,3 +192,93 @@ where
 		0
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use grin_wallet_util::grin_util::init_logger;
+	use grin_wallet_util::grin_util::logger::{LogLevel, LoggingConfig};
+
+	#[test]
+	fn test_required_node_version() {
+		let info = WalletOp::from_command("info");
+		let send = WalletOp::from_command("send");
+		let swap = WalletOp::from_command("swap");
+		assert_eq!(info, WalletOp::Basic);
+		assert_eq!(send, WalletOp::Transaction);
+		assert_eq!(swap, WalletOp::Swap);
+		assert_eq!(WalletOp::from_command("swap_start"), WalletOp::Swap);
+		assert!(required_node_version(swap) > required_node_version(info));
+
+		// Old node is fine for the basic functions, but not for swaps
+		let old_node = "3.1.0";
+		assert!(is_node_compatible(info, old_node));
+		assert!(is_node_compatible(send, old_node));
+		assert!(!is_node_compatible(swap, old_node));
+		assert!(is_node_compatible(swap, MIN_SWAP_NODE_VERSION));
+
+		// Too old node blocks everything
+		assert!(!is_node_compatible(info, "2.4.0"));
+
+		// Version that can't be recognized is not trusted
+		assert!(!is_node_compatible(info, "unknown"));
+		assert!(!is_node_compatible(swap, ""));
+	}
+
+	#[test]
+	fn test_allow_incompatible_node() {
+		let info = WalletOp::from_command("info");
+		assert_eq!(check_node_version(info, "3.1.0", false), Ok(None));
+		assert_eq!(check_node_version(info, "3.1.0", true), Ok(None));
+
+		// Incompatible node stops the command by default
+		let report = check_node_version(info, "2.4.0", false).unwrap_err();
+		assert!(report.contains("2.4.0"));
+		assert!(report.contains(MIN_COMPAT_NODE_VERSION));
+
+		// With the override the command proceeds with a warning
+		let warning = check_node_version(info, "2.4.0", true).unwrap().unwrap();
+		assert!(warning.contains("2.4.0"));
+		assert!(warning.contains("--allow-incompatible-node"));
+
+		// Unrecognized version is reported, the user can override it too
+		let report = check_node_version(info, "custom-build", false).unwrap_err();
+		assert!(report.contains("Unable to recognize the version 'custom-build'"));
+		assert!(report.contains("--allow-incompatible-node"));
+		let warning = check_node_version(info, "custom-build", true)
+			.unwrap()
+			.unwrap();
+		assert!(warning.contains("custom-build"));
+	}
+
+	#[test]
+	fn test_flush_logs() {
+		// The wallet logger writes into the file, it is the only logger that the tests install
+		let log_file =
+			std::env::temp_dir().join(format!("mwc-wallet-flush-logs-{}.log", std::process::id()));
+		let _ = std::fs::remove_file(&log_file);
+		init_logger(
+			Some(LoggingConfig {
+				log_to_stdout: false,
+				log_to_file: true,
+				file_log_level: LogLevel::Info,
+				log_file_path: log_file.to_str().unwrap().to_string(),
+				log_file_append: false,
+				log_max_size: None,
+				..LoggingConfig::default()
+			}),
+			None,
+		);
+
+		for i in 0..100 {
+			log::info!("flush_logs record {}", i);
+		}
+
+		// Everything is written before the exit
+		flush_logs();
+		let written = std::fs::read_to_string(&log_file).unwrap();
+		let _ = std::fs::remove_file(&log_file);
+		assert!(written.contains("flush_logs record 0\n"));
+		assert!(written.contains("flush_logs record 99\n"));
+	}
+}
@@ -32,10 +32,12 @@ use grin_wallet_impls::{DefaultLCProvider, DefaultWalletImpl};
 use grin_wallet_impls::{PathToSlate, SlateGetter as _};
 use grin_wallet_libwallet::proof::proofaddress;
 use grin_wallet_libwallet::proof::proofaddress::ProvableAddress;
-use grin_wallet_libwallet::Slate;
+use grin_wallet_libwallet::swap::config::SwapConfig;
+use grin_wallet_libwallet::swap::types::Currency;
 use grin_wallet_libwallet::{
-	IssueInvoiceTxArgs, NodeClient, SwapStartArgs, WalletInst, WalletLCProvider,
+	IssueInvoiceTxArgs, NodeClient, SwapAcceptArgs, SwapStartArgs, WalletInst, WalletLCProvider,
 };
+use grin_wallet_libwallet::{Slate, SlateVersion};
 use grin_wallet_util::grin_core as core;
 use grin_wallet_util::grin_core::core::amount_to_hr_string;
 use grin_wallet_util::grin_core::global;
@@ -43,8 +45,10 @@ use grin_wallet_util::grin_keychain as keychain;
 use linefeed::terminal::Signal;
 use linefeed::{Interface, ReadResult};
 use rpassword;
+use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 // define what to do on argument error
 macro_rules! arg_parse {
@@ -399,6 +403,14 @@ pub fn parse_listen_args(
 	})
 }
 
+pub fn parse_slate_serve_args(args: &ArgMatches) -> Result<command::SlateServeArgs, ParseError> {
+	let listen = parse_required(args, "listen")?;
+	Ok(command::SlateServeArgs {
+		listen: listen.to_owned(),
+		allow_remote: args.is_present("allow_remote"),
+	})
+}
+
 pub fn parse_owner_api_args(
 	config: &mut WalletConfig,
 	args: &ArgMatches,
@@ -568,6 +580,8 @@ pub fn parse_send_args(args: &ArgMatches) -> Result<command::SendArgs, ParseErro
 		false => None,
 	};
 
+	let split_signing = parse_split_signing(args, method)?;
+
 	if minimum_confirmations_change_outputs_is_present && !exclude_change_outputs {
 		Err(ArgumentError("minimum_confirmations_change_outputs may only be specified if exclude_change_outputs is set".to_string()))
 	} else {
@@ -584,16 +598,29 @@ pub fn parse_send_args(args: &ArgMatches) -> Result<command::SendArgs, ParseErro
 			fluff: fluff,
 			max_outputs: max_outputs,
 			payment_proof_address,
+			bind_to_recipient: args.is_present("bind_recipient"),
 			ttl_blocks,
 			target_slate_version: target_slate_version,
 			exclude_change_outputs: exclude_change_outputs,
 			minimum_confirmations_change_outputs: minimum_confirmations_change_outputs,
 			address: address,
 			outputs,
+			split_signing,
 		})
 	}
 }
 
+// The round 1 export file. The slate must stay in the file, other methods would finalize it here.
+fn parse_split_signing(args: &ArgMatches, method: &str) -> Result<Option<String>, ParseError> {
+	match args.value_of("split_signing") {
+		Some(_) if method != "file" => Err(ParseError::ArgumentError(
+			"split_signing requires the file method".to_string(),
+		)),
+		Some(file) => Ok(Some(file.to_owned())),
+		None => Ok(None),
+	}
+}
+
 pub fn parse_receive_args(receive_args: &ArgMatches) -> Result<command::ReceiveArgs, ParseError> {
 	// message
 	let message = match receive_args.is_present("message") {
@@ -610,9 +637,25 @@ pub fn parse_receive_args(receive_args: &ArgMatches) -> Result<command::ReceiveA
 		return Err(ParseError::ArgumentError(msg));
 	}
 
+	// expected amount
+	let expected_amount = match receive_args.value_of("expected_amount") {
+		Some(amount) => match core::core::amount_from_hr_string(amount) {
+			Ok(a) => Some(a),
+			Err(e) => {
+				let msg = format!(
+					"Could not parse expected amount as a number with optional decimal point. e={}",
+					e
+				);
+				return Err(ParseError::ArgumentError(msg));
+			}
+		},
+		None => None,
+	};
+
 	Ok(command::ReceiveArgs {
 		input: tx_file.to_owned(),
 		message: message,
+		expected_amount,
 	})
 }
 
@@ -671,6 +714,7 @@ pub fn parse_issue_invoice_args(
 	};
 	// dest (output file)
 	let dest = parse_required(args, "dest")?;
+	let split_signing = parse_split_signing(args, "file")?;
 	Ok(command::IssueInvoiceArgs {
 		dest: dest.into(),
 		issue_args: IssueInvoiceTxArgs {
@@ -679,7 +723,9 @@ pub fn parse_issue_invoice_args(
 			amount,
 			message,
 			target_slate_version,
+			split_signing: Some(split_signing.is_some()),
 		},
+		split_signing,
 	})
 }
 
@@ -743,6 +789,8 @@ pub fn parse_process_invoice_args(
 	// file input only
 	let tx_file = parse_required(args, "input")?;
 
+	let split_signing = parse_split_signing(args, method)?;
+
 	if prompt {
 		// Now we need to prompt the user whether they want to do this,
 		// which requires reading the slate
@@ -765,6 +813,7 @@ pub fn parse_process_invoice_args(
 		max_outputs: max_outputs,
 		input: tx_file.to_owned(),
 		ttl_blocks,
+		split_signing,
 	})
 }
 
@@ -936,6 +985,99 @@ pub fn parse_verify_proof_args(args: &ArgMatches) -> Result<command::ProofVerify
 	})
 }
 
+pub fn parse_verify_slate_args(args: &ArgMatches) -> Result<command::SlateVerifyArgs, ParseError> {
+	let input = parse_required(args, "input")?;
+	Ok(command::SlateVerifyArgs {
+		input: input.to_owned(),
+		json: args.is_present("json"),
+	})
+}
+
+pub fn parse_inspect_slate_args(
+	args: &ArgMatches,
+) -> Result<command::SlateInspectArgs, ParseError> {
+	let input = parse_required(args, "input")?;
+	Ok(command::SlateInspectArgs {
+		input: input.to_owned(),
+	})
+}
+
+pub fn parse_reexport_slate_args(
+	args: &ArgMatches,
+) -> Result<command::SlateReexportArgs, ParseError> {
+	let slate_id = match parse_required(args, "slate_id")?.parse() {
+		Ok(id) => id,
+		Err(e) => {
+			let msg = format!("Could not parse slate_id parameter. e={}", e);
+			return Err(ParseError::ArgumentError(msg));
+		}
+	};
+	let version = match parse_required(args, "version")? {
+		"2" => SlateVersion::V2,
+		"3" => SlateVersion::V3,
+		v => {
+			let msg = format!("Unsupported slate version {}, expected 2 or 3", v);
+			return Err(ParseError::ArgumentError(msg));
+		}
+	};
+	let dest = parse_required(args, "out")?;
+	Ok(command::SlateReexportArgs {
+		slate_id,
+		version,
+		dest: dest.to_owned(),
+	})
+}
+
+pub fn parse_resume_slate_args(args: &ArgMatches) -> Result<command::SlateResumeArgs, ParseError> {
+	let slate_id = match parse_required(args, "slate_id")?.parse() {
+		Ok(id) => id,
+		Err(e) => {
+			let msg = format!("Could not parse slate_id parameter. e={}", e);
+			return Err(ParseError::ArgumentError(msg));
+		}
+	};
+	let input = args.value_of("input").map(|s| s.to_owned());
+	let dest = parse_required(args, "out")?;
+	Ok(command::SlateResumeArgs {
+		slate_id,
+		input,
+		dest: dest.to_owned(),
+	})
+}
+
+pub fn parse_finalize_exported_args(
+	args: &ArgMatches,
+) -> Result<command::FinalizeExportedArgs, ParseError> {
+	let export = parse_required(args, "export")?;
+	let input = parse_required(args, "input")?;
+	let dest = parse_required(args, "out")?;
+	Ok(command::FinalizeExportedArgs {
+		export: export.to_owned(),
+		input: input.to_owned(),
+		dest: dest.to_owned(),
+	})
+}
+
+pub fn parse_swap_accept_args(args: &ArgMatches) -> Result<SwapAcceptArgs, ParseError> {
+	let min_mwc_confirmations = match args.value_of("mwc_confirmations") {
+		Some(c) => Some(parse_u64(c, "mwc_confirmations")?),
+		None => None,
+	};
+	let min_secondary_confirmations = match args.value_of("secondary_confirmations") {
+		Some(c) => Some(parse_u64(c, "secondary_confirmations")?),
+		None => None,
+	};
+	let external_ref = args
+		.value_of("external_ref")
+		.map(|s| String::from(s))
+		.filter(|s| !s.is_empty());
+	Ok(SwapAcceptArgs {
+		min_mwc_confirmations,
+		min_secondary_confirmations,
+		external_ref,
+	})
+}
+
 pub fn parse_swap_start_args(args: &ArgMatches) -> Result<SwapStartArgs, ParseError> {
 	let mwc_amount = parse_required(args, "mwc_amount")?;
 	let mwc_amount = core::core::amount_from_hr_string(mwc_amount);
@@ -1004,6 +1146,11 @@ pub fn parse_swap_start_args(args: &ArgMatches) -> Result<SwapStartArgs, ParseEr
 		None => None,
 	};
 
+	let external_ref = args
+		.value_of("external_ref")
+		.map(|s| String::from(s))
+		.filter(|s| !s.is_empty());
+
 	let dry_run = args.is_present("dry_run");
 
 	Ok(SwapStartArgs {
@@ -1022,6 +1169,7 @@ pub fn parse_swap_start_args(args: &ArgMatches) -> Result<SwapStartArgs, ParseEr
 		buyer_communication_address: destination.to_string(),
 		electrum_node_uri1,
 		electrum_node_uri2,
+		external_ref,
 		dry_run,
 	})
 }
@@ -1036,12 +1184,25 @@ pub fn parse_swap_args(args: &ArgMatches) -> Result<command::SwapArgs, ParseErro
 		Some(s) => Some(parse_f32(s, "secondary_fee")?),
 		None => None,
 	};
+	let redeem_fee = match args.value_of("redeem_fee") {
+		Some(s) => match core::core::amount_from_hr_string(s) {
+			Ok(fee) => Some(fee),
+			Err(e) => {
+				return Err(ParseError::ArgumentError(format!(
+					"Could not parse redeem_fee as a number with optional decimal point. e={}",
+					e
+				)))
+			}
+		},
+		None => None,
+	};
 	let message_file_name = args.value_of("message_file_name").map(|s| String::from(s));
 	let buyer_refund_address = args
 		.value_of("buyer_refund_address")
 		.map(|s| String::from(s));
 	let secondary_address = args.value_of("secondary_address").map(|s| String::from(s));
 	let start_listener = args.is_present("start_listener");
+	let alias = args.value_of("alias").map(|s| String::from(s));
 
 	let subcommand = if args.is_present("list") {
 		if args.is_present("check") {
@@ -1057,6 +1218,14 @@ pub fn parse_swap_args(args: &ArgMatches) -> Result<command::SwapArgs, ParseErro
 		command::SwapSubcommand::Process
 	} else if args.is_present("dump") {
 		command::SwapSubcommand::Dump
+	} else if args.is_present("message_log") {
+		command::SwapSubcommand::MessageLog
+	} else if args.is_present("simulate_refund") {
+		command::SwapSubcommand::SimulateRefund
+	} else if args.is_present("certificate") {
+		command::SwapSubcommand::Certificate
+	} else if args.is_present("verify_all") {
+		command::SwapSubcommand::VerifyAll
 	} else if args.is_present("trade_export") {
 		destination = args.value_of("trade_export").map(|s| String::from(s));
 		command::SwapSubcommand::TradeExport
@@ -1069,6 +1238,10 @@ pub fn parse_swap_args(args: &ArgMatches) -> Result<command::SwapArgs, ParseErro
 		command::SwapSubcommand::Autoswap
 	} else if args.is_present("stop_auto_swap") {
 		command::SwapSubcommand::StopAllAutoSwap
+	} else if args.is_present("rebroadcast") {
+		command::SwapSubcommand::Rebroadcast
+	} else if alias.is_some() {
+		command::SwapSubcommand::SetAlias
 	} else {
 		return Err(ParseError::ArgumentError(format!(
 			"Please define some action to do"
@@ -1077,6 +1250,10 @@ pub fn parse_swap_args(args: &ArgMatches) -> Result<command::SwapArgs, ParseErro
 
 	let electrum_node_uri1 = args.value_of("electrum_uri1").map(|s| String::from(s));
 	let electrum_node_uri2 = args.value_of("electrum_uri2").map(|s| String::from(s));
+	let rebroadcast_interval = match args.value_of("rebroadcast_interval") {
+		Some(s) => Some(parse_u64(s, "rebroadcast_interval")?),
+		None => None,
+	};
 
 	Ok(command::SwapArgs {
 		subcommand,
@@ -1086,6 +1263,7 @@ pub fn parse_swap_args(args: &ArgMatches) -> Result<command::SwapArgs, ParseErro
 		destination,
 		apisecret,
 		secondary_fee,
+		redeem_fee,
 		message_file_name,
 		buyer_refund_address,
 		start_listener,
@@ -1094,9 +1272,64 @@ pub fn parse_swap_args(args: &ArgMatches) -> Result<command::SwapArgs, ParseErro
 		electrum_node_uri1,
 		electrum_node_uri2,
 		wait_for_backup1: false, // waiting is a primary usage for qt wallet. We are not documented that properly to make available for all users.
+		rebroadcast_interval,
+		alias,
 	})
 }
 
+/// Swap engine parameters from the wallet config. Not listed parameters are using the defaults.
+pub fn swap_config(wallet_config: &WalletConfig) -> Result<SwapConfig, Error> {
+	let mut config = SwapConfig::default();
+	let engine = match &wallet_config.swap_engine {
+		Some(e) => e,
+		None => return Ok(config),
+	};
+	if let Some(v) = engine.node_call_timeout_sec {
+		config.node_call_timeout = Duration::from_secs(v);
+	}
+	if let Some(v) = engine.lock_slate_max_age {
+		config.lock_slate_max_age = v;
+	}
+	if let Some(v) = engine.refund_fee_max_multiplier {
+		config.refund_fee_max_multiplier = v;
+	}
+	if let Some(v) = engine.lock_inputs_batch_size {
+		config.lock_inputs_batch_size = v;
+	}
+	if let Some(v) = engine.pool_size_per_block {
+		config.pool_size_per_block = v;
+	}
+	if let Some(v) = engine.max_redeem_fee_multiplier {
+		config.max_redeem_fee_multiplier = v;
+	}
+	if let Some(v) = engine.clock_tolerance_sec {
+		config.clock_tolerance_sec = v;
+	}
+	if let Some(v) = engine.max_mwc_lock_blocks {
+		config.max_mwc_lock_blocks = v;
+	}
+	if let Some(v) = engine.message_min_interval_sec {
+		config.message_min_interval_sec = v;
+	}
+	if let Some(v) = engine.refund_lock_height_tolerance_percent {
+		config.refund_lock_height_tolerance_percent = v;
+	}
+	if let Some(tolerance) = &engine.lock_time_tolerance_percent {
+		for (currency, percent) in tolerance {
+			let currency = Currency::try_from(currency.as_str()).map_err(|e| {
+				ErrorKind::ArgumentError(format!(
+					"Invalid swap_engine lock_time_tolerance_percent config, {}",
+					e
+				))
+			})?;
+			config
+				.lock_time_tolerance_percent
+				.insert(currency, *percent);
+		}
+	}
+	Ok(config)
+}
+
 pub fn wallet_command<C, F>(
 	wallet_args: &ArgMatches,
 	mut wallet_config: WalletConfig,
@@ -1226,9 +1459,45 @@ where
 				wallet_inst.get_data_file_dir(),
 				&wallet_config.swap_electrumx_addr,
 			);
+			grin_wallet_libwallet::internal::checkpoint::init_slate_checkpoint_backend(
+				wallet_inst.get_data_file_dir(),
+				wallet_config.slate_checkpoints.unwrap_or(false),
+			);
+			grin_wallet_libwallet::set_payment_proof_required(
+				wallet_config.require_payment_proof.unwrap_or(false),
+			);
+			grin_wallet_libwallet::set_missing_payment_proof_error(
+				wallet_config.reject_missing_payment_proof.unwrap_or(false),
+			);
+			grin_wallet_libwallet::internal::version_log::init_slate_version_log(
+				wallet_inst.get_data_file_dir(),
+				wallet_config.slate_version_log.unwrap_or(false),
+			)?;
+			grin_wallet_impls::adapters::schema::set_slate_schema_validation(
+				wallet_config.slate_schema_validation.unwrap_or(false),
+			);
+			grin_wallet_libwallet::swap::swap::set_counterparty_allowlist(
+				wallet_config.swap_counterparty_allowlist.clone(),
+			)
+			.map_err(grin_wallet_libwallet::Error::from)?;
 
 			if let Some(account) = wallet_args.value_of("account") {
-				wallet_inst.set_parent_key_id_by_name(account)?;
+				// Fail fast with unknown account, every subcommand (send, swap, info) depends on it
+				if let Err(e) = wallet_inst.set_parent_key_id_by_name(account) {
+					match e.kind() {
+						grin_wallet_libwallet::ErrorKind::UnknownAccountLabel(_) => {
+							let accounts: Vec<String> =
+								wallet_inst.acct_path_iter().map(|a| a.label).collect();
+							return Err(ErrorKind::ArgumentError(format!(
+								"Unknown account '{}'. Available accounts: {}",
+								account,
+								accounts.join(", ")
+							))
+							.into());
+						}
+						_ => return Err(e.into()),
+					}
+				}
 			}
 			mask
 		}
@@ -1247,6 +1516,7 @@ where
 		),
 		_ => {
 			let mut owner_api = Owner::new(wallet, None, Some(tor_config.clone()));
+			owner_api.set_swap_config(swap_config(&wallet_config)?);
 			parse_and_execute(
 				&mut owner_api,
 				keychain_mask,
@@ -1322,6 +1592,10 @@ where
 				cli_mode,
 			)
 		}
+		("slate-serve", Some(args)) => {
+			let a = arg_parse!(parse_slate_serve_args(&args));
+			command::slate_serve(owner_api, keychain_mask, &a)
+		}
 		("owner_api", Some(args)) => {
 			let mut c = wallet_config.clone();
 			let mut g = global_wallet_args.clone();
@@ -1430,6 +1704,26 @@ where
 			let a = arg_parse!(parse_verify_proof_args(&args));
 			command::proof_verify(owner_api, km, a)
 		}
+		("verify_slate", Some(args)) => {
+			let a = arg_parse!(parse_verify_slate_args(&args));
+			command::slate_verify(owner_api, km, a)
+		}
+		("inspect_slate", Some(args)) => {
+			let a = arg_parse!(parse_inspect_slate_args(&args));
+			command::slate_inspect(a)
+		}
+		("reexport_slate", Some(args)) => {
+			let a = arg_parse!(parse_reexport_slate_args(&args));
+			command::slate_reexport(owner_api, km, a)
+		}
+		("resume_slate", Some(args)) => {
+			let a = arg_parse!(parse_resume_slate_args(&args));
+			command::slate_resume(owner_api, km, a)
+		}
+		("finalize_exported", Some(args)) => {
+			let a = arg_parse!(parse_finalize_exported_args(&args));
+			command::finalize_exported(owner_api, km, a)
+		}
 		("address", Some(_)) => command::address(owner_api, &global_wallet_args, km),
 		("scan", Some(args)) => {
 			let a = arg_parse!(parse_check_args(&args));
@@ -1453,8 +1747,9 @@ where
 			command::swap_start(owner_api, km, &a)
 		}
 		("swap_create_from_offer", Some(args)) => {
-			let mwc_amount = arg_parse!(parse_required(args, "file"));
-			command::swap_create_from_offer(owner_api, km, mwc_amount.to_string())
+			let file = arg_parse!(parse_required(args, "file"));
+			let accept_args = arg_parse!(parse_swap_accept_args(&args));
+			command::swap_create_from_offer(owner_api, km, file.to_string(), &accept_args)
 		}
 		("swap", Some(args)) => {
 			let a = arg_parse!(parse_swap_args(&args));
@@ -1466,6 +1761,7 @@ where
 				Some(tor_config.clone()),
 				global_wallet_args.tls_conf.clone(),
 				a,
+				&owner_api.swap_config(),
 				cli_mode,
 			)
 		}
@@ -1478,3 +1774,47 @@ where
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use grin_wallet_config::SwapEngineConfig;
+	use std::collections::BTreeMap;
+
+	#[test]
+	fn test_swap_config() {
+		// Default wallet config gives the protocol defaults
+		let mut wallet_config = WalletConfig::default();
+		assert_eq!(swap_config(&wallet_config).unwrap(), SwapConfig::default());
+		wallet_config.swap_engine = None;
+		assert_eq!(swap_config(&wallet_config).unwrap(), SwapConfig::default());
+
+		let mut tolerance = BTreeMap::new();
+		tolerance.insert("BTC".to_string(), 20);
+		wallet_config.swap_engine = Some(SwapEngineConfig {
+			node_call_timeout_sec: Some(5),
+			message_min_interval_sec: Some(60),
+			lock_time_tolerance_percent: Some(tolerance.clone()),
+			..Default::default()
+		});
+		let config = swap_config(&wallet_config).unwrap();
+		assert_eq!(config.node_call_timeout, Duration::from_secs(5));
+		assert_eq!(config.message_min_interval_sec, 60);
+		assert_eq!(config.lock_time_tolerance_percent(Currency::Btc), 20);
+		assert_eq!(
+			config.lock_time_tolerance_percent(Currency::Bch),
+			Currency::Bch.lock_time_tolerance_percent()
+		);
+		assert_eq!(
+			config.lock_slate_max_age,
+			SwapConfig::default().lock_slate_max_age
+		);
+
+		tolerance.insert("doge".to_string(), 20);
+		wallet_config.swap_engine = Some(SwapEngineConfig {
+			lock_time_tolerance_percent: Some(tolerance),
+			..Default::default()
+		});
+		assert!(swap_config(&wallet_config).is_err());
+	}
+}
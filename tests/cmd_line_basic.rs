@@ -158,6 +158,17 @@ fn command_line_test_impl(test_dir: &str) -> Result<(), grin_wallet_controller::
 	let arg_vec = vec!["mwc-wallet", "-p", "password", "-a", "mining", "info"];
 	execute_command(&app, test_dir, "wallet1", &client1, arg_vec)?;
 
+	// Unknown account should fail before any operation
+	let arg_vec = vec!["mwc-wallet", "-p", "password", "-a", "unknown", "info"];
+	assert_eq!(
+		execute_command(&app, test_dir, "wallet1", &client1, arg_vec)
+			.unwrap_err()
+			.kind(),
+		grin_wallet_controller::ErrorKind::ArgumentError(
+			"Unknown account 'unknown'. Available accounts: account_1, default, mining".to_string()
+		)
+	);
+
 	// try a file exchange
 	let file_name = format!("{}/tx1.part_tx", test_dir);
 	let response_file_name = format!("{}/tx1.part_tx.response", test_dir);